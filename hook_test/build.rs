@@ -0,0 +1,6 @@
+// 为 libhook_test.so 写入 DT_SONAME，soname: 规则的场景依赖它在复制到其他路径后仍可识别
+// 显式以 full RELRO 链接，relro-restore 场景依赖 GOT 位于 PT_GNU_RELRO 内
+fn main() {
+    println!("cargo:rustc-cdylib-link-arg=-Wl,-soname,libhook_test.so");
+    println!("cargo:rustc-cdylib-link-arg=-Wl,-z,relro,-z,now");
+}
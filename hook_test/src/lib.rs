@@ -2,6 +2,15 @@ use std::ffi::c_char;
 
 static mut XT_LEN_SINK: usize = 0;
 
+// 取 puts 地址存入函数表，使 puts 同时拥有 JUMP_SLOT 和 GLOB_DAT/ABS 重定位
+static PUTS_TABLE: [unsafe extern "C" fn(*const c_char) -> libc::c_int; 1] = [libc::puts];
+
+// libm 导入：参数与返回值均经浮点寄存器传递，且结果精确，可按位比较
+unsafe extern "C" {
+    fn fmod(x: f64, y: f64) -> f64;
+    fn fmodf(x: f32, y: f32) -> f32;
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn hook_test_trigger() {
     let msg = b"hook-test-trigger\n\0";
@@ -22,3 +31,22 @@ pub extern "C" fn hook_test_trigger_with_input(msg: *const c_char) {
         let _ = libc::puts(msg);
     }
 }
+
+#[unsafe(no_mangle)]
+pub extern "C" fn hook_test_trigger_fnptr() {
+    let msg = b"hook-test-trigger-fnptr\n\0";
+    unsafe {
+        let puts = std::ptr::read_volatile(&PUTS_TABLE[0]);
+        let _ = puts(msg.as_ptr() as *const c_char);
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn hook_test_trigger_fmod(x: f64, y: f64) -> f64 {
+    unsafe { fmod(x, y) }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn hook_test_trigger_fmodf(x: f32, y: f32) -> f32 {
+    unsafe { fmodf(x, y) }
+}
@@ -1,14 +1,33 @@
+mod artifact;
 mod automatic;
 mod basic;
+mod callback_thread;
 mod cycles;
+mod exec_only;
 mod filters;
+mod fork_child;
+mod fp_args;
+mod inspect;
+mod mixed_reloc;
+mod scan_snapshot;
 mod stack_api;
 mod stress;
+mod suppress;
+#[cfg(feature = "textrel-patch")]
+mod textrel;
 
 use crate::test_ctx::env_flag;
 
 pub unsafe fn run_all() {
     run("cfi-slowpath-disabled", basic::scenario_cfi_slowpath_disabled);
+    run("cfi-slowpath-restore", basic::scenario_cfi_slowpath_restore);
+    run(
+        "cfi-scope-modules-with-hooks",
+        basic::scenario_cfi_scope_modules_with_hooks,
+    );
+    run("cfi-scope-disabled", basic::scenario_cfi_scope_disabled);
+    run("cfi-patch-report", basic::scenario_cfi_patch_report);
+    run("cfi-ignore-rules", basic::scenario_cfi_ignore_rules);
     run("single", basic::scenario_single_hook_unhook);
     run("multi-chain", basic::scenario_multi_hook_chain_unhook);
     run(
@@ -23,13 +42,69 @@ pub unsafe fn run_all() {
         "return-address-stack",
         stack_api::scenario_return_address_stack_api,
     );
+    run("proxy-scope-panic", stack_api::scenario_proxy_scope_panic);
+    run("caller-module", stack_api::scenario_caller_module);
+    run("deep-hub-nesting", stack_api::scenario_deep_hub_nesting);
+    run("hub-backtrace", stack_api::scenario_hub_backtrace);
+    run("fp-args", fp_args::scenario_fp_args_roundtrip);
     run("ignore", basic::scenario_ignore);
+    run("del-ignore", basic::scenario_del_ignore);
+    run("batch-register", basic::scenario_batch_register);
+    run("rule-file", basic::scenario_rule_file);
+    run("hook-errors", basic::scenario_hook_errors);
+    run("pause-resume", basic::scenario_pause_resume);
+    run("record-capacity", basic::scenario_record_capacity);
+    run("init-options", basic::scenario_init_options);
+    run("log-sink", basic::scenario_log_sink);
+    run("shutdown-reinit", basic::scenario_shutdown_reinit);
+    run("unhook-symbol", basic::scenario_unhook_symbol);
+    run("fork-child-prev-func", fork_child::scenario_fork_child_prev_func);
+    run(
+        "fork-exec-restores-got",
+        fork_child::scenario_fork_exec_restores_got,
+    );
+    run("reinit-after-fork", fork_child::scenario_reinit_after_fork);
     run("automatic", automatic::scenario_automatic_refresh);
     run(
         "records-dlopen-callbacks",
         automatic::scenario_records_and_dlopen_callbacks,
     );
+    run(
+        "callback-dedicated-thread",
+        callback_thread::scenario_callback_dedicated_thread,
+    );
+    run("module-inspector", inspect::scenario_module_inspector);
+    run("list-module-symbols", inspect::scenario_list_module_symbols);
+    run("resolve-address", inspect::scenario_resolve_address);
+    run("slot-export-queries", inspect::scenario_slot_queries);
+    run("safe-read-stats", inspect::scenario_safe_read_stats);
+    run("relro-restore", inspect::scenario_relro_protection_restored);
+    run(
+        "with-writable-restore",
+        inspect::scenario_with_writable_restores_protection,
+    );
+    run("trampoline-wx", inspect::scenario_trampoline_wx);
+    run("trampoline-vma-name", inspect::scenario_trampoline_vma_name);
+    run("list-hook-tasks", inspect::scenario_list_hook_tasks);
+    run("slot-guard", inspect::scenario_slot_guard);
+    run("state-dump", inspect::scenario_state_dump);
+    run("hook-artifact", artifact::scenario_hook_artifact);
+    run(
+        "scan-snapshot-capture",
+        scan_snapshot::scenario_scan_snapshot_capture,
+    );
+    run(
+        "observation-suppressed",
+        suppress::scenario_observation_suppressed,
+    );
+    run("thread-hook-toggle", suppress::scenario_thread_hook_toggle);
+    run("thread-filter", suppress::scenario_thread_filter);
     run("callee-filter", filters::scenario_callee_filter);
+    run("mixed-reloc-classes", mixed_reloc::scenario_mixed_reloc_classes);
+    run(
+        "mixed-reloc-classes-callee-filter",
+        mixed_reloc::scenario_mixed_reloc_classes_callee_filter,
+    );
     run(
         "callee-filter-lazy-bind",
         filters::scenario_callee_filter_lazy_bind,
@@ -42,6 +117,19 @@ pub unsafe fn run_all() {
         "single-same-basename-multi-instance",
         filters::scenario_single_same_basename_multi_instance,
     );
+    run(
+        "partial-ex-filter-by-base",
+        filters::scenario_partial_ex_filter_by_base,
+    );
+    run(
+        "all-with-callers-same-basename",
+        filters::scenario_all_with_callers_same_basename,
+    );
+    run("soname-rule", filters::scenario_soname_rule);
+    run("versioned-symbol", filters::scenario_versioned_symbol);
+    run("single-pattern", filters::scenario_single_pattern);
+    run("module-reload-events", filters::scenario_module_reload_events);
+    run("orig-func", filters::scenario_orig_func_lifecycle);
     run(
         "instance-qualified-path-rule",
         filters::scenario_instance_qualified_path_rule,
@@ -66,6 +154,21 @@ pub unsafe fn run_all() {
         "namespace-rule-from-handle-api",
         filters::scenario_namespace_rule_from_handle_api,
     );
+    run(
+        "duplicate-callee-instances",
+        filters::scenario_duplicate_callee_instances,
+    );
+    run("capabilities", automatic::scenario_capabilities);
+    run("set-mode", automatic::scenario_set_mode);
+    run("request-refresh-async", automatic::scenario_request_refresh_async);
+    run(
+        "linker-internal-fallback",
+        automatic::scenario_linker_internal_fallback_toggle,
+    );
+    run("dlclose-callbacks", automatic::scenario_dlclose_callbacks);
+    run("dlopen-filter", automatic::scenario_dlopen_filter);
+    run("proxy-priority", automatic::scenario_proxy_priority);
+    run("record-listener", automatic::scenario_record_listener);
     run("auto-reload", automatic::scenario_auto_reload_stability);
     run(
         "auto-reload-periodic-forced",
@@ -79,6 +182,7 @@ pub unsafe fn run_all() {
         "auto-reload-long-stress",
         automatic::scenario_auto_reload_long_stress,
     );
+    run("auto-dlopen-burst", automatic::scenario_auto_dlopen_burst);
     run("cycle-guard-auto", cycles::scenario_cycle_guard_auto);
     run(
         "cycle-guard-manual-no-leave",
@@ -88,12 +192,28 @@ pub unsafe fn run_all() {
         "concurrent-stress",
         stress::scenario_concurrent_hooking_stress,
     );
+    run(
+        "hook-single-during-refresh",
+        stress::scenario_hook_single_during_refresh,
+    );
     run(
         "persistent-parallel-stress",
         stress::scenario_persistent_hook_parallel_stress,
     );
     run("perf", stress::scenario_perf_smoke);
+    run("mprotect-batching", stress::scenario_mprotect_batching);
+    run("refresh-handle-bench", stress::scenario_refresh_handle_bench);
     run("leak", stress::scenario_leak_smoke);
+    run("guard-slot-churn", stress::scenario_guard_slot_churn);
+    run("guard-fault-stats", stress::scenario_guard_fault_stats);
+    run(
+        "guard-extended-signals",
+        stress::scenario_guard_extended_signals,
+    );
+    run(
+        "sigchain-front-reassert",
+        stress::scenario_sigchain_front_reassert,
+    );
     if env_flag("HOOK_TEST_AUTO_MARATHON") {
         run(
             "auto-reload-marathon",
@@ -103,6 +223,13 @@ pub unsafe fn run_all() {
     if env_flag("HOOK_TEST_MARATHON") {
         run("manual-churn-marathon", stress::scenario_manual_churn_marathon);
     }
+    if env_flag("HOOK_TEST_EXEC_ONLY_HEADERS") {
+        run("exec-only-headers", exec_only::scenario_exec_only_headers);
+    }
+    #[cfg(feature = "textrel-patch")]
+    if env_flag("HOOK_TEST_TEXTREL") {
+        run("textrel-patch", textrel::scenario_textrel_patch);
+    }
     if env_flag("HOOK_TEST_SOAK") {
         run("soak-suite", stress::scenario_soak_suite);
     }
@@ -0,0 +1,54 @@
+use std::ffi::c_void;
+
+use srx_hook::{
+    ArtifactKind, HookMode, clear, hook_single, init, is_hook_artifact, open_module, refresh,
+    unhook,
+};
+
+use crate::test_ctx::{ensure_ok, hook_puts_quiet, load_hook_test};
+
+pub unsafe fn scenario_hook_artifact() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init manual artifact");
+    let handle = load_hook_test();
+
+    let stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single artifact failed");
+    ensure_ok(refresh(), "refresh artifact");
+
+    // 生效中的 GOT slot 指向 hub trampoline
+    let inspector = open_module("libhook_test.so").expect("open_module artifact failed");
+    let slot = *inspector
+        .got_slots_for("puts")
+        .expect("got slots for puts")
+        .first()
+        .expect("no GOT slot for puts");
+    let trampo = std::ptr::read_volatile(slot as *const usize);
+    assert_eq!(
+        is_hook_artifact(trampo),
+        Some(ArtifactKind::Trampoline),
+        "live trampoline not classified"
+    );
+    assert_eq!(
+        is_hook_artifact(init as *const () as usize),
+        Some(ArtifactKind::OwnModuleCode),
+        "own code not classified"
+    );
+    assert_eq!(
+        is_hook_artifact(libc::puts as *const () as usize),
+        None,
+        "libc puts classified as artifact"
+    );
+
+    ensure_ok(unhook(stub), "unhook artifact");
+    drop(inspector);
+    libc::dlclose(handle);
+    clear();
+}
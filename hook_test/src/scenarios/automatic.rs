@@ -1,16 +1,26 @@
-use std::ffi::c_void;
-use std::sync::atomic::Ordering;
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 
 use srx_hook::{
-    HookMode, RECORD_ITEM_ALL, add_dlopen_callback, clear, del_dlopen_callback, get_recordable,
-    get_records, hook_single, init, set_recordable,
+    DLOPEN_RESULT_NOLOAD, DlopenFilterAction, HookMode, MonitorStrategy, RECORD_ITEM_ALL,
+    RECORD_ITEM_OP, RecordOp, SignalHandlerMode, SrxHookErrno, add_dlclose_callback,
+    add_dlopen_callback, add_dlopen_filter, clear, clear_record_listener, del_dlclose_callback,
+    del_dlopen_callback, del_dlopen_filter, enable_linker_internal_fallback, get_capabilities,
+    get_event_refresh_count, get_hook_tasks, get_linker_fallback_stats, get_mode,
+    get_monitor_refresh_counts, get_monitor_status, get_proxy_chain, get_record_entries,
+    get_recordable, get_records, hook_all, hook_single, hook_single_with_priority, init, refresh,
+    request_refresh_async, request_refresh_async_handle, set_mode, set_monitor_debounce,
+    set_record_listener, set_recordable, unhook, with_prev_func_as,
 };
 
 use crate::test_ctx::{
-    DLOPEN_POST_COUNT, DLOPEN_PRE_COUNT, HOOK_A_COUNT, current_rss_kb, ensure_ok, env_usize,
-    hook_puts_quiet, hook_test_dlopen_post, hook_test_dlopen_pre, hook_test_trigger, load_hook_test,
-    ScopedEnv,
+    DLOPEN_LAST_RESULT, DLOPEN_POST_COUNT, DLOPEN_PRE_COUNT, HOOK_A_COUNT, HOOK_B_COUNT,
+    HOOK_C_COUNT, ScopedEnv, current_rss_kb, ensure_ok, env_usize, hook_puts_a_chain,
+    hook_puts_b_chain, hook_puts_c_chain, hook_puts_quiet, hook_test_dlopen_post,
+    hook_test_dlopen_pre, hook_test_fixtures, hook_test_trigger, load_hook_test,
+    load_hook_test_with_flags,
 };
 
 pub unsafe fn scenario_automatic_refresh() {
@@ -80,12 +90,102 @@ pub unsafe fn scenario_records_and_dlopen_callbacks() {
         "dlopen post callback not fired"
     );
 
-    let records = get_records(RECORD_ITEM_ALL).unwrap_or_default();
+    let entries = get_record_entries(RECORD_ITEM_ALL);
     assert!(
-        records.contains("HOOK"),
-        "operation records should contain HOOK entry"
+        entries.iter().any(|entry| entry.op == Some(RecordOp::Hook)
+            && entry.sym_name.as_deref() == Some("puts")
+            && entry.lib_name.as_deref() == Some("libhook_test.so")
+            && entry.errno.is_some()
+            && entry.stub.is_some_and(|stub| stub != 0)),
+        "operation records should contain hook entry for puts: {entries:?}"
     );
 
+    // 未选中的字段为 None
+    let ops_only = get_record_entries(RECORD_ITEM_OP);
+    assert_eq!(ops_only.len(), entries.len());
+    assert!(ops_only.iter().all(|entry| entry.op.is_some()
+        && entry.timestamp_ms.is_none()
+        && entry.sym_name.is_none()
+        && entry.stub.is_none()));
+
+    // dlopen(NULL) 只取得主程序 handle：不投递回调，也不触发刷新
+    DLOPEN_PRE_COUNT.store(0, Ordering::Relaxed);
+    DLOPEN_POST_COUNT.store(0, Ordering::Relaxed);
+    let refresh_before = get_event_refresh_count();
+    let main_handle = libc::dlopen(std::ptr::null(), libc::RTLD_NOW);
+    assert!(!main_handle.is_null(), "dlopen(NULL) failed");
+    std::thread::sleep(Duration::from_millis(300));
+    assert_eq!(
+        DLOPEN_PRE_COUNT.load(Ordering::Relaxed),
+        0,
+        "dlopen(NULL) fired pre callback"
+    );
+    assert_eq!(
+        DLOPEN_POST_COUNT.load(Ordering::Relaxed),
+        0,
+        "dlopen(NULL) fired post callback"
+    );
+
+    // RTLD_NOLOAD 投递带区分结果码的回调，但不触发刷新
+    let noload_handle = load_hook_test_with_flags(libc::RTLD_NOW | libc::RTLD_NOLOAD);
+    std::thread::sleep(Duration::from_millis(300));
+    assert_eq!(
+        DLOPEN_PRE_COUNT.load(Ordering::Relaxed),
+        1,
+        "RTLD_NOLOAD pre callback count"
+    );
+    assert_eq!(
+        DLOPEN_POST_COUNT.load(Ordering::Relaxed),
+        1,
+        "RTLD_NOLOAD post callback count"
+    );
+    assert_eq!(
+        DLOPEN_LAST_RESULT.load(Ordering::Relaxed),
+        DLOPEN_RESULT_NOLOAD,
+        "RTLD_NOLOAD post result"
+    );
+    assert_eq!(
+        get_event_refresh_count(),
+        refresh_before,
+        "NULL/RTLD_NOLOAD dlopen requested a refresh"
+    );
+
+    // 真实加载仍然触发刷新
+    let mut real_fixture = hook_test_fixtures("noload_real", 1);
+    std::thread::sleep(Duration::from_millis(300));
+    assert_eq!(
+        DLOPEN_LAST_RESULT.load(Ordering::Relaxed),
+        0,
+        "real load post result"
+    );
+    assert!(
+        get_event_refresh_count() > refresh_before,
+        "real dlopen did not refresh"
+    );
+
+    // 同一路径卸载后重新加载：自动刷新再次挂上时记为 REHOOK，与首次绑定区分
+    let real_path = real_fixture[0].path().to_string_lossy().into_owned();
+    let real_c_path = real_fixture[0].c_path();
+    real_fixture[0].close();
+    std::thread::sleep(Duration::from_millis(300));
+    let reopened = libc::dlopen(real_c_path.as_ptr(), libc::RTLD_NOW);
+    assert!(!reopened.is_null(), "reopen records fixture failed");
+    std::thread::sleep(Duration::from_millis(300));
+    let entries = get_record_entries(RECORD_ITEM_ALL);
+    assert!(
+        entries
+            .iter()
+            .any(|entry| entry.op == Some(RecordOp::Rehook)
+                && entry.sym_name.as_deref() == Some("puts")
+                && entry.lib_name.as_deref() == Some(real_path.as_str())
+                && entry.errno == Some(0)),
+        "reload should record REHOOK for puts: {entries:?}"
+    );
+    libc::dlclose(reopened);
+    drop(real_fixture);
+    libc::dlclose(noload_handle);
+    libc::dlclose(main_handle);
+
     ensure_ok(
         del_dlopen_callback(
             Some(hook_test_dlopen_pre),
@@ -99,6 +199,324 @@ pub unsafe fn scenario_records_and_dlopen_callbacks() {
     clear();
 }
 
+// pre 回调收到的模块路径与回调内 hook 接口的返回值
+static DLCLOSE_PRE_PATHS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+static DLCLOSE_POST_RESULTS: Mutex<Vec<i32>> = Mutex::new(Vec::new());
+static DLCLOSE_REENTRY_REJECTED: AtomicBool = AtomicBool::new(false);
+
+unsafe extern "C" fn record_dlclose_pre(
+    _handle: *mut c_void,
+    pathname: *const c_char,
+    _arg: *mut c_void,
+) {
+    let pathname = if pathname.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(pathname).to_string_lossy().into_owned()
+    };
+    DLCLOSE_PRE_PATHS.lock().unwrap().push(pathname);
+    let reentry = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    );
+    if reentry.is_none() {
+        DLCLOSE_REENTRY_REJECTED.store(true, Ordering::Relaxed);
+    }
+}
+
+unsafe extern "C" fn record_dlclose_post(_handle: *mut c_void, result: i32, _arg: *mut c_void) {
+    DLCLOSE_POST_RESULTS.lock().unwrap().push(result);
+}
+
+pub unsafe fn scenario_dlclose_callbacks() {
+    clear();
+    ensure_ok(init(HookMode::Automatic, true), "init dlclose callbacks");
+    DLCLOSE_PRE_PATHS.lock().unwrap().clear();
+    DLCLOSE_POST_RESULTS.lock().unwrap().clear();
+    DLCLOSE_REENTRY_REJECTED.store(false, Ordering::Relaxed);
+
+    // 重复注册按 (pre, post, arg) 去重，只投递一次
+    for _ in 0..2 {
+        ensure_ok(
+            add_dlclose_callback(
+                Some(record_dlclose_pre),
+                Some(record_dlclose_post),
+                std::ptr::null_mut(),
+            ),
+            "add_dlclose_callback",
+        );
+    }
+
+    let mut fixtures = hook_test_fixtures("dlclose_cb", 1);
+    fixtures[0].close();
+    let pre_paths = DLCLOSE_PRE_PATHS.lock().unwrap().clone();
+    assert_eq!(pre_paths.len(), 1, "dlclose pre callback count mismatch");
+    assert!(
+        pre_paths[0].ends_with("dlclose_cb_0/libhook_test.so"),
+        "dlclose pre callback path mismatch: {}",
+        pre_paths[0]
+    );
+    assert_eq!(
+        *DLCLOSE_POST_RESULTS.lock().unwrap(),
+        vec![0],
+        "dlclose post callback result mismatch"
+    );
+    assert!(
+        DLCLOSE_REENTRY_REJECTED.load(Ordering::Relaxed),
+        "hook_single inside dlclose callback was not rejected"
+    );
+
+    ensure_ok(
+        del_dlclose_callback(
+            Some(record_dlclose_pre),
+            Some(record_dlclose_post),
+            std::ptr::null_mut(),
+        ),
+        "del_dlclose_callback",
+    );
+    let handle = load_hook_test();
+    libc::dlclose(handle);
+    assert_eq!(
+        DLCLOSE_PRE_PATHS.lock().unwrap().len(),
+        1,
+        "dlclose callback fired after del"
+    );
+
+    drop(fixtures);
+    clear();
+}
+
+// 过滤器调用次数与重定向目标；过滤器内部的 dlopen 不应再次进入过滤器
+static FILTER_CALLS: AtomicUsize = AtomicUsize::new(0);
+static FILTER_REDIRECT_TARGET: Mutex<Option<CString>> = Mutex::new(None);
+
+unsafe extern "C" fn filter_dlopen(
+    filename: *const c_char,
+    redirect_buf: *mut c_char,
+    redirect_buf_len: usize,
+    _arg: *mut c_void,
+) -> DlopenFilterAction {
+    FILTER_CALLS.fetch_add(1, Ordering::Relaxed);
+    let nested = libc::dlopen(filename, libc::RTLD_NOW | libc::RTLD_NOLOAD);
+    if !nested.is_null() {
+        libc::dlclose(nested);
+    }
+
+    let name = CStr::from_ptr(filename).to_string_lossy();
+    if name.contains("filter_deny_") {
+        return DlopenFilterAction::Deny;
+    }
+    if name.ends_with("/redirect_me.so") {
+        let target = FILTER_REDIRECT_TARGET.lock().unwrap();
+        let Some(target) = target.as_ref() else {
+            return DlopenFilterAction::Allow;
+        };
+        let bytes = target.as_bytes_with_nul();
+        assert!(bytes.len() <= redirect_buf_len, "redirect buffer too small");
+        std::ptr::copy_nonoverlapping(bytes.as_ptr() as *const c_char, redirect_buf, bytes.len());
+        return DlopenFilterAction::Redirect;
+    }
+    DlopenFilterAction::Allow
+}
+
+pub unsafe fn scenario_dlopen_filter() {
+    clear();
+    // 夹具先加载再卸载，只保留文件供过滤器测试
+    let mut deny_fixtures = hook_test_fixtures("filter_deny", 1);
+    let mut redirect_fixtures = hook_test_fixtures("filter_redirect", 1);
+    deny_fixtures[0].close();
+    redirect_fixtures[0].close();
+    *FILTER_REDIRECT_TARGET.lock().unwrap() = Some(redirect_fixtures[0].c_path());
+
+    ensure_ok(init(HookMode::Automatic, true), "init dlopen filter");
+    ensure_ok(
+        add_dlopen_callback(
+            Some(hook_test_dlopen_pre),
+            Some(hook_test_dlopen_post),
+            std::ptr::null_mut(),
+        ),
+        "add_dlopen_callback filter",
+    );
+    ensure_ok(
+        add_dlopen_filter(filter_dlopen, std::ptr::null_mut()),
+        "add_dlopen_filter",
+    );
+    DLOPEN_POST_COUNT.store(0, Ordering::Relaxed);
+    FILTER_CALLS.store(0, Ordering::Relaxed);
+
+    // 拒绝时不实际加载，但 dlerror 带有拒绝原因
+    libc::dlerror();
+    let denied = libc::dlopen(deny_fixtures[0].c_path().as_ptr(), libc::RTLD_NOW);
+    assert!(denied.is_null(), "denied library was loaded");
+    let err = libc::dlerror();
+    assert!(!err.is_null(), "denied dlopen left no dlerror");
+    let err = CStr::from_ptr(err).to_string_lossy();
+    assert!(
+        err.contains("denied by srx_hook: filter"),
+        "denied dlerror: {err}"
+    );
+    assert_eq!(
+        DLOPEN_POST_COUNT.load(Ordering::Relaxed),
+        1,
+        "deny skipped post"
+    );
+    assert_eq!(
+        DLOPEN_LAST_RESULT.load(Ordering::Relaxed),
+        -1,
+        "deny post result"
+    );
+    assert_eq!(FILTER_CALLS.load(Ordering::Relaxed), 1, "filter re-entered");
+
+    let redirected = libc::dlopen(c"/srx_hook_missing/redirect_me.so".as_ptr(), libc::RTLD_NOW);
+    assert!(!redirected.is_null(), "redirected dlopen failed");
+    // 重定向后的 handle 指向夹具副本，能解析出其导出符号
+    hook_test_trigger(redirected);
+    assert_eq!(
+        DLOPEN_LAST_RESULT.load(Ordering::Relaxed),
+        0,
+        "redirect post result"
+    );
+    assert_eq!(FILTER_CALLS.load(Ordering::Relaxed), 2, "filter re-entered");
+    libc::dlclose(redirected);
+
+    ensure_ok(
+        del_dlopen_filter(filter_dlopen, std::ptr::null_mut()),
+        "del_dlopen_filter",
+    );
+    let handle = load_hook_test();
+    assert_eq!(
+        FILTER_CALLS.load(Ordering::Relaxed),
+        2,
+        "filter fired after del"
+    );
+    libc::dlclose(handle);
+
+    ensure_ok(
+        del_dlopen_callback(
+            Some(hook_test_dlopen_pre),
+            Some(hook_test_dlopen_post),
+            std::ptr::null_mut(),
+        ),
+        "del_dlopen_callback filter",
+    );
+    *FILTER_REDIRECT_TARGET.lock().unwrap() = None;
+    drop(deny_fixtures);
+    drop(redirect_fixtures);
+    clear();
+}
+
+// 监听器收到的 (op, sym_name, stub) 与回调内 hook 接口的返回值
+static LISTENED_RECORDS: Mutex<Vec<(RecordOp, String, u64)>> = Mutex::new(Vec::new());
+static LISTENER_REENTRY_REJECTED: AtomicBool = AtomicBool::new(false);
+
+unsafe extern "C" fn record_listener(
+    _timestamp_ms: u64,
+    op: RecordOp,
+    _caller_lib_name: *const c_char,
+    _lib_name: *const c_char,
+    sym_name: *const c_char,
+    _new_addr: *mut c_void,
+    _errno: i32,
+    stub: u64,
+    _arg: *mut c_void,
+) {
+    let sym_name = CStr::from_ptr(sym_name).to_string_lossy().into_owned();
+    LISTENED_RECORDS.lock().unwrap().push((op, sym_name, stub));
+    if get_records(RECORD_ITEM_ALL).is_none() && unhook(stub) != SrxHookErrno::Ok {
+        LISTENER_REENTRY_REJECTED.store(true, Ordering::Relaxed);
+    }
+}
+
+pub unsafe fn scenario_record_listener() {
+    clear();
+    ensure_ok(init(HookMode::Automatic, true), "init record listener");
+    LISTENED_RECORDS.lock().unwrap().clear();
+    LISTENER_REENTRY_REJECTED.store(false, Ordering::Relaxed);
+    // 监听器不依赖 recordable
+    ensure_ok(
+        set_record_listener(record_listener, std::ptr::null_mut()),
+        "set_record_listener",
+    );
+
+    let handle = load_hook_test();
+    let stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single record listener failed");
+    ensure_ok(unhook(stub), "unhook record listener");
+    // monitor 自身的 loader hook 也会产生记录，只比较本任务的
+    let task_records: Vec<_> = LISTENED_RECORDS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, _, record_stub)| *record_stub == stub)
+        .cloned()
+        .collect();
+    assert_eq!(
+        task_records,
+        vec![
+            (RecordOp::Hook, "puts".to_string(), stub),
+            (RecordOp::Unhook, String::new(), stub),
+        ],
+        "listener records mismatch"
+    );
+    assert!(
+        LISTENER_REENTRY_REJECTED.load(Ordering::Relaxed),
+        "srx_hook calls inside record listener were not rejected"
+    );
+    assert!(
+        get_records(RECORD_ITEM_ALL).is_none(),
+        "listener should not enable the record buffer"
+    );
+
+    // clear() 投递 CLEAR 并保留监听器
+    clear();
+    assert!(
+        LISTENED_RECORDS
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(op, _, _)| *op == RecordOp::Clear),
+        "clear() did not notify the listener"
+    );
+    ensure_ok(init(HookMode::Automatic, true), "re-init record listener");
+    let stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single after clear failed");
+    assert!(
+        LISTENED_RECORDS
+            .lock()
+            .unwrap()
+            .contains(&(RecordOp::Hook, "puts".to_string(), stub)),
+        "listener dropped by clear()"
+    );
+
+    ensure_ok(clear_record_listener(), "clear_record_listener");
+    let count = LISTENED_RECORDS.lock().unwrap().len();
+    clear();
+    assert_eq!(
+        LISTENED_RECORDS.lock().unwrap().len(),
+        count,
+        "listener fired after clear_record_listener"
+    );
+    libc::dlclose(handle);
+}
+
 pub unsafe fn scenario_auto_reload_stability() {
     clear();
     ensure_ok(init(HookMode::Automatic, true), "init auto reload");
@@ -137,6 +555,89 @@ pub unsafe fn scenario_auto_reload_stability() {
     clear();
 }
 
+pub unsafe fn scenario_capabilities() {
+    clear();
+    assert!(
+        get_capabilities().is_none(),
+        "capabilities reported before init"
+    );
+
+    ensure_ok(init(HookMode::Manual, true), "init capabilities manual");
+    let caps = get_capabilities().expect("capabilities missing after init");
+    assert!(caps.android_api_level > 0, "api level not probed: {caps:?}");
+    assert!(caps.maps_readable, "/proc/self/maps not readable: {caps:?}");
+    assert_ne!(caps.signal_handler_mode, SignalHandlerMode::None);
+    assert_eq!(caps.monitor_strategy, MonitorStrategy::Inactive);
+    assert!(
+        !caps.periodic_fallback,
+        "manual mode reports periodic fallback"
+    );
+    assert!(
+        !caps.cfi.skipped || caps.cfi.patched_modules + caps.cfi.failed_modules == 0,
+        "skipped cfi reports module hooks: {caps:?}"
+    );
+    clear();
+
+    for (value, expected) in [("1", true), ("0", false)] {
+        let _periodic_guard = ScopedEnv::set("SRX_HOOK_MONITOR_PERIODIC", value);
+        ensure_ok(
+            init(HookMode::Automatic, true),
+            "init capabilities automatic",
+        );
+        let caps = get_capabilities().expect("capabilities missing after automatic init");
+        // loader hook 回退后会补装 legacy hook，仅在 loader 不可用时要求 legacy
+        assert_ne!(caps.monitor_strategy, MonitorStrategy::Inactive, "{caps:?}");
+        if !caps.loader_hooks_available {
+            assert_eq!(caps.monitor_strategy, MonitorStrategy::LegacyHooks);
+        }
+        assert_eq!(
+            caps.periodic_fallback, expected,
+            "periodic fallback does not follow override {value}: {caps:?}"
+        );
+        clear();
+    }
+    assert!(
+        get_capabilities().is_none(),
+        "capabilities reported after clear"
+    );
+}
+
+// 关闭 linker 内部回退后 dlopen 仍经标准路径完成，且不再计入 do_dlopen 使用次数
+pub unsafe fn scenario_linker_internal_fallback_toggle() {
+    clear();
+    ensure_ok(
+        init(HookMode::Automatic, true),
+        "init linker internal fallback",
+    );
+    let api_level = get_capabilities()
+        .expect("capabilities missing after init")
+        .android_api_level;
+
+    enable_linker_internal_fallback(false);
+    let before = get_linker_fallback_stats();
+    let handle = load_hook_test();
+    hook_test_trigger(handle);
+    libc::dlclose(handle);
+    let after = get_linker_fallback_stats();
+    assert_eq!(
+        after.used, before.used,
+        "disabled linker internal fallback still used"
+    );
+
+    enable_linker_internal_fallback(true);
+    let handle = load_hook_test();
+    hook_test_trigger(handle);
+    libc::dlclose(handle);
+    let stats = get_linker_fallback_stats();
+    println!(
+        "linker internal fallback: api={} used={} lock_timeouts={} last_error={:?}",
+        api_level, stats.used, stats.lock_timeouts, stats.last_error
+    );
+
+    enable_linker_internal_fallback(matches!(api_level, 24 | 25));
+    clear();
+}
+
 pub unsafe fn scenario_auto_reload_forced_periodic_fallback() {
     clear();
     let _periodic_guard = ScopedEnv::set("SRX_HOOK_MONITOR_PERIODIC", "1");
@@ -173,6 +674,7 @@ pub unsafe fn scenario_auto_reload_forced_periodic_fallback() {
         libc::dlclose(handle);
         std::thread::sleep(Duration::from_millis(40));
     }
+    assert_monitor_status_follows_override(true);
     let rss_after = current_rss_kb();
     let delta = rss_after.saturating_sub(rss_before);
     println!(
@@ -224,6 +726,93 @@ pub unsafe fn scenario_auto_reload_forced_periodic_disabled() {
         libc::dlclose(handle);
         std::thread::sleep(Duration::from_millis(30));
     }
+    assert_monitor_status_follows_override(false);
+    clear();
+}
+
+// monitor 状态与能力报告一致，并反映 SRX_HOOK_MONITOR_PERIODIC 覆盖
+fn assert_monitor_status_follows_override(periodic: bool) {
+    let caps = get_capabilities().expect("capabilities missing");
+    let status = get_monitor_status();
+    assert!(
+        status.thread_running,
+        "monitor thread not running: {status:?}"
+    );
+    assert!(
+        !status.parked,
+        "automatic monitor reported parked: {status:?}"
+    );
+    assert_ne!(status.strategy, MonitorStrategy::Inactive, "{status:?}");
+    assert_eq!(
+        status.strategy, caps.monitor_strategy,
+        "{status:?} {caps:?}"
+    );
+    assert_eq!(
+        status.periodic_enabled, periodic,
+        "periodic fallback does not follow override: {status:?}"
+    );
+    assert!(
+        status.last_event_refresh_ms != 0 || status.last_periodic_refresh_ms != 0,
+        "no monitor refresh recorded: {status:?}"
+    );
+    assert!(!status.poll_interval.is_zero(), "{status:?}");
+}
+
+pub unsafe fn scenario_auto_dlopen_burst() {
+    const LIBS: usize = 32;
+
+    clear();
+    // 先复制好夹具并卸载，突发阶段只剩 dlopen 本身
+    let mut fixtures = hook_test_fixtures("dlopen_burst", LIBS);
+    for fixture in &mut fixtures {
+        fixture.close();
+    }
+    let paths: Vec<CString> = fixtures.iter().map(|fixture| fixture.c_path()).collect();
+
+    set_monitor_debounce(Duration::from_millis(40));
+    ensure_ok(init(HookMode::Automatic, true), "init dlopen burst");
+    let stub = hook_all(
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_all dlopen burst failed");
+    std::thread::sleep(Duration::from_millis(300));
+
+    let refresh_before = get_event_refresh_count();
+    let handles: Vec<*mut c_void> = paths
+        .iter()
+        .map(|path| {
+            let handle = libc::dlopen(path.as_ptr(), libc::RTLD_NOW);
+            assert!(!handle.is_null(), "dlopen burst load failed");
+            handle
+        })
+        .collect();
+    std::thread::sleep(Duration::from_millis(500));
+    let passes = get_event_refresh_count() - refresh_before;
+
+    for (idx, handle) in handles.iter().enumerate() {
+        HOOK_A_COUNT.store(0, Ordering::Relaxed);
+        hook_test_trigger(*handle);
+        assert!(
+            HOOK_A_COUNT.load(Ordering::Relaxed) > 0,
+            "dlopen burst hook miss at lib={idx}"
+        );
+    }
+    println!("dlopen burst: libs={} refresh passes={}", LIBS, passes);
+    assert!(
+        passes * 4 <= LIBS as u64,
+        "dlopen burst not coalesced: libs={LIBS} passes={passes}"
+    );
+
+    for handle in handles {
+        libc::dlclose(handle);
+    }
+    ensure_ok(unhook(stub), "unhook dlopen burst");
+    drop(fixtures);
+    set_monitor_debounce(Duration::from_millis(30));
     clear();
 }
 
@@ -288,9 +877,9 @@ pub unsafe fn scenario_auto_reload_long_stress() {
         dlopen_pre >= rounds,
         "dlopen pre callback count too small: {dlopen_pre}"
     );
-    assert!(
-        dlopen_post >= rounds,
-        "dlopen post callback count too small: {dlopen_post}"
+    assert_eq!(
+        dlopen_pre, dlopen_post,
+        "dlopen pre/post callbacks not paired"
     );
     assert!(delta < 4096, "auto reload long stress rss delta too large: {delta}KB");
 
@@ -334,6 +923,7 @@ pub unsafe fn scenario_auto_reload_marathon() {
 
     let rounds = env_usize("HOOK_TEST_AUTO_MARATHON_ROUNDS", 2000);
     let report_step = env_usize("HOOK_TEST_AUTO_MARATHON_REPORT_STEP", 200);
+    let refresh_before = get_monitor_refresh_counts();
     let rss_before = current_rss_kb();
     let start = Instant::now();
     for round in 0..rounds {
@@ -368,21 +958,35 @@ pub unsafe fn scenario_auto_reload_marathon() {
     let delta = rss_after.saturating_sub(rss_before);
     let dlopen_pre = DLOPEN_PRE_COUNT.load(Ordering::Relaxed);
     let dlopen_post = DLOPEN_POST_COUNT.load(Ordering::Relaxed);
+    let refresh_after = get_monitor_refresh_counts();
+    let full_refreshes = refresh_after.full - refresh_before.full;
+    let scoped_refreshes = refresh_after.scoped - refresh_before.scoped;
     println!(
-        "auto reload marathon done: rounds={} pre={} post={} elapsed={:?} rss_delta={}KB",
+        "auto reload marathon done: rounds={} pre={} post={} full={} scoped={} elapsed={:?} rss_delta={}KB",
         rounds,
         dlopen_pre,
         dlopen_post,
+        full_refreshes,
+        scoped_refreshes,
         start.elapsed(),
         delta
     );
+    // dlclose 只清理被卸载的模块，全量刷新基本只剩 dlopen 触发的那一轮
+    assert!(
+        scoped_refreshes * 2 >= rounds as u64,
+        "auto reload marathon scoped refreshes too few: {scoped_refreshes}"
+    );
+    assert!(
+        full_refreshes < rounds as u64 * 2,
+        "auto reload marathon full refreshes not reduced: {full_refreshes}"
+    );
     assert!(
         dlopen_pre >= rounds,
         "auto reload marathon pre count too small: {dlopen_pre}"
     );
-    assert!(
-        dlopen_post >= rounds,
-        "auto reload marathon post count too small: {dlopen_post}"
+    assert_eq!(
+        dlopen_pre, dlopen_post,
+        "auto reload marathon pre/post callbacks not paired"
     );
     assert!(
         delta < 8192,
@@ -400,3 +1004,300 @@ pub unsafe fn scenario_auto_reload_marathon() {
 
     clear();
 }
+
+pub unsafe fn scenario_set_mode() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init manual set mode");
+    assert_eq!(
+        set_mode(HookMode::Manual),
+        SrxHookErrno::Ok,
+        "same mode not accepted"
+    );
+    let _stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single set mode failed");
+    let handle = load_hook_test();
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert_eq!(
+        HOOK_A_COUNT.load(Ordering::Relaxed),
+        0,
+        "manual mode applied hook without refresh"
+    );
+    assert_eq!(
+        get_capabilities().expect("capabilities").monitor_strategy,
+        MonitorStrategy::Inactive
+    );
+
+    // 切到 Automatic 后由 monitor 补刷 Manual 期间入队的任务
+    ensure_ok(set_mode(HookMode::Automatic), "set_mode automatic");
+    assert_eq!(get_mode(), HookMode::Automatic);
+    assert_ne!(
+        get_capabilities().expect("capabilities").monitor_strategy,
+        MonitorStrategy::Inactive
+    );
+    std::thread::sleep(Duration::from_millis(1200));
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert!(
+        HOOK_A_COUNT.load(Ordering::Relaxed) >= 1,
+        "automatic switch did not apply queued task"
+    );
+
+    // 切回 Manual：monitor 任务卸载，新加载的模块等到手动 refresh
+    ensure_ok(set_mode(HookMode::Manual), "set_mode manual");
+    assert_eq!(get_mode(), HookMode::Manual);
+    assert_eq!(
+        get_capabilities().expect("capabilities").monitor_strategy,
+        MonitorStrategy::Inactive
+    );
+    let refresh_before = get_event_refresh_count();
+    let fixtures = hook_test_fixtures("set_mode_manual", 1);
+    std::thread::sleep(Duration::from_millis(300));
+    assert_eq!(
+        get_event_refresh_count(),
+        refresh_before,
+        "parked monitor refreshed on dlopen"
+    );
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(fixtures[0].handle());
+    assert_eq!(
+        HOOK_A_COUNT.load(Ordering::Relaxed),
+        0,
+        "manual mode hooked new module without refresh"
+    );
+    ensure_ok(refresh(), "refresh after set_mode manual");
+    hook_test_trigger(fixtures[0].handle());
+    assert!(
+        HOOK_A_COUNT.load(Ordering::Relaxed) >= 1,
+        "refresh did not hook new module"
+    );
+
+    drop(fixtures);
+    libc::dlclose(handle);
+    clear();
+}
+
+pub unsafe fn scenario_request_refresh_async() {
+    clear();
+    assert_eq!(
+        request_refresh_async(),
+        SrxHookErrno::InitErrSafe,
+        "request_refresh_async accepted before init"
+    );
+    ensure_ok(init(HookMode::Manual, true), "init manual request refresh");
+    assert_eq!(
+        request_refresh_async(),
+        SrxHookErrno::InitErrSafe,
+        "request_refresh_async accepted in manual mode"
+    );
+    clear();
+
+    ensure_ok(
+        init(HookMode::Automatic, true),
+        "init automatic request refresh",
+    );
+    let _stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single request refresh failed");
+    std::thread::sleep(Duration::from_millis(1200));
+
+    let refresh_before = get_event_refresh_count();
+    ensure_ok(request_refresh_async(), "request_refresh_async");
+    let deadline = Instant::now() + Duration::from_secs(3);
+    while get_event_refresh_count() == refresh_before && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    assert!(
+        get_event_refresh_count() > refresh_before,
+        "request_refresh_async did not wake monitor"
+    );
+
+    assert_eq!(
+        request_refresh_async_handle(std::ptr::null_mut()),
+        SrxHookErrno::InvalidArg
+    );
+    let handle = load_hook_test();
+    ensure_ok(
+        request_refresh_async_handle(handle),
+        "request_refresh_async_handle",
+    );
+    std::thread::sleep(Duration::from_millis(1200));
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert!(
+        HOOK_A_COUNT.load(Ordering::Relaxed) >= 1,
+        "hook missing after request_refresh_async_handle"
+    );
+    libc::dlclose(handle);
+    clear();
+}
+
+// 透传到下一个 proxy 的 __loader_dlclose 钩子，只计数
+static LOADER_DLCLOSE_HITS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe extern "C" fn hook_loader_dlclose_passthrough(
+    handle: *mut c_void,
+    caller: *const c_void,
+) -> libc::c_int {
+    type LoaderDlcloseFn = unsafe extern "C" fn(*mut c_void, *const c_void) -> libc::c_int;
+    LOADER_DLCLOSE_HITS.fetch_add(1, Ordering::Relaxed);
+    let self_ptr = hook_loader_dlclose_passthrough as *mut c_void;
+    with_prev_func_as(self_ptr, |prev: Option<LoaderDlcloseFn>| {
+        prev.map_or(-1, |prev_fn| unsafe { prev_fn(handle, caller) })
+    })
+    .unwrap_or(-1)
+}
+
+// 按 (proxy, 优先级) 校验 caller_rule 下所有 puts slot 的链顺序
+fn assert_priority_chain(caller_rule: &str, expected: &[(*mut c_void, i32)], stage: &str) {
+    let chains = get_proxy_chain(caller_rule, "puts").expect("get_proxy_chain failed");
+    assert!(!chains.is_empty(), "no proxy chain {stage}");
+    for chain in chains {
+        let actual: Vec<_> = chain
+            .entries
+            .iter()
+            .filter(|entry| entry.enabled)
+            .map(|entry| (entry.proxy_addr as *mut c_void, entry.priority))
+            .collect();
+        assert_eq!(actual, expected, "proxy priority order {stage}");
+    }
+}
+
+pub unsafe fn scenario_proxy_priority() {
+    clear();
+    ensure_ok(init(HookMode::Automatic, true), "init automatic priority");
+    assert_eq!(
+        hook_single_with_priority(
+            "libhook_test.so",
+            None,
+            "puts",
+            hook_puts_a_chain as *mut c_void,
+            i32::MAX,
+            None,
+            std::ptr::null_mut(),
+        )
+        .err(),
+        Some(SrxHookErrno::InvalidArg),
+        "reserved priority accepted"
+    );
+
+    // 注册顺序 B、A、C；按 LIFO 应为 C、A、B，按优先级应为 A、B、C
+    let mut fixtures = hook_test_fixtures("proxy_priority", 1);
+    let rule = "proxy_priority_0/libhook_test.so";
+    let mut stubs = Vec::new();
+    for (proxy, priority) in [
+        (hook_puts_b_chain as *mut c_void, 0),
+        (hook_puts_a_chain as *mut c_void, 10),
+        (hook_puts_c_chain as *mut c_void, -5),
+    ] {
+        let stub = hook_single_with_priority(
+            rule,
+            None,
+            "puts",
+            proxy,
+            priority,
+            None,
+            std::ptr::null_mut(),
+        )
+        .expect("hook_single_with_priority failed");
+        stubs.push(stub);
+    }
+    ensure_ok(refresh(), "refresh priority chain");
+    let expected = [
+        (hook_puts_a_chain as *mut c_void, 10),
+        (hook_puts_b_chain as *mut c_void, 0),
+        (hook_puts_c_chain as *mut c_void, -5),
+    ];
+    assert_priority_chain(rule, &expected, "after first load");
+    let tasks = get_hook_tasks();
+    for (stub, priority) in stubs.iter().zip([0, 10, -5]) {
+        let task = tasks
+            .iter()
+            .find(|task| task.stub == *stub)
+            .expect("priority task missing");
+        assert_eq!(task.priority, priority, "task priority mismatch");
+    }
+
+    // 卸载后重新加载，新的 hub 仍按优先级建链
+    let path = fixtures[0].c_path();
+    fixtures[0].close();
+    let handle = libc::dlopen(path.as_ptr(), libc::RTLD_NOW);
+    assert!(!handle.is_null(), "reload priority fixture failed");
+    ensure_ok(refresh(), "refresh after reload");
+    assert_priority_chain(rule, &expected, "after reload");
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    HOOK_B_COUNT.store(0, Ordering::Relaxed);
+    HOOK_C_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    for (count, name) in [
+        (&HOOK_A_COUNT, "A"),
+        (&HOOK_B_COUNT, "B"),
+        (&HOOK_C_COUNT, "C"),
+    ] {
+        assert!(
+            count.load(Ordering::Relaxed) >= 1,
+            "hook {name} not hit after reload"
+        );
+    }
+    for stub in stubs {
+        ensure_ok(unhook(stub), "unhook priority task");
+    }
+    libc::dlclose(handle);
+
+    // monitor 的内部 proxy 固定位于链首，用户最高可用优先级也排在其后
+    if get_capabilities().expect("capabilities").monitor_strategy != MonitorStrategy::LoaderHooks {
+        println!("skip loader priority check: loader hooks inactive");
+        drop(fixtures);
+        clear();
+        return;
+    }
+    let stub = hook_single_with_priority(
+        "libdl.so",
+        None,
+        "__loader_dlclose",
+        hook_loader_dlclose_passthrough as *mut c_void,
+        i32::MAX - 1,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook __loader_dlclose failed");
+    ensure_ok(refresh(), "refresh loader priority");
+    let chains = get_proxy_chain("libdl.so", "__loader_dlclose").expect("loader chain failed");
+    assert!(!chains.is_empty(), "no __loader_dlclose chain");
+    for chain in chains {
+        let head: Vec<_> = chain
+            .entries
+            .iter()
+            .filter(|entry| entry.enabled)
+            .take(2)
+            .map(|entry| (entry.stub, entry.priority))
+            .collect();
+        assert_eq!(
+            head,
+            [(None, i32::MAX), (Some(stub), i32::MAX - 1)],
+            "monitor proxy not at chain head"
+        );
+    }
+    LOADER_DLCLOSE_HITS.store(0, Ordering::Relaxed);
+    libc::dlclose(load_hook_test());
+    assert!(
+        LOADER_DLCLOSE_HITS.load(Ordering::Relaxed) >= 1,
+        "user __loader_dlclose proxy not reached"
+    );
+    ensure_ok(unhook(stub), "unhook loader priority");
+    drop(fixtures);
+    clear();
+}
@@ -1,12 +1,24 @@
-use std::ffi::c_void;
+use std::ffi::{CStr, c_char, c_void};
+use std::sync::Mutex;
 use std::sync::atomic::Ordering;
+use std::time::Duration;
 
-use srx_hook::{HookMode, add_ignore, clear, hook_single, init, refresh, unhook};
+use srx_hook::{
+    CfiAddrSource, CfiPatchScope, CfiPatchStatus, CfiSymbolKind, HUB_STACK_FIXED_DEPTH, HookMode,
+    HookSpec, HookStub, InitOptions, LogLevel, RECORD_ITEM_ALL, RECORD_ITEM_ERRNO, RECORD_ITEM_OP,
+    RECORD_ITEM_STUB, RECORD_ITEM_SYM_NAME, RecordOp, SrxHookErrno, add_ignore, clear,
+    clear_log_sink, del_ignore, dump_records, get_capabilities, get_cfi_patch_report, get_debug,
+    get_hook_tasks, get_ignores, get_log_level, get_mode, get_proxy_chain, get_record_capacity,
+    get_record_dropped_count, get_record_entries, get_records, hook_all_checked, hook_batch,
+    hook_single, hook_single_checked, init, init_with_options, load_rules_from_file,
+    load_rules_from_str, pause, refresh, restore_cfi_slowpath, resume, set_debug, set_log_level,
+    set_log_sink, set_record_capacity, set_recordable, shutdown, unhook, unhook_symbol,
+};
 
 use crate::test_ctx::{
     HOOK_A_COUNT, HOOK_B_COUNT, HOOK_C_COUNT, ensure_ok, hook_puts_a_chain, hook_puts_b_chain,
     hook_puts_c_chain, hook_puts_no_leave, hook_puts_quiet, load_hook_test,
-    verify_cfi_slowpath_disabled, hook_test_trigger,
+    verify_cfi_slowpath_disabled, verify_cfi_slowpath_intact, hook_test_trigger,
 };
 
 pub unsafe fn scenario_cfi_slowpath_disabled() {
@@ -16,6 +28,220 @@ pub unsafe fn scenario_cfi_slowpath_disabled() {
     clear();
 }
 
+// restore_cfi_slowpath 恢复原始指令后，刷新不再改写，范围降为 ModulesWithHooks
+pub unsafe fn scenario_cfi_slowpath_restore() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init manual cfi restore");
+    verify_cfi_slowpath_disabled();
+    ensure_ok(restore_cfi_slowpath(), "restore cfi slowpath");
+    verify_cfi_slowpath_intact();
+    ensure_ok(refresh(), "refresh after cfi restore");
+    verify_cfi_slowpath_intact();
+    let caps = get_capabilities().expect("capabilities missing after cfi restore");
+    if !caps.cfi.skipped {
+        assert_eq!(caps.cfi.scope, CfiPatchScope::ModulesWithHooks);
+        assert_eq!(caps.cfi.patched_slowpath, 0, "slowpath patch left behind");
+    }
+    clear();
+}
+
+// 只替换存在活动 slot 的模块的 CFI slot，slot 全部恢复后的刷新同时撤掉该模块的 CFI 补丁
+pub unsafe fn scenario_cfi_scope_modules_with_hooks() {
+    clear();
+    let opts = InitOptions::new()
+        .mode(HookMode::Manual)
+        .debug(true)
+        .cfi_patch_scope(CfiPatchScope::ModulesWithHooks);
+    ensure_ok(init_with_options(opts), "init cfi modules scope");
+    verify_cfi_slowpath_intact();
+    let handle = load_hook_test();
+
+    let stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single cfi modules scope failed");
+    ensure_ok(refresh(), "refresh cfi modules scope");
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert!(
+        HOOK_A_COUNT.load(Ordering::Relaxed) >= 1,
+        "hook not hit with cfi modules scope"
+    );
+    verify_cfi_slowpath_intact();
+    let caps = get_capabilities().expect("capabilities missing in cfi modules scope");
+    if !caps.cfi.skipped {
+        assert_eq!(caps.cfi.scope, CfiPatchScope::ModulesWithHooks);
+        assert_eq!(caps.cfi.patched_slowpath, 0, "slowpath patched");
+        assert!(caps.cfi.patched_modules >= 1, "hooked module not patched");
+    }
+
+    ensure_ok(unhook(stub), "unhook cfi modules scope");
+    ensure_ok(refresh(), "refresh after unhook cfi modules scope");
+    let caps = get_capabilities().expect("capabilities missing after unhook");
+    assert_eq!(caps.cfi.patched_modules, 0, "unhooked module still patched");
+    libc::dlclose(handle);
+    clear();
+}
+
+// Disabled 不做任何 CFI 补丁，并回滚此前 Global 范围遗留的指令补丁
+pub unsafe fn scenario_cfi_scope_disabled() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init manual cfi global");
+    verify_cfi_slowpath_disabled();
+    clear();
+
+    let opts = InitOptions::new()
+        .mode(HookMode::Manual)
+        .debug(true)
+        .cfi_patch_scope(CfiPatchScope::Disabled);
+    ensure_ok(init_with_options(opts), "init cfi disabled scope");
+    verify_cfi_slowpath_intact();
+    let caps = get_capabilities().expect("capabilities missing in cfi disabled scope");
+    assert!(caps.cfi.skipped, "cfi patch not skipped");
+    assert_eq!(caps.cfi.scope, CfiPatchScope::Disabled);
+
+    let handle = load_hook_test();
+    let _stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single cfi disabled scope failed");
+    ensure_ok(refresh(), "refresh cfi disabled scope");
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert!(
+        HOOK_A_COUNT.load(Ordering::Relaxed) >= 1,
+        "hook not hit with cfi disabled scope"
+    );
+    assert_eq!(
+        get_capabilities().map(|caps| caps.cfi.patched_modules),
+        Some(0)
+    );
+    libc::dlclose(handle);
+    clear();
+}
+
+// 报告与 CFI_PATCH 记录一一对应；先 restore 并刷出空报告，保证重新 init 后的记录是新增
+pub unsafe fn scenario_cfi_patch_report() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init cfi report baseline");
+    set_recordable(true);
+    ensure_ok(restore_cfi_slowpath(), "restore cfi before report");
+    assert!(
+        get_cfi_patch_report().is_empty(),
+        "cfi report not empty after restore"
+    );
+    let _ = get_record_entries(RECORD_ITEM_ALL);
+    clear();
+
+    ensure_ok(init(HookMode::Manual, true), "init cfi report");
+    set_recordable(true);
+    let report = get_cfi_patch_report();
+    let caps = get_capabilities().expect("capabilities missing in cfi report");
+    if caps.cfi.skipped {
+        assert!(report.is_empty(), "cfi report not empty when skipped");
+        clear();
+        return;
+    }
+    assert!(
+        report
+            .iter()
+            .any(|entry| entry.kind == CfiSymbolKind::Slowpath
+                && entry.status == CfiPatchStatus::Patched),
+        "cfi report missing patched slowpath"
+    );
+    assert_eq!(
+        report
+            .iter()
+            .filter(|entry| entry.status == CfiPatchStatus::Patched)
+            .count(),
+        caps.cfi.patched_slowpath
+    );
+    assert!(
+        report.iter().all(|entry| entry.module.is_some()),
+        "cfi report entry without module"
+    );
+
+    let records: Vec<_> = get_record_entries(RECORD_ITEM_ALL)
+        .into_iter()
+        .filter(|entry| entry.op == Some(RecordOp::CfiPatch))
+        .collect();
+    assert_eq!(
+        records.len(),
+        report.len(),
+        "cfi patch record count mismatch"
+    );
+    for entry in &report {
+        assert!(
+            records
+                .iter()
+                .any(|record| record.new_addr == Some(entry.addr)),
+            "cfi patch record missing for 0x{:x}",
+            entry.addr
+        );
+    }
+    clear();
+}
+
+// ignore 规则命中的模块不再扫描 CFI 地址；此前已 patch 的地址保持原样，restore 时一并恢复
+pub unsafe fn scenario_cfi_ignore_rules() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init cfi ignore baseline");
+    verify_cfi_slowpath_disabled();
+    let patched_before: Vec<usize> = get_cfi_patch_report()
+        .into_iter()
+        .filter(|entry| entry.status == CfiPatchStatus::Patched)
+        .map(|entry| entry.addr)
+        .collect();
+
+    // 后缀规则 ".so" 命中全部共享库，dlsym 解析到的 slowpath 不受影响
+    ensure_ok(add_ignore(".so"), "add_ignore all shared libs");
+    ensure_ok(refresh(), "refresh after cfi ignore");
+    verify_cfi_slowpath_disabled();
+    let report = get_cfi_patch_report();
+    for addr in &patched_before {
+        assert!(
+            report
+                .iter()
+                .any(|entry| entry.addr == *addr && entry.status == CfiPatchStatus::Patched),
+            "patched cfi addr 0x{addr:x} dropped after ignore"
+        );
+    }
+    assert!(
+        report
+            .iter()
+            .all(|entry| !entry.ignored || entry.source != CfiAddrSource::Dlsym),
+        "dlsym cfi addr marked ignored"
+    );
+
+    ensure_ok(restore_cfi_slowpath(), "restore cfi with ignored modules");
+    verify_cfi_slowpath_intact();
+    assert!(
+        get_cfi_patch_report().is_empty(),
+        "ignored cfi addrs not restored"
+    );
+    clear();
+
+    // init 前加入的 ignore 规则从首次扫描起生效
+    ensure_ok(add_ignore(".so"), "add_ignore before init");
+    ensure_ok(init(HookMode::Manual, true), "init cfi with ignore");
+    verify_cfi_slowpath_disabled();
+    assert!(
+        get_cfi_patch_report().iter().all(|entry| !entry.ignored),
+        "fresh cfi patch kept ignored addrs"
+    );
+    clear();
+}
+
 pub unsafe fn scenario_single_hook_unhook() {
     clear();
     ensure_ok(init(HookMode::Manual, true), "init manual single");
@@ -80,6 +306,15 @@ pub unsafe fn scenario_multi_hook_chain_unhook() {
     )
     .expect("hook_single C failed");
     ensure_ok(refresh(), "refresh chain");
+    // 后挂载的 proxy 排在链首
+    assert_proxy_chain(
+        &[
+            (hook_puts_c_chain as *mut c_void, true, Some(stub_c)),
+            (hook_puts_b_chain as *mut c_void, true, Some(stub_b)),
+            (hook_puts_a_chain as *mut c_void, true, Some(stub_a)),
+        ],
+        "after hooking A, B, C",
+    );
 
     HOOK_A_COUNT.store(0, Ordering::Relaxed);
     HOOK_B_COUNT.store(0, Ordering::Relaxed);
@@ -99,6 +334,15 @@ pub unsafe fn scenario_multi_hook_chain_unhook() {
     );
 
     ensure_ok(unhook(stub_b), "unhook B");
+    // 中间节点保留在链上但已禁用，不再归属任何任务
+    assert_proxy_chain(
+        &[
+            (hook_puts_c_chain as *mut c_void, true, Some(stub_c)),
+            (hook_puts_b_chain as *mut c_void, false, None),
+            (hook_puts_a_chain as *mut c_void, true, Some(stub_a)),
+        ],
+        "after unhooking B",
+    );
     HOOK_A_COUNT.store(0, Ordering::Relaxed);
     HOOK_B_COUNT.store(0, Ordering::Relaxed);
     HOOK_C_COUNT.store(0, Ordering::Relaxed);
@@ -143,6 +387,22 @@ pub unsafe fn scenario_multi_hook_chain_unhook() {
     clear();
 }
 
+// 每个已挂载 puts slot 的 hub 链按 (proxy, 启用, 所属任务) 依次匹配，最后落到原函数
+fn assert_proxy_chain(expected: &[(*mut c_void, bool, Option<HookStub>)], stage: &str) {
+    let puts = unsafe { libc::dlsym(libc::RTLD_DEFAULT, c"puts".as_ptr()) } as usize;
+    let chains = get_proxy_chain("libhook_test.so", "puts").expect("get_proxy_chain failed");
+    assert!(!chains.is_empty(), "no proxy chain {stage}");
+    for chain in chains {
+        let actual: Vec<_> = chain
+            .entries
+            .iter()
+            .map(|entry| (entry.proxy_addr as *mut c_void, entry.enabled, entry.stub))
+            .collect();
+        assert_eq!(actual, expected, "proxy chain order {stage}");
+        assert_eq!(chain.orig_func, puts, "chain tail {stage}");
+    }
+}
+
 pub unsafe fn scenario_same_proxy_multi_stub_unhook() {
     clear();
     ensure_ok(init(HookMode::Manual, true), "init manual same proxy");
@@ -254,3 +514,629 @@ pub unsafe fn scenario_ignore() {
     libc::dlclose(handle);
     clear();
 }
+
+// 删除 ignore 规则后，下次 refresh 补挂此前被跳过的模块；重复添加不增加条目
+pub unsafe fn scenario_del_ignore() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init manual del ignore");
+    ensure_ok(add_ignore("libhook_test.so"), "add_ignore");
+    ensure_ok(add_ignore("libhook_test.so"), "add_ignore duplicate");
+    assert_eq!(get_ignores(), vec!["libhook_test.so".to_string()]);
+    let handle = load_hook_test();
+
+    let stub = hook_all_checked(
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_all del ignore case failed");
+    ensure_ok(refresh(), "refresh with ignore");
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert_eq!(
+        HOOK_A_COUNT.load(Ordering::Relaxed),
+        0,
+        "ignored module was hooked"
+    );
+
+    ensure_ok(del_ignore("libhook_test.so"), "del_ignore");
+    assert_eq!(del_ignore("libhook_test.so"), SrxHookErrno::NotFound);
+    assert!(get_ignores().is_empty(), "ignore rule still listed");
+    ensure_ok(refresh(), "refresh after del_ignore");
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert!(
+        HOOK_A_COUNT.load(Ordering::Relaxed) > 0,
+        "module not hooked after del_ignore"
+    );
+
+    ensure_ok(unhook(stub), "unhook del ignore case");
+    libc::dlclose(handle);
+    clear();
+}
+
+pub unsafe fn scenario_batch_register() {
+    clear();
+    ensure_ok(init(HookMode::Automatic, true), "init batch");
+    set_recordable(true);
+    let handle = load_hook_test();
+
+    let spec = |sym_name, new_func: *mut c_void| HookSpec {
+        caller_path_name: "libhook_test.so",
+        callee_path_name: None,
+        sym_name,
+        new_func,
+        hooked: None,
+        hooked_arg: std::ptr::null_mut(),
+    };
+    let stubs = hook_batch(&[
+        spec("puts", hook_puts_a_chain as *mut c_void),
+        spec("puts", hook_puts_b_chain as *mut c_void),
+        // 与第一个条目 proxy 重复、符号为空的条目被拒绝，其余条目照常生效
+        spec("puts", hook_puts_a_chain as *mut c_void),
+        spec("", hook_puts_c_chain as *mut c_void),
+    ]);
+    assert!(
+        stubs[0].is_some() && stubs[1].is_some(),
+        "batch entries rejected: {stubs:?}"
+    );
+    assert_eq!(stubs[2], None, "duplicate proxy accepted");
+    assert_eq!(stubs[3], None, "empty symbol accepted");
+
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    HOOK_B_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert!(
+        HOOK_A_COUNT.load(Ordering::Relaxed) >= 1 && HOOK_B_COUNT.load(Ordering::Relaxed) >= 1,
+        "batch hooks not hit"
+    );
+
+    // 每个生效条目各有一条 HOOK 记录
+    let records = get_records(RECORD_ITEM_OP | RECORD_ITEM_STUB).unwrap_or_default();
+    for stub in stubs.iter().flatten() {
+        let line = format!("HOOK,0x{stub:x},");
+        assert_eq!(
+            records.lines().filter(|item| *item == line).count(),
+            1,
+            "missing HOOK record for stub {stub}: {records}"
+        );
+    }
+
+    for stub in stubs.into_iter().flatten() {
+        ensure_ok(unhook(stub), "unhook batch entry");
+    }
+    set_recordable(false);
+    libc::dlclose(handle);
+    clear();
+}
+
+fn resolve_rule_proxy(name: &str) -> *mut c_void {
+    match name {
+        "hook_puts_quiet" => hook_puts_quiet as *mut c_void,
+        _ => std::ptr::null_mut(),
+    }
+}
+
+pub unsafe fn scenario_rule_file() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init rule file");
+    let handle = load_hook_test();
+
+    let text = "\
+# 规则文件加载
+libhook_test.so puts hook_puts_quiet   # 注册 hook_single
+
+libhook_test.so puts missing_proxy
+libhook_test.so puts
+ignore libsrx_rule_file_unused.so
+";
+    let report = load_rules_from_str(text, resolve_rule_proxy);
+    let results: Vec<_> = report
+        .lines
+        .iter()
+        .map(|line| (line.line, line.result.map(|stub| stub.is_some())))
+        .collect();
+    assert_eq!(
+        results,
+        vec![
+            (2, Ok(true)),
+            (4, Err(SrxHookErrno::NotFound)),
+            (5, Err(SrxHookErrno::InvalidArg)),
+            (6, Ok(false)),
+        ],
+        "unexpected rule report: {report:?}"
+    );
+    assert!(!report.is_ok(), "report with failures is ok");
+    assert_eq!(report.failures().count(), 2, "failure count mismatch");
+    let stub = report.lines[0]
+        .result
+        .ok()
+        .flatten()
+        .expect("rule line stub missing");
+    ensure_ok(refresh(), "refresh rule file");
+
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert!(
+        HOOK_A_COUNT.load(Ordering::Relaxed) > 0,
+        "rule file hook not triggered"
+    );
+    ensure_ok(unhook(stub), "unhook rule file stub");
+
+    let path = format!("/data/local/tmp/srx_rule_file_{}.txt", std::process::id());
+    std::fs::write(&path, "* libc.so puts hook_puts_quiet\n").expect("write rule file");
+    let report = load_rules_from_file(&path, resolve_rule_proxy).expect("load rule file");
+    let _ = std::fs::remove_file(&path);
+    assert!(report.is_ok(), "rule file load failed: {report:?}");
+    let stubs = report
+        .lines
+        .iter()
+        .filter_map(|line| line.result.ok().flatten());
+    for stub in stubs {
+        ensure_ok(unhook(stub), "unhook rule file hook_all");
+    }
+    let missing = "/data/local/tmp/srx_rule_file_missing.txt";
+    assert_eq!(
+        load_rules_from_file(missing, resolve_rule_proxy),
+        Err(SrxHookErrno::NotFound),
+        "missing rule file accepted"
+    );
+
+    libc::dlclose(handle);
+    clear();
+}
+
+pub unsafe fn scenario_hook_errors() {
+    clear();
+    let proxy = hook_puts_quiet as *mut c_void;
+    let null = std::ptr::null_mut();
+    assert_eq!(
+        hook_single_checked("libhook_test.so", None, "puts", proxy, None, null),
+        Err(SrxHookErrno::Uninit),
+        "hook before init accepted"
+    );
+
+    ensure_ok(init(HookMode::Manual, true), "init hook errors");
+    set_recordable(true);
+    assert_eq!(
+        hook_single_checked("", None, "puts", proxy, None, null),
+        Err(SrxHookErrno::InvalidArg)
+    );
+    assert_eq!(
+        hook_all_checked(None, "puts", std::ptr::null_mut(), None, null),
+        Err(SrxHookErrno::InvalidArg)
+    );
+
+    let stub = hook_single_checked("libhook_test.so", None, "puts", proxy, None, null)
+        .expect("first hook rejected");
+    assert_eq!(
+        hook_single_checked("libhook_test.so", None, "puts", proxy, None, null),
+        Err(SrxHookErrno::RepeatedFunc),
+        "identical registration accepted"
+    );
+    // 同一 proxy 挂到其他 caller 不算重复
+    let other = hook_single_checked("libc.so", None, "puts", proxy, None, null)
+        .expect("same proxy for another caller rejected");
+
+    // 注册阶段的拒绝以 stub 0 记录具体错误码
+    let records =
+        get_records(RECORD_ITEM_OP | RECORD_ITEM_SYM_NAME | RECORD_ITEM_ERRNO | RECORD_ITEM_STUB)
+            .unwrap_or_default();
+    let rejected = format!("HOOK,puts,{},0x0,", SrxHookErrno::RepeatedFunc.as_i32());
+    assert!(
+        records.lines().any(|line| line == rejected),
+        "missing rejection record: {records}"
+    );
+
+    ensure_ok(unhook(other), "unhook other caller");
+    ensure_ok(unhook(stub), "unhook first");
+    assert!(
+        hook_single_checked("libhook_test.so", None, "puts", proxy, None, null).is_ok(),
+        "registration rejected after unhook"
+    );
+    set_recordable(false);
+    clear();
+}
+
+pub unsafe fn scenario_pause_resume() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init manual pause");
+    set_recordable(true);
+    let handle = load_hook_test();
+
+    let stub_a = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_a_chain as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single A failed");
+    let stub_b = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_b_chain as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single B failed");
+    ensure_ok(refresh(), "refresh pause");
+
+    ensure_ok(pause(stub_a), "pause A");
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    HOOK_B_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert_eq!(
+        HOOK_A_COUNT.load(Ordering::Relaxed),
+        0,
+        "paused hook A still hit"
+    );
+    assert!(
+        HOOK_B_COUNT.load(Ordering::Relaxed) >= 1,
+        "hook B lost while A paused"
+    );
+    let paused: Vec<_> = get_hook_tasks()
+        .into_iter()
+        .filter(|task| task.paused)
+        .map(|task| task.stub)
+        .collect();
+    assert_eq!(paused, vec![stub_a], "unexpected paused tasks");
+
+    // 全部暂停后调用落到原函数
+    ensure_ok(pause(stub_b), "pause B");
+    HOOK_B_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert_eq!(
+        HOOK_B_COUNT.load(Ordering::Relaxed),
+        0,
+        "paused hook B still hit"
+    );
+
+    ensure_ok(resume(stub_a), "resume A");
+    ensure_ok(resume(stub_a), "resume A twice");
+    hook_test_trigger(handle);
+    assert!(
+        HOOK_A_COUNT.load(Ordering::Relaxed) >= 1,
+        "hook A not hit after resume"
+    );
+    assert_eq!(
+        pause(stub_a + stub_b + 1000),
+        SrxHookErrno::InvalidArg,
+        "unknown stub paused"
+    );
+
+    let records = get_records(RECORD_ITEM_OP | RECORD_ITEM_STUB).unwrap_or_default();
+    for expected in [
+        format!("PAUSE,0x{stub_a:x},"),
+        format!("PAUSE,0x{stub_b:x},"),
+        format!("RESUME,0x{stub_a:x},"),
+    ] {
+        assert!(
+            records.lines().any(|line| line == expected),
+            "missing {expected} record: {records}"
+        );
+    }
+
+    // 暂停中的任务可以直接卸载
+    ensure_ok(unhook(stub_b), "unhook paused B");
+    ensure_ok(unhook(stub_a), "unhook A");
+    set_recordable(false);
+    clear();
+}
+
+pub unsafe fn scenario_record_capacity() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init manual record capacity");
+    set_recordable(true);
+    assert_eq!(
+        set_record_capacity(0),
+        SrxHookErrno::InvalidArg,
+        "zero capacity accepted"
+    );
+    ensure_ok(set_record_capacity(4), "set_record_capacity 4");
+    assert_eq!(get_record_capacity(), 4);
+
+    // 1 条 HOOK + 10 条 PAUSE/RESUME，只保留最新 4 条
+    let stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single record capacity failed");
+    for _ in 0..5 {
+        ensure_ok(pause(stub), "pause record capacity");
+        ensure_ok(resume(stub), "resume record capacity");
+    }
+    let ops: Vec<_> = get_record_entries(RECORD_ITEM_OP)
+        .into_iter()
+        .map(|entry| entry.op)
+        .collect();
+    assert_eq!(
+        ops,
+        [
+            RecordOp::Pause,
+            RecordOp::Resume,
+            RecordOp::Pause,
+            RecordOp::Resume
+        ]
+        .map(Some),
+        "ring buffer did not keep newest records"
+    );
+    assert_eq!(get_record_dropped_count(), 7);
+
+    // 缩容保留最新的记录
+    ensure_ok(set_record_capacity(2), "set_record_capacity 2");
+    assert_eq!(get_record_entries(RECORD_ITEM_OP).len(), 2);
+    assert_eq!(get_record_dropped_count(), 9);
+
+    let mut fds = [0; 2];
+    assert_eq!(libc::pipe(fds.as_mut_ptr()), 0, "pipe failed");
+    ensure_ok(dump_records(fds[1], RECORD_ITEM_OP), "dump_records");
+    libc::close(fds[1]);
+    let mut buf = [0u8; 256];
+    let n = libc::read(fds[0], buf.as_mut_ptr() as *mut c_void, buf.len());
+    libc::close(fds[0]);
+    let dumped = String::from_utf8_lossy(&buf[..n.max(0) as usize]).into_owned();
+    assert_eq!(
+        dumped, "# dropped: 9\nPAUSE,\nRESUME,\n",
+        "dump header mismatch"
+    );
+
+    // clear() 归零丢弃计数，容量保持
+    clear();
+    assert_eq!(get_record_dropped_count(), 0);
+    assert_eq!(get_record_capacity(), 2);
+    ensure_ok(set_record_capacity(4096), "restore record capacity");
+}
+
+// sink 收到的 (level, tag, msg)
+static LOG_LINES: Mutex<Vec<(i32, String, String)>> = Mutex::new(Vec::new());
+// sink 内部调用 clear_log_sink 的结果，应被外部回调守卫拒绝而不是自锁
+static LOG_SINK_REENTRY: Mutex<Option<SrxHookErrno>> = Mutex::new(None);
+
+unsafe extern "C" fn collect_log(
+    level: i32,
+    tag: *const c_char,
+    msg: *const c_char,
+    _arg: *mut c_void,
+) {
+    let tag = CStr::from_ptr(tag).to_string_lossy().into_owned();
+    let msg = CStr::from_ptr(msg).to_string_lossy().into_owned();
+    LOG_LINES.lock().unwrap().push((level, tag, msg));
+    *LOG_SINK_REENTRY.lock().unwrap() = Some(clear_log_sink());
+}
+
+pub unsafe fn scenario_log_sink() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init log sink");
+    LOG_LINES.lock().unwrap().clear();
+    ensure_ok(
+        set_log_sink(collect_log, std::ptr::null_mut(), false),
+        "set_log_sink",
+    );
+
+    // 重复注册在 WARN 级别输出拒绝原因
+    let proxy = hook_puts_quiet as *mut c_void;
+    let null = std::ptr::null_mut();
+    let stub = hook_single_checked("libhook_test.so", None, "puts", proxy, None, null)
+        .expect("first hook rejected");
+    assert_eq!(
+        hook_single_checked("libhook_test.so", None, "puts", proxy, None, null),
+        Err(SrxHookErrno::RepeatedFunc)
+    );
+    {
+        let lines = LOG_LINES.lock().unwrap();
+        assert!(
+            lines.iter().any(|(level, tag, msg)| *level == 5
+                && tag == "srx_hook"
+                && msg.contains("hook puts rejected")),
+            "rejection not delivered to log sink: {lines:?}"
+        );
+    }
+    assert_eq!(
+        LOG_SINK_REENTRY.lock().unwrap().take(),
+        Some(SrxHookErrno::InitErrSafe),
+        "clear_log_sink inside sink not rejected"
+    );
+
+    // Error 级别屏蔽 WARN；debug 开关与级别互相映射
+    set_log_level(LogLevel::Error);
+    assert!(!get_debug());
+    let count = LOG_LINES.lock().unwrap().len();
+    assert_eq!(
+        hook_single_checked("libhook_test.so", None, "puts", proxy, None, null),
+        Err(SrxHookErrno::RepeatedFunc)
+    );
+    assert_eq!(
+        LOG_LINES.lock().unwrap().len(),
+        count,
+        "WARN delivered at Error level"
+    );
+    set_debug(true);
+    assert_eq!(get_log_level(), LogLevel::Debug);
+    set_debug(false);
+    assert_eq!(get_log_level(), LogLevel::Warn);
+
+    ensure_ok(clear_log_sink(), "clear_log_sink");
+    let count = LOG_LINES.lock().unwrap().len();
+    assert_eq!(
+        hook_single_checked("libhook_test.so", None, "puts", proxy, None, null),
+        Err(SrxHookErrno::RepeatedFunc)
+    );
+    assert_eq!(
+        LOG_LINES.lock().unwrap().len(),
+        count,
+        "log sink called after clear_log_sink"
+    );
+
+    ensure_ok(unhook(stub), "unhook log sink");
+    clear();
+}
+
+pub unsafe fn scenario_unhook_symbol() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init manual unhook symbol");
+    let handle = load_hook_test();
+
+    let stub_a = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_a_chain as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single A failed");
+    hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_b_chain as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single B failed");
+    ensure_ok(refresh(), "refresh unhook symbol");
+
+    assert_eq!(
+        unhook_symbol("puts", Some("libother.so")),
+        (0, SrxHookErrno::Ok),
+        "unrelated caller rule removed tasks"
+    );
+    assert_eq!(
+        unhook_symbol("puts", Some("libhook_test.so")),
+        (2, SrxHookErrno::Ok),
+        "unhook_symbol count mismatch"
+    );
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    HOOK_B_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert_eq!(
+        HOOK_A_COUNT.load(Ordering::Relaxed) + HOOK_B_COUNT.load(Ordering::Relaxed),
+        0,
+        "hook still hit after unhook_symbol"
+    );
+    assert_eq!(unhook(stub_a), SrxHookErrno::InvalidArg, "stub survived");
+    clear();
+}
+
+// 挂载 puts 并确认命中，返回 stub
+unsafe fn hook_puts_and_hit(handle: *mut c_void, label: &str) -> HookStub {
+    let stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .unwrap_or_else(|| panic!("hook_single {label} failed"));
+    ensure_ok(refresh(), label);
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert!(
+        HOOK_A_COUNT.load(Ordering::Relaxed) >= 1,
+        "{label}: hook not hit"
+    );
+    stub
+}
+
+pub unsafe fn scenario_shutdown_reinit() {
+    clear();
+    let handle = load_hook_test();
+
+    // 自动模式启动 monitor 线程，shutdown 后 GOT 恢复
+    ensure_ok(init(HookMode::Automatic, true), "init shutdown round 1");
+    hook_puts_and_hit(handle, "shutdown round 1");
+    ensure_ok(shutdown(false), "shutdown keep cfi");
+    assert!(get_hook_tasks().is_empty(), "tasks left after shutdown");
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert_eq!(
+        HOOK_A_COUNT.load(Ordering::Relaxed),
+        0,
+        "hook still active after shutdown"
+    );
+
+    // 回滚 CFI 补丁后再次 init 需重新 patch
+    ensure_ok(init(HookMode::Manual, true), "init shutdown round 2");
+    let stub = hook_puts_and_hit(handle, "shutdown round 2");
+    ensure_ok(unhook(stub), "unhook shutdown round 2");
+    ensure_ok(shutdown(true), "shutdown revert cfi");
+
+    ensure_ok(init(HookMode::Manual, true), "init shutdown round 3");
+    verify_cfi_slowpath_disabled();
+    hook_puts_and_hit(handle, "shutdown round 3");
+
+    libc::dlclose(handle);
+    clear();
+}
+
+pub unsafe fn scenario_init_options() {
+    clear();
+    let invalid = [
+        InitOptions::new().monitor_poll(Duration::from_secs(2), Duration::from_secs(1)),
+        InitOptions::new().monitor_poll(Duration::ZERO, Duration::from_secs(1)),
+        InitOptions::new().loader_stable_threshold(0),
+        InitOptions::new().hub_destroy_delay(0),
+        InitOptions::new().record_capacity(0),
+        InitOptions::new().hub_stack_spill(0, 64),
+        InitOptions::new().hub_stack_spill(HUB_STACK_FIXED_DEPTH + 1, 64),
+        InitOptions::new().hub_stack_spill(16, 8),
+    ];
+    for opts in invalid {
+        assert_eq!(
+            init_with_options(opts.clone()),
+            SrxHookErrno::InitErrInvalidArg,
+            "invalid options accepted: {opts:?}"
+        );
+    }
+
+    // 参数无效不占用初始化，之后仍可正常 init
+    let opts = InitOptions::new()
+        .mode(HookMode::Manual)
+        .debug(true)
+        .monitor_poll(Duration::from_millis(200), Duration::from_secs(2))
+        .loader_stable_threshold(16)
+        .hub_destroy_delay(3)
+        .record_capacity(128)
+        .disable_cfi_patch();
+    ensure_ok(init_with_options(opts), "init_with_options");
+    assert_eq!(get_mode(), HookMode::Manual);
+    assert_eq!(get_record_capacity(), 128);
+    let caps = get_capabilities().expect("capabilities missing after init_with_options");
+    assert!(caps.cfi.skipped, "cfi patch not skipped");
+
+    let handle = load_hook_test();
+    let _stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single init options failed");
+    ensure_ok(refresh(), "refresh init options");
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert!(
+        HOOK_A_COUNT.load(Ordering::Relaxed) >= 1,
+        "hook not hit with init options"
+    );
+
+    // 普通 init 恢复默认参数，CFI 重新 patch
+    libc::dlclose(handle);
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init defaults after options");
+    verify_cfi_slowpath_disabled();
+    ensure_ok(set_record_capacity(4096), "restore record capacity");
+    clear();
+}
@@ -0,0 +1,83 @@
+use std::ffi::{c_char, c_void};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use srx_hook::{
+    CallbackDispatch, HookMode, HookStub, clear, get_callback_dispatch, hook_single, init, refresh,
+    set_callback_dispatch, unhook,
+};
+
+use crate::test_ctx::{
+    ensure_ok, hook_puts_a_chain, hook_puts_b_chain, hook_puts_c_chain, load_hook_test,
+};
+
+const SLOW_CALLBACK_DELAY: Duration = Duration::from_millis(300);
+
+static DELIVERED: Mutex<Vec<HookStub>> = Mutex::new(Vec::new());
+
+unsafe extern "C" fn slow_hooked_callback(
+    task_stub: HookStub,
+    _status_code: i32,
+    _caller_path_name: *const c_char,
+    _sym_name: *const c_char,
+    _new_func: *mut c_void,
+    _prev_func: *mut c_void,
+    _arg: *mut c_void,
+) {
+    std::thread::sleep(SLOW_CALLBACK_DELAY);
+    DELIVERED.lock().unwrap().push(task_stub);
+}
+
+pub unsafe fn scenario_callback_dedicated_thread() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init manual callback thread");
+    set_callback_dispatch(CallbackDispatch::DedicatedThread);
+    assert_eq!(get_callback_dispatch(), CallbackDispatch::DedicatedThread);
+    DELIVERED.lock().unwrap().clear();
+    let handle = load_hook_test();
+
+    let stubs: Vec<HookStub> = [
+        hook_puts_a_chain as *mut c_void,
+        hook_puts_b_chain as *mut c_void,
+        hook_puts_c_chain as *mut c_void,
+    ]
+    .into_iter()
+    .map(|new_func| {
+        hook_single(
+            "libhook_test.so",
+            None,
+            "puts",
+            new_func,
+            Some(slow_hooked_callback),
+            std::ptr::null_mut(),
+        )
+        .expect("hook_single with callback failed")
+    })
+    .collect();
+
+    // 三个慢回调内联执行至少需要 3 * 300ms，异步模式下 refresh 不应被阻塞
+    let start = Instant::now();
+    ensure_ok(refresh(), "refresh callback thread");
+    let elapsed = start.elapsed();
+    assert!(
+        elapsed < SLOW_CALLBACK_DELAY,
+        "refresh blocked by slow callback: {elapsed:?}"
+    );
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while DELIVERED.lock().unwrap().len() < stubs.len() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    assert_eq!(
+        *DELIVERED.lock().unwrap(),
+        stubs,
+        "callbacks not delivered in task order"
+    );
+
+    set_callback_dispatch(CallbackDispatch::Inline);
+    for stub in stubs {
+        ensure_ok(unhook(stub), "unhook callback thread");
+    }
+    libc::dlclose(handle);
+    clear();
+}
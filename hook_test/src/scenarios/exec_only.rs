@@ -0,0 +1,69 @@
+use std::ffi::c_void;
+use std::sync::atomic::Ordering;
+
+use srx_hook::{HookMode, clear, enable_header_file_fallback, hook_single, init, refresh, unhook};
+
+use crate::test_ctx::{
+    HOOK_A_COUNT, ensure_ok, hook_puts_quiet, hook_test_fixtures, hook_test_trigger,
+    module_base_from_handle,
+};
+
+// 将副本的 ELF header 所在页改为 execute-only，模拟加固构建下程序头不可读
+// 要求 fixture 的 .dynsym/.rela 等表不与 header 共页
+pub unsafe fn scenario_exec_only_headers() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init exec-only headers");
+    let fixture = hook_test_fixtures("exec_only", 1);
+    let handle = fixture[0].handle();
+    let base = module_base_from_handle(handle).expect("resolve exec-only copy base failed");
+    let page = libc::sysconf(libc::_SC_PAGESIZE) as usize;
+    assert_eq!(
+        libc::mprotect(base as *mut c_void, page, libc::PROT_EXEC),
+        0,
+        "mprotect header page execute-only failed"
+    );
+
+    let caller = fixture[0].path().to_str().expect("exec-only path utf8");
+    enable_header_file_fallback(false);
+    let stub = hook_single(
+        caller,
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single exec-only failed");
+    ensure_ok(refresh(), "refresh exec-only without fallback");
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert_eq!(
+        HOOK_A_COUNT.load(Ordering::Relaxed),
+        0,
+        "exec-only module hooked without file fallback"
+    );
+    ensure_ok(unhook(stub), "unhook exec-only without fallback");
+
+    enable_header_file_fallback(true);
+    let stub = hook_single(
+        caller,
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single exec-only fallback failed");
+    ensure_ok(refresh(), "refresh exec-only with fallback");
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert!(
+        HOOK_A_COUNT.load(Ordering::Relaxed) >= 1,
+        "exec-only module not hooked via file headers"
+    );
+    ensure_ok(unhook(stub), "unhook exec-only with fallback");
+
+    let _ = libc::mprotect(base as *mut c_void, page, libc::PROT_READ);
+    drop(fixture);
+    clear();
+}
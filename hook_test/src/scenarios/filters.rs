@@ -1,15 +1,23 @@
-use std::ffi::c_void;
+use std::ffi::{CString, c_char, c_void};
+use std::sync::Mutex;
 use std::sync::atomic::Ordering;
 
 use srx_hook::{
-    HookMode, add_ignore, clear, get_module_identity, get_module_identity_with_symbol, hook_all,
-    hook_single, init, refresh, unhook,
+    CalleeSelection, HookEventKind, HookMode, HookStub, RECORD_ITEM_ALL, RECORD_ITEM_ERRNO,
+    RECORD_ITEM_INSTANCE, RECORD_ITEM_LIB_NAME, RECORD_ITEM_NAMESPACE, RECORD_ITEM_OP,
+    RECORD_ITEM_SYM_NAME, RecordOp, SingleBindPolicy, SrxHookErrno, add_ignore, clear,
+    get_hook_tasks, get_module_identity, get_module_identity_with_symbol, get_orig_func,
+    get_orig_funcs, get_pattern_match_limit, get_record_entries, get_records, hook_all,
+    hook_all_with_callers, hook_all_with_callers_checked, hook_partial_ex, hook_single,
+    hook_single_checked, hook_single_pattern, hook_single_pattern_checked, hook_single_with_policy,
+    init, refresh, set_callee_selection, set_hook_event_callback, set_pattern_match_limit,
+    set_recordable, unhook,
 };
 
 use crate::test_ctx::{
-    HOOK_A_COUNT, ensure_ok, hook_puts_quiet, hook_test_trigger, load_hook_test,
-    load_hook_test_abs, load_hook_test_lazy, module_base_from_handle, module_instance_from_handle,
-    prepare_same_basename_hook_test_instances, resolve_symbol_module_base,
+    HOOK_A_COUNT, ensure_ok, hook_puts_quiet, hook_test_fixtures, hook_test_trigger,
+    load_hook_test, load_hook_test_lazy, module_base_from_handle, module_instance_from_handle,
+    resolve_symbol_module_base,
 };
 
 pub unsafe fn scenario_callee_filter() {
@@ -187,9 +195,8 @@ pub unsafe fn scenario_single_same_basename_multi_instance() {
     clear();
     ensure_ok(init(HookMode::Manual, true), "init single same basename");
 
-    let (path_a, path_b) = prepare_same_basename_hook_test_instances();
-    let handle_a = load_hook_test_abs(&path_a);
-    let handle_b = load_hook_test_abs(&path_b);
+    let mut fixtures = hook_test_fixtures("same_basename", 2);
+    let (handle_a, handle_b) = (fixtures[0].handle(), fixtures[1].handle());
 
     let base_a = module_base_from_handle(handle_a).expect("resolve base for handle_a failed");
     let base_b = module_base_from_handle(handle_b).expect("resolve base for handle_b failed");
@@ -206,20 +213,14 @@ pub unsafe fn scenario_single_same_basename_multi_instance() {
     .expect("hook_single same basename failed");
     ensure_ok(refresh(), "refresh single same basename");
 
-    HOOK_A_COUNT.store(0, Ordering::Relaxed);
-    hook_test_trigger(handle_a);
-    let count_after_a = HOOK_A_COUNT.load(Ordering::Relaxed);
-    hook_test_trigger(handle_b);
-    let count_after_b = HOOK_A_COUNT.load(Ordering::Relaxed);
-    let hit_a = count_after_a > 0;
-    let hit_b = count_after_b > count_after_a;
+    let (hit_a, hit_b) = instance_hits(handle_a, handle_b);
     assert!(
         hit_a ^ hit_b,
         "single task should bind exactly one instance, hit_a={hit_a}, hit_b={hit_b}"
     );
 
     if hit_a {
-        libc::dlclose(handle_a);
+        fixtures[0].close();
         ensure_ok(refresh(), "refresh single same basename rebind");
         HOOK_A_COUNT.store(0, Ordering::Relaxed);
         hook_test_trigger(handle_b);
@@ -228,9 +229,8 @@ pub unsafe fn scenario_single_same_basename_multi_instance() {
             "single task should rebind to remaining instance after unload"
         );
         ensure_ok(unhook(stub), "unhook single same basename");
-        libc::dlclose(handle_b);
     } else {
-        libc::dlclose(handle_b);
+        fixtures[1].close();
         ensure_ok(refresh(), "refresh single same basename rebind");
         HOOK_A_COUNT.store(0, Ordering::Relaxed);
         hook_test_trigger(handle_a);
@@ -239,9 +239,480 @@ pub unsafe fn scenario_single_same_basename_multi_instance() {
             "single task should rebind to remaining instance after unload"
         );
         ensure_ok(unhook(stub), "unhook single same basename");
-        libc::dlclose(handle_a);
     }
+    drop(fixtures);
+
+    // AllMatches 挂上两个实例；NewestInstance 只挂后加载的实例
+    let cases = [
+        ("same_basename_all", SingleBindPolicy::AllMatches, true),
+        ("same_basename_new", SingleBindPolicy::NewestInstance, false),
+    ];
+    for (name, policy, expect_hit_a) in cases {
+        let fixtures = hook_test_fixtures(name, 2);
+        let stub = hook_single_with_policy(
+            "libhook_test.so",
+            None,
+            "puts",
+            hook_puts_quiet as *mut c_void,
+            policy,
+            None,
+            std::ptr::null_mut(),
+        )
+        .expect("hook_single_with_policy failed");
+        ensure_ok(refresh(), "refresh single bind policy");
+        assert_eq!(
+            instance_hits(fixtures[0].handle(), fixtures[1].handle()),
+            (expect_hit_a, true),
+            "{policy:?} bound unexpected instances"
+        );
+        ensure_ok(unhook(stub), "unhook single bind policy");
+        drop(fixtures);
+    }
+    clear();
+}
+
+// 依次触发两个实例，返回各自是否经过 proxy
+unsafe fn instance_hits(handle_a: *mut c_void, handle_b: *mut c_void) -> (bool, bool) {
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle_a);
+    let count_after_a = HOOK_A_COUNT.load(Ordering::Relaxed);
+    hook_test_trigger(handle_b);
+    let count_after_b = HOOK_A_COUNT.load(Ordering::Relaxed);
+    (count_after_a > 0, count_after_b > count_after_a)
+}
+
+// arg 为目标实例的基址，只放行该实例
+unsafe extern "C" fn allow_base_only(
+    _caller_path_name: *const c_char,
+    base_addr: usize,
+    _instance_id: usize,
+    _namespace_id: usize,
+    arg: *mut c_void,
+) -> bool {
+    base_addr == arg as usize
+}
+
+// 同 basename 的两个实例：hook_partial_ex 的过滤器按基址只选中第二个实例
+pub unsafe fn scenario_partial_ex_filter_by_base() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init partial ex filter");
+    let fixtures = hook_test_fixtures("partial_ex_base", 2);
+    let (handle_a, handle_b) = (fixtures[0].handle(), fixtures[1].handle());
+    let base_b = module_base_from_handle(handle_b).expect("resolve second instance base failed");
+
+    let stub = hook_partial_ex(
+        allow_base_only,
+        base_b as *mut c_void,
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_partial_ex failed");
+    ensure_ok(refresh(), "refresh partial ex filter");
+    assert_eq!(
+        instance_hits(handle_a, handle_b),
+        (false, true),
+        "base filter should select only the second instance"
+    );
+
+    ensure_ok(unhook(stub), "unhook partial ex filter");
+    drop(fixtures);
+    clear();
+}
+
+// 同 basename 的两个实例：白名单按 @base 只选中其一；宽泛的 ignore 跳过两者，'!' 例外把另一个放回
+pub unsafe fn scenario_all_with_callers_same_basename() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init all with callers");
+
+    let fixtures = hook_test_fixtures("allow_list", 2);
+    let (handle_a, handle_b) = (fixtures[0].handle(), fixtures[1].handle());
+    let base_a = module_base_from_handle(handle_a).expect("resolve base for handle_a failed");
+    let base_b = module_base_from_handle(handle_b).expect("resolve base for handle_b failed");
+    assert_ne!(
+        base_a, base_b,
+        "allow list test needs two loaded module instances"
+    );
+    let rule_a = format!("libhook_test.so@0x{base_a:x}");
+    let rule_b = format!("libhook_test.so@0x{base_b:x}");
+
+    let hits = || {
+        HOOK_A_COUNT.store(0, Ordering::Relaxed);
+        hook_test_trigger(handle_a);
+        let after_a = HOOK_A_COUNT.load(Ordering::Relaxed);
+        hook_test_trigger(handle_b);
+        let after_b = HOOK_A_COUNT.load(Ordering::Relaxed);
+        (after_a > 0, after_b > after_a)
+    };
+
+    assert_eq!(
+        hook_all_with_callers_checked(
+            &[],
+            None,
+            "puts",
+            hook_puts_quiet as *mut c_void,
+            None,
+            std::ptr::null_mut(),
+        ),
+        Err(SrxHookErrno::InvalidArg),
+        "empty allow list accepted"
+    );
+
+    let stub = hook_all_with_callers(
+        &[rule_a.as_str()],
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_all_with_callers failed");
+    ensure_ok(refresh(), "refresh allow list");
+    assert_eq!(
+        hits(),
+        (true, false),
+        "allow list should hook only instance a"
+    );
+    let tasks = get_hook_tasks();
+    let task = tasks
+        .iter()
+        .find(|task| task.stub == stub)
+        .expect("allow list task missing");
+    assert_eq!(task.caller_allow_list, vec![rule_a.clone()]);
+    ensure_ok(unhook(stub), "unhook allow list");
+
+    ensure_ok(add_ignore("libhook_test.so"), "add_ignore basename");
+    ensure_ok(add_ignore(&format!("!{rule_b}")), "add_ignore negated");
+    assert_eq!(add_ignore("!"), SrxHookErrno::InvalidArg);
+    let stub = hook_all_with_callers(
+        &["libhook_test.so"],
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_all_with_callers basename failed");
+    ensure_ok(refresh(), "refresh negated ignore");
+    assert_eq!(
+        hits(),
+        (false, true),
+        "negated ignore should keep only instance b"
+    );
+    ensure_ok(unhook(stub), "unhook negated ignore");
+
+    drop(fixtures);
+    clear();
+}
+
+// soname: 规则按 DT_SONAME 匹配 caller / callee 与 ignore，不受模块所在路径影响
+pub unsafe fn scenario_soname_rule() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init soname rule");
+    let handle = load_hook_test();
+
+    let stub = hook_single(
+        "soname:libhook_test.so",
+        Some("soname:libc.so"),
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single soname failed");
+    ensure_ok(refresh(), "refresh soname");
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert!(
+        HOOK_A_COUNT.load(Ordering::Relaxed) >= 1,
+        "soname caller/callee rule not hooked"
+    );
+    ensure_ok(unhook(stub), "unhook soname");
+
+    // hook_test.so 作为路径规则能按后缀匹配，作为 soname 规则则要求完全相等
+    let stub = hook_single(
+        "soname:hook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single mismatched soname failed");
+    ensure_ok(refresh(), "refresh mismatched soname");
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert_eq!(
+        HOOK_A_COUNT.load(Ordering::Relaxed),
+        0,
+        "mismatched soname rule hooked"
+    );
+    ensure_ok(unhook(stub), "unhook mismatched soname");
+
+    ensure_ok(add_ignore("soname:libhook_test.so"), "add_ignore soname");
+    let stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single soname ignore failed");
+    ensure_ok(refresh(), "refresh soname ignore");
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert_eq!(
+        HOOK_A_COUNT.load(Ordering::Relaxed),
+        0,
+        "soname ignore rule not applied"
+    );
+    ensure_ok(unhook(stub), "unhook soname ignore");
+
+    libc::dlclose(handle);
+    clear();
+}
+
+// bionic 导出的 puts 带版本 LIBC，libhook_test.so 的导入经 verneed 绑定到该版本
+pub unsafe fn scenario_versioned_symbol() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init versioned symbol");
+    let handle = load_hook_test();
+
+    let stub = hook_single(
+        "libhook_test.so",
+        Some("libc.so"),
+        "puts@LIBC",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single puts@LIBC failed");
+    ensure_ok(refresh(), "refresh versioned symbol");
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert!(
+        HOOK_A_COUNT.load(Ordering::Relaxed) >= 1,
+        "versioned symbol not hooked"
+    );
+    ensure_ok(unhook(stub), "unhook versioned symbol");
+
+    let stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts@LIBC_P",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single puts@LIBC_P failed");
+    ensure_ok(refresh(), "refresh mismatched version");
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert_eq!(
+        HOOK_A_COUNT.load(Ordering::Relaxed),
+        0,
+        "mismatched symbol version hooked"
+    );
+    ensure_ok(unhook(stub), "unhook mismatched version");
+
+    for sym_name in ["puts@", "@LIBC"] {
+        let result = hook_single_checked(
+            "libhook_test.so",
+            None,
+            sym_name,
+            hook_puts_quiet as *mut c_void,
+            None,
+            std::ptr::null_mut(),
+        );
+        assert_eq!(result, Err(SrxHookErrno::InvalidArg), "{sym_name} accepted");
+    }
+
+    libc::dlclose(handle);
+    clear();
+}
+
+// 通配符号 "pu*s" 展开为 puts，上限为 1 时单个匹配仍可挂载；'@' 与通配混用、上限 0 均被拒绝
+pub unsafe fn scenario_single_pattern() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init single pattern");
+    let handle = load_hook_test();
+
+    let stub = hook_single_pattern(
+        "libhook_test.so",
+        None,
+        "pu*s",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single_pattern pu*s failed");
+    let limit = get_pattern_match_limit();
+    ensure_ok(set_pattern_match_limit(1), "set pattern limit");
+    ensure_ok(refresh(), "refresh single pattern");
+    ensure_ok(set_pattern_match_limit(limit), "restore pattern limit");
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert!(
+        HOOK_A_COUNT.load(Ordering::Relaxed) >= 1,
+        "pattern symbol not hooked"
+    );
+    ensure_ok(unhook(stub), "unhook single pattern");
+
+    let result = hook_single_pattern_checked(
+        "libhook_test.so",
+        None,
+        "pu*s@LIBC",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    );
+    assert_eq!(
+        result,
+        Err(SrxHookErrno::InvalidArg),
+        "versioned pattern accepted"
+    );
+    assert_eq!(set_pattern_match_limit(0), SrxHookErrno::InvalidArg);
+
+    libc::dlclose(handle);
+    clear();
+}
+
+static RELOAD_EVENTS: Mutex<Vec<HookEventKind>> = Mutex::new(Vec::new());
+
+unsafe extern "C" fn record_reload_event(
+    _task_stub: HookStub,
+    event: HookEventKind,
+    status_code: i32,
+    _caller_path_name: *const c_char,
+    _sym_name: *const c_char,
+    _new_func: *mut c_void,
+    _prev_func: *mut c_void,
+    _arg: *mut c_void,
+) {
+    // Manual 模式写入前的 OrigAddr 预告不计入
+    if status_code == 0 {
+        RELOAD_EVENTS.lock().unwrap().push(event);
+    }
+}
+
+// 同一路径的实例卸载后重新加载：任务先收到 ModuleUnloaded，重新挂上时收到 Rehooked
+pub unsafe fn scenario_module_reload_events() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init module reload events");
+
+    let mut fixtures = hook_test_fixtures("reload_events", 1);
+    let path = fixtures[0].path().to_string_lossy().into_owned();
+    let c_path = fixtures[0].c_path();
+
+    let stub = hook_single(
+        path.as_str(),
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single module reload events failed");
+    RELOAD_EVENTS.lock().unwrap().clear();
+    ensure_ok(
+        set_hook_event_callback(stub, Some(record_reload_event), std::ptr::null_mut()),
+        "set hook event callback",
+    );
+    ensure_ok(refresh(), "refresh module reload events");
+
+    fixtures[0].close();
+    ensure_ok(refresh(), "refresh after dlclose");
+
+    let handle = libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW);
+    assert!(!handle.is_null(), "reopen reload fixture failed");
+    ensure_ok(refresh(), "refresh after reopen");
 
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert!(
+        HOOK_A_COUNT.load(Ordering::Relaxed) > 0,
+        "reloaded instance should be hooked again"
+    );
+
+    ensure_ok(unhook(stub), "unhook module reload events");
+    let events: Vec<HookEventKind> = RELOAD_EVENTS
+        .lock()
+        .unwrap()
+        .iter()
+        .copied()
+        .filter(|event| *event != HookEventKind::Hooked)
+        .collect();
+    assert_eq!(
+        events,
+        vec![
+            HookEventKind::ModuleUnloaded,
+            HookEventKind::Rehooked,
+            HookEventKind::Unhooked,
+        ],
+        "unexpected lifecycle events across reload"
+    );
+
+    libc::dlclose(handle);
+    drop(fixtures);
+    clear();
+}
+
+// 原始函数地址只在任务绑定 slot 期间有效，模块卸载并刷新后返回 None
+pub unsafe fn scenario_orig_func_lifecycle() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init orig func");
+
+    let unbound = hook_single(
+        "libsrx_not_loaded.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single unbound orig func failed");
+    assert!(
+        get_orig_func(unbound).is_none(),
+        "unbound task should have no orig func"
+    );
+    ensure_ok(unhook(unbound), "unhook unbound orig func");
+
+    let mut fixtures = hook_test_fixtures("orig_func", 1);
+    let path = fixtures[0].path().to_string_lossy().into_owned();
+    let stub = hook_single(
+        path.as_str(),
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single orig func failed");
+    ensure_ok(refresh(), "refresh orig func");
+
+    let puts_name = CString::new("puts").expect("cstring failed");
+    let libc_puts = libc::dlsym(libc::RTLD_DEFAULT, puts_name.as_ptr());
+    assert_eq!(
+        get_orig_func(stub),
+        Some(libc_puts),
+        "orig func should be the unhooked puts"
+    );
+    let orig_funcs = get_orig_funcs(stub);
+    assert_eq!(orig_funcs.len(), 1, "expected one bound slot");
+    assert_eq!(orig_funcs[0].0, path, "orig func caller path mismatch");
+
+    fixtures[0].close();
+    ensure_ok(refresh(), "refresh orig func after dlclose");
+    assert!(
+        get_orig_func(stub).is_none(),
+        "orig func should be dropped after module unload"
+    );
+
+    ensure_ok(unhook(stub), "unhook orig func");
+    drop(fixtures);
     clear();
 }
 
@@ -249,9 +720,8 @@ pub unsafe fn scenario_instance_qualified_path_rule() {
     clear();
     ensure_ok(init(HookMode::Manual, true), "init instance-qualified rule");
 
-    let (path_a, path_b) = prepare_same_basename_hook_test_instances();
-    let handle_a = load_hook_test_abs(&path_a);
-    let handle_b = load_hook_test_abs(&path_b);
+    let fixtures = hook_test_fixtures("same_basename", 2);
+    let (handle_a, handle_b) = (fixtures[0].handle(), fixtures[1].handle());
 
     let base_a = module_base_from_handle(handle_a).expect("resolve base for handle_a failed");
     let instance_a =
@@ -309,8 +779,7 @@ pub unsafe fn scenario_instance_qualified_path_rule() {
     );
     ensure_ok(unhook(right_stub), "unhook right instance rule");
 
-    libc::dlclose(handle_b);
-    libc::dlclose(handle_a);
+    drop(fixtures);
     clear();
 }
 
@@ -318,9 +787,8 @@ pub unsafe fn scenario_ignore_instance_qualified_rule() {
     clear();
     ensure_ok(init(HookMode::Manual, true), "init ignore instance-qualified rule");
 
-    let (path_a, path_b) = prepare_same_basename_hook_test_instances();
-    let handle_a = load_hook_test_abs(&path_a);
-    let handle_b = load_hook_test_abs(&path_b);
+    let fixtures = hook_test_fixtures("same_basename", 2);
+    let (handle_a, handle_b) = (fixtures[0].handle(), fixtures[1].handle());
 
     let base_a = module_base_from_handle(handle_a).expect("resolve base for handle_a failed");
     let instance_a =
@@ -357,18 +825,17 @@ pub unsafe fn scenario_ignore_instance_qualified_rule() {
     );
 
     ensure_ok(unhook(stub), "unhook ignore instance-qualified rule");
-    libc::dlclose(handle_b);
-    libc::dlclose(handle_a);
+    drop(fixtures);
     clear();
 }
 
 pub unsafe fn scenario_instance_rule_from_handle_api() {
     clear();
     ensure_ok(init(HookMode::Manual, true), "init instance rule from handle api");
+    set_recordable(true);
 
-    let (path_a, path_b) = prepare_same_basename_hook_test_instances();
-    let handle_a = load_hook_test_abs(&path_a);
-    let handle_b = load_hook_test_abs(&path_b);
+    let fixtures = hook_test_fixtures("same_basename", 2);
+    let (handle_a, handle_b) = (fixtures[0].handle(), fixtures[1].handle());
 
     let identity_a = get_module_identity(handle_a)
         .or_else(|| get_module_identity_with_symbol(handle_a, "hook_test_trigger"));
@@ -404,8 +871,33 @@ pub unsafe fn scenario_instance_rule_from_handle_api() {
     );
 
     ensure_ok(unhook(stub), "unhook instance rule from handle api");
-    libc::dlclose(handle_b);
-    libc::dlclose(handle_a);
+
+    // UNHOOK 记录带有 caller 实例，文本可直接作为 caller 规则使用
+    let records = get_records(
+        RECORD_ITEM_OP | RECORD_ITEM_LIB_NAME | RECORD_ITEM_INSTANCE | RECORD_ITEM_NAMESPACE,
+    )
+    .unwrap_or_default();
+    let expected = format!("UNHOOK,{},", identity_a.caller_rule());
+    assert!(
+        records.lines().any(|line| line == expected),
+        "missing instance-qualified UNHOOK record {expected}: {records}"
+    );
+    let unhook_entry = get_record_entries(RECORD_ITEM_ALL)
+        .into_iter()
+        .find(|entry| entry.op == Some(RecordOp::Unhook) && entry.stub == Some(stub))
+        .expect("UNHOOK entry missing");
+    assert_eq!(unhook_entry.caller_base_addr, Some(identity_a.base_addr));
+    assert_eq!(
+        unhook_entry.caller_instance_id,
+        Some(identity_a.instance_id)
+    );
+    assert_eq!(
+        unhook_entry.caller_namespace_id,
+        Some(identity_a.namespace_id)
+    );
+
+    set_recordable(false);
+    drop(fixtures);
     clear();
 }
 
@@ -476,9 +968,8 @@ pub unsafe fn scenario_namespace_rule_from_handle_api() {
     clear();
     ensure_ok(init(HookMode::Manual, true), "init namespace rule from handle api");
 
-    let (path_a, path_b) = prepare_same_basename_hook_test_instances();
-    let handle_a = load_hook_test_abs(&path_a);
-    let handle_b = load_hook_test_abs(&path_b);
+    let fixtures = hook_test_fixtures("same_basename", 2);
+    let (handle_a, handle_b) = (fixtures[0].handle(), fixtures[1].handle());
 
     let identity_a = get_module_identity(handle_a)
         .or_else(|| get_module_identity_with_symbol(handle_a, "hook_test_trigger"));
@@ -551,7 +1042,52 @@ pub unsafe fn scenario_namespace_rule_from_handle_api() {
     );
 
     ensure_ok(unhook(right_stub), "unhook right namespace rule");
-    libc::dlclose(handle_b);
-    libc::dlclose(handle_a);
+    drop(fixtures);
+    clear();
+}
+
+// 两份 libhook_test 同时加载，callee 规则按 basename 匹配到两个导出实例
+pub unsafe fn scenario_duplicate_callee_instances() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init duplicate callee");
+    set_recordable(true);
+
+    let fixtures = hook_test_fixtures("duplicate_callee", 2);
+
+    let stub = hook_all(
+        Some("libhook_test.so"),
+        "hook_test_trigger",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_all duplicate callee failed");
+    ensure_ok(
+        set_callee_selection(stub, CalleeSelection::Error),
+        "set callee selection",
+    );
+    assert_eq!(
+        set_callee_selection(stub + 1000, CalleeSelection::Error),
+        SrxHookErrno::InvalidArg
+    );
+    ensure_ok(refresh(), "refresh duplicate callee");
+    ensure_ok(refresh(), "refresh duplicate callee again");
+
+    // 多实例只记录一次
+    let records = get_records(RECORD_ITEM_LIB_NAME | RECORD_ITEM_SYM_NAME | RECORD_ITEM_ERRNO)
+        .unwrap_or_default();
+    let marker = format!(
+        "libhook_test.so,hook_test_trigger,{},",
+        SrxHookErrno::AmbiguousCallee.as_i32()
+    );
+    assert_eq!(
+        records.lines().filter(|line| *line == marker).count(),
+        1,
+        "duplicate callee should be recorded once: {records}"
+    );
+
+    ensure_ok(unhook(stub), "unhook duplicate callee");
+    set_recordable(false);
+    drop(fixtures);
     clear();
 }
@@ -0,0 +1,198 @@
+use std::ffi::{CStr, c_char, c_void};
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use srx_hook::{
+    HookMode, InitOptions, SrxHookErrno, clear, get_fork_prev_fallback_count,
+    get_import_slot_values, hook_single, init, init_with_options, is_forked_child, refresh,
+    reinit_after_fork, unhook, with_prev_func_as,
+};
+
+use crate::test_ctx::{
+    HOOK_A_COUNT, PutsFn, ensure_ok, hook_puts_quiet, hook_test_trigger, load_hook_test,
+    module_base_from_handle,
+};
+
+const CHILD_MARKER: &CStr = c"fork-child-reached-libc";
+const TRUE_BIN: &CStr = c"/system/bin/true";
+
+static CHILD_PIPE_FD: AtomicI32 = AtomicI32::new(-1);
+static CHILD_STATUS: AtomicI32 = AtomicI32::new(-1);
+
+// 在 proxy 内 fork：子进程没有线程状态，仍需通过 with_prev_func 调到真实 puts
+unsafe extern "C" fn hook_puts_fork(s: *const c_char) -> i32 {
+    let self_ptr = hook_puts_fork as *mut c_void;
+    libc::fflush(std::ptr::null_mut());
+    let pid = libc::fork();
+    if pid == 0 {
+        libc::dup2(CHILD_PIPE_FD.load(Ordering::Relaxed), libc::STDOUT_FILENO);
+        let ret = with_prev_func_as(self_ptr, |prev: Option<PutsFn>| {
+            prev.map_or(-1, |prev_fn| unsafe { prev_fn(CHILD_MARKER.as_ptr()) })
+        })
+        .unwrap_or(-1);
+        libc::fflush(std::ptr::null_mut());
+        let ok = ret >= 0 && get_fork_prev_fallback_count() > 0;
+        libc::_exit(if ok { 0 } else { 1 });
+    }
+    if pid > 0 {
+        let mut status = 0;
+        libc::waitpid(pid, &mut status, 0);
+        CHILD_STATUS.store(status, Ordering::Relaxed);
+    }
+    with_prev_func_as(self_ptr, |prev: Option<PutsFn>| {
+        prev.map_or(0, |prev_fn| unsafe { prev_fn(s) })
+    })
+    .unwrap_or(0)
+}
+
+pub unsafe fn scenario_fork_child_prev_func() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init fork child");
+    let handle = load_hook_test();
+
+    let mut fds = [0; 2];
+    assert_eq!(libc::pipe(fds.as_mut_ptr()), 0, "pipe failed");
+    CHILD_PIPE_FD.store(fds[1], Ordering::Relaxed);
+    CHILD_STATUS.store(-1, Ordering::Relaxed);
+
+    let stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_fork as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single fork child failed");
+    ensure_ok(refresh(), "refresh fork child");
+    hook_test_trigger(handle);
+    ensure_ok(unhook(stub), "unhook fork child");
+    libc::close(fds[1]);
+
+    let mut output = Vec::new();
+    let mut buf = [0u8; 256];
+    loop {
+        let n = libc::read(fds[0], buf.as_mut_ptr() as *mut c_void, buf.len());
+        if n <= 0 {
+            break;
+        }
+        output.extend_from_slice(&buf[..n as usize]);
+    }
+    libc::close(fds[0]);
+
+    let status = CHILD_STATUS.load(Ordering::Relaxed);
+    assert!(
+        libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0,
+        "fork child prev_func fallback failed, status={status}"
+    );
+    assert!(
+        String::from_utf8_lossy(&output).contains(CHILD_MARKER.to_str().unwrap()),
+        "fork child puts did not reach libc"
+    );
+
+    libc::dlclose(handle);
+    clear();
+}
+
+// 子进程在 exec 前读取 slot：与 expect_orig 一致时 exec true，否则以 1 退出，返回 waitpid 状态
+unsafe fn fork_exec_check_slot(slot_addr: usize, orig: usize, expect_orig: bool) -> i32 {
+    let argv = [TRUE_BIN.as_ptr(), std::ptr::null()];
+    let pid = libc::fork();
+    if pid == 0 {
+        let value = std::ptr::read_volatile(slot_addr as *const usize);
+        if (value == orig) == expect_orig {
+            libc::execv(TRUE_BIN.as_ptr(), argv.as_ptr());
+        }
+        libc::_exit(1);
+    }
+    assert!(pid > 0, "fork failed");
+    let mut status = 0;
+    libc::waitpid(pid, &mut status, 0);
+    status
+}
+
+// 开启 restore_got_in_fork_child 后子进程 exec 前的 puts slot 已写回原始值，父进程保持 hook；未开启时子进程保留改写
+pub unsafe fn scenario_fork_exec_restores_got() {
+    clear();
+    let handle = load_hook_test();
+    let base = module_base_from_handle(handle).expect("hook_test base not found");
+    let (_, slot_addr, orig) = *get_import_slot_values("libhook_test.so", "puts")
+        .expect("slot query failed")
+        .iter()
+        .find(|(identity, _, _)| identity.base_addr == base)
+        .expect("puts slot not found");
+
+    for restore in [true, false] {
+        let opts = InitOptions::new()
+            .mode(HookMode::Manual)
+            .debug(true)
+            .restore_got_in_fork_child(restore);
+        ensure_ok(init_with_options(opts), "init fork exec");
+        let stub = hook_single(
+            "libhook_test.so",
+            None,
+            "puts",
+            hook_puts_quiet as *mut c_void,
+            None,
+            std::ptr::null_mut(),
+        )
+        .expect("hook_single fork exec failed");
+        ensure_ok(refresh(), "refresh fork exec");
+        let hooked = || std::ptr::read_volatile(slot_addr as *const usize) != orig;
+        assert!(hooked(), "puts slot not hooked before fork");
+
+        let status = fork_exec_check_slot(slot_addr, orig, restore);
+        assert!(
+            libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0,
+            "fork child slot check failed, restore={restore} status={status}"
+        );
+        assert!(hooked(), "parent puts slot changed after fork");
+        ensure_ok(unhook(stub), "unhook fork exec");
+        clear();
+    }
+    libc::dlclose(handle);
+}
+
+// fork 后不 exec 的子进程调用 reinit_after_fork 后 hook 重新计数；父进程中调用被拒绝
+pub unsafe fn scenario_reinit_after_fork() {
+    for mode in [HookMode::Manual, HookMode::Automatic] {
+        clear();
+        ensure_ok(init(mode, true), "init reinit after fork");
+        let handle = load_hook_test();
+        assert_eq!(
+            reinit_after_fork(),
+            SrxHookErrno::Invalid,
+            "reinit_after_fork accepted in parent"
+        );
+        let stub = hook_single(
+            "libhook_test.so",
+            None,
+            "puts",
+            hook_puts_quiet as *mut c_void,
+            None,
+            std::ptr::null_mut(),
+        )
+        .expect("hook_single reinit after fork failed");
+        ensure_ok(refresh(), "refresh reinit after fork");
+
+        let pid = libc::fork();
+        if pid == 0 {
+            let ok = reinit_after_fork() == SrxHookErrno::Ok && !is_forked_child() && {
+                HOOK_A_COUNT.store(0, Ordering::Relaxed);
+                hook_test_trigger(handle);
+                HOOK_A_COUNT.load(Ordering::Relaxed) > 0
+            };
+            libc::_exit(if ok { 0 } else { 1 });
+        }
+        assert!(pid > 0, "fork failed");
+        let mut status = 0;
+        libc::waitpid(pid, &mut status, 0);
+        assert!(
+            libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0,
+            "hooks not firing in child after reinit, mode={mode:?} status={status}"
+        );
+
+        ensure_ok(unhook(stub), "unhook reinit after fork");
+        libc::dlclose(handle);
+    }
+    clear();
+}
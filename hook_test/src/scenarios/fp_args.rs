@@ -0,0 +1,108 @@
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+
+use srx_hook::{HookMode, clear, hook_single, init, prev_func_as, refresh, unhook};
+
+use crate::test_ctx::{ensure_ok, hook_test_trigger_fmod, hook_test_trigger_fmodf, load_hook_test};
+
+type FmodFn = unsafe extern "C" fn(f64, f64) -> f64;
+type FmodfFn = unsafe extern "C" fn(f32, f32) -> f32;
+
+// proxy 收到的参数与 prev 返回值的位模式，校验 trampoline 前后浮点寄存器未被破坏
+static FMOD_HITS: AtomicUsize = AtomicUsize::new(0);
+static FMOD_ARGS: [AtomicU64; 2] = [AtomicU64::new(0), AtomicU64::new(0)];
+static FMOD_RET: AtomicU64 = AtomicU64::new(0);
+static FMODF_ARGS: [AtomicU32; 2] = [AtomicU32::new(0), AtomicU32::new(0)];
+static FMODF_RET: AtomicU32 = AtomicU32::new(0);
+
+unsafe extern "C" fn hook_fmod(x: f64, y: f64) -> f64 {
+    FMOD_HITS.fetch_add(1, Ordering::Relaxed);
+    FMOD_ARGS[0].store(x.to_bits(), Ordering::Relaxed);
+    FMOD_ARGS[1].store(y.to_bits(), Ordering::Relaxed);
+    let prev: Option<FmodFn> = prev_func_as(hook_fmod as *mut c_void);
+    let ret = prev.map_or(f64::NAN, |prev_fn| unsafe { prev_fn(x, y) });
+    FMOD_RET.store(ret.to_bits(), Ordering::Relaxed);
+    ret
+}
+
+unsafe extern "C" fn hook_fmodf(x: f32, y: f32) -> f32 {
+    FMOD_HITS.fetch_add(1, Ordering::Relaxed);
+    FMODF_ARGS[0].store(x.to_bits(), Ordering::Relaxed);
+    FMODF_ARGS[1].store(y.to_bits(), Ordering::Relaxed);
+    let prev: Option<FmodfFn> = prev_func_as(hook_fmodf as *mut c_void);
+    let ret = prev.map_or(f32::NAN, |prev_fn| unsafe { prev_fn(x, y) });
+    FMODF_RET.store(ret.to_bits(), Ordering::Relaxed);
+    ret
+}
+
+// 经 trampoline 调用 libm 的 fmod/fmodf：proxy 看到的参数、返回给调用方的值都须与直接计算按位一致
+pub unsafe fn scenario_fp_args_roundtrip() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init fp args");
+    let handle = load_hook_test();
+
+    let stubs = [
+        ("fmod", hook_fmod as *mut c_void),
+        ("fmodf", hook_fmodf as *mut c_void),
+    ]
+    .map(|(sym, proxy)| {
+        hook_single(
+            "libhook_test.so",
+            None,
+            sym,
+            proxy,
+            None,
+            std::ptr::null_mut(),
+        )
+        .unwrap_or_else(|| panic!("hook_single {sym} failed"))
+    });
+    ensure_ok(refresh(), "refresh fp args");
+
+    FMOD_HITS.store(0, Ordering::Relaxed);
+    // 含次正规数与负数，低位尾数全部参与比较
+    for (x, y) in [
+        (123456.789_f64, 0.1_f64),
+        (-7.0e-310, 3.0e-311),
+        (1.0e300, -std::f64::consts::PI),
+    ] {
+        let ret = hook_test_trigger_fmod(handle, x, y);
+        assert_eq!(FMOD_ARGS[0].load(Ordering::Relaxed), x.to_bits(), "fmod x");
+        assert_eq!(FMOD_ARGS[1].load(Ordering::Relaxed), y.to_bits(), "fmod y");
+        assert_eq!(ret.to_bits(), (x % y).to_bits(), "fmod result {x} % {y}");
+        assert_eq!(
+            FMOD_RET.load(Ordering::Relaxed),
+            ret.to_bits(),
+            "fmod return"
+        );
+    }
+    for (x, y) in [
+        (-1234.5678_f32, 3.3_f32),
+        (1.0e-40, 7.0e-42),
+        (3.0e38, std::f32::consts::E),
+    ] {
+        let ret = hook_test_trigger_fmodf(handle, x, y);
+        assert_eq!(
+            FMODF_ARGS[0].load(Ordering::Relaxed),
+            x.to_bits(),
+            "fmodf x"
+        );
+        assert_eq!(
+            FMODF_ARGS[1].load(Ordering::Relaxed),
+            y.to_bits(),
+            "fmodf y"
+        );
+        assert_eq!(ret.to_bits(), (x % y).to_bits(), "fmodf result {x} % {y}");
+        assert_eq!(
+            FMODF_RET.load(Ordering::Relaxed),
+            ret.to_bits(),
+            "fmodf return"
+        );
+    }
+    assert_eq!(FMOD_HITS.load(Ordering::Relaxed), 6, "fp proxies not hit");
+
+    for stub in stubs {
+        ensure_ok(unhook(stub), "unhook fp args");
+    }
+    libc::dlclose(handle);
+    clear();
+}
@@ -0,0 +1,727 @@
+use std::ffi::{CStr, c_char, c_void};
+use std::os::fd::AsRawFd;
+use std::sync::atomic::Ordering;
+
+use srx_hook::fixtures::run_writable;
+use srx_hook::{
+    ChainOwner, HookMode, HookTaskType, InitOptions, ModuleInspector, RECORD_ITEM_ALL,
+    RECORD_ITEM_NEW_ADDR, RecordOp, SrxHookErrno, TRAMPOLINE_VMA_NAME, clear, dump_state,
+    get_export_address, get_hook_tasks, get_import_slot_values, get_mprotect_count,
+    get_protect_restore_failed_count, get_record_entries, get_records, get_safe_read_stats,
+    get_state_dump, hook_all, hook_partial, hook_single, init, init_with_options, inspect_chain,
+    is_slot_guard_enabled, list_exports, list_exports_with, list_imports, list_imports_with,
+    open_module, refresh, resolve_address, set_recordable, set_slot_guard, unhook,
+};
+
+use crate::test_ctx::{
+    HOOK_A_COUNT, ensure_ok, hook_puts_a_chain, hook_puts_b_chain, hook_puts_c_chain,
+    hook_puts_quiet, hook_test_trigger, load_hook_test, mapping_name, module_base_from_handle,
+};
+
+pub unsafe fn scenario_module_inspector() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init manual inspector");
+    let handle = load_hook_test();
+    let base = module_base_from_handle(handle).expect("hook_test base not found");
+
+    let inspector = open_module("libhook_test.so").expect("open_module failed");
+    assert_eq!(
+        inspector.identity().base_addr,
+        base,
+        "inspector base mismatch"
+    );
+    assert!(
+        inspector.imports().any(|name| name == "puts"),
+        "puts missing from imports"
+    );
+    assert!(
+        inspector.exports().any(|name| name == "hook_test_trigger"),
+        "hook_test_trigger missing from exports"
+    );
+    assert!(
+        !inspector
+            .got_slots_for("puts")
+            .unwrap_or_default()
+            .is_empty(),
+        "no GOT slot for puts"
+    );
+    if let Some((start, end)) = inspector.relro_range() {
+        assert!(start < end, "invalid relro range");
+    }
+    assert!(
+        inspector.is_hooked("puts").is_empty(),
+        "puts hooked before hook_single"
+    );
+    let preview = inspect_chain("libhook_test.so", Some("libc.so"), "puts");
+    assert!(!preview.is_empty(), "no chain preview before hook_single");
+    assert!(
+        preview.iter().all(|slot| slot.entries.is_empty()),
+        "unexpected chain entries before hook_single"
+    );
+
+    let stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single inspector failed");
+    ensure_ok(refresh(), "refresh inspector");
+    assert_eq!(
+        inspector.is_hooked("puts"),
+        vec![stub],
+        "is_hooked did not report stub"
+    );
+    let preview = inspect_chain("libhook_test.so", Some("libc.so"), "puts");
+    assert!(
+        preview.iter().any(|slot| slot
+            .entries
+            .iter()
+            .any(|entry| entry.owner == ChainOwner::OurTask(stub))),
+        "chain preview missing our task"
+    );
+    ensure_ok(unhook(stub), "unhook inspector");
+    assert!(
+        inspector.is_hooked("puts").is_empty(),
+        "puts still hooked after unhook"
+    );
+
+    // 原 handle 关闭后检视句柄仍持有引用，模块不应被卸载
+    libc::dlclose(handle);
+    let reopened = open_module("libhook_test.so").expect("module unloaded while pinned");
+    assert_eq!(reopened.identity().base_addr, base, "pinned module moved");
+    drop(reopened);
+    drop(inspector);
+    clear();
+}
+
+// 导入来自重定位表、导出来自动态符号表；流式版本回调返回 false 后不再投递
+pub unsafe fn scenario_list_module_symbols() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init list symbols");
+    let handle = load_hook_test();
+    let base = module_base_from_handle(handle).expect("hook_test base not found");
+
+    let imports = list_imports("libhook_test.so");
+    let (identity, names) = imports
+        .iter()
+        .find(|(identity, _)| identity.base_addr == base)
+        .expect("hook_test missing from list_imports");
+    assert!(names.iter().any(|name| name == "puts"), "puts not imported");
+    assert!(
+        names.windows(2).all(|pair| pair[0] < pair[1]),
+        "imports not deduplicated"
+    );
+    let exports = list_exports(&identity.caller_rule());
+    assert_eq!(exports.len(), 1, "instance rule matched several modules");
+    assert!(
+        exports[0].1.iter().any(|name| name == "hook_test_trigger"),
+        "hook_test_trigger not exported"
+    );
+
+    let mut streamed = 0;
+    let status = list_imports_with("libhook_test.so", |_, _| {
+        streamed += 1;
+        false
+    });
+    ensure_ok(status, "list_imports_with");
+    assert_eq!(streamed, 1, "callback continued after returning false");
+    assert_eq!(
+        list_exports_with("libnot_loaded_anywhere.so", |_, _| true),
+        SrxHookErrno::NotFound
+    );
+
+    libc::dlclose(handle);
+    clear();
+}
+
+pub unsafe fn scenario_resolve_address() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init resolve address");
+    set_recordable(true);
+    let handle = load_hook_test();
+    let base = module_base_from_handle(handle).expect("hook_test base not found");
+    let trigger = libc::dlsym(handle, c"hook_test_trigger".as_ptr()) as usize;
+    assert_ne!(trigger, 0, "hook_test_trigger not found");
+
+    let resolved = resolve_address(trigger).expect("hook_test_trigger not resolved");
+    assert_eq!(resolved.name, "hook_test_trigger");
+    assert_eq!(resolved.addr, trigger);
+    assert_eq!(resolved.module.base_addr, base);
+    if resolved.size > 1 {
+        let inner = resolve_address(trigger + 1).expect("inner address not resolved");
+        assert_eq!(
+            inner.addr, trigger,
+            "inner address resolved to another symbol"
+        );
+    }
+    assert!(resolve_address(0).is_none());
+
+    // debug 开启时 NEW_ADDR 带符号标注；调用方规则不匹配任何模块，不会真正改写 slot
+    let stub = hook_single(
+        "libnot_loaded_anywhere.so",
+        None,
+        "puts",
+        trigger as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single failed");
+    let records = get_records(RECORD_ITEM_NEW_ADDR).expect("records missing");
+    assert!(
+        records.contains("libhook_test.so!hook_test_trigger+0x0"),
+        "new_addr not annotated: {records}"
+    );
+    ensure_ok(unhook(stub), "unhook resolve address");
+
+    libc::dlclose(handle);
+    clear();
+}
+
+pub unsafe fn scenario_slot_queries() {
+    clear();
+    let handle = load_hook_test();
+    let base = module_base_from_handle(handle).expect("hook_test base not found");
+
+    // 未 init 时同样可查询
+    let before = get_import_slot_values("libhook_test.so", "puts").expect("slot query failed");
+    let (_, slot_addr, orig) = *before
+        .iter()
+        .find(|(identity, _, _)| identity.base_addr == base)
+        .expect("puts slot not found");
+    let libc_puts = libc::dlsym(libc::RTLD_DEFAULT, c"puts".as_ptr()) as usize;
+    let exports = get_export_address("libc.so", "puts").expect("export query failed");
+    assert!(
+        exports.iter().any(|(_, addr)| *addr == libc_puts),
+        "libc puts not among exports"
+    );
+    assert_eq!(
+        get_export_address("", "puts").err(),
+        Some(SrxHookErrno::InvalidArg)
+    );
+
+    ensure_ok(init(HookMode::Manual, true), "init slot queries");
+    let stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single slot queries failed");
+    let slot_value = || {
+        get_import_slot_values("libhook_test.so", "puts")
+            .expect("slot query failed")
+            .into_iter()
+            .find(|(_, addr, _)| *addr == slot_addr)
+            .map(|(_, _, value)| value)
+    };
+    assert!(
+        slot_value().is_some_and(|value| value != orig),
+        "slot not rewritten"
+    );
+    ensure_ok(unhook(stub), "unhook slot queries");
+    assert_eq!(slot_value(), Some(orig), "slot not restored");
+
+    libc::dlclose(handle);
+    clear();
+}
+
+unsafe extern "C" fn allow_hook_test_only(
+    caller_path_name: *const c_char,
+    _arg: *mut c_void,
+) -> bool {
+    unsafe { CStr::from_ptr(caller_path_name) }
+        .to_str()
+        .is_ok_and(|path| path.ends_with("libhook_test.so"))
+}
+
+pub unsafe fn scenario_list_hook_tasks() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init manual task listing");
+    let handle = load_hook_test();
+    let null = std::ptr::null_mut();
+
+    let single = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_a_chain as *mut c_void,
+        None,
+        null,
+    )
+    .expect("hook_single listing failed");
+    let partial = hook_partial(
+        allow_hook_test_only,
+        null,
+        None,
+        "puts",
+        hook_puts_b_chain as *mut c_void,
+        None,
+        null,
+    )
+    .expect("hook_partial listing failed");
+    // callee 不存在，任务不持有任何 slot
+    let all = hook_all(
+        Some("libsrx_listing_absent.so"),
+        "strlen",
+        hook_puts_c_chain as *mut c_void,
+        None,
+        null,
+    )
+    .expect("hook_all listing failed");
+    ensure_ok(refresh(), "refresh task listing");
+
+    let tasks = get_hook_tasks();
+    let stubs: Vec<_> = tasks.iter().map(|task| task.stub).collect();
+    assert_eq!(
+        stubs,
+        [single, partial, all],
+        "task listing order: {tasks:?}"
+    );
+
+    assert_eq!(tasks[0].task_type, HookTaskType::Single);
+    assert_eq!(
+        tasks[0].caller_path_name.as_deref(),
+        Some("libhook_test.so")
+    );
+    assert_eq!(tasks[0].sym_name, "puts");
+    assert_eq!(tasks[0].new_func, hook_puts_a_chain as *mut c_void as usize);
+    assert!(
+        tasks[0].slot_count >= 1,
+        "single task owns no slot: {tasks:?}"
+    );
+
+    assert_eq!(tasks[1].task_type, HookTaskType::Partial);
+    assert_eq!(tasks[1].caller_path_name, None);
+    assert!(
+        tasks[1].slot_count >= 1,
+        "partial task owns no slot: {tasks:?}"
+    );
+
+    assert_eq!(tasks[2].task_type, HookTaskType::All);
+    assert_eq!(
+        tasks[2].callee_path_name.as_deref(),
+        Some("libsrx_listing_absent.so")
+    );
+    assert_eq!(tasks[2].sym_name, "strlen");
+    assert_eq!(
+        tasks[2].slot_count, 0,
+        "absent callee task owns slots: {tasks:?}"
+    );
+
+    ensure_ok(unhook(partial), "unhook partial listing");
+    let stubs: Vec<_> = get_hook_tasks().iter().map(|task| task.stub).collect();
+    assert_eq!(stubs, [single, all], "listing after unhook");
+
+    ensure_ok(unhook(single), "unhook single listing");
+    ensure_ok(unhook(all), "unhook all listing");
+    assert!(
+        get_hook_tasks().is_empty(),
+        "listing not empty after unhook"
+    );
+    libc::dlclose(handle);
+    clear();
+}
+
+// 模拟其他框架直接改写 GOT：巡检开启时 refresh 重新写回并以 SlotTampered 记录 REHOOK
+pub unsafe fn scenario_slot_guard() {
+    clear();
+    ensure_ok(
+        init_with_options(
+            InitOptions::new()
+                .mode(HookMode::Manual)
+                .debug(true)
+                .slot_guard(true),
+        ),
+        "init slot guard",
+    );
+    assert!(is_slot_guard_enabled(), "slot guard option not applied");
+    set_recordable(true);
+    let handle = load_hook_test();
+    let _stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single slot guard failed");
+    ensure_ok(refresh(), "refresh slot guard");
+
+    let inspector = open_module("libhook_test.so").expect("open_module failed");
+    let slots = inspector.got_slots_for("puts").expect("puts slots");
+    assert!(!slots.is_empty(), "no GOT slot for puts");
+    let libc_puts = libc::dlsym(libc::RTLD_DEFAULT, c"puts".as_ptr()) as usize;
+    for slot in &slots {
+        overwrite_got_slot(*slot, libc_puts);
+    }
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert_eq!(
+        HOOK_A_COUNT.load(Ordering::Relaxed),
+        0,
+        "overwritten slot still reached proxy"
+    );
+
+    ensure_ok(refresh(), "refresh repairs slot");
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert!(
+        HOOK_A_COUNT.load(Ordering::Relaxed) >= 1,
+        "slot guard did not re-apply hook"
+    );
+    assert!(
+        get_record_entries(RECORD_ITEM_ALL).iter().any(|entry| {
+            entry.op == Some(RecordOp::Rehook)
+                && entry.errno == Some(SrxHookErrno::SlotTampered.as_i32())
+        }),
+        "no tampered REHOOK record"
+    );
+
+    // 关闭巡检后 refresh 不再修复
+    set_slot_guard(false);
+    for slot in &slots {
+        overwrite_got_slot(*slot, libc_puts);
+    }
+    ensure_ok(refresh(), "refresh without slot guard");
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert_eq!(
+        HOOK_A_COUNT.load(Ordering::Relaxed),
+        0,
+        "slot repaired with guard disabled"
+    );
+    drop(inspector);
+    libc::dlclose(handle);
+    set_recordable(false);
+    clear();
+}
+
+unsafe fn overwrite_got_slot(slot: usize, value: usize) {
+    let page_size = libc::sysconf(libc::_SC_PAGESIZE) as usize;
+    let page = slot & !(page_size - 1);
+    assert_eq!(
+        libc::mprotect(
+            page as *mut c_void,
+            page_size,
+            libc::PROT_READ | libc::PROT_WRITE
+        ),
+        0,
+        "mprotect GOT page writable failed"
+    );
+    std::ptr::write_volatile(slot as *mut usize, value);
+    let _ = libc::mprotect(page as *mut c_void, page_size, libc::PROT_READ);
+}
+
+pub unsafe fn scenario_state_dump() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init state dump");
+    let handle = load_hook_test();
+    let stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single state dump failed");
+    ensure_ok(refresh(), "refresh state dump");
+
+    let proxy_line = format!(
+        "  proxy 0x{:x} enabled=true stubs=[{stub}]",
+        hook_puts_quiet as *const () as usize
+    );
+    let text = get_state_dump();
+    assert!(
+        text.lines()
+            .any(|line| line.starts_with("slot ") && line.contains("/libhook_test.so ")),
+        "hooked slot missing from dump:\n{text}"
+    );
+    assert!(
+        text.lines().any(|line| line == proxy_line),
+        "proxy line missing from dump:\n{text}"
+    );
+    let task_prefix = format!("task {stub} type=Single caller=libhook_test.so ");
+    assert!(
+        text.lines().any(|line| line.starts_with(&task_prefix)),
+        "task line missing from dump:\n{text}"
+    );
+
+    let path = format!("/data/local/tmp/srx_state_dump_{}.txt", std::process::id());
+    let file = std::fs::File::create(&path).expect("create state dump file");
+    ensure_ok(dump_state(file.as_raw_fd()), "dump_state");
+    drop(file);
+    let dumped = std::fs::read_to_string(&path).expect("read state dump file");
+    let _ = std::fs::remove_file(&path);
+    assert!(
+        dumped.lines().any(|line| line == proxy_line),
+        "proxy line missing from fd dump"
+    );
+    assert_eq!(dump_state(-1), SrxHookErrno::InvalidArg);
+
+    ensure_ok(unhook(stub), "unhook state dump");
+    let text = get_state_dump();
+    assert!(
+        !text.lines().any(|line| line.contains("/libhook_test.so ")),
+        "slot still dumped after unhook:\n{text}"
+    );
+    assert!(text.contains("# tasks: 0\n"), "task still dumped:\n{text}");
+    libc::dlclose(handle);
+    clear();
+}
+
+// /proc/self/maps 中包含 addr 的映射的权限字段，如 "r--p"
+fn mapping_perms(addr: usize) -> Option<String> {
+    let maps = std::fs::read_to_string("/proc/self/maps").ok()?;
+    maps.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let (start, end) = fields.next()?.split_once('-')?;
+        let start = usize::from_str_radix(start, 16).ok()?;
+        let end = usize::from_str_radix(end, 16).ok()?;
+        if addr < start || addr >= end {
+            return None;
+        }
+        fields.next().map(str::to_owned)
+    })
+}
+
+// 用一页临时匿名映射探测内核是否支持 PR_SET_VMA_ANON_NAME
+unsafe fn vma_naming_supported() -> bool {
+    const PR_SET_VMA: libc::c_int = 0x53564d41;
+    let len = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+    let page = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    if page == libc::MAP_FAILED {
+        return false;
+    }
+    let name = c"srx_hook_test:probe";
+    let result = unsafe {
+        libc::prctl(
+            PR_SET_VMA,
+            0 as libc::c_ulong,
+            page as libc::c_ulong,
+            len as libc::c_ulong,
+            name.as_ptr() as libc::c_ulong,
+        )
+    };
+    unsafe {
+        libc::munmap(page, len);
+    }
+    result == 0
+}
+
+fn assert_relro_slots_read_only(inspector: &ModuleInspector, slots: &[usize], stage: &str) {
+    for &slot in slots {
+        if !inspector.is_addr_in_relro(slot) {
+            continue;
+        }
+        let perms = mapping_perms(slot).expect("GOT page missing from maps");
+        assert!(
+            !perms.contains('w'),
+            "RELRO GOT page 0x{slot:x} writable {stage}: {perms}"
+        );
+    }
+}
+
+pub unsafe fn scenario_relro_protection_restored() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init relro");
+    let handle = load_hook_test();
+    let inspector = open_module("libhook_test.so").expect("open_module failed");
+    let slots = inspector.got_slots_for("puts").unwrap_or_default();
+    assert!(
+        slots.iter().any(|&slot| inspector.is_addr_in_relro(slot)),
+        "libhook_test.so puts slot not in RELRO"
+    );
+    assert_relro_slots_read_only(&inspector, &slots, "before hook");
+
+    let stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single relro failed");
+    ensure_ok(refresh(), "refresh relro");
+    assert_eq!(inspector.is_hooked("puts"), vec![stub]);
+    assert_relro_slots_read_only(&inspector, &slots, "after hook");
+    hook_test_trigger(handle);
+
+    ensure_ok(unhook(stub), "unhook relro");
+    assert_relro_slots_read_only(&inspector, &slots, "after unhook");
+    libc::dlclose(handle);
+    clear();
+}
+
+// 临时可写后按原值恢复：已可写的页不发出 mprotect，只读与可执行页恢复为原权限
+pub unsafe fn scenario_with_writable_restores_protection() {
+    let page_size = libc::sysconf(libc::_SC_PAGESIZE) as usize;
+    let failures_before = get_protect_restore_failed_count();
+    for (prot, expected) in [
+        (libc::PROT_READ | libc::PROT_WRITE, "rw-p"),
+        (libc::PROT_READ, "r--p"),
+        (libc::PROT_READ | libc::PROT_EXEC, "r-xp"),
+    ] {
+        let page = libc::mmap(
+            std::ptr::null_mut(),
+            page_size,
+            prot,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        );
+        assert_ne!(page, libc::MAP_FAILED, "mmap {expected} page failed");
+        let addr = page as usize;
+        let before = get_mprotect_count();
+        let written = run_writable(addr, size_of::<usize>(), || {
+            std::ptr::write_volatile(addr as *mut usize, 0x5a5a);
+            std::ptr::read_volatile(addr as *const usize)
+        })
+        .expect("run_writable failed");
+        let calls = get_mprotect_count() - before;
+        assert_eq!(written, 0x5a5a, "write through {expected} page lost");
+        assert_eq!(
+            calls,
+            if prot & libc::PROT_WRITE != 0 { 0 } else { 2 },
+            "unexpected mprotect calls for {expected} page"
+        );
+        assert_eq!(
+            mapping_perms(addr).as_deref(),
+            Some(expected),
+            "protection not restored"
+        );
+        libc::munmap(page, page_size);
+    }
+    assert_eq!(
+        get_protect_restore_failed_count(),
+        failures_before,
+        "restore failure counted"
+    );
+}
+
+// slot 回读优先走 process_vm_readv，只有系统调用不可用时才计入信号保护读取
+pub unsafe fn scenario_safe_read_stats() {
+    clear();
+    let handle = load_hook_test();
+    let before = get_safe_read_stats();
+    let slots = get_import_slot_values("libhook_test.so", "puts").expect("slot query failed");
+    assert!(!slots.is_empty(), "puts slot not found");
+    let after = get_safe_read_stats();
+    let reads =
+        (after.syscall_reads - before.syscall_reads) + (after.guard_reads - before.guard_reads);
+    assert!(
+        reads >= slots.len() as u64,
+        "slot reads not counted: before={before:?} after={after:?}"
+    );
+    assert_eq!(
+        after.syscall_faults, before.syscall_faults,
+        "mapped slot reported unreadable"
+    );
+    println!("safe read stats: {after:?}");
+    libc::dlclose(handle);
+}
+
+// 默认 W^X：已发布的 trampoline 页为 RX；rwx_trampolines 兼容模式下保持 RWX
+pub unsafe fn scenario_trampoline_wx() {
+    for rwx in [false, true] {
+        clear();
+        ensure_ok(
+            init_with_options(
+                InitOptions::new()
+                    .mode(HookMode::Manual)
+                    .rwx_trampolines(rwx),
+            ),
+            "init trampoline w^x",
+        );
+        let handle = load_hook_test();
+        let base = module_base_from_handle(handle).expect("hook_test base not found");
+        let stub = hook_single(
+            "libhook_test.so",
+            None,
+            "puts",
+            hook_puts_quiet as *mut c_void,
+            None,
+            std::ptr::null_mut(),
+        )
+        .expect("hook_single trampoline w^x failed");
+        ensure_ok(refresh(), "refresh trampoline w^x");
+
+        let slots = get_import_slot_values("libhook_test.so", "puts").expect("slot query failed");
+        let (_, _, trampo) = *slots
+            .iter()
+            .find(|(identity, _, _)| identity.base_addr == base)
+            .expect("puts slot not found");
+        let perms = mapping_perms(trampo).expect("trampoline page missing from maps");
+        assert!(perms.contains('x'), "trampoline not executable: {perms}");
+        assert_eq!(
+            perms.contains('w'),
+            rwx,
+            "trampoline page writability mismatch (rwx={rwx}): {perms}"
+        );
+        hook_test_trigger(handle);
+
+        ensure_ok(unhook(stub), "unhook trampoline w^x");
+        libc::dlclose(handle);
+    }
+    clear();
+}
+
+// hook 生效期间 trampoline 页在 maps 中带有 TRAMPOLINE_VMA_NAME 标签
+pub unsafe fn scenario_trampoline_vma_name() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init trampoline vma name");
+    let handle = load_hook_test();
+    let base = module_base_from_handle(handle).expect("hook_test base not found");
+    let stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single trampoline vma name failed");
+    ensure_ok(refresh(), "refresh trampoline vma name");
+
+    let slots = get_import_slot_values("libhook_test.so", "puts").expect("slot query failed");
+    let (_, _, trampo) = *slots
+        .iter()
+        .find(|(identity, _, _)| identity.base_addr == base)
+        .expect("puts slot not found");
+    // 共享页由同名 memfd 映射，退回匿名页时依赖 PR_SET_VMA_ANON_NAME
+    let name = mapping_name(trampo).expect("trampoline page missing from maps");
+    if name.starts_with("/memfd:") {
+        assert!(
+            name.contains(TRAMPOLINE_VMA_NAME),
+            "trampoline memfd not labelled: {name}"
+        );
+    } else if unsafe { vma_naming_supported() } {
+        assert_eq!(
+            name,
+            format!("[anon:{TRAMPOLINE_VMA_NAME}]"),
+            "trampoline page not labelled"
+        );
+    } else {
+        println!("skip trampoline-vma-name label check: kernel lacks PR_SET_VMA_ANON_NAME");
+    }
+
+    ensure_ok(unhook(stub), "unhook trampoline vma name");
+    libc::dlclose(handle);
+    clear();
+}
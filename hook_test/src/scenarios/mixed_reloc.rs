@@ -0,0 +1,96 @@
+use std::ffi::c_void;
+use std::sync::atomic::Ordering;
+
+use srx_hook::{HookMode, clear, hook_single, init, refresh, unhook};
+
+use crate::test_ctx::{
+    HOOK_A_COUNT, HOOK_B_COUNT, ensure_ok, hook_puts_a_chain, hook_puts_b_chain, hook_test_trigger,
+    hook_test_trigger_fnptr, load_hook_test,
+};
+
+fn reset_counts() {
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    HOOK_B_COUNT.store(0, Ordering::Relaxed);
+}
+
+fn counts() -> (usize, usize) {
+    (
+        HOOK_A_COUNT.load(Ordering::Relaxed),
+        HOOK_B_COUNT.load(Ordering::Relaxed),
+    )
+}
+
+// 直接调用与函数表调用各触发一次，返回两条路径上 A/B 的命中数
+unsafe fn trigger_both(handle: *mut c_void) -> [(usize, usize); 2] {
+    reset_counts();
+    hook_test_trigger(handle);
+    let call = counts();
+    reset_counts();
+    hook_test_trigger_fnptr(handle);
+    [call, counts()]
+}
+
+// libhook_test 同时直接调用 puts 并把 &puts 存入函数表
+// 两条路径必须一起被 hook、一起形成调用链、一起恢复
+unsafe fn run_mixed(callee: Option<&str>) {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init mixed reloc");
+    let handle = load_hook_test();
+
+    let stub_a = hook_single(
+        "libhook_test.so",
+        callee,
+        "puts",
+        hook_puts_a_chain as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single mixed A failed");
+    let stub_b = hook_single(
+        "libhook_test.so",
+        callee,
+        "puts",
+        hook_puts_b_chain as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single mixed B failed");
+    ensure_ok(refresh(), "refresh mixed reloc");
+
+    let [call, table] = trigger_both(handle);
+    assert!(
+        call.0 >= 1 && call.1 >= 1,
+        "call path not chained: {call:?}"
+    );
+    assert!(
+        table.0 >= 1 && table.1 >= 1,
+        "function pointer path not chained: {table:?}"
+    );
+
+    ensure_ok(unhook(stub_b), "unhook mixed B");
+    let [call, table] = trigger_both(handle);
+    assert!(
+        call.0 >= 1 && call.1 == 0,
+        "call path after unhook B: {call:?}"
+    );
+    assert!(
+        table.0 >= 1 && table.1 == 0,
+        "function pointer path after unhook B: {table:?}"
+    );
+
+    ensure_ok(unhook(stub_a), "unhook mixed A");
+    let [call, table] = trigger_both(handle);
+    assert_eq!(call, (0, 0), "call path not restored");
+    assert_eq!(table, (0, 0), "function pointer path not restored");
+
+    libc::dlclose(handle);
+    clear();
+}
+
+pub unsafe fn scenario_mixed_reloc_classes() {
+    run_mixed(None);
+}
+
+pub unsafe fn scenario_mixed_reloc_classes_callee_filter() {
+    run_mixed(Some("libc.so"));
+}
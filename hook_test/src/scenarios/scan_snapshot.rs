@@ -0,0 +1,39 @@
+use std::os::fd::AsRawFd;
+
+use srx_hook::{HookMode, clear, dump_scan_snapshots, enable_scan_snapshot_capture, init, refresh};
+
+use crate::test_ctx::{ensure_ok, load_hook_test};
+
+pub unsafe fn scenario_scan_snapshot_capture() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init scan snapshot");
+    let handle = load_hook_test();
+
+    enable_scan_snapshot_capture(true);
+    ensure_ok(refresh(), "refresh with capture");
+    enable_scan_snapshot_capture(false);
+    // 关闭后不再追加新的轮次
+    ensure_ok(refresh(), "refresh without capture");
+
+    let path = format!(
+        "/data/local/tmp/srx_scan_snapshot_{}.txt",
+        std::process::id()
+    );
+    let file = std::fs::File::create(&path).expect("create snapshot file");
+    ensure_ok(dump_scan_snapshots(file.as_raw_fd()), "dump scan snapshots");
+    drop(file);
+    let text = std::fs::read_to_string(&path).expect("read snapshot file");
+    let _ = std::fs::remove_file(&path);
+
+    assert_eq!(text.matches("srx-scan-snapshot 1\n").count(), 1);
+    assert!(text.lines().any(|line| line.starts_with("dlinfo ")));
+    assert!(
+        text.lines()
+            .any(|line| line.starts_with("module ") && line.ends_with("/libhook_test.so")),
+        "libhook_test.so missing from snapshot"
+    );
+    assert!(text.ends_with("end\n"));
+
+    libc::dlclose(handle);
+    clear();
+}
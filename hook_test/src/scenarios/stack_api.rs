@@ -1,10 +1,17 @@
-use std::ffi::c_void;
-use std::sync::atomic::Ordering;
+use std::ffi::{c_char, c_void};
+use std::panic::{AssertUnwindSafe, catch_unwind, resume_unwind};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
-use srx_hook::{HookMode, clear, hook_single, init, pop_stack, refresh, unhook};
+use srx_hook::{
+    HUB_STACK_FIXED_DEPTH, HookMode, InitOptions, ProxyScope, clear, get_caller_module,
+    get_hub_backtrace, get_hub_stack_stats, get_return_address, hook_all, hook_single, init,
+    init_with_options, pop_stack, prev_func_as, proxy_leave, refresh, unhook,
+};
 
 use crate::test_ctx::{
-    STACK_API_COUNT, ensure_ok, hook_puts_return_address_stack, load_hook_test, hook_test_trigger,
+    PutsFn, STACK_API_COUNT, ensure_ok, hook_puts_return_address_stack, hook_test_fixtures,
+    hook_test_trigger, hook_test_trigger_fmod, load_hook_test,
 };
 
 pub unsafe fn scenario_return_address_stack_api() {
@@ -35,3 +42,302 @@ pub unsafe fn scenario_return_address_stack_api() {
     libc::dlclose(handle);
     clear();
 }
+
+static SCOPE_PANIC_CALLS: AtomicUsize = AtomicUsize::new(0);
+static SCOPE_PANIC_BALANCED: AtomicBool = AtomicBool::new(true);
+
+// proxy 在持有 ProxyScope 时 panic，展开后 hub 帧与 proxy 帧都应已释放
+unsafe extern "C" fn hook_puts_scope_panic(_s: *const c_char) -> i32 {
+    let self_ptr = hook_puts_scope_panic as *mut c_void;
+    let unwound = catch_unwind(AssertUnwindSafe(|| {
+        let scope = ProxyScope::new(self_ptr);
+        if !scope.entered() {
+            SCOPE_PANIC_BALANCED.store(false, Ordering::Relaxed);
+        }
+        resume_unwind(Box::new("proxy scope panic"));
+    }))
+    .is_err();
+
+    let rescope = ProxyScope::new(self_ptr);
+    let balanced = unwound && rescope.entered() && get_return_address().is_null();
+    if !balanced {
+        SCOPE_PANIC_BALANCED.store(false, Ordering::Relaxed);
+    }
+    SCOPE_PANIC_CALLS.fetch_add(1, Ordering::Relaxed);
+    0
+}
+
+pub unsafe fn scenario_proxy_scope_panic() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init proxy scope");
+    let handle = load_hook_test();
+
+    let stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_scope_panic as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single proxy scope failed");
+    ensure_ok(refresh(), "refresh proxy scope");
+
+    SCOPE_PANIC_CALLS.store(0, Ordering::Relaxed);
+    SCOPE_PANIC_BALANCED.store(true, Ordering::Relaxed);
+    for _ in 0..4 {
+        hook_test_trigger(handle);
+    }
+    assert!(
+        SCOPE_PANIC_CALLS.load(Ordering::Relaxed) >= 4,
+        "proxy scope hook lost calls"
+    );
+    assert!(
+        SCOPE_PANIC_BALANCED.load(Ordering::Relaxed),
+        "proxy stack unbalanced after panic"
+    );
+    assert!(
+        get_return_address().is_null(),
+        "hub frame leaked after proxy scope"
+    );
+
+    ensure_ok(unhook(stub), "unhook proxy scope");
+    libc::dlclose(handle);
+    clear();
+}
+
+static CALLER_MODULE_HITS: AtomicUsize = AtomicUsize::new(0);
+static CALLER_MODULE_MISMATCH: AtomicUsize = AtomicUsize::new(0);
+
+// 全局 puts proxy：只统计由 libhook_test.so 发起的调用，第二次起应命中线程缓存
+unsafe extern "C" fn hook_puts_caller_module(_s: *const c_char) -> i32 {
+    let self_ptr = hook_puts_caller_module as *mut c_void;
+    match get_caller_module() {
+        Some(module) if module.pathname.ends_with("libhook_test.so") => {
+            if module.base_addr == 0 || module.instance_id == 0 {
+                CALLER_MODULE_MISMATCH.fetch_add(1, Ordering::Relaxed);
+            }
+            CALLER_MODULE_HITS.fetch_add(1, Ordering::Relaxed);
+        }
+        _ => {}
+    }
+    proxy_leave(self_ptr);
+    0
+}
+
+pub unsafe fn scenario_caller_module() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init caller module");
+    let handle = load_hook_test();
+    assert!(
+        get_caller_module().is_none(),
+        "caller module should be none outside proxy"
+    );
+
+    let stub = hook_all(
+        None,
+        "puts",
+        hook_puts_caller_module as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_all caller module failed");
+    ensure_ok(refresh(), "refresh caller module");
+
+    CALLER_MODULE_HITS.store(0, Ordering::Relaxed);
+    CALLER_MODULE_MISMATCH.store(0, Ordering::Relaxed);
+    for _ in 0..4 {
+        hook_test_trigger(handle);
+    }
+    ensure_ok(unhook(stub), "unhook caller module");
+
+    assert!(
+        CALLER_MODULE_HITS.load(Ordering::Relaxed) >= 4,
+        "caller module did not resolve to libhook_test.so"
+    );
+    assert_eq!(
+        CALLER_MODULE_MISMATCH.load(Ordering::Relaxed),
+        0,
+        "caller module identity incomplete inside proxy"
+    );
+
+    libc::dlclose(handle);
+    clear();
+}
+
+// 嵌套链：第 N 层 proxy 触发第 N+1 个副本的 puts，每层经过不同模块的 hub
+static NEST_HANDLES: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+static NEST_LEVEL: AtomicUsize = AtomicUsize::new(0);
+static NEST_HITS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe extern "C" fn hook_puts_nest(s: *const c_char) -> i32 {
+    NEST_HITS.fetch_add(1, Ordering::Relaxed);
+    let level = NEST_LEVEL.fetch_add(1, Ordering::Relaxed) + 1;
+    let next = NEST_HANDLES.lock().unwrap().get(level).copied();
+    if let Some(handle) = next {
+        hook_test_trigger(handle as *mut c_void);
+    }
+    // with_prev_func 依赖的 proxy 栈同样只有固定深度，这里直接取 prev
+    let prev: Option<PutsFn> = prev_func_as(hook_puts_nest as *mut c_void);
+    prev.map_or(0, |prev_fn| unsafe { prev_fn(s) })
+}
+
+unsafe fn run_nested_chain(opts: InitOptions, stage: &str) -> usize {
+    clear();
+    ensure_ok(init_with_options(opts), stage);
+    let stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_nest as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single nested chain failed");
+    ensure_ok(refresh(), stage);
+
+    NEST_LEVEL.store(0, Ordering::Relaxed);
+    NEST_HITS.store(0, Ordering::Relaxed);
+    let first = NEST_HANDLES.lock().unwrap()[0];
+    hook_test_trigger(first as *mut c_void);
+    ensure_ok(unhook(stub), stage);
+    NEST_HITS.load(Ordering::Relaxed)
+}
+
+// 嵌套深度超过 Hub 固定栈时转入堆区，每一层 proxy 都应被调用
+pub unsafe fn scenario_deep_hub_nesting() {
+    let depth = HUB_STACK_FIXED_DEPTH + 8;
+    let fixtures = hook_test_fixtures("deep_nest", depth);
+    *NEST_HANDLES.lock().unwrap() = fixtures
+        .iter()
+        .map(|fixture| fixture.handle() as usize)
+        .collect();
+
+    // 关闭堆区时固定栈之后的层级绕过 proxy
+    let before = get_hub_stack_stats();
+    let hits = run_nested_chain(
+        InitOptions::new()
+            .mode(HookMode::Manual)
+            .hub_stack_spill(HUB_STACK_FIXED_DEPTH, HUB_STACK_FIXED_DEPTH),
+        "init nested without spill",
+    );
+    assert_eq!(hits, HUB_STACK_FIXED_DEPTH, "fixed stack depth mismatch");
+    assert!(
+        get_hub_stack_stats().overflows > before.overflows,
+        "overflow not counted"
+    );
+
+    let before = get_hub_stack_stats();
+    let hits = run_nested_chain(
+        InitOptions::new().mode(HookMode::Manual),
+        "init nested with spill",
+    );
+    assert_eq!(hits, depth, "nested proxies lost past fixed stack");
+    let stats = get_hub_stack_stats();
+    assert!(
+        stats.spilled_frames >= before.spilled_frames + 8,
+        "spilled frames not counted: {stats:?}"
+    );
+    assert_eq!(stats.overflows, before.overflows, "unexpected overflow");
+    assert!(stats.max_depth >= depth, "max depth not tracked: {stats:?}");
+
+    // 再次嵌套复用已分配的堆区
+    let allocs = stats.spill_allocs;
+    NEST_LEVEL.store(0, Ordering::Relaxed);
+    NEST_HITS.store(0, Ordering::Relaxed);
+    let stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_nest as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single nested chain failed");
+    ensure_ok(refresh(), "refresh nested reuse");
+    hook_test_trigger(fixtures[0].handle());
+    assert_eq!(NEST_HITS.load(Ordering::Relaxed), depth);
+    assert_eq!(
+        get_hub_stack_stats().spill_allocs,
+        allocs,
+        "spill area reallocated"
+    );
+    ensure_ok(unhook(stub), "unhook nested reuse");
+
+    NEST_HANDLES.lock().unwrap().clear();
+    drop(fixtures);
+    clear();
+}
+
+type FmodFn = unsafe extern "C" fn(f64, f64) -> f64;
+
+static BACKTRACE_HANDLE: AtomicUsize = AtomicUsize::new(0);
+static BACKTRACE_DEPTH: AtomicUsize = AtomicUsize::new(0);
+static BACKTRACE_ADDRS: [AtomicUsize; 4] = [const { AtomicUsize::new(0) }; 4];
+
+// 在 puts 的 proxy 内采集当前线程的 hub 调用链
+unsafe extern "C" fn hook_puts_backtrace(s: *const c_char) -> i32 {
+    let mut buf = [0usize; 4];
+    let depth = get_hub_backtrace(&mut buf);
+    BACKTRACE_DEPTH.store(depth, Ordering::Relaxed);
+    for (slot, addr) in BACKTRACE_ADDRS.iter().zip(buf) {
+        slot.store(addr, Ordering::Relaxed);
+    }
+    let prev: Option<PutsFn> = prev_func_as(hook_puts_backtrace as *mut c_void);
+    prev.map_or(0, |prev_fn| unsafe { prev_fn(s) })
+}
+
+// fmod 的 proxy 内再经 libhook_test 的 GOT 调用 puts，形成两层 hub 帧
+unsafe extern "C" fn hook_fmod_nested(x: f64, y: f64) -> f64 {
+    unsafe { hook_test_trigger(BACKTRACE_HANDLE.load(Ordering::Relaxed) as *mut c_void) };
+    let prev: Option<FmodFn> = prev_func_as(hook_fmod_nested as *mut c_void);
+    prev.map_or(f64::NAN, |prev_fn| unsafe { prev_fn(x, y) })
+}
+
+pub unsafe fn scenario_hub_backtrace() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init hub backtrace");
+    let handle = load_hook_test();
+    BACKTRACE_HANDLE.store(handle as usize, Ordering::Relaxed);
+
+    let stubs = [
+        ("puts", hook_puts_backtrace as *mut c_void),
+        ("fmod", hook_fmod_nested as *mut c_void),
+    ]
+    .map(|(sym, proxy)| {
+        hook_single(
+            "libhook_test.so",
+            None,
+            sym,
+            proxy,
+            None,
+            std::ptr::null_mut(),
+        )
+        .unwrap_or_else(|| panic!("hook_single {sym} failed"))
+    });
+    ensure_ok(refresh(), "refresh hub backtrace");
+
+    hook_test_trigger(handle);
+    assert_eq!(BACKTRACE_DEPTH.load(Ordering::Relaxed), 1, "direct depth");
+    let direct = BACKTRACE_ADDRS[0].load(Ordering::Relaxed);
+    assert_ne!(direct, 0, "direct return address missing");
+
+    let ret = hook_test_trigger_fmod(handle, 7.5, 2.0);
+    assert_eq!(ret, 1.5, "nested fmod result");
+    assert_eq!(BACKTRACE_DEPTH.load(Ordering::Relaxed), 2, "nested depth");
+    let inner = BACKTRACE_ADDRS[0].load(Ordering::Relaxed);
+    let outer = BACKTRACE_ADDRS[1].load(Ordering::Relaxed);
+    // 最内层是 hook_test_trigger 中 puts 的返回地址，与直接调用时一致
+    assert_eq!(inner, direct, "inner frame mismatch");
+    assert!(outer != 0 && outer != inner, "outer frame missing");
+
+    // 调用链返回后不残留帧
+    let mut buf = [0usize; 4];
+    assert_eq!(get_hub_backtrace(&mut buf), 0, "stale hub frames");
+
+    for stub in stubs {
+        ensure_ok(unhook(stub), "unhook hub backtrace");
+    }
+    libc::dlclose(handle);
+    clear();
+}
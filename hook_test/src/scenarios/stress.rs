@@ -1,19 +1,31 @@
-use std::ffi::c_void;
-use std::sync::atomic::Ordering;
+use std::collections::BTreeSet;
+use std::ffi::{CString, c_void};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Barrier};
 use std::time::{Duration, Instant};
 
-use srx_hook::{HookMode, clear, hook_single, init, refresh, unhook};
+use srx_hook::fixtures::run_guarded;
+use srx_hook::{
+    HookMode, RECORD_ITEM_ALL, RecordOp, SignalHandlerMode, SrxHookErrno, clear, clear_and_drain,
+    drain_retired_hubs, enable_extended_signal_guard, enable_sigchain_front_reassert,
+    get_capabilities, get_guard_fault_stats, get_guard_slot_stats, get_hook_stats,
+    get_import_slot_values, get_module_identity, get_mprotect_count, get_record_entries,
+    get_sigchain_reassert_count, get_thread_state_stats, hook_all, hook_single, init, open_module,
+    refresh, refresh_handle, reset_thread_state_stats, set_hook_stats_enabled, set_recordable,
+    unhook,
+};
 
 use crate::test_ctx::{
-    HOOK_A_COUNT, current_rss_kb, ensure_ok, env_usize, hook_puts_quiet, hook_test_trigger,
-    load_hook_test,
+    HOOK_A_COUNT, current_rss_kb, ensure_ok, env_usize, hook_puts_quiet, hook_test_fixtures,
+    hook_test_trigger, load_hook_test, loaded_module_count, mapping_name,
 };
 
 pub unsafe fn scenario_concurrent_hooking_stress() {
     clear();
     ensure_ok(init(HookMode::Manual, true), "init concurrent stress");
     let handle = load_hook_test();
+    reset_thread_state_stats();
+    let live_before = get_thread_state_stats().live_states;
 
     let worker_count = env_usize("HOOK_TEST_CONCURRENT_WORKERS", 72);
     let worker_calls = env_usize("HOOK_TEST_CONCURRENT_CALLS", 80);
@@ -52,7 +64,97 @@ pub unsafe fn scenario_concurrent_hooking_stress() {
     for worker in workers {
         worker.join().expect("concurrent worker panic");
     }
+    // 工作线程已全部退出，退役的 hub 无需等待销毁延迟
+    assert!(
+        drain_retired_hubs(Duration::from_secs(2)),
+        "retired hubs not drained after concurrent stress"
+    );
+    // 单层 hook 不应触及任何栈上限，退出的工作线程已释放各自的线程状态
+    let stats = get_thread_state_stats();
+    assert_eq!(
+        (
+            stats.key_init_failures,
+            stats.bind_failures,
+            stats.hub_stack_overflows,
+            stats.proxy_stack_overflows,
+        ),
+        (0, 0, 0, 0),
+        "thread state failures under concurrent stress: {stats:?}"
+    );
+    assert!(
+        stats.live_states <= live_before,
+        "worker thread states leaked: {stats:?} before={live_before}"
+    );
+
+    libc::dlclose(handle);
+    clear();
+}
+
+// 刷新线程持续全量刷新时注册 hook：注册只登记任务，不应等待进行中的刷新
+pub unsafe fn scenario_hook_single_during_refresh() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init hook during refresh");
+    let handle = load_hook_test();
+
+    let load_tasks = env_usize("HOOK_TEST_REFRESH_LOAD_TASKS", 64);
+    let rounds = env_usize("HOOK_TEST_REGISTER_ROUNDS", 200);
+    let max_latency = Duration::from_millis(env_usize("HOOK_TEST_REGISTER_MAX_MS", 5) as u64);
+
+    // hook_all 扫描全部已加载模块使每轮刷新耗时足够长；callee 不存在，不会真正改写 slot
+    // 完全相同的注册会以 RepeatedFunc 拒绝，各任务使用不同的 callee 规则
+    let mut load_stubs = Vec::with_capacity(load_tasks);
+    for index in 0..load_tasks {
+        let callee = format!("libsrx_refresh_load_absent_{index}.so");
+        let stub = hook_all(
+            Some(&callee),
+            "strlen",
+            hook_puts_quiet as *mut c_void,
+            None,
+            std::ptr::null_mut(),
+        )
+        .expect("hook_all refresh load failed");
+        load_stubs.push(stub);
+    }
+
+    let running = Arc::new(AtomicBool::new(true));
+    let refresher = {
+        let running = Arc::clone(&running);
+        std::thread::spawn(move || {
+            while running.load(Ordering::Relaxed) {
+                let _ = refresh();
+            }
+        })
+    };
+    std::thread::sleep(Duration::from_millis(20));
+
+    let mut worst = Duration::ZERO;
+    let mut stubs = Vec::with_capacity(rounds);
+    for index in 0..rounds {
+        let callee = format!("libsrx_register_absent_{index}.so");
+        let begin = Instant::now();
+        let stub = hook_single(
+            "libhook_test.so",
+            Some(&callee),
+            "puts",
+            hook_puts_quiet as *mut c_void,
+            None,
+            std::ptr::null_mut(),
+        )
+        .expect("hook_single during refresh failed");
+        worst = worst.max(begin.elapsed());
+        stubs.push(stub);
+    }
+    running.store(false, Ordering::Relaxed);
+    refresher.join().expect("refresher panic");
+    assert!(
+        worst <= max_latency,
+        "hook_single blocked behind refresh: worst={worst:?}"
+    );
 
+    ensure_ok(refresh(), "refresh after registration");
+    for stub in stubs.into_iter().chain(load_stubs) {
+        ensure_ok(unhook(stub), "unhook during refresh");
+    }
     libc::dlclose(handle);
     clear();
 }
@@ -122,6 +224,8 @@ pub unsafe fn scenario_perf_smoke() {
     )
     .expect("hook_single perf failed");
     ensure_ok(refresh(), "refresh perf");
+    assert!(get_hook_stats(stub).is_none(), "hook stats on by default");
+    ensure_ok(set_hook_stats_enabled(stub, true), "enable hook stats");
 
     HOOK_A_COUNT.store(0, Ordering::Relaxed);
     let start = Instant::now();
@@ -133,11 +237,76 @@ pub unsafe fn scenario_perf_smoke() {
     assert!(count >= 1000, "perf loop hook lost calls: {count}");
     println!("perf smoke: 1000 trigger calls in {:?}", elapsed);
 
+    let stats = get_hook_stats(stub).expect("hook stats missing");
+    assert_eq!(stats.hits, count as u64, "hook stats hits mismatch");
+    assert_eq!(stats.threads, 1, "hook stats thread count");
+    assert!(stats.last_hit_ns > 0, "hook stats missing last hit");
+    ensure_ok(set_hook_stats_enabled(stub, false), "disable hook stats");
+    hook_test_trigger(handle);
+    assert!(
+        get_hook_stats(stub).is_none(),
+        "hook stats kept after disable"
+    );
+
     ensure_ok(unhook(stub), "unhook perf");
     libc::dlclose(handle);
     clear();
 }
 
+// 进程内模块达到 100 个以上时，只刷新新加载的一个库应远快于全量刷新
+pub unsafe fn scenario_refresh_handle_bench() {
+    const MIN_MODULES: usize = 100;
+    const ROUNDS: u32 = 20;
+
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init refresh handle bench");
+    let padding = MIN_MODULES.saturating_sub(loaded_module_count()).max(1);
+    let fixtures = hook_test_fixtures("refresh_bench", padding);
+    let module_count = loaded_module_count();
+
+    let stub = hook_all(
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_all refresh handle bench failed");
+    ensure_ok(refresh(), "refresh handle bench warmup");
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        ensure_ok(refresh(), "full refresh bench");
+    }
+    let full = start.elapsed();
+
+    let handle = fixtures[padding - 1].handle();
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        ensure_ok(refresh_handle(handle), "refresh_handle bench");
+    }
+    let targeted = start.elapsed();
+    println!(
+        "refresh handle bench: modules={} rounds={} full={:?} handle={:?}",
+        module_count, ROUNDS, full, targeted
+    );
+    assert!(
+        targeted * 4 < full,
+        "refresh_handle not cheaper than full refresh: full={full:?} handle={targeted:?}"
+    );
+
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert!(
+        HOOK_A_COUNT.load(Ordering::Relaxed) > 0,
+        "refresh_handle lost the hook on its module"
+    );
+
+    ensure_ok(unhook(stub), "unhook refresh handle bench");
+    drop(fixtures);
+    clear();
+}
+
 pub unsafe fn scenario_leak_smoke() {
     clear();
     ensure_ok(init(HookMode::Manual, true), "init leak");
@@ -173,6 +342,224 @@ pub unsafe fn scenario_leak_smoke() {
     clear();
 }
 
+// 分批创建大量短命线程并发进入信号守卫，守卫 slot 的链表节点数只取决于单批并发量，与累计线程数无关
+pub unsafe fn scenario_guard_slot_churn() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init guard slot churn");
+    let handle = load_hook_test();
+
+    let waves = env_usize("HOOK_TEST_GUARD_CHURN_WAVES", 24);
+    let wave_size = env_usize("HOOK_TEST_GUARD_CHURN_THREADS", 96);
+    let before = get_guard_slot_stats();
+    let handle_addr = handle as usize;
+    for _ in 0..waves {
+        let barrier = Arc::new(Barrier::new(wave_size));
+        let workers: Vec<_> = (0..wave_size)
+            .map(|_| {
+                let barrier = Arc::clone(&barrier);
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    for _ in 0..4 {
+                        assert!(
+                            get_module_identity(handle_addr as *mut c_void).is_some(),
+                            "module identity under guard failed"
+                        );
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().expect("guard churn worker panic");
+        }
+    }
+
+    let after = get_guard_slot_stats();
+    println!(
+        "guard slot churn: threads={} extra before={} after={} recycled={}",
+        waves * wave_size,
+        before.extra_slots,
+        after.extra_slots,
+        after.recycled
+    );
+    let bound = before.extra_slots + wave_size.saturating_sub(after.base_slots);
+    assert!(
+        after.extra_slots <= bound,
+        "guard slots grew with thread count: {} > {}",
+        after.extra_slots,
+        bound
+    );
+    assert_eq!(
+        (after.base_claimed, after.extra_claimed),
+        (0, 0),
+        "guard slots still claimed after workers exited"
+    );
+
+    libc::dlclose(handle);
+    clear();
+}
+
+// 全量 hook 与刷新走遍各守卫阶段后，访问异常统计的阶段计数与总数一致，GUARD_FAULT 记录不超过捕获数
+pub unsafe fn scenario_guard_fault_stats() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init guard fault stats");
+    set_recordable(true);
+    let handle = load_hook_test();
+    let before = get_guard_fault_stats();
+
+    let stub = hook_all(
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_all guard fault stats failed");
+    ensure_ok(refresh(), "refresh guard fault stats");
+    hook_test_trigger(handle);
+    ensure_ok(unhook(stub), "unhook guard fault stats");
+
+    let after = get_guard_fault_stats();
+    println!(
+        "guard fault stats: total={} by_phase={:?} recent={}",
+        after.total,
+        after.by_phase,
+        after.recent.len()
+    );
+    assert!(after.total >= before.total, "guard fault total decreased");
+    let phase_sum: u64 = after.by_phase.iter().map(|(_, count)| *count).sum();
+    assert_eq!(phase_sum, after.total, "per-phase counts differ from total");
+    assert!(
+        after.recent.len() as u64 <= after.total && after.recent.len() <= 16,
+        "recent faults exceed capture count: {:?}",
+        after.recent
+    );
+    let phases: BTreeSet<&str> = after.by_phase.iter().map(|(phase, _)| *phase).collect();
+    assert!(
+        after.recent.iter().all(|fault| phases.contains(fault.phase)
+            && [libc::SIGSEGV, libc::SIGBUS, libc::SIGILL, libc::SIGTRAP].contains(&fault.signum)),
+        "recent fault with unknown phase or signal: {:?}",
+        after.recent
+    );
+
+    let fault_records: Vec<_> = get_record_entries(RECORD_ITEM_ALL)
+        .into_iter()
+        .filter(|entry| entry.op == Some(RecordOp::GuardFault))
+        .collect();
+    assert!(
+        fault_records.len() as u64 <= after.total,
+        "more GUARD_FAULT records than captured faults: {fault_records:?}"
+    );
+    assert!(
+        fault_records.iter().all(|entry| entry
+            .lib_name
+            .as_deref()
+            .is_some_and(|phase| phases.contains(phase))),
+        "GUARD_FAULT record with unknown phase: {fault_records:?}"
+    );
+
+    set_recordable(false);
+    libc::dlclose(handle);
+    clear();
+}
+
+#[inline(never)]
+unsafe fn illegal_instruction() {
+    #[cfg(target_arch = "aarch64")]
+    std::arch::asm!("udf #0");
+    #[cfg(target_arch = "x86_64")]
+    std::arch::asm!("ud2");
+}
+
+#[inline(never)]
+unsafe fn breakpoint_trap() {
+    #[cfg(target_arch = "aarch64")]
+    std::arch::asm!("brk #0");
+    #[cfg(target_arch = "x86_64")]
+    std::arch::asm!("int3");
+}
+
+// 开启扩展守卫后，工作线程在守卫内执行非法指令与断点陷阱都能跳回，访问异常统计记下对应信号
+pub unsafe fn scenario_guard_extended_signals() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init guard extended signals");
+    enable_extended_signal_guard(true);
+
+    let worker = std::thread::spawn(|| unsafe {
+        let ill = run_guarded(|| illegal_instruction());
+        let ill_fault = get_guard_fault_stats().recent.last().copied();
+        let trap = run_guarded(|| breakpoint_trap());
+        let trap_fault = get_guard_fault_stats().recent.last().copied();
+        let after = run_guarded(|| 7usize);
+        (ill, ill_fault, trap, trap_fault, after)
+    });
+    let (ill, ill_fault, trap, trap_fault, after) =
+        worker.join().expect("guard extended signals worker panic");
+    enable_extended_signal_guard(false);
+
+    assert_eq!(
+        ill,
+        Err(SrxHookErrno::SegvErr),
+        "SIGILL not caught by guard"
+    );
+    assert_eq!(
+        ill_fault.map(|fault| fault.signum),
+        Some(libc::SIGILL),
+        "SIGILL missing from fault stats"
+    );
+    assert_eq!(
+        trap,
+        Err(SrxHookErrno::SegvErr),
+        "SIGTRAP not caught by guard"
+    );
+    assert_eq!(
+        trap_fault.map(|fault| fault.signum),
+        Some(libc::SIGTRAP),
+        "SIGTRAP missing from fault stats"
+    );
+    assert_eq!(
+        after,
+        Ok(7),
+        "guard unusable after recovering from SIGILL/SIGTRAP"
+    );
+    println!("guard extended signals: SIGILL and SIGTRAP recovered");
+
+    clear();
+}
+
+// Manual 模式的 refresh 按间隔重新提升 sigchain 链首：间隔内重复刷新与关闭开关后计数不变，非 sigchain 模式从不计数
+pub unsafe fn scenario_sigchain_front_reassert() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init sigchain front reassert");
+    let mode = get_capabilities()
+        .expect("capabilities missing after init")
+        .signal_handler_mode;
+
+    let before = get_sigchain_reassert_count();
+    ensure_ok(refresh(), "refresh sigchain front reassert");
+    let first = get_sigchain_reassert_count();
+    ensure_ok(refresh(), "refresh sigchain front reassert again");
+    let second = get_sigchain_reassert_count();
+    enable_sigchain_front_reassert(false);
+    ensure_ok(refresh(), "refresh sigchain front reassert disabled");
+    let disabled = get_sigchain_reassert_count();
+    enable_sigchain_front_reassert(true);
+    println!(
+        "sigchain front reassert: mode={mode:?} before={before} first={first} second={second}"
+    );
+
+    if mode != SignalHandlerMode::Sigchain {
+        assert_eq!(first, before, "reassert counted outside sigchain mode");
+    }
+    assert!(
+        first <= before + 1,
+        "single refresh reasserted more than once"
+    );
+    assert_eq!(second, first, "reassert not rate limited");
+    assert_eq!(disabled, second, "reassert ran while disabled");
+
+    clear();
+}
+
 pub unsafe fn scenario_manual_churn_marathon() {
     clear();
     ensure_ok(init(HookMode::Manual, true), "init manual churn marathon");
@@ -208,6 +595,10 @@ pub unsafe fn scenario_manual_churn_marathon() {
         }
     }
 
+    assert!(
+        drain_retired_hubs(Duration::from_secs(2)),
+        "retired hubs not drained after manual churn marathon"
+    );
     let rss_after = current_rss_kb();
     let delta = rss_after.saturating_sub(rss_before);
     let elapsed = start.elapsed();
@@ -218,9 +609,75 @@ pub unsafe fn scenario_manual_churn_marathon() {
     assert!(delta < 8192, "manual churn marathon rss delta too large: {delta}KB");
 
     libc::dlclose(handle);
+    marathon_trampoline_pool_rss();
     clear();
 }
 
+// 同时存活的大量 hub 共享 trampoline 页：对比每个 hub 独占一页时的 RSS 开销
+unsafe fn marathon_trampoline_pool_rss() {
+    let hubs = env_usize("HOOK_TEST_MARATHON_HUBS", 128);
+    let fixtures = hook_test_fixtures("marathon_pool", hubs);
+    let page_kb = libc::sysconf(libc::_SC_PAGESIZE) as usize / 1024;
+    let rss_before = current_rss_kb();
+
+    let stub = hook_all(
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_all marathon pool failed");
+    ensure_ok(refresh(), "refresh marathon pool");
+    let rss_hooked = current_rss_kb();
+
+    let slots = get_import_slot_values("libhook_test.so", "puts").expect("slot query failed");
+    let trampolines: BTreeSet<usize> = slots.iter().map(|(_, _, value)| *value).collect();
+    let pages: BTreeSet<usize> = trampolines
+        .iter()
+        .map(|addr| addr & !(page_kb * 1024 - 1))
+        .collect();
+    let delta = rss_hooked.saturating_sub(rss_before);
+    println!(
+        "marathon trampoline pool: hubs={} pages={} rss_delta={}KB page_per_hub={}KB",
+        trampolines.len(),
+        pages.len(),
+        delta,
+        trampolines.len() * page_kb
+    );
+    assert!(
+        trampolines.len() >= hubs,
+        "missing hubs: {}",
+        trampolines.len()
+    );
+    // 退回每页一个槽位（memfd 不可用）时无共享可比
+    let shared = trampolines
+        .first()
+        .and_then(|addr| mapping_name(*addr))
+        .is_some_and(|name| name.starts_with("/memfd:"));
+    if shared {
+        assert!(
+            pages.len() * 8 <= trampolines.len(),
+            "trampolines not pooled: hubs={} pages={}",
+            trampolines.len(),
+            pages.len()
+        );
+        assert!(
+            delta < trampolines.len() * page_kb,
+            "pooled hubs rss {delta}KB not below one page per hub"
+        );
+    } else {
+        println!("marathon trampoline pool: memfd alias unavailable, skip rss comparison");
+    }
+
+    ensure_ok(unhook(stub), "unhook marathon pool");
+    assert!(
+        clear_and_drain(Duration::from_secs(2)),
+        "retired hubs not drained after marathon pool"
+    );
+    drop(fixtures);
+}
+
 pub unsafe fn scenario_soak_suite() {
     let rounds = env_usize("HOOK_TEST_SOAK_ROUNDS", 6);
     let report_step = env_usize("HOOK_TEST_SOAK_REPORT_STEP", 1);
@@ -256,3 +713,91 @@ pub unsafe fn scenario_soak_suite() {
     );
     assert!(delta < 32768, "soak suite rss delta too large: {delta}KB");
 }
+
+// 同一模块上挂载大量 slot 时按页批量切换保护：mprotect 次数只与页数相关，与 slot 数无关
+pub unsafe fn scenario_mprotect_batching() {
+    const MAX_SYMBOLS: usize = 64;
+
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init mprotect batching");
+    let handle = load_hook_test();
+    let inspector = open_module("libhook_test.so").expect("open_module failed");
+
+    // 预热一次刷新，模块扫描附带的 CFI 补丁不计入下面的统计
+    let warmup = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single warmup failed");
+    ensure_ok(refresh(), "refresh mprotect warmup");
+
+    // proxy 直接指向符号原本的实现，调用行为不变
+    let mut stubs = vec![warmup];
+    let mut slots: BTreeSet<usize> = inspector
+        .got_slots_for("puts")
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+    for name in inspector
+        .imports()
+        .filter(|name| name != "puts")
+        .take(MAX_SYMBOLS)
+    {
+        let Ok(c_name) = CString::new(name.as_str()) else {
+            continue;
+        };
+        let target = libc::dlsym(libc::RTLD_DEFAULT, c_name.as_ptr());
+        let sym_slots = inspector.got_slots_for(&name).unwrap_or_default();
+        if target.is_null() || sym_slots.is_empty() {
+            continue;
+        }
+        let stub = hook_single(
+            "libhook_test.so",
+            None,
+            &name,
+            target,
+            None,
+            std::ptr::null_mut(),
+        )
+        .expect("hook_single mprotect batching failed");
+        stubs.push(stub);
+        slots.extend(sym_slots);
+    }
+    let page_size = libc::sysconf(libc::_SC_PAGESIZE) as usize;
+    let pages: BTreeSet<usize> = slots.iter().map(|slot| slot & !(page_size - 1)).collect();
+    assert!(stubs.len() > 1, "no hookable imports in libhook_test.so");
+
+    let before = get_mprotect_count();
+    ensure_ok(refresh(), "refresh mprotect batching");
+    let refresh_calls = get_mprotect_count() - before;
+    hook_test_trigger(handle);
+
+    let before = get_mprotect_count();
+    clear();
+    let restore_calls = get_mprotect_count() - before;
+    println!(
+        "mprotect batching: tasks={} slots={} pages={} refresh={} restore={}",
+        stubs.len(),
+        slots.len(),
+        pages.len(),
+        refresh_calls,
+        restore_calls
+    );
+    let limit = pages.len() as u64 * 2;
+    assert!(
+        refresh_calls <= limit,
+        "refresh issued {refresh_calls} mprotect calls for {} pages",
+        pages.len()
+    );
+    assert!(
+        restore_calls <= limit,
+        "restore issued {restore_calls} mprotect calls for {} pages",
+        pages.len()
+    );
+    drop(inspector);
+    libc::dlclose(handle);
+}
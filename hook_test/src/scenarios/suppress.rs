@@ -0,0 +1,265 @@
+use std::ffi::c_void;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::{Arc, Barrier};
+
+use srx_hook::{
+    HookMode, SrxHookErrno, ThreadHookPause, clear, get_hook_tasks, hook_single,
+    hook_single_for_threads, init, is_observation_suppressed, is_thread_hook_enabled, refresh,
+    set_task_threads, set_thread_hook_enabled, srx_hook_suppress_begin, srx_hook_suppress_end,
+    unhook, with_observation_suppressed,
+};
+
+use crate::test_ctx::{
+    HOOK_A_COUNT, HOOK_B_COUNT, ensure_ok, hook_puts_observe, hook_puts_quiet, hook_test_trigger,
+    load_hook_test,
+};
+
+pub unsafe fn scenario_observation_suppressed() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init suppress");
+    let handle = load_hook_test();
+
+    let stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_observe as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single suppress failed");
+    ensure_ok(refresh(), "refresh suppress");
+
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    HOOK_B_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert_eq!(
+        HOOK_A_COUNT.load(Ordering::Relaxed),
+        1,
+        "unsuppressed call not observed"
+    );
+
+    // 模拟记录器在抑制作用域内刷写：调用照常转发，但不计入观测
+    with_observation_suppressed(|| {
+        assert!(is_observation_suppressed(), "scope not active");
+        hook_test_trigger(handle);
+        with_observation_suppressed(|| hook_test_trigger(handle));
+        hook_test_trigger(handle);
+    });
+    assert!(!is_observation_suppressed(), "scope leaked");
+    assert_eq!(
+        HOOK_A_COUNT.load(Ordering::Relaxed),
+        1,
+        "suppressed calls observed"
+    );
+    assert_eq!(
+        HOOK_B_COUNT.load(Ordering::Relaxed),
+        4,
+        "suppressed calls not forwarded"
+    );
+
+    srx_hook_suppress_begin();
+    hook_test_trigger(handle);
+    srx_hook_suppress_end();
+    hook_test_trigger(handle);
+    assert_eq!(
+        HOOK_A_COUNT.load(Ordering::Relaxed),
+        2,
+        "extern C scope mismatch"
+    );
+
+    // clear 会复位未配对的 begin
+    srx_hook_suppress_begin();
+    ensure_ok(unhook(stub), "unhook suppress");
+    libc::dlclose(handle);
+    clear();
+    assert!(
+        !is_observation_suppressed(),
+        "clear did not reset suppression"
+    );
+}
+
+pub unsafe fn scenario_thread_hook_toggle() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init thread toggle");
+    let handle = load_hook_test();
+
+    let stub = hook_single(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single thread toggle failed");
+    ensure_ok(refresh(), "refresh thread toggle");
+
+    // 两个线程同时调用，只有未关闭 hook 的线程计数
+    const CALLS: usize = 200;
+    let barrier = Arc::new(Barrier::new(2));
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    let handle_addr = handle as usize;
+    let workers: Vec<_> = [false, true]
+        .into_iter()
+        .map(|enabled| {
+            let barrier = Arc::clone(&barrier);
+            std::thread::spawn(move || {
+                assert!(is_thread_hook_enabled(), "new thread not enabled");
+                set_thread_hook_enabled(enabled);
+                barrier.wait();
+                for _ in 0..CALLS {
+                    unsafe { hook_test_trigger(handle_addr as *mut c_void) };
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().expect("thread toggle worker panic");
+    }
+    assert_eq!(
+        HOOK_A_COUNT.load(Ordering::Relaxed),
+        CALLS,
+        "disabled thread still intercepted"
+    );
+
+    // 守卫可嵌套，析构后恢复进入前的状态
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    {
+        let _outer = ThreadHookPause::new();
+        hook_test_trigger(handle);
+        {
+            let _inner = ThreadHookPause::new();
+            hook_test_trigger(handle);
+        }
+        assert!(!is_thread_hook_enabled(), "inner pause re-enabled hook");
+        hook_test_trigger(handle);
+    }
+    assert!(is_thread_hook_enabled(), "pause not restored");
+    hook_test_trigger(handle);
+    assert_eq!(
+        HOOK_A_COUNT.load(Ordering::Relaxed),
+        1,
+        "paused calls intercepted"
+    );
+
+    ensure_ok(unhook(stub), "unhook thread toggle");
+    libc::dlclose(handle);
+    clear();
+}
+
+// 两个工作线程各调用 CALLS 次，返回期间的命中数；线程先报告 tid，等 configure 完成后再调用
+unsafe fn run_tid_workers(handle: *mut c_void, configure: impl FnOnce(i32, i32)) -> usize {
+    const CALLS: usize = 200;
+    let start = Arc::new(Barrier::new(3));
+    let (tid_tx, tid_rx) = mpsc::channel();
+    let handle_addr = handle as usize;
+    let workers: Vec<_> = (0..2)
+        .map(|idx| {
+            let start = Arc::clone(&start);
+            let tid_tx = tid_tx.clone();
+            std::thread::spawn(move || {
+                tid_tx
+                    .send((idx, unsafe { libc::gettid() }))
+                    .expect("send tid");
+                start.wait();
+                for _ in 0..CALLS {
+                    unsafe { hook_test_trigger(handle_addr as *mut c_void) };
+                }
+            })
+        })
+        .collect();
+    let mut tids = [0; 2];
+    for _ in 0..2 {
+        let (idx, tid) = tid_rx.recv().expect("recv tid");
+        tids[idx] = tid;
+    }
+    configure(tids[0], tids[1]);
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    start.wait();
+    for worker in workers {
+        worker.join().expect("tid filter worker panic");
+    }
+    let hits = HOOK_A_COUNT.load(Ordering::Relaxed);
+    assert!(hits == 0 || hits == CALLS, "unexpected hit count: {hits}");
+    hits
+}
+
+pub unsafe fn scenario_thread_filter() {
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init thread filter");
+    let handle = load_hook_test();
+
+    let rejected = hook_single_for_threads(
+        "libhook_test.so",
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        &[0],
+        None,
+        std::ptr::null_mut(),
+    );
+    assert_eq!(
+        rejected,
+        Err(SrxHookErrno::InvalidArg),
+        "non-positive tid accepted"
+    );
+
+    // 只限定第一个工作线程
+    let mut stub = 0;
+    let hits = run_tid_workers(handle, |first, _| {
+        stub = hook_single_for_threads(
+            "libhook_test.so",
+            None,
+            "puts",
+            hook_puts_quiet as *mut c_void,
+            &[first],
+            None,
+            std::ptr::null_mut(),
+        )
+        .expect("hook_single_for_threads failed");
+        ensure_ok(refresh(), "refresh thread filter");
+        let task = get_hook_tasks()
+            .into_iter()
+            .find(|task| task.stub == stub)
+            .expect("thread filter task missing");
+        assert_eq!(task.threads, vec![first], "task threads mismatch");
+    });
+    assert!(hits > 0, "filtered thread not intercepted");
+
+    // 主线程不在集合内
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert_eq!(
+        HOOK_A_COUNT.load(Ordering::Relaxed),
+        0,
+        "unlisted thread intercepted"
+    );
+
+    // 替换为第二个工作线程：两个线程中仍只有一个计数
+    let hits = run_tid_workers(handle, |_, second| {
+        ensure_ok(set_task_threads(stub, &[second]), "set task threads");
+    });
+    assert!(hits > 0, "updated thread set not applied");
+
+    // 集合不含任何工作线程时两者都不计数，清空后恢复对所有线程生效
+    let hits = run_tid_workers(handle, |first, second| {
+        let unused = first.max(second) + 1;
+        ensure_ok(set_task_threads(stub, &[unused]), "set unused thread");
+    });
+    assert_eq!(hits, 0, "unlisted workers intercepted");
+    ensure_ok(set_task_threads(stub, &[]), "reset task threads");
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert_eq!(
+        HOOK_A_COUNT.load(Ordering::Relaxed),
+        1,
+        "empty thread set not applied to all threads"
+    );
+    assert_eq!(set_task_threads(stub, &[-1]), SrxHookErrno::InvalidArg);
+
+    ensure_ok(unhook(stub), "unhook thread filter");
+    libc::dlclose(handle);
+    clear();
+}
@@ -0,0 +1,57 @@
+use std::ffi::{CString, c_void};
+use std::sync::atomic::Ordering;
+
+use srx_hook::{HookMode, clear, hook_single, init, refresh, unhook};
+
+use crate::test_ctx::{
+    HOOK_A_COUNT, ensure_ok, hook_puts_quiet, hook_test_trigger, load_hook_test_abs,
+};
+
+// 需要外部提供带 DT_TEXTREL 的 fixture：导出 hook_test_trigger，并通过 .text 绝对地址调用 puts
+pub unsafe fn scenario_textrel_patch() {
+    let Ok(lib_path) = std::env::var("HOOK_TEST_TEXTREL_LIB") else {
+        println!("skip textrel-patch: HOOK_TEST_TEXTREL_LIB not set");
+        return;
+    };
+    let lib_path = CString::new(lib_path).expect("textrel lib path cstring failed");
+    let lib_name = lib_path
+        .to_str()
+        .ok()
+        .and_then(|path| path.rsplit('/').next())
+        .expect("textrel lib basename")
+        .to_string();
+
+    clear();
+    ensure_ok(init(HookMode::Manual, true), "init textrel patch");
+    let handle = load_hook_test_abs(&lib_path);
+
+    let stub = hook_single(
+        lib_name.as_str(),
+        None,
+        "puts",
+        hook_puts_quiet as *mut c_void,
+        None,
+        std::ptr::null_mut(),
+    )
+    .expect("hook_single textrel failed");
+    ensure_ok(refresh(), "refresh textrel");
+
+    HOOK_A_COUNT.store(0, Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert!(
+        HOOK_A_COUNT.load(Ordering::Relaxed) >= 1,
+        "text relocation call site not patched"
+    );
+
+    ensure_ok(unhook(stub), "unhook textrel");
+    let before = HOOK_A_COUNT.load(Ordering::Relaxed);
+    hook_test_trigger(handle);
+    assert_eq!(
+        before,
+        HOOK_A_COUNT.load(Ordering::Relaxed),
+        "text relocation call site still hooked after unhook"
+    );
+
+    libc::dlclose(handle);
+    clear();
+}
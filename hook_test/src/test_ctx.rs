@@ -1,9 +1,11 @@
 use std::ffi::{CString, c_char, c_void};
 use std::fs;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
 
+use srx_hook::fixtures::{FixtureBuilder, LoadedFixture};
 use srx_hook::{
-    SrxHookErrno, get_prev_func, get_return_address, pop_stack, proxy_leave, with_prev_func,
+    SrxHookErrno, get_return_address, is_observation_suppressed, pop_stack, prev_func_as,
+    proxy_leave, with_prev_func_as,
 };
 
 pub static HOOK_A_COUNT: AtomicUsize = AtomicUsize::new(0);
@@ -12,6 +14,7 @@ pub static HOOK_C_COUNT: AtomicUsize = AtomicUsize::new(0);
 pub static STACK_API_COUNT: AtomicUsize = AtomicUsize::new(0);
 pub static DLOPEN_PRE_COUNT: AtomicUsize = AtomicUsize::new(0);
 pub static DLOPEN_POST_COUNT: AtomicUsize = AtomicUsize::new(0);
+pub static DLOPEN_LAST_RESULT: AtomicI32 = AtomicI32::new(0);
 
 pub type PutsFn = unsafe extern "C" fn(*const c_char) -> i32;
 pub type StrlenFn = unsafe extern "C" fn(*const c_char) -> usize;
@@ -31,12 +34,8 @@ unsafe extern "C" {
 pub unsafe extern "C" fn hook_puts_a_chain(s: *const c_char) -> i32 {
     HOOK_A_COUNT.fetch_add(1, Ordering::Relaxed);
     let self_ptr = hook_puts_a_chain as *mut c_void;
-    with_prev_func(self_ptr, |prev| {
-        if prev.is_null() {
-            return 0;
-        }
-        let prev_fn: PutsFn = unsafe { std::mem::transmute(prev) };
-        unsafe { prev_fn(s) }
+    with_prev_func_as(self_ptr, |prev: Option<PutsFn>| {
+        prev.map_or(0, |prev_fn| unsafe { prev_fn(s) })
     })
     .unwrap_or(0)
 }
@@ -44,12 +43,8 @@ pub unsafe extern "C" fn hook_puts_a_chain(s: *const c_char) -> i32 {
 pub unsafe extern "C" fn hook_puts_b_chain(s: *const c_char) -> i32 {
     HOOK_B_COUNT.fetch_add(1, Ordering::Relaxed);
     let self_ptr = hook_puts_b_chain as *mut c_void;
-    with_prev_func(self_ptr, |prev| {
-        if prev.is_null() {
-            return 0;
-        }
-        let prev_fn: PutsFn = unsafe { std::mem::transmute(prev) };
-        unsafe { prev_fn(s) }
+    with_prev_func_as(self_ptr, |prev: Option<PutsFn>| {
+        prev.map_or(0, |prev_fn| unsafe { prev_fn(s) })
     })
     .unwrap_or(0)
 }
@@ -57,12 +52,8 @@ pub unsafe extern "C" fn hook_puts_b_chain(s: *const c_char) -> i32 {
 pub unsafe extern "C" fn hook_puts_c_chain(s: *const c_char) -> i32 {
     HOOK_C_COUNT.fetch_add(1, Ordering::Relaxed);
     let self_ptr = hook_puts_c_chain as *mut c_void;
-    with_prev_func(self_ptr, |prev| {
-        if prev.is_null() {
-            return 0;
-        }
-        let prev_fn: PutsFn = unsafe { std::mem::transmute(prev) };
-        unsafe { prev_fn(s) }
+    with_prev_func_as(self_ptr, |prev: Option<PutsFn>| {
+        prev.map_or(0, |prev_fn| unsafe { prev_fn(s) })
     })
     .unwrap_or(0)
 }
@@ -75,15 +66,22 @@ pub unsafe extern "C" fn hook_puts_quiet(s: *const c_char) -> i32 {
     0
 }
 
+// 观测型 proxy：抑制作用域内不计数，但仍沿 prev 链转发
+pub unsafe extern "C" fn hook_puts_observe(s: *const c_char) -> i32 {
+    if !is_observation_suppressed() {
+        HOOK_A_COUNT.fetch_add(1, Ordering::Relaxed);
+    }
+    HOOK_B_COUNT.fetch_add(1, Ordering::Relaxed);
+    let self_ptr = hook_puts_observe as *mut c_void;
+    let result = prev_func_as::<PutsFn>(self_ptr).map_or(0, |prev_fn| unsafe { prev_fn(s) });
+    proxy_leave(self_ptr);
+    result
+}
+
 pub unsafe extern "C" fn hook_puts_no_leave(s: *const c_char) -> i32 {
     HOOK_A_COUNT.fetch_add(1, Ordering::Relaxed);
     let self_ptr = hook_puts_no_leave as *mut c_void;
-    let prev = get_prev_func(self_ptr);
-    if prev.is_null() {
-        return 0;
-    }
-    let prev_fn: PutsFn = unsafe { std::mem::transmute(prev) };
-    unsafe { prev_fn(s) }
+    prev_func_as::<PutsFn>(self_ptr).map_or(0, |prev_fn| unsafe { prev_fn(s) })
 }
 
 pub unsafe extern "C" fn hook_puts_return_address_stack(s: *const c_char) -> i32 {
@@ -94,13 +92,7 @@ pub unsafe extern "C" fn hook_puts_return_address_stack(s: *const c_char) -> i32
         !return_address.is_null(),
         "return_address API should be valid in proxy context"
     );
-    let prev = get_prev_func(self_ptr);
-    let result = if prev.is_null() {
-        0
-    } else {
-        let prev_fn: PutsFn = unsafe { std::mem::transmute(prev) };
-        unsafe { prev_fn(s) }
-    };
+    let result = prev_func_as::<PutsFn>(self_ptr).map_or(0, |prev_fn| unsafe { prev_fn(s) });
     pop_stack(return_address);
     result
 }
@@ -108,12 +100,8 @@ pub unsafe extern "C" fn hook_puts_return_address_stack(s: *const c_char) -> i32
 pub unsafe extern "C" fn hook_puts_cycle_guard(s: *const c_char) -> i32 {
     HOOK_A_COUNT.fetch_add(1, Ordering::Relaxed);
     let self_ptr = hook_puts_cycle_guard as *mut c_void;
-    with_prev_func(self_ptr, |prev| {
-        let mut result = 0;
-        if !prev.is_null() {
-            let prev_fn: PutsFn = unsafe { std::mem::transmute(prev) };
-            result = unsafe { prev_fn(s) };
-        }
+    with_prev_func_as(self_ptr, |prev: Option<PutsFn>| {
+        let result = prev.map_or(0, |prev_fn| unsafe { prev_fn(s) });
         if !s.is_null() {
             let _ = unsafe { libc::strlen(s) };
         }
@@ -125,12 +113,7 @@ pub unsafe extern "C" fn hook_puts_cycle_guard(s: *const c_char) -> i32 {
 pub unsafe extern "C" fn hook_puts_cycle_manual_no_leave(s: *const c_char) -> i32 {
     HOOK_A_COUNT.fetch_add(1, Ordering::Relaxed);
     let self_ptr = hook_puts_cycle_manual_no_leave as *mut c_void;
-    let prev = get_prev_func(self_ptr);
-    let mut result = 0;
-    if !prev.is_null() {
-        let prev_fn: PutsFn = unsafe { std::mem::transmute(prev) };
-        result = unsafe { prev_fn(s) };
-    }
+    let result = prev_func_as::<PutsFn>(self_ptr).map_or(0, |prev_fn| unsafe { prev_fn(s) });
     if !s.is_null() {
         let _ = unsafe { libc::strlen(s) };
     }
@@ -140,12 +123,8 @@ pub unsafe extern "C" fn hook_puts_cycle_manual_no_leave(s: *const c_char) -> i3
 pub unsafe extern "C" fn hook_strlen_cycle_guard(s: *const c_char) -> usize {
     HOOK_B_COUNT.fetch_add(1, Ordering::Relaxed);
     let self_ptr = hook_strlen_cycle_guard as *mut c_void;
-    with_prev_func(self_ptr, |prev| {
-        let mut result = 0usize;
-        if !prev.is_null() {
-            let prev_fn: StrlenFn = unsafe { std::mem::transmute(prev) };
-            result = unsafe { prev_fn(s) };
-        }
+    with_prev_func_as(self_ptr, |prev: Option<StrlenFn>| {
+        let result = prev.map_or(0usize, |prev_fn| unsafe { prev_fn(s) });
         let msg = b"cycle-guard\n\0";
         let _ = unsafe { libc::puts(msg.as_ptr() as *const c_char) };
         result
@@ -156,12 +135,7 @@ pub unsafe extern "C" fn hook_strlen_cycle_guard(s: *const c_char) -> usize {
 pub unsafe extern "C" fn hook_strlen_cycle_manual_no_leave(s: *const c_char) -> usize {
     HOOK_B_COUNT.fetch_add(1, Ordering::Relaxed);
     let self_ptr = hook_strlen_cycle_manual_no_leave as *mut c_void;
-    let prev = get_prev_func(self_ptr);
-    let mut result = 0usize;
-    if !prev.is_null() {
-        let prev_fn: StrlenFn = unsafe { std::mem::transmute(prev) };
-        result = unsafe { prev_fn(s) };
-    }
+    let result = prev_func_as::<StrlenFn>(self_ptr).map_or(0usize, |prev_fn| unsafe { prev_fn(s) });
     let msg = b"cycle-manual\n\0";
     let _ = unsafe { libc::puts(msg.as_ptr() as *const c_char) };
     result
@@ -173,9 +147,10 @@ pub unsafe extern "C" fn hook_test_dlopen_pre(_filename: *const c_char, _arg: *m
 
 pub unsafe extern "C" fn hook_test_dlopen_post(
     _filename: *const c_char,
-    _result: i32,
+    result: i32,
     _arg: *mut c_void,
 ) {
+    DLOPEN_LAST_RESULT.store(result, Ordering::Relaxed);
     DLOPEN_POST_COUNT.fetch_add(1, Ordering::Relaxed);
 }
 
@@ -199,6 +174,21 @@ pub fn current_rss_kb() -> usize {
     0
 }
 
+// maps 行第 6 列起为映射名称，匿名命名映射显示为 [anon:<name>]
+pub fn mapping_name(addr: usize) -> Option<String> {
+    let maps = std::fs::read_to_string("/proc/self/maps").ok()?;
+    maps.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let (start, end) = fields.next()?.split_once('-')?;
+        let start = usize::from_str_radix(start, 16).ok()?;
+        let end = usize::from_str_radix(end, 16).ok()?;
+        if addr < start || addr >= end {
+            return None;
+        }
+        Some(fields.nth(4).unwrap_or("").to_owned())
+    })
+}
+
 pub fn env_usize(name: &str, default: usize) -> usize {
     std::env::var(name)
         .ok()
@@ -276,32 +266,56 @@ fn android_api_level() -> i32 {
         .unwrap_or(0)
 }
 
-pub unsafe fn verify_cfi_slowpath_disabled() {
-    #[cfg(target_arch = "aarch64")]
-    {
-        if android_api_level() < ANDROID_API_LEVEL_CFI_DISABLE {
-            return;
-        }
+// libdl 导出的 __cfi_slowpath / __cfi_slowpath_diag 首条指令，系统无需处理 CFI 时返回 None
+#[cfg(target_arch = "aarch64")]
+unsafe fn cfi_slowpath_instructions() -> Option<(u32, Option<u32>)> {
+    if android_api_level() < ANDROID_API_LEVEL_CFI_DISABLE {
+        return None;
+    }
 
-        let handle = libc::dlopen(c"libdl.so".as_ptr(), libc::RTLD_NOW);
-        assert!(!handle.is_null(), "verify cfi: dlopen libdl.so failed");
-        let slowpath = libc::dlsym(handle, c"__cfi_slowpath".as_ptr());
-        let slowpath_diag = libc::dlsym(handle, c"__cfi_slowpath_diag".as_ptr());
-        assert!(!slowpath.is_null(), "verify cfi: __cfi_slowpath missing");
+    let handle = libc::dlopen(c"libdl.so".as_ptr(), libc::RTLD_NOW);
+    assert!(!handle.is_null(), "verify cfi: dlopen libdl.so failed");
+    let slowpath = libc::dlsym(handle, c"__cfi_slowpath".as_ptr());
+    let slowpath_diag = libc::dlsym(handle, c"__cfi_slowpath_diag".as_ptr());
+    assert!(!slowpath.is_null(), "verify cfi: __cfi_slowpath missing");
+
+    let slowpath_inst = std::ptr::read_volatile(slowpath as *const u32);
+    let slowpath_diag_inst =
+        (!slowpath_diag.is_null()).then(|| std::ptr::read_volatile(slowpath_diag as *const u32));
+    libc::dlclose(handle);
+    Some((slowpath_inst, slowpath_diag_inst))
+}
 
-        let slowpath_inst = std::ptr::read_volatile(slowpath as *const u32);
+pub unsafe fn verify_cfi_slowpath_disabled() {
+    #[cfg(target_arch = "aarch64")]
+    if let Some((slowpath_inst, slowpath_diag_inst)) = cfi_slowpath_instructions() {
         assert_eq!(
             slowpath_inst, ARM64_RET_INST,
             "verify cfi: __cfi_slowpath not patched"
         );
-        if !slowpath_diag.is_null() {
-            let slowpath_diag_inst = std::ptr::read_volatile(slowpath_diag as *const u32);
+        if let Some(slowpath_diag_inst) = slowpath_diag_inst {
             assert_eq!(
                 slowpath_diag_inst, ARM64_RET_INST,
                 "verify cfi: __cfi_slowpath_diag not patched"
             );
         }
-        libc::dlclose(handle);
+    }
+}
+
+// ModulesWithHooks / Disabled 范围或 restore_cfi_slowpath 之后，slowpath 入口保持原始指令
+pub unsafe fn verify_cfi_slowpath_intact() {
+    #[cfg(target_arch = "aarch64")]
+    if let Some((slowpath_inst, slowpath_diag_inst)) = cfi_slowpath_instructions() {
+        assert_ne!(
+            slowpath_inst, ARM64_RET_INST,
+            "verify cfi: __cfi_slowpath still patched"
+        );
+        if let Some(slowpath_diag_inst) = slowpath_diag_inst {
+            assert_ne!(
+                slowpath_diag_inst, ARM64_RET_INST,
+                "verify cfi: __cfi_slowpath_diag still patched"
+            );
+        }
     }
 }
 
@@ -334,23 +348,16 @@ pub unsafe fn load_hook_test_lazy() -> *mut c_void {
     load_hook_test_with_flags(libc::RTLD_LAZY)
 }
 
-pub fn prepare_same_basename_hook_test_instances() -> (CString, CString) {
-    let src = format!("{HOOK_TEST_WORK_DIR}/libhook_test.so");
-    let dir_a = format!("{HOOK_TEST_WORK_DIR}/ns_a");
-    let dir_b = format!("{HOOK_TEST_WORK_DIR}/ns_b");
-    let dst_a = format!("{dir_a}/libhook_test.so");
-    let dst_b = format!("{dir_b}/libhook_test.so");
-
-    fs::create_dir_all(&dir_a).expect("create ns_a dir failed");
-    fs::create_dir_all(&dir_b).expect("create ns_b dir failed");
-    fs::copy(&src, &dst_a).expect("copy libhook_test to ns_a failed");
-    fs::copy(&src, &dst_b).expect("copy libhook_test to ns_b failed");
-
-    let path_a = CString::new(dst_a).expect("path_a cstring failed");
-    let path_b = CString::new(dst_b).expect("path_b cstring failed");
-    (path_a, path_b)
+// 将 libhook_test.so 复制到 count 个目录并分别加载，得到同 basename 的不同实例
+pub fn hook_test_fixtures(name: &str, count: usize) -> Vec<LoadedFixture> {
+    FixtureBuilder::new(HOOK_TEST_WORK_DIR, "libhook_test.so")
+        .name(name)
+        .instances(count)
+        .build()
+        .expect("prepare libhook_test fixtures failed")
 }
 
+#[cfg(feature = "textrel-patch")]
 pub unsafe fn load_hook_test_abs(path: &CString) -> *mut c_void {
     let handle = libc::dlopen(path.as_ptr(), libc::RTLD_NOW);
     assert!(!handle.is_null(), "dlopen abs hook_test failed");
@@ -408,6 +415,25 @@ pub unsafe fn module_instance_from_handle(handle: *mut c_void) -> Option<usize>
     }
 }
 
+// 当前进程中 dl_iterate_phdr 可见的模块数
+pub fn loaded_module_count() -> usize {
+    unsafe extern "C" fn iterate_cb(
+        _info: *mut libc::dl_phdr_info,
+        _size: usize,
+        data: *mut c_void,
+    ) -> libc::c_int {
+        let count = unsafe { &mut *(data as *mut usize) };
+        *count += 1;
+        0
+    }
+
+    let mut count = 0usize;
+    unsafe {
+        libc::dl_iterate_phdr(Some(iterate_cb), &mut count as *mut usize as *mut c_void);
+    }
+    count
+}
+
 pub unsafe fn hook_test_trigger(handle: *mut c_void) {
     let sym_name = CString::new("hook_test_trigger").expect("cstring failed");
     let sym = libc::dlsym(handle, sym_name.as_ptr());
@@ -423,3 +449,24 @@ pub unsafe fn hook_test_trigger_with_input(handle: *mut c_void, msg: &CString) {
     let trigger: unsafe extern "C" fn(*const c_char) = std::mem::transmute(sym);
     trigger(msg.as_ptr());
 }
+
+pub unsafe fn hook_test_trigger_fnptr(handle: *mut c_void) {
+    let sym = libc::dlsym(handle, c"hook_test_trigger_fnptr".as_ptr());
+    assert!(!sym.is_null(), "dlsym hook_test_trigger_fnptr failed");
+    let trigger: unsafe extern "C" fn() = std::mem::transmute(sym);
+    trigger();
+}
+
+pub unsafe fn hook_test_trigger_fmod(handle: *mut c_void, x: f64, y: f64) -> f64 {
+    let sym = libc::dlsym(handle, c"hook_test_trigger_fmod".as_ptr());
+    assert!(!sym.is_null(), "dlsym hook_test_trigger_fmod failed");
+    let trigger: unsafe extern "C" fn(f64, f64) -> f64 = std::mem::transmute(sym);
+    trigger(x, y)
+}
+
+pub unsafe fn hook_test_trigger_fmodf(handle: *mut c_void, x: f32, y: f32) -> f32 {
+    let sym = libc::dlsym(handle, c"hook_test_trigger_fmodf".as_ptr());
+    assert!(!sym.is_null(), "dlsym hook_test_trigger_fmodf failed");
+    let trigger: unsafe extern "C" fn(f32, f32) -> f32 = std::mem::transmute(sym);
+    trigger(x, y)
+}
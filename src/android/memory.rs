@@ -2,14 +2,118 @@
 
 use crate::errno::Errno;
 use crate::log;
+use std::ffi::CStr;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::sync::atomic::{Ordering, fence};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU64, Ordering, fence};
+
+use super::signal_guard::{self, GuardPhase};
+
+mod mprotect;
 
 pub const PROT_READ_FLAG: u32 = 0x1;
 pub const PROT_WRITE_FLAG: u32 = 0x2;
 pub const PROT_EXEC_FLAG: u32 = 0x4;
 
+// set_addr_protect 发出的 mprotect 调用累计数，用于调试观察批量写入效果
+static MPROTECT_CALLS: AtomicU64 = AtomicU64::new(0);
+
+pub fn mprotect_count() -> u64 {
+    MPROTECT_CALLS.load(Ordering::Relaxed)
+}
+
+// 写入后未能恢复原始保护的累计次数，对应页面保持可写
+static PROTECT_RESTORE_FAILURES: AtomicU64 = AtomicU64::new(0);
+
+pub fn protect_restore_failure_count() -> u64 {
+    PROTECT_RESTORE_FAILURES.load(Ordering::Relaxed)
+}
+
+// process_vm_readv 被 EINTR 打断时的最大重试次数
+const VM_READ_RETRIES: u32 = 3;
+
+// safe_read_usize 的读取路径统计：系统调用读到的值、系统调用判定为不可读的地址、回退到信号保护直接读取的次数
+static VM_READS: AtomicU64 = AtomicU64::new(0);
+static VM_READ_FAULTS: AtomicU64 = AtomicU64::new(0);
+static GUARDED_READS: AtomicU64 = AtomicU64::new(0);
+// 内核不支持或被 seccomp 拦截后不再尝试系统调用
+static VM_READ_UNAVAILABLE: AtomicBool = AtomicBool::new(false);
+
+pub fn safe_read_counts() -> (u64, u64, u64) {
+    (
+        VM_READS.load(Ordering::Relaxed),
+        VM_READ_FAULTS.load(Ordering::Relaxed),
+        GUARDED_READS.load(Ordering::Relaxed),
+    )
+}
+
+// 读取任意地址上的一个字：优先经 process_vm_readv 读本进程，地址未映射时由内核返回 EFAULT，
+// 不触发真实的 SIGSEGV；系统调用不可用或只读到部分字节时回退到信号保护下的直接读取
+pub fn safe_read_usize(addr: usize) -> Option<usize> {
+    if !VM_READ_UNAVAILABLE.load(Ordering::Relaxed) {
+        match vm_read_usize(addr) {
+            Ok(value) => {
+                VM_READS.fetch_add(1, Ordering::Relaxed);
+                return Some(value);
+            }
+            Err(libc::EFAULT) => {
+                VM_READ_FAULTS.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+            Err(libc::ENOSYS | libc::EPERM) => {
+                VM_READ_UNAVAILABLE.store(true, Ordering::Relaxed);
+                log::info(format_args!(
+                    "process_vm_readv unavailable, speculative reads use signal guard"
+                ));
+            }
+            Err(_) => {}
+        }
+    }
+    GUARDED_READS.fetch_add(1, Ordering::Relaxed);
+    signal_guard::with_guard_tagged(GuardPhase::SafeRead, || unsafe {
+        std::ptr::read_volatile(addr as *const usize)
+    })
+    .ok()
+}
+
+// 返回 errno；只读到部分字节时返回 0，交由调用方回退
+fn vm_read_usize(addr: usize) -> Result<usize, i32> {
+    let mut value = 0usize;
+    let size = std::mem::size_of::<usize>();
+    let local = libc::iovec {
+        iov_base: &mut value as *mut usize as *mut libc::c_void,
+        iov_len: size,
+    };
+    let remote = libc::iovec {
+        iov_base: addr as *mut libc::c_void,
+        iov_len: size,
+    };
+    for _ in 0..VM_READ_RETRIES {
+        let read = unsafe {
+            libc::syscall(
+                libc::SYS_process_vm_readv,
+                libc::getpid(),
+                &local as *const libc::iovec,
+                1usize,
+                &remote as *const libc::iovec,
+                1usize,
+                0usize,
+            )
+        };
+        if read == size as libc::c_long {
+            return Ok(value);
+        }
+        if read >= 0 {
+            return Err(0);
+        }
+        let err = std::io::Error::last_os_error().raw_os_error().unwrap_or(0);
+        if err != libc::EINTR {
+            return Err(err);
+        }
+    }
+    Err(libc::EINTR)
+}
+
 // 查询指定地址范围的内存保护属性
 // pathname 可选，用于加速 maps 行过滤；不匹配时回退纯地址查找
 pub fn get_mem_protect(addr: usize, len: usize, pathname: Option<&str>) -> Result<u32, Errno> {
@@ -113,6 +217,94 @@ fn scan_maps_for_protect(addr: usize, len: usize, pathname: Option<&str>) -> Res
     Ok(prot)
 }
 
+// prctl 常量，旧版 libc 未导出
+const PR_GET_TAGGED_ADDR_CTRL: libc::c_int = 56;
+const PR_TAGGED_ADDR_ENABLE: libc::c_int = 1;
+const PR_SET_VMA: libc::c_int = 0x53564d41;
+const PR_SET_VMA_ANON_NAME: libc::c_int = 0;
+// aarch64 TBI：地址最高字节为标签（MTE 标签位于其低 4 位），不参与寻址
+#[cfg(target_arch = "aarch64")]
+const ADDR_TAG_MASK: usize = 0xff << 56;
+
+// 0 未检测，1 未启用，2 已启用
+static TAGGED_ADDR_STATE: AtomicU8 = AtomicU8::new(0);
+
+// 去掉地址中的标签字节，x86_64 上原样返回
+pub const fn strip_addr_tag(addr: usize) -> usize {
+    #[cfg(target_arch = "aarch64")]
+    {
+        addr & !ADDR_TAG_MASK
+    }
+    #[cfg(not(target_arch = "aarch64"))]
+    {
+        addr
+    }
+}
+
+// 比较 GOT 值与导出地址前统一去掉标签：启用 tagged address ABI（含 MTE）时两者标签可能不同
+pub fn canonicalize_tagged_addr(addr: usize) -> usize {
+    if cfg!(target_arch = "aarch64") && is_tagged_addr_enabled() {
+        strip_addr_tag(addr)
+    } else {
+        addr
+    }
+}
+
+// 去掉 PAC 签名：XPACLRI 位于 HINT 空间，不支持 PAuth 的处理器上按 NOP 执行
+// 仅用于比较与按数据访问代码地址，调用仍须使用原始（已签名）的值
+pub fn strip_pac(addr: usize) -> usize {
+    #[cfg(target_arch = "aarch64")]
+    {
+        if has_pauth() {
+            let stripped: usize;
+            unsafe {
+                core::arch::asm!(
+                    "hint #7",
+                    inout("x30") addr => stripped,
+                    options(nomem, nostack, preserves_flags)
+                );
+            }
+            return stripped;
+        }
+    }
+    addr
+}
+
+// 用于比较的代码地址：依次去掉 PAC 签名与地址标签
+pub fn canonical_code_addr(addr: usize) -> usize {
+    canonicalize_tagged_addr(strip_pac(addr))
+}
+
+// AT_HWCAP 中的 PACA 位只检测一次，0 未检测，1 不支持，2 支持
+#[cfg(target_arch = "aarch64")]
+fn has_pauth() -> bool {
+    const HWCAP_PACA: libc::c_ulong = 1 << 30;
+    static PAUTH_STATE: AtomicU8 = AtomicU8::new(0);
+    match PAUTH_STATE.load(Ordering::Relaxed) {
+        1 => false,
+        2 => true,
+        _ => {
+            let supported = unsafe { libc::getauxval(libc::AT_HWCAP) } & HWCAP_PACA != 0;
+            PAUTH_STATE.store(if supported { 2 } else { 1 }, Ordering::Relaxed);
+            supported
+        }
+    }
+}
+
+// PR_GET_TAGGED_ADDR_CTRL 结果只检测一次
+fn is_tagged_addr_enabled() -> bool {
+    match TAGGED_ADDR_STATE.load(Ordering::Relaxed) {
+        1 => false,
+        2 => true,
+        _ => {
+            let ctrl = unsafe { libc::prctl(PR_GET_TAGGED_ADDR_CTRL, 0, 0, 0, 0) };
+            let enabled = ctrl >= 0 && ctrl & PR_TAGGED_ADDR_ENABLE != 0;
+            TAGGED_ADDR_STATE.store(if enabled { 2 } else { 1 }, Ordering::Relaxed);
+            enabled
+        }
+    }
+}
+
 // 查询单个指针大小地址的保护属性
 pub fn get_addr_protect(addr: usize, pathname: Option<&str>) -> Result<u32, Errno> {
     get_mem_protect(addr, std::mem::size_of::<usize>(), pathname)
@@ -120,16 +312,89 @@ pub fn get_addr_protect(addr: usize, pathname: Option<&str>) -> Result<u32, Errn
 
 // 修改指定地址所在页面的保护属性
 pub fn set_addr_protect(addr: usize, prot: u32) -> Result<(), Errno> {
-    let (start, len) = page_bounds(addr);
-    let result = unsafe { libc::mprotect(start as *mut libc::c_void, len, prot as i32) };
-    if result != 0 {
-        let err = std::io::Error::last_os_error().raw_os_error().unwrap_or(0);
+    set_mem_protect(addr, std::mem::size_of::<usize>(), prot)
+}
+
+// 在 [addr, addr + len) 可写期间执行 f，结束后恢复为查询到的原始保护
+// 原始保护已含写权限时不发出任何 mprotect；否则保留读与执行位只追加写权限
+// 范围应位于同一映射内，跨映射时查询结果为各段交集，恢复后可能与原值不同
+// 恢复失败只记录日志与计数，f 的结果照常返回
+pub fn with_writable<T>(addr: usize, len: usize, f: impl FnOnce() -> T) -> Result<T, Errno> {
+    let old_prot = get_mem_protect(addr, len, None).map_err(|_| Errno::GetProt)?;
+    if old_prot & PROT_WRITE_FLAG != 0 {
+        return Ok(f());
+    }
+    set_mem_protect(addr, len, old_prot | PROT_READ_FLAG | PROT_WRITE_FLAG)
+        .map_err(|_| Errno::SetProt)?;
+    let result = f();
+    restore_mem_protect(addr, len, old_prot);
+    Ok(result)
+}
+
+// 恢复写入前记录的保护，失败时页面保持可写，记录后继续
+pub fn restore_mem_protect(addr: usize, len: usize, old_prot: u32) {
+    if set_mem_protect(addr, len, old_prot).is_err() {
+        PROTECT_RESTORE_FAILURES.fetch_add(1, Ordering::Relaxed);
+        log::warn(format_args!(
+            "restore protection 0x{:x} at 0x{:x} failed, page left writable",
+            old_prot, addr
+        ));
+    }
+}
+
+// 修改覆盖 [addr, addr + len) 的全部页面的保护属性
+fn set_mem_protect(addr: usize, len: usize, prot: u32) -> Result<(), Errno> {
+    let (start, len) = page_bounds(addr, len);
+    MPROTECT_CALLS.fetch_add(1, Ordering::Relaxed);
+    if let Err(err) = mprotect::mprotect(start, len, prot as i32) {
         log::error(format_args!("mprotect failed: {err}"));
         return Err(Errno::Unknown);
     }
     Ok(())
 }
 
+// 不分配、不加锁、不写日志地写入一个指针大小的值，供 fork 子进程的 atfork 回调使用
+// prot 为页面当前保护，不含写权限时经原始系统调用临时追加写权限，写入后恢复
+pub fn write_usize_raw(addr: usize, value: usize, prot: u32) -> bool {
+    let (start, len) = page_bounds(addr, std::mem::size_of::<usize>());
+    let writable = prot & PROT_WRITE_FLAG != 0;
+    let writable_prot = prot | PROT_READ_FLAG | PROT_WRITE_FLAG;
+    if !writable && !mprotect::raw_mprotect(start, len, writable_prot as i32) {
+        return false;
+    }
+    unsafe { std::ptr::write_volatile(addr as *mut usize, value) };
+    if !writable {
+        let _ = mprotect::raw_mprotect(start, len, prot as i32);
+    }
+    true
+}
+
+// 始终经原始系统调用修改保护，绕过可能被其他框架 hook 的 libc mprotect（默认关闭）
+pub fn set_prefer_syscall_mprotect(flag: bool) {
+    mprotect::set_prefer_syscall(flag);
+}
+
+// 为匿名映射设置 /proc/self/maps 中显示的名称（[anon:<name>]），便于 tombstone 排查
+// 部分旧内核只保存名称指针而不复制，name 必须在映射存续期间保持有效
+// 内核不支持时（ENOSYS / EINVAL）静默忽略，命名失败不影响映射本身
+pub fn name_anon_region(addr: usize, len: usize, name: &'static CStr) {
+    let result = unsafe {
+        libc::prctl(
+            PR_SET_VMA,
+            PR_SET_VMA_ANON_NAME as libc::c_ulong,
+            addr as libc::c_ulong,
+            len as libc::c_ulong,
+            name.as_ptr() as libc::c_ulong,
+        )
+    };
+    if result != 0 {
+        let err = std::io::Error::last_os_error().raw_os_error().unwrap_or(0);
+        if err != libc::ENOSYS && err != libc::EINVAL {
+            log::debug(format_args!("name anon region 0x{addr:x} failed: {err}"));
+        }
+    }
+}
+
 // 单地址指令缓存刷新，仅发出内存屏障
 pub fn flush_instruction_cache(addr: usize) {
     let _ = addr;
@@ -195,15 +460,20 @@ fn page_size() -> usize {
     unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
 }
 
-// 计算地址所在页面的起始地址和覆盖长度（页对齐）
-fn page_bounds(addr: usize) -> (usize, usize) {
+// 地址所在页面的起始地址
+pub fn page_start(addr: usize) -> usize {
+    page_bounds(addr, 1).0
+}
+
+// 计算覆盖 [addr, addr + len) 的页面起始地址和长度（页对齐）
+fn page_bounds(addr: usize, len: usize) -> (usize, usize) {
     let page_size = page_size();
     if page_size == 0 {
-        return (addr, std::mem::size_of::<usize>());
+        return (addr, len);
     }
     let page_mask = !(page_size - 1);
     let start = addr & page_mask;
-    let end = (addr + std::mem::size_of::<usize>() - 1) & page_mask;
+    let end = (addr + len.max(1) - 1) & page_mask;
     let end = end + page_size;
     (start, end - start)
 }
@@ -234,3 +504,31 @@ fn normalize_maps_pathname(pathname: &str) -> &str {
         None => pathname,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{canonical_code_addr, strip_addr_tag, strip_pac};
+
+    #[test]
+    #[cfg(target_arch = "aarch64")]
+    fn strip_addr_tag_masks_top_byte() {
+        assert_eq!(strip_addr_tag(0xb400_007f_1234_5678), 0x0000_007f_1234_5678);
+        assert_eq!(strip_addr_tag(0x0f00_0000_0000_1000), 0x1000);
+        assert_eq!(strip_addr_tag(0x7f_1234_5678), 0x7f_1234_5678);
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "aarch64"))]
+    fn strip_addr_tag_is_identity_off_aarch64() {
+        assert_eq!(strip_addr_tag(0xb400_007f_1234_5678), 0xb400_007f_1234_5678);
+        assert_eq!(strip_addr_tag(0x7f_1234_5678), 0x7f_1234_5678);
+    }
+
+    // PAC 与非 PAC 设备上未签名的地址都应原样保留
+    #[test]
+    fn strip_pac_keeps_unsigned_code_addresses() {
+        let addr = strip_pac_keeps_unsigned_code_addresses as fn() as usize;
+        assert_eq!(strip_pac(addr), addr);
+        assert_eq!(canonical_code_addr(addr), addr);
+    }
+}
@@ -0,0 +1,200 @@
+// mprotect 的调用路径：优先经 dlsym 解析到的 libc 导出函数，失败时改用原始系统调用
+// 其他框架可能 hook 各模块（含本库）的 libc mprotect，其代理偶尔会拒绝改动
+
+use crate::log;
+use std::ffi::c_void;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+type MprotectFn = unsafe extern "C" fn(*mut c_void, libc::size_t, libc::c_int) -> libc::c_int;
+
+// 原始系统调用的 EINTR 重试上限
+const EINTR_RETRY_LIMIT: usize = 4;
+
+static PREFER_SYSCALL: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum MprotectPath {
+    Libc,
+    Syscall,
+}
+
+pub(super) fn set_prefer_syscall(flag: bool) {
+    PREFER_SYSCALL.store(flag, Ordering::Relaxed);
+}
+
+// 成功时返回实际生效的路径，失败时返回最后一次的 errno
+pub(super) fn mprotect(start: usize, len: usize, prot: i32) -> Result<MprotectPath, i32> {
+    mprotect_with(
+        libc_mprotect_fn(),
+        syscall_mprotect,
+        PREFER_SYSCALL.load(Ordering::Relaxed),
+        start,
+        len,
+        prot,
+    )
+}
+
+// dlsym 拿到的是 libc 的真实导出地址，不经过本模块可能已被改写的 GOT
+fn libc_mprotect_fn() -> MprotectFn {
+    static LIBC_FN: OnceLock<MprotectFn> = OnceLock::new();
+    *LIBC_FN.get_or_init(|| {
+        let ptr = unsafe { libc::dlsym(libc::RTLD_DEFAULT, c"mprotect".as_ptr()) };
+        if ptr.is_null() {
+            return libc::mprotect;
+        }
+        unsafe { std::mem::transmute::<*mut c_void, MprotectFn>(ptr) }
+    })
+}
+
+// 只发出原始系统调用，不重试、不写日志，供 fork 子进程等不能分配内存的上下文使用
+pub(super) fn raw_mprotect(start: usize, len: usize, prot: i32) -> bool {
+    unsafe { syscall_mprotect(start as *mut c_void, len, prot) == 0 }
+}
+
+unsafe extern "C" fn syscall_mprotect(
+    addr: *mut c_void,
+    len: libc::size_t,
+    prot: libc::c_int,
+) -> libc::c_int {
+    unsafe { libc::syscall(libc::SYS_mprotect, addr, len, prot) as libc::c_int }
+}
+
+// 范围未映射或参数非法属于内核给出的结论，原始系统调用同样会失败，不再重试
+fn is_kernel_rejection(err: i32) -> bool {
+    matches!(err, libc::ENOMEM | libc::EINVAL)
+}
+
+fn mprotect_with(
+    libc_fn: MprotectFn,
+    syscall_fn: MprotectFn,
+    prefer_syscall: bool,
+    start: usize,
+    len: usize,
+    prot: i32,
+) -> Result<MprotectPath, i32> {
+    if !prefer_syscall {
+        match call(libc_fn, start, len, prot) {
+            Ok(()) => return Ok(MprotectPath::Libc),
+            Err(err) if is_kernel_rejection(err) => return Err(err),
+            Err(err) => log::debug(format_args!(
+                "libc mprotect 0x{start:x} failed: {err}, retrying with raw syscall"
+            )),
+        }
+    }
+
+    let mut retries = 0;
+    loop {
+        match call(syscall_fn, start, len, prot) {
+            Ok(()) => {
+                if !prefer_syscall {
+                    log::info(format_args!(
+                        "mprotect 0x{start:x} succeeded via raw syscall after libc failure"
+                    ));
+                }
+                return Ok(MprotectPath::Syscall);
+            }
+            Err(libc::EINTR) if retries < EINTR_RETRY_LIMIT => retries += 1,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn call(f: MprotectFn, start: usize, len: usize, prot: i32) -> Result<(), i32> {
+    if unsafe { f(start as *mut c_void, len, prot) } == 0 {
+        return Ok(());
+    }
+    Err(std::io::Error::last_os_error().raw_os_error().unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EINTR_RETRY_LIMIT, MprotectPath, mprotect_with};
+    use std::ffi::c_void;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn fail_with(err: i32) -> libc::c_int {
+        #[cfg(target_os = "android")]
+        unsafe {
+            *libc::__errno() = err
+        };
+        #[cfg(not(target_os = "android"))]
+        unsafe {
+            *libc::__errno_location() = err
+        };
+        -1
+    }
+
+    unsafe extern "C" fn succeed(_: *mut c_void, _: libc::size_t, _: libc::c_int) -> libc::c_int {
+        0
+    }
+
+    unsafe extern "C" fn reject_eperm(
+        _: *mut c_void,
+        _: libc::size_t,
+        _: libc::c_int,
+    ) -> libc::c_int {
+        fail_with(libc::EPERM)
+    }
+
+    unsafe extern "C" fn reject_enomem(
+        _: *mut c_void,
+        _: libc::size_t,
+        _: libc::c_int,
+    ) -> libc::c_int {
+        fail_with(libc::ENOMEM)
+    }
+
+    static EINTR_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    unsafe extern "C" fn always_eintr(
+        _: *mut c_void,
+        _: libc::size_t,
+        _: libc::c_int,
+    ) -> libc::c_int {
+        EINTR_CALLS.fetch_add(1, Ordering::Relaxed);
+        fail_with(libc::EINTR)
+    }
+
+    #[test]
+    fn libc_success_skips_syscall() {
+        assert_eq!(
+            mprotect_with(succeed, reject_eperm, false, 0x1000, 0x1000, 0),
+            Ok(MprotectPath::Libc)
+        );
+    }
+
+    // 被 hook 的 libc mprotect 拒绝时改走原始系统调用
+    #[test]
+    fn unexpected_libc_error_falls_back_to_syscall() {
+        assert_eq!(
+            mprotect_with(reject_eperm, succeed, false, 0x1000, 0x1000, 0),
+            Ok(MprotectPath::Syscall)
+        );
+    }
+
+    #[test]
+    fn kernel_rejection_is_not_retried() {
+        assert_eq!(
+            mprotect_with(reject_enomem, succeed, false, 0x1000, 0x1000, 0),
+            Err(libc::ENOMEM)
+        );
+    }
+
+    #[test]
+    fn prefer_syscall_bypasses_libc() {
+        assert_eq!(
+            mprotect_with(reject_eperm, succeed, true, 0x1000, 0x1000, 0),
+            Ok(MprotectPath::Syscall)
+        );
+    }
+
+    #[test]
+    fn syscall_eintr_retry_is_bounded() {
+        assert_eq!(
+            mprotect_with(reject_eperm, always_eintr, false, 0x1000, 0x1000, 0),
+            Err(libc::EINTR)
+        );
+        assert_eq!(EINTR_CALLS.load(Ordering::Relaxed), EINTR_RETRY_LIMIT + 1);
+    }
+}
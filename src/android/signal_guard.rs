@@ -1,6 +1,7 @@
-// 信号守卫模块：拦截 SIGSEGV/SIGBUS 并通过 sigsetjmp/siglongjmp 安全恢复
+// 信号守卫模块：拦截 SIGSEGV/SIGBUS（可选 SIGILL/SIGTRAP）并通过 sigsetjmp/siglongjmp 安全恢复
 // 支持 sigchain（ART 环境）和 sigaction 两种安装模式
 
+use crate::api::SignalHandlerMode;
 use crate::errno::Errno;
 use crate::log;
 use crate::runtime::MutexPoisonRecover;
@@ -8,13 +9,15 @@ use std::cell::UnsafeCell;
 use std::ffi::{CStr, c_void};
 use std::mem::MaybeUninit;
 use std::ptr;
-use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Mutex, OnceLock};
 
 // 预分配的静态 slot 数量上限
 const GUARD_BASE_SLOT_MAX: usize = 64;
 // 单线程最大嵌套守卫深度
 const GUARD_STACK_DEPTH_MAX: usize = 8;
+// sigchain 模式下周期性重新提升到链首的最小间隔（毫秒）
+const FRONT_REASSERT_INTERVAL_MS: u64 = 30_000;
 // 备用信号栈最小尺寸（64KB）
 const SIGALTSTACK_MIN_SIZE: usize = 64 * 1024;
 // SA_EXPOSE_TAGBITS: 允许信号处理器看到 MTE tag 位
@@ -38,12 +41,23 @@ const SIGCHAIN_LIB_CANDIDATES: [&CStr; 7] = [
 
 // 信号守卫全局开关
 static SIGSEGV_ENABLE: AtomicBool = AtomicBool::new(true);
+// SIGILL/SIGTRAP 守卫开关，默认关闭以免与调试器的断点处理冲突
+static EXTENDED_ENABLE: AtomicBool = AtomicBool::new(false);
+// SIGILL/SIGTRAP 处理器当前是否已安装，随主处理器同模式安装与卸载
+static EXTENDED_INSTALLED: AtomicBool = AtomicBool::new(false);
 // 信号处理器引用计数，支持多次 add/remove 配对
 static HANDLER_REF_COUNT: AtomicUsize = AtomicUsize::new(0);
 static HANDLER_INSTALL_MODE: AtomicUsize = AtomicUsize::new(HANDLER_MODE_NONE);
+// ART 重新注册 fault manager 后守卫可能落到链后，默认周期性调用 EnsureFrontOfChain 重新提升
+static FRONT_REASSERT_ENABLE: AtomicBool = AtomicBool::new(true);
+// 重新提升的累计次数与最近一次的单调时钟毫秒，0 表示尚未发生
+static FRONT_REASSERT_COUNT: AtomicU64 = AtomicU64::new(0);
+static FRONT_REASSERT_LAST_MS: AtomicU64 = AtomicU64::new(0);
 // 保存被替换的原始 sigaction，用于链式转发和卸载恢复
 static SIGSEGV_OLD_ACTION: AtomicPtr<libc::sigaction> = AtomicPtr::new(ptr::null_mut());
 static SIGBUS_OLD_ACTION: AtomicPtr<libc::sigaction> = AtomicPtr::new(ptr::null_mut());
+static SIGILL_OLD_ACTION: AtomicPtr<libc::sigaction> = AtomicPtr::new(ptr::null_mut());
+static SIGTRAP_OLD_ACTION: AtomicPtr<libc::sigaction> = AtomicPtr::new(ptr::null_mut());
 
 // 动态解析的 libc 信号函数指针类型
 type SigActionFn = unsafe extern "C" fn(
@@ -84,14 +98,23 @@ struct SigchainApiFns {
 
 // sigjmp_buf 的字长度，取决于目标架构
 const fn sigjmp_buf_words() -> usize {
-    #[cfg(target_arch = "aarch64")]
+    #[cfg(all(target_os = "android", target_arch = "aarch64"))]
     {
         33
     }
-    #[cfg(target_arch = "x86_64")]
+    #[cfg(all(target_os = "android", target_arch = "x86_64"))]
     {
         12
     }
+    // 宿主机测试构建按 glibc 的 sigjmp_buf 大小
+    #[cfg(all(not(target_os = "android"), target_arch = "aarch64"))]
+    {
+        39
+    }
+    #[cfg(all(not(target_os = "android"), target_arch = "x86_64"))]
+    {
+        25
+    }
     #[cfg(not(any(target_arch = "aarch64", target_arch = "x86_64")))]
     {
         65
@@ -110,6 +133,10 @@ struct GuardSlot {
     active: AtomicBool,
     depth: AtomicUsize,
     last_signal: AtomicUsize,
+    // 最近一次捕获信号的 si_addr
+    fault_addr: AtomicUsize,
+    // 每层嵌套守卫的阶段标签（GuardPhase 的取值）
+    phases: [AtomicUsize; GUARD_STACK_DEPTH_MAX],
     env_stack: [UnsafeCell<MaybeUninit<SigJmpBuf>>; GUARD_STACK_DEPTH_MAX],
 }
 
@@ -122,6 +149,8 @@ impl GuardSlot {
             active: AtomicBool::new(false),
             depth: AtomicUsize::new(0),
             last_signal: AtomicUsize::new(0),
+            fault_addr: AtomicUsize::new(0),
+            phases: [const { AtomicUsize::new(0) }; GUARD_STACK_DEPTH_MAX],
             env_stack: [const { UnsafeCell::new(MaybeUninit::uninit()) }; GUARD_STACK_DEPTH_MAX],
         }
     }
@@ -132,6 +161,58 @@ impl GuardSlot {
     }
 }
 
+// with_guard_tagged 的调用阶段标签，捕获的访问异常按阶段计数
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GuardPhase {
+    Other = 0,
+    ElfInit = 1,
+    ElfLookup = 2,
+    CollectSlot = 3,
+    PatchSlot = 4,
+    SafeRead = 5,
+    ModuleIdentity = 6,
+    Symbolize = 7,
+    Inspect = 8,
+    CfiScan = 9,
+    CfiPatch = 10,
+}
+
+impl GuardPhase {
+    const ALL: [GuardPhase; 11] = [
+        GuardPhase::Other,
+        GuardPhase::ElfInit,
+        GuardPhase::ElfLookup,
+        GuardPhase::CollectSlot,
+        GuardPhase::PatchSlot,
+        GuardPhase::SafeRead,
+        GuardPhase::ModuleIdentity,
+        GuardPhase::Symbolize,
+        GuardPhase::Inspect,
+        GuardPhase::CfiScan,
+        GuardPhase::CfiPatch,
+    ];
+
+    fn from_index(index: usize) -> GuardPhase {
+        Self::ALL.get(index).copied().unwrap_or(GuardPhase::Other)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GuardPhase::Other => "other",
+            GuardPhase::ElfInit => "elf_init",
+            GuardPhase::ElfLookup => "elf_lookup",
+            GuardPhase::CollectSlot => "collect_slot",
+            GuardPhase::PatchSlot => "patch_slot",
+            GuardPhase::SafeRead => "safe_read",
+            GuardPhase::ModuleIdentity => "module_identity",
+            GuardPhase::Symbolize => "symbolize",
+            GuardPhase::Inspect => "inspect",
+            GuardPhase::CfiScan => "cfi_scan",
+            GuardPhase::CfiPatch => "cfi_patch",
+        }
+    }
+}
+
 // 动态扩展的 slot 链表节点，当静态 slot 耗尽时使用
 struct GuardNode {
     slot: GuardSlot,
@@ -154,17 +235,22 @@ static GUARD_BASE_SLOTS: [GuardSlot; GUARD_BASE_SLOT_MAX] =
 static GUARD_EXTRA_HEAD: AtomicPtr<GuardNode> = AtomicPtr::new(ptr::null_mut());
 
 unsafe extern "C" {
+    // glibc 的 sigsetjmp 是宏，实际符号为 __sigsetjmp
+    #[cfg_attr(not(target_os = "android"), link_name = "__sigsetjmp")]
     fn sigsetjmp(env: *mut SigJmpBuf, savemask: libc::c_int) -> libc::c_int;
     fn siglongjmp(env: *mut SigJmpBuf, val: libc::c_int) -> !;
 }
 
 mod abi;
 mod altstack;
+mod fault;
 mod guard_exec;
 mod handlers;
 mod sigchain;
 mod slot;
 
+pub use fault::GuardFaultEntry;
+
 fn handler_lock() -> &'static Mutex<()> {
     static HANDLER_LOCK: OnceLock<Mutex<()>> = OnceLock::new();
     HANDLER_LOCK.get_or_init(|| Mutex::new(()))
@@ -184,6 +270,14 @@ pub(super) fn load_sigbus_old_action() -> *const libc::sigaction {
     SIGBUS_OLD_ACTION.load(Ordering::Acquire) as *const libc::sigaction
 }
 
+pub(super) fn load_sigill_old_action() -> *const libc::sigaction {
+    SIGILL_OLD_ACTION.load(Ordering::Acquire) as *const libc::sigaction
+}
+
+pub(super) fn load_sigtrap_old_action() -> *const libc::sigaction {
+    SIGTRAP_OLD_ACTION.load(Ordering::Acquire) as *const libc::sigaction
+}
+
 pub fn enable(flag: bool) {
     SIGSEGV_ENABLE.store(flag, Ordering::SeqCst);
 }
@@ -192,6 +286,155 @@ pub fn is_enabled() -> bool {
     SIGSEGV_ENABLE.load(Ordering::SeqCst)
 }
 
+// 开关 SIGILL/SIGTRAP 守卫；主处理器已安装时立即按当前模式安装或卸载
+pub fn enable_extended(flag: bool) {
+    let _handler_lock = handler_lock().lock_or_poison();
+    EXTENDED_ENABLE.store(flag, Ordering::SeqCst);
+    if HANDLER_REF_COUNT.load(Ordering::Acquire) == 0 {
+        return;
+    }
+    if flag {
+        install_extended_handlers(HANDLER_INSTALL_MODE.load(Ordering::Acquire));
+    } else {
+        remove_extended_handlers(HANDLER_INSTALL_MODE.load(Ordering::Acquire));
+    }
+}
+
+pub fn is_extended_enabled() -> bool {
+    EXTENDED_ENABLE.load(Ordering::SeqCst)
+}
+
+// 安装失败只告警，不影响 SIGSEGV/SIGBUS 守卫；调用方需持有 handler_lock
+fn install_extended_handlers(mode: usize) {
+    if EXTENDED_INSTALLED.load(Ordering::Acquire) {
+        return;
+    }
+    let installed = match mode {
+        HANDLER_MODE_SIGCHAIN => sigchain::install_extended_sigchain_handlers(),
+        HANDLER_MODE_SIGACTION => install_extended_sigactions(),
+        _ => false,
+    };
+    if installed {
+        EXTENDED_INSTALLED.store(true, Ordering::Release);
+        log::debug(format_args!("SIGILL/SIGTRAP guard handlers installed"));
+    } else {
+        log::warn(format_args!("SIGILL/SIGTRAP guard handlers install failed"));
+    }
+}
+
+fn install_extended_sigactions() -> bool {
+    unsafe {
+        let mut act: libc::sigaction = std::mem::zeroed();
+        act.sa_sigaction = handlers::sigill_handler as *const () as usize;
+        act.sa_flags = libc::SA_SIGINFO | libc::SA_ONSTACK | libc::SA_RESTART;
+        libc::sigfillset(&mut act.sa_mask);
+
+        let mut old_ill: libc::sigaction = std::mem::zeroed();
+        if abi::raw_sigaction(libc::SIGILL, &act, &mut old_ill) != 0 {
+            return false;
+        }
+
+        act.sa_sigaction = handlers::sigtrap_handler as *const () as usize;
+        let mut old_trap: libc::sigaction = std::mem::zeroed();
+        if abi::raw_sigaction(libc::SIGTRAP, &act, &mut old_trap) != 0 {
+            let _ = abi::raw_sigaction(libc::SIGILL, &old_ill, std::ptr::null_mut());
+            return false;
+        }
+
+        store_old_action(&SIGILL_OLD_ACTION, old_ill);
+        store_old_action(&SIGTRAP_OLD_ACTION, old_trap);
+    }
+    true
+}
+
+fn remove_extended_handlers(mode: usize) {
+    if !EXTENDED_INSTALLED.swap(false, Ordering::AcqRel) {
+        return;
+    }
+    if mode == HANDLER_MODE_SIGCHAIN {
+        let _ = sigchain::remove_extended_sigchain_handlers();
+    } else if mode == HANDLER_MODE_SIGACTION {
+        unsafe {
+            let old_ill = load_sigill_old_action();
+            if !old_ill.is_null() {
+                let _ = abi::raw_sigaction(libc::SIGILL, old_ill, std::ptr::null_mut());
+            }
+            let old_trap = load_sigtrap_old_action();
+            if !old_trap.is_null() {
+                let _ = abi::raw_sigaction(libc::SIGTRAP, old_trap, std::ptr::null_mut());
+            }
+        }
+    }
+}
+
+// 进入守卫时据此决定是否一并临时屏蔽 SIGILL/SIGTRAP
+fn extended_installed() -> bool {
+    EXTENDED_INSTALLED.load(Ordering::Acquire)
+}
+
+pub fn enable_front_reassert(flag: bool) {
+    FRONT_REASSERT_ENABLE.store(flag, Ordering::SeqCst);
+}
+
+pub fn front_reassert_count() -> u64 {
+    FRONT_REASSERT_COUNT.load(Ordering::Relaxed)
+}
+
+fn monotonic_ms() -> u64 {
+    let mut ts: libc::timespec = unsafe { std::mem::zeroed() };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts);
+    }
+    (ts.tv_sec as u64)
+        .saturating_mul(1000)
+        .saturating_add(ts.tv_nsec as u64 / 1_000_000)
+}
+
+// sigchain 模式下重新把守卫 handler 提升到链首，force 为 false 时按 FRONT_REASSERT_INTERVAL_MS 限频
+// 持有 handler_lock 检查引用计数，与 remove_handler 串行，卸载后不会再次提升
+pub fn reassert_front_of_chain(force: bool) -> bool {
+    if !FRONT_REASSERT_ENABLE.load(Ordering::SeqCst)
+        || HANDLER_INSTALL_MODE.load(Ordering::Acquire) != HANDLER_MODE_SIGCHAIN
+    {
+        return false;
+    }
+    let now = monotonic_ms();
+    let last = FRONT_REASSERT_LAST_MS.load(Ordering::Acquire);
+    if !force && last != 0 && now.saturating_sub(last) < FRONT_REASSERT_INTERVAL_MS {
+        return false;
+    }
+
+    let _handler_lock = handler_lock().lock_or_poison();
+    if HANDLER_REF_COUNT.load(Ordering::Acquire) == 0
+        || HANDLER_INSTALL_MODE.load(Ordering::Acquire) != HANDLER_MODE_SIGCHAIN
+    {
+        return false;
+    }
+    let reasserted = if extended_installed() {
+        sigchain::ensure_front_of_chain(&[libc::SIGSEGV, libc::SIGBUS, libc::SIGILL, libc::SIGTRAP])
+    } else {
+        sigchain::ensure_front_of_chain(&[libc::SIGSEGV, libc::SIGBUS])
+    };
+    if !reasserted {
+        return false;
+    }
+    FRONT_REASSERT_LAST_MS.store(now.max(1), Ordering::Release);
+    let count = FRONT_REASSERT_COUNT.fetch_add(1, Ordering::Relaxed) + 1;
+    log::debug(format_args!(
+        "sigchain handlers reasserted front of chain force={} count={}",
+        force, count
+    ));
+    true
+}
+
+pub fn handler_mode() -> SignalHandlerMode {
+    match HANDLER_INSTALL_MODE.load(Ordering::Acquire) {
+        HANDLER_MODE_SIGCHAIN => SignalHandlerMode::Sigchain,
+        HANDLER_MODE_SIGACTION => SignalHandlerMode::Sigaction,
+        _ => SignalHandlerMode::None,
+    }
+}
+
 // 安装信号处理器，引用计数管理
 // 优先尝试 sigchain 模式（ART 环境），失败则回退 sigaction
 pub fn add_handler() -> Result<(), Errno> {
@@ -210,6 +453,9 @@ pub fn add_handler() -> Result<(), Errno> {
         HANDLER_INSTALL_MODE.store(HANDLER_MODE_SIGCHAIN, Ordering::Release);
         HANDLER_REF_COUNT.store(1, Ordering::Release);
         log::debug(format_args!("signal handlers installed via sigchain"));
+        if is_extended_enabled() {
+            install_extended_handlers(HANDLER_MODE_SIGCHAIN);
+        }
         return Ok(());
     }
 
@@ -238,10 +484,13 @@ pub fn add_handler() -> Result<(), Errno> {
     HANDLER_INSTALL_MODE.store(HANDLER_MODE_SIGACTION, Ordering::Release);
     HANDLER_REF_COUNT.store(1, Ordering::Release);
     log::debug(format_args!("signal handlers installed via sigaction"));
+    if is_extended_enabled() {
+        install_extended_handlers(HANDLER_MODE_SIGACTION);
+    }
     Ok(())
 }
 
-// 卸载信号处理器，引用计数归零时恢复原始 handler
+// 卸载信号处理器，引用计数归零时恢复原始 handler（含已安装的 SIGILL/SIGTRAP）
 pub fn remove_handler() {
     let _handler_lock = handler_lock().lock_or_poison();
     let current_count = HANDLER_REF_COUNT.load(Ordering::Acquire);
@@ -254,6 +503,7 @@ pub fn remove_handler() {
     }
 
     let mode = HANDLER_INSTALL_MODE.load(Ordering::Acquire);
+    remove_extended_handlers(mode);
     if mode == HANDLER_MODE_SIGCHAIN {
         let _ = sigchain::remove_sigchain_handlers();
     } else if mode == HANDLER_MODE_SIGACTION {
@@ -272,10 +522,38 @@ pub fn remove_handler() {
     HANDLER_REF_COUNT.store(0, Ordering::Release);
 }
 
+// 逐次释放引用直至归零，恢复原始 handler
+pub fn remove_all_handlers() {
+    while HANDLER_REF_COUNT.load(Ordering::Acquire) > 0 {
+        remove_handler();
+    }
+}
+
+pub fn slot_stats() -> crate::api::GuardSlotStats {
+    slot::slot_stats()
+}
+
+pub fn fault_stats() -> crate::api::GuardFaultStats {
+    fault::fault_stats()
+}
+
+// 序号大于 seq 的近期访问异常，按序号升序
+pub fn faults_after(seq: u64) -> Vec<GuardFaultEntry> {
+    fault::faults_after(seq)
+}
+
 // 在信号守卫保护下执行闭包，捕获 SIGSEGV/SIGBUS 后返回 Err
 pub fn with_guard<T, F>(f: F) -> Result<T, Errno>
 where
     F: FnOnce() -> T,
 {
-    guard_exec::with_guard_impl(f)
+    guard_exec::with_guard_impl(GuardPhase::Other, f)
+}
+
+// 同 with_guard，捕获到的访问异常计入 phase
+pub fn with_guard_tagged<T, F>(phase: GuardPhase, f: F) -> Result<T, Errno>
+where
+    F: FnOnce() -> T,
+{
+    guard_exec::with_guard_impl(phase, f)
 }
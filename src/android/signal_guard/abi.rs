@@ -233,13 +233,17 @@ pub(super) unsafe fn raw_sigprocmask(
     if syscall_result == 0 { 0 } else { result }
 }
 
-// 临时屏蔽 SIGSEGV 和 SIGBUS，返回之前的信号掩码
+// 临时屏蔽 SIGSEGV 和 SIGBUS（扩展守卫已安装时还有 SIGILL/SIGTRAP），返回之前的信号掩码
 pub(super) fn block_guard_signals(prev_mask: &mut libc::sigset_t) -> Result<(), Errno> {
     let mut block_mask: libc::sigset_t = unsafe { std::mem::zeroed() };
     unsafe {
         libc::sigemptyset(&mut block_mask);
         libc::sigaddset(&mut block_mask, libc::SIGSEGV);
         libc::sigaddset(&mut block_mask, libc::SIGBUS);
+        if super::extended_installed() {
+            libc::sigaddset(&mut block_mask, libc::SIGILL);
+            libc::sigaddset(&mut block_mask, libc::SIGTRAP);
+        }
         if raw_sigprocmask(libc::SIG_BLOCK, &block_mask, prev_mask) != 0 {
             return Err(Errno::SegvErr);
         }
@@ -276,7 +280,7 @@ pub(super) fn read_ucontext_sigmask(ucontext: *mut libc::c_void) -> libc::sigset
         return mask;
     }
 
-    #[cfg(target_arch = "x86_64")]
+    #[cfg(all(target_arch = "x86_64", target_os = "android"))]
     {
         let context = unsafe { &*(ucontext as *const libc::ucontext_t) };
         let copy_len =
@@ -290,7 +294,8 @@ pub(super) fn read_ucontext_sigmask(ucontext: *mut libc::c_void) -> libc::sigset
         }
     }
 
-    #[cfg(not(target_arch = "x86_64"))]
+    // 宿主机测试构建的 glibc ucontext_t 只有 uc_sigmask
+    #[cfg(not(all(target_arch = "x86_64", target_os = "android")))]
     {
         let context = unsafe { &*(ucontext as *const libc::ucontext_t) };
         mask = unsafe { std::ptr::read(std::ptr::addr_of!(context.uc_sigmask)) };
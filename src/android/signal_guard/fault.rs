@@ -0,0 +1,122 @@
+// 守卫捕获的访问异常统计：总数、按阶段计数与最近若干次的环形缓冲区
+// 写入发生在信号处理器中，只使用预分配的静态原子变量
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use super::GuardPhase;
+use crate::api::{GuardFault, GuardFaultStats};
+
+// 环形缓冲区保留的最近访问异常数
+const FAULT_RING_SIZE: usize = 16;
+
+// seq 为 0 表示条目为空或正在写入
+struct FaultSlot {
+    seq: AtomicU64,
+    ts_ms: AtomicU64,
+    signal: AtomicUsize,
+    addr: AtomicUsize,
+    phase: AtomicUsize,
+}
+
+impl FaultSlot {
+    const fn new() -> Self {
+        Self {
+            seq: AtomicU64::new(0),
+            ts_ms: AtomicU64::new(0),
+            signal: AtomicUsize::new(0),
+            addr: AtomicUsize::new(0),
+            phase: AtomicUsize::new(0),
+        }
+    }
+}
+
+// 累计捕获数，同时作为环形缓冲区的写入序号
+static FAULT_TOTAL: AtomicU64 = AtomicU64::new(0);
+static FAULT_BY_PHASE: [AtomicU64; GuardPhase::ALL.len()] =
+    [const { AtomicU64::new(0) }; GuardPhase::ALL.len()];
+static FAULT_RING: [FaultSlot; FAULT_RING_SIZE] = [const { FaultSlot::new() }; FAULT_RING_SIZE];
+
+// 环形缓冲区中的一次访问异常，seq 从 1 开始递增
+pub struct GuardFaultEntry {
+    pub seq: u64,
+    pub ts_ms: u64,
+    pub fault: GuardFault,
+}
+
+// 信号上下文中调用：clock_gettime 与原子操作均为 async-signal-safe
+pub(super) fn record_fault(signal: usize, addr: usize, phase: usize) {
+    let seq = FAULT_TOTAL.fetch_add(1, Ordering::AcqRel) + 1;
+    let phase = GuardPhase::from_index(phase) as usize;
+    FAULT_BY_PHASE[phase].fetch_add(1, Ordering::Relaxed);
+
+    let slot = &FAULT_RING[((seq - 1) as usize) % FAULT_RING_SIZE];
+    slot.seq.store(0, Ordering::Release);
+    slot.ts_ms.store(realtime_ms(), Ordering::Relaxed);
+    slot.signal.store(signal, Ordering::Relaxed);
+    slot.addr.store(addr, Ordering::Relaxed);
+    slot.phase.store(phase, Ordering::Relaxed);
+    slot.seq.store(seq, Ordering::Release);
+}
+
+fn realtime_ms() -> u64 {
+    let mut ts: libc::timespec = unsafe { std::mem::zeroed() };
+    if unsafe { libc::clock_gettime(libc::CLOCK_REALTIME, &mut ts) } != 0 {
+        return 0;
+    }
+    ts.tv_sec as u64 * 1000 + ts.tv_nsec as u64 / 1_000_000
+}
+
+// 读取前后 seq 一致才视为完整条目，写入中或被覆盖的条目跳过
+fn ring_snapshot() -> Vec<GuardFaultEntry> {
+    let mut entries: Vec<GuardFaultEntry> = FAULT_RING
+        .iter()
+        .filter_map(|slot| {
+            let seq = slot.seq.load(Ordering::Acquire);
+            if seq == 0 {
+                return None;
+            }
+            let ts_ms = slot.ts_ms.load(Ordering::Relaxed);
+            let signal = slot.signal.load(Ordering::Relaxed);
+            let addr = slot.addr.load(Ordering::Relaxed);
+            let phase = slot.phase.load(Ordering::Relaxed);
+            if slot.seq.load(Ordering::Acquire) != seq {
+                return None;
+            }
+            let (signum, code) = super::slot::decode_signal_info(signal);
+            Some(GuardFaultEntry {
+                seq,
+                ts_ms,
+                fault: GuardFault {
+                    signum,
+                    code,
+                    addr,
+                    phase: GuardPhase::from_index(phase).name(),
+                },
+            })
+        })
+        .collect();
+    entries.sort_unstable_by_key(|entry| entry.seq);
+    entries
+}
+
+pub(super) fn faults_after(seq: u64) -> Vec<GuardFaultEntry> {
+    let mut entries = ring_snapshot();
+    entries.retain(|entry| entry.seq > seq);
+    entries
+}
+
+pub(super) fn fault_stats() -> GuardFaultStats {
+    GuardFaultStats {
+        total: FAULT_TOTAL.load(Ordering::Acquire),
+        by_phase: GuardPhase::ALL
+            .iter()
+            .zip(&FAULT_BY_PHASE)
+            .map(|(phase, count)| (phase.name(), count.load(Ordering::Relaxed)))
+            .filter(|(_, count)| *count != 0)
+            .collect(),
+        recent: ring_snapshot()
+            .into_iter()
+            .map(|entry| entry.fault)
+            .collect(),
+    }
+}
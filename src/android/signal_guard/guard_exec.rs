@@ -8,11 +8,11 @@ use std::sync::atomic::Ordering;
 use super::abi;
 use super::altstack;
 use super::slot;
-use super::{GUARD_STACK_DEPTH_MAX, GuardSlot, sigsetjmp};
+use super::{GUARD_STACK_DEPTH_MAX, GuardPhase, GuardSlot, sigsetjmp};
 
-// 在信号守卫保护下执行闭包
+// 在信号守卫保护下执行闭包，phase 记入当前嵌套层，供信号处理器归类访问异常
 // 流程: 确保备用栈 -> 获取 slot -> 屏蔽信号 -> sigsetjmp -> 执行闭包
-pub(super) fn with_guard_impl<T, F>(f: F) -> Result<T, Errno>
+pub(super) fn with_guard_impl<T, F>(phase: GuardPhase, f: F) -> Result<T, Errno>
 where
     F: FnOnce() -> T,
 {
@@ -99,6 +99,7 @@ where
     }
 
     slot.last_signal.store(0, Ordering::Release);
+    slot.phases[depth].store(phase as usize, Ordering::Release);
     slot.depth.store(depth + 1, Ordering::Release);
     slot.active.store(true, Ordering::Release);
     let _guard_reset = GuardReset { slot, armed: true };
@@ -114,8 +115,11 @@ where
             if signal_info != 0 {
                 let (signum, code) = slot::decode_signal_info(signal_info);
                 log::debug(format_args!(
-                    "guard caught signal signum={} code={}",
-                    signum, code
+                    "guard caught signal signum={} code={} addr=0x{:x} phase={}",
+                    signum,
+                    code,
+                    slot.fault_addr.load(Ordering::Acquire),
+                    phase.name()
                 ));
             }
             Err(Errno::SegvErr)
@@ -1,11 +1,14 @@
-// SIGSEGV/SIGBUS 信号处理器实现
+// SIGSEGV/SIGBUS/SIGILL/SIGTRAP 信号处理器实现
 // 先尝试守卫跳转，失败则链式转发给之前注册的 handler
 
 use std::ptr;
 
 use super::abi;
 use super::slot;
-use super::{load_sigbus_old_action, load_sigsegv_old_action};
+use super::{
+    load_sigbus_old_action, load_sigill_old_action, load_sigsegv_old_action,
+    load_sigtrap_old_action,
+};
 
 // 将信号转发给之前注册的 handler
 // 处理 SA_SIGINFO/SA_NODEFER/SA_RESETHAND 标志，正确切换信号掩码
@@ -150,3 +153,47 @@ pub(super) extern "C" fn sigbus_handler(
         );
     }
 }
+
+// SIGILL 处理入口：扩展守卫开启时安装，与 SIGTRAP 处理器互为对端
+pub(super) extern "C" fn sigill_handler(
+    sig: libc::c_int,
+    info: *mut libc::siginfo_t,
+    ucontext: *mut libc::c_void,
+) {
+    if slot::handle_guard_signal(sig, info) {
+        return;
+    }
+    unsafe {
+        let old = load_sigill_old_action();
+        dispatch_old_handler(
+            sig,
+            info,
+            ucontext,
+            old,
+            sigill_handler as *const () as usize,
+            sigtrap_handler as *const () as usize,
+        );
+    }
+}
+
+// SIGTRAP 处理入口：守卫外的断点等原样转发给之前的 handler（如调试器）
+pub(super) extern "C" fn sigtrap_handler(
+    sig: libc::c_int,
+    info: *mut libc::siginfo_t,
+    ucontext: *mut libc::c_void,
+) {
+    if slot::handle_guard_signal(sig, info) {
+        return;
+    }
+    unsafe {
+        let old = load_sigtrap_old_action();
+        dispatch_old_handler(
+            sig,
+            info,
+            ucontext,
+            old,
+            sigtrap_handler as *const () as usize,
+            sigill_handler as *const () as usize,
+        );
+    }
+}
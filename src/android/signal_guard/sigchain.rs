@@ -128,6 +128,19 @@ fn add_sigchain_handler(signum: libc::c_int, callback: SigchainCallbackFn) -> bo
     true
 }
 
+// 将已注册的 handler 重新提升到链首；未解析到 EnsureFrontOfChain 时返回 false
+pub(super) fn ensure_front_of_chain(signals: &[libc::c_int]) -> bool {
+    let Some(ensure_front) = sigchain_api_fns().and_then(|fns| fns.ensure_front_of_chain_fn) else {
+        return false;
+    };
+    for signum in signals {
+        unsafe {
+            ensure_front(*signum);
+        }
+    }
+    true
+}
+
 // 同时注册 SIGSEGV 和 SIGBUS 的 sigchain handler
 // SIGBUS 注册失败时回滚 SIGSEGV 的注册
 pub(super) fn install_sigchain_handlers() -> bool {
@@ -141,6 +154,29 @@ pub(super) fn install_sigchain_handlers() -> bool {
     true
 }
 
+// 扩展守卫：同时注册 SIGILL 和 SIGTRAP，SIGTRAP 失败时回滚 SIGILL
+pub(super) fn install_extended_sigchain_handlers() -> bool {
+    if !add_sigchain_handler(libc::SIGILL, slot::sigill_sigchain_callback()) {
+        return false;
+    }
+    if !add_sigchain_handler(libc::SIGTRAP, slot::sigtrap_sigchain_callback()) {
+        let _ = remove_extended_sigchain_handlers();
+        return false;
+    }
+    true
+}
+
+pub(super) fn remove_extended_sigchain_handlers() -> bool {
+    let Some(fns) = sigchain_api_fns() else {
+        return false;
+    };
+    unsafe {
+        (fns.remove_special_handler_fn)(libc::SIGILL, slot::sigill_sigchain_callback());
+        (fns.remove_special_handler_fn)(libc::SIGTRAP, slot::sigtrap_sigchain_callback());
+    }
+    true
+}
+
 pub(super) fn remove_sigchain_handlers() -> bool {
     let Some(fns) = sigchain_api_fns() else {
         return false;
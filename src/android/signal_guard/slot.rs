@@ -1,13 +1,36 @@
 // 守卫 slot 的分配、查找与信号跳转逻辑
 // slot 按 tid 归属线程，支持静态数组 + 动态链表两级存储
 
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
 use super::{
     GUARD_BASE_SLOTS, GUARD_EXTRA_HEAD, GUARD_STACK_DEPTH_MAX, GuardNode, GuardSlot,
     SigchainCallbackFn, siglongjmp,
 };
 use super::abi;
+use super::fault;
+use crate::api::GuardSlotStats;
+
+// 每次扩展链表前最多探测的候选 slot 数，限制回收检查的耗时
+const GUARD_RECLAIM_PROBE_MAX: usize = 32;
+
+// 已分配的链表节点数与回收转交的 slot 累计数
+static GUARD_EXTRA_COUNT: AtomicUsize = AtomicUsize::new(0);
+static GUARD_RECYCLED: AtomicU64 = AtomicU64::new(0);
+
+// 依次遍历静态数组和动态链表中的全部 slot
+fn all_slots() -> impl Iterator<Item = &'static GuardSlot> {
+    let mut node = GUARD_EXTRA_HEAD.load(Ordering::Acquire);
+    let extra = std::iter::from_fn(move || {
+        if node.is_null() {
+            return None;
+        }
+        let slot = unsafe { &(*node).slot };
+        node = unsafe { (*node).next.load(Ordering::Acquire) };
+        Some(slot)
+    });
+    GUARD_BASE_SLOTS.iter().chain(extra)
+}
 
 // 在静态数组和动态链表中按 tid 查找已归属的 slot
 fn find_slot_by_tid(tid: usize) -> Option<&'static GuardSlot> {
@@ -48,7 +71,7 @@ fn find_active_slot_by_tid(tid: usize) -> Option<&'static GuardSlot> {
     None
 }
 
-// 为指定 tid 获取 slot：先查已有 -> CAS 抢占空闲 -> 堆分配新节点
+// 为指定 tid 获取 slot：先查已有 -> CAS 抢占空闲 -> 回收已退出线程的 slot -> 堆分配新节点
 // 新节点通过 CAS 无锁插入链表头部
 pub(super) fn acquire_slot(tid: usize) -> Option<&'static GuardSlot> {
     if let Some(slot) = find_slot_by_tid(tid) {
@@ -78,9 +101,14 @@ pub(super) fn acquire_slot(tid: usize) -> Option<&'static GuardSlot> {
         node = unsafe { (*node).next.load(Ordering::Acquire) };
     }
 
+    if let Some(slot) = reclaim_dead_slot(tid) {
+        return Some(slot);
+    }
+
     let node = Box::new(GuardNode::new());
     node.slot.tid.store(tid, Ordering::Release);
     let node_ptr = Box::into_raw(node);
+    GUARD_EXTRA_COUNT.fetch_add(1, Ordering::Relaxed);
 
     loop {
         let head = GUARD_EXTRA_HEAD.load(Ordering::Acquire);
@@ -96,6 +124,62 @@ pub(super) fn acquire_slot(tid: usize) -> Option<&'static GuardSlot> {
     }
 }
 
+// 所属线程已退出却仍被占用的 slot 直接转交给 tid；链表节点只复用不释放，信号处理器可能正在无锁遍历
+// 只考虑 active == false 且 depth == 0 的 slot：其所属线程不在守卫中，不会有信号跳转到它的 env
+fn reclaim_dead_slot(tid: usize) -> Option<&'static GuardSlot> {
+    let pid = unsafe { libc::getpid() };
+    let mut probes = 0usize;
+    for slot in all_slots() {
+        let owner = slot.tid.load(Ordering::Acquire);
+        if owner == 0
+            || owner == tid
+            || slot.active.load(Ordering::Acquire)
+            || slot.depth.load(Ordering::Acquire) != 0
+        {
+            continue;
+        }
+        if probes >= GUARD_RECLAIM_PROBE_MAX {
+            break;
+        }
+        probes += 1;
+        if is_thread_alive(pid, owner) {
+            continue;
+        }
+        if slot
+            .tid
+            .compare_exchange(owner, tid, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+        {
+            slot.last_signal.store(0, Ordering::Release);
+            slot.fault_addr.store(0, Ordering::Release);
+            GUARD_RECYCLED.fetch_add(1, Ordering::Relaxed);
+            return Some(slot);
+        }
+    }
+    None
+}
+
+// tgkill 信号 0 只做存在性检查；只有 ESRCH 视为线程已退出，其他错误按存活处理
+fn is_thread_alive(pid: libc::pid_t, tid: usize) -> bool {
+    let ret = unsafe { libc::syscall(libc::SYS_tgkill, pid, tid as libc::pid_t, 0) };
+    ret == 0 || std::io::Error::last_os_error().raw_os_error() != Some(libc::ESRCH)
+}
+
+pub(super) fn slot_stats() -> GuardSlotStats {
+    let claimed = |slot: &GuardSlot| slot.tid.load(Ordering::Acquire) != 0;
+    let base_claimed = GUARD_BASE_SLOTS.iter().filter(|slot| claimed(slot)).count();
+    GuardSlotStats {
+        base_slots: GUARD_BASE_SLOTS.len(),
+        base_claimed,
+        extra_slots: GUARD_EXTRA_COUNT.load(Ordering::Relaxed),
+        extra_claimed: all_slots()
+            .skip(GUARD_BASE_SLOTS.len())
+            .filter(|slot| claimed(slot))
+            .count(),
+        recycled: GUARD_RECYCLED.load(Ordering::Relaxed),
+    }
+}
+
 // 将信号号和 si_code 编码为单个 usize：高 16 位为 signum，低 16 位为 code
 fn encode_signal_info(signum: libc::c_int, code: libc::c_int) -> usize {
     let signum_u16 = (signum.max(0) as u16) as usize;
@@ -109,7 +193,7 @@ pub(super) fn decode_signal_info(encoded: usize) -> (libc::c_int, libc::c_int) {
     (signum, code)
 }
 
-// 信号处理器核心：查找当前线程的活跃 slot，记录信号信息后 siglongjmp 跳回
+// 信号处理器核心：查找当前线程的活跃 slot，记录信号信息与访问异常统计后 siglongjmp 跳回
 // 在信号上下文中调用，必须是 async-signal-safe
 fn maybe_jump_guard_slot(sig: libc::c_int, info: *mut libc::siginfo_t) -> bool {
     let tid = abi::current_thread_id();
@@ -122,9 +206,15 @@ fn maybe_jump_guard_slot(sig: libc::c_int, info: *mut libc::siginfo_t) -> bool {
     }
     let index = depth - 1;
     unsafe {
-        let code = if info.is_null() { 0 } else { (*info).si_code };
-        slot.last_signal
-            .store(encode_signal_info(sig, code), Ordering::Release);
+        let (code, addr) = if info.is_null() {
+            (0, 0)
+        } else {
+            ((*info).si_code, (*info).si_addr() as usize)
+        };
+        let signal = encode_signal_info(sig, code);
+        slot.last_signal.store(signal, Ordering::Release);
+        slot.fault_addr.store(addr, Ordering::Release);
+        fault::record_fault(signal, addr, slot.phases[index].load(Ordering::Acquire));
         let env = slot.env_ptr(index);
         siglongjmp(env, 1);
     }
@@ -146,6 +236,22 @@ pub(super) extern "C" fn sigbus_sigchain_handler(
     maybe_jump_guard_slot(sig, info)
 }
 
+pub(super) extern "C" fn sigill_sigchain_handler(
+    sig: libc::c_int,
+    info: *mut libc::siginfo_t,
+    _ucontext: *mut libc::c_void,
+) -> bool {
+    maybe_jump_guard_slot(sig, info)
+}
+
+pub(super) extern "C" fn sigtrap_sigchain_handler(
+    sig: libc::c_int,
+    info: *mut libc::siginfo_t,
+    _ucontext: *mut libc::c_void,
+) -> bool {
+    maybe_jump_guard_slot(sig, info)
+}
+
 pub(super) fn sigsegv_sigchain_callback() -> SigchainCallbackFn {
     sigsegv_sigchain_handler
 }
@@ -154,6 +260,14 @@ pub(super) fn sigbus_sigchain_callback() -> SigchainCallbackFn {
     sigbus_sigchain_handler
 }
 
+pub(super) fn sigill_sigchain_callback() -> SigchainCallbackFn {
+    sigill_sigchain_handler
+}
+
+pub(super) fn sigtrap_sigchain_callback() -> SigchainCallbackFn {
+    sigtrap_sigchain_handler
+}
+
 // sigaction 模式下的信号处理入口，供 handlers 模块调用
 pub(super) fn handle_guard_signal(sig: libc::c_int, info: *mut libc::siginfo_t) -> bool {
     maybe_jump_guard_slot(sig, info)
@@ -1,6 +1,8 @@
 use crate::errno::Errno;
 use crate::runtime;
 use std::ffi::{c_char, c_void};
+use std::marker::PhantomData;
+use std::time::Duration;
 
 // hook 任务的唯一标识，由运行时分配
 pub type HookStub = u64;
@@ -16,15 +18,81 @@ pub type HookedCallback = unsafe extern "C" fn(
     arg: *mut c_void,
 );
 
+// 任务生命周期回调：除 HookedCallback 覆盖的挂载结果外，还通知卸载与模块重载后的重新绑定
+pub type HookEventCallback = unsafe extern "C" fn(
+    task_stub: HookStub,
+    event: HookEventKind,
+    status_code: i32,
+    caller_path_name: *const c_char,
+    sym_name: *const c_char,
+    new_func: *mut c_void,
+    prev_func: *mut c_void,
+    arg: *mut c_void,
+);
+
 // 自定义 caller 过滤器，返回 true 表示允许 hook 该 caller
 pub type CallerAllowFilter =
     unsafe extern "C" fn(caller_path_name: *const c_char, arg: *mut c_void) -> bool;
 
+// 带模块实例信息的 caller 过滤器，可按 base / instance / namespace 区分同名 so 的多个实例
+// 在刷新锁内调用，应尽快返回；其中调用本库 API 会返回 InitErrSafe
+pub type CallerAllowFilterEx = unsafe extern "C" fn(
+    caller_path_name: *const c_char,
+    base_addr: usize,
+    instance_id: usize,
+    namespace_id: usize,
+    arg: *mut c_void,
+) -> bool;
+
 // dlopen 前后回调，用于外部观测动态加载行为
 pub type PreDlopenCallback = unsafe extern "C" fn(filename: *const c_char, arg: *mut c_void);
 pub type PostDlopenCallback =
     unsafe extern "C" fn(filename: *const c_char, result: i32, arg: *mut c_void);
 
+// dlopen 过滤器的决定：放行、拒绝（dlopen 返回 NULL，post 回调收到失败），或改为加载 redirect_buf 中的路径
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DlopenFilterAction {
+    Allow = 0,
+    Deny = 1,
+    Redirect = 2,
+}
+
+// dlopen 过滤器，在 pre 回调之后、真正加载之前调用
+// Redirect 时把新路径以 nul 结尾写入 redirect_buf（容量 redirect_buf_len 字节）
+pub type DlopenFilterCallback = unsafe extern "C" fn(
+    filename: *const c_char,
+    redirect_buf: *mut c_char,
+    redirect_buf_len: usize,
+    arg: *mut c_void,
+) -> DlopenFilterAction;
+
+// dlclose 前后回调；pre 在真正卸载前投递，pathname 为按 handle 解析出的模块路径，无法解析时为空指针
+// post 的 result 为 dlclose 的返回值
+pub type PreDlcloseCallback =
+    unsafe extern "C" fn(handle: *mut c_void, pathname: *const c_char, arg: *mut c_void);
+pub type PostDlcloseCallback =
+    unsafe extern "C" fn(handle: *mut c_void, result: i32, arg: *mut c_void);
+
+// 日志级别，取值与 Android 日志优先级一致，低于所设级别的日志不输出
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Debug = 3,
+    Info = 4,
+    Warn = 5,
+    Error = 6,
+}
+
+// 日志 sink，level 为 Android 日志优先级（3 DEBUG ~ 6 ERROR），msg 只在回调期间有效
+// 可能在 monitor 线程或信号处理相关路径上调用，实现应避免阻塞
+pub type LogSink =
+    unsafe extern "C" fn(level: i32, tag: *const c_char, msg: *const c_char, arg: *mut c_void);
+
+// PostDlopenCallback 的 result：0 为新加载成功，-1 为失败
+// 带 RTLD_NOLOAD 的 dlopen 成功时为该值，表示仅取得已加载模块的 handle
+pub const DLOPEN_RESULT_NOLOAD: i32 = 1;
+
 // 模块实例标识，用于区分同名 so 的不同加载实例
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct ModuleIdentity {
@@ -49,8 +117,77 @@ impl ModuleIdentity {
     }
 }
 
+// 模块只读检视句柄：打开时解析一次 ELF 并持有模块引用，drop 时释放引用
+// 所有查询只读且带信号保护，不修改任何 slot
+pub struct ModuleInspector {
+    inner: runtime::Inspection,
+}
+
+impl ModuleInspector {
+    pub fn identity(&self) -> ModuleIdentity {
+        self.inner.identity()
+    }
+
+    // 本模块引用的外部符号名，按需从动态符号表读取
+    pub fn imports(&self) -> impl Iterator<Item = String> + '_ {
+        self.inner.symbols(false)
+    }
+
+    // 本模块定义的导出符号名
+    pub fn exports(&self) -> impl Iterator<Item = String> + '_ {
+        self.inner.symbols(true)
+    }
+
+    // 指定符号的全部 GOT slot 地址，调用点在前、数据槽在后
+    pub fn got_slots_for(&self, sym_name: &str) -> Result<Vec<usize>, Errno> {
+        self.inner.got_slots_for(sym_name)
+    }
+
+    pub fn relro_range(&self) -> Option<(usize, usize)> {
+        self.inner.relro_range()
+    }
+
+    // 地址是否位于本模块的 PT_GNU_RELRO 段，hook 后该段保持原有的只读保护
+    pub fn is_addr_in_relro(&self, addr: usize) -> bool {
+        self.inner.is_addr_in_relro(addr)
+    }
+
+    pub fn build_id(&self) -> Option<Vec<u8>> {
+        self.inner.build_id()
+    }
+
+    // 当前在本模块该符号上生效的 hook 任务
+    pub fn is_hooked(&self, sym_name: &str) -> Vec<HookStub> {
+        if in_external_callback() {
+            return Vec::new();
+        }
+        self.inner.hooked_by(sym_name)
+    }
+}
+
+// trampoline 页在 /proc/self/maps 中的名称：共享页来自同名 memfd，显示为 /memfd:srx_hook:trampoline (deleted)
+// 退回匿名页时显示为 [anon:srx_hook:trampoline]（需内核支持 PR_SET_VMA）
+pub const TRAMPOLINE_VMA_NAME: &str = "srx_hook:trampoline";
+
+// 每个线程 Hub 栈的固定数组深度，嵌套超过溢出阈值的帧转入线程私有的堆区
+pub const HUB_STACK_FIXED_DEPTH: usize = 32;
+// Hub 栈默认总深度上限（固定数组 + 堆区），超出后本次调用绕过 proxy
+pub const HUB_STACK_DEFAULT_HARD_CAP: usize = 256;
+
+// hook ABI 版本：调用链语义、回调签名等对外约定变化时递增
+// 版本 1 为引入版本校验之前的约定
+pub const ABI_VERSION: u32 = 2;
+// 运行时仍兼容的最低客户端 ABI 版本
+pub const MIN_CLIENT_ABI_VERSION: u32 = 1;
+
+// 供外部编译的 proxy 在运行期查询运行时 ABI 版本
+#[unsafe(no_mangle)]
+pub extern "C" fn srx_hook_abi_version() -> u32 {
+    ABI_VERSION
+}
+
 // 操作记录字段掩码
-pub const RECORD_ITEM_ALL: u32 = 0xFF;
+pub const RECORD_ITEM_ALL: u32 = 0x3FF;
 pub const RECORD_ITEM_TIMESTAMP: u32 = 1 << 0;
 pub const RECORD_ITEM_CALLER_LIB_NAME: u32 = 1 << 1;
 pub const RECORD_ITEM_OP: u32 = 1 << 2;
@@ -59,6 +196,59 @@ pub const RECORD_ITEM_SYM_NAME: u32 = 1 << 4;
 pub const RECORD_ITEM_NEW_ADDR: u32 = 1 << 5;
 pub const RECORD_ITEM_ERRNO: u32 = 1 << 6;
 pub const RECORD_ITEM_STUB: u32 = 1 << 7;
+// caller 模块实例（base_addr 与 instance_id）及 namespace，文本中以 caller 规则后缀 @0x..%0x..^0x.. 接在 lib_name 后
+pub const RECORD_ITEM_INSTANCE: u32 = 1 << 8;
+pub const RECORD_ITEM_NAMESPACE: u32 = 1 << 9;
+
+// 审计记录的操作类型；Rehook 为任务重新挂上随模块卸载失去的 caller（期间的失败记录同样为 Rehook），Clear 只投递给记录监听器
+// GuardFault 为信号守卫捕获的访问异常：LIB_NAME 为阶段标签，SYM_NAME 为信号名与 si_code，NEW_ADDR 为 si_addr
+// CfiPatch 为 CFI 指令补丁报告中新增或状态变化的地址：LIB_NAME 为所属模块，SYM_NAME 为符号名与地址来源（失败时附重试次数），NEW_ADDR 为地址
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RecordOp {
+    Hook = 0,
+    Unhook = 1,
+    Pause = 2,
+    Resume = 3,
+    Rehook = 4,
+    Clear = 5,
+    GuardFault = 6,
+    CfiPatch = 7,
+}
+
+// 记录监听器，每条记录写入后在释放 state 锁的线程上按写入顺序投递，字段与 RecordEntry 相同
+// 字符串参数只在回调期间有效；注册阶段被拒绝的 hook 记录在下一次投递点送达
+pub type RecordCallback = unsafe extern "C" fn(
+    timestamp_ms: u64,
+    op: RecordOp,
+    caller_lib_name: *const c_char,
+    lib_name: *const c_char,
+    sym_name: *const c_char,
+    new_addr: *mut c_void,
+    errno: i32,
+    stub: HookStub,
+    arg: *mut c_void,
+);
+
+// get_record_entries 返回的结构化审计记录，未被 item_flags 选中的字段为 None
+// lib_name 对 single 任务为 caller 规则，partial/all 任务为 "PARTIAL"/"ALL"
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecordEntry {
+    pub timestamp_ms: Option<u64>,
+    pub caller_lib_name: Option<String>,
+    pub op: Option<RecordOp>,
+    pub lib_name: Option<String>,
+    pub sym_name: Option<String>,
+    pub new_addr: Option<usize>,
+    // 写入校验失败（PatchVerifyFailed）的记录中回读到的 slot 值，随 new_addr 一起选中
+    pub observed_addr: Option<usize>,
+    pub errno: Option<i32>,
+    pub stub: Option<HookStub>,
+    // 仅绑定到单个 caller 模块实例的记录带有以下字段
+    pub caller_base_addr: Option<usize>,
+    pub caller_instance_id: Option<usize>,
+    pub caller_namespace_id: Option<usize>,
+}
 
 // Automatic: dlopen/dlclose 事件自动触发刷新
 // Manual: 需要手动调用 refresh() 应用 hook
@@ -79,6 +269,486 @@ impl HookMode {
     }
 }
 
+// init_with_options 的配置，未设置的运行时参数在 init 时恢复默认值
+// record_capacity 例外：未设置时保留 set_record_capacity 的当前值
+#[derive(Clone, Debug)]
+pub struct InitOptions {
+    pub(crate) mode: HookMode,
+    pub(crate) debug: bool,
+    pub(crate) monitor_poll: Option<(Duration, Duration)>,
+    pub(crate) loader_stable_threshold: Option<usize>,
+    pub(crate) hub_destroy_delay_secs: Option<u64>,
+    pub(crate) record_capacity: Option<usize>,
+    pub(crate) cfi_patch_scope: CfiPatchScope,
+    pub(crate) slot_guard: bool,
+    pub(crate) rwx_trampolines: bool,
+    pub(crate) hub_stack_spill: Option<(usize, usize)>,
+    pub(crate) fork_child_restore: bool,
+}
+
+impl InitOptions {
+    pub fn new() -> Self {
+        Self {
+            mode: HookMode::Automatic,
+            debug: false,
+            monitor_poll: None,
+            loader_stable_threshold: None,
+            hub_destroy_delay_secs: None,
+            record_capacity: None,
+            cfi_patch_scope: CfiPatchScope::Global,
+            slot_guard: false,
+            rwx_trampolines: false,
+            hub_stack_spill: None,
+            fork_child_restore: false,
+        }
+    }
+
+    pub fn mode(mut self, mode: HookMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    // 周期性兜底巡检的最小/最大间隔，要求 1ms <= min <= max；SRX_HOOK_MONITOR_PERIODIC 仍决定是否巡检
+    pub fn monitor_poll(mut self, min: Duration, max: Duration) -> Self {
+        self.monitor_poll = Some((min, max));
+        self
+    }
+
+    // loader 调用连续成功多少次后从周期巡检降回纯事件模式，要求非 0
+    pub fn loader_stable_threshold(mut self, count: usize) -> Self {
+        self.loader_stable_threshold = Some(count);
+        self
+    }
+
+    // retired hub 延迟销毁的秒数，要求非 0
+    pub fn hub_destroy_delay(mut self, secs: u64) -> Self {
+        self.hub_destroy_delay_secs = Some(secs);
+        self
+    }
+
+    // 记录缓冲区容量，取值范围同 set_record_capacity
+    pub fn record_capacity(mut self, max_entries: usize) -> Self {
+        self.record_capacity = Some(max_entries);
+        self
+    }
+
+    // 跳过 CFI slowpath 与模块 CFI slot 的补丁，适合确认宿主未启用 CFI 的场景
+    pub fn disable_cfi_patch(mut self) -> Self {
+        self.cfi_patch_scope = CfiPatchScope::Disabled;
+        self
+    }
+
+    // CFI 补丁范围，默认 Global；disable_cfi_patch 等价于 Disabled
+    pub fn cfi_patch_scope(mut self, scope: CfiPatchScope) -> Self {
+        self.cfi_patch_scope = scope;
+        self
+    }
+
+    // 开启 slot 完整性巡检，等价于 init 后调用 set_slot_guard(true)
+    pub fn slot_guard(mut self, flag: bool) -> Self {
+        self.slot_guard = flag;
+        self
+    }
+
+    // trampoline 页默认 W^X：写入时 RW、发布前切为 RX；true 时直接映射 RWX，省去每个 hub 的 mprotect
+    pub fn rwx_trampolines(mut self, flag: bool) -> Self {
+        self.rwx_trampolines = flag;
+        self
+    }
+
+    // Hub 栈在固定数组用到 threshold 帧后转入堆区，总深度到达 hard_cap 后新的嵌套调用绕过 proxy
+    // 要求 1 <= threshold <= HUB_STACK_FIXED_DEPTH 且 hard_cap >= threshold，hard_cap == threshold 即不使用堆区
+    pub fn hub_stack_spill(mut self, threshold: usize, hard_cap: usize) -> Self {
+        self.hub_stack_spill = Some((threshold, hard_cap));
+        self
+    }
+
+    // fork 子进程中由 atfork 回调把已改写的 GOT slot 写回原始值，子进程 exec 前调用的函数不再经过 hub
+    pub fn restore_got_in_fork_child(mut self, flag: bool) -> Self {
+        self.fork_child_restore = flag;
+        self
+    }
+}
+
+impl Default for InitOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// HookedCallback 的投递方式
+// Inline: 在执行刷新的线程上同步调用（monitor 线程或调用 refresh 的线程）
+// DedicatedThread: 投递到 srx_hook_callbacks 线程异步调用，同一刷新产生的事件按序投递
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CallbackDispatch {
+    Inline = 0,
+    DedicatedThread = 1,
+}
+
+// HookEventCallback 的事件类型
+// Hooked: 首次挂上某个 caller 模块的 slot，或挂载失败（status_code 非 0）
+// Rehooked: 同一路径的模块卸载后重新加载，任务再次挂上其 slot
+// Unhooked: unhook / unhook_symbol / clear 移除任务，每个曾挂载的 caller 模块各一次
+// ModuleUnloaded: 刷新发现任务挂载过的 caller 模块已卸载，prev_func 为 0
+#[repr(i32)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum HookEventKind {
+    Hooked = 0,
+    Rehooked = 1,
+    Unhooked = 2,
+    ModuleUnloaded = 3,
+}
+
+// callee 规则匹配到多个导出该符号的实例时（如 APEX bionic 与 vendor 副本并存）的选择策略
+// All: 接受任一实例的导出；CallerNamespace: 优先与 caller 同 namespace 的实例，无同 namespace 实例时退回 All
+// Error: 不做选择，该任务以 AmbiguousCallee 上报
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum CalleeSelection {
+    #[default]
+    All = 0,
+    CallerNamespace = 1,
+    Error = 2,
+}
+
+// hook_single 的 caller 规则匹配到多个同名实例时的绑定策略
+// FirstMatch: 绑定刷新中首个挂上的实例，该实例卸载后再重新挑选
+// AllMatches: 挂上全部匹配的实例，相当于限定在该 caller 规则内的 hook_all
+// NewestInstance: 在匹配的实例中挑选最新加载的一个，先比较经 dlopen 观察到的先后，未观察到的按加载顺序；已绑定的实例卸载前不迁移
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum SingleBindPolicy {
+    #[default]
+    FirstMatch = 0,
+    AllMatches = 1,
+    NewestInstance = 2,
+}
+
+// hook 任务的作用域：hook_single / hook_partial / hook_all
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum HookTaskType {
+    Single,
+    Partial,
+    All,
+}
+
+// 已注册 hook 任务的快照；caller_path_name 仅 Single 任务有值，slot_count 为当前持有的 GOT slot 数
+// caller_allow_list 仅 hook_all_with_callers 注册的任务非空，threads 为空表示对所有线程生效
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TaskInfo {
+    pub stub: HookStub,
+    pub task_type: HookTaskType,
+    pub caller_path_name: Option<String>,
+    pub caller_allow_list: Vec<String>,
+    pub callee_path_name: Option<String>,
+    pub sym_name: String,
+    pub new_func: usize,
+    pub slot_count: usize,
+    pub paused: bool,
+    pub priority: i32,
+    pub threads: Vec<i32>,
+}
+
+// 任务的 proxy 命中统计；last_hit_ns 为 CLOCK_MONOTONIC 纳秒，threads 最多区分 64 个线程
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct HookStats {
+    pub hits: u64,
+    pub last_hit_ns: u64,
+    pub threads: usize,
+}
+
+// hook_batch 的单个条目，字段与 hook_single 的参数一一对应
+#[derive(Copy, Clone, Debug)]
+pub struct HookSpec<'a> {
+    pub caller_path_name: &'a str,
+    pub callee_path_name: Option<&'a str>,
+    pub sym_name: &'a str,
+    pub new_func: *mut c_void,
+    pub hooked: Option<HookedCallback>,
+    pub hooked_arg: *mut c_void,
+}
+
+// load_rules_from_str 中单个规则行的结果，line 从 1 开始
+// hook 行成功时为 Ok(Some(stub))，ignore 行成功时为 Ok(None)
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RuleLineResult {
+    pub line: usize,
+    pub result: Result<Option<HookStub>, Errno>,
+}
+
+// 规则文本的加载报告，按行序列出每个非空、非注释行的结果
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RuleLoadReport {
+    pub lines: Vec<RuleLineResult>,
+}
+
+impl RuleLoadReport {
+    pub fn is_ok(&self) -> bool {
+        self.lines.iter().all(|line| line.result.is_ok())
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &RuleLineResult> {
+        self.lines.iter().filter(|line| line.result.is_err())
+    }
+}
+
+// is_hook_artifact 的地址归属
+// Trampoline: hub trampoline 页池；InternalProxy: 框架内部 proxy 的函数体（按 .eh_frame 中的 FDE 范围）；OwnModuleCode: 本库的其余代码段
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ArtifactKind {
+    Trampoline,
+    InternalProxy,
+    OwnModuleCode,
+}
+
+// 调用链条目的归属：本库的 hook 任务，或不属于本库的外部改写
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ChainOwner {
+    OurTask(HookStub),
+    Foreign,
+}
+
+// 调用链中的单个入口，外部入口附带 dladdr 解析出的 模块!符号+偏移
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChainEntryInfo {
+    pub func: usize,
+    pub owner: ChainOwner,
+    pub symbolized: Option<String>,
+}
+
+// 单个 slot 的调用链预览：entries 按调用顺序排列，最后落到 orig（外部改写且原函数无法确定时为 0）
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChainPreview {
+    pub module: ModuleIdentity,
+    pub slot_addr: usize,
+    pub entries: Vec<ChainEntryInfo>,
+    pub orig: usize,
+}
+
+// hub 调用链中的单个 proxy 节点；已全部 unhook 的节点保留在链上，enabled 为 false、ref_count 为 0
+// stub 为挂载该 proxy 的首个任务，任务已移除时为 None
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProxyChainEntry {
+    pub proxy_addr: usize,
+    pub enabled: bool,
+    pub ref_count: usize,
+    pub priority: i32,
+    pub stub: Option<HookStub>,
+}
+
+// 已挂载 slot 的实际调用链：entries 按 trampoline 遍历顺序排列，全部跳过后落到 orig_func
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProxyChain {
+    pub module: ModuleIdentity,
+    pub slot_addr: usize,
+    pub entries: Vec<ProxyChainEntry>,
+    pub orig_func: usize,
+}
+
+// 地址反查结果：所属模块实例，以及动态符号表中包含该地址的符号名、起始地址与大小
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ResolvedSymbol {
+    pub module: ModuleIdentity,
+    pub name: String,
+    pub addr: usize,
+    pub size: usize,
+}
+
+// linker 内部 do_dlopen 回退路径的使用统计
+// used: 实际调用 do_dlopen 的次数；lock_timeouts: 等待 g_dl_mutex 超时而放弃该路径的次数
+// last_error: 最近一次 do_dlopen 失败时的 linker 错误信息
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LinkerFallbackStats {
+    pub used: u64,
+    pub lock_timeouts: u64,
+    pub last_error: Option<String>,
+}
+
+// 探测性内存读取（slot 回读、callee 校验、CFI 候选扫描）的路径统计
+// syscall_reads: 经 process_vm_readv 读到的次数；syscall_faults: 内核判定地址不可读、未触发信号的次数
+// guard_reads: 系统调用不可用或部分读取时回退到信号保护直接读取的次数
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SafeReadStats {
+    pub syscall_reads: u64,
+    pub syscall_faults: u64,
+    pub guard_reads: u64,
+}
+
+// 信号守卫 slot 的占用统计：静态数组 base_slots 个，耗尽后按需分配链表节点 extra_slots 个（只增不减）
+// *_claimed 为当前被线程占用的数量；recycled 为扩展链表前从已退出线程处回收转交的累计次数
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct GuardSlotStats {
+    pub base_slots: usize,
+    pub base_claimed: usize,
+    pub extra_slots: usize,
+    pub extra_claimed: usize,
+    pub recycled: u64,
+}
+
+// 信号守卫捕获的一次访问异常：addr 为 si_addr，phase 为调用方标注的阶段（如 elf_init、collect_slot）
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct GuardFault {
+    pub signum: i32,
+    pub code: i32,
+    pub addr: usize,
+    pub phase: &'static str,
+}
+
+// 信号守卫访问异常统计：total 为累计捕获数，by_phase 只列出计数非零的阶段
+// recent 为最近至多 16 次访问异常，按发生顺序排列
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GuardFaultStats {
+    pub total: u64,
+    pub by_phase: Vec<(&'static str, u64)>,
+    pub recent: Vec<GuardFault>,
+}
+
+// Hub 栈溢出区的使用统计（全部线程累计）
+// spilled_frames: 压入堆区的帧数；spill_allocs: 线程为堆区预留内存的次数
+// overflows: 到达硬上限或堆区无法分配、调用绕过 proxy 的次数；max_depth: 使用堆区时观察到的最大嵌套深度
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct HubStackStats {
+    pub spilled_frames: u64,
+    pub spill_allocs: u64,
+    pub overflows: u64,
+    pub max_depth: usize,
+}
+
+// 线程状态的诊断计数，进程内累计，reset_thread_state_stats 清零
+// key_init_failures: pthread key 创建失败次数；bind_failures: 线程状态绑定失败次数
+// reserved_hits: 线程退出析构期间再次进入 hook、被拒绝访问线程状态的次数
+// hub_stack_overflows: 同 HubStackStats::overflows；proxy_stack_overflows: proxy 栈满、递归检测失效的次数
+// live_states: 当前存活的线程状态数；fork 子进程不创建线程状态，其中读取为 0
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ThreadStateStats {
+    pub key_init_failures: u64,
+    pub bind_failures: u64,
+    pub reserved_hits: u64,
+    pub hub_stack_overflows: u64,
+    pub proxy_stack_overflows: u64,
+    pub live_states: u64,
+}
+
+// full: 枚举全部模块的刷新（dlopen、周期巡检、dlclose 身份解析失败的兜底）
+// scoped: dlclose 后只清理被卸载模块的刷新
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MonitorRefreshCounts {
+    pub full: u64,
+    pub scoped: u64,
+}
+
+// 自动模式 monitor 当前使用的加载监控策略；loader hook 回退后补装 legacy hook 时报告 LegacyHooks
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MonitorStrategy {
+    Inactive,
+    LoaderHooks,
+    LegacyHooks,
+}
+
+// 自动模式 monitor 的运行状况
+// parked: 线程存活但因 set_mode 切到 Manual 而挂起；periodic_escalated: 因 loader 调用回退而临时开启周期巡检
+// poll_interval 为线程当前的巡检等待间隔；两个时间戳为最近一次事件触发 / 周期巡检刷新的 Unix 毫秒，0 表示尚未发生
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct MonitorStatus {
+    pub thread_running: bool,
+    pub parked: bool,
+    pub strategy: MonitorStrategy,
+    pub periodic_enabled: bool,
+    pub periodic_escalated: bool,
+    pub loader_success_streak: usize,
+    pub poll_interval: Duration,
+    pub last_event_refresh_ms: u64,
+    pub last_periodic_refresh_ms: u64,
+}
+
+// SIGSEGV/SIGBUS 守卫处理器的安装方式
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SignalHandlerMode {
+    None,
+    Sigaction,
+    Sigchain,
+}
+
+// CFI 补丁范围
+// Global: 将进程内全部 __cfi_slowpath / __cfi_slowpath_diag 入口改写为 RET，并替换写入 hook 的模块 GOT 中的 CFI slot
+// ModulesWithHooks: 不改写指令，只替换存在活动 slot 的模块 GOT 中的 CFI slot，每轮刷新重新计算，其他模块（如 libart）保留 CFI 检查
+// Disabled: 不做任何 CFI 补丁，init 时回滚此前遗留的补丁
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum CfiPatchScope {
+    #[default]
+    Global,
+    ModulesWithHooks,
+    Disabled,
+}
+
+// CFI 指令补丁地址对应的符号
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CfiSymbolKind {
+    Slowpath,
+    SlowpathDiag,
+}
+
+// CFI 地址的发现途径，同一地址经多条途径找到时按 Dlsym、ElfExport、ImportSlot 的顺序取首个
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CfiAddrSource {
+    Dlsym,
+    ElfExport,
+    ImportSlot,
+}
+
+// Failed 携带已失败的次数，达到重试上限后不再尝试
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CfiPatchStatus {
+    Patched,
+    Failed(u8),
+}
+
+// get_cfi_patch_report 的单个地址；module 为 dladdr 解析出的所属模块路径
+// ignored 表示地址只由命中 add_ignore 规则的模块提供，此前的补丁保持原样，不再重试或扩展
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CfiPatchEntry {
+    pub addr: usize,
+    pub kind: CfiSymbolKind,
+    pub source: CfiAddrSource,
+    pub module: Option<String>,
+    pub status: CfiPatchStatus,
+    pub ignored: bool,
+}
+
+// CFI slowpath 处理情况：skipped 表示系统无 CFI、架构不需要处理或 init 时关闭了 CFI 补丁，模块计数为当前存活模块的 hook 结果
+// scope 为当前生效的补丁范围（restore_cfi_slowpath 后由 Global 降为 ModulesWithHooks），patched_slowpath 为已改写为 RET 的指令地址数
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct CfiPatchState {
+    pub skipped: bool,
+    pub scope: CfiPatchScope,
+    pub patched_slowpath: usize,
+    pub patched_modules: usize,
+    pub failed_modules: usize,
+}
+
+// init 时的环境探测结果；monitor_strategy / periodic_fallback / signal_handler_mode / cfi 为查询时的实时值
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Capabilities {
+    pub android_api_level: i32,
+    pub loader_hooks_available: bool,
+    pub monitor_strategy: MonitorStrategy,
+    pub periodic_fallback: bool,
+    pub signal_handler_mode: SignalHandlerMode,
+    pub dlinfo_available: bool,
+    pub dladdr1_available: bool,
+    pub cfi: CfiPatchState,
+    pub hw_mte: bool,
+    pub hw_bti: bool,
+    pub hw_pac: bool,
+    pub maps_readable: bool,
+}
+
 // 在外部回调中调用 API 会导致死锁，此守卫统一拦截
 #[inline]
 fn in_external_callback() -> bool {
@@ -94,189 +764,1051 @@ pub fn is_forked_child() -> bool {
     runtime::is_forked_child()
 }
 
-// 初始化 hook 运行时，只能调用一次
-pub fn init(mode: HookMode, debug: bool) -> Errno {
+// 声明客户端编译时的 ABI 版本，client 为客户端模块内的任意地址（通常是其 proxy）
+// 不兼容时返回 AbiMismatch，此后该模块中的 proxy 注册被拒绝（checked 版本返回 AbiMismatch），其他客户端不受影响
+// 同一模块重新声明兼容版本后恢复注册
+pub fn set_client_abi(version: u32, client: *const c_void) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::set_client_abi(version, client as usize)
+}
+
+// 已声明的客户端 ABI 范围 (最小, 最大)，未声明时返回 None
+pub fn get_client_abi_range() -> Option<(u32, u32)> {
+    runtime::get_client_abi_range()
+}
+
+// 初始化 hook 运行时，只能调用一次；其余参数取默认值
+pub fn init(mode: HookMode, debug: bool) -> Errno {
+    init_with_options(InitOptions::new().mode(mode).debug(debug))
+}
+
+// 按 InitOptions 初始化，参数在 monitor 线程与 CFI 补丁启动前生效；参数无效时返回 InitErrInvalidArg
+// 已初始化时直接返回当前初始化状态，不应用新参数
+pub fn init_with_options(opts: InitOptions) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::init_with_options(opts)
+}
+
+// 仅供 fork 后不 exec、继续运行的子进程调用：按子进程 PID 重建运行时，使已注册的 hook 重新生效
+// 父进程中调用或尚未初始化时分别返回 Invalid 与当前初始化状态；只有调用线程会重新绑定线程状态
+pub fn reinit_after_fork() -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::reinit_after_fork()
+}
+
+// 按 caller 路径精确匹配单个模块进行 hook
+// 同一符号的调用点（JUMP_SLOT）与取地址槽（GLOB_DAT/ABS）作为整体 hook 和恢复：
+// caller 内保存的 &sym 函数指针同样经过 proxy 链，callee 过滤对两类同时生效
+// sym_name 写作 "name@VERSION" 时只 hook 绑定到该符号版本的导入，callee 地址同样按该版本解析；
+// 不带版本时沿用默认版本（name@@VERSION）
+// 注册失败时返回具体原因：InvalidArg 参数无效、RepeatedFunc 与已注册任务完全相同、
+// Uninit 未初始化、AbiMismatch 客户端 ABI 不兼容、InitErrSafe 在外部回调中调用
+pub fn hook_single_checked(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    if in_external_callback() {
+        return Err(Errno::InitErrSafe);
+    }
+    runtime::hook_single(
+        caller_path_name,
+        callee_path_name,
+        sym_name,
+        new_func,
+        hooked,
+        hooked_arg,
+    )
+}
+
+// 同 hook_single_checked，并指定 proxy 在 hub 链中的优先级：数值大的先执行，与注册顺序无关
+// 同优先级时后注册的先执行，hook_single 等价于优先级 0；i32::MAX 保留给内部 proxy，返回 InvalidArg
+// 同一 proxy 已在该 slot 的链上时沿用已有节点的位置
+pub fn hook_single_with_priority(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    priority: i32,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    if in_external_callback() {
+        return Err(Errno::InitErrSafe);
+    }
+    runtime::hook_single_with_priority(
+        caller_path_name,
+        callee_path_name,
+        sym_name,
+        new_func,
+        priority,
+        hooked,
+        hooked_arg,
+    )
+}
+
+// 同 hook_single_checked，proxy 只拦截 tids 中线程发起的调用，其他线程照常沿链转发、如同该 proxy 未启用
+// tids 为 gettid 返回的线程 id，需为正数，为空时对所有线程生效；同一 proxy 被多个任务挂到同一 slot 时以最后设置的集合为准
+pub fn hook_single_for_threads(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    tids: &[i32],
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    if in_external_callback() {
+        return Err(Errno::InitErrSafe);
+    }
+    runtime::hook_single_for_threads(
+        caller_path_name,
+        callee_path_name,
+        sym_name,
+        new_func,
+        tids,
+        hooked,
+        hooked_arg,
+    )
+}
+
+// 同 hook_single_checked，并指定 caller 规则匹配到多个实例时的绑定策略，FirstMatch 等价于 hook_single
+pub fn hook_single_with_policy(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    policy: SingleBindPolicy,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    if in_external_callback() {
+        return Err(Errno::InitErrSafe);
+    }
+    runtime::hook_single_with_policy(
+        caller_path_name,
+        callee_path_name,
+        sym_name,
+        new_func,
+        policy,
+        hooked,
+        hooked_arg,
+    )
+}
+
+// hook_single_checked 的兼容版本，失败原因可通过 records 查看
+pub fn hook_single(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Option<HookStub> {
+    hook_single_checked(
+        caller_path_name,
+        callee_path_name,
+        sym_name,
+        new_func,
+        hooked,
+        hooked_arg,
+    )
+    .ok()
+}
+
+// 按 caller 规则 hook 名称匹配 sym_pattern 的全部导入，'*' 匹配任意长度字符（如 "pthread_*"）
+// 应用时遍历 caller 的重定位表展开为具体符号，HookedCallback、事件与 records 均使用具体符号名；
+// 单个模块匹配数超过 set_pattern_match_limit 上限时该模块不挂载，refresh 以 PatternLimit 上报
+// sym_pattern 含 '@' 时返回 InvalidArg，不含 '*' 时等同 hook_single_checked
+pub fn hook_single_pattern_checked(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_pattern: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    if in_external_callback() {
+        return Err(Errno::InitErrSafe);
+    }
+    runtime::hook_single_pattern(
+        caller_path_name,
+        callee_path_name,
+        sym_pattern,
+        new_func,
+        hooked,
+        hooked_arg,
+    )
+}
+
+pub fn hook_single_pattern(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_pattern: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Option<HookStub> {
+    hook_single_pattern_checked(
+        caller_path_name,
+        callee_path_name,
+        sym_pattern,
+        new_func,
+        hooked,
+        hooked_arg,
+    )
+    .ok()
+}
+
+// 批量注册 hook_single 任务，所有条目合并后只做一轮扫描
+// 返回值与 specs 顺序一致，被拒绝的条目带各自的错误码，不影响其他条目
+pub fn hook_batch_checked(specs: &[HookSpec]) -> Vec<Result<HookStub, Errno>> {
+    if in_external_callback() {
+        return vec![Err(Errno::InitErrSafe); specs.len()];
+    }
+    runtime::hook_batch(specs)
+}
+
+// hook_batch_checked 的兼容版本，无效或 proxy 地址重复的条目为 None
+pub fn hook_batch(specs: &[HookSpec]) -> Vec<Option<HookStub>> {
+    hook_batch_checked(specs)
+        .into_iter()
+        .map(Result::ok)
+        .collect()
+}
+
+// 通过自定义过滤器选择性 hook 多个 caller，错误码同 hook_single_checked
+pub fn hook_partial_checked(
+    caller_allow_filter: CallerAllowFilter,
+    caller_allow_filter_arg: *mut c_void,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    if in_external_callback() {
+        return Err(Errno::InitErrSafe);
+    }
+    runtime::hook_partial(
+        caller_allow_filter,
+        caller_allow_filter_arg,
+        callee_path_name,
+        sym_name,
+        new_func,
+        hooked,
+        hooked_arg,
+    )
+}
+
+pub fn hook_partial(
+    caller_allow_filter: CallerAllowFilter,
+    caller_allow_filter_arg: *mut c_void,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Option<HookStub> {
+    hook_partial_checked(
+        caller_allow_filter,
+        caller_allow_filter_arg,
+        callee_path_name,
+        sym_name,
+        new_func,
+        hooked,
+        hooked_arg,
+    )
+    .ok()
+}
+
+// 与 hook_partial_checked 相同，过滤器额外收到 caller 模块实例的 base、instance 与 namespace
+pub fn hook_partial_ex(
+    caller_allow_filter: CallerAllowFilterEx,
+    caller_allow_filter_arg: *mut c_void,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    if in_external_callback() {
+        return Err(Errno::InitErrSafe);
+    }
+    runtime::hook_partial_ex(
+        caller_allow_filter,
+        caller_allow_filter_arg,
+        callee_path_name,
+        sym_name,
+        new_func,
+        hooked,
+        hooked_arg,
+    )
+}
+
+// hook 所有已加载和未来加载的 caller 模块，错误码同 hook_single_checked
+pub fn hook_all_checked(
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    if in_external_callback() {
+        return Err(Errno::InitErrSafe);
+    }
+    runtime::hook_all(callee_path_name, sym_name, new_func, hooked, hooked_arg)
+}
+
+pub fn hook_all(
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Option<HookStub> {
+    hook_all_checked(callee_path_name, sym_name, new_func, hooked, hooked_arg).ok()
+}
+
+// 只 hook 匹配 callers 中任一规则的 caller 模块（含未来加载的），规则语义同 hook_single
+// callers 为空或含无法解析的规则时返回 InvalidArg，其余错误码同 hook_single_checked
+pub fn hook_all_with_callers_checked(
+    callers: &[&str],
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    if in_external_callback() {
+        return Err(Errno::InitErrSafe);
+    }
+    runtime::hook_all_with_callers(
+        callers,
+        callee_path_name,
+        sym_name,
+        new_func,
+        hooked,
+        hooked_arg,
+    )
+}
+
+pub fn hook_all_with_callers(
+    callers: &[&str],
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Option<HookStub> {
+    hook_all_with_callers_checked(
+        callers,
+        callee_path_name,
+        sym_name,
+        new_func,
+        hooked,
+        hooked_arg,
+    )
+    .ok()
+}
+
+// 按注册顺序列出当前已注册的 hook 任务（含尚未合并的任务），不含框架内部的 monitor 任务
+pub fn get_hook_tasks() -> Vec<TaskInfo> {
+    if in_external_callback() {
+        return Vec::new();
+    }
+    runtime::get_hook_tasks()
+}
+
+// 卸载指定 hook 任务，同一调用点的其他任务不受影响
+pub fn unhook(stub: HookStub) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::unhook(stub)
+}
+
+// 卸载所有 hook sym_name 的任务；caller_rule 非空时只卸载 caller 规则被其覆盖的 hook_single 任务
+// 返回卸载的任务数与首个失败的状态，monitor 内部任务不受影响
+pub fn unhook_symbol(sym_name: &str, caller_rule: Option<&str>) -> (usize, Errno) {
+    if in_external_callback() {
+        return (0, Errno::InitErrSafe);
+    }
+    runtime::unhook_symbol(sym_name, caller_rule)
+}
+
+// 暂停指定 hook 任务：GOT 与 hub 保持不变，调用跳过该任务的 proxy，之后 refresh 新挂的 slot 同样暂停
+pub fn pause(stub: HookStub) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::pause(stub)
+}
+
+// 恢复被 pause 的 hook 任务，对未暂停的任务无影响
+pub fn resume(stub: HookStub) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::resume(stub)
+}
+
+// 为任务注册生命周期回调，callback 为 None 时注销；投递方式与 HookedCallback 相同
+pub fn set_hook_event_callback(
+    stub: HookStub,
+    callback: Option<HookEventCallback>,
+    arg: *mut c_void,
+) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::set_hook_event_callback(stub, callback, arg)
+}
+
+// 整体替换任务的线程集合，已挂载的 slot 立即生效，调用中的线程看到的是替换前或替换后的完整集合
+// tids 为空时恢复对所有线程生效，含非正数 tid 或任务不存在时返回 InvalidArg
+pub fn set_task_threads(stub: HookStub, tids: &[i32]) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::set_task_threads(stub, tids)
+}
+
+// 开关任务的命中统计，默认关闭；链首 proxy 与经 get_prev_func 进入的后续 proxy 均计数
+pub fn set_hook_stats_enabled(stub: HookStub, enabled: bool) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::set_hook_stats_enabled(stub, enabled)
+}
+
+// 读取任务的命中统计，未开启统计或任务不存在时返回 None
+pub fn get_hook_stats(stub: HookStub) -> Option<HookStats> {
+    if in_external_callback() {
+        return None;
+    }
+    runtime::get_hook_stats(stub)
+}
+
+// 任务首个已绑定 slot 的原始函数地址，可绕过整条 hub 链直接调用被 hook 的函数
+// 尚未绑定任何 slot 时返回 None；目标模块被 dlclose 后地址即失效，下次 refresh 清理该 slot 后同样返回 None
+pub fn get_orig_func(stub: HookStub) -> Option<*mut c_void> {
+    if in_external_callback() {
+        return None;
+    }
+    runtime::get_orig_func(stub)
+}
+
+// 任务每个已绑定 slot 的 (caller 路径, 原始函数地址)，失效规则同 get_orig_func
+pub fn get_orig_funcs(stub: HookStub) -> Vec<(String, *mut c_void)> {
+    if in_external_callback() {
+        return Vec::new();
+    }
+    runtime::get_orig_funcs(stub)
+}
+
+// 设置任务在 callee 规则匹配到多个导出实例时的处理策略，下次 refresh 起生效
+pub fn set_callee_selection(stub: HookStub, selection: CalleeSelection) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::set_callee_selection(stub, selection)
+}
+
+// 将指定 caller 路径加入忽略列表，后续 hook 跳过该模块
+// 以 '!' 开头的规则为例外：匹配的模块即使命中其他 ignore 规则也照常 hook
+pub fn add_ignore(caller_path_name: &str) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::add_ignore(caller_path_name)
+}
+
+// 删除与 add_ignore 时文本完全相同的规则（含 '!' 前缀），不存在时返回 NotFound
+// Automatic 模式下随即请求一次全量刷新，Manual 模式在下次 refresh 时补挂此前被跳过的模块
+pub fn del_ignore(caller_path_name: &str) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::del_ignore(caller_path_name)
+}
+
+// 当前的 ignore 规则，按添加顺序排列
+pub fn get_ignores() -> Vec<String> {
+    if in_external_callback() {
+        return Vec::new();
+    }
+    runtime::get_ignores()
+}
+
+// 从文本注册 hook，每行一条，# 之后为注释：
+//   caller_rule [callee_rule] sym_name proxy_name   caller_rule 为 * 时注册 hook_all，否则 hook_single
+//   ignore <rule>                                    等价于 add_ignore
+// 规则支持与 hook 接口相同的 @base / %instance / ^namespace 后缀，callee_rule 为 * 表示不限定
+// proxy_name 经 new_func_resolver 解析为函数地址，返回空指针时该行以 NotFound 失败，不影响其他行
+pub fn load_rules_from_str(
+    text: &str,
+    new_func_resolver: fn(&str) -> *mut c_void,
+) -> RuleLoadReport {
+    if in_external_callback() {
+        return RuleLoadReport::default();
+    }
+    runtime::load_rules(text, new_func_resolver)
+}
+
+// 读取规则文件后按 load_rules_from_str 注册，文件无法读取时返回 NotFound
+pub fn load_rules_from_file(
+    path: &str,
+    new_func_resolver: fn(&str) -> *mut c_void,
+) -> Result<RuleLoadReport, Errno> {
+    if in_external_callback() {
+        return Err(Errno::InitErrSafe);
+    }
+    let text = std::fs::read_to_string(path).map_err(|_| Errno::NotFound)?;
+    Ok(runtime::load_rules(&text, new_func_resolver))
+}
+
+// 从 dlopen 句柄获取模块实例标识
+pub fn get_module_identity(handle: *mut c_void) -> Option<ModuleIdentity> {
+    if in_external_callback() {
+        return None;
+    }
+    runtime::get_module_identity(handle)
+}
+
+// 从 dlopen 句柄获取模块标识，dlinfo 不可用时回退到符号探测
+pub fn get_module_identity_with_symbol(
+    handle: *mut c_void,
+    probe_symbol: &str,
+) -> Option<ModuleIdentity> {
+    if in_external_callback() {
+        return None;
+    }
+    runtime::get_module_identity_with_symbol(handle, probe_symbol)
+}
+
+// 按 caller 规则（支持实例级限定）打开首个匹配的已加载模块
+pub fn open_module(rule: &str) -> Result<ModuleInspector, Errno> {
+    if in_external_callback() {
+        return Err(Errno::InitErrSafe);
+    }
+    if rule.is_empty() {
+        return Err(Errno::InvalidArg);
+    }
+    runtime::open_module(rule).map(|inner| ModuleInspector { inner })
+}
+
+// 匹配 caller 规则的每个模块实例经重定位表（.rel(a).plt / .rel(a).dyn / Android 压缩重定位）引用的符号名
+// 按模块去重排序，同名 so 的不同实例分别列出；规则无效或没有可读模块时返回空列表
+pub fn list_imports(caller_rule: &str) -> Vec<(ModuleIdentity, Vec<String>)> {
+    if in_external_callback() || caller_rule.is_empty() {
+        return Vec::new();
+    }
+    runtime::list_module_symbols(caller_rule, false)
+}
+
+// 匹配 caller 规则的每个模块实例在动态符号表中定义且 st_value 非零的导出符号名
+pub fn list_exports(caller_rule: &str) -> Vec<(ModuleIdentity, Vec<String>)> {
+    if in_external_callback() || caller_rule.is_empty() {
+        return Vec::new();
+    }
+    runtime::list_module_symbols(caller_rule, true)
+}
+
+// list_imports 的流式版本：逐个符号回调而不汇总全部模块，callback 返回 false 时停止遍历
+// 没有任何可读模块时返回 NotFound
+pub fn list_imports_with(
+    caller_rule: &str,
+    mut callback: impl FnMut(&ModuleIdentity, &str) -> bool,
+) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    if caller_rule.is_empty() {
+        return Errno::InvalidArg;
+    }
+    runtime::for_each_module_symbol(caller_rule, false, &mut callback)
+}
+
+// list_exports 的流式版本，语义同 list_imports_with
+pub fn list_exports_with(
+    caller_rule: &str,
+    mut callback: impl FnMut(&ModuleIdentity, &str) -> bool,
+) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    if caller_rule.is_empty() {
+        return Errno::InvalidArg;
+    }
+    runtime::for_each_module_symbol(caller_rule, true, &mut callback)
+}
+
+// 注册 hook 之前预览其将加入的调用链，可据此拒绝与未知第三方 hook 共存
+// 只读：按 hook_single 相同的规则定位 slot，不修改状态也不写入内存；出错时返回空列表
+pub fn inspect_chain(
+    caller_rule: &str,
+    callee_rule: Option<&str>,
+    sym_name: &str,
+) -> Vec<ChainPreview> {
+    if in_external_callback() || caller_rule.is_empty() || sym_name.is_empty() {
+        return Vec::new();
+    }
+    runtime::inspect_chain(caller_rule, callee_rule, sym_name).unwrap_or_default()
+}
+
+// 匹配 caller 规则的各模块实例中已挂载 sym_name 的 slot 的 hub 调用链，用于排查 proxy 未被调用
+// 只读，调用进行中也可查询；运行时正在 clear / shutdown 时返回 Resetting
+pub fn get_proxy_chain(caller_rule: &str, sym_name: &str) -> Result<Vec<ProxyChain>, Errno> {
+    if in_external_callback() {
+        return Err(Errno::InitErrSafe);
+    }
+    if caller_rule.is_empty() || sym_name.is_empty() {
+        return Err(Errno::InvalidArg);
+    }
+    runtime::proxy_chains(caller_rule, sym_name)
+}
+
+// 匹配 caller 规则的各模块实例中 sym_name 的 GOT slot 地址及其当前值：(模块, slot 地址, 当前值)
+// 只读，无需先调用 init；运行时正在 clear / shutdown 时返回 Resetting
+pub fn get_import_slot_values(
+    caller_rule: &str,
+    sym_name: &str,
+) -> Result<Vec<(ModuleIdentity, usize, usize)>, Errno> {
+    if in_external_callback() {
+        return Err(Errno::InitErrSafe);
+    }
+    if caller_rule.is_empty() || sym_name.is_empty() {
+        return Err(Errno::InvalidArg);
+    }
+    runtime::import_slot_values(caller_rule, sym_name)
+}
+
+// 匹配 callee 规则的各模块实例中 sym_name 的定义地址，同一实例的全局定义在前、弱定义在后
+// 只读，无需先调用 init；运行时正在 clear / shutdown 时返回 Resetting
+pub fn get_export_address(
+    callee_rule: &str,
+    sym_name: &str,
+) -> Result<Vec<(ModuleIdentity, usize)>, Errno> {
+    if in_external_callback() {
+        return Err(Errno::InitErrSafe);
+    }
+    if callee_rule.is_empty() || sym_name.is_empty() {
+        return Err(Errno::InvalidArg);
+    }
+    runtime::export_addresses(callee_rule, sym_name)
+}
+
+// 将地址反查为所属模块中的符号：先经 dladdr 定位模块，再按范围扫描其动态符号表
+pub fn resolve_address(addr: usize) -> Option<ResolvedSymbol> {
+    if in_external_callback() || addr == 0 {
+        return None;
+    }
+    runtime::resolve_address(addr)
+}
+
+// 判断代码地址是否属于 hook 框架自身：只读已发布的快照，无锁，可在采样线程高频调用
+pub fn is_hook_artifact(addr: usize) -> Option<ArtifactKind> {
+    runtime::is_hook_artifact(addr)
+}
+
+// 手动模式下触发一次全量刷新，将待生效的 hook 应用到已加载模块
+pub fn refresh() -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::refresh()
+}
+
+// 只对 handle 对应的模块应用全部任务，适合手动模式下刚 dlopen 一个库的场景
+// 随其一同加载的依赖库与已卸载模块的清理仍需 refresh()；handle 无法解析时退回全量刷新
+pub fn refresh_handle(handle: *mut c_void) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::refresh_handle(handle)
+}
+
+// 自动模式下唤醒 monitor 线程做一轮全量刷新后立即返回，适合 monitor 观察不到的加载方式
+// 不阻塞调用线程，可在任意线程调用；手动模式或未初始化时返回 InitErrSafe
+pub fn request_refresh_async() -> Errno {
+    runtime::request_refresh_async()
+}
+
+// 同 request_refresh_async，但先按 handle 记录模块身份，再由 monitor 刷新
+pub fn request_refresh_async_handle(handle: *mut c_void) -> Errno {
+    runtime::request_refresh_async_handle(handle)
+}
+
+// 清除所有 hook 任务并重置运行时状态
+pub fn clear() {
+    if in_external_callback() {
+        return;
+    }
+    runtime::clear();
+}
+
+// 同 clear，但不强制释放仍可能在栈上的 hub：复位后最多等待 timeout 让活跃 proxy 调用返回再回收
+// 返回 retired hub 是否已全部释放，超时未释放的由后续 init 后的 refresh / monitor 周期回收
+pub fn clear_and_drain(timeout: Duration) -> bool {
+    if in_external_callback() {
+        return false;
+    }
+    runtime::clear_and_drain(timeout)
+}
+
+// 等待所有 hub 栈帧弹出（或超时）后立即回收全部 retired hub，不再等待 hub_destroy_delay
+// 返回是否已全部释放；适合测试收尾或宿主确认没有线程停留在 proxy 中时调用
+pub fn drain_retired_hubs(timeout: Duration) -> bool {
+    if in_external_callback() {
+        return false;
+    }
+    runtime::drain_retired_hubs(timeout)
+}
+
+// 彻底关闭运行时，适合宿主卸载本库前调用：在 clear() 基础上卸载全部信号 handler，
+// 并移除记录监听器与日志 sink；revert_cfi 为 true 时同时回滚 CFI slowpath 补丁
+// 返回 CFI 回滚结果，失败时其余清理仍会完成；之后可再次 init()
+pub fn shutdown(revert_cfi: bool) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::shutdown(revert_cfi)
+}
+
+// 当前已改写为 RET 或尝试改写失败的 CFI 地址，按地址排序；restore_cfi_slowpath 或 revert 后为空
+// recordable 开启或设置了记录监听器时，报告的变化同时以 CFI_PATCH 记录写入 records
+pub fn get_cfi_patch_report() -> Vec<CfiPatchEntry> {
+    runtime::cfi_patch_report()
+}
+
+// 将改写为 RET 的 __cfi_slowpath 入口恢复为原始指令并刷新指令缓存，其他模块（如 libart）重新获得 CFI 检查
+// 之后的刷新不再改写指令，补丁范围由 Global 降为 ModulesWithHooks，hook 所在模块的 GOT CFI slot 仍被替换
+// 再次 init 时按 InitOptions 的范围重新处理；存在恢复失败的地址时返回 CfiHookFailed
+pub fn restore_cfi_slowpath() -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::restore_cfi_slowpath()
+}
+
+// 运行时切换模式：Manual→Automatic 安装 dlopen/dlclose 监控并启动 monitor 线程，
+// Automatic→Manual 卸载内部监控任务并挂起 monitor 线程；get_mode() 立即反映新值
+// 切换期间发生的 dlopen 可能未被自动处理，需要一次手动 refresh()
+pub fn set_mode(mode: HookMode) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::set_mode(mode)
+}
+
+pub fn get_mode() -> HookMode {
+    if in_external_callback() {
+        return HookMode::Manual;
+    }
+    runtime::get_mode()
+}
+
+// 设置日志级别；set_debug(true) 等价于 Debug，set_debug(false) 等价于 Warn，init 按其 debug 参数同样设置
+pub fn set_log_level(level: LogLevel) {
+    if in_external_callback() {
+        return;
+    }
+    runtime::set_log_level(level);
+}
+
+pub fn get_log_level() -> LogLevel {
+    runtime::get_log_level()
+}
+
+// 当前级别为 Debug 时返回 true
+pub fn get_debug() -> bool {
+    if in_external_callback() {
+        return false;
+    }
+    runtime::get_debug()
+}
+
+pub fn set_debug(debug: bool) {
+    if in_external_callback() {
+        return;
+    }
+    runtime::set_debug(debug);
+}
+
+pub fn get_recordable() -> bool {
+    if in_external_callback() {
+        return false;
+    }
+    runtime::get_recordable()
+}
+
+pub fn set_recordable(recordable: bool) {
+    if in_external_callback() {
+        return;
+    }
+    runtime::set_recordable(recordable);
+}
+
+// 按字段掩码导出操作记录文本
+pub fn get_records(item_flags: u32) -> Option<String> {
+    if in_external_callback() {
+        return None;
+    }
+    runtime::get_records(item_flags)
+}
+
+// 结构化导出审计记录，item_flags 与 get_records 相同，用于按字段断言而不解析文本
+pub fn get_record_entries(item_flags: u32) -> Vec<RecordEntry> {
+    if in_external_callback() {
+        return Vec::new();
+    }
+    runtime::get_record_entries(item_flags)
+}
+
+// 设置记录缓冲区容量（1..=1048576 条，默认 4096），缩容时保留最新的记录
+pub fn set_record_capacity(max_entries: usize) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::set_record_capacity(max_entries)
+}
+
+pub fn get_record_capacity() -> usize {
+    if in_external_callback() {
+        return 0;
+    }
+    runtime::get_record_capacity()
+}
+
+// 缓冲区满或缩容时淘汰的记录数，clear() 后归零；dump_records 以 "# dropped: N" 首行输出同一数值
+pub fn get_record_dropped_count() -> u64 {
     if in_external_callback() {
-        return Errno::InitErrSafe;
+        return 0;
     }
-    runtime::init(mode, debug)
+    runtime::get_record_dropped_count()
 }
 
-// 按 caller 路径精确匹配单个模块进行 hook
-pub fn hook_single(
-    caller_path_name: &str,
-    callee_path_name: Option<&str>,
-    sym_name: &str,
-    new_func: *mut c_void,
-    hooked: Option<HookedCallback>,
-    hooked_arg: *mut c_void,
-) -> Option<HookStub> {
+// 设置记录监听器，替换已有监听器；不依赖 recordable，clear() 不会移除监听器
+pub fn set_record_listener(callback: RecordCallback, arg: *mut c_void) -> Errno {
     if in_external_callback() {
-        return None;
+        return Errno::InitErrSafe;
     }
-    runtime::hook_single(
-        caller_path_name,
-        callee_path_name,
-        sym_name,
-        new_func,
-        hooked,
-        hooked_arg,
-    )
+    runtime::set_record_listener(callback, arg)
 }
 
-// 通过自定义过滤器选择性 hook 多个 caller
-pub fn hook_partial(
-    caller_allow_filter: CallerAllowFilter,
-    caller_allow_filter_arg: *mut c_void,
-    callee_path_name: Option<&str>,
-    sym_name: &str,
-    new_func: *mut c_void,
-    hooked: Option<HookedCallback>,
-    hooked_arg: *mut c_void,
-) -> Option<HookStub> {
+// 移除记录监听器，尚未投递的记录随之丢弃
+pub fn clear_record_listener() -> Errno {
     if in_external_callback() {
-        return None;
+        return Errno::InitErrSafe;
     }
-    runtime::hook_partial(
-        caller_allow_filter,
-        caller_allow_filter_arg,
-        callee_path_name,
-        sym_name,
-        new_func,
-        hooked,
-        hooked_arg,
-    )
+    runtime::clear_record_listener()
 }
 
-// hook 所有已加载和未来加载的 caller 模块
-pub fn hook_all(
-    callee_path_name: Option<&str>,
-    sym_name: &str,
-    new_func: *mut c_void,
-    hooked: Option<HookedCallback>,
-    hooked_arg: *mut c_void,
-) -> Option<HookStub> {
+// 按字段掩码将操作记录写入文件描述符，首行为 "# dropped: N" 表示已淘汰的条数
+pub fn dump_records(fd: i32, item_flags: u32) -> Errno {
     if in_external_callback() {
-        return None;
+        return Errno::InitErrSafe;
     }
-    runtime::hook_all(callee_path_name, sym_name, new_func, hooked, hooked_arg)
+    runtime::dump_records(fd, item_flags)
 }
 
-// 卸载指定 hook 任务，同一调用点的其他任务不受影响
-pub fn unhook(stub: HookStub) -> Errno {
+// 设置日志 sink，keep_logcat 为 false 时日志只交给 sink；fork 子进程中不调用 sink，仍写 logcat
+pub fn set_log_sink(sink: LogSink, arg: *mut c_void, keep_logcat: bool) -> Errno {
     if in_external_callback() {
         return Errno::InitErrSafe;
     }
-    runtime::unhook(stub)
+    runtime::set_log_sink(sink, arg, keep_logcat);
+    Errno::Ok
 }
 
-// 将指定 caller 路径加入忽略列表，后续 hook 跳过该模块
-pub fn add_ignore(caller_path_name: &str) -> Errno {
+// 移除日志 sink，恢复只写 logcat
+pub fn clear_log_sink() -> Errno {
     if in_external_callback() {
         return Errno::InitErrSafe;
     }
-    runtime::add_ignore(caller_path_name)
+    runtime::clear_log_sink();
+    Errno::Ok
 }
 
-// 从 dlopen 句柄获取模块实例标识
-pub fn get_module_identity(handle: *mut c_void) -> Option<ModuleIdentity> {
+pub fn enable_debug(debug: bool) {
+    set_debug(debug);
+}
+
+// 启用或禁用 SIGSEGV/SIGBUS 信号保护
+pub fn enable_sigsegv_protection(flag: bool) {
     if in_external_callback() {
-        return None;
+        return;
     }
-    runtime::get_module_identity(handle)
+    runtime::enable_sigsegv_protection(flag);
 }
 
-// 从 dlopen 句柄获取模块标识，dlinfo 不可用时回退到符号探测
-pub fn get_module_identity_with_symbol(
-    handle: *mut c_void,
-    probe_symbol: &str,
-) -> Option<ModuleIdentity> {
+// 启用或禁用 SIGILL/SIGTRAP 信号保护（默认关闭），开启后守卫内的非法指令与断点陷阱同样跳回返回错误
+// 与 SIGSEGV/SIGBUS 处理器同模式安装，已初始化时立即生效；调试器依赖 SIGTRAP 时保持关闭
+pub fn enable_extended_signal_guard(flag: bool) {
     if in_external_callback() {
-        return None;
+        return;
     }
-    runtime::get_module_identity_with_symbol(handle, probe_symbol)
+    runtime::enable_extended_signal_guard(flag);
 }
 
-// 手动模式下触发一次全量刷新，将待生效的 hook 应用到已加载模块
-pub fn refresh() -> Errno {
+// 启用或禁用 sigchain 链首位置的周期性重新提升（默认开启），仅 sigchain 安装模式生效
+// monitor 线程每轮、Manual 模式每次 refresh 时按 30 秒间隔调用 EnsureFrontOfChain，dlopen 新加载 libart/libsigchain 后立即执行
+pub fn enable_sigchain_front_reassert(flag: bool) {
     if in_external_callback() {
-        return Errno::InitErrSafe;
+        return;
     }
-    runtime::refresh()
+    runtime::enable_sigchain_front_reassert(flag);
 }
 
-// 清除所有 hook 任务并重置运行时状态
-pub fn clear() {
+// 启用或禁用程序头的文件回退：首个 LOAD 段不可读时 open+pread 模块文件获取程序头
+// 沙箱内无法打开 /system 等路径时可关闭，此时该类模块被跳过
+pub fn enable_header_file_fallback(flag: bool) {
     if in_external_callback() {
         return;
     }
-    runtime::clear();
+    runtime::enable_header_file_fallback(flag);
 }
 
-pub fn get_mode() -> HookMode {
+// 启用或禁用 slot 写入后的回读校验（默认启用），包括刷新缓存后针对别名映射的 PatchVerifyFailed 确认
+// 校验发现写入无效的页会在模块实例存活期间被跳过，并以 WriteRejected / PatchVerifyFailed 上报
+pub fn enable_write_verification(flag: bool) {
     if in_external_callback() {
-        return HookMode::Manual;
+        return;
     }
-    runtime::get_mode()
+    runtime::enable_write_verification(flag);
 }
 
-pub fn get_debug() -> bool {
+// 启用后页面保护修改始终走原始 mprotect 系统调用（默认关闭）
+// 关闭时先调用 libc mprotect，被其他框架的代理拒绝时再以系统调用重试
+pub fn enable_raw_mprotect(flag: bool) {
     if in_external_callback() {
-        return false;
+        return;
     }
-    runtime::get_debug()
+    runtime::enable_raw_mprotect(flag);
 }
 
-pub fn set_debug(debug: bool) {
+// 启用或禁用 slot 完整性巡检（默认关闭）：refresh 与周期巡检时回读已挂载的 slot，
+// 被其他框架改写的重新写回并以 SlotTampered 上报；改写值形似外来 trampoline 时接为链尾而不覆盖
+pub fn set_slot_guard(flag: bool) {
     if in_external_callback() {
         return;
     }
-    runtime::set_debug(debug);
+    runtime::set_slot_guard(flag);
 }
 
-pub fn get_recordable() -> bool {
+pub fn is_slot_guard_enabled() -> bool {
+    runtime::is_slot_guard_enabled()
+}
+
+// 通配 hook 在单个模块内允许的最大匹配符号数（默认 64），0 返回 InvalidArg
+pub fn set_pattern_match_limit(limit: usize) -> Errno {
     if in_external_callback() {
-        return false;
+        return Errno::InitErrSafe;
     }
-    runtime::get_recordable()
+    runtime::set_pattern_match_limit(limit)
 }
 
-pub fn set_recordable(recordable: bool) {
+pub fn get_pattern_match_limit() -> usize {
+    runtime::get_pattern_match_limit()
+}
+
+// 因写入被拒绝而加入黑名单的 slot 页累计数
+pub fn get_write_rejected_count() -> u64 {
+    runtime::write_rejected_page_count()
+}
+
+// 写入并刷新后回读不一致（PatchVerifyFailed）的累计次数，不含触发保护的写入失败
+pub fn get_patch_verify_failed_count() -> u64 {
+    runtime::patch_verify_failure_count()
+}
+
+// 本库改写页面保护发出的 mprotect 累计次数（调试用），同页的批量写入只计一对
+pub fn get_mprotect_count() -> u64 {
+    runtime::mprotect_count()
+}
+
+// 写入后恢复原始页面保护失败的累计次数，失败不影响 hook 结果，页面保持可写
+pub fn get_protect_restore_failed_count() -> u64 {
+    runtime::protect_restore_failure_count()
+}
+
+// 守卫 handler 被重新提升到 sigchain 链首的累计次数
+pub fn get_sigchain_reassert_count() -> u64 {
+    runtime::sigchain_reassert_count()
+}
+
+// 启用或禁用 loader 符号不可用时的最后一级回退：持 linker 内部 g_dl_mutex 调用 do_dlopen
+// 该路径依赖 linker 私有符号与锁布局，默认仅在必需的 API 24/25 上启用；禁用时回退到标准 dlopen
+pub fn enable_linker_internal_fallback(flag: bool) {
     if in_external_callback() {
         return;
     }
-    runtime::set_recordable(recordable);
+    runtime::enable_linker_internal_fallback(flag);
 }
 
-// 按字段掩码导出操作记录文本
-pub fn get_records(item_flags: u32) -> Option<String> {
+pub fn get_linker_fallback_stats() -> LinkerFallbackStats {
+    runtime::linker_fallback_stats()
+}
+
+pub fn get_safe_read_stats() -> SafeReadStats {
+    runtime::safe_read_stats()
+}
+
+pub fn get_guard_slot_stats() -> GuardSlotStats {
+    runtime::guard_slot_stats()
+}
+
+pub fn get_guard_fault_stats() -> GuardFaultStats {
+    runtime::guard_fault_stats()
+}
+
+// 启用或禁用模块扫描快照采集（默认关闭），开启时丢弃之前的采集
+// 每轮刷新记录 phdr / maps 枚举结果、hint 缓存、dlinfo 可用性与合并后的模块列表，仅保留最近若干轮
+pub fn enable_scan_snapshot_capture(flag: bool) {
     if in_external_callback() {
-        return None;
+        return;
     }
-    runtime::get_records(item_flags)
+    runtime::enable_scan_snapshot_capture(flag);
 }
 
-// 按字段掩码将操作记录写入文件描述符
-pub fn dump_records(fd: i32, item_flags: u32) -> Errno {
+// 将已采集的扫描快照写入文件描述符，内容包含完整模块路径与地址，仅用于诊断
+pub fn dump_scan_snapshots(fd: i32) -> Errno {
     if in_external_callback() {
         return Errno::InitErrSafe;
     }
-    runtime::dump_records(fd, item_flags)
+    runtime::dump_scan_snapshots(fd)
 }
 
-pub fn enable_debug(debug: bool) {
-    set_debug(debug);
+// 将运行时状态写入文件描述符：每个 slot 的 caller、地址、原函数、trampoline 与按调用顺序的 proxy 链，
+// 每个任务的规则与已绑定 slot 数，以及 retired hub 与活跃栈帧计数；内容不做脱敏，仅用于本地诊断
+pub fn dump_state(fd: i32) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::dump_state(fd)
 }
 
-// 启用或禁用 SIGSEGV/SIGBUS 信号保护
-pub fn enable_sigsegv_protection(flag: bool) {
+// 同 dump_state，以字符串返回
+pub fn get_state_dump() -> String {
     if in_external_callback() {
-        return;
+        return String::new();
     }
-    runtime::enable_sigsegv_protection(flag);
+    runtime::state_dump_text()
 }
 
 // 在 proxy 中获取调用链的下一个函数指针
+// fork 子进程中线程状态不可用，改为按全局 proxy 索引查找下一个启用的 proxy 或原函数：
+// 不做递归检测，同一 proxy 挂在多个调用点时按首个包含它的调用点解析
 pub fn get_prev_func(func: *mut c_void) -> *mut c_void {
     runtime::get_prev_func(func)
 }
@@ -289,11 +1821,149 @@ where
     runtime::with_prev_func(func, f)
 }
 
+// 可由 prev 指针还原的 extern "C" 函数指针类型，已为 0~12 个参数的 fn / unsafe fn 实现
+// 其他类型（如变参函数）可自行 unsafe impl，须保证与裸函数指针布局一致
+pub unsafe trait PrevFn: Copy + 'static {
+    // ptr 非空且指向签名一致的函数
+    unsafe fn from_prev(ptr: *mut c_void) -> Self;
+}
+
+macro_rules! impl_prev_fn {
+    ($($arg:ident),*) => {
+        unsafe impl<Ret: 'static, $($arg: 'static),*> PrevFn for extern "C" fn($($arg),*) -> Ret {
+            unsafe fn from_prev(ptr: *mut c_void) -> Self {
+                unsafe { std::mem::transmute_copy(&ptr) }
+            }
+        }
+
+        unsafe impl<Ret: 'static, $($arg: 'static),*> PrevFn
+            for unsafe extern "C" fn($($arg),*) -> Ret
+        {
+            unsafe fn from_prev(ptr: *mut c_void) -> Self {
+                unsafe { std::mem::transmute_copy(&ptr) }
+            }
+        }
+    };
+}
+
+impl_prev_fn!();
+impl_prev_fn!(A1);
+impl_prev_fn!(A1, A2);
+impl_prev_fn!(A1, A2, A3);
+impl_prev_fn!(A1, A2, A3, A4);
+impl_prev_fn!(A1, A2, A3, A4, A5);
+impl_prev_fn!(A1, A2, A3, A4, A5, A6);
+impl_prev_fn!(A1, A2, A3, A4, A5, A6, A7);
+impl_prev_fn!(A1, A2, A3, A4, A5, A6, A7, A8);
+impl_prev_fn!(A1, A2, A3, A4, A5, A6, A7, A8, A9);
+impl_prev_fn!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10);
+impl_prev_fn!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11);
+impl_prev_fn!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12);
+
+fn typed_prev<F: PrevFn>(prev: *mut c_void) -> Option<F> {
+    if prev.is_null() {
+        return None;
+    }
+    Some(unsafe { F::from_prev(prev) })
+}
+
+// with_prev_func 的类型化版本，prev 为空时传入 None
+pub fn with_prev_func_as<F: PrevFn, R>(
+    func: *mut c_void,
+    f: impl FnOnce(Option<F>) -> R,
+) -> Option<R> {
+    runtime::with_prev_func(func, |prev| f(typed_prev(prev)))
+}
+
+// get_prev_func 的类型化版本，配合 proxy_leave 手动管理栈帧
+pub fn prev_func_as<F: PrevFn>(func: *mut c_void) -> Option<F> {
+    typed_prev(runtime::get_prev_func(func))
+}
+
+// fork 子进程中 get_prev_func / with_prev_func 走索引回退的累计次数
+pub fn get_fork_prev_fallback_count() -> u64 {
+    runtime::fork_prev_fallback_count()
+}
+
+// Hub 栈溢出区与溢出绕过的累计统计，见 InitOptions::hub_stack_spill
+pub fn get_hub_stack_stats() -> HubStackStats {
+    runtime::hub_stack_stats()
+}
+
+pub fn get_thread_state_stats() -> ThreadStateStats {
+    runtime::thread_state_stats()
+}
+
+// 清零 ThreadStateStats 中的计数，供测试在一轮负载前取基线
+pub fn reset_thread_state_stats() {
+    runtime::reset_thread_state_stats()
+}
+
+// 切换 HookedCallback 投递方式；从 DedicatedThread 切回 Inline 时阻塞到队列中的事件投递完毕
+// 异步模式下队列有界，溢出的事件被丢弃并计数，clear() 会丢弃尚未投递的事件
+// dlopen pre/post 回调需在加载前后同步执行，不受此设置影响
+pub fn set_callback_dispatch(mode: CallbackDispatch) {
+    if in_external_callback() {
+        return;
+    }
+    runtime::set_callback_dispatch(mode);
+}
+
+pub fn get_callback_dispatch() -> CallbackDispatch {
+    runtime::get_callback_dispatch()
+}
+
+// 异步投递模式下因队列已满被丢弃的回调事件累计数
+pub fn get_dropped_callback_count() -> u64 {
+    runtime::dropped_callback_count()
+}
+
+// 自动模式下 dlopen 事件的合并窗口：monitor 被唤醒后再等待该时长收集后续加载的模块，合并为一次刷新
+// 默认 30ms，上限 500ms，传 0 关闭合并；宜在 init 之前设置，运行中修改从下一轮刷新生效
+// 手动 refresh() 不经过 monitor，不受此设置影响
+pub fn set_monitor_debounce(window: Duration) {
+    if in_external_callback() {
+        return;
+    }
+    runtime::set_monitor_debounce(window);
+}
+
+// 自动模式下由 dlopen/dlclose 事件触发的刷新轮数累计值，不含周期性兜底巡检
+pub fn get_event_refresh_count() -> u64 {
+    runtime::event_refresh_count()
+}
+
+// 自动模式 monitor 的刷新轮数累计值，按是否枚举全部模块区分
+pub fn get_monitor_refresh_counts() -> MonitorRefreshCounts {
+    runtime::monitor_refresh_counts()
+}
+
+// 自动模式 monitor 的线程、策略与巡检状态，用于发现 loader→legacy 回退或周期巡检升级
+pub fn get_monitor_status() -> MonitorStatus {
+    runtime::monitor_status()
+}
+
+// 运行时能力探测结果，init 成功之前返回 None
+pub fn get_capabilities() -> Option<Capabilities> {
+    runtime::capabilities()
+}
+
 // 获取当前 proxy 调用的返回地址
 pub fn get_return_address() -> *mut c_void {
     runtime::get_return_address()
 }
 
+// 由内向外把当前线程全部存活 hub 帧的返回地址写入 buf，返回写入数；buf 不足时只写最内层的 buf.len() 个
+// 多层被 hook 的调用嵌套时可据此拼出轻量调用栈；不分配内存、不加锁，可在采样信号处理函数中调用
+pub fn get_hub_backtrace(buf: &mut [usize]) -> usize {
+    runtime::get_hub_backtrace(buf)
+}
+
+// 获取当前 proxy 调用方所在模块，不在 hub 调用链内或反查失败时返回 None
+pub fn get_caller_module() -> Option<ModuleIdentity> {
+    runtime::get_caller_module()
+}
+
 // 手动弹出 trampoline 栈帧
 pub fn pop_stack(return_address: *mut c_void) {
     runtime::pop_stack(return_address)
@@ -309,6 +1979,111 @@ pub fn proxy_leave(func: *mut c_void) {
     runtime::proxy_leave(func)
 }
 
+// proxy_enter / proxy_leave 的 RAII 守卫，提前返回或 panic 展开时同样释放栈帧
+// entered() 为 false 表示命中递归环，proxy 应直接调用原函数；fork 子进程中没有线程状态，守卫恒为已进入且不做任何操作
+pub struct ProxyScope {
+    func: *mut c_void,
+    entered: bool,
+    active: bool,
+}
+
+impl ProxyScope {
+    pub fn new(func: *mut c_void) -> Self {
+        if runtime::is_forked_child() {
+            return Self {
+                func,
+                entered: true,
+                active: false,
+            };
+        }
+        Self {
+            func,
+            entered: runtime::proxy_enter(func),
+            active: true,
+        }
+    }
+
+    pub fn entered(&self) -> bool {
+        self.entered
+    }
+}
+
+impl Drop for ProxyScope {
+    fn drop(&mut self) {
+        if self.active {
+            runtime::proxy_scope_leave(self.func, self.entered);
+        }
+    }
+}
+
+// 在当前线程的观测抑制作用域内执行 f，可嵌套
+// 作用域内被 hook 的调用仍正常沿 prev 链转发，proxy 通过 is_observation_suppressed 决定是否产生观测数据
+pub fn with_observation_suppressed<R>(f: impl FnOnce() -> R) -> R {
+    struct SuppressGuard;
+
+    impl Drop for SuppressGuard {
+        fn drop(&mut self) {
+            runtime::suppress_end();
+        }
+    }
+
+    runtime::suppress_begin();
+    let _guard = SuppressGuard;
+    f()
+}
+
+pub fn is_observation_suppressed() -> bool {
+    runtime::is_suppressed()
+}
+
+// 供外部编译的 proxy 使用的抑制作用域，begin/end 需在同一线程成对调用
+#[unsafe(no_mangle)]
+pub extern "C" fn srx_hook_suppress_begin() {
+    runtime::suppress_begin()
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn srx_hook_suppress_end() {
+    runtime::suppress_end()
+}
+
+// 当前线程是否经过 hook：关闭后本线程的调用直接转发原函数，不压 hub 栈也不进入任何 proxy
+// 新线程默认启用；fork 子进程没有线程状态，设置不生效、恒为启用
+pub fn set_thread_hook_enabled(enabled: bool) {
+    let _ = runtime::set_thread_hook_enabled(enabled);
+}
+
+pub fn is_thread_hook_enabled() -> bool {
+    runtime::is_thread_hook_enabled()
+}
+
+// 作用域内暂停当前线程的 hook，析构时恢复进入前的状态，可嵌套；只能在创建它的线程上释放
+pub struct ThreadHookPause {
+    prev: bool,
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl ThreadHookPause {
+    pub fn new() -> Self {
+        Self {
+            prev: runtime::set_thread_hook_enabled(false),
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl Default for ThreadHookPause {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ThreadHookPause {
+    fn drop(&mut self) {
+        let _ = runtime::set_thread_hook_enabled(self.prev);
+    }
+}
+
 // 注册 dlopen 前后回调
 pub fn add_dlopen_callback(
     pre: Option<PreDlopenCallback>,
@@ -332,3 +2107,44 @@ pub fn del_dlopen_callback(
     }
     runtime::del_dlopen_callback(pre, post, data)
 }
+
+// 注册 dlopen 过滤器，按 (filter, data) 去重；多个过滤器按注册顺序询问，首个非 Allow 的决定生效
+// 过滤器内部发起的 dlopen 不再经过过滤链；仅自动模式的 monitor 能拦截 dlopen
+pub fn add_dlopen_filter(filter: DlopenFilterCallback, data: *mut c_void) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::add_dlopen_filter(filter, data)
+}
+
+// 注销 dlopen 过滤器
+pub fn del_dlopen_filter(filter: DlopenFilterCallback, data: *mut c_void) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::del_dlopen_filter(filter, data)
+}
+
+// 注册 dlclose 前后回调，仅自动模式的 monitor 能观测到 dlclose
+pub fn add_dlclose_callback(
+    pre: Option<PreDlcloseCallback>,
+    post: Option<PostDlcloseCallback>,
+    data: *mut c_void,
+) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::add_dlclose_callback(pre, post, data)
+}
+
+// 注销 dlclose 前后回调
+pub fn del_dlclose_callback(
+    pre: Option<PreDlcloseCallback>,
+    post: Option<PostDlcloseCallback>,
+    data: *mut c_void,
+) -> Errno {
+    if in_external_callback() {
+        return Errno::InitErrSafe;
+    }
+    runtime::del_dlclose_callback(pre, post, data)
+}
@@ -3,6 +3,7 @@
 use crate::errno::Errno;
 use crate::log;
 use crate::android::memory as util;
+use std::cell::Cell;
 use std::collections::BTreeSet;
 use std::ffi::{CStr, c_char};
 use std::mem;
@@ -15,10 +16,17 @@ mod hash;
 mod packed;
 // 重定位条目的 r_sym / r_type 提取
 mod reloc;
+// DT_RELR 相对重定位位图解码
+mod relr;
+// 内存头部不可读时从文件读取程序头
+mod header_file;
 
 use hash::{elf_gnu_hash, elf_hash};
 use packed::PackedRelocIterator;
-use reloc::{elf_r_sym, elf_r_type};
+use reloc::{UnsupportedReloc, elf_r_sym, elf_r_type, unsupported_reloc};
+use relr::relr_covers;
+
+pub use header_file::FileHeaders;
 
 // ELF header e_ident 相关常量
 const EI_NIDENT: usize = 16;
@@ -36,8 +44,17 @@ const EV_CURRENT: u8 = 1;
 const ET_EXEC: u16 = 2;
 const ET_DYN: u16 = 3;
 const SHN_UNDEF: u16 = 0;
+// 符号绑定类型（st_info 高 4 位）
+const STB_GLOBAL: u8 = 1;
+const STB_WEAK: u8 = 2;
+const STB_GNU_UNIQUE: u8 = 10;
+// 符号类型（st_info 低 4 位）
+const STT_TLS: u8 = 6;
 const PT_LOAD: u32 = 1;
 const PT_DYNAMIC: u32 = 2;
+const PT_NOTE: u32 = 4;
+const PT_GNU_RELRO: u32 = 0x6474e552;
+const PF_X: u32 = 1;
 
 // dynamic section 标签常量
 const DT_NULL: i64 = 0;
@@ -47,17 +64,47 @@ const DT_STRTAB: i64 = 5;
 const DT_SYMTAB: i64 = 6;
 const DT_RELA: i64 = 7;
 const DT_RELASZ: i64 = 8;
+const DT_STRSZ: i64 = 10;
+const DT_SONAME: i64 = 14;
 const DT_REL: i64 = 17;
 const DT_RELSZ: i64 = 18;
 const DT_PLTREL: i64 = 20;
+const DT_TEXTREL: i64 = 22;
 const DT_JMPREL: i64 = 23;
+const DT_FLAGS: i64 = 30;
+const DT_RELRSZ: i64 = 35;
+const DT_RELR: i64 = 36;
+const DT_RELRENT: i64 = 37;
 const DT_GNU_HASH: i64 = 0x6ffffef5;
+const DT_VERSYM: i64 = 0x6ffffff0;
+const DT_VERDEF: i64 = 0x6ffffffc;
+const DT_VERDEFNUM: i64 = 0x6ffffffd;
+const DT_VERNEED: i64 = 0x6ffffffe;
+const DT_VERNEEDNUM: i64 = 0x6fffffff;
+
+// versym 条目：低 15 位为版本索引，最高位表示非默认版本（foo@V 而非 foo@@V）
+const VERSYM_VERSION: u16 = 0x7fff;
+const VERSYM_HIDDEN: u16 = 0x8000;
+// 版本索引 0 / 1 分别为局部与无版本的全局符号
+const VER_NDX_GLOBAL: u16 = 1;
 
 // Android 私有 packed relocation 标签
 const DT_ANDROID_REL: i64 = 0x6000_000f;
 const DT_ANDROID_RELA: i64 = 0x6000_0010;
 const DT_ANDROID_RELSZ: i64 = 0x6000_0011;
 const DT_ANDROID_RELASZ: i64 = 0x6000_0012;
+// 标准 DT_RELR 之前 Android 使用的私有编号，编码格式相同
+const DT_ANDROID_RELR: i64 = 0x6fff_e000;
+const DT_ANDROID_RELRSZ: i64 = 0x6fff_e001;
+const DT_ANDROID_RELRENT: i64 = 0x6fff_e003;
+
+// DT_FLAGS 中表示存在 text relocation 的标志位
+const DF_TEXTREL: u64 = 0x4;
+
+// 头部与各表条目数上限，超过时按损坏的映像处理
+const MAX_PHNUM: usize = 256;
+const MAX_DYN_ENTRIES: usize = 4096;
+const MAX_RELOC_ENTRIES: usize = 1 << 22;
 
 const EM_AARCH64: u16 = 183;
 const EM_X86_64: u16 = 62;
@@ -66,11 +113,21 @@ const EM_X86_64: u16 = 62;
 const R_AARCH64_JUMP_SLOT: u32 = 1026;
 const R_AARCH64_GLOB_DAT: u32 = 1025;
 const R_AARCH64_ABS64: u32 = 257;
+const R_AARCH64_TLS_DTPMOD64: u32 = 1028;
+const R_AARCH64_TLS_DTPREL64: u32 = 1029;
+const R_AARCH64_TLS_TPREL64: u32 = 1030;
+const R_AARCH64_TLSDESC: u32 = 1031;
+const R_AARCH64_IRELATIVE: u32 = 1032;
 
 // x86_64 重定位类型
 const R_X86_64_JUMP_SLOT: u32 = 7;
 const R_X86_64_GLOB_DAT: u32 = 6;
 const R_X86_64_64: u32 = 1;
+const R_X86_64_DTPMOD64: u32 = 16;
+const R_X86_64_DTPOFF64: u32 = 17;
+const R_X86_64_TPOFF64: u32 = 18;
+const R_X86_64_TLSDESC: u32 = 36;
+const R_X86_64_IRELATIVE: u32 = 37;
 
 #[cfg(target_arch = "aarch64")]
 const EXPECTED_MACHINE: u16 = EM_AARCH64;
@@ -171,6 +228,47 @@ struct ElfRela {
     r_addend: ElfSxword,
 }
 
+// ELF64 版本定义（.gnu.version_d），首个 aux 条目为版本名
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ElfVerdef {
+    vd_version: ElfHalf,
+    vd_flags: ElfHalf,
+    vd_ndx: ElfHalf,
+    vd_cnt: ElfHalf,
+    vd_hash: ElfWord,
+    vd_aux: ElfWord,
+    vd_next: ElfWord,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ElfVerdaux {
+    vda_name: ElfWord,
+    vda_next: ElfWord,
+}
+
+// ELF64 版本需求（.gnu.version_r），每个依赖库一项，aux 链为所需的各个版本
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ElfVerneed {
+    vn_version: ElfHalf,
+    vn_cnt: ElfHalf,
+    vn_file: ElfWord,
+    vn_aux: ElfWord,
+    vn_next: ElfWord,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ElfVernaux {
+    vna_hash: ElfWord,
+    vna_flags: ElfHalf,
+    vna_other: ElfHalf,
+    vna_name: ElfWord,
+    vna_next: ElfWord,
+}
+
 // 已解析的 ELF 映像，持有 hook 所需的全部元数据
 pub struct Elf {
     pathname: String,
@@ -178,21 +276,33 @@ pub struct Elf {
     base_addr: usize,
     // base_addr 与 ELF 虚拟地址之间的偏移量
     bias_addr: usize,
-    ehdr: *const ElfEhdr,
-    phdr: *const ElfPhdr,
+    // 程序头副本，来源为内存映像或磁盘文件
+    phdrs: Vec<ElfPhdr>,
     dyn_section: *const ElfDyn,
     dyn_sz: usize,
     strtab: *const c_char,
     symtab: *const ElfSym,
+    // DT_SONAME 在 strtab 中的偏移
+    soname_off: Option<usize>,
+    // 符号版本表：versym 与 dynsym 一一对应，verdef / verneed 为版本名来源
+    versym: *const u16,
+    verdef: usize,
+    verdef_cnt: usize,
+    verneed: usize,
+    verneed_cnt: usize,
     // .rel(a).plt 段地址与大小
     relplt: usize,
     relplt_sz: usize,
     // .rel(a).dyn 段地址与大小
     reldyn: usize,
     reldyn_sz: usize,
-    // Android packed relocation 段地址与大小
+    // Android packed relocation 段地址与大小，是否带 addend 由 DT_ANDROID_REL / DT_ANDROID_RELA 决定
     relandroid: usize,
     relandroid_sz: usize,
+    relandroid_is_rela: bool,
+    // DT_RELR 相对重定位表地址与大小
+    relr: usize,
+    relr_sz: usize,
     // hash 表的 bucket 数组与计数
     bucket: *const u32,
     bucket_cnt: u32,
@@ -207,9 +317,51 @@ pub struct Elf {
     symoffset: u32,
     is_use_gnu_hash: bool,
     is_use_rela: bool,
+    // DT_TEXTREL 或 DF_TEXTREL：存在直接修补 .text 的重定位，部分调用不经过 GOT
+    has_text_relocs: bool,
+    // 已输出过跳过日志的不支持重定位类别，按 UnsupportedReloc::bit 置位
+    unsupported_logged: Cell<u8>,
 }
 
+// 单个符号按重定位类别划分的 GOT slot
+#[derive(Default)]
+pub struct GotSlots {
+    // .rel(a).plt 中的 JUMP_SLOT，对应直接调用
+    pub plt: Vec<usize>,
+    // GLOB_DAT / ABS，对应取函数地址存入数据的位置
+    pub data: Vec<usize>,
+}
+
+impl GotSlots {
+    pub fn is_empty(&self) -> bool {
+        self.plt.is_empty() && self.data.is_empty()
+    }
+
+    pub fn into_vec(self) -> Vec<usize> {
+        let mut all = self.plt;
+        all.extend(self.data);
+        all
+    }
+}
+
+// 收集过程中的候选 slot；有 callee 过滤时 plt / data 只收已解析且值匹配的 slot
+#[derive(Default)]
+struct SlotCandidates {
+    plt: BTreeSet<usize>,
+    data: BTreeSet<usize>,
+    // 尚未解析、值指向 PLT stub 的调用槽，是否接受取决于同一符号已解析 slot 的结论
+    lazy_plt: BTreeSet<usize>,
+    // 存在已解析但指向其他定义的 slot
+    rejected: bool,
+    // 符号出现在不支持改写的重定位中（IRELATIVE / TLS），没有可用 slot 时据此说明原因
+    unsupported: bool,
+}
 
 include!("elf/check_init.inc.rs");
 include!("elf/api.inc.rs");
 include!("elf/lookup.inc.rs");
+include!("elf/inspect.inc.rs");
+include!("elf/version.inc.rs");
+
+#[cfg(test)]
+mod tests;
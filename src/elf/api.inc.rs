@@ -2,6 +2,7 @@
 
 impl Elf {
     // 对指定符号执行 PLT/GOT hook，依次扫描 .rel(a).plt、.rel(a).dyn、.rel(a).android
+    // symbol 可写作 "name@VERSION" 只替换绑定到该版本的导入
     pub unsafe fn hook(
         &self,
         symbol: &str,
@@ -14,7 +15,8 @@ impl Elf {
 
         log::info(format_args!("hooking {} in {}", symbol, self.pathname));
 
-        let symidx = match self.find_symidx_by_name(symbol) {
+        let (name, version) = split_symbol_version(symbol);
+        let symidx = match self.find_symidx_by_name(name, version) {
             Ok(symidx) => symidx,
             Err(Errno::NotFound) => return Ok(()),
             Err(err) => return Err(err),
@@ -126,16 +128,15 @@ impl Elf {
         }
 
         if self.relandroid != 0 {
-            let mut packed =
-                PackedRelocIterator::new(self.relandroid, self.relandroid_sz, self.is_use_rela)?;
+            let mut packed = self.packed_relocs()?;
             while let Some(reloc) = packed.next()? {
                 self.find_and_replace(
-                    if self.is_use_rela {
+                    if self.relandroid_is_rela {
                         ".rela.android"
                     } else {
                         ".rel.android"
                     },
-                    false,
+                    elf_r_type(reloc.r_info) == R_GENERIC_JUMP_SLOT,
                     symbol,
                     new_func,
                     old_func,
@@ -151,30 +152,133 @@ impl Elf {
     }
 
     // 通过符号名查找导出函数的绝对地址，未定义或值为 0 时返回 None
-    pub fn find_export_function(&self, symbol: &str) -> Option<usize> {
-        let symidx = self.find_symidx_by_name(symbol).ok()?;
-        unsafe {
-            let sym = &*self.symtab.add(symidx as usize);
+    // version 为 None 时接受任意版本，优先全局定义，没有全局定义时退回弱定义
+    pub fn find_export_function(&self, symbol: &str, version: Option<&str>) -> Option<usize> {
+        self.find_export_candidates(symbol, version)
+            .first()
+            .copied()
+    }
+
+    // 同名导出的全部定义地址，顺序同 find_symidx_candidates，重复地址只保留一次
+    pub fn find_export_candidates(&self, symbol: &str, version: Option<&str>) -> Vec<usize> {
+        let Ok(candidates) = self.find_symidx_candidates(symbol, version) else {
+            return Vec::new();
+        };
+        let mut addrs = Vec::new();
+        for symidx in candidates {
+            let sym = unsafe { &*self.symtab.add(symidx as usize) };
             if sym.st_shndx == SHN_UNDEF || sym.st_value == 0 {
-                return None;
+                continue;
+            }
+            let addr = self.bias_addr + sym.st_value as usize;
+            if !addrs.contains(&addr) {
+                addrs.push(addr);
+            }
+        }
+        addrs
+    }
+
+    pub fn has_text_relocs(&self) -> bool {
+        self.has_text_relocs
+    }
+
+    // 从内存中的 PT_NOTE 读取 GNU build-id，note 所在页不可读时由调用方的信号保护兜底
+    pub unsafe fn build_id(&self) -> Option<Vec<u8>> {
+        self.phdrs
+            .iter()
+            .filter(|ph| ph.p_type == PT_NOTE && ph.p_memsz != 0)
+            .find_map(|ph| {
+                let notes = slice::from_raw_parts(
+                    (self.bias_addr + ph.p_vaddr as usize) as *const u8,
+                    ph.p_memsz as usize,
+                );
+                header_file::find_build_id(notes).map(<[u8]>::to_vec)
+            })
+    }
+
+    // 统计 .rel(a).dyn / .rel(a).android 中引用该符号且修补位置位于可执行段的重定位数量
+    // 这些调用点不经过 GOT，默认无法被 hook，用于量化覆盖盲区
+    pub unsafe fn count_text_relocs(
+        &self,
+        symbol: &str,
+        version: Option<&str>,
+    ) -> Result<usize, Errno> {
+        if !self.has_text_relocs {
+            return Ok(0);
+        }
+        let symidx = match self.find_symidx_by_name(symbol, version) {
+            Ok(value) => value,
+            Err(Errno::NotFound) => return Ok(0),
+            Err(err) => return Err(err),
+        };
+
+        let mut count = 0usize;
+        let mut visit = |r_offset: usize, r_info: ElfXword| {
+            if elf_r_sym(r_info) == symidx
+                && self.is_addr_in_exec_segments(self.bias_addr + r_offset)
+            {
+                count += 1;
+            }
+        };
+
+        if self.reldyn != 0 {
+            if self.is_use_rela {
+                let cnt = self.reldyn_sz / mem::size_of::<ElfRela>();
+                let relas = slice::from_raw_parts(self.reldyn as *const ElfRela, cnt);
+                for rela in relas {
+                    visit(rela.r_offset as usize, rela.r_info);
+                }
+            } else {
+                let cnt = self.reldyn_sz / mem::size_of::<ElfRel>();
+                let rels = slice::from_raw_parts(self.reldyn as *const ElfRel, cnt);
+                for rel in rels {
+                    visit(rel.r_offset as usize, rel.r_info);
+                }
+            }
+        }
+
+        if self.relandroid != 0 {
+            let mut packed = self.packed_relocs()?;
+            while let Some(reloc) = packed.next()? {
+                visit(reloc.r_offset, reloc.r_info);
             }
-            Some(self.bias_addr + sym.st_value as usize)
         }
+
+        Ok(count)
     }
 
-    // 收集指定符号的所有 GOT slot 地址，可选按 callee 地址过滤
+    // 收集指定符号（可限定版本）的所有 GOT slot 地址，可选按 callee 地址过滤
     pub unsafe fn find_got_slots(
         &self,
         symbol: &str,
+        version: Option<&str>,
         callee_addrs: Option<&BTreeSet<usize>>,
     ) -> Result<Vec<usize>, Errno> {
-        let symidx = match self.find_symidx_by_name(symbol) {
+        self.find_got_slots_by_class(symbol, version, callee_addrs)
+            .map(GotSlots::into_vec)
+    }
+
+    // 按重定位类别收集 GOT slot：JUMP_SLOT 为调用点，GLOB_DAT/ABS 为取地址的数据槽
+    // callee 过滤逐个 slot 判断值是否指向目标定义，lazy 调用槽跟随同一符号已解析 slot 的结论，避免只 hook 一半
+    pub unsafe fn find_got_slots_by_class(
+        &self,
+        symbol: &str,
+        version: Option<&str>,
+        callee_addrs: Option<&BTreeSet<usize>>,
+    ) -> Result<GotSlots, Errno> {
+        let symidx = match self.find_symidx_by_name(symbol, version) {
             Ok(value) => value,
-            Err(Errno::NotFound) => return Ok(Vec::new()),
+            Err(Errno::NotFound) => return Ok(GotSlots::default()),
             Err(err) => return Err(err),
         };
 
-        let mut slots = BTreeSet::new();
+        // TLS 变量的导入只经 TLS 类重定位访问，没有可改写的函数地址
+        if self.is_tls_symbol(symidx) {
+            self.note_unsupported(UnsupportedReloc::Tls);
+            return Err(Errno::UnsupportedReloc);
+        }
+
+        let mut slots = SlotCandidates::default();
 
         if self.relplt != 0 {
             let relplt_cnt = self.relplt_sz
@@ -245,12 +349,11 @@ impl Elf {
         }
 
         if self.relandroid != 0 {
-            let mut packed =
-                PackedRelocIterator::new(self.relandroid, self.relandroid_sz, self.is_use_rela)?;
+            let mut packed = self.packed_relocs()?;
             while let Some(reloc) = packed.next()? {
                 self.collect_slot(
                     &mut slots,
-                    false,
+                    elf_r_type(reloc.r_info) == R_GENERIC_JUMP_SLOT,
                     symidx,
                     callee_addrs,
                     reloc.r_offset,
@@ -259,13 +362,105 @@ impl Elf {
             }
         }
 
-        Ok(slots.into_iter().collect())
+        if slots.plt.is_empty() && slots.data.is_empty() && slots.unsupported {
+            return Err(Errno::UnsupportedReloc);
+        }
+        // lazy slot 与同一符号的已解析 slot 绑定到同一定义：有匹配的已解析 slot 时一并接受，
+        // 全部已解析 slot 都指向其他定义时一并拒绝，没有已解析 slot 时按单一候选启发式接受
+        let resolved_matched = !slots.plt.is_empty() || !slots.data.is_empty();
+        if resolved_matched || !slots.rejected {
+            slots.plt.append(&mut slots.lazy_plt);
+        }
+        Ok(GotSlots {
+            plt: slots.plt.into_iter().collect(),
+            data: slots.data.into_iter().collect(),
+        })
+    }
+
+    // 列出 JUMP_SLOT / GLOB_DAT / ABS 重定位引用的符号中名称满足 filter 的部分，按名称去重排序
+    // 供通配 hook 展开为具体符号名，之后逐个名称走 find_got_slots_by_class
+    pub unsafe fn find_import_names(
+        &self,
+        filter: impl Fn(&str) -> bool,
+    ) -> Result<Vec<String>, Errno> {
+        let mut symidxs = BTreeSet::new();
+        let mut visit = |is_plt: bool, r_info: ElfXword| {
+            let r_type = elf_r_type(r_info);
+            let wanted = if is_plt {
+                r_type == R_GENERIC_JUMP_SLOT
+            } else {
+                r_type == R_GENERIC_GLOB_DAT || r_type == R_GENERIC_ABS
+            };
+            if wanted {
+                symidxs.insert(elf_r_sym(r_info));
+            }
+        };
+
+        for (addr, size, is_plt) in [
+            (self.relplt, self.relplt_sz, true),
+            (self.reldyn, self.reldyn_sz, false),
+        ] {
+            if addr == 0 {
+                continue;
+            }
+            if self.is_use_rela {
+                let cnt = size / mem::size_of::<ElfRela>();
+                for rela in slice::from_raw_parts(addr as *const ElfRela, cnt) {
+                    visit(is_plt, rela.r_info);
+                }
+            } else {
+                let cnt = size / mem::size_of::<ElfRel>();
+                for rel in slice::from_raw_parts(addr as *const ElfRel, cnt) {
+                    visit(is_plt, rel.r_info);
+                }
+            }
+        }
+
+        if self.relandroid != 0 {
+            let mut packed = self.packed_relocs()?;
+            while let Some(reloc) = packed.next()? {
+                let is_plt = elf_r_type(reloc.r_info) == R_GENERIC_JUMP_SLOT;
+                visit(is_plt, reloc.r_info);
+            }
+        }
+
+        let names: BTreeSet<&str> = symidxs
+            .into_iter()
+            .filter(|&symidx| symidx != 0)
+            .filter_map(|symidx| self.sym_name(symidx))
+            .filter(|name| !name.is_empty() && filter(name))
+            .collect();
+        Ok(names.into_iter().map(str::to_string).collect())
+    }
+
+    // 检查单条重定位条目是否匹配目标符号，匹配则将 GOT slot 地址加入对应类别
+    // packed relocation 中也可能出现 JUMP_SLOT，调用方按 r_type 区分调用点与数据槽
+    unsafe fn packed_relocs(&self) -> Result<PackedRelocIterator, Errno> {
+        PackedRelocIterator::new(self.relandroid, self.relandroid_sz, self.relandroid_is_rela)
+    }
+
+    // 每个模块的每类不支持重定位只输出一次日志
+    fn note_unsupported(&self, kind: UnsupportedReloc) {
+        let logged = self.unsupported_logged.get();
+        if logged & kind.bit() != 0 {
+            return;
+        }
+        self.unsupported_logged.set(logged | kind.bit());
+        log::debug(format_args!(
+            "{}: skip {} relocations, slot does not hold a function address",
+            self.pathname,
+            kind.name()
+        ));
+    }
+
+    unsafe fn is_tls_symbol(&self, symidx: u32) -> bool {
+        let sym = &*self.symtab.add(symidx as usize);
+        sym.st_info & 0xf == STT_TLS
     }
 
-    // 检查单条重定位条目是否匹配目标符号，匹配则将 GOT slot 地址加入集合
     fn collect_slot(
         &self,
-        slots: &mut BTreeSet<usize>,
+        slots: &mut SlotCandidates,
         is_plt: bool,
         symidx: u32,
         callee_addrs: Option<&BTreeSet<usize>>,
@@ -278,32 +473,63 @@ impl Elf {
         }
 
         let r_type = elf_r_type(r_info);
+        if let Some(kind) = unsupported_reloc(EXPECTED_MACHINE, r_type) {
+            self.note_unsupported(kind);
+            slots.unsupported = true;
+            return Ok(());
+        }
         if is_plt && r_type != R_GENERIC_JUMP_SLOT {
             return Ok(());
         }
         if !is_plt && r_type != R_GENERIC_GLOB_DAT && r_type != R_GENERIC_ABS {
             return Ok(());
         }
+        if unsafe { self.is_relr_covered(r_offset) } {
+            log::debug(format_args!(
+                "skip symidx {} at offset {:p}: covered by DT_RELR",
+                symidx, r_offset as *const ()
+            ));
+            return Ok(());
+        }
 
         let addr = self.bias_addr + r_offset;
         if addr < self.base_addr {
             return Err(Errno::Format);
         }
 
+        // text relocation 的修补位置在代码段内，写入需要临时放开代码页，仅在 textrel-patch 特性下处理
+        if !is_plt
+            && self.has_text_relocs
+            && !cfg!(feature = "textrel-patch")
+            && self.is_addr_in_exec_segments(addr)
+        {
+            return Ok(());
+        }
+
+        // 有 callee 过滤时逐个 slot 判断；部分卸载的模块中 slot 可能已不可读，
+        // 经 safe_read_usize 读取避免触发真实的 SIGSEGV，读不到的 slot 无法确认归属，直接跳过
         if let Some(expected_addrs) = callee_addrs {
-            let value = unsafe { ptr::read(addr as *const usize) };
-            let matched = expected_addrs.contains(&value);
-            if !matched {
+            let Some(value) = util::safe_read_usize(addr) else {
+                return Ok(());
+            };
+            // 导出地址不含签名与标签，slot 值先去掉 PAC 签名与 TBI / MTE 标签再比较
+            let value = util::canonical_code_addr(value);
+            if !expected_addrs.contains(&value) {
                 // PLT lazy binding 场景：slot 尚未解析，值指向 LOAD 段内的 stub
-                let may_lazy_match =
-                    is_plt && expected_addrs.len() == 1 && self.is_addr_in_load_segments(value);
-                if !may_lazy_match {
-                    return Ok(());
+                if is_plt && expected_addrs.len() == 1 && self.is_addr_in_load_segments(value) {
+                    slots.lazy_plt.insert(addr);
+                } else {
+                    slots.rejected = true;
                 }
+                return Ok(());
             }
         }
 
-        slots.insert(addr);
+        if is_plt {
+            slots.plt.insert(addr);
+        } else {
+            slots.data.insert(addr);
+        }
         Ok(())
     }
 }
@@ -2,7 +2,10 @@
 
 // 校验内存中的 ELF header：magic、class、字节序、架构等
 pub unsafe fn check_elf_header(base_addr: usize) -> Result<(), Errno> {
-    let ehdr = &*(base_addr as *const ElfEhdr);
+    validate_ehdr(&*(base_addr as *const ElfEhdr))
+}
+
+fn validate_ehdr(ehdr: &ElfEhdr) -> Result<(), Errno> {
     if ehdr.e_ident[..SELFMAG] != ELFMAG {
         return Err(Errno::Format);
     }
@@ -31,6 +34,23 @@ pub unsafe fn check_elf_header(base_addr: usize) -> Result<(), Errno> {
     Ok(())
 }
 
+// dynamic 条目中的虚拟地址换算为内存地址，溢出或落在 base 之前时视为格式错误
+fn dyn_ptr(bias_addr: usize, base_addr: usize, entry: &ElfDyn) -> Result<usize, Errno> {
+    bias_addr
+        .checked_add(entry.d_un as usize)
+        .filter(|ptr| *ptr >= base_addr)
+        .ok_or(Errno::Format)
+}
+
+// dynamic 条目是否声明了 text relocation：DT_TEXTREL 存在或 DT_FLAGS 含 DF_TEXTREL
+fn is_text_reloc_entry(entry: &ElfDyn) -> bool {
+    match entry.d_tag {
+        DT_TEXTREL => true,
+        DT_FLAGS => entry.d_un & DF_TEXTREL != 0,
+        _ => false,
+    }
+}
+
 impl Elf {
     // 从内存映像解析 ELF，提取 dynamic section 中的符号表、重定位表、hash 表等
     pub unsafe fn init(base_addr: usize, pathname: &str) -> Result<Self, Errno> {
@@ -38,10 +58,38 @@ impl Elf {
             return Err(Errno::Invalid);
         }
 
-        let ehdr = base_addr as *const ElfEhdr;
-        let phdr = (base_addr + (*ehdr).e_phoff as usize) as *const ElfPhdr;
-        let phdrs = slice::from_raw_parts(phdr, (*ehdr).e_phnum as usize);
+        let ehdr = &*(base_addr as *const ElfEhdr);
+        if ehdr.e_phnum as usize > MAX_PHNUM
+            || ehdr.e_phentsize as usize != mem::size_of::<ElfPhdr>()
+        {
+            log::error(format_args!("{}: bad program header table", pathname));
+            return Err(Errno::Format);
+        }
+        let phdr = base_addr
+            .checked_add(ehdr.e_phoff as usize)
+            .ok_or(Errno::Format)? as *const ElfPhdr;
+        let phdrs = slice::from_raw_parts(phdr, ehdr.e_phnum as usize).to_vec();
+        Self::init_with_phdrs(base_addr, pathname, phdrs)
+    }
+
+    // 使用磁盘文件中的程序头解析，适用于首个 LOAD 段 execute-only 的模块
+    // 文件与内存是否为同一映像由调用方通过 build-id 校验
+    pub unsafe fn init_with_file_headers(
+        base_addr: usize,
+        pathname: &str,
+        headers: &FileHeaders,
+    ) -> Result<Self, Errno> {
+        if base_addr == 0 {
+            return Err(Errno::Invalid);
+        }
+        Self::init_with_phdrs(base_addr, pathname, headers.phdrs.clone())
+    }
 
+    unsafe fn init_with_phdrs(
+        base_addr: usize,
+        pathname: &str,
+        phdrs: Vec<ElfPhdr>,
+    ) -> Result<Self, Errno> {
         // 找到 offset=0 的 PT_LOAD 段，计算 bias（加载基址与虚拟地址的差值）
         let phdr0 = phdrs
             .iter()
@@ -58,25 +106,35 @@ impl Elf {
             .find(|ph| ph.p_type == PT_DYNAMIC)
             .ok_or(Errno::Format)?;
 
-        let dyn_section = (bias_addr + dhdr.p_vaddr as usize) as *const ElfDyn;
+        let dyn_section = bias_addr
+            .checked_add(dhdr.p_vaddr as usize)
+            .ok_or(Errno::Format)? as *const ElfDyn;
         let dyn_sz = dhdr.p_memsz as usize;
 
         let mut elf = Elf {
             pathname: pathname.to_string(),
             base_addr,
             bias_addr,
-            ehdr,
-            phdr,
+            phdrs,
             dyn_section,
             dyn_sz,
             strtab: ptr::null(),
             symtab: ptr::null(),
+            soname_off: None,
+            versym: ptr::null(),
+            verdef: 0,
+            verdef_cnt: 0,
+            verneed: 0,
+            verneed_cnt: 0,
             relplt: 0,
             relplt_sz: 0,
             reldyn: 0,
             reldyn_sz: 0,
             relandroid: 0,
             relandroid_sz: 0,
+            relandroid_is_rela: false,
+            relr: 0,
+            relr_sz: 0,
             bucket: ptr::null(),
             bucket_cnt: 0,
             chain: ptr::null(),
@@ -87,70 +145,100 @@ impl Elf {
             symoffset: 0,
             is_use_gnu_hash: false,
             is_use_rela: false,
+            has_text_relocs: false,
+            unsupported_logged: Cell::new(0),
         };
 
-        // 遍历 dynamic section，提取各表地址和大小
-        let dyn_cnt = dyn_sz / mem::size_of::<ElfDyn>();
+        // 程序头可能来自文件，访问前确认 dynamic section 位于已加载的段内
+        let dyn_cnt = (dyn_sz / mem::size_of::<ElfDyn>()).min(MAX_DYN_ENTRIES);
+        let dyn_bytes = dyn_cnt * mem::size_of::<ElfDyn>();
+        elf.check_table("dynamic", dyn_section as usize, dyn_bytes)?;
+
+        // 遍历 dynamic section，提取各表地址和大小；地址与大小在读取表内容之前统一校验
+        let mut strtab_sz = 0;
         let dyn_entries = slice::from_raw_parts(dyn_section, dyn_cnt);
         for dyn_entry in dyn_entries {
             match dyn_entry.d_tag {
                 DT_NULL => break,
                 DT_STRTAB => {
-                    let ptr = (bias_addr + dyn_entry.d_un as usize) as *const c_char;
-                    if (ptr as usize) < base_addr {
-                        return Err(Errno::Format);
-                    }
+                    let ptr = dyn_ptr(bias_addr, base_addr, dyn_entry)? as *const c_char;
                     elf.strtab = ptr;
                 }
                 DT_SYMTAB => {
-                    let ptr = (bias_addr + dyn_entry.d_un as usize) as *const ElfSym;
-                    if (ptr as usize) < base_addr {
-                        return Err(Errno::Format);
-                    }
+                    let ptr = dyn_ptr(bias_addr, base_addr, dyn_entry)? as *const ElfSym;
                     elf.symtab = ptr;
                 }
+                DT_STRSZ => {
+                    strtab_sz = dyn_entry.d_un as usize;
+                }
+                DT_SONAME => {
+                    elf.soname_off = Some(dyn_entry.d_un as usize);
+                }
+                DT_VERSYM => {
+                    let ptr = dyn_ptr(bias_addr, base_addr, dyn_entry)? as *const u16;
+                    elf.versym = ptr;
+                }
+                DT_VERDEF | DT_VERNEED => {
+                    let ptr = dyn_ptr(bias_addr, base_addr, dyn_entry)?;
+                    if dyn_entry.d_tag == DT_VERDEF {
+                        elf.verdef = ptr;
+                    } else {
+                        elf.verneed = ptr;
+                    }
+                }
+                DT_VERDEFNUM => {
+                    elf.verdef_cnt = dyn_entry.d_un as usize;
+                }
+                DT_VERNEEDNUM => {
+                    elf.verneed_cnt = dyn_entry.d_un as usize;
+                }
+                DT_TEXTREL | DT_FLAGS if is_text_reloc_entry(dyn_entry) => {
+                    elf.has_text_relocs = true;
+                }
                 DT_PLTREL => {
                     elf.is_use_rela = dyn_entry.d_un as i64 == DT_RELA;
                 }
                 DT_JMPREL => {
-                    let ptr = bias_addr + dyn_entry.d_un as usize;
-                    if ptr < base_addr {
-                        return Err(Errno::Format);
-                    }
+                    let ptr = dyn_ptr(bias_addr, base_addr, dyn_entry)?;
                     elf.relplt = ptr;
                 }
                 DT_PLTRELSZ => {
                     elf.relplt_sz = dyn_entry.d_un as usize;
                 }
                 DT_REL | DT_RELA => {
-                    let ptr = bias_addr + dyn_entry.d_un as usize;
-                    if ptr < base_addr {
-                        return Err(Errno::Format);
-                    }
+                    let ptr = dyn_ptr(bias_addr, base_addr, dyn_entry)?;
                     elf.reldyn = ptr;
                 }
                 DT_RELSZ | DT_RELASZ => {
                     elf.reldyn_sz = dyn_entry.d_un as usize;
                 }
                 DT_ANDROID_REL | DT_ANDROID_RELA => {
-                    let ptr = bias_addr + dyn_entry.d_un as usize;
-                    if ptr < base_addr {
-                        return Err(Errno::Format);
-                    }
+                    let ptr = dyn_ptr(bias_addr, base_addr, dyn_entry)?;
                     elf.relandroid = ptr;
+                    elf.relandroid_is_rela = dyn_entry.d_tag == DT_ANDROID_RELA;
                 }
                 DT_ANDROID_RELSZ | DT_ANDROID_RELASZ => {
                     elf.relandroid_sz = dyn_entry.d_un as usize;
                 }
+                DT_RELR | DT_ANDROID_RELR => {
+                    let ptr = dyn_ptr(bias_addr, base_addr, dyn_entry)?;
+                    elf.relr = ptr;
+                }
+                DT_RELRSZ | DT_ANDROID_RELRSZ => {
+                    elf.relr_sz = dyn_entry.d_un as usize;
+                }
+                DT_RELRENT | DT_ANDROID_RELRENT
+                    if dyn_entry.d_un as usize != mem::size_of::<ElfAddr>() =>
+                {
+                    return Err(Errno::Format);
+                }
                 DT_HASH => {
                     // 优先使用 GNU hash，已有则跳过 ELF hash
                     if elf.is_use_gnu_hash {
                         continue;
                     }
-                    let raw = (bias_addr + dyn_entry.d_un as usize) as *const u32;
-                    if (raw as usize) < base_addr {
-                        return Err(Errno::Format);
-                    }
+                    let raw = dyn_ptr(bias_addr, base_addr, dyn_entry)? as *const u32;
+                    elf.check_table("DT_HASH", raw as usize, 8)?;
                     elf.bucket_cnt = *raw;
                     elf.chain_cnt = *raw.add(1);
                     elf.bucket = raw.add(2);
@@ -158,10 +246,8 @@ impl Elf {
                 }
                 DT_GNU_HASH => {
                     // GNU hash 布局：nbuckets | symoffset | bloom_sz | bloom_shift | bloom[] | buckets[] | chains[]
-                    let raw = (bias_addr + dyn_entry.d_un as usize) as *const u32;
-                    if (raw as usize) < base_addr {
-                        return Err(Errno::Format);
-                    }
+                    let raw = dyn_ptr(bias_addr, base_addr, dyn_entry)? as *const u32;
+                    elf.check_table("DT_GNU_HASH", raw as usize, 16)?;
                     elf.bucket_cnt = *raw;
                     elf.symoffset = *raw.add(1);
                     elf.bloom_sz = *raw.add(2);
@@ -175,6 +261,11 @@ impl Elf {
             }
         }
 
+        elf.check_tables(strtab_sz)?;
+        if elf.is_use_gnu_hash {
+            elf.chain_cnt = elf.gnu_dynsym_count()?;
+        }
+
         // Android packed relocation 以 "APS2" 魔数开头，跳过 4 字节头部
         if elf.relandroid != 0 {
             let rel = elf.relandroid as *const u8;
@@ -192,6 +283,13 @@ impl Elf {
 
         elf.check()?;
 
+        if elf.has_text_relocs {
+            log::info(format_args!(
+                "{} has text relocations, calls patched into .text bypass GOT",
+                elf.pathname
+            ));
+        }
+
         log::info(format_args!(
             "init OK: {} ({} {} PLT:{} DYN:{} ANDROID:{})",
             elf.pathname,
@@ -214,8 +312,7 @@ impl Elf {
     fn check(&self) -> Result<(), Errno> {
         if self.base_addr == 0
             || self.bias_addr == 0
-            || self.ehdr.is_null()
-            || self.phdr.is_null()
+            || self.phdrs.is_empty()
             || self.strtab.is_null()
             || self.symtab.is_null()
             || self.bucket.is_null()
@@ -223,17 +320,122 @@ impl Elf {
         {
             return Err(Errno::Format);
         }
-        if self.is_use_gnu_hash && self.bloom.is_null() {
+        if self.is_use_gnu_hash && (self.bloom.is_null() || self.bloom_sz == 0) {
             return Err(Errno::Format);
         }
         Ok(())
     }
 
 
+    // 各表连同声明的大小必须完整落在同一个 PT_LOAD 段内，条目数不超过上限
+    fn check_tables(&self, strtab_sz: usize) -> Result<(), Errno> {
+        let reloc_sz = if self.is_use_rela {
+            mem::size_of::<ElfRela>()
+        } else {
+            mem::size_of::<ElfRel>()
+        };
+        for (name, size, entry_sz) in [
+            ("DT_JMPREL", self.relplt_sz, reloc_sz),
+            ("DT_REL(A)", self.reldyn_sz, reloc_sz),
+            ("DT_ANDROID_REL(A)", self.relandroid_sz, 1),
+            ("DT_RELR", self.relr_sz, mem::size_of::<ElfAddr>()),
+        ] {
+            if size / entry_sz > MAX_RELOC_ENTRIES {
+                log::error(format_args!(
+                    "{}: {} too large (0x{:x})",
+                    self.pathname, name, size
+                ));
+                return Err(Errno::Format);
+            }
+        }
+        self.check_table("DT_JMPREL", self.relplt, self.relplt_sz)?;
+        self.check_table("DT_REL(A)", self.reldyn, self.reldyn_sz)?;
+        self.check_table("DT_ANDROID_REL(A)", self.relandroid, self.relandroid_sz)?;
+        self.check_table("DT_RELR", self.relr, self.relr_sz)?;
+        self.check_table("DT_STRTAB", self.strtab as usize, strtab_sz.max(1))?;
+        self.check_table("DT_SYMTAB", self.symtab as usize, mem::size_of::<ElfSym>())?;
+        self.check_table("DT_VERSYM", self.versym as usize, mem::size_of::<u16>())?;
+        self.check_table("DT_VERDEF", self.verdef, mem::size_of::<ElfVerdef>())?;
+        self.check_table("DT_VERNEED", self.verneed, mem::size_of::<ElfVerneed>())?;
+
+        // bucket 与 chain 的长度由表头给出；GNU hash 的 chain 长度未知，只校验起点
+        let words = mem::size_of::<u32>();
+        if self.is_use_gnu_hash {
+            let bloom_bytes = self.bloom_sz as usize * mem::size_of::<usize>();
+            self.check_table("DT_GNU_HASH bloom", self.bloom as usize, bloom_bytes)?;
+            let bucket_bytes = self.bucket_cnt as usize * words;
+            self.check_table("DT_GNU_HASH buckets", self.bucket as usize, bucket_bytes)?;
+            self.check_table("DT_GNU_HASH chains", self.chain as usize, words)?;
+        } else if !self.bucket.is_null() {
+            let table_bytes = (self.bucket_cnt as usize + self.chain_cnt as usize) * words;
+            self.check_table("DT_HASH", self.bucket as usize, table_bytes)?;
+        }
+        Ok(())
+    }
+
+    // GNU hash 不记录符号总数：从最大的 bucket 起沿 chain 找到链尾，chain 不得越出所在的 PT_LOAD 段
+    unsafe fn gnu_dynsym_count(&self) -> Result<u32, Errno> {
+        let buckets = slice::from_raw_parts(self.bucket, self.bucket_cnt as usize);
+        let max_idx = buckets.iter().copied().max().unwrap_or(0);
+        if max_idx < self.symoffset {
+            return Ok(self.symoffset);
+        }
+        let chain_words = self.load_segment_tail(self.chain as usize) / mem::size_of::<u32>();
+        let end_idx = (self.symoffset as usize).saturating_add(chain_words);
+        let mut idx = max_idx as usize;
+        while idx < end_idx {
+            let hash = *self.chain.add(idx - self.symoffset as usize);
+            idx += 1;
+            if hash & 1 != 0 {
+                return u32::try_from(idx).map_err(|_| Errno::Format);
+            }
+        }
+        log::error(format_args!(
+            "{}: DT_GNU_HASH chain has no end within its segment",
+            self.pathname
+        ));
+        Err(Errno::Format)
+    }
+
+    // addr 到其所在 PT_LOAD 段末尾的字节数，不在任何段内时为 0
+    fn load_segment_tail(&self, addr: usize) -> usize {
+        self.phdrs
+            .iter()
+            .filter(|phdr| phdr.p_type == PT_LOAD)
+            .find_map(|phdr| {
+                let start = self.bias_addr.wrapping_add(phdr.p_vaddr as usize);
+                let end = start.saturating_add(phdr.p_memsz as usize);
+                (addr >= start && addr < end).then(|| end - addr)
+            })
+            .unwrap_or(0)
+    }
+
+    // 地址为 0 表示未声明该表，直接通过
+    fn check_table(&self, name: &str, addr: usize, size: usize) -> Result<(), Errno> {
+        if addr == 0 || self.is_range_in_load_segments(addr, size) {
+            return Ok(());
+        }
+        log::error(format_args!(
+            "{}: {} [0x{:x}, +0x{:x}) outside load segments",
+            self.pathname, name, addr, size
+        ));
+        Err(Errno::Format)
+    }
+
+    fn is_range_in_load_segments(&self, addr: usize, size: usize) -> bool {
+        let Some(end_addr) = addr.checked_add(size) else {
+            return false;
+        };
+        self.phdrs.iter().any(|phdr| {
+            let start = self.bias_addr.wrapping_add(phdr.p_vaddr as usize);
+            let end = start.saturating_add(phdr.p_memsz as usize);
+            phdr.p_type == PT_LOAD && addr >= start && end_addr <= end
+        })
+    }
+
     // 判断地址是否落在某个 PT_LOAD 段的虚拟地址范围内
     fn is_addr_in_load_segments(&self, addr: usize) -> bool {
-        let phdrs = unsafe { slice::from_raw_parts(self.phdr, (*self.ehdr).e_phnum as usize) };
-        for phdr in phdrs {
+        for phdr in &self.phdrs {
             if phdr.p_type != PT_LOAD {
                 continue;
             }
@@ -246,4 +448,28 @@ impl Elf {
         false
     }
 
+    // r_offset 同时出现在 DT_RELR 中时目标是相对重定位而非符号 slot，不应改写
+    unsafe fn is_relr_covered(&self, r_offset: usize) -> bool {
+        if self.relr == 0 {
+            return false;
+        }
+        let cnt = self.relr_sz / mem::size_of::<ElfAddr>();
+        let entries = slice::from_raw_parts(self.relr as *const ElfAddr, cnt);
+        relr_covers(entries, r_offset)
+    }
+
+    // 判断地址是否落在可执行的 PT_LOAD 段内，用于识别 text relocation 的修补位置
+    fn is_addr_in_exec_segments(&self, addr: usize) -> bool {
+        for phdr in &self.phdrs {
+            if phdr.p_type != PT_LOAD || phdr.p_flags & PF_X == 0 {
+                continue;
+            }
+            let start = self.bias_addr + phdr.p_vaddr as usize;
+            let end = start.saturating_add(phdr.p_memsz as usize);
+            if addr >= start && addr < end {
+                return true;
+            }
+        }
+        false
+    }
 }
@@ -0,0 +1,15 @@
+// 符号绑定类型：weak_only 只有弱定义；pair@V1 为全局定义、pair@@V2 为弱定义；unique_value 为 GNU_UNIQUE
+__attribute__((weak)) int weak_only(void) { return 1; }
+int pair_global(void) { return 2; }
+__attribute__((weak)) int pair_weak(void) { return 3; }
+
+__asm__(".symver pair_global, pair@V1");
+__asm__(".symver pair_weak, pair@@V2");
+
+__asm__(".data\n"
+        ".globl unique_value\n"
+        ".type unique_value, @gnu_unique_object\n"
+        ".size unique_value, 4\n"
+        "unique_value:\n"
+        ".long 7\n"
+        ".text");
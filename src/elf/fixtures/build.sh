@@ -0,0 +1,11 @@
+#!/bin/sh
+# 重新生成 ELF 单元测试使用的版本符号与绑定类型夹具（x86_64）
+set -e
+cd "$(dirname "$0")"
+FLAGS="-shared -fPIC -nostdlib -O2 -s -Wl,--build-id=none -Wl,--hash-style=gnu -Wl,-z,noseparate-code"
+cc $FLAGS -Wl,--version-script=versioned_callee.map \
+    -o libversioned_callee.so versioned_callee.c
+cc $FLAGS -o libversioned_caller.so versioned_caller.c -L. -lversioned_callee
+cc $FLAGS -Wl,--version-script=binding_callee.map \
+    -o libbinding_callee.so binding_callee.c
+cc $FLAGS -ftls-model=initial-exec -o libtls_caller.so tls_caller.c
@@ -0,0 +1,4 @@
+// 导入 TLS 变量：initial-exec 模型下只产生 TPOFF64 重定位，槽位存放线程偏移而非函数地址
+extern __thread int tls_value;
+
+int read_tls(void) { return tls_value; }
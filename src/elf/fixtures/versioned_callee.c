@@ -0,0 +1,6 @@
+// 同名符号的两个版本：foo@V1 为旧版本，foo@@V2 为默认版本
+int foo_v1(void) { return 1; }
+int foo_v2(void) { return 2; }
+
+__asm__(".symver foo_v1, foo@V1");
+__asm__(".symver foo_v2, foo@@V2");
@@ -0,0 +1,6 @@
+// 显式绑定旧版本 foo@V1，导入项经 DT_VERNEED 记录版本
+extern int foo(void);
+
+__asm__(".symver foo, foo@V1");
+
+int call_foo(void) { return foo(); }
@@ -0,0 +1,144 @@
+// 从磁盘文件读取 ELF header 与程序头，用于内存中首个 LOAD 段不可读（execute-only）的模块
+// 仅头部来自文件，重定位表、符号表等仍通过内存访问
+
+use super::{ElfEhdr, ElfPhdr, PT_NOTE, validate_ehdr};
+use crate::errno::Errno;
+use std::fs::File;
+use std::mem;
+use std::os::unix::fs::FileExt;
+use std::ptr;
+
+// 头部读取上限，覆盖常见模块的全部程序头
+const HEADER_READ_MAX: usize = 64 * 1024;
+const HEADER_READ_MIN: usize = 4096;
+// build-id note 的描述符上限，超出视为损坏
+const NOTE_READ_MAX: usize = 4096;
+const NT_GNU_BUILD_ID: u32 = 3;
+const GNU_NOTE_NAME: &[u8] = b"GNU\0";
+
+// 文件中读到的程序头与 build-id
+pub struct FileHeaders {
+    pub(super) phdrs: Vec<ElfPhdr>,
+    build_id: Option<Vec<u8>>,
+}
+
+impl FileHeaders {
+    // 打开模块文件并 pread 头部，失败时返回原因供调用方记录
+    pub fn read(pathname: &str) -> Result<Self, Errno> {
+        if !pathname.starts_with('/') {
+            return Err(Errno::Invalid);
+        }
+        let file = File::open(pathname).map_err(|_| Errno::ReadElf)?;
+
+        let mut buf = vec![0u8; HEADER_READ_MIN];
+        let len = read_at_most(&file, &mut buf, 0)?;
+        buf.truncate(len);
+        let need = phdrs_end(&buf)?;
+        if need > buf.len() {
+            if need > HEADER_READ_MAX {
+                return Err(Errno::Format);
+            }
+            buf.resize(need, 0);
+            let len = read_at_most(&file, &mut buf, 0)?;
+            buf.truncate(len);
+        }
+        let phdrs = parse_phdrs(&buf)?;
+
+        let build_id = phdrs
+            .iter()
+            .filter(|ph| ph.p_type == PT_NOTE)
+            .find_map(|ph| {
+                let size = ph.p_filesz as usize;
+                if size == 0 || size > NOTE_READ_MAX {
+                    return None;
+                }
+                let mut notes = vec![0u8; size];
+                let len = read_at_most(&file, &mut notes, ph.p_offset).ok()?;
+                find_build_id(&notes[..len]).map(<[u8]>::to_vec)
+            });
+
+        Ok(Self { phdrs, build_id })
+    }
+
+    pub fn build_id(&self) -> Option<&[u8]> {
+        self.build_id.as_deref()
+    }
+}
+
+fn read_at_most(file: &File, buf: &mut [u8], offset: u64) -> Result<usize, Errno> {
+    let mut done = 0;
+    while done < buf.len() {
+        match file.read_at(&mut buf[done..], offset + done as u64) {
+            Ok(0) => break,
+            Ok(n) => done += n,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(_) => return Err(Errno::ReadElf),
+        }
+    }
+    Ok(done)
+}
+
+fn read_ehdr(buf: &[u8]) -> Result<ElfEhdr, Errno> {
+    if buf.len() < mem::size_of::<ElfEhdr>() {
+        return Err(Errno::Format);
+    }
+    let ehdr = unsafe { ptr::read_unaligned(buf.as_ptr() as *const ElfEhdr) };
+    validate_ehdr(&ehdr)?;
+    if ehdr.e_phnum == 0 || ehdr.e_phentsize as usize != mem::size_of::<ElfPhdr>() {
+        return Err(Errno::Format);
+    }
+    Ok(ehdr)
+}
+
+// 程序头表末尾在文件中的偏移
+fn phdrs_end(buf: &[u8]) -> Result<usize, Errno> {
+    let ehdr = read_ehdr(buf)?;
+    let table_sz = ehdr.e_phnum as usize * mem::size_of::<ElfPhdr>();
+    usize::try_from(ehdr.e_phoff)
+        .ok()
+        .and_then(|phoff| phoff.checked_add(table_sz))
+        .ok_or(Errno::Format)
+}
+
+// 解析并校验程序头，要求整张表落在已读取的范围内
+pub(super) fn parse_phdrs(buf: &[u8]) -> Result<Vec<ElfPhdr>, Errno> {
+    let ehdr = read_ehdr(buf)?;
+    let end = phdrs_end(buf)?;
+    if end > buf.len() {
+        return Err(Errno::Format);
+    }
+    let phoff = ehdr.e_phoff as usize;
+    let phdrs: Vec<ElfPhdr> = (0..ehdr.e_phnum as usize)
+        .map(|idx| unsafe {
+            let at = buf.as_ptr().add(phoff + idx * mem::size_of::<ElfPhdr>());
+            ptr::read_unaligned(at as *const ElfPhdr)
+        })
+        .collect();
+    Ok(phdrs)
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> Option<u32> {
+    let bytes = buf.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+// 在 PT_NOTE 内容中查找 NT_GNU_BUILD_ID，name/desc 均按 4 字节对齐
+pub(super) fn find_build_id(notes: &[u8]) -> Option<&[u8]> {
+    let mut offset = 0;
+    while offset + 12 <= notes.len() {
+        let namesz = read_u32(notes, offset)? as usize;
+        let descsz = read_u32(notes, offset + 4)? as usize;
+        let note_type = read_u32(notes, offset + 8)?;
+        let name_at = offset + 12;
+        let desc_at = name_at.checked_add(namesz.checked_next_multiple_of(4)?)?;
+        let next = desc_at.checked_add(descsz.checked_next_multiple_of(4)?)?;
+        if desc_at.checked_add(descsz)? > notes.len() {
+            return None;
+        }
+        if note_type == NT_GNU_BUILD_ID && &notes[name_at..name_at + namesz] == GNU_NOTE_NAME {
+            return Some(&notes[desc_at..desc_at + descsz]);
+        }
+        offset = next;
+    }
+    None
+}
@@ -0,0 +1,69 @@
+// 只读查询：遍历动态符号表、RELRO 范围与 SONAME，供模块检视和规则匹配使用
+
+impl Elf {
+    // 动态符号表条目数：SYSV hash 的 nchain，GNU hash 为初始化时沿 chain 求出的值
+    pub fn dynsym_count(&self) -> u32 {
+        self.chain_cnt
+    }
+
+    // 返回符号名及是否由本模块定义，名称为空或非 UTF-8 时返回 None
+    pub unsafe fn dynsym_entry(&self, idx: u32) -> Option<(&str, bool)> {
+        let name = self.sym_name(idx).filter(|name| !name.is_empty())?;
+        let sym = &*self.symtab.add(idx as usize);
+        Some((name, sym.st_shndx != SHN_UNDEF && sym.st_value != 0))
+    }
+
+    // 反查地址落在 [st_value, st_value + st_size) 内的已定义符号，返回符号名、起始地址与大小
+    // 大小为 0 的符号只匹配起始地址
+    pub unsafe fn find_symbol_by_addr(&self, addr: usize) -> Option<(&str, usize, usize)> {
+        let offset = addr.checked_sub(self.bias_addr)?;
+        for idx in 1..self.chain_cnt {
+            let sym = &*self.symtab.add(idx as usize);
+            if sym.st_shndx == SHN_UNDEF || sym.st_value == 0 {
+                continue;
+            }
+            let start = sym.st_value as usize;
+            let size = sym.st_size as usize;
+            let contains = if size == 0 {
+                offset == start
+            } else {
+                offset >= start && offset - start < size
+            };
+            if !contains {
+                continue;
+            }
+            if let Some(name) = self.sym_name(idx).filter(|name| !name.is_empty()) {
+                return Some((name, self.bias_addr + start, size));
+            }
+        }
+        None
+    }
+
+    // PT_GNU_RELRO 覆盖的内存范围 [start, end)
+    pub fn relro_range(&self) -> Option<(usize, usize)> {
+        self.phdrs
+            .iter()
+            .find(|ph| ph.p_type == PT_GNU_RELRO && ph.p_memsz != 0)
+            .map(|ph| {
+                let start = self.bias_addr + ph.p_vaddr as usize;
+                (start, start + ph.p_memsz as usize)
+            })
+    }
+
+    // 地址是否落在任一 PT_GNU_RELRO 段内，full RELRO 模块的 GOT 位于其中
+    pub fn is_addr_in_relro(&self, addr: usize) -> bool {
+        self.phdrs
+            .iter()
+            .filter(|ph| ph.p_type == PT_GNU_RELRO && ph.p_memsz != 0)
+            .any(|ph| {
+                let start = self.bias_addr + ph.p_vaddr as usize;
+                addr >= start && addr - start < ph.p_memsz as usize
+            })
+    }
+
+    // DT_SONAME 声明的模块名，未声明或不是合法 UTF-8 时返回 None
+    pub unsafe fn soname(&self) -> Option<&str> {
+        let offset = self.soname_off?;
+        CStr::from_ptr(self.strtab.add(offset)).to_str().ok()
+    }
+}
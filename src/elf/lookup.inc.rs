@@ -1,46 +1,103 @@
 // 符号查找与 GOT slot 替换逻辑，通过 include! 嵌入 elf.rs
 
 impl Elf {
-    // 按名称查找符号索引，根据 hash 类型分派到对应查找算法
-    fn find_symidx_by_name(&self, symbol: &str) -> Result<u32, Errno> {
+    // 按名称与版本查找符号索引，多个候选时取排序后的首个
+    fn find_symidx_by_name(&self, symbol: &str, version: Option<&str>) -> Result<u32, Errno> {
+        self.find_symidx_candidates(symbol, version)
+            .map(|candidates| candidates[0])
+    }
+
+    // 收集同名的全部候选符号：GLOBAL 与 GNU_UNIQUE 在前，WEAK 在后
+    // 未指定版本时接受任意版本，同一绑定类型内默认版本排在非默认版本之前
+    fn find_symidx_candidates(
+        &self,
+        symbol: &str,
+        version: Option<&str>,
+    ) -> Result<Vec<u32>, Errno> {
+        let query = match version {
+            Some(version) => VersionQuery::Named(version),
+            None => VersionQuery::Any,
+        };
+        let mut candidates = Vec::new();
         if self.is_use_gnu_hash {
-            self.gnu_hash_lookup(symbol)
+            self.gnu_hash_lookup(symbol, query, &mut candidates)?;
         } else {
-            self.elf_hash_lookup(symbol)
+            self.elf_hash_lookup(symbol, query, &mut candidates)?;
+        }
+        if candidates.is_empty() {
+            return Err(Errno::NotFound);
         }
+        candidates.sort_by_key(|&idx| unsafe {
+            let is_default = self.version_accepts(idx, VersionQuery::Default);
+            (self.sym_bind_rank(idx), !is_default)
+        });
+        Ok(candidates)
     }
 
-    // 通过 DT_HASH 的 bucket/chain 链表查找符号
-    fn elf_hash_lookup(&self, symbol: &str) -> Result<u32, Errno> {
+    // 绑定类型优先级：GNU_UNIQUE 按全局符号处理，其他绑定排在 WEAK 之后
+    unsafe fn sym_bind_rank(&self, idx: u32) -> u8 {
+        let sym = &*self.symtab.add(idx as usize);
+        match sym.st_info >> 4 {
+            STB_GLOBAL | STB_GNU_UNIQUE => 0,
+            STB_WEAK => 1,
+            _ => 2,
+        }
+    }
+
+    // 通过 DT_HASH 的 bucket/chain 链表查找符号，匹配项追加到 found
+    fn elf_hash_lookup(
+        &self,
+        symbol: &str,
+        query: VersionQuery<'_>,
+        found: &mut Vec<u32>,
+    ) -> Result<(), Errno> {
         if self.bucket_cnt == 0 {
-            return Err(Errno::NotFound);
+            return Ok(());
         }
         let hash = elf_hash(symbol.as_bytes());
         let mut i = unsafe { *self.bucket.add((hash % self.bucket_cnt) as usize) };
+        // 每个符号至多出现一次，步数超过 chain_cnt 说明链中有环
+        let mut steps = 0;
         while i != 0 {
+            if i >= self.chain_cnt || steps >= self.chain_cnt {
+                return Err(self.corrupt_hash("DT_HASH", i));
+            }
+            steps += 1;
             if let Some(name) = unsafe { self.sym_name(i) }
                 && name == symbol
+                && unsafe { self.version_accepts(i, query) }
             {
                 log::info(format_args!("found {} at symidx: {} (ELF_HASH)", symbol, i));
-                return Ok(i);
+                found.push(i);
             }
             i = unsafe { *self.chain.add(i as usize) };
         }
-        Err(Errno::NotFound)
+        Ok(())
     }
 
-    // GNU hash 查找：先尝试已定义符号，失败后回退到未定义符号区间
-    fn gnu_hash_lookup(&self, symbol: &str) -> Result<u32, Errno> {
-        if let Ok(symidx) = self.gnu_hash_lookup_def(symbol) {
-            return Ok(symidx);
+    // GNU hash 查找：先查已定义符号，没有匹配时回退到未定义符号区间
+    fn gnu_hash_lookup(
+        &self,
+        symbol: &str,
+        query: VersionQuery<'_>,
+        found: &mut Vec<u32>,
+    ) -> Result<(), Errno> {
+        self.gnu_hash_lookup_def(symbol, query, found)?;
+        if found.is_empty() {
+            self.gnu_hash_lookup_undef(symbol, query, found);
         }
-        self.gnu_hash_lookup_undef(symbol)
+        Ok(())
     }
 
     // GNU hash 查找已定义符号：bloom filter 快速排除 -> bucket 定位 -> chain 遍历
-    fn gnu_hash_lookup_def(&self, symbol: &str) -> Result<u32, Errno> {
+    fn gnu_hash_lookup_def(
+        &self,
+        symbol: &str,
+        query: VersionQuery<'_>,
+        found: &mut Vec<u32>,
+    ) -> Result<(), Errno> {
         if self.bucket_cnt == 0 {
-            return Err(Errno::NotFound);
+            return Ok(());
         }
         let hash = elf_gnu_hash(symbol.as_bytes());
         let elfclass_bits = mem::size_of::<ElfAddr>() * 8;
@@ -50,53 +107,63 @@ impl Elf {
         let mask = (1usize << (hash as usize % elfclass_bits))
             | (1usize << ((hash >> self.bloom_shift) as usize % elfclass_bits));
         if (word & mask) != mask {
-            return Err(Errno::NotFound);
+            return Ok(());
         }
 
         let mut i = unsafe { *self.bucket.add((hash % self.bucket_cnt) as usize) };
         if i < self.symoffset {
-            return Err(Errno::NotFound);
+            return Ok(());
         }
 
         // 遍历 chain，hash 低位匹配后再比较符号名；chain 最低位为 1 表示链尾
+        // chain_cnt 为初始化时求出的符号总数，越过它说明链尾标记缺失
         loop {
+            if i >= self.chain_cnt {
+                return Err(self.corrupt_hash("DT_GNU_HASH", i));
+            }
             let symname = unsafe { self.sym_name(i) };
             let symhash = unsafe { *self.chain.add((i - self.symoffset) as usize) };
             if let Some(name) = symname
                 && (hash | 1) == (symhash | 1)
                 && name == symbol
+                && unsafe { self.version_accepts(i, query) }
             {
                 log::info(format_args!(
                     "found {} at symidx: {} (GNU_HASH DEF)",
                     symbol, i
                 ));
-                return Ok(i);
+                found.push(i);
             }
             if (symhash & 1) != 0 {
                 break;
             }
             i += 1;
         }
-
-        Err(Errno::NotFound)
+        Ok(())
     }
 
     // 线性扫描 symoffset 之前的未定义符号区间（GNU hash 不索引这些符号）
-    fn gnu_hash_lookup_undef(&self, symbol: &str) -> Result<u32, Errno> {
-        let mut i = 0u32;
-        while i < self.symoffset {
+    fn gnu_hash_lookup_undef(&self, symbol: &str, query: VersionQuery<'_>, found: &mut Vec<u32>) {
+        for i in 0..self.symoffset {
             if let Some(name) = unsafe { self.sym_name(i) }
                 && name == symbol
+                && unsafe { self.version_accepts(i, query) }
             {
                 log::info(format_args!(
                     "found {} at symidx: {} (GNU_HASH UNDEF)",
                     symbol, i
                 ));
-                return Ok(i);
+                found.push(i);
             }
-            i += 1;
         }
-        Err(Errno::NotFound)
+    }
+
+    fn corrupt_hash(&self, table: &str, symidx: u32) -> Errno {
+        log::error(format_args!(
+            "{}: {} chain corrupt at symidx {}",
+            self.pathname, table, symidx
+        ));
+        Errno::Format
     }
 
     // 通过符号索引从 strtab 获取符号名
@@ -129,12 +196,23 @@ impl Elf {
             return Ok(());
         }
         let r_type = elf_r_type(r_info);
+        if let Some(kind) = unsupported_reloc(EXPECTED_MACHINE, r_type) {
+            self.note_unsupported(kind);
+            return Ok(());
+        }
         if is_plt && r_type != R_GENERIC_JUMP_SLOT {
             return Ok(());
         }
         if !is_plt && r_type != R_GENERIC_GLOB_DAT && r_type != R_GENERIC_ABS {
             return Ok(());
         }
+        if unsafe { self.is_relr_covered(r_offset) } {
+            log::debug(format_args!(
+                "skip {} at {} offset {:p}: covered by DT_RELR",
+                symbol, section, r_offset as *const ()
+            ));
+            return Ok(());
+        }
 
         if let Some(found) = found {
             *found = true;
@@ -99,6 +99,11 @@ impl PackedRelocIterator {
     unsafe fn read_group_fields(&mut self) -> Result<(), Errno> {
         self.group_size = self.decoder.next()?;
         self.group_flags = self.decoder.next()?;
+        // 空分组无法推进，只会出现在损坏的流中
+        if self.group_size == 0 {
+            log::error(format_args!("empty group in android packed relocation"));
+            return Err(Errno::Format);
+        }
 
         if (self.group_flags & Self::RELOCATION_GROUPED_BY_OFFSET_DELTA_FLAG) != 0 {
             self.group_r_offset_delta = self.decoder.next()?;
@@ -116,7 +121,7 @@ impl PackedRelocIterator {
                 return Err(Errno::Format);
             }
             let val = self.decoder.next()?;
-            self.r_addend += val as isize;
+            self.r_addend = self.r_addend.wrapping_add(val as isize);
         } else if (self.group_flags & Self::RELOCATION_GROUP_HAS_ADDEND_FLAG) == 0 {
             self.r_addend = 0;
         }
@@ -151,7 +156,7 @@ impl PackedRelocIterator {
             && (self.group_flags & Self::RELOCATION_GROUPED_BY_ADDEND_FLAG) == 0
         {
             let val = self.decoder.next()?;
-            self.r_addend += val as isize;
+            self.r_addend = self.r_addend.wrapping_add(val as isize);
         }
 
         self.relocation_index += 1;
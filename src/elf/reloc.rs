@@ -1,6 +1,10 @@
-// 从 ELF64 重定位条目的 r_info 字段提取符号索引和重定位类型
+// 从 ELF64 重定位条目的 r_info 字段提取符号索引和重定位类型，并识别不支持改写的重定位类型
 
-use super::ElfXword;
+use super::{
+    EM_AARCH64, EM_X86_64, ElfXword, R_AARCH64_IRELATIVE, R_AARCH64_TLS_DTPMOD64,
+    R_AARCH64_TLS_DTPREL64, R_AARCH64_TLS_TPREL64, R_AARCH64_TLSDESC, R_X86_64_DTPMOD64,
+    R_X86_64_DTPOFF64, R_X86_64_IRELATIVE, R_X86_64_TLSDESC, R_X86_64_TPOFF64,
+};
 
 // 提取高 32 位作为符号索引
 pub(super) fn elf_r_sym(info: ElfXword) -> u32 {
@@ -11,3 +15,42 @@ pub(super) fn elf_r_sym(info: ElfXword) -> u32 {
 pub(super) fn elf_r_type(info: ElfXword) -> u32 {
     (info & 0xffff_ffff) as u32
 }
+
+// 槽位内容不是函数地址的重定位：IRELATIVE 存放 resolver 的返回值，TLS 类存放线程局部偏移或描述符
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum UnsupportedReloc {
+    Irelative,
+    TlsDesc,
+    Tls,
+}
+
+impl UnsupportedReloc {
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            Self::Irelative => "IRELATIVE",
+            Self::TlsDesc => "TLSDESC",
+            Self::Tls => "TLS",
+        }
+    }
+
+    pub(super) fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+// 按机器类型识别不支持的重定位，其余类型返回 None 交给常规的类型过滤
+pub(super) fn unsupported_reloc(machine: u16, r_type: u32) -> Option<UnsupportedReloc> {
+    match (machine, r_type) {
+        (EM_AARCH64, R_AARCH64_IRELATIVE) | (EM_X86_64, R_X86_64_IRELATIVE) => {
+            Some(UnsupportedReloc::Irelative)
+        }
+        (EM_AARCH64, R_AARCH64_TLSDESC) | (EM_X86_64, R_X86_64_TLSDESC) => {
+            Some(UnsupportedReloc::TlsDesc)
+        }
+        (EM_AARCH64, R_AARCH64_TLS_DTPMOD64 | R_AARCH64_TLS_DTPREL64 | R_AARCH64_TLS_TPREL64)
+        | (EM_X86_64, R_X86_64_DTPMOD64 | R_X86_64_DTPOFF64 | R_X86_64_TPOFF64) => {
+            Some(UnsupportedReloc::Tls)
+        }
+        _ => None,
+    }
+}
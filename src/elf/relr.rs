@@ -0,0 +1,34 @@
+// DT_RELR 紧凑相对重定位解码：偶数条目为地址，奇数条目为其后 63 个字的位图
+
+use std::mem;
+
+use super::ElfAddr;
+
+const WORD_SIZE: usize = mem::size_of::<ElfAddr>();
+// 位图条目除最低位标志外可描述 63 个字
+const BITMAP_SPAN: usize = (ElfAddr::BITS as usize - 1) * WORD_SIZE;
+
+// 按条目判断 offset 是否被 RELR 表覆盖，位图条目只检查对应的一位，不展开全部地址
+pub(super) fn relr_covers(entries: &[ElfAddr], offset: usize) -> bool {
+    let mut base = 0usize;
+    for &entry in entries {
+        if entry & 1 == 0 {
+            if entry as usize == offset {
+                return true;
+            }
+            base = entry as usize + WORD_SIZE;
+            continue;
+        }
+        if offset >= base
+            && offset < base + BITMAP_SPAN
+            && (offset - base).is_multiple_of(WORD_SIZE)
+        {
+            let bit = (offset - base) / WORD_SIZE + 1;
+            if entry & (1 << bit) != 0 {
+                return true;
+            }
+        }
+        base += BITMAP_SPAN;
+    }
+    false
+}
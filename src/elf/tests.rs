@@ -0,0 +1,709 @@
+// ELF 解析辅助逻辑的单元测试
+use super::header_file::{find_build_id, parse_phdrs};
+use super::packed::PackedRelocIterator;
+use super::reloc::{UnsupportedReloc, unsupported_reloc};
+use super::relr::relr_covers;
+use super::{
+    DF_TEXTREL, DT_FLAGS, DT_NULL, DT_PLTRELSZ, DT_STRTAB, DT_TEXTREL, ELFCLASS64, ELFDATA2LSB,
+    ELFMAG, EM_AARCH64, EM_X86_64, ET_DYN, EV_CURRENT, EXPECTED_MACHINE, Elf, ElfDyn, ElfEhdr,
+    ElfPhdr, ElfXword, PT_DYNAMIC, PT_LOAD, R_AARCH64_ABS64, R_AARCH64_GLOB_DAT,
+    R_AARCH64_IRELATIVE, R_AARCH64_JUMP_SLOT, R_AARCH64_TLS_DTPMOD64, R_AARCH64_TLS_DTPREL64,
+    R_AARCH64_TLS_TPREL64, R_AARCH64_TLSDESC, R_X86_64_64, R_X86_64_DTPMOD64, R_X86_64_DTPOFF64,
+    R_X86_64_GLOB_DAT, R_X86_64_IRELATIVE, R_X86_64_JUMP_SLOT, R_X86_64_TLSDESC, R_X86_64_TPOFF64,
+    is_text_reloc_entry, split_symbol_version,
+};
+use crate::errno::Errno;
+use std::{mem, ptr};
+
+// 版本符号夹具由 fixtures/build.sh 生成：callee 导出 foo@V1 与默认版本 foo@@V2，caller 导入 foo@V1
+const VERSIONED_CALLEE: &[u8] = include_bytes!("fixtures/libversioned_callee.so");
+const VERSIONED_CALLER: &[u8] = include_bytes!("fixtures/libversioned_caller.so");
+// 绑定类型夹具：weak_only 仅有弱定义，pair@V1 全局、pair@@V2 弱定义，unique_value 为 GNU_UNIQUE
+const BINDING_CALLEE: &[u8] = include_bytes!("fixtures/libbinding_callee.so");
+// TLS 夹具：导入 tls_value，只有一条 TPOFF64 重定位
+const TLS_CALLER: &[u8] = include_bytes!("fixtures/libtls_caller.so");
+
+fn dyn_entry(d_tag: i64, d_un: u64) -> ElfDyn {
+    ElfDyn { d_tag, d_un }
+}
+
+fn has_text_relocs(entries: &[ElfDyn]) -> bool {
+    entries
+        .iter()
+        .take_while(|entry| entry.d_tag != DT_NULL)
+        .any(is_text_reloc_entry)
+}
+
+#[test]
+fn detects_dt_textrel_tag() {
+    let entries = [
+        dyn_entry(DT_STRTAB, 0x1000),
+        dyn_entry(DT_TEXTREL, 0),
+        dyn_entry(DT_NULL, 0),
+    ];
+    assert!(has_text_relocs(&entries));
+}
+
+#[test]
+fn detects_df_textrel_in_dt_flags() {
+    // DF_BIND_NOW | DF_TEXTREL
+    let entries = [dyn_entry(DT_FLAGS, 0x8 | DF_TEXTREL), dyn_entry(DT_NULL, 0)];
+    assert!(has_text_relocs(&entries));
+}
+
+#[test]
+fn ignores_dt_flags_without_textrel() {
+    // DF_BIND_NOW 单独出现不代表存在 text relocation
+    let entries = [dyn_entry(DT_FLAGS, 0x8), dyn_entry(DT_NULL, 0)];
+    assert!(!has_text_relocs(&entries));
+}
+
+#[test]
+fn stops_at_dt_null() {
+    let entries = [dyn_entry(DT_NULL, 0), dyn_entry(DT_TEXTREL, 0)];
+    assert!(!has_text_relocs(&entries));
+}
+
+#[test]
+fn relr_bitmap_covers_following_words() {
+    // 偶数条目为地址本身；奇数条目 bit n 覆盖 base + (n - 1) 个字，每个位图之后 base 前进 63 个字
+    let entries = [
+        0x1000,
+        1 | (1 << 1) | (1 << 3) | (1 << 63),
+        1 | (1 << 2),
+        1,
+        1 | (1 << 1),
+        0x8000,
+        1 | (1 << 1),
+    ];
+    let covered = [
+        0x1000, 0x1008, 0x1018, 0x11f8, 0x1208, 0x15f0, 0x8000, 0x8008,
+    ];
+    for offset in covered {
+        assert!(relr_covers(&entries, offset), "0x{offset:x} not covered");
+    }
+    let uncovered = [0, 0x1010, 0x1009, 0x11f0, 0x1200, 0x13f8, 0x8010];
+    for offset in uncovered {
+        assert!(!relr_covers(&entries, offset), "0x{offset:x} covered");
+    }
+    assert!(!relr_covers(&[], 0x1000));
+}
+
+// packed relocation 分组标志，与 PackedRelocIterator 中的定义一致
+const GROUPED_BY_INFO: i64 = 1;
+const GROUPED_BY_OFFSET_DELTA: i64 = 2;
+const GROUPED_BY_ADDEND: i64 = 4;
+const GROUP_HAS_ADDEND: i64 = 8;
+
+fn sleb128(out: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        let done = (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0);
+        out.push(if done { byte } else { byte | 0x80 });
+        if done {
+            return;
+        }
+    }
+}
+
+// 按 flags 编码一个分组：组内共享的字段写在组头，其余逐条写入；relocs 为 (offset 增量, r_info, addend 增量)
+fn packed_group(out: &mut Vec<u8>, flags: i64, is_rela: bool, relocs: &[(i64, u64, i64)]) {
+    let (delta, info, addend) = relocs[0];
+    let has = |flag: i64| flags & flag != 0;
+    sleb128(out, relocs.len() as i64);
+    sleb128(out, flags);
+    if has(GROUPED_BY_OFFSET_DELTA) {
+        sleb128(out, delta);
+    }
+    if has(GROUPED_BY_INFO) {
+        sleb128(out, info as i64);
+    }
+    if has(GROUP_HAS_ADDEND) && has(GROUPED_BY_ADDEND) {
+        sleb128(out, addend);
+    }
+    for &(delta, info, addend) in relocs {
+        if !has(GROUPED_BY_OFFSET_DELTA) {
+            sleb128(out, delta);
+        }
+        if !has(GROUPED_BY_INFO) {
+            sleb128(out, info as i64);
+        }
+        if is_rela && has(GROUP_HAS_ADDEND) && !has(GROUPED_BY_ADDEND) {
+            sleb128(out, addend);
+        }
+    }
+}
+
+// (flags, relocs) 的分组描述
+type PackedGroup = (i64, Vec<(i64, u64, i64)>);
+
+// 不含 "APS2" 魔数的流：重定位总数、起始 offset，之后为各分组
+fn packed_stream(start: i64, groups: &[PackedGroup], is_rela: bool) -> Vec<u8> {
+    let mut out = Vec::new();
+    let count = groups.iter().map(|(_, relocs)| relocs.len()).sum::<usize>();
+    sleb128(&mut out, count as i64);
+    sleb128(&mut out, start);
+    for (flags, relocs) in groups {
+        packed_group(&mut out, *flags, is_rela, relocs);
+    }
+    out
+}
+
+fn decode_packed(stream: &[u8], is_rela: bool) -> Result<Vec<(usize, ElfXword)>, Errno> {
+    let addr = stream.as_ptr() as usize;
+    let mut packed = unsafe { PackedRelocIterator::new(addr, stream.len(), is_rela) }?;
+    let mut relocs = Vec::new();
+    while let Some(reloc) = unsafe { packed.next() }? {
+        relocs.push((reloc.r_offset, reloc.r_info));
+    }
+    Ok(relocs)
+}
+
+#[test]
+fn packed_relocs_decode_every_group_flag_combination() {
+    for is_rela in [true, false] {
+        for flags in 0..16 {
+            let has = |flag: i64| flags & flag != 0;
+            let deltas = if has(GROUPED_BY_OFFSET_DELTA) {
+                [0x10, 0x10, 0x10]
+            } else {
+                [0x8, 0x18, -0x10]
+            };
+            let infos = if has(GROUPED_BY_INFO) {
+                [5 << 32 | 7; 3]
+            } else {
+                [1 << 32 | 7, 2 << 32 | 6, 3 << 32 | 1]
+            };
+            let addends = if has(GROUPED_BY_ADDEND) {
+                [4; 3]
+            } else {
+                [1, -2, 3]
+            };
+            let relocs: Vec<_> = (0..3).map(|i| (deltas[i], infos[i], addends[i])).collect();
+            // 第二个分组不带任何标志，检查组间状态切换
+            let tail = (0, vec![(0x20, 9 << 32 | 7, 0)]);
+            let stream = packed_stream(0x1000, &[(flags, relocs), tail], is_rela);
+
+            let result = decode_packed(&stream, is_rela);
+            if !is_rela && has(GROUP_HAS_ADDEND) && has(GROUPED_BY_ADDEND) {
+                assert_eq!(result, Err(Errno::Format), "flags {flags} in REL stream");
+                continue;
+            }
+            let mut offset = 0x1000i64;
+            let mut expected = Vec::new();
+            for (delta, info) in deltas.iter().zip(infos).chain([(&0x20, 9 << 32 | 7)]) {
+                offset += delta;
+                expected.push((offset as usize, info));
+            }
+            assert_eq!(result, Ok(expected), "flags {flags}, rela {is_rela}");
+        }
+    }
+}
+
+#[test]
+fn malformed_packed_streams_are_rejected() {
+    let stream = packed_stream(0x1000, &[(0, vec![(0x8, 7, 0), (0x8, 7, 0)])], true);
+    assert_eq!(
+        decode_packed(&stream[..stream.len() - 1], true),
+        Err(Errno::Format)
+    );
+    // 声明 1 条重定位但分组为空
+    let mut empty_group = Vec::new();
+    for value in [1, 0x1000, 0, 0] {
+        sleb128(&mut empty_group, value);
+    }
+    assert_eq!(decode_packed(&empty_group, true), Err(Errno::Format));
+}
+
+// 构造 ELF header + 程序头表的文件头部字节
+fn header_bytes(phoff: u64, phdrs: &[(u32, u64, u64)]) -> Vec<u8> {
+    let mut e_ident = [0u8; 16];
+    e_ident[..4].copy_from_slice(&ELFMAG);
+    e_ident[4] = ELFCLASS64;
+    e_ident[5] = ELFDATA2LSB;
+    e_ident[6] = EV_CURRENT;
+    let ehdr = ElfEhdr {
+        e_ident,
+        e_type: ET_DYN,
+        e_machine: EXPECTED_MACHINE,
+        e_version: EV_CURRENT as u32,
+        e_entry: 0,
+        e_phoff: phoff,
+        e_shoff: 0,
+        e_flags: 0,
+        e_ehsize: mem::size_of::<ElfEhdr>() as u16,
+        e_phentsize: mem::size_of::<ElfPhdr>() as u16,
+        e_phnum: phdrs.len() as u16,
+        e_shentsize: 0,
+        e_shnum: 0,
+        e_shstrndx: 0,
+    };
+    let mut buf = vec![0u8; phoff as usize + phdrs.len() * mem::size_of::<ElfPhdr>()];
+    unsafe {
+        std::ptr::write_unaligned(buf.as_mut_ptr() as *mut ElfEhdr, ehdr);
+        for (idx, &(p_type, p_offset, p_vaddr)) in phdrs.iter().enumerate() {
+            let phdr = ElfPhdr {
+                p_type,
+                p_flags: 0,
+                p_offset,
+                p_vaddr,
+                p_paddr: p_vaddr,
+                p_filesz: 0x1000,
+                p_memsz: 0x1000,
+                p_align: 0x1000,
+            };
+            let at = phoff as usize + idx * mem::size_of::<ElfPhdr>();
+            std::ptr::write_unaligned(buf.as_mut_ptr().add(at) as *mut ElfPhdr, phdr);
+        }
+    }
+    buf
+}
+
+fn note(name: &[u8], note_type: u32, desc: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&(name.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&note_type.to_le_bytes());
+    buf.extend_from_slice(name);
+    buf.resize(buf.len().next_multiple_of(4), 0);
+    buf.extend_from_slice(desc);
+    buf.resize(buf.len().next_multiple_of(4), 0);
+    buf
+}
+
+#[test]
+fn parses_program_headers_from_file_bytes() {
+    let buf = header_bytes(64, &[(PT_LOAD, 0, 0), (PT_DYNAMIC, 0x2000, 0x3000)]);
+    let phdrs = parse_phdrs(&buf).expect("valid headers");
+    assert_eq!(phdrs.len(), 2);
+    assert_eq!(phdrs[0].p_type, PT_LOAD);
+    assert_eq!(phdrs[1].p_type, PT_DYNAMIC);
+    assert_eq!(phdrs[1].p_vaddr, 0x3000);
+}
+
+#[test]
+fn rejects_program_headers_past_read_window() {
+    let mut buf = header_bytes(64, &[(PT_LOAD, 0, 0), (PT_DYNAMIC, 0x2000, 0x3000)]);
+    buf.truncate(buf.len() - 1);
+    assert!(parse_phdrs(&buf).is_err());
+}
+
+#[test]
+fn rejects_bad_magic() {
+    let mut buf = header_bytes(64, &[(PT_LOAD, 0, 0)]);
+    buf[0] = 0;
+    assert!(parse_phdrs(&buf).is_err());
+}
+
+#[test]
+fn finds_gnu_build_id_after_other_notes() {
+    let id = [0xde, 0xad, 0xbe, 0xef, 0x01];
+    let mut notes = note(b"Android\0", 1, &[0u8; 4]);
+    notes.extend(note(b"GNU\0", 3, &id));
+    assert_eq!(find_build_id(&notes), Some(&id[..]));
+}
+
+#[test]
+fn truncated_note_has_no_build_id() {
+    let notes = note(b"GNU\0", 3, &[1, 2, 3, 4, 5, 6, 7, 8]);
+    assert_eq!(find_build_id(&notes[..notes.len() - 4]), None);
+}
+
+// 按程序头把 PT_LOAD 段复制到各自的虚拟地址，得到与 linker 映射布局一致的映像
+// 以 u64 为存储单元，保证 ElfDyn / ElfSym 的对齐
+fn map_fixture(file: &[u8]) -> Vec<u64> {
+    let ehdr = unsafe { ptr::read_unaligned(file.as_ptr() as *const ElfEhdr) };
+    let loads: Vec<ElfPhdr> = (0..ehdr.e_phnum as usize)
+        .map(|idx| unsafe {
+            let at = file
+                .as_ptr()
+                .add(ehdr.e_phoff as usize + idx * mem::size_of::<ElfPhdr>());
+            ptr::read_unaligned(at as *const ElfPhdr)
+        })
+        .filter(|ph| ph.p_type == PT_LOAD)
+        .collect();
+    let size = loads
+        .iter()
+        .map(|ph| ph.p_vaddr + ph.p_memsz)
+        .max()
+        .unwrap_or(0);
+    let mut image = vec![0u64; (size as usize).div_ceil(8)];
+    let base = image.as_mut_ptr() as *mut u8;
+    for ph in &loads {
+        let src = &file[ph.p_offset as usize..][..ph.p_filesz as usize];
+        unsafe { ptr::copy_nonoverlapping(src.as_ptr(), base.add(ph.p_vaddr as usize), src.len()) };
+    }
+    image
+}
+
+fn init_fixture(image: &[u64], pathname: &str) -> Elf {
+    unsafe { Elf::init(image.as_ptr() as usize, pathname) }.expect("fixture parses")
+}
+
+// 改写映像中某个 dynamic 条目的值，模拟损坏或恶意构造的头部
+fn patch_dyn(image: &mut [u64], d_tag: i64, d_un: u64) {
+    let base = image.as_mut_ptr() as *mut u8;
+    let ehdr = unsafe { ptr::read(base as *const ElfEhdr) };
+    let phdrs = unsafe { base.add(ehdr.e_phoff as usize) } as *const ElfPhdr;
+    let dynamic = (0..ehdr.e_phnum as usize)
+        .map(|idx| unsafe { ptr::read(phdrs.add(idx)) })
+        .find(|ph| ph.p_type == PT_DYNAMIC)
+        .expect("fixture has PT_DYNAMIC");
+    let cnt = dynamic.p_memsz as usize / mem::size_of::<ElfDyn>();
+    let entries = unsafe { base.add(dynamic.p_vaddr as usize) } as *mut ElfDyn;
+    let entry = (0..cnt)
+        .map(|idx| unsafe { &mut *entries.add(idx) })
+        .find(|entry| entry.d_tag == d_tag)
+        .expect("fixture declares tag");
+    entry.d_un = d_un;
+}
+
+fn init_patched(d_tag: i64, d_un: u64) -> Result<Elf, Errno> {
+    let mut image = map_fixture(VERSIONED_CALLER);
+    patch_dyn(&mut image, d_tag, d_un);
+    unsafe { Elf::init(image.as_ptr() as usize, "libcorrupt.so") }
+}
+
+#[test]
+fn oversized_relocation_table_is_rejected() {
+    let image_bytes = (map_fixture(VERSIONED_CALLER).len() * 8) as u64;
+    assert!(init_patched(DT_PLTRELSZ, image_bytes).is_err_and(|err| err == Errno::Format));
+    assert!(init_patched(DT_PLTRELSZ, u64::MAX / 2).is_err_and(|err| err == Errno::Format));
+}
+
+#[test]
+fn strtab_outside_image_is_rejected() {
+    let before_base = (-0x1000i64) as u64;
+    let past_end = (map_fixture(VERSIONED_CALLER).len() * 8) as u64;
+    assert!(init_patched(DT_STRTAB, before_base).is_err_and(|err| err == Errno::Format));
+    assert!(init_patched(DT_STRTAB, past_end).is_err_and(|err| err == Errno::Format));
+}
+
+#[test]
+fn splits_versioned_symbol_name() {
+    assert_eq!(split_symbol_version("foo"), ("foo", None));
+    assert_eq!(split_symbol_version("foo@V1"), ("foo", Some("V1")));
+    assert_eq!(split_symbol_version("foo@"), ("foo", Some("")));
+}
+
+#[test]
+fn export_lookup_prefers_default_version() {
+    let image = map_fixture(VERSIONED_CALLEE);
+    let elf = init_fixture(&image, "libversioned_callee.so");
+    let v1 = elf.find_export_function("foo", Some("V1"));
+    let v2 = elf.find_export_function("foo", Some("V2"));
+    assert!(v1.is_some() && v2.is_some());
+    assert_ne!(v1, v2);
+    assert_eq!(elf.find_export_function("foo", None), v2);
+    assert_eq!(elf.find_export_function("foo", Some("V3")), None);
+}
+
+#[test]
+fn symbol_by_addr_resolves_inside_function_range() {
+    let image = map_fixture(VERSIONED_CALLEE);
+    let elf = init_fixture(&image, "libversioned_callee.so");
+    let foo = elf.find_export_function("foo", None).expect("foo exported");
+    let (name, start, size) = unsafe { elf.find_symbol_by_addr(foo) }.expect("foo resolved");
+    assert_eq!((name, start), ("foo", foo));
+    if size > 1 {
+        let inner = unsafe { elf.find_symbol_by_addr(foo + size - 1) };
+        assert_eq!(
+            inner.map(|(name, start, _)| (name, start)),
+            Some(("foo", foo))
+        );
+    }
+    let outside = unsafe { elf.find_symbol_by_addr(elf.bias_addr.wrapping_sub(1)) };
+    assert_eq!(outside, None);
+}
+
+#[test]
+fn weak_only_export_is_found() {
+    let image = map_fixture(BINDING_CALLEE);
+    let elf = init_fixture(&image, "libbinding_callee.so");
+    let weak = elf.find_export_function("weak_only", None);
+    assert!(weak.is_some());
+    assert_eq!(
+        elf.find_export_candidates("weak_only", None),
+        Vec::from_iter(weak)
+    );
+}
+
+#[test]
+fn global_export_is_preferred_over_weak() {
+    let image = map_fixture(BINDING_CALLEE);
+    let elf = init_fixture(&image, "libbinding_callee.so");
+    let global = elf
+        .find_export_function("pair", Some("V1"))
+        .expect("pair@V1");
+    let weak = elf
+        .find_export_function("pair", Some("V2"))
+        .expect("pair@@V2");
+    assert_ne!(global, weak);
+    assert_eq!(elf.find_export_candidates("pair", None), vec![global, weak]);
+    assert_eq!(elf.find_export_function("pair", None), Some(global));
+}
+
+#[test]
+fn gnu_unique_export_ranks_as_global() {
+    let image = map_fixture(BINDING_CALLEE);
+    let elf = init_fixture(&image, "libbinding_callee.so");
+    let symidx = elf
+        .find_symidx_by_name("unique_value", None)
+        .expect("unique_value defined");
+    assert_eq!(unsafe { elf.sym_bind_rank(symidx) }, 0);
+    assert!(elf.find_export_function("unique_value", None).is_some());
+}
+
+#[test]
+fn import_lookup_matches_needed_version() {
+    let image = map_fixture(VERSIONED_CALLER);
+    let elf = init_fixture(&image, "libversioned_caller.so");
+    let symidx = elf
+        .find_symidx_by_name("foo", Some("V1"))
+        .expect("foo@V1 imported");
+    assert_eq!(unsafe { elf.sym_version(symidx) }, Some("V1"));
+    assert_eq!(elf.find_symidx_by_name("foo", None), Ok(symidx));
+    let v2 = elf.find_symidx_by_name("foo", Some("V2"));
+    assert_eq!(v2, Err(Errno::NotFound));
+}
+
+// 夹具按 x86_64 链接，重定位类型只在该架构上与 R_GENERIC_JUMP_SLOT 一致
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn got_slots_are_filtered_by_version() {
+    let image = map_fixture(VERSIONED_CALLER);
+    let elf = init_fixture(&image, "libversioned_caller.so");
+    let v1 = unsafe { elf.find_got_slots("foo", Some("V1"), None) };
+    let v2 = unsafe { elf.find_got_slots("foo", Some("V2"), None) };
+    assert_eq!(v1.map(|slots| slots.len()), Ok(1));
+    assert_eq!(v2, Ok(Vec::new()));
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn import_names_come_from_relocations() {
+    let image = map_fixture(VERSIONED_CALLER);
+    let elf = init_fixture(&image, "libversioned_caller.so");
+    let all = unsafe { elf.find_import_names(|_| true) };
+    let none = unsafe { elf.find_import_names(|name| name.starts_with("call")) };
+    assert_eq!(all, Ok(vec!["foo".to_string()]));
+    assert_eq!(none, Ok(Vec::new()));
+}
+
+// vendor 库把 JUMP_SLOT 放在带 grouped addend 的 packed 分组中，slot 查找与导入名都要能看到
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn jump_slot_in_packed_group_is_found() {
+    let image = map_fixture(VERSIONED_CALLER);
+    let mut elf = init_fixture(&image, "libversioned_caller.so");
+    let slots = unsafe { elf.find_got_slots("foo", Some("V1"), None) }.expect("slot in .rela.plt");
+    let symidx = elf.find_symidx_by_name("foo", Some("V1")).expect("foo@V1");
+    let offset = (slots[0] - elf.bias_addr) as i64;
+    let jump_slot = (symidx as u64) << 32 | super::R_GENERIC_JUMP_SLOT as u64;
+    let flags = GROUPED_BY_INFO | GROUP_HAS_ADDEND | GROUPED_BY_ADDEND;
+    let stream = packed_stream(0, &[(flags, vec![(offset, jump_slot, 0)])], true);
+    elf.relplt = 0;
+    elf.reldyn = 0;
+    elf.relandroid = stream.as_ptr() as usize;
+    elf.relandroid_sz = stream.len();
+    elf.relandroid_is_rela = true;
+
+    let packed = unsafe { elf.find_got_slots_by_class("foo", Some("V1"), None) }.expect("packed");
+    assert_eq!(packed.plt, slots);
+    assert!(packed.data.is_empty());
+    let names = unsafe { elf.find_import_names(|_| true) };
+    assert_eq!(names, Ok(vec!["foo".to_string()]));
+}
+
+// 同一符号的 JUMP_SLOT 与 GLOB_DAT 经 packed 重定位引用（offset 为相邻差值），slot 值由测试直接写入
+// GLOB_DAT 默认紧邻 JUMP_SLOT 之前，data_offset 指定时放在该偏移
+#[cfg(target_arch = "x86_64")]
+fn mixed_slot_elf(image: &[u64], data_offset: Option<i64>) -> (Elf, usize, usize, Vec<u8>) {
+    let mut elf = init_fixture(image, "libversioned_caller.so");
+    let slots = unsafe { elf.find_got_slots("foo", Some("V1"), None) }.expect("slot in .rela.plt");
+    let symidx = elf.find_symidx_by_name("foo", Some("V1")).expect("foo@V1");
+    let plt_offset = (slots[0] - elf.bias_addr) as i64;
+    let data_offset = data_offset.unwrap_or(plt_offset - 8);
+    let jump_slot = (symidx as u64) << 32 | super::R_GENERIC_JUMP_SLOT as u64;
+    let glob_dat = (symidx as u64) << 32 | super::R_GENERIC_GLOB_DAT as u64;
+    let flags = GROUPED_BY_INFO | GROUP_HAS_ADDEND | GROUPED_BY_ADDEND;
+    let stream = packed_stream(
+        0,
+        &[
+            (flags, vec![(plt_offset, jump_slot, 0)]),
+            (flags, vec![(data_offset - plt_offset, glob_dat, 0)]),
+        ],
+        true,
+    );
+    elf.relplt = 0;
+    elf.reldyn = 0;
+    elf.relandroid = stream.as_ptr() as usize;
+    elf.relandroid_sz = stream.len();
+    elf.relandroid_is_rela = true;
+    let (plt, data) = (slots[0], elf.bias_addr + data_offset as usize);
+    (elf, plt, data, stream)
+}
+
+#[cfg(target_arch = "x86_64")]
+fn write_slot(image: &mut [u64], addr: usize, value: usize) {
+    image[(addr - image.as_ptr() as usize) / 8] = value as u64;
+}
+
+// callee 过滤逐个 slot 判断：指向其他定义的 slot 不连累匹配的 slot
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn callee_filter_is_applied_per_slot() {
+    let mut image = map_fixture(VERSIONED_CALLER);
+    let (elf, plt, data, _stream) = mixed_slot_elf(&image, None);
+    let expected = std::collections::BTreeSet::from([0x7000_1000usize]);
+    write_slot(&mut image, plt, 0x7000_1000);
+    write_slot(&mut image, data, 0x7000_2000);
+    let slots = unsafe { elf.find_got_slots_by_class("foo", Some("V1"), Some(&expected)) };
+    let slots = slots.expect("filtered slots");
+    assert_eq!((slots.plt, slots.data), (vec![plt], Vec::new()));
+
+    write_slot(&mut image, plt, 0x7000_2000);
+    write_slot(&mut image, data, 0x7000_1000);
+    let slots = unsafe { elf.find_got_slots_by_class("foo", Some("V1"), Some(&expected)) };
+    let slots = slots.expect("filtered slots");
+    assert_eq!((slots.plt, slots.data), (Vec::new(), vec![data]));
+}
+
+// 未解析的调用槽跟随同一符号 GLOB_DAT 的结论，两类一起接受或一起拒绝
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn lazy_plt_slot_follows_resolved_data_slot() {
+    let mut image = map_fixture(VERSIONED_CALLER);
+    let (elf, plt, data, _stream) = mixed_slot_elf(&image, None);
+    let expected = std::collections::BTreeSet::from([0x7000_1000usize]);
+    let stub = elf.bias_addr + 0x10;
+    write_slot(&mut image, plt, stub);
+    write_slot(&mut image, data, 0x7000_1000);
+    let slots = unsafe { elf.find_got_slots_by_class("foo", Some("V1"), Some(&expected)) };
+    let slots = slots.expect("filtered slots");
+    assert_eq!((slots.plt, slots.data), (vec![plt], vec![data]));
+
+    write_slot(&mut image, data, 0x7000_2000);
+    let slots = unsafe { elf.find_got_slots_by_class("foo", Some("V1"), Some(&expected)) };
+    assert!(slots.is_ok_and(|slots| slots.is_empty()));
+}
+
+// DT_TEXTREL 模块中修补位置落在代码段内的重定位计入盲区；默认不进入 slot 集合，textrel-patch 下交给写入路径
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn text_relocation_in_exec_segment_is_gated_by_feature() {
+    let image = map_fixture(VERSIONED_CALLER);
+    let probe = init_fixture(&image, "libversioned_caller.so");
+    let text = probe
+        .phdrs
+        .iter()
+        .find(|ph| ph.p_type == PT_LOAD && ph.p_flags & super::PF_X != 0)
+        .expect("fixture has an executable segment")
+        .p_vaddr as i64;
+    let (mut elf, plt, data, _stream) = mixed_slot_elf(&image, Some(text));
+    assert!(elf.is_addr_in_exec_segments(data));
+
+    let slots = unsafe { elf.find_got_slots_by_class("foo", Some("V1"), None) }.expect("slots");
+    assert_eq!((slots.plt, slots.data), (vec![plt], vec![data]));
+    assert_eq!(unsafe { elf.count_text_relocs("foo", Some("V1")) }, Ok(0));
+
+    elf.has_text_relocs = true;
+    let slots = unsafe { elf.find_got_slots_by_class("foo", Some("V1"), None) }.expect("slots");
+    let patched = if cfg!(feature = "textrel-patch") {
+        vec![data]
+    } else {
+        Vec::new()
+    };
+    assert_eq!((slots.plt, slots.data), (vec![plt], patched));
+    assert_eq!(unsafe { elf.count_text_relocs("foo", Some("V1")) }, Ok(1));
+}
+
+// 把 fixture 的 hash 表替换为给定的 bucket / chain，符号表与字符串表沿用 fixture
+fn with_sysv_hash(elf: &mut Elf, bucket: &[u32], chain: &[u32]) {
+    elf.is_use_gnu_hash = false;
+    elf.bucket = bucket.as_ptr();
+    elf.bucket_cnt = bucket.len() as u32;
+    elf.chain = chain.as_ptr();
+    elf.chain_cnt = chain.len() as u32;
+}
+
+#[test]
+fn self_referential_hash_chain_terminates() {
+    let image = map_fixture(VERSIONED_CALLER);
+    let mut elf = init_fixture(&image, "libversioned_caller.so");
+    let bucket = [1];
+    let chain = [0, 1];
+    with_sysv_hash(&mut elf, &bucket, &chain);
+    let looped = elf.find_symidx_by_name("no_such_symbol", None);
+    assert_eq!(looped, Err(Errno::Format));
+    assert_eq!(elf.find_export_function("no_such_symbol", None), None);
+
+    // bucket 指向 chain 之外的索引同样视为损坏，不会越界读取
+    let bucket = [7];
+    with_sysv_hash(&mut elf, &bucket, &chain);
+    let out_of_range = elf.find_symidx_by_name("no_such_symbol", None);
+    assert_eq!(out_of_range, Err(Errno::Format));
+}
+
+#[test]
+fn gnu_hash_chain_without_end_marker_terminates() {
+    let image = map_fixture(VERSIONED_CALLER);
+    let mut elf = init_fixture(&image, "libversioned_caller.so");
+    // bloom 全 1 放行所有符号，chain 覆盖 fixture 的全部符号但均无链尾标记
+    let bloom = [usize::MAX];
+    let bucket = [1u32];
+    let chain = vec![0u32; elf.dynsym_count() as usize - 1];
+    elf.is_use_gnu_hash = true;
+    elf.bloom = bloom.as_ptr();
+    elf.bloom_sz = 1;
+    elf.bloom_shift = 6;
+    elf.bucket = bucket.as_ptr();
+    elf.bucket_cnt = 1;
+    elf.chain = chain.as_ptr();
+    elf.symoffset = 1;
+    let looped = elf.find_symidx_by_name("no_such_symbol", None);
+    assert_eq!(looped, Err(Errno::Format));
+}
+
+#[test]
+fn unsupported_relocation_types_are_classified_per_machine() {
+    let cases = [
+        (EM_AARCH64, R_AARCH64_IRELATIVE, UnsupportedReloc::Irelative),
+        (EM_AARCH64, R_AARCH64_TLSDESC, UnsupportedReloc::TlsDesc),
+        (EM_AARCH64, R_AARCH64_TLS_DTPMOD64, UnsupportedReloc::Tls),
+        (EM_AARCH64, R_AARCH64_TLS_DTPREL64, UnsupportedReloc::Tls),
+        (EM_AARCH64, R_AARCH64_TLS_TPREL64, UnsupportedReloc::Tls),
+        (EM_X86_64, R_X86_64_IRELATIVE, UnsupportedReloc::Irelative),
+        (EM_X86_64, R_X86_64_TLSDESC, UnsupportedReloc::TlsDesc),
+        (EM_X86_64, R_X86_64_DTPMOD64, UnsupportedReloc::Tls),
+        (EM_X86_64, R_X86_64_DTPOFF64, UnsupportedReloc::Tls),
+        (EM_X86_64, R_X86_64_TPOFF64, UnsupportedReloc::Tls),
+    ];
+    for (machine, r_type, kind) in cases {
+        assert_eq!(
+            unsupported_reloc(machine, r_type),
+            Some(kind),
+            "type {r_type}"
+        );
+    }
+    // 可改写的类型与其他架构的编号不会被误判
+    for r_type in [R_AARCH64_JUMP_SLOT, R_AARCH64_GLOB_DAT, R_AARCH64_ABS64] {
+        assert_eq!(unsupported_reloc(EM_AARCH64, r_type), None);
+    }
+    for r_type in [R_X86_64_JUMP_SLOT, R_X86_64_GLOB_DAT, R_X86_64_64] {
+        assert_eq!(unsupported_reloc(EM_X86_64, r_type), None);
+    }
+    assert_eq!(unsupported_reloc(EM_X86_64, R_AARCH64_IRELATIVE), None);
+    assert_eq!(unsupported_reloc(EM_AARCH64, R_X86_64_IRELATIVE), None);
+}
+
+#[test]
+fn tls_import_reports_unsupported_reloc() {
+    let image = map_fixture(TLS_CALLER);
+    let elf = init_fixture(&image, "libtls_caller.so");
+    let slots = unsafe { elf.find_got_slots_by_class("tls_value", None, None) };
+    assert_eq!(slots.err(), Some(Errno::UnsupportedReloc));
+    let missing = unsafe { elf.find_got_slots_by_class("no_such_symbol", None, None) };
+    assert!(missing.is_ok_and(|slots| slots.is_empty()));
+}
@@ -0,0 +1,87 @@
+// 符号版本：解析 versym / verdef / verneed，按 "name@VERSION" 区分同名符号的不同版本，通过 include! 嵌入 elf.rs
+
+// 符号查找时的版本约束
+#[derive(Clone, Copy)]
+enum VersionQuery<'a> {
+    // 默认版本（foo@@V）或无版本符号
+    Default,
+    Any,
+    Named(&'a str),
+}
+
+// 拆分 "name@VERSION"，不含 @ 时版本为 None
+pub fn split_symbol_version(symbol: &str) -> (&str, Option<&str>) {
+    match symbol.split_once('@') {
+        Some((name, version)) => (name, Some(version)),
+        None => (symbol, None),
+    }
+}
+
+impl Elf {
+    // 符号绑定的版本名：已定义符号取自 verdef，导入符号取自 verneed
+    pub unsafe fn sym_version(&self, idx: u32) -> Option<&str> {
+        let ndx = self.sym_versym(idx)? & VERSYM_VERSION;
+        if ndx <= VER_NDX_GLOBAL {
+            return None;
+        }
+        let name_off = self.verdef_name(ndx).or_else(|| self.verneed_name(ndx))?;
+        CStr::from_ptr(self.strtab.add(name_off as usize))
+            .to_str()
+            .ok()
+    }
+
+    unsafe fn sym_versym(&self, idx: u32) -> Option<u16> {
+        (!self.versym.is_null()).then(|| *self.versym.add(idx as usize))
+    }
+
+    // 按 vd_ndx 查找版本定义，返回首个 aux 条目的名称偏移
+    unsafe fn verdef_name(&self, ndx: u16) -> Option<ElfWord> {
+        let mut addr = self.verdef;
+        for _ in 0..self.verdef_cnt {
+            let def = &*(addr as *const ElfVerdef);
+            if def.vd_ndx == ndx {
+                let aux = &*((addr + def.vd_aux as usize) as *const ElfVerdaux);
+                return (def.vd_cnt != 0).then_some(aux.vda_name);
+            }
+            if def.vd_next == 0 {
+                break;
+            }
+            addr += def.vd_next as usize;
+        }
+        None
+    }
+
+    // 按 vna_other 在各依赖库的版本需求中查找，返回名称偏移
+    unsafe fn verneed_name(&self, ndx: u16) -> Option<ElfWord> {
+        let mut addr = self.verneed;
+        for _ in 0..self.verneed_cnt {
+            let need = &*(addr as *const ElfVerneed);
+            let mut aux_addr = addr + need.vn_aux as usize;
+            for _ in 0..need.vn_cnt {
+                let aux = &*(aux_addr as *const ElfVernaux);
+                if aux.vna_other & VERSYM_VERSION == ndx {
+                    return Some(aux.vna_name);
+                }
+                if aux.vna_next == 0 {
+                    break;
+                }
+                aux_addr += aux.vna_next as usize;
+            }
+            if need.vn_next == 0 {
+                break;
+            }
+            addr += need.vn_next as usize;
+        }
+        None
+    }
+
+    unsafe fn version_accepts(&self, idx: u32, query: VersionQuery<'_>) -> bool {
+        match query {
+            VersionQuery::Default => self
+                .sym_versym(idx)
+                .is_none_or(|versym| versym & VERSYM_HIDDEN == 0),
+            VersionQuery::Any => true,
+            VersionQuery::Named(version) => self.sym_version(idx) == Some(version),
+        }
+    }
+}
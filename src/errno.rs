@@ -2,46 +2,55 @@
 #[repr(i32)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Errno {
-    Ok = 0,                // 成功
-    Uninit = 1,            // 未初始化
-    InitErrInvalidArg = 2, // 初始化参数无效
-    InitErrSym = 3,        // 符号解析失败
-    InitErrTask = 4,       // 任务管理器初始化失败
-    InitErrHook = 5,       // hook 引擎初始化失败
-    InitErrElf = 6,        // ELF 解析器初始化失败
-    InitErrElfRefr = 7,    // ELF 刷新器初始化失败
-    InitErrTrampo = 8,     // trampoline 管理器初始化失败
-    InitErrSig = 9,        // 信号守卫初始化失败
-    InitErrDlMtr = 10,     // dlopen 监控器初始化失败
-    InvalidArg = 11,       // 参数无效
-    UnmatchOrigFunc = 12,  // 原函数地址不匹配
-    NoSym = 13,            // 符号未找到
-    GetProt = 14,          // 读取内存保护属性失败
-    SetProt = 15,          // 设置内存保护属性失败
-    SetGot = 16,           // 写入 GOT 表项失败
-    NewTrampo = 17,        // 创建 trampoline 失败
-    AppendTrampo = 18,     // 追加 trampoline 节点失败
-    GotVerify = 19,        // GOT 表项校验失败
-    RepeatedFunc = 20,     // 重复的 proxy 函数
-    ReadElf = 21,          // 读取 ELF 信息失败
-    CfiHookFailed = 22,    // CFI hook 失败
-    OrigAddr = 23,         // 原始地址获取失败
-    InitErrCfi = 24,       // CFI 模块初始化失败
-    Ignore = 25,           // 模块在忽略列表中
-    InitErrSafe = 26,      // 在外部回调中调用，拒绝执行
-    InitErrHub = 27,       // hub 管理器初始化失败
-    Oom = 28,              // 内存分配失败
-    Dup = 29,              // 重复操作
-    NotFound = 30,         // 未找到目标
-    Max = 255,             // 保留上界
-    Unknown = 1001,        // 未知错误
-    Invalid = 1002,        // 无效状态
-    NoMem = 1003,          // 内存不足
-    Repeat = 1004,         // 重复请求
-    BadMaps = 1006,        // /proc/self/maps 解析失败
-    Format = 1007,         // 格式错误
-    ElfInit = 1008,        // ELF 初始化失败
-    SegvErr = 1009,        // 信号保护触发
+    Ok = 0,                 // 成功
+    Uninit = 1,             // 未初始化
+    InitErrInvalidArg = 2,  // 初始化参数无效
+    InitErrSym = 3,         // 符号解析失败
+    InitErrTask = 4,        // 任务管理器初始化失败
+    InitErrHook = 5,        // hook 引擎初始化失败
+    InitErrElf = 6,         // ELF 解析器初始化失败
+    InitErrElfRefr = 7,     // ELF 刷新器初始化失败
+    InitErrTrampo = 8,      // trampoline 管理器初始化失败
+    InitErrSig = 9,         // 信号守卫初始化失败
+    InitErrDlMtr = 10,      // dlopen 监控器初始化失败
+    InvalidArg = 11,        // 参数无效
+    UnmatchOrigFunc = 12,   // 原函数地址不匹配
+    NoSym = 13,             // 符号未找到
+    GetProt = 14,           // 读取内存保护属性失败
+    SetProt = 15,           // 设置内存保护属性失败
+    SetGot = 16,            // 写入 GOT 表项失败
+    NewTrampo = 17,         // 创建 trampoline 失败
+    AppendTrampo = 18,      // 追加 trampoline 节点失败
+    GotVerify = 19,         // GOT 表项校验失败
+    RepeatedFunc = 20,      // 重复的 proxy 函数
+    ReadElf = 21,           // 读取 ELF 信息失败
+    CfiHookFailed = 22,     // CFI hook 失败
+    OrigAddr = 23,          // 原始地址获取失败
+    InitErrCfi = 24,        // CFI 模块初始化失败
+    Ignore = 25,            // 模块在忽略列表中
+    InitErrSafe = 26,       // 在外部回调中调用，拒绝执行
+    InitErrHub = 27,        // hub 管理器初始化失败
+    Oom = 28,               // 内存分配失败
+    Dup = 29,               // 重复操作
+    NotFound = 30,          // 未找到目标
+    AbiMismatch = 31,       // 客户端 ABI 版本不受支持
+    WriteRejected = 32,     // slot 所在页拒绝写入（写入无效或反复触发保护）
+    ModuleUnloading = 33,   // 模块在应用 hook 期间被并发卸载
+    AmbiguousCallee = 34,   // callee 规则匹配到多个导出该符号的实例，且任务要求唯一
+    SlotTampered = 35,      // 已挂载的 slot 被第三方改写，巡检时已重新写回
+    PatternLimit = 36,      // 通配符号在单个模块内的匹配数超过上限，该模块未 hook
+    UnsupportedReloc = 37,  // 符号只出现在 IRELATIVE / TLS 等不支持改写的重定位中
+    Resetting = 38,         // 运行时正在 clear / shutdown 复位，只读查询被拒绝
+    PatchVerifyFailed = 39, // 写入并刷新缓存后回读的 slot 值与写入值不一致
+    Max = 255,              // 保留上界
+    Unknown = 1001,         // 未知错误
+    Invalid = 1002,         // 无效状态
+    NoMem = 1003,           // 内存不足
+    Repeat = 1004,          // 重复请求
+    BadMaps = 1006,         // /proc/self/maps 解析失败
+    Format = 1007,          // 格式错误
+    ElfInit = 1008,         // ELF 初始化失败
+    SegvErr = 1009,         // 信号保护触发
 }
 
 impl Errno {
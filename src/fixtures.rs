@@ -0,0 +1,177 @@
+// 集成测试夹具：将同一个 so 复制到多个目录后分别加载，得到同 basename 的不同实例
+// 可选加载后删除文件（maps 中显示为 "(deleted)"）、RTLD_LAZY / RTLD_NODELETE 等加载方式
+// LoadedFixture 在 Drop 时 dlclose 并删除复制出的文件与目录
+use crate::android::{memory, signal_guard};
+use crate::api::{ModuleIdentity, get_module_identity};
+use crate::errno::Errno;
+use std::ffi::{CStr, CString, c_void};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub struct FixtureBuilder {
+    work_dir: PathBuf,
+    library: String,
+    name: String,
+    instances: usize,
+    lazy: bool,
+    deleted: bool,
+    nodelete: bool,
+}
+
+impl FixtureBuilder {
+    // library 为 work_dir 下的源 so 文件名，复制后的实例保持相同 basename
+    pub fn new(work_dir: impl Into<PathBuf>, library: &str) -> Self {
+        Self {
+            work_dir: work_dir.into(),
+            library: library.to_string(),
+            name: "fixture".to_string(),
+            instances: 1,
+            lazy: false,
+            deleted: false,
+            nodelete: false,
+        }
+    }
+
+    // 实例目录前缀，第 i 个实例位于 work_dir/{name}_{i}/
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    pub fn instances(mut self, count: usize) -> Self {
+        self.instances = count;
+        self
+    }
+
+    pub fn with_lazy(mut self, lazy: bool) -> Self {
+        self.lazy = lazy;
+        self
+    }
+
+    // 加载后删除文件，模块映射保留但路径带 "(deleted)" 后缀
+    pub fn deleted(mut self, deleted: bool) -> Self {
+        self.deleted = deleted;
+        self
+    }
+
+    pub fn nodelete(mut self, nodelete: bool) -> Self {
+        self.nodelete = nodelete;
+        self
+    }
+
+    fn dlopen_flags(&self) -> libc::c_int {
+        let mut flags = if self.lazy {
+            libc::RTLD_LAZY
+        } else {
+            libc::RTLD_NOW
+        };
+        if self.nodelete {
+            flags |= libc::RTLD_NODELETE;
+        }
+        flags
+    }
+
+    // 任一实例失败时已加载的实例随返回的错误一并释放
+    pub fn build(self) -> io::Result<Vec<LoadedFixture>> {
+        let source = self.work_dir.join(&self.library);
+        let mut fixtures = Vec::with_capacity(self.instances);
+        for idx in 0..self.instances {
+            let dir = self.work_dir.join(format!("{}_{}", self.name, idx));
+            fs::create_dir_all(&dir)?;
+            let path = dir.join(&self.library);
+            fs::copy(&source, &path)?;
+            let mut fixture = LoadedFixture {
+                handle: std::ptr::null_mut(),
+                path,
+                dir,
+                identity: None,
+            };
+            fixture.handle = dlopen(&fixture.path, self.dlopen_flags())?;
+            fixture.identity = get_module_identity(fixture.handle);
+            if self.deleted {
+                fs::remove_file(&fixture.path)?;
+            }
+            fixtures.push(fixture);
+        }
+        Ok(fixtures)
+    }
+}
+
+fn dlopen(path: &Path, flags: libc::c_int) -> io::Result<*mut c_void> {
+    let c_path = CString::new(path.to_string_lossy().as_bytes())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains nul"))?;
+    let handle = unsafe { libc::dlopen(c_path.as_ptr(), flags) };
+    if !handle.is_null() {
+        return Ok(handle);
+    }
+    let reason = unsafe {
+        let err = libc::dlerror();
+        if err.is_null() {
+            "unknown dlopen error".to_string()
+        } else {
+            CStr::from_ptr(err).to_string_lossy().into_owned()
+        }
+    };
+    Err(io::Error::other(format!(
+        "dlopen {}: {}",
+        path.display(),
+        reason
+    )))
+}
+
+pub struct LoadedFixture {
+    handle: *mut c_void,
+    path: PathBuf,
+    dir: PathBuf,
+    identity: Option<ModuleIdentity>,
+}
+
+impl LoadedFixture {
+    // close 之后返回空指针
+    pub fn handle(&self) -> *mut c_void {
+        self.handle
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn c_path(&self) -> CString {
+        CString::new(self.path.to_string_lossy().as_bytes()).unwrap_or_default()
+    }
+
+    // 加载后立即采集的实例标识，删除文件前完成
+    pub fn identity(&self) -> Option<&ModuleIdentity> {
+        self.identity.as_ref()
+    }
+
+    // 提前卸载实例；文件与目录仍在 Drop 时清理
+    pub fn close(&mut self) {
+        if self.handle.is_null() {
+            return;
+        }
+        unsafe {
+            libc::dlclose(self.handle);
+        }
+        self.handle = std::ptr::null_mut();
+    }
+}
+
+impl Drop for LoadedFixture {
+    fn drop(&mut self) {
+        self.close();
+        let _ = fs::remove_file(&self.path);
+        let _ = fs::remove_dir(&self.dir);
+    }
+}
+
+// 在信号守卫下执行闭包，供集成测试验证守卫捕获的信号后能跳回；捕获到信号时返回 SegvErr
+pub fn run_guarded<T>(f: impl FnOnce() -> T) -> Result<T, Errno> {
+    signal_guard::with_guard(f)
+}
+
+// 在 [addr, addr + len) 临时可写期间执行闭包，供集成测试验证原始保护的精确恢复
+pub fn run_writable<T>(addr: usize, len: usize, f: impl FnOnce() -> T) -> Result<T, Errno> {
+    memory::with_writable(addr, len, f)
+}
@@ -9,38 +9,79 @@ compile_error!("srx_hook supports Android only (use cargo clippy/test/doc on hos
 compile_error!("srx_hook supports only 64-bit architectures: aarch64 and x86_64");
 
 // 公共 API 层，提供 hook 注册、刷新、忽略等操作
-#[cfg(target_os = "android")]
+#[cfg(any(target_os = "android", test))]
 mod api;
 // ELF 解析核心，处理动态段、符号表、重定位表
-#[cfg(target_os = "android")]
+#[cfg(any(target_os = "android", test))]
 mod elf;
 // 错误码定义
-#[cfg(target_os = "android")]
+#[cfg(any(target_os = "android", test))]
 mod errno;
-// 日志输出，使用 Android logcat
-#[cfg(target_os = "android")]
+// 日志输出，默认写 Android logcat，可交给用户 sink
+#[cfg(any(target_os = "android", test))]
 mod log;
 // Android 相关：内存保护与信号守卫
-#[cfg(target_os = "android")]
+#[cfg(any(target_os = "android", test))]
 mod android;
 // 运行时状态管理：生命周期、刷新管道、规则编译
-#[cfg(target_os = "android")]
+#[cfg(any(target_os = "android", test))]
 mod runtime;
+// 测试夹具：复制并加载同一模块的多个实例
+#[cfg(all(target_os = "android", feature = "test-fixtures"))]
+pub mod fixtures;
 // 版本信息
-#[cfg(target_os = "android")]
+#[cfg(any(target_os = "android", test))]
 mod version;
 
-#[cfg(target_os = "android")]
+#[cfg(any(target_os = "android", test))]
 pub use api::{
-    CallerAllowFilter, HookMode, HookStub, HookedCallback, ModuleIdentity, PostDlopenCallback,
-    PreDlopenCallback, RECORD_ITEM_ALL, RECORD_ITEM_CALLER_LIB_NAME, RECORD_ITEM_ERRNO,
-    RECORD_ITEM_LIB_NAME, RECORD_ITEM_NEW_ADDR, RECORD_ITEM_OP, RECORD_ITEM_STUB,
-    RECORD_ITEM_SYM_NAME, RECORD_ITEM_TIMESTAMP, add_dlopen_callback, add_ignore, clear,
-    del_dlopen_callback, dump_records, enable_debug, enable_sigsegv_protection, get_debug,
-    get_mode, get_module_identity, get_module_identity_with_symbol, get_prev_func,
-    get_recordable, get_records, get_return_address, get_version, hook_all, hook_partial,
-    hook_single, init, is_forked_child, pop_stack, proxy_enter, proxy_leave, refresh,
-    set_debug, set_recordable, unhook, with_prev_func,
+    ABI_VERSION, ArtifactKind, CallbackDispatch, CalleeSelection, CallerAllowFilter,
+    CallerAllowFilterEx, Capabilities, CfiAddrSource, CfiPatchEntry, CfiPatchScope, CfiPatchState,
+    CfiPatchStatus, CfiSymbolKind, ChainEntryInfo, ChainOwner, ChainPreview, DLOPEN_RESULT_NOLOAD,
+    DlopenFilterAction, DlopenFilterCallback, GuardFault, GuardFaultStats, GuardSlotStats,
+    HUB_STACK_DEFAULT_HARD_CAP, HUB_STACK_FIXED_DEPTH, HookEventCallback, HookEventKind, HookMode,
+    HookSpec, HookStats, HookStub, HookTaskType, HookedCallback, HubStackStats, InitOptions,
+    LinkerFallbackStats, LogLevel, LogSink, MIN_CLIENT_ABI_VERSION, ModuleIdentity,
+    ModuleInspector, MonitorRefreshCounts, MonitorStatus, MonitorStrategy, PostDlcloseCallback,
+    PostDlopenCallback, PreDlcloseCallback, PreDlopenCallback, PrevFn, ProxyChain, ProxyChainEntry,
+    ProxyScope, RECORD_ITEM_ALL, RECORD_ITEM_CALLER_LIB_NAME, RECORD_ITEM_ERRNO,
+    RECORD_ITEM_INSTANCE, RECORD_ITEM_LIB_NAME, RECORD_ITEM_NAMESPACE, RECORD_ITEM_NEW_ADDR,
+    RECORD_ITEM_OP, RECORD_ITEM_STUB, RECORD_ITEM_SYM_NAME, RECORD_ITEM_TIMESTAMP, RecordCallback,
+    RecordEntry, RecordOp, ResolvedSymbol, RuleLineResult, RuleLoadReport, SafeReadStats,
+    SignalHandlerMode, SingleBindPolicy, TRAMPOLINE_VMA_NAME, TaskInfo, ThreadHookPause,
+    ThreadStateStats, add_dlclose_callback, add_dlopen_callback, add_dlopen_filter, add_ignore,
+    clear, clear_and_drain, clear_log_sink, clear_record_listener, del_dlclose_callback,
+    del_dlopen_callback, del_dlopen_filter, del_ignore, drain_retired_hubs, dump_records,
+    dump_scan_snapshots, dump_state, enable_debug, enable_extended_signal_guard,
+    enable_header_file_fallback, enable_linker_internal_fallback, enable_raw_mprotect,
+    enable_scan_snapshot_capture, enable_sigchain_front_reassert, enable_sigsegv_protection,
+    enable_write_verification, get_callback_dispatch, get_caller_module, get_capabilities,
+    get_cfi_patch_report, get_client_abi_range, get_debug, get_dropped_callback_count,
+    get_event_refresh_count, get_export_address, get_fork_prev_fallback_count,
+    get_guard_fault_stats, get_guard_slot_stats, get_hook_stats, get_hook_tasks, get_hub_backtrace,
+    get_hub_stack_stats, get_ignores, get_import_slot_values, get_linker_fallback_stats,
+    get_log_level, get_mode, get_module_identity, get_module_identity_with_symbol,
+    get_monitor_refresh_counts, get_monitor_status, get_mprotect_count, get_orig_func,
+    get_orig_funcs, get_patch_verify_failed_count, get_pattern_match_limit, get_prev_func,
+    get_protect_restore_failed_count, get_proxy_chain, get_record_capacity,
+    get_record_dropped_count, get_record_entries, get_recordable, get_records, get_return_address,
+    get_safe_read_stats, get_sigchain_reassert_count, get_state_dump, get_thread_state_stats,
+    get_version, get_write_rejected_count, hook_all, hook_all_checked, hook_all_with_callers,
+    hook_all_with_callers_checked, hook_batch, hook_batch_checked, hook_partial,
+    hook_partial_checked, hook_partial_ex, hook_single, hook_single_checked,
+    hook_single_for_threads, hook_single_pattern, hook_single_pattern_checked,
+    hook_single_with_policy, hook_single_with_priority, init, init_with_options, inspect_chain,
+    is_forked_child, is_hook_artifact, is_observation_suppressed, is_slot_guard_enabled,
+    is_thread_hook_enabled, list_exports, list_exports_with, list_imports, list_imports_with,
+    load_rules_from_file, load_rules_from_str, open_module, pause, pop_stack, prev_func_as,
+    proxy_enter, proxy_leave, refresh, refresh_handle, reinit_after_fork, request_refresh_async,
+    request_refresh_async_handle, reset_thread_state_stats, resolve_address, restore_cfi_slowpath,
+    resume, set_callback_dispatch, set_callee_selection, set_client_abi, set_debug,
+    set_hook_event_callback, set_hook_stats_enabled, set_log_level, set_log_sink, set_mode,
+    set_monitor_debounce, set_pattern_match_limit, set_record_capacity, set_record_listener,
+    set_recordable, set_slot_guard, set_task_threads, set_thread_hook_enabled, shutdown,
+    srx_hook_abi_version, srx_hook_suppress_begin, srx_hook_suppress_end, unhook, unhook_symbol,
+    with_observation_suppressed, with_prev_func, with_prev_func_as,
 };
-#[cfg(target_os = "android")]
+#[cfg(any(target_os = "android", test))]
 pub use errno::Errno as SrxHookErrno;
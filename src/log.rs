@@ -1,5 +1,9 @@
-use std::fmt;
-use std::sync::atomic::{AtomicI32, Ordering};
+use crate::api::{LogLevel, LogSink};
+use std::cell::Cell;
+use std::ffi::{c_char, c_void};
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::{PoisonError, RwLock};
 
 pub const ANDROID_LOG_DEBUG: i32 = 3;
 pub const ANDROID_LOG_INFO: i32 = 4;
@@ -8,46 +12,150 @@ pub const ANDROID_LOG_ERROR: i32 = 6;
 
 const LOG_TAG_ANDROID: &[u8] = b"srx_hook\0";
 
+// 单行日志上限，超出部分截断；格式化写入栈上缓冲区，日志路径不分配内存
+const LOG_LINE_MAX: usize = 1024;
+
 static LOG_PRIORITY: AtomicI32 = AtomicI32::new(ANDROID_LOG_WARN);
 
+#[derive(Clone, Copy)]
+struct SinkEntry {
+    sink: LogSink,
+    arg: usize,
+    keep_logcat: bool,
+}
+
+// 用户日志 sink；投递期间持有读锁，替换与清除取写锁，返回后不再有进行中的旧 sink 调用
+// SINK_SET 供未设置 sink 时的快速路径跳过加锁
+static SINK: RwLock<Option<SinkEntry>> = RwLock::new(None);
+static SINK_SET: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    // sink 内部再次产生的日志直接写 logcat，避免递归
+    static IN_SINK: Cell<bool> = const { Cell::new(false) };
+}
+
+#[cfg(target_os = "android")]
 #[link(name = "log")]
 unsafe extern "C" {
-    fn __android_log_write(prio: i32, tag: *const i8, text: *const i8) -> i32;
+    fn __android_log_write(prio: i32, tag: *const c_char, text: *const c_char) -> i32;
+}
+
+// 宿主机测试构建没有 liblog，日志丢弃
+#[cfg(not(target_os = "android"))]
+unsafe fn __android_log_write(_prio: i32, _tag: *const c_char, _text: *const c_char) -> i32 {
+    0
 }
 
-// 设置日志级别，启用时输出 DEBUG 及以上，禁用时仅输出 WARN 及以上
+// debug 开关映射到日志级别：启用时输出 DEBUG 及以上，禁用时仅输出 WARN 及以上
 pub fn set_debug_enabled(enabled: bool) {
-    let priority = if enabled {
-        ANDROID_LOG_DEBUG
+    let level = if enabled {
+        LogLevel::Debug
     } else {
-        ANDROID_LOG_WARN
+        LogLevel::Warn
     };
-    LOG_PRIORITY.store(priority, Ordering::SeqCst);
+    set_level(level);
+}
+
+pub(crate) fn set_level(level: LogLevel) {
+    LOG_PRIORITY.store(level as i32, Ordering::SeqCst);
+}
+
+pub(crate) fn level() -> LogLevel {
+    match LOG_PRIORITY.load(Ordering::Relaxed) {
+        ANDROID_LOG_DEBUG => LogLevel::Debug,
+        ANDROID_LOG_INFO => LogLevel::Info,
+        ANDROID_LOG_WARN => LogLevel::Warn,
+        _ => LogLevel::Error,
+    }
+}
+
+#[inline]
+pub(crate) fn is_enabled(level: LogLevel) -> bool {
+    enabled(level as i32)
+}
+
+// keep_logcat 为 true 时 sink 与 logcat 同时输出
+// 调用方不得处于 sink 内部（api 层已拒绝外部回调中的调用），否则取写锁会自锁
+pub(crate) fn set_sink(sink: LogSink, arg: *mut c_void, keep_logcat: bool) {
+    let mut slot = SINK.write().unwrap_or_else(PoisonError::into_inner);
+    *slot = Some(SinkEntry {
+        sink,
+        arg: arg as usize,
+        keep_logcat,
+    });
+    SINK_SET.store(true, Ordering::Release);
+}
+
+// 等待进行中的 sink 调用结束后返回
+pub(crate) fn clear_sink() {
+    let mut slot = SINK.write().unwrap_or_else(PoisonError::into_inner);
+    *slot = None;
+    SINK_SET.store(false, Ordering::Release);
 }
 
 fn enabled(priority: i32) -> bool {
     LOG_PRIORITY.load(Ordering::Relaxed) <= priority
 }
 
+// 定长缓冲区写入器，保留末尾 1 字节放 nul，内嵌的 nul 替换为空格
+struct LineBuf {
+    buf: [u8; LOG_LINE_MAX],
+    len: usize,
+}
+
+impl Write for LineBuf {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        for &byte in text.as_bytes() {
+            if self.len + 1 >= LOG_LINE_MAX {
+                break;
+            }
+            self.buf[self.len] = if byte == 0 { b' ' } else { byte };
+            self.len += 1;
+        }
+        Ok(())
+    }
+}
+
+// fork 子进程与 sink 内部的日志只写 logcat；子进程继承的锁状态不可信，不加锁
+fn sink_enabled() -> bool {
+    SINK_SET.load(Ordering::Acquire)
+        && !IN_SINK.with(Cell::get)
+        && !crate::runtime::is_forked_child()
+}
+
+// 持读锁投递给 sink，返回是否还需写 logcat；sink 以外部回调身份运行，其中调用 api 会被拒绝
+fn dispatch_to_sink(priority: i32, tag: *const c_char, text: *const c_char) -> bool {
+    let slot = SINK.read().unwrap_or_else(PoisonError::into_inner);
+    let Some(entry) = *slot else {
+        return true;
+    };
+    IN_SINK.with(|in_sink| in_sink.set(true));
+    crate::runtime::run_in_external_callback(|| unsafe {
+        (entry.sink)(priority, tag, text, entry.arg as *mut c_void);
+    });
+    IN_SINK.with(|in_sink| in_sink.set(false));
+    entry.keep_logcat
+}
+
 fn write_log(priority: i32, args: fmt::Arguments) {
     if !enabled(priority) {
         return;
     }
 
-    unsafe {
-        let mut text = format!("{args}").into_bytes();
-        for byte in &mut text {
-            if *byte == 0 {
-                *byte = b' ';
-            }
-        }
-        text.push(0);
+    let mut line = LineBuf {
+        buf: [0; LOG_LINE_MAX],
+        len: 0,
+    };
+    let _ = line.write_fmt(args);
+    line.buf[line.len] = 0;
+    let text = line.buf.as_ptr() as *const c_char;
+    let tag = LOG_TAG_ANDROID.as_ptr() as *const c_char;
 
-        __android_log_write(
-            priority,
-            LOG_TAG_ANDROID.as_ptr() as *const i8,
-            text.as_ptr() as *const i8,
-        );
+    let keep_logcat = !sink_enabled() || dispatch_to_sink(priority, tag, text);
+    if keep_logcat {
+        unsafe {
+            __android_log_write(priority, tag, text);
+        }
     }
 }
 
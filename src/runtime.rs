@@ -1,21 +1,32 @@
 // runtime 模块入口，将内部子模块的功能统一暴露为 crate 级公共接口
 use crate::api::{
-    CallerAllowFilter, HookMode, HookStub, HookedCallback, ModuleIdentity, PostDlopenCallback,
-    PreDlopenCallback,
+    ArtifactKind, CallbackDispatch, CalleeSelection, CallerAllowFilter, CallerAllowFilterEx,
+    Capabilities, CfiPatchEntry, ChainPreview, DlopenFilterCallback, GuardFaultStats,
+    GuardSlotStats, HookEventCallback, HookMode, HookSpec, HookStats, HookStub, HookedCallback,
+    HubStackStats, InitOptions, LinkerFallbackStats, LogLevel, LogSink, ModuleIdentity,
+    MonitorRefreshCounts, MonitorStatus, PostDlcloseCallback, PostDlopenCallback,
+    PreDlcloseCallback, PreDlopenCallback, ProxyChain, RecordCallback, RecordEntry, ResolvedSymbol,
+    RuleLoadReport, SafeReadStats, SingleBindPolicy, TaskInfo, ThreadStateStats,
 };
 use crate::errno::Errno;
 use std::ffi::c_void;
+use std::time::Duration;
 
+mod artifact;
 mod cfi;
 mod callback_ctx;
+mod client_abi;
 mod hub;
+mod inspect;
 mod lifecycle;
 mod record;
 mod refresh;
+mod rule_file;
 mod rules;
 mod state;
 mod thread_state;
 
+pub(crate) use inspect::Inspection;
 pub(crate) use state::MutexPoisonRecover;
 
 pub(crate) fn is_forked_child() -> bool {
@@ -30,8 +41,77 @@ pub(crate) fn in_external_callback() -> bool {
     callback_ctx::is_in_external_callback()
 }
 
-pub(crate) fn init(mode: HookMode, debug: bool) -> Errno {
-    lifecycle::init(mode, debug)
+pub(crate) fn run_in_external_callback<R>(f: impl FnOnce() -> R) -> R {
+    callback_ctx::run_in_external_callback(f)
+}
+
+pub(crate) fn set_client_abi(version: u32, client_addr: usize) -> Errno {
+    client_abi::set_client_abi(version, client_addr)
+}
+
+pub(crate) fn get_client_abi_range() -> Option<(u32, u32)> {
+    client_abi::get_client_abi_range()
+}
+
+pub(crate) fn open_module(rule: &str) -> Result<Inspection, Errno> {
+    inspect::open_module(rule)
+}
+
+pub(crate) fn list_module_symbols(
+    caller_rule: &str,
+    exports: bool,
+) -> Vec<(ModuleIdentity, Vec<String>)> {
+    inspect::list_module_symbols(caller_rule, exports)
+}
+
+pub(crate) fn for_each_module_symbol(
+    caller_rule: &str,
+    exports: bool,
+    callback: &mut dyn FnMut(&ModuleIdentity, &str) -> bool,
+) -> Errno {
+    inspect::for_each_module_symbol(caller_rule, exports, callback)
+}
+
+pub(crate) fn inspect_chain(
+    caller_rule: &str,
+    callee_rule: Option<&str>,
+    sym_name: &str,
+) -> Result<Vec<ChainPreview>, Errno> {
+    inspect::inspect_chain(caller_rule, callee_rule, sym_name)
+}
+
+pub(crate) fn proxy_chains(caller_rule: &str, sym_name: &str) -> Result<Vec<ProxyChain>, Errno> {
+    inspect::proxy_chains(caller_rule, sym_name)
+}
+
+pub(crate) fn import_slot_values(
+    caller_rule: &str,
+    sym_name: &str,
+) -> Result<Vec<(ModuleIdentity, usize, usize)>, Errno> {
+    inspect::import_slot_values(caller_rule, sym_name)
+}
+
+pub(crate) fn export_addresses(
+    callee_rule: &str,
+    sym_name: &str,
+) -> Result<Vec<(ModuleIdentity, usize)>, Errno> {
+    inspect::export_addresses(callee_rule, sym_name)
+}
+
+pub(crate) fn resolve_address(addr: usize) -> Option<ResolvedSymbol> {
+    inspect::resolve_address(addr)
+}
+
+pub(crate) fn is_hook_artifact(addr: usize) -> Option<ArtifactKind> {
+    artifact::classify(addr)
+}
+
+pub(crate) fn init_with_options(opts: InitOptions) -> Errno {
+    lifecycle::init_with_options(opts)
+}
+
+pub(crate) fn reinit_after_fork() -> Errno {
+    lifecycle::reinit_after_fork()
 }
 
 pub(crate) fn hook_single(
@@ -41,7 +121,7 @@ pub(crate) fn hook_single(
     new_func: *mut c_void,
     hooked: Option<HookedCallback>,
     hooked_arg: *mut c_void,
-) -> Option<HookStub> {
+) -> Result<HookStub, Errno> {
     lifecycle::hook_single(
         caller_path_name,
         callee_path_name,
@@ -52,6 +132,88 @@ pub(crate) fn hook_single(
     )
 }
 
+pub(crate) fn hook_single_with_priority(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    priority: i32,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    lifecycle::hook_single_with_priority(
+        caller_path_name,
+        callee_path_name,
+        sym_name,
+        new_func,
+        priority,
+        hooked,
+        hooked_arg,
+    )
+}
+
+pub(crate) fn hook_single_for_threads(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    tids: &[i32],
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    lifecycle::hook_single_for_threads(
+        caller_path_name,
+        callee_path_name,
+        sym_name,
+        new_func,
+        tids,
+        hooked,
+        hooked_arg,
+    )
+}
+
+pub(crate) fn hook_single_with_policy(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    policy: SingleBindPolicy,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    lifecycle::hook_single_with_policy(
+        caller_path_name,
+        callee_path_name,
+        sym_name,
+        new_func,
+        policy,
+        hooked,
+        hooked_arg,
+    )
+}
+
+pub(crate) fn hook_batch(specs: &[HookSpec]) -> Vec<Result<HookStub, Errno>> {
+    lifecycle::hook_batch(specs)
+}
+
+pub(crate) fn hook_single_pattern(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_pattern: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    lifecycle::hook_single_pattern(
+        caller_path_name,
+        callee_path_name,
+        sym_pattern,
+        new_func,
+        hooked,
+        hooked_arg,
+    )
+}
+
 pub(crate) fn hook_partial(
     caller_allow_filter: CallerAllowFilter,
     caller_allow_filter_arg: *mut c_void,
@@ -60,7 +222,7 @@ pub(crate) fn hook_partial(
     new_func: *mut c_void,
     hooked: Option<HookedCallback>,
     hooked_arg: *mut c_void,
-) -> Option<HookStub> {
+) -> Result<HookStub, Errno> {
     lifecycle::hook_partial(
         caller_allow_filter,
         caller_allow_filter_arg,
@@ -72,24 +234,122 @@ pub(crate) fn hook_partial(
     )
 }
 
+pub(crate) fn hook_partial_ex(
+    caller_allow_filter: CallerAllowFilterEx,
+    caller_allow_filter_arg: *mut c_void,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    lifecycle::hook_partial_ex(
+        caller_allow_filter,
+        caller_allow_filter_arg,
+        callee_path_name,
+        sym_name,
+        new_func,
+        hooked,
+        hooked_arg,
+    )
+}
+
 pub(crate) fn hook_all(
     callee_path_name: Option<&str>,
     sym_name: &str,
     new_func: *mut c_void,
     hooked: Option<HookedCallback>,
     hooked_arg: *mut c_void,
-) -> Option<HookStub> {
+) -> Result<HookStub, Errno> {
     lifecycle::hook_all(callee_path_name, sym_name, new_func, hooked, hooked_arg)
 }
 
+pub(crate) fn hook_all_with_callers(
+    callers: &[&str],
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    lifecycle::hook_all_with_callers(
+        callers,
+        callee_path_name,
+        sym_name,
+        new_func,
+        hooked,
+        hooked_arg,
+    )
+}
+
+pub(crate) fn get_hook_tasks() -> Vec<TaskInfo> {
+    lifecycle::get_hook_tasks()
+}
+
 pub(crate) fn unhook(stub: HookStub) -> Errno {
     lifecycle::unhook(stub)
 }
 
+pub(crate) fn unhook_symbol(sym_name: &str, caller_rule: Option<&str>) -> (usize, Errno) {
+    lifecycle::unhook_symbol(sym_name, caller_rule)
+}
+
+pub(crate) fn pause(stub: HookStub) -> Errno {
+    lifecycle::pause(stub)
+}
+
+pub(crate) fn resume(stub: HookStub) -> Errno {
+    lifecycle::resume(stub)
+}
+
+pub(crate) fn set_hook_event_callback(
+    stub: HookStub,
+    callback: Option<HookEventCallback>,
+    arg: *mut c_void,
+) -> Errno {
+    lifecycle::set_hook_event_callback(stub, callback, arg)
+}
+
+pub(crate) fn set_task_threads(stub: HookStub, tids: &[i32]) -> Errno {
+    lifecycle::set_task_threads(stub, tids)
+}
+
+pub(crate) fn set_hook_stats_enabled(stub: HookStub, enabled: bool) -> Errno {
+    lifecycle::set_hook_stats_enabled(stub, enabled)
+}
+
+pub(crate) fn get_hook_stats(stub: HookStub) -> Option<HookStats> {
+    lifecycle::get_hook_stats(stub)
+}
+
+pub(crate) fn get_orig_func(stub: HookStub) -> Option<*mut c_void> {
+    lifecycle::get_orig_func(stub)
+}
+
+pub(crate) fn get_orig_funcs(stub: HookStub) -> Vec<(String, *mut c_void)> {
+    lifecycle::get_orig_funcs(stub)
+}
+
+pub(crate) fn set_callee_selection(stub: HookStub, selection: CalleeSelection) -> Errno {
+    lifecycle::set_callee_selection(stub, selection)
+}
+
 pub(crate) fn add_ignore(caller_path_name: &str) -> Errno {
     lifecycle::add_ignore(caller_path_name)
 }
 
+pub(crate) fn del_ignore(caller_path_name: &str) -> Errno {
+    lifecycle::del_ignore(caller_path_name)
+}
+
+pub(crate) fn get_ignores() -> Vec<String> {
+    lifecycle::get_ignores()
+}
+
+pub(crate) fn load_rules(text: &str, new_func_resolver: fn(&str) -> *mut c_void) -> RuleLoadReport {
+    rule_file::load_rules(text, new_func_resolver)
+}
+
 pub(crate) fn get_module_identity(handle: *mut c_void) -> Option<ModuleIdentity> {
     lifecycle::get_module_identity(handle)
 }
@@ -105,10 +365,46 @@ pub(crate) fn refresh() -> Errno {
     lifecycle::refresh()
 }
 
+pub(crate) fn refresh_handle(handle: *mut c_void) -> Errno {
+    lifecycle::refresh_handle(handle)
+}
+
+pub(crate) fn request_refresh_async() -> Errno {
+    lifecycle::request_monitor_refresh(None)
+}
+
+pub(crate) fn request_refresh_async_handle(handle: *mut c_void) -> Errno {
+    lifecycle::request_monitor_refresh(Some(handle))
+}
+
 pub(crate) fn clear() {
     lifecycle::clear();
 }
 
+pub(crate) fn shutdown(revert_cfi: bool) -> Errno {
+    lifecycle::shutdown(revert_cfi)
+}
+
+pub(crate) fn restore_cfi_slowpath() -> Errno {
+    lifecycle::restore_cfi_slowpath()
+}
+
+pub(crate) fn cfi_patch_report() -> Vec<CfiPatchEntry> {
+    lifecycle::cfi_patch_report()
+}
+
+pub(crate) fn clear_and_drain(timeout: Duration) -> bool {
+    lifecycle::clear_and_drain(timeout)
+}
+
+pub(crate) fn drain_retired_hubs(timeout: Duration) -> bool {
+    lifecycle::drain_retired_hubs(timeout)
+}
+
+pub(crate) fn set_mode(mode: HookMode) -> Errno {
+    lifecycle::set_mode(mode)
+}
+
 pub(crate) fn get_mode() -> HookMode {
     lifecycle::get_mode()
 }
@@ -133,6 +429,46 @@ pub(crate) fn get_records(item_flags: u32) -> Option<String> {
     lifecycle::get_records(item_flags)
 }
 
+pub(crate) fn get_record_entries(item_flags: u32) -> Vec<RecordEntry> {
+    lifecycle::get_record_entries(item_flags)
+}
+
+pub(crate) fn set_log_level(level: LogLevel) {
+    lifecycle::set_log_level(level);
+}
+
+pub(crate) fn get_log_level() -> LogLevel {
+    lifecycle::get_log_level()
+}
+
+pub(crate) fn set_log_sink(sink: LogSink, arg: *mut c_void, keep_logcat: bool) {
+    lifecycle::set_log_sink(sink, arg, keep_logcat);
+}
+
+pub(crate) fn clear_log_sink() {
+    lifecycle::clear_log_sink();
+}
+
+pub(crate) fn set_record_capacity(max_entries: usize) -> Errno {
+    lifecycle::set_record_capacity(max_entries)
+}
+
+pub(crate) fn get_record_capacity() -> usize {
+    lifecycle::get_record_capacity()
+}
+
+pub(crate) fn get_record_dropped_count() -> u64 {
+    lifecycle::get_record_dropped_count()
+}
+
+pub(crate) fn set_record_listener(callback: RecordCallback, arg: *mut c_void) -> Errno {
+    lifecycle::set_record_listener(callback, arg)
+}
+
+pub(crate) fn clear_record_listener() -> Errno {
+    lifecycle::clear_record_listener()
+}
+
 pub(crate) fn dump_records(fd: i32, item_flags: u32) -> Errno {
     lifecycle::dump_records(fd, item_flags)
 }
@@ -141,6 +477,146 @@ pub(crate) fn enable_sigsegv_protection(flag: bool) {
     lifecycle::enable_sigsegv_protection(flag)
 }
 
+pub(crate) fn enable_extended_signal_guard(flag: bool) {
+    lifecycle::enable_extended_signal_guard(flag)
+}
+
+pub(crate) fn enable_sigchain_front_reassert(flag: bool) {
+    lifecycle::enable_sigchain_front_reassert(flag)
+}
+
+pub(crate) fn sigchain_reassert_count() -> u64 {
+    lifecycle::sigchain_reassert_count()
+}
+
+pub(crate) fn fork_prev_fallback_count() -> u64 {
+    lifecycle::fork_prev_fallback_count()
+}
+
+pub(crate) fn hub_stack_stats() -> HubStackStats {
+    lifecycle::hub_stack_stats()
+}
+
+pub(crate) fn thread_state_stats() -> ThreadStateStats {
+    lifecycle::thread_state_stats()
+}
+
+pub(crate) fn reset_thread_state_stats() {
+    lifecycle::reset_thread_state_stats()
+}
+
+pub(crate) fn enable_header_file_fallback(flag: bool) {
+    lifecycle::enable_header_file_fallback(flag)
+}
+
+pub(crate) fn enable_write_verification(flag: bool) {
+    lifecycle::enable_write_verification(flag)
+}
+
+pub(crate) fn enable_raw_mprotect(flag: bool) {
+    lifecycle::enable_raw_mprotect(flag)
+}
+
+pub(crate) fn set_slot_guard(flag: bool) {
+    lifecycle::set_slot_guard(flag)
+}
+
+pub(crate) fn is_slot_guard_enabled() -> bool {
+    lifecycle::is_slot_guard_enabled()
+}
+
+pub(crate) fn set_pattern_match_limit(limit: usize) -> Errno {
+    lifecycle::set_pattern_match_limit(limit)
+}
+
+pub(crate) fn get_pattern_match_limit() -> usize {
+    lifecycle::get_pattern_match_limit()
+}
+
+pub(crate) fn enable_linker_internal_fallback(flag: bool) {
+    lifecycle::enable_linker_internal_fallback(flag)
+}
+
+pub(crate) fn linker_fallback_stats() -> LinkerFallbackStats {
+    lifecycle::linker_fallback_stats()
+}
+
+pub(crate) fn write_rejected_page_count() -> u64 {
+    lifecycle::write_rejected_page_count()
+}
+
+pub(crate) fn patch_verify_failure_count() -> u64 {
+    lifecycle::patch_verify_failure_count()
+}
+
+pub(crate) fn mprotect_count() -> u64 {
+    lifecycle::mprotect_count()
+}
+
+pub(crate) fn protect_restore_failure_count() -> u64 {
+    lifecycle::protect_restore_failure_count()
+}
+
+pub(crate) fn safe_read_stats() -> SafeReadStats {
+    lifecycle::safe_read_stats()
+}
+
+pub(crate) fn guard_slot_stats() -> GuardSlotStats {
+    lifecycle::guard_slot_stats()
+}
+
+pub(crate) fn guard_fault_stats() -> GuardFaultStats {
+    lifecycle::guard_fault_stats()
+}
+
+pub(crate) fn enable_scan_snapshot_capture(flag: bool) {
+    lifecycle::enable_scan_snapshot_capture(flag)
+}
+
+pub(crate) fn dump_scan_snapshots(fd: i32) -> Errno {
+    lifecycle::dump_scan_snapshots(fd)
+}
+
+pub(crate) fn dump_state(fd: i32) -> Errno {
+    lifecycle::dump_state(fd)
+}
+
+pub(crate) fn state_dump_text() -> String {
+    lifecycle::state_dump_text()
+}
+
+pub(crate) fn set_callback_dispatch(mode: CallbackDispatch) {
+    lifecycle::set_callback_dispatch(mode)
+}
+
+pub(crate) fn set_monitor_debounce(window: Duration) {
+    lifecycle::set_monitor_debounce(window)
+}
+
+pub(crate) fn get_callback_dispatch() -> CallbackDispatch {
+    lifecycle::get_callback_dispatch()
+}
+
+pub(crate) fn dropped_callback_count() -> u64 {
+    lifecycle::dropped_callback_count()
+}
+
+pub(crate) fn event_refresh_count() -> u64 {
+    lifecycle::event_refresh_count()
+}
+
+pub(crate) fn monitor_status() -> MonitorStatus {
+    lifecycle::monitor_status()
+}
+
+pub(crate) fn monitor_refresh_counts() -> MonitorRefreshCounts {
+    lifecycle::monitor_refresh_counts()
+}
+
+pub(crate) fn capabilities() -> Option<Capabilities> {
+    lifecycle::capabilities()
+}
+
 pub(crate) fn get_prev_func(func: *mut c_void) -> *mut c_void {
     lifecycle::get_prev_func(func)
 }
@@ -156,6 +632,14 @@ pub(crate) fn get_return_address() -> *mut c_void {
     lifecycle::get_return_address()
 }
 
+pub(crate) fn get_hub_backtrace(buf: &mut [usize]) -> usize {
+    lifecycle::get_hub_backtrace(buf)
+}
+
+pub(crate) fn get_caller_module() -> Option<ModuleIdentity> {
+    lifecycle::get_caller_module()
+}
+
 pub(crate) fn pop_stack(return_address: *mut c_void) {
     lifecycle::pop_stack(return_address)
 }
@@ -168,6 +652,30 @@ pub(crate) fn proxy_leave(func: *mut c_void) {
     lifecycle::proxy_leave(func)
 }
 
+pub(crate) fn proxy_scope_leave(func: *mut c_void, entered: bool) {
+    lifecycle::proxy_scope_leave(func, entered)
+}
+
+pub(crate) fn suppress_begin() {
+    lifecycle::suppress_begin()
+}
+
+pub(crate) fn suppress_end() {
+    lifecycle::suppress_end()
+}
+
+pub(crate) fn is_suppressed() -> bool {
+    lifecycle::is_suppressed()
+}
+
+pub(crate) fn set_thread_hook_enabled(enabled: bool) -> bool {
+    lifecycle::set_thread_hook_enabled(enabled)
+}
+
+pub(crate) fn is_thread_hook_enabled() -> bool {
+    lifecycle::is_thread_hook_enabled()
+}
+
 pub(crate) fn add_dlopen_callback(
     pre: Option<PreDlopenCallback>,
     post: Option<PostDlopenCallback>,
@@ -184,14 +692,39 @@ pub(crate) fn del_dlopen_callback(
     lifecycle::del_dlopen_callback(pre, post, data)
 }
 
+pub(crate) fn add_dlopen_filter(filter: DlopenFilterCallback, data: *mut c_void) -> Errno {
+    lifecycle::add_dlopen_filter(filter, data)
+}
+
+pub(crate) fn del_dlopen_filter(filter: DlopenFilterCallback, data: *mut c_void) -> Errno {
+    lifecycle::del_dlopen_filter(filter, data)
+}
+
+pub(crate) fn add_dlclose_callback(
+    pre: Option<PreDlcloseCallback>,
+    post: Option<PostDlcloseCallback>,
+    data: *mut c_void,
+) -> Errno {
+    lifecycle::add_dlclose_callback(pre, post, data)
+}
+
+pub(crate) fn del_dlclose_callback(
+    pre: Option<PreDlcloseCallback>,
+    post: Option<PostDlcloseCallback>,
+    data: *mut c_void,
+) -> Errno {
+    lifecycle::del_dlclose_callback(pre, post, data)
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
-        clear, hook_single, init, proxy_enter, proxy_leave,
+        clear, get_hook_tasks, hook_all, hook_single, is_suppressed, is_thread_hook_enabled,
+        proxy_enter, proxy_leave,
         rules::{module_match, path_match},
-        set_debug,
+        set_debug, set_thread_hook_enabled, suppress_begin, suppress_end, unhook, unhook_symbol,
     };
-    use crate::api::HookMode;
+    use crate::api::{HookMode, HookTaskType, init};
     use crate::errno::Errno;
     use std::ffi::c_void;
 
@@ -217,6 +750,7 @@ mod tests {
     fn module_match_checks_base_when_rule_has_suffix() {
         assert!(module_match(
             "/data/app/libfoo.so",
+            None,
             0x1A2B,
             0,
             0,
@@ -224,6 +758,7 @@ mod tests {
         ));
         assert!(!module_match(
             "/data/app/libfoo.so",
+            None,
             0x1A2B,
             0,
             0,
@@ -235,6 +770,7 @@ mod tests {
     fn module_match_falls_back_to_path_when_suffix_is_invalid() {
         assert!(!module_match(
             "/data/app/libfoo.so",
+            None,
             0x1A2B,
             0,
             0,
@@ -242,6 +778,7 @@ mod tests {
         ));
         assert!(!module_match(
             "/data/app/libfoo.so",
+            None,
             0x1A2B,
             0,
             0,
@@ -249,16 +786,49 @@ mod tests {
         ));
     }
 
+    // 同一栈深度的重复 enter 视为上一帧已返回而清理，环路必然发生在更深的栈帧
+    #[inline(never)]
+    fn nested_proxy_enter(func: *mut c_void) -> bool {
+        std::hint::black_box(proxy_enter(std::hint::black_box(func)))
+    }
+
     #[test]
     fn proxy_stack_rejects_cycle() {
         let func = 0x1234usize as *mut c_void;
         assert!(proxy_enter(func));
-        assert!(!proxy_enter(func));
+        assert!(!nested_proxy_enter(func));
         proxy_leave(func);
         assert!(proxy_enter(func));
         proxy_leave(func);
     }
 
+    #[test]
+    fn observation_suppression_nests() {
+        assert!(!is_suppressed());
+        suppress_begin();
+        suppress_begin();
+        suppress_end();
+        assert!(is_suppressed());
+        suppress_end();
+        assert!(!is_suppressed());
+        // 多余的 end 不会使深度下溢
+        suppress_end();
+        suppress_begin();
+        assert!(is_suppressed());
+        suppress_end();
+    }
+
+    #[test]
+    fn thread_hook_toggle_is_per_thread() {
+        assert!(is_thread_hook_enabled());
+        assert!(set_thread_hook_enabled(false));
+        assert!(!is_thread_hook_enabled());
+        // 新线程默认启用
+        assert!(std::thread::spawn(is_thread_hook_enabled).join().unwrap());
+        assert!(!set_thread_hook_enabled(true));
+        assert!(is_thread_hook_enabled());
+    }
+
     #[test]
     fn duplicated_proxy_address_is_rejected() {
         unsafe extern "C" fn dummy_proxy(_s: *const i8) -> i32 {
@@ -277,7 +847,7 @@ mod tests {
             None,
             std::ptr::null_mut(),
         );
-        assert!(first.is_some());
+        assert!(first.is_ok());
 
         let second = hook_single(
             "libdummy.so",
@@ -287,7 +857,109 @@ mod tests {
             None,
             std::ptr::null_mut(),
         );
-        assert!(second.is_none());
+        assert_eq!(second, Err(Errno::RepeatedFunc));
+
+        // unhook 后同样的注册重新被接受
+        assert_eq!(unhook(first.unwrap()), Errno::Ok);
+        let third = hook_single(
+            "libdummy.so",
+            None,
+            "puts",
+            dummy_proxy as *mut c_void,
+            None,
+            std::ptr::null_mut(),
+        );
+        assert!(third.is_ok());
+        clear();
+    }
+
+    #[test]
+    fn hook_tasks_are_listed_in_registration_order() {
+        unsafe extern "C" fn first_proxy(_s: *const i8) -> i32 {
+            0
+        }
+        unsafe extern "C" fn second_proxy(_s: *const i8) -> i32 {
+            0
+        }
+
+        clear();
+        assert_eq!(init(HookMode::Manual, false), Errno::Ok);
+        let first = hook_single(
+            "libdummy.so",
+            Some("libc.so"),
+            "puts",
+            first_proxy as *mut c_void,
+            None,
+            std::ptr::null_mut(),
+        )
+        .unwrap();
+        let second = hook_all(
+            None,
+            "strlen",
+            second_proxy as *mut c_void,
+            None,
+            std::ptr::null_mut(),
+        )
+        .unwrap();
+
+        let tasks = get_hook_tasks();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].stub, first);
+        assert_eq!(tasks[0].task_type, HookTaskType::Single);
+        assert_eq!(tasks[0].caller_path_name.as_deref(), Some("libdummy.so"));
+        assert_eq!(tasks[0].callee_path_name.as_deref(), Some("libc.so"));
+        assert_eq!(tasks[1].stub, second);
+        assert_eq!(tasks[1].task_type, HookTaskType::All);
+        assert_eq!(tasks[1].sym_name, "strlen");
+        clear();
+        assert!(get_hook_tasks().is_empty());
+    }
+
+    #[test]
+    fn unhook_symbol_filters_by_symbol_and_caller() {
+        unsafe extern "C" fn first_proxy(_s: *const i8) -> i32 {
+            0
+        }
+        unsafe extern "C" fn second_proxy(_s: *const i8) -> i32 {
+            0
+        }
+        unsafe extern "C" fn third_proxy(_s: *const i8) -> i32 {
+            0
+        }
+
+        clear();
+        assert_eq!(init(HookMode::Manual, false), Errno::Ok);
+        let null = std::ptr::null_mut();
+        let _ = hook_single(
+            "libdummy.so",
+            None,
+            "puts",
+            first_proxy as *mut c_void,
+            None,
+            null,
+        )
+        .unwrap();
+        let second = hook_single(
+            "libother.so",
+            None,
+            "puts",
+            second_proxy as *mut c_void,
+            None,
+            null,
+        )
+        .unwrap();
+        let third = hook_all(None, "strlen", third_proxy as *mut c_void, None, null).unwrap();
+
+        assert_eq!(unhook_symbol("", None), (0, Errno::InvalidArg));
+        assert_eq!(unhook_symbol("puts", Some("libdummy.so")), (1, Errno::Ok));
+        // hook_all 任务没有 caller 规则，按 caller 过滤时不受影响
+        assert_eq!(unhook_symbol("strlen", Some("libdummy.so")), (0, Errno::Ok));
+        let stubs: Vec<_> = get_hook_tasks().iter().map(|task| task.stub).collect();
+        assert_eq!(stubs, vec![second, third]);
+
+        assert_eq!(unhook_symbol("puts", None), (1, Errno::Ok));
+        assert_eq!(unhook(second), Errno::InvalidArg);
+        assert_eq!(get_hook_tasks().len(), 1);
         clear();
     }
 }
@@ -0,0 +1,260 @@
+// hook 框架自身代码地址的分类：trampoline 页池、内部 proxy 函数、自身模块代码段
+// 查询只读取已发布的快照，不加锁；旧快照按延迟期退役后释放，与 hub 的延迟回收一致
+use crate::api::ArtifactKind;
+use crate::runtime::state::MutexPoisonRecover;
+use once_cell::sync::Lazy;
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+mod fde;
+
+// 退役快照的保留时长，查询仅持有快照指针数微秒，远小于该值
+const SNAPSHOT_RETIRE_SEC: u64 = 5;
+const PF_X: u32 = 1;
+const PT_GNU_EH_FRAME: u32 = 0x6474_e550;
+
+#[derive(Clone, Default)]
+struct Snapshot {
+    // 按起始地址排序的 trampoline 页
+    trampo_pages: Vec<usize>,
+    trampo_page_size: usize,
+    // 按起始地址排序的内部 proxy 函数体 [start, end)，栈上的返回地址落在函数体内
+    proxies: Vec<(usize, usize)>,
+    // 自身模块可执行段 [start, end)
+    own_code: Vec<(usize, usize)>,
+}
+
+impl Snapshot {
+    fn classify(&self, addr: usize) -> Option<ArtifactKind> {
+        let idx = self.trampo_pages.partition_point(|&page| page <= addr);
+        if idx > 0 && addr < self.trampo_pages[idx - 1] + self.trampo_page_size {
+            return Some(ArtifactKind::Trampoline);
+        }
+        let idx = self.proxies.partition_point(|&(start, _)| start <= addr);
+        if idx > 0 && addr < self.proxies[idx - 1].1 {
+            return Some(ArtifactKind::InternalProxy);
+        }
+        self.own_code
+            .iter()
+            .any(|&(start, end)| addr >= start && addr < end)
+            .then_some(ArtifactKind::OwnModuleCode)
+    }
+}
+
+struct Publisher {
+    current: Snapshot,
+    retired: Vec<(usize, u64)>,
+}
+
+static PUBLISHED: AtomicPtr<Snapshot> = AtomicPtr::new(ptr::null_mut());
+static PUBLISHER: Lazy<Mutex<Publisher>> = Lazy::new(|| {
+    Mutex::new(Publisher {
+        current: Snapshot::default(),
+        retired: Vec::new(),
+    })
+});
+
+fn now_sec() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+fn update<F: FnOnce(&mut Snapshot)>(f: F) {
+    let mut publisher = PUBLISHER.lock_or_poison();
+    f(&mut publisher.current);
+    let next = Box::into_raw(Box::new(publisher.current.clone()));
+    let old = PUBLISHED.swap(next, Ordering::AcqRel);
+    let now = now_sec();
+    if !old.is_null() {
+        publisher.retired.push((old as usize, now));
+    }
+    publisher.retired.retain(|&(raw, retired_at)| {
+        if now.saturating_sub(retired_at) <= SNAPSHOT_RETIRE_SEC {
+            return true;
+        }
+        unsafe {
+            drop(Box::from_raw(raw as *mut Snapshot));
+        }
+        false
+    });
+}
+
+// trampoline 页池增减页后发布当前全部页
+pub(super) fn publish_trampo_pages(mut pages: Vec<usize>, page_size: usize) {
+    pages.sort_unstable();
+    update(|snapshot| {
+        snapshot.trampo_pages = pages;
+        snapshot.trampo_page_size = page_size;
+    });
+}
+
+// 初始化时登记内部 proxy 并解析自身模块的可执行段
+pub(super) fn publish_static(proxies: Vec<usize>) {
+    let own = own_module();
+    let proxies = proxy_ranges(&own, proxies);
+    update(|snapshot| {
+        snapshot.proxies = proxies;
+        snapshot.own_code = own.code;
+    });
+}
+
+// 按 FDE 给出的函数体范围登记 proxy，查不到 FDE 时只登记入口地址
+fn proxy_ranges(own: &OwnModule, mut entries: Vec<usize>) -> Vec<(usize, usize)> {
+    entries.sort_unstable();
+    entries.dedup();
+    entries
+        .into_iter()
+        .map(|entry| {
+            own.eh_frame_hdr
+                .and_then(|hdr| unsafe { fde::function_range(hdr, entry) })
+                .filter(|&(start, _)| start == entry)
+                .unwrap_or((entry, entry + 1))
+        })
+        .collect()
+}
+
+// clear() 后运行时回到未初始化状态，内部 proxy 与自身代码段在下次 init 时重新登记
+pub(super) fn reset_static() {
+    update(|snapshot| {
+        snapshot.proxies.clear();
+        snapshot.own_code.clear();
+    });
+}
+
+pub(super) fn classify(addr: usize) -> Option<ArtifactKind> {
+    if addr == 0 {
+        return None;
+    }
+    let snapshot = PUBLISHED.load(Ordering::Acquire);
+    if snapshot.is_null() {
+        return None;
+    }
+    unsafe { &*snapshot }.classify(addr)
+}
+
+// 不经过全局快照的分类，供并行执行的单元测试使用
+#[cfg(test)]
+pub(super) fn classify_static(proxies: Vec<usize>, addr: usize) -> Option<ArtifactKind> {
+    let own = own_module();
+    let snapshot = Snapshot {
+        proxies: proxy_ranges(&own, proxies),
+        own_code: own.code,
+        ..Snapshot::default()
+    };
+    snapshot.classify(addr)
+}
+
+#[derive(Default)]
+struct OwnModule {
+    // PF_X 的 LOAD 段 [start, end)
+    code: Vec<(usize, usize)>,
+    // PT_GNU_EH_FRAME 的运行时地址
+    eh_frame_hdr: Option<usize>,
+}
+
+// 以本函数地址定位自身模块，收集其 PF_X 的 LOAD 段与 .eh_frame_hdr
+fn own_module() -> OwnModule {
+    struct Search {
+        probe: usize,
+        module: OwnModule,
+    }
+
+    unsafe extern "C" fn iterate_cb(
+        info: *mut libc::dl_phdr_info,
+        _size: usize,
+        data: *mut c_void,
+    ) -> libc::c_int {
+        let search = unsafe { &mut *(data as *mut Search) };
+        let info = unsafe { &*info };
+        if info.dlpi_phdr.is_null() {
+            return 0;
+        }
+        let phdrs = unsafe { std::slice::from_raw_parts(info.dlpi_phdr, info.dlpi_phnum as usize) };
+        let ranges: Vec<(usize, usize)> = phdrs
+            .iter()
+            .filter(|ph| ph.p_type == libc::PT_LOAD && ph.p_flags & PF_X != 0)
+            .map(|ph| {
+                let start = info.dlpi_addr as usize + ph.p_vaddr as usize;
+                (start, start + ph.p_memsz as usize)
+            })
+            .collect();
+        if ranges
+            .iter()
+            .any(|&(start, end)| search.probe >= start && search.probe < end)
+        {
+            search.module = OwnModule {
+                code: ranges,
+                eh_frame_hdr: phdrs
+                    .iter()
+                    .find(|ph| ph.p_type == PT_GNU_EH_FRAME)
+                    .map(|ph| info.dlpi_addr as usize + ph.p_vaddr as usize),
+            };
+            return 1;
+        }
+        0
+    }
+
+    let mut search = Search {
+        probe: own_module as *const () as usize,
+        module: OwnModule::default(),
+    };
+    unsafe {
+        libc::dl_iterate_phdr(Some(iterate_cb), &mut search as *mut Search as *mut c_void);
+    }
+    search.module
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArtifactKind, Snapshot, fde, own_module};
+
+    #[test]
+    fn classifies_each_range() {
+        let snapshot = Snapshot {
+            trampo_pages: vec![0x10000, 0x30000],
+            trampo_page_size: 0x1000,
+            proxies: vec![(0x5010, 0x5040), (0x5080, 0x50c0)],
+            own_code: vec![(0x5000, 0x6000)],
+        };
+        assert_eq!(snapshot.classify(0x10000), Some(ArtifactKind::Trampoline));
+        assert_eq!(snapshot.classify(0x30fff), Some(ArtifactKind::Trampoline));
+        assert_eq!(snapshot.classify(0x11000), None);
+        assert_eq!(snapshot.classify(0x5080), Some(ArtifactKind::InternalProxy));
+        assert_eq!(snapshot.classify(0x50bc), Some(ArtifactKind::InternalProxy));
+        assert_eq!(snapshot.classify(0x50c0), Some(ArtifactKind::OwnModuleCode));
+        assert_eq!(snapshot.classify(0x5044), Some(ArtifactKind::OwnModuleCode));
+        assert_eq!(snapshot.classify(0x6000), None);
+    }
+
+    #[test]
+    fn own_code_contains_this_module() {
+        let probe = classifies_each_range as *const () as usize;
+        assert!(
+            own_module()
+                .code
+                .iter()
+                .any(|&(start, end)| probe >= start && probe < end)
+        );
+    }
+
+    // 自身模块的 FDE 覆盖函数入口，函数体范围从入口开始
+    #[test]
+    fn fde_range_starts_at_function_entry() {
+        let entry = classifies_each_range as *const () as usize;
+        let hdr = own_module()
+            .eh_frame_hdr
+            .expect("own module has .eh_frame_hdr");
+        let (start, end) = unsafe { fde::function_range(hdr, entry) }.expect("fde for entry");
+        assert_eq!(start, entry);
+        assert!(end > start + 4);
+        assert_eq!(
+            unsafe { fde::function_range(hdr, end - 1) }.map(|r| r.0),
+            Some(start)
+        );
+    }
+}
@@ -0,0 +1,207 @@
+// 经自身模块的 .eh_frame_hdr 查找覆盖某地址的 FDE，以 [pc_begin, pc_begin + pc_range) 作为函数体范围
+// 只支持 lld / ld 生成的常见指针编码，无法解析时返回 None
+use std::ptr;
+
+const DW_EH_PE_OMIT: u8 = 0xff;
+const DW_EH_PE_ABSPTR: u8 = 0x00;
+const DW_EH_PE_ULEB128: u8 = 0x01;
+const DW_EH_PE_UDATA2: u8 = 0x02;
+const DW_EH_PE_UDATA4: u8 = 0x03;
+const DW_EH_PE_UDATA8: u8 = 0x04;
+const DW_EH_PE_SLEB128: u8 = 0x09;
+const DW_EH_PE_SDATA2: u8 = 0x0a;
+const DW_EH_PE_SDATA4: u8 = 0x0b;
+const DW_EH_PE_SDATA8: u8 = 0x0c;
+const DW_EH_PE_PCREL: u8 = 0x10;
+const DW_EH_PE_DATAREL: u8 = 0x30;
+const DW_EH_PE_INDIRECT: u8 = 0x80;
+
+// CIE 增强串的长度上限，超出视为无法解析
+const AUGMENTATION_LIMIT: usize = 8;
+
+struct Reader {
+    pos: usize,
+}
+
+impl Reader {
+    unsafe fn read<T: Copy>(&mut self) -> T {
+        let value = unsafe { ptr::read_unaligned(self.pos as *const T) };
+        self.pos += size_of::<T>();
+        value
+    }
+
+    unsafe fn uleb(&mut self) -> u64 {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte: u8 = unsafe { self.read() };
+            if shift < 64 {
+                value |= u64::from(byte & 0x7f) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return value;
+            }
+        }
+    }
+
+    unsafe fn sleb(&mut self) -> i64 {
+        let mut value = 0i64;
+        let mut shift = 0;
+        loop {
+            let byte: u8 = unsafe { self.read() };
+            if shift < 64 {
+                value |= i64::from(byte & 0x7f) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    value |= -1i64 << shift;
+                }
+                return value;
+            }
+        }
+    }
+
+    // 按 DW_EH_PE 编码读取一个指针，data_base 为 datarel 的基准
+    unsafe fn encoded(&mut self, enc: u8, data_base: usize) -> Option<usize> {
+        if enc == DW_EH_PE_OMIT || enc & DW_EH_PE_INDIRECT != 0 {
+            return None;
+        }
+        let field = self.pos;
+        let value = unsafe {
+            match enc & 0x0f {
+                DW_EH_PE_ABSPTR => self.read::<usize>(),
+                DW_EH_PE_ULEB128 => self.uleb() as usize,
+                DW_EH_PE_UDATA2 => usize::from(self.read::<u16>()),
+                DW_EH_PE_UDATA4 => self.read::<u32>() as usize,
+                DW_EH_PE_UDATA8 => self.read::<u64>() as usize,
+                DW_EH_PE_SLEB128 => self.sleb() as usize,
+                DW_EH_PE_SDATA2 => self.read::<i16>() as usize,
+                DW_EH_PE_SDATA4 => self.read::<i32>() as usize,
+                DW_EH_PE_SDATA8 => self.read::<i64>() as usize,
+                _ => return None,
+            }
+        };
+        let base = match enc & 0x70 {
+            0 => 0,
+            DW_EH_PE_PCREL => field,
+            DW_EH_PE_DATAREL => data_base,
+            _ => return None,
+        };
+        Some(base.wrapping_add(value))
+    }
+}
+
+// eh_frame_hdr 为本模块已映射的段，addr 不在任何 FDE 内时返回 None
+pub(super) unsafe fn function_range(eh_frame_hdr: usize, addr: usize) -> Option<(usize, usize)> {
+    let mut reader = Reader { pos: eh_frame_hdr };
+    let (version, ptr_enc, count_enc, table_enc) = unsafe {
+        (
+            reader.read::<u8>(),
+            reader.read::<u8>(),
+            reader.read::<u8>(),
+            reader.read::<u8>(),
+        )
+    };
+    if version != 1 || table_enc != DW_EH_PE_DATAREL | DW_EH_PE_SDATA4 {
+        return None;
+    }
+    unsafe { reader.encoded(ptr_enc, eh_frame_hdr) }?;
+    let count = unsafe { reader.encoded(count_enc, eh_frame_hdr) }?;
+
+    // 二分表为按 initial_location 升序的定长 (initial_location, fde) 对
+    let table = reader.pos;
+    let entry = |idx: usize| {
+        let at = table + idx * 8;
+        let read = |pos: usize| unsafe { ptr::read_unaligned(pos as *const i32) } as usize;
+        (
+            eh_frame_hdr.wrapping_add(read(at)),
+            eh_frame_hdr.wrapping_add(read(at + 4)),
+        )
+    };
+    let (mut low, mut high) = (0, count);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if entry(mid).0 <= addr {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    if low == 0 {
+        return None;
+    }
+    let (start, len) = unsafe { fde_range(entry(low - 1).1) }?;
+    let end = start.checked_add(len)?;
+    (addr >= start && addr < end).then_some((start, end))
+}
+
+unsafe fn fde_range(fde: usize) -> Option<(usize, usize)> {
+    let mut reader = Reader { pos: fde };
+    let length: u32 = unsafe { reader.read() };
+    if length == 0 || length == u32::MAX {
+        return None;
+    }
+    let cie_field = reader.pos;
+    let cie_offset: u32 = unsafe { reader.read() };
+    // CIE 自身的该字段为 0
+    if cie_offset == 0 {
+        return None;
+    }
+    let enc = unsafe { cie_fde_encoding(cie_field.checked_sub(cie_offset as usize)?) }?;
+    let start = unsafe { reader.encoded(enc, 0) }?;
+    let len = unsafe { reader.encoded(enc & 0x0f, 0) }?;
+    Some((start, len))
+}
+
+// 取 CIE 增强数据中 'R' 声明的 FDE 指针编码，没有 'z' 增强时为 absptr
+unsafe fn cie_fde_encoding(cie: usize) -> Option<u8> {
+    let mut reader = Reader { pos: cie };
+    let length: u32 = unsafe { reader.read() };
+    let cie_id: u32 = unsafe { reader.read() };
+    if length == 0 || length == u32::MAX || cie_id != 0 {
+        return None;
+    }
+    let version: u8 = unsafe { reader.read() };
+    let mut augmentation = [0u8; AUGMENTATION_LIMIT];
+    let mut aug_len = 0;
+    loop {
+        let byte: u8 = unsafe { reader.read() };
+        if byte == 0 {
+            break;
+        }
+        *augmentation.get_mut(aug_len)? = byte;
+        aug_len += 1;
+    }
+    let augmentation = &augmentation[..aug_len];
+    if augmentation.first() != Some(&b'z') {
+        return Some(DW_EH_PE_ABSPTR);
+    }
+    unsafe {
+        reader.uleb();
+        reader.sleb();
+        if version == 1 {
+            reader.read::<u8>();
+        } else {
+            reader.uleb();
+        }
+        reader.uleb();
+    }
+    for &tag in &augmentation[1..] {
+        match tag {
+            b'R' => return Some(unsafe { reader.read() }),
+            // personality 指针可能为 indirect，只需跳过其字段
+            b'P' => {
+                let enc: u8 = unsafe { reader.read() };
+                unsafe { reader.encoded(enc & !DW_EH_PE_INDIRECT, 0) }?;
+            }
+            b'L' => {
+                unsafe { reader.read::<u8>() };
+            }
+            b'S' | b'B' => {}
+            _ => return None,
+        }
+    }
+    Some(DW_EH_PE_ABSPTR)
+}
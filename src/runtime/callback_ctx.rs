@@ -4,12 +4,35 @@ use std::cell::Cell;
 thread_local! {
     #[allow(clippy::missing_const_for_thread_local)]
     static EXTERNAL_CALLBACK_DEPTH: Cell<u32> = Cell::new(0);
+    #[allow(clippy::missing_const_for_thread_local)]
+    static DLOPEN_FILTER_ACTIVE: Cell<bool> = Cell::new(false);
 }
 
 pub(super) fn is_in_external_callback() -> bool {
     EXTERNAL_CALLBACK_DEPTH.with(|depth| depth.get() > 0)
 }
 
+pub(super) fn is_in_dlopen_filter() -> bool {
+    DLOPEN_FILTER_ACTIVE.with(Cell::get)
+}
+
+// 以外部回调身份执行 dlopen 过滤器，期间本线程发起的 dlopen 不再进入过滤链
+pub(super) fn run_in_dlopen_filter<R, F>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    struct FilterGuard(bool);
+
+    impl Drop for FilterGuard {
+        fn drop(&mut self) {
+            DLOPEN_FILTER_ACTIVE.with(|active| active.set(self.0));
+        }
+    }
+
+    let _guard = FilterGuard(DLOPEN_FILTER_ACTIVE.with(|active| active.replace(true)));
+    run_in_external_callback(f)
+}
+
 // 在 RAII guard 保护下执行外部回调，确保 panic 时深度也能正确回退
 pub(super) fn run_in_external_callback<R, F>(f: F) -> R
 where
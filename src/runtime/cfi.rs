@@ -1,53 +1,181 @@
 // CFI (Control Flow Integrity) 绕过模块，负责禁用 Android 的 CFI slowpath 检查
 // 仅 aarch64 架构有实际实现，其他架构为空操作
+use crate::api::{CfiPatchEntry, CfiPatchScope, CfiPatchState};
 use crate::elf;
 use crate::errno::Errno;
+use crate::log;
+use std::collections::BTreeSet;
 use std::ffi::{CStr, c_char};
-use std::sync::OnceLock;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU8, Ordering};
 
-use super::state::ModuleInfo;
+use super::state::{ModuleInfo, MutexPoisonRecover};
 
 // Android O (API 26) 起引入 CFI，低于此版本无需处理
 const ANDROID_API_LEVEL_CFI_DISABLE: i32 = 26;
 const SYSTEM_PROP_VALUE_MAX: usize = 92;
 const RTLD_NEXT_FALLBACK: *mut libc::c_void = (-1isize) as *mut libc::c_void;
 
-// 全局初始化一次的 CFI 禁用结果缓存
-static CFI_DISABLE_STATUS: OnceLock<Errno> = OnceLock::new();
+// CFI 禁用结果缓存，revert 后清空，下次 init 重新 patch
+static CFI_DISABLE_STATUS: Mutex<Option<Errno>> = Mutex::new(None);
+// 当前生效的补丁范围（CfiPatchScope 的序号），由 init_with_options 配置，restore_slowpath 后降为 ModulesWithHooks
+static CFI_PATCH_SCOPE: AtomicU8 = AtomicU8::new(CfiPatchScope::Global as u8);
 
+#[cfg(target_os = "android")]
 unsafe extern "C" {
     fn __system_property_get(name: *const c_char, value: *mut c_char) -> libc::c_int;
 }
 
+// 宿主机测试构建没有系统属性，按未读到处理
+#[cfg(not(target_os = "android"))]
+unsafe fn __system_property_get(_name: *const c_char, _value: *mut c_char) -> libc::c_int {
+    0
+}
+
 #[cfg(target_arch = "aarch64")]
 mod module_hook;
 #[cfg(target_arch = "aarch64")]
 mod slowpath;
 
-pub(super) fn disable_slowpath() -> Errno {
-    *CFI_DISABLE_STATUS.get_or_init(disable_slowpath_impl)
+pub(super) fn set_patch_scope(scope: CfiPatchScope) {
+    CFI_PATCH_SCOPE.store(scope as u8, Ordering::Release);
+}
+
+fn patch_scope() -> CfiPatchScope {
+    match CFI_PATCH_SCOPE.load(Ordering::Acquire) {
+        0 => CfiPatchScope::Global,
+        1 => CfiPatchScope::ModulesWithHooks,
+        _ => CfiPatchScope::Disabled,
+    }
 }
 
-pub(super) fn refresh_slowpath_patch() -> Errno {
-    refresh_slowpath_patch_impl()
+// 模块 GOT 中的 CFI slot 在 Global 与 ModulesWithHooks 下都需要替换
+fn patch_enabled() -> bool {
+    patch_scope() != CfiPatchScope::Disabled
+}
+
+// 只有 Global 改写 slowpath 指令
+fn slowpath_patch_enabled() -> bool {
+    patch_scope() == CfiPatchScope::Global
+}
+
+// init 时按范围处理：Global 改写指令（结果缓存到 revert 为止），其余范围回滚此前 init 遗留的补丁
+// ignored 为命中 ignore 规则的模块 (base_addr, instance_id)，扫描 CFI 地址时跳过
+pub(super) fn disable_slowpath(ignored: &BTreeSet<(usize, usize)>) -> Errno {
+    let status = match patch_scope() {
+        CfiPatchScope::Global => {
+            return *CFI_DISABLE_STATUS
+                .lock_or_poison()
+                .get_or_insert_with(|| disable_slowpath_impl(ignored));
+        }
+        CfiPatchScope::ModulesWithHooks => restore_slowpath_patch(),
+        CfiPatchScope::Disabled => revert_slowpath_patch(),
+    };
+    if status != Errno::Ok {
+        log::warn(format_args!(
+            "cfi revert for scope {:?} status {:?}",
+            patch_scope(),
+            status
+        ));
+    }
+    Errno::Ok
+}
+
+// 回滚模块 GOT hook 与 slowpath 指令补丁
+pub(super) fn revert_slowpath_patch() -> Errno {
+    let mut status = CFI_DISABLE_STATUS.lock_or_poison();
+    *status = None;
+    revert_slowpath_patch_impl()
+}
+
+// 只恢复 slowpath 指令补丁，Global 范围降为 ModulesWithHooks，保留模块 GOT hook
+pub(super) fn restore_slowpath() -> Errno {
+    if patch_scope() == CfiPatchScope::Global {
+        set_patch_scope(CfiPatchScope::ModulesWithHooks);
+    }
+    restore_slowpath_patch()
+}
+
+fn restore_slowpath_patch() -> Errno {
+    let mut status = CFI_DISABLE_STATUS.lock_or_poison();
+    *status = None;
+    restore_slowpath_patch_impl()
+}
+
+pub(super) fn refresh_slowpath_patch(ignored: &BTreeSet<(usize, usize)>) -> Errno {
+    if !slowpath_patch_enabled() {
+        return Errno::Ok;
+    }
+    refresh_slowpath_patch_impl(ignored)
+}
+
+// 上一轮 CFI 模块扫描有剩余或忽略的模块有变化时，刷新即使模块未变化也需继续
+pub(super) fn has_pending_slowpath_scan(ignored: &BTreeSet<(usize, usize)>) -> bool {
+    slowpath_patch_enabled() && has_pending_slowpath_scan_impl(ignored)
 }
 
 pub(super) fn ensure_module_cfi_hook(module: &ModuleInfo, elf: &elf::Elf) -> Errno {
+    if !patch_enabled() {
+        return Errno::Ok;
+    }
     ensure_module_cfi_hook_impl(module, elf)
 }
 
-pub(super) fn retain_module_cfi_hook_state(modules: &[ModuleInfo]) {
-    retain_module_cfi_hook_state_impl(modules)
+// 清理已卸载模块的状态；hooked 为刷新结束时仍有活动 slot 的模块 (base_addr, instance_id)，
+// ModulesWithHooks 范围下其余存活模块的 CFI slot 同时恢复为原始值
+pub(super) fn retain_module_cfi_hook_state(
+    modules: &[ModuleInfo],
+    hooked: Option<&BTreeSet<(usize, usize)>>,
+) {
+    let hooked = hooked.filter(|_| patch_scope() == CfiPatchScope::ModulesWithHooks);
+    retain_module_cfi_hook_state_impl(modules, hooked)
+}
+
+pub(super) fn proxy_addrs() -> Vec<usize> {
+    proxy_addrs_impl()
+}
+
+pub(super) fn patch_state() -> CfiPatchState {
+    patch_state_impl()
+}
+
+pub(super) fn patch_report() -> Vec<CfiPatchEntry> {
+    patch_report_impl()
+}
+
+// 补丁报告的变更代数，未变化时无需重新生成报告
+pub(super) fn patch_report_generation() -> u64 {
+    patch_report_generation_impl()
+}
+
+#[cfg(target_arch = "aarch64")]
+fn disable_slowpath_impl(ignored: &BTreeSet<(usize, usize)>) -> Errno {
+    slowpath::disable_slowpath_impl(ignored)
+}
+
+#[cfg(target_arch = "aarch64")]
+fn refresh_slowpath_patch_impl(ignored: &BTreeSet<(usize, usize)>) -> Errno {
+    slowpath::refresh_slowpath_patch_impl(ignored)
 }
 
 #[cfg(target_arch = "aarch64")]
-fn disable_slowpath_impl() -> Errno {
-    slowpath::disable_slowpath_impl()
+fn revert_slowpath_patch_impl() -> Errno {
+    let module_status = module_hook::revert_module_cfi_hooks_impl();
+    let slowpath_status = slowpath::revert_slowpath_patch_impl();
+    if module_status != Errno::Ok {
+        return module_status;
+    }
+    slowpath_status
 }
 
 #[cfg(target_arch = "aarch64")]
-fn refresh_slowpath_patch_impl() -> Errno {
-    slowpath::refresh_slowpath_patch_impl()
+fn restore_slowpath_patch_impl() -> Errno {
+    slowpath::revert_slowpath_patch_impl()
+}
+
+#[cfg(target_arch = "aarch64")]
+fn has_pending_slowpath_scan_impl(ignored: &BTreeSet<(usize, usize)>) -> bool {
+    slowpath::has_pending_scan_impl(ignored)
 }
 
 #[cfg(target_arch = "aarch64")]
@@ -56,27 +184,107 @@ fn ensure_module_cfi_hook_impl(module: &ModuleInfo, elf: &elf::Elf) -> Errno {
 }
 
 #[cfg(target_arch = "aarch64")]
-fn retain_module_cfi_hook_state_impl(modules: &[ModuleInfo]) {
-    module_hook::retain_module_cfi_hook_state_impl(modules)
+fn retain_module_cfi_hook_state_impl(
+    modules: &[ModuleInfo],
+    hooked: Option<&BTreeSet<(usize, usize)>>,
+) {
+    module_hook::retain_module_cfi_hook_state_impl(modules, hooked)
+}
+
+#[cfg(target_arch = "aarch64")]
+fn proxy_addrs_impl() -> Vec<usize> {
+    module_hook::proxy_addrs_impl()
+}
+
+#[cfg(target_arch = "aarch64")]
+fn patch_state_impl() -> CfiPatchState {
+    if !patch_enabled() || android_api_level() < ANDROID_API_LEVEL_CFI_DISABLE {
+        return CfiPatchState {
+            skipped: true,
+            scope: patch_scope(),
+            ..CfiPatchState::default()
+        };
+    }
+    let (patched_modules, failed_modules) = module_hook::hook_state_counts_impl();
+    CfiPatchState {
+        skipped: false,
+        scope: patch_scope(),
+        patched_slowpath: slowpath::patched_count_impl(),
+        patched_modules,
+        failed_modules,
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+fn patch_report_impl() -> Vec<CfiPatchEntry> {
+    slowpath::patch_report_impl()
+}
+
+#[cfg(target_arch = "aarch64")]
+fn patch_report_generation_impl() -> u64 {
+    slowpath::patch_generation_impl()
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+fn disable_slowpath_impl(_ignored: &BTreeSet<(usize, usize)>) -> Errno {
+    Errno::Ok
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+fn refresh_slowpath_patch_impl(_ignored: &BTreeSet<(usize, usize)>) -> Errno {
+    Errno::Ok
 }
 
 #[cfg(not(target_arch = "aarch64"))]
-fn disable_slowpath_impl() -> Errno {
+fn revert_slowpath_patch_impl() -> Errno {
     Errno::Ok
 }
 
 #[cfg(not(target_arch = "aarch64"))]
-fn refresh_slowpath_patch_impl() -> Errno {
+fn restore_slowpath_patch_impl() -> Errno {
     Errno::Ok
 }
 
+#[cfg(not(target_arch = "aarch64"))]
+fn has_pending_slowpath_scan_impl(_ignored: &BTreeSet<(usize, usize)>) -> bool {
+    false
+}
+
 #[cfg(not(target_arch = "aarch64"))]
 fn ensure_module_cfi_hook_impl(_module: &ModuleInfo, _elf: &elf::Elf) -> Errno {
     Errno::Ok
 }
 
 #[cfg(not(target_arch = "aarch64"))]
-fn retain_module_cfi_hook_state_impl(_modules: &[ModuleInfo]) {}
+fn retain_module_cfi_hook_state_impl(
+    _modules: &[ModuleInfo],
+    _hooked: Option<&BTreeSet<(usize, usize)>>,
+) {
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+fn proxy_addrs_impl() -> Vec<usize> {
+    Vec::new()
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+fn patch_state_impl() -> CfiPatchState {
+    CfiPatchState {
+        skipped: true,
+        scope: patch_scope(),
+        ..CfiPatchState::default()
+    }
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+fn patch_report_impl() -> Vec<CfiPatchEntry> {
+    Vec::new()
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+fn patch_report_generation_impl() -> u64 {
+    0
+}
 
 fn android_api_level() -> i32 {
     let prop_name = c"ro.build.version.sdk";
@@ -1,5 +1,6 @@
 // 模块级 CFI hook，将各模块 GOT 中的 __cfi_slowpath 替换为空操作 proxy
-use crate::android::{memory, signal_guard};
+use crate::android::memory;
+use crate::android::signal_guard::{self, GuardPhase};
 use crate::elf;
 use crate::errno::Errno;
 use crate::log;
@@ -43,6 +44,18 @@ fn module_cfi_hook_states() -> &'static Mutex<BTreeMap<ModuleCfiKey, ModuleCfiHo
     MODULE_CFI_HOOK_STATES.get_or_init(|| Mutex::new(BTreeMap::new()))
 }
 
+// 已改写的 CFI GOT slot 及其原始值，供 revert 恢复
+struct ModuleCfiSlots {
+    pathname: String,
+    slots: BTreeMap<usize, usize>,
+}
+
+fn module_cfi_slot_origins() -> &'static Mutex<BTreeMap<ModuleCfiKey, ModuleCfiSlots>> {
+    static MODULE_CFI_SLOT_ORIGINS: OnceLock<Mutex<BTreeMap<ModuleCfiKey, ModuleCfiSlots>>> =
+        OnceLock::new();
+    MODULE_CFI_SLOT_ORIGINS.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
 // 确保指定模块的 CFI GOT slot 已被 hook，带重试限制
 pub(super) fn ensure_module_cfi_hook_impl(module: &ModuleInfo, elf: &elf::Elf) -> Errno {
     if android_api_level() < ANDROID_API_LEVEL_CFI_DISABLE {
@@ -83,8 +96,11 @@ pub(super) fn ensure_module_cfi_hook_impl(module: &ModuleInfo, elf: &elf::Elf) -
     entry.status
 }
 
-// 清理已卸载模块的 CFI hook 状态记录
-pub(super) fn retain_module_cfi_hook_state_impl(modules: &[ModuleInfo]) {
+// 清理已卸载模块的 CFI hook 状态记录；hooked 非空时存活但不在其中的模块也移出，并恢复其 CFI slot
+pub(super) fn retain_module_cfi_hook_state_impl(
+    modules: &[ModuleInfo],
+    hooked: Option<&BTreeSet<(usize, usize)>>,
+) {
     let alive_keys: BTreeSet<ModuleCfiKey> = modules
         .iter()
         .map(|module| ModuleCfiKey {
@@ -92,10 +108,84 @@ pub(super) fn retain_module_cfi_hook_state_impl(modules: &[ModuleInfo]) {
             instance_id: module.instance_id,
         })
         .collect();
+    let keep = |key: &ModuleCfiKey| {
+        alive_keys.contains(key)
+            && hooked.is_none_or(|hooked| hooked.contains(&(key.base_addr, key.instance_id)))
+    };
     module_cfi_hook_states()
-        .lock()
-        .unwrap()
-        .retain(|key, _| alive_keys.contains(key));
+        .lock_or_poison()
+        .retain(|key, _| keep(key));
+    let mut released = Vec::new();
+    module_cfi_slot_origins()
+        .lock_or_poison()
+        .retain(|key, module| {
+            if keep(key) {
+                return true;
+            }
+            if alive_keys.contains(key) {
+                released.push(std::mem::take(&mut module.slots));
+            }
+            false
+        });
+    if released.is_empty() {
+        return;
+    }
+    let proxies = proxy_addrs_impl();
+    let failed: usize = released
+        .iter()
+        .map(|slots| revert_module_slots(slots, &proxies))
+        .sum();
+    log::debug(format_args!(
+        "cfi module hook released modules={} failed_slots={}",
+        released.len(),
+        failed
+    ));
+}
+
+// 将存活模块中仍指向 proxy 的 CFI slot 恢复为原始值，并清空 hook 状态以便之后重新 hook
+pub(super) fn revert_module_cfi_hooks_impl() -> Errno {
+    let origins = std::mem::take(&mut *module_cfi_slot_origins().lock_or_poison());
+    module_cfi_hook_states().lock_or_poison().clear();
+    let proxies = proxy_addrs_impl();
+    let failed: usize = origins
+        .values()
+        .map(|module| revert_module_slots(&module.slots, &proxies))
+        .sum();
+    if failed > 0 {
+        log::warn(format_args!(
+            "cfi module hook revert failed slots={}",
+            failed
+        ));
+        return Errno::CfiHookFailed;
+    }
+    Errno::Ok
+}
+
+// 只恢复仍指向 proxy 的 slot，已被其他代码改写的保持不动，返回恢复失败的数量
+fn revert_module_slots(slots: &BTreeMap<usize, usize>, proxies: &[usize]) -> usize {
+    let mut failed = 0usize;
+    for (slot_addr, original) in slots {
+        let current = signal_guard::with_guard_tagged(GuardPhase::CfiPatch, || unsafe {
+            std::ptr::read(*slot_addr as *const usize)
+        });
+        if !current.is_ok_and(|current| proxies.contains(&current)) {
+            continue;
+        }
+        if patch_module_cfi_slot(*slot_addr, *original).is_err() {
+            failed += 1;
+        }
+    }
+    failed
+}
+
+// 存活模块中 CFI hook 成功与失败的模块数
+pub(super) fn hook_state_counts_impl() -> (usize, usize) {
+    let states = module_cfi_hook_states().lock_or_poison();
+    let patched = states
+        .values()
+        .filter(|state| state.status == Errno::Ok)
+        .count();
+    (patched, states.len() - patched)
 }
 
 fn hook_module_cfi_symbols(module: &ModuleInfo, elf: &elf::Elf) -> Errno {
@@ -132,48 +222,68 @@ fn hook_module_cfi_symbol_slots(
     symbol: &str,
     proxy_addr: usize,
 ) -> Result<usize, Errno> {
-    let slots = signal_guard::with_guard(|| unsafe { elf.find_got_slots(symbol, None) })
-        .map_err(|_| Errno::ReadElf)?
-        .map_err(|_| Errno::ReadElf)?;
+    let slots = signal_guard::with_guard_tagged(GuardPhase::CfiScan, || unsafe {
+        elf.find_got_slots(symbol, None, None)
+    })
+    .map_err(|_| Errno::ReadElf)?
+    .map_err(|_| Errno::ReadElf)?;
     if slots.is_empty() {
         return Ok(0);
     }
     for slot_addr in &slots {
-        patch_module_cfi_slot(*slot_addr, proxy_addr, &module.pathname)?;
+        let original = patch_module_cfi_slot(*slot_addr, proxy_addr)?;
+        if original == proxy_addr {
+            continue;
+        }
+        let key = ModuleCfiKey {
+            base_addr: module.base_addr,
+            instance_id: module.instance_id,
+        };
+        module_cfi_slot_origins()
+            .lock_or_poison()
+            .entry(key)
+            .or_insert_with(|| ModuleCfiSlots {
+                pathname: module.pathname.clone(),
+                slots: BTreeMap::new(),
+            })
+            .slots
+            .insert(*slot_addr, original);
     }
     Ok(slots.len())
 }
 
-// 将模块 GOT 中的 CFI slot 写入 proxy 地址
-fn patch_module_cfi_slot(slot_addr: usize, proxy_addr: usize, pathname: &str) -> Result<(), Errno> {
+// 将模块 GOT 中的 CFI slot 写入目标地址（proxy 或 revert 时的原始值），返回写入前的值
+fn patch_module_cfi_slot(slot_addr: usize, target_addr: usize) -> Result<usize, Errno> {
     // 已经是目标值则跳过
-    let current = signal_guard::with_guard(|| unsafe { std::ptr::read(slot_addr as *const usize) })
-        .map_err(|_| Errno::ReadElf)?;
-    if current == proxy_addr {
-        return Ok(());
-    }
-
-    let old_prot = memory::get_addr_protect(slot_addr, Some(pathname)).map_err(|_| Errno::GetProt)?;
-    let writable_prot = memory::PROT_READ_FLAG | memory::PROT_WRITE_FLAG;
-    if old_prot != writable_prot {
-        memory::set_addr_protect(slot_addr, writable_prot).map_err(|_| Errno::SetProt)?;
+    let current = signal_guard::with_guard_tagged(GuardPhase::CfiPatch, || unsafe {
+        std::ptr::read(slot_addr as *const usize)
+    })
+    .map_err(|_| Errno::ReadElf)?;
+    if current == target_addr {
+        return Ok(current);
     }
 
-    let write_result = signal_guard::with_guard(|| unsafe {
-        let atomic_slot = &*(slot_addr as *const AtomicUsize);
-        atomic_slot.store(proxy_addr, Ordering::SeqCst);
-        atomic_slot.load(Ordering::SeqCst)
-    });
-    if old_prot != writable_prot {
-        let _ = memory::set_addr_protect(slot_addr, old_prot);
-    }
+    let write_result = memory::with_writable(slot_addr, std::mem::size_of::<usize>(), || {
+        signal_guard::with_guard_tagged(GuardPhase::CfiPatch, || unsafe {
+            let atomic_slot = &*(slot_addr as *const AtomicUsize);
+            atomic_slot.store(target_addr, Ordering::SeqCst);
+            atomic_slot.load(Ordering::SeqCst)
+        })
+    })?;
 
     let written_addr = write_result.map_err(|_| Errno::SetGot)?;
-    if written_addr != proxy_addr {
+    if written_addr != target_addr {
         return Err(Errno::GotVerify);
     }
     memory::flush_instruction_cache(slot_addr);
-    Ok(())
+    Ok(current)
+}
+
+pub(super) fn proxy_addrs_impl() -> Vec<usize> {
+    vec![
+        cfi_slowpath_proxy as *mut c_void as usize,
+        cfi_slowpath_diag_proxy as *mut c_void as usize,
+    ]
 }
 
 // CFI slowpath 空操作代理，替换原始 __cfi_slowpath 使其不执行检查
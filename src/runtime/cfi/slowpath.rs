@@ -1,16 +1,26 @@
 // CFI slowpath 全局补丁，通过将 __cfi_slowpath 函数体改写为 RET 指令来禁用 CFI 检查
-use crate::android::{memory, signal_guard};
-use crate::elf;
+use crate::android::memory;
+use crate::android::signal_guard::{self, GuardPhase};
+use crate::api::{CfiAddrSource, CfiPatchEntry, CfiPatchStatus, CfiSymbolKind};
 use crate::errno::Errno;
 use crate::log;
 use crate::runtime::state::MutexPoisonRecover;
 use std::collections::{BTreeMap, BTreeSet};
-use std::ffi::{CStr, c_void};
+use std::ffi::{CStr, CString, c_void};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
 
 use super::{ANDROID_API_LEVEL_CFI_DISABLE, RTLD_NEXT_FALLBACK, android_api_level};
+use scan_cache::{CfiScanCache, ModuleCfiFindings, ModuleScanKey};
 mod patch;
+mod scan_cache;
 
+// CFI 地址到符号与来源的映射
+type CfiAddrMap = BTreeMap<usize, (CfiSymbolKind, CfiAddrSource)>;
+
+// 刷新阶段单轮扫描新模块的时间预算，超出部分推迟到下一轮
+const CFI_SCAN_BUDGET: Duration = Duration::from_millis(2);
 // 单个地址 patch 失败后的最大重试次数
 const CFI_PATCH_FAIL_RETRY_LIMIT: u8 = 3;
 const CFI_SLOWPATH_SYMBOL: &CStr = c"__cfi_slowpath";
@@ -32,26 +42,104 @@ const CFI_SYMBOL_LIB_CANDIDATES: [&CStr; 13] = [
     c"/system/lib64/libc.so",
 ];
 
-pub(super) fn disable_slowpath_impl() -> Errno {
-    patch_cfi_slowpath(true)
+pub(super) fn disable_slowpath_impl(ignored: &BTreeSet<(usize, usize)>) -> Errno {
+    patch_cfi_slowpath(true, ignored)
+}
+
+pub(super) fn refresh_slowpath_patch_impl(ignored: &BTreeSet<(usize, usize)>) -> Errno {
+    patch_cfi_slowpath(false, ignored)
 }
 
-pub(super) fn refresh_slowpath_patch_impl() -> Errno {
-    patch_cfi_slowpath(false)
+// 将已 patch 的地址恢复为原始指令，之后的 disable/refresh 会重新 patch
+pub(super) fn revert_slowpath_patch_impl() -> Errno {
+    let patched = std::mem::take(&mut *patched_cfi_addrs().lock_or_poison());
+    failed_cfi_addrs().lock_or_poison().clear();
+    cfi_addr_origins().lock_or_poison().clear();
+    ignored_cfi_addrs().lock_or_poison().clear();
+    CFI_PATCH_GENERATION.fetch_add(1, Ordering::Release);
+    let mut failed = 0usize;
+    for (addr, original) in &patched {
+        if patch::restore_instruction(*addr, *original).is_err() {
+            failed += 1;
+            continue;
+        }
+        memory::flush_instruction_cache_range(*addr, *addr + std::mem::size_of::<u32>());
+    }
+    if failed > 0 {
+        log::warn(format_args!(
+            "cfi slowpath revert failed={} total={}",
+            failed,
+            patched.len()
+        ));
+        return Errno::CfiHookFailed;
+    }
+    Errno::Ok
+}
+
+// 当前已改写为 RET 的指令地址数
+pub(super) fn patched_count_impl() -> usize {
+    patched_cfi_addrs().lock_or_poison().len()
+}
+
+// 上一轮是否有模块因超出预算尚未扫描，或忽略的模块与上一轮不同
+pub(super) fn has_pending_scan_impl(ignored: &BTreeSet<(usize, usize)>) -> bool {
+    CFI_SCAN_PENDING.load(Ordering::Acquire) || *ignored_cfi_modules().lock_or_poison() != *ignored
+}
+
+static CFI_SCAN_PENDING: AtomicBool = AtomicBool::new(false);
+
+// 补丁报告的变更代数，地址被 patch、失败或恢复时递增，供记录导出判断是否需要重新比对
+static CFI_PATCH_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+pub(super) fn patch_generation_impl() -> u64 {
+    CFI_PATCH_GENERATION.load(Ordering::Acquire)
+}
+
+// 已 patch 与失败的地址连同来源及所属模块，按地址排序
+pub(super) fn patch_report_impl() -> Vec<CfiPatchEntry> {
+    let patched_addrs = patched_cfi_addrs().lock_or_poison();
+    let failed_addrs = failed_cfi_addrs().lock_or_poison();
+    let origins = cfi_addr_origins().lock_or_poison();
+    let ignored_addrs = ignored_cfi_addrs().lock_or_poison();
+    let mut statuses: BTreeMap<usize, CfiPatchStatus> = failed_addrs
+        .iter()
+        .map(|(addr, count)| (*addr, CfiPatchStatus::Failed(*count)))
+        .collect();
+    statuses.extend(
+        patched_addrs
+            .keys()
+            .map(|addr| (*addr, CfiPatchStatus::Patched)),
+    );
+    statuses
+        .into_iter()
+        .filter_map(|(addr, status)| {
+            let (kind, source) = origins.get(&addr).copied()?;
+            Some(CfiPatchEntry {
+                addr,
+                kind,
+                source,
+                module: module_path_of(addr),
+                status,
+                ignored: ignored_addrs.contains(&addr),
+            })
+        })
+        .collect()
 }
 
 // 核心 patch 逻辑：收集所有 CFI 符号地址并逐一写入 RET 指令
 // require_slowpath 为 true 时表示初始化阶段，必须至少成功 patch 一个 slowpath 地址
-fn patch_cfi_slowpath(require_slowpath: bool) -> Errno {
+fn patch_cfi_slowpath(require_slowpath: bool, ignored: &BTreeSet<(usize, usize)>) -> Errno {
+    *ignored_cfi_modules().lock_or_poison() = ignored.clone();
     if android_api_level() < ANDROID_API_LEVEL_CFI_DISABLE {
         return Errno::Ok;
     }
 
-    let (slowpath_addrs, slowpath_diag_addrs) = resolve_cfi_symbols();
-    if require_slowpath && slowpath_addrs.is_empty() {
+    let (resolved, held) = resolve_cfi_symbols(require_slowpath, ignored);
+    let has_kind = |kind| resolved.values().any(|(found, _)| *found == kind);
+    if require_slowpath && !has_kind(CfiSymbolKind::Slowpath) {
         return Errno::InitErrCfi;
     }
-    if require_slowpath && slowpath_diag_addrs.is_empty() {
+    if require_slowpath && !has_kind(CfiSymbolKind::SlowpathDiag) {
         log::warn(format_args!(
             "cfi slowpath diag symbol missing, patched slowpath only"
         ));
@@ -59,52 +147,65 @@ fn patch_cfi_slowpath(require_slowpath: bool) -> Errno {
 
     let mut patched_addrs = patched_cfi_addrs().lock_or_poison();
     let mut failed_addrs = failed_cfi_addrs().lock_or_poison();
-    let mut all_addrs = slowpath_addrs.clone();
-    all_addrs.extend(slowpath_diag_addrs.iter().copied());
-    patched_addrs.retain(|addr| all_addrs.contains(addr));
-    failed_addrs.retain(|addr, _| all_addrs.contains(addr));
+    let mut origins = cfi_addr_origins().lock_or_poison();
+    // 只由忽略的模块提供的地址不再 patch，已 patch 的保持原样并在报告中标记
+    let kept: CfiAddrMap = held
+        .into_iter()
+        .filter(|(addr, _)| !resolved.contains_key(addr) && patched_addrs.contains_key(addr))
+        .collect();
+    patched_addrs.retain(|addr, _| resolved.contains_key(addr) || kept.contains_key(addr));
+    failed_addrs.retain(|addr, _| resolved.contains_key(addr));
+    *origins = resolved.clone();
+    origins.extend(kept.iter().map(|(addr, origin)| (*addr, *origin)));
+    let kept: BTreeSet<usize> = kept.into_keys().collect();
+    let mut ignored_addrs = ignored_cfi_addrs().lock_or_poison();
+    let ignored_changed = *ignored_addrs != kept;
+    if ignored_changed && !kept.is_empty() {
+        log::info(format_args!(
+            "cfi left {} patched addrs of ignored modules untouched",
+            kept.len()
+        ));
+    }
+    *ignored_addrs = kept;
 
     let mut patched_slowpath = 0usize;
     let mut patched_diag = 0usize;
     let mut failed_slowpath = 0usize;
     let mut failed_diag = 0usize;
-    for addr in &slowpath_addrs {
-        if patched_addrs.contains(addr) {
-            continue;
-        }
-        let fail_count = failed_addrs.get(addr).copied().unwrap_or(0);
-        if fail_count >= CFI_PATCH_FAIL_RETRY_LIMIT {
-            continue;
-        }
-        if patch::patch_ret_instruction(*addr).is_err() {
-            failed_addrs.insert(*addr, fail_count.saturating_add(1));
-            failed_slowpath += 1;
-            continue;
-        }
-        memory::flush_instruction_cache_range(*addr, *addr + std::mem::size_of::<u32>());
-        patched_addrs.insert(*addr);
-        failed_addrs.remove(addr);
-        patched_slowpath += 1;
-    }
-    for addr in &slowpath_diag_addrs {
-        if patched_addrs.contains(addr) {
-            continue;
-        }
-        let fail_count = failed_addrs.get(addr).copied().unwrap_or(0);
-        if fail_count >= CFI_PATCH_FAIL_RETRY_LIMIT {
-            continue;
-        }
-        if patch::patch_ret_instruction(*addr).is_err() {
-            failed_addrs.insert(*addr, fail_count.saturating_add(1));
-            failed_diag += 1;
-            continue;
+    // slowpath 先于 diag 处理
+    for kind in [CfiSymbolKind::Slowpath, CfiSymbolKind::SlowpathDiag] {
+        let (patched, failed) = match kind {
+            CfiSymbolKind::Slowpath => (&mut patched_slowpath, &mut failed_slowpath),
+            CfiSymbolKind::SlowpathDiag => (&mut patched_diag, &mut failed_diag),
+        };
+        for (addr, _) in resolved.iter().filter(|(_, (found, _))| *found == kind) {
+            if patched_addrs.contains_key(addr) {
+                continue;
+            }
+            let fail_count = failed_addrs.get(addr).copied().unwrap_or(0);
+            if fail_count >= CFI_PATCH_FAIL_RETRY_LIMIT {
+                continue;
+            }
+            let Ok(original) = patch::patch_ret_instruction(*addr) else {
+                failed_addrs.insert(*addr, fail_count.saturating_add(1));
+                *failed += 1;
+                continue;
+            };
+            memory::flush_instruction_cache_range(*addr, *addr + std::mem::size_of::<u32>());
+            patched_addrs.insert(*addr, original);
+            failed_addrs.remove(addr);
+            *patched += 1;
         }
-        memory::flush_instruction_cache_range(*addr, *addr + std::mem::size_of::<u32>());
-        patched_addrs.insert(*addr);
-        failed_addrs.remove(addr);
-        patched_diag += 1;
     }
 
+    if ignored_changed
+        || patched_slowpath > 0
+        || patched_diag > 0
+        || failed_slowpath > 0
+        || failed_diag > 0
+    {
+        CFI_PATCH_GENERATION.fetch_add(1, Ordering::Release);
+    }
     if patched_slowpath > 0 || patched_diag > 0 {
         log::debug(format_args!(
             "cfi patched new slowpath={} diag={}",
@@ -120,42 +221,74 @@ fn patch_cfi_slowpath(require_slowpath: bool) -> Errno {
         ));
     }
     if require_slowpath
-        && !slowpath_addrs
-            .iter()
-            .any(|addr| patched_addrs.contains(addr))
+        && !resolved.iter().any(|(addr, (kind, _))| {
+            *kind == CfiSymbolKind::Slowpath && patched_addrs.contains_key(addr)
+        })
     {
         return Errno::InitErrCfi;
     }
     Errno::Ok
 }
 
-// 通过 dlsym、ELF 导出表、GOT import slot 三种途径收集所有 CFI 符号地址
-fn resolve_cfi_symbols() -> (BTreeSet<usize>, BTreeSet<usize>) {
-    let mut slowpath_addrs = BTreeSet::new();
-    let mut slowpath_diag_addrs = BTreeSet::new();
-    let modules = enumerate_loaded_modules();
-
-    if let Some(addr) = resolve_symbol_with_fallback(CFI_SLOWPATH_SYMBOL) {
-        slowpath_addrs.insert(addr);
+// 通过 dlsym、ELF 导出表、GOT import slot 三种途径收集所有 CFI 符号地址，保留每个地址首次发现的来源
+// 模块扫描结果按实例缓存，刷新阶段只扫描新模块；初始化阶段不设预算，保证 require_slowpath 判定完整
+// 忽略的模块不扫描，其缓存中已有的结果单独返回，用于保留此前的补丁
+fn resolve_cfi_symbols(
+    require_slowpath: bool,
+    ignored: &BTreeSet<(usize, usize)>,
+) -> (CfiAddrMap, CfiAddrMap) {
+    let mut resolved = BTreeMap::new();
+    for (kind, symbol) in [
+        (CfiSymbolKind::Slowpath, CFI_SLOWPATH_SYMBOL),
+        (CfiSymbolKind::SlowpathDiag, CFI_SLOWPATH_DIAG_SYMBOL),
+    ] {
+        if let Some(addr) = resolve_symbol_with_fallback(symbol) {
+            resolved.entry(addr).or_insert((kind, CfiAddrSource::Dlsym));
+        }
     }
-    if let Some(addr) = resolve_symbol_with_fallback(CFI_SLOWPATH_DIAG_SYMBOL) {
-        slowpath_diag_addrs.insert(addr);
+
+    let modules: Vec<(ModuleScanKey, LoadedModule)> = enumerate_loaded_modules()
+        .into_iter()
+        .filter(|module| module.base_addr != 0)
+        .map(|module| {
+            let key = ModuleScanKey {
+                base_addr: module.base_addr,
+                instance_id: module.instance_id,
+                file_id: module_file_id(&module.pathname),
+            };
+            (key, module)
+        })
+        .collect();
+    let skip: BTreeSet<ModuleScanKey> = modules
+        .iter()
+        .map(|(key, _)| *key)
+        .filter(|key| ignored.contains(&(key.base_addr, key.instance_id)))
+        .collect();
+    let budget = (!require_slowpath).then_some(CFI_SCAN_BUDGET);
+    let mut cache = cfi_scan_cache().lock_or_poison();
+    let stats = cache.update(&modules, &skip, budget, scan_module_cfi_symbols);
+    CFI_SCAN_PENDING.store(stats.deferred > 0, Ordering::Release);
+    if stats.scanned > 0 || stats.deferred > 0 {
+        log::debug(format_args!(
+            "cfi module scan scanned={} cached={} deferred={}",
+            stats.scanned, stats.cached, stats.deferred
+        ));
     }
+    cache.collect(|key| !skip.contains(key), &mut resolved);
+    let mut held = BTreeMap::new();
+    cache.collect(|key| skip.contains(key), &mut held);
+    (resolved, held)
+}
 
-    collect_module_symbol_addrs_from_elf(&modules, "__cfi_slowpath", &mut slowpath_addrs);
-    collect_module_symbol_addrs_from_elf(&modules, "__cfi_slowpath_diag", &mut slowpath_diag_addrs);
-    collect_module_symbol_addrs_from_import_slots(&modules, "__cfi_slowpath", &mut slowpath_addrs);
-    collect_module_symbol_addrs_from_import_slots(
-        &modules,
-        "__cfi_slowpath_diag",
-        &mut slowpath_diag_addrs,
-    );
-    (slowpath_addrs, slowpath_diag_addrs)
+fn cfi_scan_cache() -> &'static Mutex<CfiScanCache> {
+    static CFI_SCAN_CACHE: OnceLock<Mutex<CfiScanCache>> = OnceLock::new();
+    CFI_SCAN_CACHE.get_or_init(|| Mutex::new(CfiScanCache::default()))
 }
 
-fn patched_cfi_addrs() -> &'static Mutex<BTreeSet<usize>> {
-    static PATCHED_CFI_ADDRS: OnceLock<Mutex<BTreeSet<usize>>> = OnceLock::new();
-    PATCHED_CFI_ADDRS.get_or_init(|| Mutex::new(BTreeSet::new()))
+// 已 patch 的地址及其原始指令，供 revert 恢复
+fn patched_cfi_addrs() -> &'static Mutex<BTreeMap<usize, u32>> {
+    static PATCHED_CFI_ADDRS: OnceLock<Mutex<BTreeMap<usize, u32>>> = OnceLock::new();
+    PATCHED_CFI_ADDRS.get_or_init(|| Mutex::new(BTreeMap::new()))
 }
 
 fn failed_cfi_addrs() -> &'static Mutex<BTreeMap<usize, u8>> {
@@ -163,6 +296,33 @@ fn failed_cfi_addrs() -> &'static Mutex<BTreeMap<usize, u8>> {
     FAILED_CFI_ADDRS.get_or_init(|| Mutex::new(BTreeMap::new()))
 }
 
+// 最近一次收集到的地址及其符号与来源，供补丁报告使用
+fn cfi_addr_origins() -> &'static Mutex<CfiAddrMap> {
+    static CFI_ADDR_ORIGINS: OnceLock<Mutex<CfiAddrMap>> = OnceLock::new();
+    CFI_ADDR_ORIGINS.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+// 属于忽略模块、保持原样未恢复的已 patch 地址
+fn ignored_cfi_addrs() -> &'static Mutex<BTreeSet<usize>> {
+    static IGNORED_CFI_ADDRS: OnceLock<Mutex<BTreeSet<usize>>> = OnceLock::new();
+    IGNORED_CFI_ADDRS.get_or_init(|| Mutex::new(BTreeSet::new()))
+}
+
+// 上一轮扫描时忽略的模块 (base_addr, instance_id)
+fn ignored_cfi_modules() -> &'static Mutex<BTreeSet<(usize, usize)>> {
+    static IGNORED_CFI_MODULES: OnceLock<Mutex<BTreeSet<(usize, usize)>>> = OnceLock::new();
+    IGNORED_CFI_MODULES.get_or_init(|| Mutex::new(BTreeSet::new()))
+}
+
+fn module_path_of(addr: usize) -> Option<String> {
+    let mut info: libc::Dl_info = unsafe { std::mem::zeroed() };
+    if unsafe { libc::dladdr(addr as *const c_void, &mut info) } == 0 || info.dli_fname.is_null() {
+        return None;
+    }
+    let pathname = unsafe { CStr::from_ptr(info.dli_fname) };
+    Some(pathname.to_string_lossy().into_owned())
+}
+
 fn resolve_symbol_with_fallback(symbol: &CStr) -> Option<usize> {
     if let Some(addr) = resolve_symbol_from_handle(RTLD_NEXT_FALLBACK, symbol) {
         return Some(addr);
@@ -195,53 +355,49 @@ fn resolve_symbol_from_library(lib_name: &CStr, symbol: &CStr) -> Option<usize>
     addr
 }
 
-fn collect_module_symbol_addrs_from_elf(modules: &[LoadedModule], symbol: &str, out: &mut BTreeSet<usize>) {
-    for module in modules {
-        if module.base_addr == 0 {
-            continue;
-        }
-        let found = signal_guard::with_guard(|| unsafe {
-            let elf = elf::Elf::init(module.base_addr, &module.pathname).ok()?;
-            elf.find_export_function(symbol)
-        })
-        .ok()
-        .flatten();
-        if let Some(addr) = found {
-            out.insert(addr);
-        }
+// 模块文件的 (dev, inode)，无法 stat 时为 (0, 0)
+fn module_file_id(pathname: &str) -> (u64, u64) {
+    let Ok(path) = CString::new(pathname) else {
+        return (0, 0);
+    };
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::stat(path.as_ptr(), &mut stat) } != 0 {
+        return (0, 0);
     }
+    (stat.st_dev as u64, stat.st_ino as u64)
 }
 
-fn collect_module_symbol_addrs_from_import_slots(
-    modules: &[LoadedModule],
-    symbol: &str,
-    out: &mut BTreeSet<usize>,
-) {
-    for module in modules {
-        if module.base_addr == 0 {
-            continue;
-        }
-        let candidates = signal_guard::with_guard(|| unsafe {
-            let elf = elf::Elf::init(module.base_addr, &module.pathname).ok()?;
-            let slots = elf.find_got_slots(symbol, None).ok()?;
-            let mut values = Vec::with_capacity(slots.len());
-            for slot in slots {
-                let value = std::ptr::read(slot as *const usize);
+// 单个模块只解析一次 ELF，同时查找两个符号的导出地址和 GOT import slot 中的值
+// ELF 初始化走 refresh 相同的受保护路径，首段不可读时按开关回退到文件程序头
+fn scan_module_cfi_symbols(module: &LoadedModule) -> ModuleCfiFindings {
+    let Ok(elf) = crate::runtime::refresh::init_module_elf(module.base_addr, &module.pathname)
+    else {
+        return ModuleCfiFindings::default();
+    };
+    signal_guard::with_guard_tagged(GuardPhase::CfiScan, || unsafe {
+        let mut findings = ModuleCfiFindings::default();
+        for (symbol, out) in [
+            ("__cfi_slowpath", &mut findings.slowpath),
+            ("__cfi_slowpath_diag", &mut findings.diag),
+        ] {
+            out.extend(
+                elf.find_export_function(symbol, None)
+                    .into_iter()
+                    .map(|addr| (addr, CfiAddrSource::ElfExport)),
+            );
+            for slot in elf.find_got_slots(symbol, None, None).unwrap_or_default() {
+                // 候选地址随后按数据访问改写函数体，须去掉 PAC 签名与标签
+                let value = memory::canonical_code_addr(memory::safe_read_usize(slot).unwrap_or(0));
                 if value != 0 && patch::is_plausible_cfi_runtime_addr(value) {
-                    values.push(value);
+                    out.push((value, CfiAddrSource::ImportSlot));
                 }
             }
-            Some(values)
-        })
-        .ok()
-        .flatten();
-        let Some(candidates) = candidates else {
-            continue;
-        };
-        for candidate in candidates {
-            out.insert(candidate);
         }
-    }
+        Some(findings)
+    })
+    .ok()
+    .flatten()
+    .unwrap_or_default()
 }
 
 fn enumerate_loaded_modules() -> Vec<LoadedModule> {
@@ -266,6 +422,7 @@ fn enumerate_loaded_modules() -> Vec<LoadedModule> {
         }
         modules.push(LoadedModule {
             base_addr: info.dlpi_addr as usize,
+            instance_id: info.dlpi_name as usize,
             pathname: pathname.to_string(),
         });
         0
@@ -281,5 +438,6 @@ fn enumerate_loaded_modules() -> Vec<LoadedModule> {
 #[derive(Debug, Clone)]
 struct LoadedModule {
     base_addr: usize,
+    instance_id: usize,
     pathname: String,
 }
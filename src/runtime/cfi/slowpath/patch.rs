@@ -1,45 +1,54 @@
 // CFI slowpath 指令级补丁，将目标地址的指令改写为 ARM64 RET
-use crate::android::{memory, signal_guard};
+use crate::android::memory;
+use crate::android::signal_guard::{self, GuardPhase};
 use crate::errno::Errno;
 use std::ffi::{CStr, c_void};
 
 // ARM64 RET 指令编码 (0xd65f03c0)
 const ARM64_RET_INST: u32 = 0xd65f03c0;
 
-// 将指定地址的指令改写为 RET，使 CFI slowpath 函数立即返回
-pub(super) fn patch_ret_instruction(addr: usize) -> Result<(), Errno> {
+// 将指定地址的指令改写为 RET，使 CFI slowpath 函数立即返回，返回改写前的原始指令
+pub(super) fn patch_ret_instruction(addr: usize) -> Result<u32, Errno> {
+    let original = read_instruction(addr).ok_or(Errno::InitErrCfi)?;
     // 已经是 RET 则无需重复 patch
-    if read_ret_instruction(addr).is_some_and(|instruction| instruction == ARM64_RET_INST) {
-        return Ok(());
+    if original == ARM64_RET_INST {
+        return Ok(original);
     }
+    write_instruction(addr, ARM64_RET_INST)?;
+    Ok(original)
+}
 
-    let old_prot = memory::get_addr_protect(addr, None).map_err(|_| Errno::InitErrCfi)?;
-    let writable = memory::PROT_READ_FLAG | memory::PROT_WRITE_FLAG | memory::PROT_EXEC_FLAG;
-    let changed_protect = old_prot != writable;
-    if changed_protect {
-        memory::set_addr_protect(addr, writable).map_err(|_| Errno::InitErrCfi)?;
+// 将 RET 恢复为 patch 前的原始指令，已被其他代码改写的地址保持不动
+pub(super) fn restore_instruction(addr: usize, original: u32) -> Result<(), Errno> {
+    if original == ARM64_RET_INST || read_instruction(addr) != Some(ARM64_RET_INST) {
+        return Ok(());
     }
+    write_instruction(addr, original)
+}
 
-    let write_result = signal_guard::with_guard(|| unsafe {
-        std::ptr::write_volatile(addr as *mut u32, ARM64_RET_INST);
-        std::ptr::read_volatile(addr as *const u32)
-    });
-
-    if changed_protect {
-        let _ = memory::set_addr_protect(addr, old_prot);
-    }
+fn write_instruction(addr: usize, value: u32) -> Result<(), Errno> {
+    let write_result = memory::with_writable(addr, std::mem::size_of::<u32>(), || {
+        signal_guard::with_guard_tagged(GuardPhase::CfiPatch, || unsafe {
+            std::ptr::write_volatile(addr as *mut u32, value);
+            std::ptr::read_volatile(addr as *const u32)
+        })
+    })
+    .map_err(|_| Errno::InitErrCfi)?;
 
     let Ok(instruction) = write_result else {
         return Err(Errno::InitErrCfi);
     };
-    if instruction != ARM64_RET_INST {
+    if instruction != value {
         return Err(Errno::InitErrCfi);
     }
     Ok(())
 }
 
-fn read_ret_instruction(addr: usize) -> Option<u32> {
-    signal_guard::with_guard(|| unsafe { std::ptr::read_volatile(addr as *const u32) }).ok()
+fn read_instruction(addr: usize) -> Option<u32> {
+    signal_guard::with_guard_tagged(GuardPhase::CfiPatch, || unsafe {
+        std::ptr::read_volatile(addr as *const u32)
+    })
+    .ok()
 }
 
 // 判断地址是否可能指向 CFI 运行时代码
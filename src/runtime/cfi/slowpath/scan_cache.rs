@@ -0,0 +1,287 @@
+// CFI 符号扫描结果的按模块缓存：已扫描模块直接复用，新模块按时间预算分批扫描
+use crate::api::{CfiAddrSource, CfiSymbolKind};
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{Duration, Instant};
+
+// 按 base_addr + instance_id 区分模块实例，与模块级 CFI hook 状态一致
+// 卸载后同一基址重新加载时 dlpi_name 指针可能复用，再以文件的 (dev, inode) 区分
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub(super) struct ModuleScanKey {
+    pub(super) base_addr: usize,
+    pub(super) instance_id: usize,
+    pub(super) file_id: (u64, u64),
+}
+
+// 单个模块中找到的 CFI 地址及来源（导出符号或 GOT import slot 中的值），均为空表示模块无 CFI
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(super) struct ModuleCfiFindings {
+    pub(super) slowpath: Vec<(usize, CfiAddrSource)>,
+    pub(super) diag: Vec<(usize, CfiAddrSource)>,
+}
+
+// 单次扫描的计数：新扫描、命中缓存、因超出预算推迟到下一轮
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(super) struct CfiScanStats {
+    pub(super) scanned: usize,
+    pub(super) cached: usize,
+    pub(super) deferred: usize,
+}
+
+#[derive(Default)]
+pub(super) struct CfiScanCache {
+    findings: BTreeMap<ModuleScanKey, ModuleCfiFindings>,
+}
+
+impl CfiScanCache {
+    // 淘汰已卸载模块并扫描未缓存的模块；budget 为 None 时扫描全部
+    // 每轮至少扫描一个模块，保证预算过小时仍能推进；skip 中的模块不扫描，已有结果保留
+    pub(super) fn update<M, F>(
+        &mut self,
+        modules: &[(ModuleScanKey, M)],
+        skip: &BTreeSet<ModuleScanKey>,
+        budget: Option<Duration>,
+        mut scan: F,
+    ) -> CfiScanStats
+    where
+        F: FnMut(&M) -> ModuleCfiFindings,
+    {
+        let alive: BTreeSet<ModuleScanKey> = modules.iter().map(|(key, _)| *key).collect();
+        self.findings.retain(|key, _| alive.contains(key));
+
+        let start = Instant::now();
+        let mut stats = CfiScanStats::default();
+        for (key, module) in modules {
+            if skip.contains(key) {
+                continue;
+            }
+            if self.findings.contains_key(key) {
+                stats.cached += 1;
+                continue;
+            }
+            if let Some(budget) = budget
+                && stats.scanned > 0
+                && start.elapsed() >= budget
+            {
+                stats.deferred += 1;
+                continue;
+            }
+            self.findings.insert(*key, scan(module));
+            stats.scanned += 1;
+        }
+        stats
+    }
+
+    // 收集 include 接受的模块结果，已存在的地址保留先前记录的来源
+    pub(super) fn collect(
+        &self,
+        include: impl Fn(&ModuleScanKey) -> bool,
+        out: &mut BTreeMap<usize, (CfiSymbolKind, CfiAddrSource)>,
+    ) {
+        for (_, findings) in self.findings.iter().filter(|(key, _)| include(key)) {
+            for (kind, addrs) in [
+                (CfiSymbolKind::Slowpath, &findings.slowpath),
+                (CfiSymbolKind::SlowpathDiag, &findings.diag),
+            ] {
+                for (addr, source) in addrs {
+                    out.entry(*addr).or_insert((kind, *source));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CfiScanCache, CfiScanStats, ModuleCfiFindings, ModuleScanKey};
+    use crate::api::{CfiAddrSource, CfiSymbolKind};
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::time::Duration;
+
+    fn module(base_addr: usize) -> (ModuleScanKey, usize) {
+        let key = ModuleScanKey {
+            base_addr,
+            instance_id: base_addr + 1,
+            file_id: (1, base_addr as u64),
+        };
+        (key, base_addr)
+    }
+
+    fn findings_for(base_addr: &usize) -> ModuleCfiFindings {
+        ModuleCfiFindings {
+            slowpath: vec![(*base_addr + 0x10, CfiAddrSource::ElfExport)],
+            diag: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn steady_state_pass_hits_cache_only() {
+        let mut cache = CfiScanCache::default();
+        let modules = [module(0x1000), module(0x2000), module(0x3000)];
+
+        let first = cache.update(&modules, &BTreeSet::new(), None, findings_for);
+        assert_eq!(
+            first,
+            CfiScanStats {
+                scanned: 3,
+                cached: 0,
+                deferred: 0
+            }
+        );
+
+        let mut scans = 0;
+        let second = cache.update(&modules, &BTreeSet::new(), None, |m| {
+            scans += 1;
+            findings_for(m)
+        });
+        assert_eq!(scans, 0);
+        assert_eq!(second.cached, 3);
+
+        let mut addrs = BTreeMap::new();
+        cache.collect(|_| true, &mut addrs);
+        assert_eq!(addrs.len(), 3);
+        assert!(
+            addrs
+                .values()
+                .all(|(kind, _)| *kind == CfiSymbolKind::Slowpath)
+        );
+    }
+
+    #[test]
+    fn exhausted_budget_defers_remaining_modules() {
+        let mut cache = CfiScanCache::default();
+        let modules = [module(0x1000), module(0x2000), module(0x3000)];
+
+        let first = cache.update(
+            &modules,
+            &BTreeSet::new(),
+            Some(Duration::ZERO),
+            findings_for,
+        );
+        assert_eq!((first.scanned, first.deferred), (1, 2));
+        let second = cache.update(
+            &modules,
+            &BTreeSet::new(),
+            Some(Duration::ZERO),
+            findings_for,
+        );
+        assert_eq!((second.scanned, second.cached, second.deferred), (1, 1, 1));
+        let third = cache.update(
+            &modules,
+            &BTreeSet::new(),
+            Some(Duration::ZERO),
+            findings_for,
+        );
+        assert_eq!((third.scanned, third.cached, third.deferred), (1, 2, 0));
+    }
+
+    #[test]
+    fn unloaded_modules_are_evicted_and_new_ones_scanned() {
+        let mut cache = CfiScanCache::default();
+        let _ = cache.update(
+            &[module(0x1000), module(0x2000)],
+            &BTreeSet::new(),
+            None,
+            findings_for,
+        );
+
+        let stats = cache.update(
+            &[module(0x2000), module(0x4000)],
+            &BTreeSet::new(),
+            None,
+            findings_for,
+        );
+        assert_eq!((stats.scanned, stats.cached), (1, 1));
+
+        let mut addrs = BTreeMap::new();
+        cache.collect(|_| true, &mut addrs);
+        assert_eq!(addrs.keys().copied().collect::<Vec<_>>(), [0x2010, 0x4010]);
+    }
+
+    // 同一基址、同一 name 指针重新加载了另一个文件时不能复用旧结果
+    #[test]
+    fn reload_at_same_base_with_other_file_is_rescanned() {
+        let mut cache = CfiScanCache::default();
+        let (key, base) = module(0x1000);
+        let _ = cache.update(&[(key, base)], &BTreeSet::new(), None, findings_for);
+
+        let reloaded = ModuleScanKey {
+            file_id: (1, 0x9999),
+            ..key
+        };
+        let stats = cache.update(
+            &[(reloaded, base + 0x100)],
+            &BTreeSet::new(),
+            None,
+            findings_for,
+        );
+        assert_eq!((stats.scanned, stats.cached), (1, 0));
+
+        let mut addrs = BTreeMap::new();
+        cache.collect(|_| true, &mut addrs);
+        assert_eq!(addrs.keys().copied().collect::<Vec<_>>(), [0x1110]);
+    }
+
+    // 同一地址既是导出又出现在其他模块的 import slot 中时保留先收集到的来源
+    #[test]
+    fn first_source_wins_for_shared_address() {
+        let mut cache = CfiScanCache::default();
+        let _ = cache.update(
+            &[module(0x1000), module(0x2000)],
+            &BTreeSet::new(),
+            None,
+            |base_addr| {
+                let source = if *base_addr == 0x1000 {
+                    CfiAddrSource::ElfExport
+                } else {
+                    CfiAddrSource::ImportSlot
+                };
+                ModuleCfiFindings {
+                    slowpath: vec![(0x1010, source)],
+                    diag: Vec::new(),
+                }
+            },
+        );
+
+        let mut addrs = BTreeMap::from([(0x1010, (CfiSymbolKind::Slowpath, CfiAddrSource::Dlsym))]);
+        cache.collect(|_| true, &mut addrs);
+        assert_eq!(
+            addrs[&0x1010],
+            (CfiSymbolKind::Slowpath, CfiAddrSource::Dlsym)
+        );
+
+        let mut addrs = BTreeMap::new();
+        cache.collect(|_| true, &mut addrs);
+        assert_eq!(addrs[&0x1010].1, CfiAddrSource::ElfExport);
+    }
+
+    // 忽略的模块不扫描也不淘汰，结果只对接受该模块的 collect 可见
+    #[test]
+    fn skipped_modules_keep_findings_without_rescan() {
+        let mut cache = CfiScanCache::default();
+        let modules = [module(0x1000), module(0x2000)];
+        let _ = cache.update(&modules, &BTreeSet::new(), None, findings_for);
+
+        let skip = BTreeSet::from([modules[0].0]);
+        let mut scans = 0;
+        let stats = cache.update(&modules, &skip, None, |m| {
+            scans += 1;
+            findings_for(m)
+        });
+        assert_eq!((scans, stats.cached), (0, 1));
+
+        let mut kept = BTreeMap::new();
+        cache.collect(|key| !skip.contains(key), &mut kept);
+        assert_eq!(kept.keys().copied().collect::<Vec<_>>(), [0x2010]);
+        let mut skipped = BTreeMap::new();
+        cache.collect(|key| skip.contains(key), &mut skipped);
+        assert_eq!(skipped.keys().copied().collect::<Vec<_>>(), [0x1010]);
+
+        let new_module = module(0x3000);
+        let skip = BTreeSet::from([new_module.0]);
+        let stats = cache.update(&[new_module], &skip, None, findings_for);
+        assert_eq!(stats, CfiScanStats::default());
+        let mut addrs = BTreeMap::new();
+        cache.collect(|_| true, &mut addrs);
+        assert!(addrs.is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+// 外部 proxy 编译期 ABI 版本跟踪
+// 记录进程内声明过的客户端 ABI 范围；客户端以其所在模块区分，声明了不兼容版本的模块中的 proxy 拒绝注册
+use crate::api::{ABI_VERSION, MIN_CLIENT_ABI_VERSION};
+use crate::errno::Errno;
+use crate::log;
+use crate::runtime::state::MutexPoisonRecover;
+use std::collections::BTreeSet;
+use std::ffi::c_void;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+pub(super) struct ClientAbiTracker {
+    // 可接受的客户端 ABI 区间
+    floor: u32,
+    current: u32,
+    // 未声明时 min 为 u32::MAX、max 为 0
+    min_seen: AtomicU32,
+    max_seen: AtomicU32,
+    // 最近一次声明不兼容的客户端模块基址，重新声明兼容版本后移除
+    rejected: Mutex<BTreeSet<usize>>,
+}
+
+impl ClientAbiTracker {
+    pub(super) const fn new(floor: u32, current: u32) -> Self {
+        Self {
+            floor,
+            current,
+            min_seen: AtomicU32::new(u32::MAX),
+            max_seen: AtomicU32::new(0),
+            rejected: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    // client 为客户端模块基址，0 表示无法识别，只记录范围
+    pub(super) fn declare(&self, version: u32, client: usize) -> Errno {
+        if version == 0 {
+            return Errno::InvalidArg;
+        }
+        self.min_seen.fetch_min(version, Ordering::AcqRel);
+        self.max_seen.fetch_max(version, Ordering::AcqRel);
+        let supported = (self.floor..=self.current).contains(&version);
+        if client != 0 {
+            let mut rejected = self.rejected.lock_or_poison();
+            if supported {
+                rejected.remove(&client);
+            } else {
+                rejected.insert(client);
+            }
+        }
+        if !supported {
+            log::warn(format_args!(
+                "client abi {} unsupported, runtime abi {} (min {}), client 0x{:x}",
+                version, self.current, self.floor, client
+            ));
+            return Errno::AbiMismatch;
+        }
+        Errno::Ok
+    }
+
+    pub(super) fn range(&self) -> Option<(u32, u32)> {
+        let max = self.max_seen.load(Ordering::Acquire);
+        if max == 0 {
+            return None;
+        }
+        Some((self.min_seen.load(Ordering::Acquire), max))
+    }
+
+    pub(super) fn registration_allowed(&self, client: usize) -> bool {
+        client == 0 || !self.rejected.lock_or_poison().contains(&client)
+    }
+}
+
+static CLIENT_ABI: ClientAbiTracker = ClientAbiTracker::new(MIN_CLIENT_ABI_VERSION, ABI_VERSION);
+
+// 地址所在模块的基址，无法解析时为 0
+fn client_module_base(addr: usize) -> usize {
+    if addr == 0 {
+        return 0;
+    }
+    let mut info: libc::Dl_info = unsafe { std::mem::zeroed() };
+    if unsafe { libc::dladdr(addr as *const c_void, &mut info) } == 0 {
+        return 0;
+    }
+    info.dli_fbase as usize
+}
+
+// client_addr 为客户端模块内的任意地址（通常是其 proxy 函数）
+pub(super) fn set_client_abi(version: u32, client_addr: usize) -> Errno {
+    CLIENT_ABI.declare(version, client_module_base(client_addr))
+}
+
+pub(super) fn get_client_abi_range() -> Option<(u32, u32)> {
+    CLIENT_ABI.range()
+}
+
+// 按 proxy 所在模块判断，只拒绝声明过不兼容版本的客户端
+pub(super) fn registration_allowed(new_func: usize) -> bool {
+    CLIENT_ABI.registration_allowed(client_module_base(new_func))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ClientAbiTracker;
+    use crate::errno::Errno;
+
+    const CLIENT_A: usize = 0x7000_0000;
+    const CLIENT_B: usize = 0x7100_0000;
+
+    #[test]
+    fn undeclared_client_is_allowed() {
+        let tracker = ClientAbiTracker::new(1, 2);
+        assert_eq!(tracker.range(), None);
+        assert!(tracker.registration_allowed(CLIENT_A));
+    }
+
+    #[test]
+    fn supported_clients_are_recorded() {
+        let tracker = ClientAbiTracker::new(1, 2);
+        assert_eq!(tracker.declare(2, CLIENT_A), Errno::Ok);
+        assert_eq!(tracker.declare(1, CLIENT_B), Errno::Ok);
+        assert_eq!(tracker.range(), Some((1, 2)));
+        assert!(tracker.registration_allowed(CLIENT_A));
+        assert!(tracker.registration_allowed(CLIENT_B));
+    }
+
+    #[test]
+    fn client_below_floor_blocks_only_itself() {
+        let tracker = ClientAbiTracker::new(2, 3);
+        assert_eq!(tracker.declare(3, CLIENT_A), Errno::Ok);
+        assert_eq!(tracker.declare(1, CLIENT_B), Errno::AbiMismatch);
+        assert_eq!(tracker.range(), Some((1, 3)));
+        assert!(tracker.registration_allowed(CLIENT_A));
+        assert!(!tracker.registration_allowed(CLIENT_B));
+    }
+
+    #[test]
+    fn client_newer_than_runtime_is_rejected() {
+        let tracker = ClientAbiTracker::new(1, 2);
+        assert_eq!(tracker.declare(3, CLIENT_A), Errno::AbiMismatch);
+        assert_eq!(tracker.range(), Some((3, 3)));
+        assert!(!tracker.registration_allowed(CLIENT_A));
+    }
+
+    #[test]
+    fn redeclaring_supported_version_clears_rejection() {
+        let tracker = ClientAbiTracker::new(2, 3);
+        assert_eq!(tracker.declare(1, CLIENT_A), Errno::AbiMismatch);
+        assert_eq!(tracker.declare(3, CLIENT_A), Errno::Ok);
+        assert!(tracker.registration_allowed(CLIENT_A));
+    }
+
+    #[test]
+    fn unidentified_client_only_updates_range() {
+        let tracker = ClientAbiTracker::new(2, 3);
+        assert_eq!(tracker.declare(1, 0), Errno::AbiMismatch);
+        assert_eq!(tracker.range(), Some((1, 1)));
+        assert!(tracker.registration_allowed(CLIENT_A));
+    }
+
+    #[test]
+    fn zero_version_is_invalid() {
+        let tracker = ClientAbiTracker::new(1, 2);
+        assert_eq!(tracker.declare(0, CLIENT_A), Errno::InvalidArg);
+        assert_eq!(tracker.range(), None);
+    }
+}
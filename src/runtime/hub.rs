@@ -5,27 +5,55 @@ use crate::runtime::state::MutexPoisonRecover;
 use once_cell::sync::Lazy;
 use std::ptr;
 use std::sync::Mutex;
-use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+mod fork_index;
+mod hit_stats;
 mod stack;
+#[cfg(test)]
+mod tests;
+mod tid_filter;
 mod trampoline;
 
 // 延迟销毁等待时间，确保仍在栈上的 trampoline 帧安全返回
 const HUB_DESTROY_DELAY_SEC: u64 = 10;
 
+// 当前生效的延迟，由 init_with_options 配置
+static HUB_DESTROY_DELAY: AtomicU64 = AtomicU64::new(HUB_DESTROY_DELAY_SEC);
+
+// drain_retired 轮询活跃栈帧计数的间隔
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
 // proxy 链表节点，ref_count 支持同一函数被多个 task 引用
+// paused 为其中已暂停的引用数，全部引用暂停时节点 disabled 但仍保留在链表中
+// stats 为开启命中统计的 task 挂上的统计块，未开启时为空
+// tid_filter 为限定线程的 task 挂上的线程集合，为空时对所有线程生效
+// 链表按 priority 降序排列，next 可在链中间插入新节点，读取方无锁遍历
 struct ProxyNode {
     func: usize,
+    priority: i32,
     ref_count: usize,
+    paused: usize,
     enabled: AtomicBool,
-    next: *mut ProxyNode,
+    stats: AtomicPtr<hit_stats::HitStats>,
+    tid_filter: AtomicPtr<tid_filter::TidFilter>,
+    next: AtomicPtr<ProxyNode>,
+}
+
+impl ProxyNode {
+    #[inline]
+    fn next(&self) -> *mut ProxyNode {
+        self.next.load(Ordering::Acquire)
+    }
 }
 
 // Hub 核心结构：orig_addr 为原始函数地址，trampo 为 trampoline 代码地址
+// tail 为 proxy 链之后调用的函数，默认即 orig_addr，slot 巡检接入外来 hook 后改为其入口
 // head 为 proxy 链表头，采用无锁读和有锁写
 pub(super) struct Hub {
     pub(super) orig_addr: usize,
+    tail: AtomicUsize,
     pub(super) trampo: usize,
     head: AtomicPtr<ProxyNode>,
     lock: Mutex<()>,
@@ -74,7 +102,7 @@ unsafe fn destroy_hub_now(hub_ptr: *mut Hub) {
     let hub = unsafe { Box::from_raw(hub_ptr) };
     let mut node = hub.head.load(Ordering::Acquire);
     while !node.is_null() {
-        let next = unsafe { (*node).next };
+        let next = unsafe { (*node).next() };
         unsafe {
             drop(Box::from_raw(node));
         }
@@ -85,18 +113,52 @@ unsafe fn destroy_hub_now(hub_ptr: *mut Hub) {
     }
 }
 
+pub(super) fn set_rwx_trampolines(flag: bool) {
+    trampoline::set_rwx_mode(flag);
+}
+
+// None 恢复默认值
+pub(super) fn set_destroy_delay(secs: Option<u64>) {
+    HUB_DESTROY_DELAY.store(secs.unwrap_or(HUB_DESTROY_DELAY_SEC), Ordering::Relaxed);
+}
+
 // 回收已过期的 retired hub；force=true 时无视延迟和活跃帧计数
 pub(super) fn collect_retired(force: bool) {
+    let delay = if force {
+        0
+    } else {
+        HUB_DESTROY_DELAY.load(Ordering::Relaxed)
+    };
+    let _ = collect_retired_after(delay, force);
+}
+
+// 等待全局活跃栈帧归零后不再等待延迟，回收全部 retired hub
+// 超时仍有帧未弹出时保留这些 hub 交给后续周期回收；返回是否已全部释放
+pub(super) fn drain_retired(timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if active_stack_frames() == 0 && collect_retired_after(0, false) {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return RETIRED_HUBS.lock_or_poison().is_empty();
+        }
+        std::thread::sleep(DRAIN_POLL_INTERVAL);
+    }
+}
+
+// 回收退役超过 delay 秒的 hub；ignore_frames 为 false 时存在活跃栈帧则不回收
+// 返回回收后是否已无 retired hub
+fn collect_retired_after(delay: u64, ignore_frames: bool) -> bool {
     let now = now_sec();
     let active_frames = active_stack_frames();
     let mut ready = Vec::new();
-    {
+    let drained = {
         let mut retired = RETIRED_HUBS.lock_or_poison();
         let mut idx = 0;
         while idx < retired.len() {
-            let expired = force
-                || (active_frames == 0
-                    && now.saturating_sub(retired[idx].ts) >= HUB_DESTROY_DELAY_SEC);
+            let expired = (ignore_frames || active_frames == 0)
+                && now.saturating_sub(retired[idx].ts) >= delay;
             if expired {
                 let item = retired.swap_remove(idx);
                 ready.push(item.hub_ptr as *mut Hub);
@@ -104,31 +166,55 @@ pub(super) fn collect_retired(force: bool) {
                 idx += 1;
             }
         }
-    }
+        retired.is_empty()
+    };
     for hub_ptr in ready {
         unsafe {
             destroy_hub_now(hub_ptr);
         }
     }
+    tid_filter::collect(now, delay);
+    drained
 }
 
-// 创建 Hub：分配 trampoline 并绑定 push/pop 回调
-pub(super) fn create_hub(orig_addr: usize) -> Result<*mut Hub, Errno> {
-    collect_retired(false);
-
-    let hub = Box::new(Hub {
+// 构造未发布的 Hub，首个 proxy 直接挂在链表头，无需经过加锁路径
+fn new_hub(orig_addr: usize, first_proxy: usize, priority: i32) -> Box<Hub> {
+    let node = Box::new(ProxyNode {
+        func: first_proxy,
+        priority,
+        ref_count: 1,
+        paused: 0,
+        enabled: AtomicBool::new(true),
+        stats: AtomicPtr::new(ptr::null_mut()),
+        tid_filter: AtomicPtr::new(ptr::null_mut()),
+        next: AtomicPtr::new(ptr::null_mut()),
+    });
+    Box::new(Hub {
         orig_addr,
+        tail: AtomicUsize::new(orig_addr),
         trampo: 0,
-        head: AtomicPtr::new(ptr::null_mut()),
+        head: AtomicPtr::new(Box::into_raw(node)),
         lock: Mutex::new(()),
-    });
-    let hub_ptr = Box::into_raw(hub);
+    })
+}
 
-    let trampo = match trampoline::alloc_trampo() {
+// 创建 Hub：预置首个 proxy，分配 trampoline 并绑定 push/pop 回调
+// retired hub 的回收由 refresh 和 monitor 周期路径统一处理，不在每次创建时进行
+pub(super) fn create_hub(
+    orig_addr: usize,
+    first_proxy: usize,
+    priority: i32,
+) -> Result<*mut Hub, Errno> {
+    if first_proxy == 0 {
+        return Err(Errno::InvalidArg);
+    }
+    let hub_ptr = Box::into_raw(new_hub(orig_addr, first_proxy, priority));
+
+    let slot = match trampoline::alloc_trampo() {
         Ok(value) => value,
         Err(err) => {
             unsafe {
-                drop(Box::from_raw(hub_ptr));
+                destroy_hub_now(hub_ptr);
             }
             return Err(err);
         }
@@ -136,31 +222,39 @@ pub(super) fn create_hub(orig_addr: usize) -> Result<*mut Hub, Errno> {
 
     let init_result = unsafe {
         trampoline::init_trampo(
-            trampo,
+            &slot,
             hub_ptr as usize,
             stack::hub_push_stack as *const () as usize,
             stack::hub_pop_stack as *const () as usize,
         )
     };
     if let Err(err) = init_result {
-        trampoline::free_trampo(trampo);
+        trampoline::free_trampo(slot.exec);
         unsafe {
-            drop(Box::from_raw(hub_ptr));
+            destroy_hub_now(hub_ptr);
         }
         return Err(err);
     }
 
     unsafe {
-        (*hub_ptr).trampo = trampo;
+        (*hub_ptr).trampo = slot.exec;
     }
+    fork_index::insert(first_proxy, hub_ptr as usize);
     Ok(hub_ptr)
 }
 
+// fork prepare 中调用：发布供子进程解析 prev_func 的 proxy 索引，并停止复用 fork 前的共享 trampoline 页
+pub(super) fn prepare_fork() {
+    fork_index::publish();
+    trampoline::note_fork();
+}
+
 pub(super) fn destroy_hub(hub_ptr: *mut Hub, with_delay: bool) {
     if hub_ptr.is_null() {
         return;
     }
 
+    fork_index::remove_hub(hub_ptr as usize);
     collect_retired(false);
     if with_delay {
         let mut retired = RETIRED_HUBS.lock_or_poison();
@@ -176,6 +270,44 @@ pub(super) fn destroy_hub(hub_ptr: *mut Hub, with_delay: bool) {
     }
 }
 
+impl Hub {
+    #[inline]
+    fn tail(&self) -> usize {
+        self.tail.load(Ordering::Acquire)
+    }
+}
+
+// 把 proxy 链尾换成外来 hook 的入口，递归回到本 hub 时仍走 orig_addr
+pub(super) fn set_tail(hub_ptr: *mut Hub, tail: usize) {
+    if hub_ptr.is_null() || tail == 0 {
+        return;
+    }
+    unsafe { (*hub_ptr).tail.store(tail, Ordering::Release) };
+}
+
+pub(super) fn hub_tail(hub_ptr: *mut Hub) -> usize {
+    if hub_ptr.is_null() {
+        return 0;
+    }
+    unsafe { (*hub_ptr).tail() }
+}
+
+pub(super) fn hub_orig(hub_ptr: *mut Hub) -> usize {
+    if hub_ptr.is_null() {
+        return 0;
+    }
+    unsafe { (*hub_ptr).orig_addr }
+}
+
+// 链尾已接入外来 hook：对方保存的原值可能就是本 hub 的 trampoline，hub 不能随 proxy 清空而销毁
+pub(super) fn has_foreign_tail(hub_ptr: *mut Hub) -> bool {
+    if hub_ptr.is_null() {
+        return false;
+    }
+    let hub = unsafe { &*hub_ptr };
+    hub.tail() != hub.orig_addr
+}
+
 pub(super) fn hub_trampo(hub_ptr: *mut Hub) -> usize {
     if hub_ptr.is_null() {
         return 0;
@@ -183,8 +315,10 @@ pub(super) fn hub_trampo(hub_ptr: *mut Hub) -> usize {
     unsafe { (*hub_ptr).trampo }
 }
 
-// 向 Hub 添加 proxy 函数；已存在则增加引用计数并重新启用
-pub(super) fn add_proxy(hub_ptr: *mut Hub, proxy_func: usize) -> Errno {
+// 向 Hub 添加 proxy 函数；已存在则增加引用计数并重新启用，节点位置与优先级保持不变
+// 新节点插在首个优先级不高于它的节点之前：优先级高的先执行，同优先级后注册的先执行
+// 节点先链好 next 再以 Release 发布，无锁遍历的读取方只会看到插入前或插入后的完整链表
+pub(super) fn add_proxy(hub_ptr: *mut Hub, proxy_func: usize, priority: i32) -> Errno {
     if hub_ptr.is_null() || proxy_func == 0 {
         return Errno::InvalidArg;
     }
@@ -200,17 +334,31 @@ pub(super) fn add_proxy(hub_ptr: *mut Hub, proxy_func: usize) -> Errno {
             node.enabled.store(true, Ordering::SeqCst);
             return Errno::Ok;
         }
-        cursor = node.next;
+        cursor = node.next();
     }
 
-    let node = Box::new(ProxyNode {
+    let mut prev: *mut ProxyNode = ptr::null_mut();
+    let mut next = hub.head.load(Ordering::Acquire);
+    while !next.is_null() && unsafe { (*next).priority } > priority {
+        prev = next;
+        next = unsafe { (*next).next() };
+    }
+    let node_ptr = Box::into_raw(Box::new(ProxyNode {
         func: proxy_func,
+        priority,
         ref_count: 1,
+        paused: 0,
         enabled: AtomicBool::new(true),
-        next: hub.head.load(Ordering::Acquire),
-    });
-    let node_ptr = Box::into_raw(node);
-    hub.head.store(node_ptr, Ordering::Release);
+        stats: AtomicPtr::new(ptr::null_mut()),
+        tid_filter: AtomicPtr::new(ptr::null_mut()),
+        next: AtomicPtr::new(next),
+    }));
+    if prev.is_null() {
+        hub.head.store(node_ptr, Ordering::Release);
+    } else {
+        unsafe { (*prev).next.store(node_ptr, Ordering::Release) };
+    }
+    fork_index::insert(proxy_func, hub_ptr as usize);
     Errno::Ok
 }
 
@@ -229,28 +377,31 @@ pub(super) fn del_proxy(hub_ptr: *mut Hub, proxy_func: usize) -> (Errno, bool) {
     let mut cursor = hub.head.load(Ordering::Acquire);
     while !cursor.is_null() {
         let node = unsafe { &mut *cursor };
-        if node.func == proxy_func && node.enabled.load(Ordering::Acquire) {
-            if node.ref_count > 1 {
-                node.ref_count -= 1;
-            } else {
-                node.ref_count = 0;
-                node.enabled.store(false, Ordering::SeqCst);
+        if node.func == proxy_func && node.ref_count > 0 {
+            node.ref_count -= 1;
+            node.paused = node.paused.min(node.ref_count);
+            // 节点之后可能被未限定线程的任务复用，集合本身由所属任务负责释放
+            if node.ref_count == 0 {
+                node.tid_filter.store(ptr::null_mut(), Ordering::Release);
             }
+            node.enabled
+                .store(node.ref_count > node.paused, Ordering::SeqCst);
             deleted = true;
             break;
         }
-        cursor = node.next;
+        cursor = node.next();
     }
 
+    // 仅暂停的 proxy 仍占用 hub，不能随之销毁
     let mut have_enabled_proxy = false;
     let mut scan = hub.head.load(Ordering::Acquire);
     while !scan.is_null() {
         let node = unsafe { &*scan };
-        if node.enabled.load(Ordering::Acquire) {
+        if node.ref_count > 0 {
             have_enabled_proxy = true;
             break;
         }
-        scan = node.next;
+        scan = node.next();
     }
 
     if deleted {
@@ -259,6 +410,205 @@ pub(super) fn del_proxy(hub_ptr: *mut Hub, proxy_func: usize) -> (Errno, bool) {
     (Errno::NotFound, have_enabled_proxy)
 }
 
+// 暂停或恢复 proxy 的一个引用，不改变 ref_count；仍有未暂停的引用时节点保持启用
+pub(super) fn set_proxy_paused(hub_ptr: *mut Hub, proxy_func: usize, paused: bool) -> Errno {
+    if hub_ptr.is_null() || proxy_func == 0 {
+        return Errno::InvalidArg;
+    }
+
+    let hub = unsafe { &*hub_ptr };
+    let _guard = hub.lock.lock_or_poison();
+
+    let mut cursor = hub.head.load(Ordering::Acquire);
+    while !cursor.is_null() {
+        let node = unsafe { &mut *cursor };
+        if node.func == proxy_func && node.ref_count > 0 {
+            if paused {
+                node.paused = (node.paused + 1).min(node.ref_count);
+            } else {
+                node.paused = node.paused.saturating_sub(1);
+            }
+            node.enabled
+                .store(node.ref_count > node.paused, Ordering::SeqCst);
+            return Errno::Ok;
+        }
+        cursor = node.next();
+    }
+    Errno::NotFound
+}
+
+// 挂上或摘下 proxy 节点的命中统计块；摘下时只清除仍指向该块的节点，共用 proxy 的其他 task 不受影响
+pub(super) fn set_proxy_hit_stats(
+    hub_ptr: *mut Hub,
+    proxy_func: usize,
+    stats: usize,
+    attach: bool,
+) {
+    if hub_ptr.is_null() || proxy_func == 0 || stats == 0 {
+        return;
+    }
+
+    let hub = unsafe { &*hub_ptr };
+    let _guard = hub.lock.lock_or_poison();
+
+    let stats = stats as *mut hit_stats::HitStats;
+    let mut cursor = hub.head.load(Ordering::Acquire);
+    while !cursor.is_null() {
+        let node = unsafe { &*cursor };
+        if node.func == proxy_func && node.ref_count > 0 {
+            if attach {
+                node.stats.store(stats, Ordering::Release);
+            } else {
+                let _ = node.stats.compare_exchange(
+                    stats,
+                    ptr::null_mut(),
+                    Ordering::AcqRel,
+                    Ordering::Relaxed,
+                );
+            }
+            return;
+        }
+        cursor = node.next();
+    }
+}
+
+pub(super) fn acquire_hit_stats() -> usize {
+    hit_stats::acquire() as usize
+}
+
+pub(super) fn release_hit_stats(stats: usize) {
+    hit_stats::release(stats as *mut hit_stats::HitStats);
+}
+
+pub(super) fn hit_stats_snapshot(stats: usize) -> crate::api::HookStats {
+    unsafe { (*(stats as *const hit_stats::HitStats)).snapshot() }
+}
+
+// 挂上或摘下 proxy 节点的线程集合，语义同 set_proxy_hit_stats；挂上时直接替换节点上已有的集合
+pub(super) fn set_proxy_tid_filter(
+    hub_ptr: *mut Hub,
+    proxy_func: usize,
+    filter: usize,
+    attach: bool,
+) {
+    if hub_ptr.is_null() || proxy_func == 0 || filter == 0 {
+        return;
+    }
+
+    let hub = unsafe { &*hub_ptr };
+    let _guard = hub.lock.lock_or_poison();
+
+    let filter = filter as *mut tid_filter::TidFilter;
+    let mut cursor = hub.head.load(Ordering::Acquire);
+    while !cursor.is_null() {
+        let node = unsafe { &*cursor };
+        if node.func == proxy_func && node.ref_count > 0 {
+            if attach {
+                node.tid_filter.store(filter, Ordering::Release);
+            } else {
+                let _ = node.tid_filter.compare_exchange(
+                    filter,
+                    ptr::null_mut(),
+                    Ordering::AcqRel,
+                    Ordering::Relaxed,
+                );
+            }
+            return;
+        }
+        cursor = node.next();
+    }
+}
+
+pub(super) fn create_tid_filter(tids: &[i32]) -> usize {
+    tid_filter::create(tids) as usize
+}
+
+// 集合可能仍被正在选择 proxy 的线程读取，按 hub 销毁延迟释放
+pub(super) fn retire_tid_filter(filter: usize) {
+    tid_filter::retire(filter as *mut tid_filter::TidFilter, now_sec());
+}
+
+// 节点未限定线程或当前线程在集合内时放行
+#[inline]
+fn allows_thread(node: &ProxyNode, tid: i32) -> bool {
+    let filter = node.tid_filter.load(Ordering::Acquire);
+    filter.is_null() || unsafe { (*filter).allows(tid) }
+}
+
+// 节点带统计块时计一次命中，未开启统计只多一次读取
+#[inline]
+fn note_proxy_hit(node: &ProxyNode) {
+    let stats = node.stats.load(Ordering::Acquire);
+    if !stats.is_null() {
+        unsafe { (*stats).record_hit() };
+    }
+}
+
+// 按调用顺序列出启用中的 proxy，仅读取链表
+pub(super) fn enabled_proxies(hub_ptr: *mut Hub) -> Vec<usize> {
+    let mut proxies = Vec::new();
+    if hub_ptr.is_null() {
+        return proxies;
+    }
+    let hub = unsafe { &*hub_ptr };
+    let mut cursor = hub.head.load(Ordering::Acquire);
+    while !cursor.is_null() {
+        let node = unsafe { &*cursor };
+        if node.enabled.load(Ordering::Acquire) {
+            proxies.push(node.func);
+        }
+        cursor = node.next();
+    }
+    proxies
+}
+
+// 在 hub 锁内按 trampoline 遍历顺序读取全部节点：(proxy, 是否启用, 引用计数, 优先级)
+// 链表读取与 first_enabled 一样使用 Acquire，调用进行中也可安全读取
+pub(super) fn proxy_chain(hub_ptr: *mut Hub) -> Vec<(usize, bool, usize, i32)> {
+    let mut nodes = Vec::new();
+    if hub_ptr.is_null() {
+        return nodes;
+    }
+    let hub = unsafe { &*hub_ptr };
+    let _guard = hub.lock.lock_or_poison();
+    let mut cursor = hub.head.load(Ordering::Acquire);
+    while !cursor.is_null() {
+        let node = unsafe { &*cursor };
+        nodes.push((
+            node.func,
+            node.enabled.load(Ordering::Acquire),
+            node.ref_count,
+            node.priority,
+        ));
+        cursor = node.next();
+    }
+    nodes
+}
+
+// 按链表顺序列出全部 proxy 及其是否启用，供状态转储使用
+pub(super) fn proxy_nodes(hub_ptr: *mut Hub) -> Vec<(usize, bool)> {
+    let mut nodes = Vec::new();
+    if hub_ptr.is_null() {
+        return nodes;
+    }
+    let hub = unsafe { &*hub_ptr };
+    let mut cursor = hub.head.load(Ordering::Acquire);
+    while !cursor.is_null() {
+        let node = unsafe { &*cursor };
+        nodes.push((node.func, node.enabled.load(Ordering::Acquire)));
+        cursor = node.next();
+    }
+    nodes
+}
+
+pub(super) fn retired_count() -> usize {
+    RETIRED_HUBS.lock_or_poison().len()
+}
+
+pub(super) fn active_frame_count() -> usize {
+    active_stack_frames()
+}
+
 pub(super) fn first_enabled(hub_ptr: *mut Hub) -> usize {
     if hub_ptr.is_null() {
         return 0;
@@ -270,19 +620,36 @@ pub(super) fn first_enabled(hub_ptr: *mut Hub) -> usize {
         if node.enabled.load(Ordering::Acquire) {
             return node.func;
         }
-        cursor = node.next;
+        cursor = node.next();
     }
-    hub.orig_addr
+    hub.tail()
 }
 
 pub(super) fn get_prev_func(func: *mut std::ffi::c_void) -> *mut std::ffi::c_void {
     stack::get_prev_func(func)
 }
 
+// fork 子进程中 get_prev_func 走索引回退的累计次数
+pub(super) fn fork_prev_fallback_count() -> u64 {
+    fork_index::fallback_count()
+}
+
+pub(super) fn set_stack_limits(limits: Option<(usize, usize)>) {
+    stack::set_limits(limits);
+}
+
+pub(super) fn stack_stats() -> crate::api::HubStackStats {
+    stack::stats()
+}
+
 pub(super) fn get_return_address() -> *mut std::ffi::c_void {
     stack::get_return_address()
 }
 
+pub(super) fn backtrace(buf: &mut [usize]) -> usize {
+    stack::backtrace(buf)
+}
+
 pub(super) fn pop_stack(return_address: *mut std::ffi::c_void) {
     stack::pop_stack_by_return_address(return_address)
 }
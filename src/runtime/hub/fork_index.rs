@@ -0,0 +1,155 @@
+// proxy 函数到 Hub 的全局索引，供 fork 子进程在线程状态不可用时解析 prev_func
+// 父进程增删 proxy 时只维护有序条目，fork prepare 时才重建只读快照；子进程只读快照，不加锁也不分配内存
+// 父进程自身从不读取快照，因此 prepare 中可以直接释放旧快照
+// 局限：无递归检测；同一 proxy 挂在多个 Hub 上时取首个包含它的 Hub
+use crate::runtime::state::MutexPoisonRecover;
+use once_cell::sync::Lazy;
+use std::ptr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, Ordering};
+
+use super::{Hub, ProxyNode};
+
+// 按 func 排序的 (func, hub_ptr) 列表
+type Snapshot = Box<[(usize, usize)]>;
+
+static ENTRIES: Lazy<Mutex<Vec<(usize, usize)>>> = Lazy::new(|| Mutex::new(Vec::new()));
+static SNAPSHOT: AtomicPtr<Snapshot> = AtomicPtr::new(ptr::null_mut());
+static FALLBACK_HITS: AtomicU64 = AtomicU64::new(0);
+// 条目自上次发布后有变化
+static DIRTY: AtomicBool = AtomicBool::new(false);
+
+// 由 fork prepare 调用：条目有变化时重建快照并替换，子进程看到的是 fork 时刻的条目
+// 未发布的变化只可能是已退役的 Hub 仍留在旧快照中，因此每次 fork 前都必须检查
+pub(super) fn publish() {
+    let entries = ENTRIES.lock_or_poison();
+    if !DIRTY.swap(false, Ordering::AcqRel) {
+        return;
+    }
+    let snapshot: Snapshot = entries.clone().into_boxed_slice();
+    let old = SNAPSHOT.swap(Box::into_raw(Box::new(snapshot)), Ordering::AcqRel);
+    if !old.is_null() {
+        unsafe {
+            drop(Box::from_raw(old));
+        }
+    }
+}
+
+// 二分插入保持有序，重复条目忽略
+pub(super) fn insert(func: usize, hub_ptr: usize) {
+    let mut entries = ENTRIES.lock_or_poison();
+    if let Err(pos) = entries.binary_search(&(func, hub_ptr)) {
+        entries.insert(pos, (func, hub_ptr));
+        DIRTY.store(true, Ordering::Release);
+    }
+}
+
+// Hub 退役时移除其全部条目，之后 fork 出的子进程不会再访问该 Hub
+pub(super) fn remove_hub(hub_ptr: usize) {
+    let mut entries = ENTRIES.lock_or_poison();
+    let before = entries.len();
+    entries.retain(|&(_, hub)| hub != hub_ptr);
+    if entries.len() != before {
+        DIRTY.store(true, Ordering::Release);
+    }
+}
+
+// 在链表中定位 func，返回其后首个启用的 proxy，链尾返回 tail
+fn prev_in_chain(hub: &Hub, func: usize) -> Option<usize> {
+    let mut cursor: *mut ProxyNode = hub.head.load(Ordering::Acquire);
+    let mut found = false;
+    while !cursor.is_null() {
+        let node = unsafe { &*cursor };
+        if found && node.enabled.load(Ordering::Acquire) {
+            return Some(node.func);
+        }
+        if node.func == func {
+            found = true;
+        }
+        cursor = node.next();
+    }
+    found.then_some(hub.tail())
+}
+
+pub(super) fn resolve_prev_func(func: usize) -> Option<usize> {
+    let snapshot = SNAPSHOT.load(Ordering::Acquire);
+    if snapshot.is_null() {
+        return None;
+    }
+    let entries = unsafe { &**snapshot };
+    let start = entries.partition_point(|&(entry_func, _)| entry_func < func);
+    entries[start..]
+        .iter()
+        .take_while(|&&(entry_func, _)| entry_func == func)
+        .find_map(|&(_, hub_ptr)| prev_in_chain(unsafe { &*(hub_ptr as *const Hub) }, func))
+}
+
+// 子进程内只计数不打日志，避免格式化分配
+pub(super) fn note_fallback() {
+    FALLBACK_HITS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(super) fn fallback_count() -> u64 {
+    FALLBACK_HITS.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Hub, ProxyNode};
+    use super::{insert, publish, remove_hub, resolve_prev_func};
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize};
+
+    fn make_node(func: usize, enabled: bool, next: *mut ProxyNode) -> *mut ProxyNode {
+        Box::into_raw(Box::new(ProxyNode {
+            func,
+            priority: 0,
+            ref_count: 1,
+            paused: 0,
+            enabled: AtomicBool::new(enabled),
+            stats: AtomicPtr::new(std::ptr::null_mut()),
+            tid_filter: AtomicPtr::new(std::ptr::null_mut()),
+            next: AtomicPtr::new(next),
+        }))
+    }
+
+    fn make_hub(orig_addr: usize, head: *mut ProxyNode) -> Box<Hub> {
+        Box::new(Hub {
+            orig_addr,
+            tail: AtomicUsize::new(orig_addr),
+            trampo: 0,
+            head: AtomicPtr::new(head),
+            lock: Mutex::new(()),
+        })
+    }
+
+    #[test]
+    fn resolves_next_enabled_proxy_then_orig() {
+        let tail = make_node(0xf0c3, true, std::ptr::null_mut());
+        let middle = make_node(0xf0c2, false, tail);
+        let head = make_node(0xf0c1, true, middle);
+        let hub = make_hub(0xf0c0, head);
+        let hub_ptr = &*hub as *const Hub as usize;
+        for func in [0xf0c3, 0xf0c1, 0xf0c2, 0xf0c1] {
+            insert(func, hub_ptr);
+        }
+        publish();
+
+        // 跳过已禁用的节点
+        assert_eq!(resolve_prev_func(0xf0c1), Some(0xf0c3));
+        assert_eq!(resolve_prev_func(0xf0c2), Some(0xf0c3));
+        assert_eq!(resolve_prev_func(0xf0c3), Some(0xf0c0));
+        assert_eq!(resolve_prev_func(0xf0c4), None);
+
+        // 移除在下一次 fork 前发布
+        remove_hub(hub_ptr);
+        publish();
+        assert_eq!(resolve_prev_func(0xf0c1), None);
+        drop(hub);
+        unsafe {
+            drop(Box::from_raw(head));
+            drop(Box::from_raw(middle));
+            drop(Box::from_raw(tail));
+        }
+    }
+}
@@ -0,0 +1,106 @@
+// proxy 命中统计块，由 hub_push_stack / get_prev_func 在选中带统计的节点时更新
+// 统计块卸载后进入空闲列表复用而不释放：延迟销毁的 hub 上仍可能有调用写入，计数允许这点误差
+use crate::api::HookStats;
+use crate::runtime::state::MutexPoisonRecover;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+// 线程去重表容量，超出后不再计入新线程
+const THREAD_SLOTS: usize = 64;
+
+pub(super) struct HitStats {
+    hits: AtomicU64,
+    last_hit_ns: AtomicU64,
+    threads: AtomicUsize,
+    thread_ids: [AtomicUsize; THREAD_SLOTS],
+}
+
+static FREE_STATS: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+impl HitStats {
+    fn new() -> Self {
+        Self {
+            hits: AtomicU64::new(0),
+            last_hit_ns: AtomicU64::new(0),
+            threads: AtomicUsize::new(0),
+            thread_ids: std::array::from_fn(|_| AtomicUsize::new(0)),
+        }
+    }
+
+    fn reset(&self) {
+        self.hits.store(0, Ordering::Relaxed);
+        self.last_hit_ns.store(0, Ordering::Relaxed);
+        self.threads.store(0, Ordering::Relaxed);
+        for slot in &self.thread_ids {
+            slot.store(0, Ordering::Relaxed);
+        }
+    }
+
+    #[inline]
+    pub(super) fn record_hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        self.last_hit_ns.store(monotonic_ns(), Ordering::Relaxed);
+        self.note_thread(unsafe { libc::pthread_self() } as usize);
+    }
+
+    // 开放寻址登记线程，命中已登记线程时只需一次读取
+    fn note_thread(&self, thread_id: usize) {
+        let hash = thread_id.wrapping_mul(0x9e37_79b9_7f4a_7c15_u64 as usize);
+        let start = hash >> (usize::BITS - THREAD_SLOTS.trailing_zeros());
+        for offset in 0..THREAD_SLOTS {
+            let slot = &self.thread_ids[(start + offset) % THREAD_SLOTS];
+            let current = slot.load(Ordering::Relaxed);
+            if current == thread_id {
+                return;
+            }
+            if current != 0 {
+                continue;
+            }
+            match slot.compare_exchange(0, thread_id, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => {
+                    self.threads.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                Err(value) if value == thread_id => return,
+                Err(_) => {}
+            }
+        }
+    }
+
+    pub(super) fn snapshot(&self) -> HookStats {
+        HookStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            last_hit_ns: self.last_hit_ns.load(Ordering::Relaxed),
+            threads: self.threads.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn monotonic_ns() -> u64 {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts);
+    }
+    (ts.tv_sec as u64)
+        .saturating_mul(1_000_000_000)
+        .saturating_add(ts.tv_nsec as u64)
+}
+
+pub(super) fn acquire() -> *mut HitStats {
+    if let Some(stats_ptr) = FREE_STATS.lock_or_poison().pop() {
+        let stats = stats_ptr as *mut HitStats;
+        unsafe { (*stats).reset() };
+        return stats;
+    }
+    Box::into_raw(Box::new(HitStats::new()))
+}
+
+pub(super) fn release(stats: *mut HitStats) {
+    if stats.is_null() {
+        return;
+    }
+    FREE_STATS.lock_or_poison().push(stats as usize);
+}
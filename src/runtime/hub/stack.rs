@@ -1,13 +1,14 @@
 // 线程级 Hub 调用栈，追踪 trampoline 的嵌套调用关系
 // 用于 get_prev_func 链式调用和 return address 恢复
+use crate::android::memory;
+use crate::api::HubStackStats;
 use crate::runtime::thread_state;
 use std::ffi::c_void;
 use std::ptr;
 use std::sync::atomic::Ordering;
 
 pub(super) use crate::runtime::thread_state::HubFrame;
-
-type HubStack = thread_state::FixedStack<HubFrame, { thread_state::HUB_STACK_CAP }>;
+use crate::runtime::thread_state::HubStack;
 
 // 读取当前硬件栈指针，用于检测过期帧
 #[inline(always)]
@@ -41,10 +42,7 @@ fn current_stack_pointer() -> usize {
 #[inline]
 fn prune_stale_frames(stack: &mut HubStack, current_sp: usize) {
     let mut popped = 0usize;
-    loop {
-        let Some(frame) = stack.last() else {
-            break;
-        };
+    while let Some(frame) = stack.last() {
         if frame.stack_sp > current_sp {
             break;
         }
@@ -61,7 +59,19 @@ fn with_hub_stack_mut<R, F>(site: &'static str, f: F) -> Option<R>
 where
     F: FnOnce(&mut HubStack) -> R,
 {
-    let result = thread_state::with_thread_state(|state| f(state.hub_stack_mut()));
+    with_hub_stack_tid_mut(site, |stack, _| f(stack))
+}
+
+// 同 with_hub_stack_mut，额外提供当前线程 tid
+#[inline]
+fn with_hub_stack_tid_mut<R, F>(site: &'static str, f: F) -> Option<R>
+where
+    F: FnOnce(&mut HubStack, i32) -> R,
+{
+    let result = thread_state::with_thread_state(|state| {
+        let (stack, tid) = state.hub_stack_with_tid();
+        f(stack, tid)
+    });
     if result.is_none() && !thread_state::should_skip_thread_state() {
         thread_state::report_thread_state_unavailable(site);
     }
@@ -69,7 +79,9 @@ where
 }
 
 // trampoline 入口回调：清理过期帧、检测递归、查找首个活跃 proxy 并压栈
-// 同一 hub_id 已在栈中时回退到 orig_addr 防止无限递归
+// 当前线程关闭 hook 时跳过 proxy；限定了线程的 proxy 对其他线程视为未启用
+// 同一 hub_id 已在栈中时回退到 orig_addr 防止无限递归（含接入的外来 hook 回调本 hub）
+// 链尾为外来 hook 时即使不经过 proxy 也压入直通帧，使其回调本 hub 能被识别；无法记录时直接走 orig_addr
 pub(super) unsafe extern "C" fn hub_push_stack(
     hub_ptr: *mut super::Hub,
     return_addr: *mut c_void,
@@ -78,12 +90,19 @@ pub(super) unsafe extern "C" fn hub_push_stack(
         return ptr::null_mut();
     }
     let hub = unsafe { &*hub_ptr };
+    let tail = hub.tail();
+    let foreign_tail = tail != hub.orig_addr;
+    let hook_disabled = thread_state::is_thread_hook_disabled();
+    // 当前线程关闭了 hook 且链尾就是原函数：不压栈，直接转发
+    if hook_disabled && !foreign_tail {
+        return tail as *mut c_void;
+    }
     let hub_id = hub_ptr as usize;
     let current_sp = current_stack_pointer();
-    let mut next_func = hub.orig_addr;
+    let mut next_func = tail;
     let head = hub.head.load(Ordering::Acquire);
 
-    let _ = with_hub_stack_mut("hub_push_stack", |stack| {
+    let tracked = with_hub_stack_tid_mut("hub_push_stack", |stack, tid| {
         prune_stale_frames(stack, current_sp);
 
         let mut idx = 0usize;
@@ -97,37 +116,50 @@ pub(super) unsafe extern "C" fn hub_push_stack(
             idx += 1;
         }
 
-        let mut cursor = head;
+        let mut cursor = if hook_disabled { ptr::null_mut() } else { head };
         while !cursor.is_null() {
             let node = unsafe { &*cursor };
-            if node.enabled.load(Ordering::Acquire) {
+            if node.enabled.load(Ordering::Acquire) && super::allows_thread(node, tid) {
                 next_func = node.func;
                 break;
             }
-            cursor = node.next;
+            cursor = node.next();
         }
 
-        if next_func == hub.orig_addr {
+        if next_func == tail && !foreign_tail {
             return;
         }
 
+        // 直通帧不对应任何 proxy，head_ptr 与 first_proxy 置 0
+        let (head_ptr, first_proxy) = if cursor.is_null() {
+            (0, 0)
+        } else {
+            (head as usize, next_func)
+        };
         let pushed = stack.push(HubFrame {
             hub_id,
-            head_ptr: head as usize,
-            orig_addr: hub.orig_addr,
-            first_proxy: next_func,
+            head_ptr,
+            orig_addr: tail,
+            first_proxy,
             return_addr: return_addr as usize,
             stack_sp: current_sp,
         });
         if pushed {
             super::mark_stack_frame_push();
+            if !cursor.is_null() {
+                super::note_proxy_hit(unsafe { &*cursor });
+            }
             return;
         }
 
-        next_func = hub.orig_addr;
+        next_func = if foreign_tail { hub.orig_addr } else { tail };
         thread_state::report_hub_stack_overflow();
     });
 
+    // 没有线程状态时无法识别外来 hook 的回调，跳过它直接调用原函数
+    if tracked.is_none() && foreign_tail {
+        next_func = hub.orig_addr;
+    }
     next_func as *mut c_void
 }
 
@@ -141,6 +173,27 @@ pub(super) fn get_return_address() -> *mut c_void {
     .unwrap_or(ptr::null_mut())
 }
 
+// 清除过期帧后由内向外复制存活帧的返回地址，返回写入数
+// 只访问已绑定的线程状态且不分配内存，可在采样信号处理函数中调用
+pub(super) fn backtrace(buf: &mut [usize]) -> usize {
+    let current_sp = current_stack_pointer();
+    thread_state::with_bound_thread_state(|state| {
+        let stack = state.hub_stack_mut();
+        prune_stale_frames(stack, current_sp);
+        let mut written = 0usize;
+        let mut idx = stack.len();
+        while idx > 0 && written < buf.len() {
+            idx -= 1;
+            if let Some(frame) = stack.get(idx) {
+                buf[written] = frame.return_addr;
+                written += 1;
+            }
+        }
+        written
+    })
+    .unwrap_or(0)
+}
+
 pub(super) fn pop_stack_by_return_address(return_addr: *mut c_void) {
     if return_addr.is_null() {
         return;
@@ -181,13 +234,14 @@ pub(super) extern "C" fn hub_pop_stack(hub_ptr: *mut super::Hub) {
 }
 
 // 在栈中查找 func 所在帧，返回 proxy 链表中的下一个活跃节点
-// 若 func 是链表末尾则返回 orig_addr
+// 若 func 是链表末尾则返回 orig_addr；fork 子进程无线程状态时改查全局索引
 pub(super) fn get_prev_func(func: *mut c_void) -> *mut c_void {
     if func.is_null() {
         return ptr::null_mut();
     }
-    let current = func as usize;
-    with_hub_stack_mut("get_prev_func", |stack| {
+    // proxy 传入的自身地址与节点中保存的地址可能一个带 PAC 签名一个不带，去掉签名后比较
+    let current = memory::canonical_code_addr(func as usize);
+    with_hub_stack_tid_mut("get_prev_func", |stack, tid| {
         let mut idx = stack.len();
         while idx > 0 {
             idx -= 1;
@@ -199,18 +253,19 @@ pub(super) fn get_prev_func(func: *mut c_void) -> *mut c_void {
             while !cursor.is_null() {
                 let node = unsafe { &*cursor };
                 if !found {
-                    if node.func != current {
-                        cursor = node.next;
+                    if memory::canonical_code_addr(node.func) != current {
+                        cursor = node.next();
                         continue;
                     }
                     found = true;
-                    cursor = node.next;
+                    cursor = node.next();
                     continue;
                 }
-                if node.enabled.load(Ordering::Acquire) {
+                if node.enabled.load(Ordering::Acquire) && super::allows_thread(node, tid) {
+                    super::note_proxy_hit(node);
                     return node.func as *mut c_void;
                 }
-                cursor = node.next;
+                cursor = node.next();
             }
             if found {
                 return frame.orig_addr as *mut c_void;
@@ -218,17 +273,23 @@ pub(super) fn get_prev_func(func: *mut c_void) -> *mut c_void {
         }
         ptr::null_mut()
     })
-    .unwrap_or(ptr::null_mut())
+    .unwrap_or_else(|| {
+        if !thread_state::should_skip_thread_state() {
+            return ptr::null_mut();
+        }
+        super::fork_index::note_fallback();
+        super::fork_index::resolve_prev_func(func as usize).unwrap_or(0) as *mut c_void
+    })
 }
 
 pub(super) fn proxy_leave(func: *mut c_void) {
     if func.is_null() {
         return;
     }
-    let func = func as usize;
+    let func = memory::canonical_code_addr(func as usize);
     let _ = with_hub_stack_mut("proxy_leave", |stack| {
         if let Some(frame) = stack.last()
-            && frame.first_proxy == func
+            && memory::canonical_code_addr(frame.first_proxy) == func
         {
             let _ = stack.pop();
             super::mark_stack_frames_pop(1);
@@ -241,6 +302,14 @@ pub(super) fn proxy_leave(func: *mut c_void) {
     });
 }
 
+pub(super) fn set_limits(limits: Option<(usize, usize)>) {
+    thread_state::set_hub_stack_limits(limits);
+}
+
+pub(super) fn stats() -> HubStackStats {
+    thread_state::hub_stack_stats()
+}
+
 pub(super) fn clear_stack() {
     let _ = with_hub_stack_mut("clear_stack", |stack| {
         let count = stack.clear();
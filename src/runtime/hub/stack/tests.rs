@@ -1,8 +1,10 @@
 // Hub 调用栈的单元测试
 use super::{
-    HubFrame, get_prev_func, pop_stack_by_return_address, proxy_leave, with_test_hub_stack,
+    HubFrame, backtrace, get_prev_func, pop_stack_by_return_address, proxy_leave, stats,
+    with_test_hub_stack,
 };
-use std::sync::atomic::AtomicBool;
+use crate::api::{HUB_STACK_DEFAULT_HARD_CAP, HUB_STACK_FIXED_DEPTH};
+use std::sync::atomic::{AtomicBool, AtomicPtr};
 
 fn make_node(
     func: usize,
@@ -11,9 +13,13 @@ fn make_node(
 ) -> *mut super::super::ProxyNode {
     Box::into_raw(Box::new(super::super::ProxyNode {
         func,
+        priority: 0,
         ref_count: 1,
+        paused: 0,
         enabled: AtomicBool::new(enabled),
-        next,
+        stats: AtomicPtr::new(std::ptr::null_mut()),
+        tid_filter: AtomicPtr::new(std::ptr::null_mut()),
+        next: AtomicPtr::new(next),
     }))
 }
 
@@ -119,3 +125,140 @@ fn proxy_leave_removes_non_top_frame() {
         let _ = stack.clear();
     });
 }
+
+#[test]
+fn backtrace_lists_live_frames_innermost_first() {
+    with_test_hub_stack(|stack| {
+        let _ = stack.clear();
+        for (hub_id, return_addr, stack_sp) in [
+            (1, 0x111, usize::MAX),
+            (2, 0x222, usize::MAX),
+            (3, 0x333, 0),
+        ] {
+            assert!(stack.push(HubFrame {
+                hub_id,
+                head_ptr: 0,
+                orig_addr: 0,
+                first_proxy: 0,
+                return_addr,
+                stack_sp,
+            }));
+        }
+    });
+
+    // SP 已越过的栈顶帧先被清除，buf 不足时只保留最内层
+    let mut buf = [0usize; 4];
+    assert_eq!(backtrace(&mut buf), 2);
+    assert_eq!(buf[..2], [0x222, 0x111]);
+    let mut short = [0usize; 1];
+    assert_eq!(backtrace(&mut short), 1);
+    assert_eq!(short, [0x222]);
+    assert_eq!(backtrace(&mut []), 0);
+
+    with_test_hub_stack(|stack| {
+        assert_eq!(stack.len(), 2);
+        let _ = stack.clear();
+    });
+}
+
+fn spill_frame(hub_id: usize) -> HubFrame {
+    HubFrame {
+        hub_id,
+        head_ptr: 0,
+        orig_addr: 0,
+        first_proxy: 0x1000 + hub_id,
+        return_addr: 0x2000 + hub_id,
+        stack_sp: usize::MAX,
+    }
+}
+
+#[test]
+fn deep_nesting_spills_past_fixed_array() {
+    let depth = HUB_STACK_FIXED_DEPTH + 8;
+    with_test_hub_stack(|stack| {
+        let _ = stack.clear();
+        for hub_id in 0..depth {
+            assert!(stack.push(spill_frame(hub_id)), "push {hub_id} 失败");
+        }
+        assert_eq!(stack.len(), depth);
+        assert_eq!(stack.last().map(|frame| frame.hub_id), Some(depth - 1));
+        assert_eq!(
+            stack.get(HUB_STACK_FIXED_DEPTH).map(|frame| frame.hub_id),
+            Some(HUB_STACK_FIXED_DEPTH)
+        );
+        assert_eq!(
+            stack.rposition_by(|frame| frame.hub_id == HUB_STACK_FIXED_DEPTH + 2),
+            Some(HUB_STACK_FIXED_DEPTH + 2)
+        );
+    });
+
+    // 移除固定数组中的帧后，堆区最底部的帧补入固定数组，整体顺序不变
+    proxy_leave((0x1000 + 3) as *mut std::ffi::c_void);
+    pop_stack_by_return_address((0x2000 + HUB_STACK_FIXED_DEPTH + 4) as *mut std::ffi::c_void);
+    with_test_hub_stack(|stack| {
+        let expected: Vec<usize> = (0..depth)
+            .filter(|hub_id| *hub_id != 3 && *hub_id != HUB_STACK_FIXED_DEPTH + 4)
+            .collect();
+        let actual: Vec<usize> = (0..stack.len())
+            .filter_map(|index| stack.get(index).map(|frame| frame.hub_id))
+            .collect();
+        assert_eq!(actual, expected);
+        while let Some(frame) = stack.pop() {
+            assert_eq!(Some(frame.hub_id), expected.get(stack.len()).copied());
+        }
+    });
+    assert!(stats().spilled_frames >= 8);
+}
+
+#[test]
+fn get_prev_func_resolves_spilled_frame() {
+    let tail = make_node(0x7777, true, std::ptr::null_mut());
+    let head = make_node(0x6666, true, tail);
+    with_test_hub_stack(|stack| {
+        let _ = stack.clear();
+        for hub_id in 0..HUB_STACK_FIXED_DEPTH {
+            assert!(stack.push(spill_frame(hub_id)));
+        }
+        assert!(stack.push(HubFrame {
+            hub_id: usize::MAX,
+            head_ptr: head as usize,
+            orig_addr: 0x8888,
+            first_proxy: 0x6666,
+            return_addr: 0,
+            stack_sp: usize::MAX,
+        }));
+    });
+
+    assert_eq!(
+        get_prev_func(0x6666 as *mut std::ffi::c_void) as usize,
+        0x7777
+    );
+    assert_eq!(
+        get_prev_func(0x7777 as *mut std::ffi::c_void) as usize,
+        0x8888
+    );
+
+    with_test_hub_stack(|stack| {
+        let _ = stack.clear();
+    });
+    unsafe {
+        drop(Box::from_raw(head));
+        drop(Box::from_raw(tail));
+    }
+}
+
+#[test]
+fn push_fails_at_hard_cap() {
+    with_test_hub_stack(|stack| {
+        let _ = stack.clear();
+        for hub_id in 0..HUB_STACK_DEFAULT_HARD_CAP {
+            assert!(stack.push(spill_frame(hub_id)), "push {hub_id} 失败");
+        }
+        let overflows = stats().overflows;
+        assert!(!stack.push(spill_frame(HUB_STACK_DEFAULT_HARD_CAP)));
+        assert!(stats().overflows > overflows);
+        assert_eq!(stack.len(), HUB_STACK_DEFAULT_HARD_CAP);
+        assert!(stats().max_depth >= HUB_STACK_DEFAULT_HARD_CAP);
+        assert_eq!(stack.clear(), HUB_STACK_DEFAULT_HARD_CAP);
+    });
+}
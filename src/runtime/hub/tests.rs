@@ -0,0 +1,249 @@
+// Hub proxy 链表的单元测试，不分配 trampoline
+use super::{
+    RETIRED_HUBS, acquire_hit_stats, add_proxy, clear_stack, create_hub, create_tid_filter,
+    del_proxy, destroy_hub, destroy_hub_now, drain_retired, enabled_proxies, first_enabled,
+    fork_index, get_prev_func, hit_stats_snapshot, hub_trampo, new_hub, release_hit_stats,
+    retire_tid_filter, set_proxy_hit_stats, set_proxy_paused, set_proxy_tid_filter, set_tail,
+    stack,
+};
+use crate::errno::Errno;
+use crate::runtime::state::MutexPoisonRecover;
+use crate::runtime::thread_state;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+const ORIG: usize = 0xe0c0;
+const FIRST: usize = 0xe0a1;
+const SECOND: usize = 0xe0b1;
+const THIRD: usize = 0xe0d1;
+const FOURTH: usize = 0xe0e1;
+
+#[test]
+fn prelinked_first_proxy_matches_locked_add() {
+    let hub_ptr = Box::into_raw(new_hub(ORIG, FIRST, 0));
+    assert_eq!(first_enabled(hub_ptr), FIRST);
+    assert_eq!(enabled_proxies(hub_ptr), vec![FIRST]);
+
+    // 预置节点与加锁路径共用同一节点和引用计数
+    assert_eq!(add_proxy(hub_ptr, FIRST, 0), Errno::Ok);
+    assert_eq!(enabled_proxies(hub_ptr), vec![FIRST]);
+    assert_eq!(add_proxy(hub_ptr, SECOND, 0), Errno::Ok);
+    assert_eq!(enabled_proxies(hub_ptr), vec![SECOND, FIRST]);
+
+    assert_eq!(del_proxy(hub_ptr, FIRST), (Errno::Ok, true));
+    assert_eq!(del_proxy(hub_ptr, SECOND), (Errno::Ok, true));
+    assert_eq!(del_proxy(hub_ptr, FIRST), (Errno::Ok, false));
+    assert_eq!(first_enabled(hub_ptr), ORIG);
+
+    fork_index::remove_hub(hub_ptr as usize);
+    unsafe {
+        destroy_hub_now(hub_ptr);
+    }
+}
+
+#[test]
+fn proxies_inserted_by_priority_then_lifo() {
+    let hub_ptr = Box::into_raw(new_hub(ORIG, FIRST, 0));
+    // 高优先级排到链首，低优先级排在链尾，不受注册先后影响
+    assert_eq!(add_proxy(hub_ptr, SECOND, -5), Errno::Ok);
+    assert_eq!(add_proxy(hub_ptr, THIRD, 10), Errno::Ok);
+    assert_eq!(enabled_proxies(hub_ptr), vec![THIRD, FIRST, SECOND]);
+
+    // 同优先级后注册的先执行
+    assert_eq!(add_proxy(hub_ptr, FOURTH, 0), Errno::Ok);
+    assert_eq!(enabled_proxies(hub_ptr), vec![THIRD, FOURTH, FIRST, SECOND]);
+
+    // 已在链上的 proxy 再次加入时沿用原位置
+    assert_eq!(del_proxy(hub_ptr, SECOND), (Errno::Ok, true));
+    assert_eq!(add_proxy(hub_ptr, SECOND, 100), Errno::Ok);
+    assert_eq!(enabled_proxies(hub_ptr), vec![THIRD, FOURTH, FIRST, SECOND]);
+    assert_eq!(first_enabled(hub_ptr), THIRD);
+
+    for func in [FIRST, SECOND, THIRD, FOURTH] {
+        assert_eq!(del_proxy(hub_ptr, func).0, Errno::Ok);
+    }
+    fork_index::remove_hub(hub_ptr as usize);
+    unsafe {
+        destroy_hub_now(hub_ptr);
+    }
+}
+
+#[test]
+fn paused_proxy_keeps_hub_and_shared_reference() {
+    let hub_ptr = Box::into_raw(new_hub(ORIG, FIRST, 0));
+    assert_eq!(add_proxy(hub_ptr, SECOND, 0), Errno::Ok);
+
+    assert_eq!(set_proxy_paused(hub_ptr, SECOND, true), Errno::Ok);
+    assert_eq!(enabled_proxies(hub_ptr), vec![FIRST]);
+    assert_eq!(set_proxy_paused(hub_ptr, FIRST, true), Errno::Ok);
+    assert_eq!(first_enabled(hub_ptr), ORIG);
+
+    // 同一 proxy 的另一个引用未暂停，节点保持启用
+    assert_eq!(add_proxy(hub_ptr, FIRST, 0), Errno::Ok);
+    assert_eq!(enabled_proxies(hub_ptr), vec![FIRST]);
+    assert_eq!(set_proxy_paused(hub_ptr, FIRST, false), Errno::Ok);
+    assert_eq!(del_proxy(hub_ptr, FIRST), (Errno::Ok, true));
+    assert_eq!(enabled_proxies(hub_ptr), vec![FIRST]);
+    assert_eq!(del_proxy(hub_ptr, FIRST), (Errno::Ok, true));
+
+    // 只剩暂停的 proxy 时 hub 仍被占用
+    assert_eq!(enabled_proxies(hub_ptr), Vec::<usize>::new());
+    assert_eq!(set_proxy_paused(hub_ptr, SECOND, false), Errno::Ok);
+    assert_eq!(first_enabled(hub_ptr), SECOND);
+    assert_eq!(del_proxy(hub_ptr, SECOND), (Errno::Ok, false));
+    assert_eq!(set_proxy_paused(hub_ptr, SECOND, true), Errno::NotFound);
+
+    fork_index::remove_hub(hub_ptr as usize);
+    unsafe {
+        destroy_hub_now(hub_ptr);
+    }
+}
+
+#[test]
+fn hit_stats_follow_selected_proxy() {
+    let hub_ptr = Box::into_raw(new_hub(ORIG, FIRST, 0));
+    assert_eq!(add_proxy(hub_ptr, SECOND, 0), Errno::Ok);
+    let head_stats = acquire_hit_stats();
+    let tail_stats = acquire_hit_stats();
+    set_proxy_hit_stats(hub_ptr, SECOND, head_stats, true);
+    set_proxy_hit_stats(hub_ptr, FIRST, tail_stats, true);
+
+    for _ in 0..3 {
+        clear_stack();
+        let next = unsafe { stack::hub_push_stack(hub_ptr, std::ptr::null_mut()) };
+        assert_eq!(next as usize, SECOND);
+    }
+    assert_eq!(hit_stats_snapshot(head_stats).hits, 3);
+    assert_eq!(hit_stats_snapshot(head_stats).threads, 1);
+    assert_eq!(hit_stats_snapshot(tail_stats).hits, 0);
+
+    // 链上后续 proxy 经 get_prev_func 进入时计数
+    assert_eq!(get_prev_func(SECOND as *mut _) as usize, FIRST);
+    assert_eq!(hit_stats_snapshot(tail_stats).hits, 1);
+    assert_ne!(hit_stats_snapshot(tail_stats).last_hit_ns, 0);
+
+    // 摘下时只清除仍指向该统计块的节点
+    set_proxy_hit_stats(hub_ptr, SECOND, tail_stats, false);
+    set_proxy_hit_stats(hub_ptr, FIRST, tail_stats, false);
+    clear_stack();
+    let _ = unsafe { stack::hub_push_stack(hub_ptr, std::ptr::null_mut()) };
+    assert_eq!(get_prev_func(SECOND as *mut _) as usize, FIRST);
+    assert_eq!(hit_stats_snapshot(head_stats).hits, 4);
+    assert_eq!(hit_stats_snapshot(tail_stats).hits, 1);
+
+    clear_stack();
+    release_hit_stats(head_stats);
+    release_hit_stats(tail_stats);
+    fork_index::remove_hub(hub_ptr as usize);
+    unsafe {
+        destroy_hub_now(hub_ptr);
+    }
+}
+
+#[test]
+fn tid_filter_skips_other_threads() {
+    let hub_ptr = Box::into_raw(new_hub(ORIG, FIRST, 0));
+    assert_eq!(add_proxy(hub_ptr, SECOND, 0), Errno::Ok);
+    let tid = unsafe { libc::gettid() };
+    let other = create_tid_filter(&[tid + 1]);
+    let own = create_tid_filter(&[tid + 1, tid]);
+
+    // 链首限定为其他线程时，本线程从下一个节点开始
+    set_proxy_tid_filter(hub_ptr, SECOND, other, true);
+    clear_stack();
+    let next = unsafe { stack::hub_push_stack(hub_ptr, std::ptr::null_mut()) };
+    assert_eq!(next as usize, FIRST);
+
+    // get_prev_func 同样跳过不匹配的节点
+    set_proxy_tid_filter(hub_ptr, SECOND, own, true);
+    set_proxy_tid_filter(hub_ptr, FIRST, other, true);
+    clear_stack();
+    let next = unsafe { stack::hub_push_stack(hub_ptr, std::ptr::null_mut()) };
+    assert_eq!(next as usize, SECOND);
+    assert_eq!(get_prev_func(SECOND as *mut _) as usize, ORIG);
+
+    // 摘下只清除仍指向该集合的节点
+    set_proxy_tid_filter(hub_ptr, SECOND, other, false);
+    set_proxy_tid_filter(hub_ptr, FIRST, other, false);
+    assert_eq!(get_prev_func(SECOND as *mut _) as usize, FIRST);
+    set_proxy_tid_filter(hub_ptr, SECOND, own, false);
+
+    clear_stack();
+    retire_tid_filter(other);
+    retire_tid_filter(own);
+    fork_index::remove_hub(hub_ptr as usize);
+    unsafe {
+        destroy_hub_now(hub_ptr);
+    }
+}
+
+fn is_retired(hub_ptr: *mut super::Hub) -> bool {
+    RETIRED_HUBS
+        .lock_or_poison()
+        .iter()
+        .any(|item| item.hub_ptr == hub_ptr as usize)
+}
+
+#[test]
+fn retired_hub_survives_drain_until_frame_pops() {
+    let hub_ptr = Box::into_raw(new_hub(ORIG, FIRST, 0));
+    clear_stack();
+    // proxy 调用进行中：trampoline 已压入栈帧
+    let next = unsafe { stack::hub_push_stack(hub_ptr, std::ptr::null_mut()) };
+    assert_eq!(next as usize, FIRST);
+
+    destroy_hub(hub_ptr, true);
+    assert!(!drain_retired(Duration::from_millis(20)));
+    assert!(is_retired(hub_ptr));
+
+    // 帧弹出后 drain 不再等待销毁延迟
+    stack::hub_pop_stack(hub_ptr);
+    assert!(drain_retired(Duration::from_secs(2)));
+    assert!(!is_retired(hub_ptr));
+}
+
+// 外来 hook 保存的原值是本 hub 的 trampoline
+static FOREIGN_SAVED: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn real_export(value: usize) -> usize {
+    value + 1
+}
+
+extern "C" fn foreign_hook(value: usize) -> usize {
+    let saved: extern "C" fn(usize) -> usize =
+        unsafe { std::mem::transmute(FOREIGN_SAVED.load(Ordering::Acquire)) };
+    saved(value) + 100
+}
+
+extern "C" fn chained_proxy(value: usize) -> usize {
+    let prev = get_prev_func(chained_proxy as *mut _);
+    let prev: extern "C" fn(usize) -> usize = unsafe { std::mem::transmute(prev) };
+    prev(value) + 10
+}
+
+#[test]
+fn foreign_tail_calling_saved_trampoline_reaches_export() {
+    let proxy = chained_proxy as *const () as usize;
+    let hub_ptr = create_hub(real_export as *const () as usize, proxy, 0).unwrap();
+    let trampo = hub_trampo(hub_ptr);
+    FOREIGN_SAVED.store(trampo, Ordering::Release);
+    set_tail(hub_ptr, foreign_hook as *const () as usize);
+    let call: extern "C" fn(usize) -> usize = unsafe { std::mem::transmute(trampo) };
+    clear_stack();
+
+    // proxy -> 外来 hook -> 回到 trampoline -> 原函数
+    assert_eq!(call(1), 112);
+
+    // 没有可用 proxy 时仍压入直通帧，外来 hook 的回调不会再转给自己
+    assert_eq!(set_proxy_paused(hub_ptr, proxy, true), Errno::Ok);
+    assert_eq!(call(1), 102);
+
+    let _ = thread_state::with_thread_state(|state| *state.hook_disabled_mut() = true);
+    assert_eq!(set_proxy_paused(hub_ptr, proxy, false), Errno::Ok);
+    assert_eq!(call(1), 102);
+    let _ = thread_state::with_thread_state(|state| *state.hook_disabled_mut() = false);
+    assert!(stack::get_return_address().is_null());
+
+    assert_eq!(del_proxy(hub_ptr, proxy), (Errno::Ok, false));
+    destroy_hub(hub_ptr, false);
+}
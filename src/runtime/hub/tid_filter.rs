@@ -0,0 +1,54 @@
+// proxy 节点的线程过滤集合，hub_push_stack / get_prev_func 据此跳过非目标线程的调用
+// 集合创建后不再修改，更新时整体替换节点上的指针；旧集合延迟释放，避免仍在遍历链表的线程读到已释放内存
+use crate::runtime::state::MutexPoisonRecover;
+use std::sync::Mutex;
+
+pub(super) struct TidFilter {
+    // 升序且无重复
+    tids: Box<[i32]>,
+}
+
+impl TidFilter {
+    #[inline]
+    pub(super) fn allows(&self, tid: i32) -> bool {
+        self.tids.binary_search(&tid).is_ok()
+    }
+}
+
+// (集合地址, 退役时间)
+static RETIRED_FILTERS: Mutex<Vec<(usize, u64)>> = Mutex::new(Vec::new());
+
+pub(super) fn create(tids: &[i32]) -> *mut TidFilter {
+    let mut tids = tids.to_vec();
+    tids.sort_unstable();
+    tids.dedup();
+    Box::into_raw(Box::new(TidFilter {
+        tids: tids.into_boxed_slice(),
+    }))
+}
+
+pub(super) fn retire(filter: *mut TidFilter, now: u64) {
+    if filter.is_null() {
+        return;
+    }
+    RETIRED_FILTERS
+        .lock_or_poison()
+        .push((filter as usize, now));
+}
+
+// 释放退役超过 delay 秒的集合；集合只在选择 proxy 的瞬间被读取，不需要等待活跃栈帧归零
+pub(super) fn collect(now: u64, delay: u64) {
+    let ready: Vec<usize> = {
+        let mut retired = RETIRED_FILTERS.lock_or_poison();
+        let (ready, kept) = std::mem::take(&mut *retired)
+            .into_iter()
+            .partition(|(_, ts)| now.saturating_sub(*ts) >= delay);
+        *retired = kept;
+        ready.into_iter().map(|(filter, _)| filter).collect()
+    };
+    for filter in ready {
+        unsafe {
+            drop(Box::from_raw(filter as *mut TidFilter));
+        }
+    }
+}
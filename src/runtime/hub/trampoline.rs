@@ -1,169 +1,51 @@
 // Trampoline 代码生成与内存管理
-// 通过汇编模板 + 运行时数据填充，为每个 Hub 生成独立的跳板代码
-use crate::errno::Errno;
+// 通过机器码模板 + 运行时数据填充，为每个 Hub 生成独立的跳板代码
 use crate::android::memory;
+use crate::errno::Errno;
 use std::mem::size_of;
-use std::ptr;
+use std::slice;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 const TRAMPO_ALIGN: usize = 16;
 // 释放后的冷却期，防止被立即复用时指令缓存未刷新
 const TRAMPO_DELAY_SEC: u64 = 5;
 
+// 兼容模式：页面直接映射为 RWX，初始化时不再切换保护
+static RWX_MODE: AtomicBool = AtomicBool::new(false);
+
+mod aarch64;
 mod manager;
+mod x86_64;
+
+// 模板之后依次放置 push_stack、pop_stack、hub_ptr 三个数据槽，顺序与模板中的相对寻址一致
+const DATA_SLOT_PUSH_STACK: usize = 0;
+const DATA_SLOT_POP_STACK: usize = 1;
+const DATA_SLOT_HUB_PTR: usize = 2;
+const DATA_SLOT_COUNT: usize = 3;
+
+// 单个架构的 trampoline 模板，两种架构均参与编译，便于在任意主机上校验机器码
+struct TrampoTemplate {
+    name: &'static str,
+    code: &'static [u8],
+}
+
+impl TrampoTemplate {
+    // 实际写入的字节数：代码 + 数据槽
+    fn emit_size(&self) -> usize {
+        self.code.len() + size_of::<u64>() * DATA_SLOT_COUNT
+    }
+
+    // 池中单个槽位大小，按 TRAMPO_ALIGN 对齐
+    fn slot_size(&self) -> usize {
+        align_up(self.emit_size(), TRAMPO_ALIGN)
+    }
+}
 
-// aarch64 trampoline 模板：保存全部调用约定寄存器 -> push_stack -> 调用 proxy -> pop_stack -> 恢复并返回
 #[cfg(target_arch = "aarch64")]
-std::arch::global_asm!(
-    r#"
-    .text
-    .global srx_hub_trampo_template_start
-    .global srx_hub_trampo_template_data
-    .type srx_hub_trampo_template_start, %function
-srx_hub_trampo_template_start:
-    stp   x0, x1, [sp, #-0xd0]!
-    stp   x2, x3, [sp, #0x10]
-    stp   x4, x5, [sp, #0x20]
-    stp   x6, x7, [sp, #0x30]
-    stp   x8, lr, [sp, #0x40]
-    stp   q0, q1, [sp, #0x50]
-    stp   q2, q3, [sp, #0x70]
-    stp   q4, q5, [sp, #0x90]
-    stp   q6, q7, [sp, #0xb0]
-
-    ldr   x0, hub_ptr
-    mov   x1, lr
-    ldr   x16, push_stack
-    blr   x16
-    mov   x17, x0
-
-    ldp   q6, q7, [sp, #0xb0]
-    ldp   q4, q5, [sp, #0x90]
-    ldp   q2, q3, [sp, #0x70]
-    ldp   q0, q1, [sp, #0x50]
-    ldp   x8, lr, [sp, #0x40]
-    ldp   x6, x7, [sp, #0x30]
-    ldp   x4, x5, [sp, #0x20]
-    ldp   x2, x3, [sp, #0x10]
-    ldp   x0, x1, [sp], #0xd0
-
-    sub   sp, sp, #0x40
-    str   lr, [sp]
-    blr   x17
-    stp   x0, x1, [sp, #0x08]
-    stp   q0, q1, [sp, #0x20]
-
-    ldr   x0, hub_ptr
-    ldr   x16, pop_stack
-    blr   x16
-
-    ldp   x0, x1, [sp, #0x08]
-    ldp   q0, q1, [sp, #0x20]
-    ldr   lr, [sp]
-    add   sp, sp, #0x40
-    ret
-
-srx_hub_trampo_template_data:
-push_stack:
-    .quad 0
-pop_stack:
-    .quad 0
-hub_ptr:
-    .quad 0
-"#
-);
-
-// x86_64 trampoline 模板：同 aarch64 逻辑，使用 AT&T 语法
+const NATIVE_TEMPLATE: &TrampoTemplate = &aarch64::TEMPLATE;
 #[cfg(target_arch = "x86_64")]
-std::arch::global_asm!(
-    r#"
-    .text
-    .global srx_hub_trampo_template_start
-    .global srx_hub_trampo_template_data
-    .type srx_hub_trampo_template_start, @function
-srx_hub_trampo_template_start:
-    pushq   %rbp
-    movq    %rsp, %rbp
-
-    subq    $192,  %rsp
-    movupd  %xmm0, 176(%rsp)
-    movupd  %xmm1, 160(%rsp)
-    movupd  %xmm2, 144(%rsp)
-    movupd  %xmm3, 128(%rsp)
-    movupd  %xmm4, 112(%rsp)
-    movupd  %xmm5,  96(%rsp)
-    movupd  %xmm6,  80(%rsp)
-    movupd  %xmm7,  64(%rsp)
-    movq    %rax,   56(%rsp)
-    movq    %rdi,   48(%rsp)
-    movq    %rsi,   40(%rsp)
-    movq    %rdx,   32(%rsp)
-    movq    %rcx,   24(%rsp)
-    movq    %r8,    16(%rsp)
-    movq    %r9,     8(%rsp)
-    movq    %r10,     (%rsp)
-
-    movq    hub_ptr(%rip), %rdi
-    movq    8(%rbp), %rsi
-    call    *push_stack(%rip)
-    movq    %rax, %r11
-
-    movupd  176(%rsp), %xmm0
-    movupd  160(%rsp), %xmm1
-    movupd  144(%rsp), %xmm2
-    movupd  128(%rsp), %xmm3
-    movupd  112(%rsp), %xmm4
-    movupd   96(%rsp), %xmm5
-    movupd   80(%rsp), %xmm6
-    movupd   64(%rsp), %xmm7
-    movq     56(%rsp), %rax
-    movq     48(%rsp), %rdi
-    movq     40(%rsp), %rsi
-    movq     32(%rsp), %rdx
-    movq     24(%rsp), %rcx
-    movq     16(%rsp), %r8
-    movq      8(%rsp), %r9
-    movq       (%rsp), %r10
-    addq    $192,      %rsp
-
-    movq    %rbp, %rsp
-    popq    %rbp
-    subq    $8, %rsp
-    call    *%r11
-    addq    $8, %rsp
-
-    subq    $64, %rsp
-    movq    %rax,   (%rsp)
-    movq    %rdx,  8(%rsp)
-    movupd  %xmm0, 16(%rsp)
-    movupd  %xmm1, 32(%rsp)
-
-    movq    hub_ptr(%rip), %rdi
-    call    *pop_stack(%rip)
-
-    movq      (%rsp), %rax
-    movq     8(%rsp), %rdx
-    movupd  16(%rsp), %xmm0
-    movupd  32(%rsp), %xmm1
-    addq    $64, %rsp
-    ret
-
-srx_hub_trampo_template_data:
-push_stack:
-    .quad 0
-pop_stack:
-    .quad 0
-hub_ptr:
-    .quad 0
-"#
-    ,
-    options(att_syntax)
-);
-
-unsafe extern "C" {
-    static srx_hub_trampo_template_start: u8;
-    static srx_hub_trampo_template_data: u8;
-}
+const NATIVE_TEMPLATE: &TrampoTemplate = &x86_64::TEMPLATE;
 
 fn align_up(value: usize, align: usize) -> usize {
     if align == 0 {
@@ -172,12 +54,49 @@ fn align_up(value: usize, align: usize) -> usize {
     value.div_ceil(align) * align
 }
 
-// 计算单个 trampoline 占用大小：代码段 + 3 个 usize 数据槽，按 TRAMPO_ALIGN 对齐
+// 计算单个 trampoline 占用大小：代码段 + 3 个数据槽，按 TRAMPO_ALIGN 对齐
 fn trampo_size() -> usize {
-    let start = ptr::addr_of!(srx_hub_trampo_template_start) as usize;
-    let data = ptr::addr_of!(srx_hub_trampo_template_data) as usize;
-    let code_size = data.saturating_sub(start);
-    align_up(code_size + size_of::<usize>() * 3, TRAMPO_ALIGN)
+    NATIVE_TEMPLATE.slot_size()
+}
+
+// 按模板生成 trampoline 字节：复制代码并在其后填充数据槽，不涉及任何内存权限操作
+// 返回实际写入的字节数
+fn emit_trampoline_with(
+    template: &TrampoTemplate,
+    buf: &mut [u8],
+    hub_addr: usize,
+    push_fn: usize,
+    pop_fn: usize,
+) -> Result<usize, Errno> {
+    if hub_addr == 0 || push_fn == 0 || pop_fn == 0 {
+        return Err(Errno::InvalidArg);
+    }
+    let code_size = template.code.len();
+    let emit_size = template.emit_size();
+    if code_size == 0 || buf.len() < emit_size {
+        return Err(Errno::InitErrTrampo);
+    }
+
+    buf[..code_size].copy_from_slice(template.code);
+    let slots = [
+        (DATA_SLOT_PUSH_STACK, push_fn),
+        (DATA_SLOT_POP_STACK, pop_fn),
+        (DATA_SLOT_HUB_PTR, hub_addr),
+    ];
+    for (idx, value) in slots {
+        let offset = code_size + idx * size_of::<u64>();
+        buf[offset..offset + size_of::<u64>()].copy_from_slice(&(value as u64).to_le_bytes());
+    }
+    Ok(emit_size)
+}
+
+fn emit_trampoline(
+    buf: &mut [u8],
+    hub_addr: usize,
+    push_fn: usize,
+    pop_fn: usize,
+) -> Result<usize, Errno> {
+    emit_trampoline_with(NATIVE_TEMPLATE, buf, hub_addr, push_fn, pop_fn)
 }
 
 fn now_sec() -> u64 {
@@ -187,7 +106,32 @@ fn now_sec() -> u64 {
         .unwrap_or(0)
 }
 
-pub(super) fn alloc_trampo() -> Result<usize, Errno> {
+pub(super) fn set_rwx_mode(flag: bool) {
+    RWX_MODE.store(flag, Ordering::Relaxed);
+}
+
+fn is_rwx_mode() -> bool {
+    RWX_MODE.load(Ordering::Relaxed)
+}
+
+// 分配与回收时页面使用的可写保护：默认 RW，兼容模式为 RWX
+fn writable_prot() -> i32 {
+    if is_rwx_mode() {
+        libc::PROT_READ | libc::PROT_WRITE | libc::PROT_EXEC
+    } else {
+        libc::PROT_READ | libc::PROT_WRITE
+    }
+}
+
+// 分配到的槽位：exec 为执行地址，write 为写入地址（共享页经 RW 别名写入时两者不同）
+// seal 为 true 时写完需把所在页切为 RX
+pub(super) struct TrampoSlot {
+    pub(super) exec: usize,
+    write: usize,
+    seal: bool,
+}
+
+pub(super) fn alloc_trampo() -> Result<TrampoSlot, Errno> {
     manager::alloc_trampo()
 }
 
@@ -195,33 +139,29 @@ pub(super) fn free_trampo(trampo: usize) {
     manager::free_trampo(trampo);
 }
 
-// 初始化 trampoline：复制模板代码、填充数据槽、刷新 icache、设置 RX 权限
+pub(super) fn note_fork() {
+    manager::note_fork();
+}
+
+// 初始化 trampoline：经写入地址生成代码与数据槽、按执行地址刷新 icache，独占页再切换为 RX 后才交给调用方发布
+// 共享页的执行视图始终为 RX，兼容模式下页面保持 RWX，两者都不再切换保护
 pub(super) unsafe fn init_trampo(
-    trampo: usize,
+    slot: &TrampoSlot,
     hub_ptr: usize,
     push_stack: usize,
     pop_stack: usize,
 ) -> Result<(), Errno> {
-    let writable_prot = memory::PROT_READ_FLAG | memory::PROT_WRITE_FLAG;
-    memory::set_addr_protect(trampo, writable_prot).map_err(|_| Errno::InitErrTrampo)?;
-
-    let start = ptr::addr_of!(srx_hub_trampo_template_start) as usize;
-    let data = ptr::addr_of!(srx_hub_trampo_template_data) as usize;
-    let code_size = data.saturating_sub(start);
-    if code_size == 0 {
-        return Err(Errno::InitErrTrampo);
-    }
+    let buf = unsafe { slice::from_raw_parts_mut(slot.write as *mut u8, trampo_size()) };
+    let written = emit_trampoline(buf, hub_ptr, push_stack, pop_stack)?;
 
-    unsafe {
-        ptr::copy_nonoverlapping(start as *const u8, trampo as *mut u8, code_size);
-        let data_ptr = (trampo + code_size) as *mut usize;
-        ptr::write(data_ptr, push_stack);
-        ptr::write(data_ptr.add(1), pop_stack);
-        ptr::write(data_ptr.add(2), hub_ptr);
+    memory::flush_instruction_cache_range(slot.exec, slot.exec + written);
+    if !slot.seal {
+        return Ok(());
     }
-
-    memory::flush_instruction_cache_range(trampo, trampo + code_size + size_of::<usize>() * 3);
     let execute_prot = memory::PROT_READ_FLAG | memory::PROT_EXEC_FLAG;
-    memory::set_addr_protect(trampo, execute_prot).map_err(|_| Errno::InitErrTrampo)?;
+    memory::set_addr_protect(slot.exec, execute_prot).map_err(|_| Errno::InitErrTrampo)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests;
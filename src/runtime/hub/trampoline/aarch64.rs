@@ -0,0 +1,53 @@
+// aarch64 trampoline 模板机器码
+// 保存全部调用约定寄存器 -> push_stack -> 调用 proxy -> pop_stack -> 恢复并返回
+// push_stack 前保存 x0-x8、lr 与完整 128 位 q0-q7（向量参数及 HFA/HVA 参数）；
+// pop_stack 前保存返回值 x0-x1 与 q0-q3（HFA/HVA 返回值最多占 4 个向量寄存器），栈帧均保持 16 字节对齐
+// 数据槽紧跟代码末尾（0x98），ldr 字面量按 PC 相对偏移读取，修改指令时需同步调整偏移
+use super::TrampoTemplate;
+
+#[rustfmt::skip]
+const CODE: [u8; 0x98] = [
+    0xe0, 0x07, 0xb3, 0xa9, // 0x0000: stp x0, x1, [sp, #-0xd0]!
+    0xe2, 0x0f, 0x01, 0xa9, // 0x0004: stp x2, x3, [sp, #0x10]
+    0xe4, 0x17, 0x02, 0xa9, // 0x0008: stp x4, x5, [sp, #0x20]
+    0xe6, 0x1f, 0x03, 0xa9, // 0x000c: stp x6, x7, [sp, #0x30]
+    0xe8, 0x7b, 0x04, 0xa9, // 0x0010: stp x8, lr, [sp, #0x40]
+    0xe0, 0x87, 0x02, 0xad, // 0x0014: stp q0, q1, [sp, #0x50]
+    0xe2, 0x8f, 0x03, 0xad, // 0x0018: stp q2, q3, [sp, #0x70]
+    0xe4, 0x97, 0x04, 0xad, // 0x001c: stp q4, q5, [sp, #0x90]
+    0xe6, 0x9f, 0x05, 0xad, // 0x0020: stp q6, q7, [sp, #0xb0]
+    0x20, 0x04, 0x00, 0x58, // 0x0024: ldr x0, hub_ptr
+    0xe1, 0x03, 0x1e, 0xaa, // 0x0028: mov x1, lr
+    0x70, 0x03, 0x00, 0x58, // 0x002c: ldr x16, push_stack
+    0x00, 0x02, 0x3f, 0xd6, // 0x0030: blr x16
+    0xf1, 0x03, 0x00, 0xaa, // 0x0034: mov x17, x0
+    0xe6, 0x9f, 0x45, 0xad, // 0x0038: ldp q6, q7, [sp, #0xb0]
+    0xe4, 0x97, 0x44, 0xad, // 0x003c: ldp q4, q5, [sp, #0x90]
+    0xe2, 0x8f, 0x43, 0xad, // 0x0040: ldp q2, q3, [sp, #0x70]
+    0xe0, 0x87, 0x42, 0xad, // 0x0044: ldp q0, q1, [sp, #0x50]
+    0xe8, 0x7b, 0x44, 0xa9, // 0x0048: ldp x8, lr, [sp, #0x40]
+    0xe6, 0x1f, 0x43, 0xa9, // 0x004c: ldp x6, x7, [sp, #0x30]
+    0xe4, 0x17, 0x42, 0xa9, // 0x0050: ldp x4, x5, [sp, #0x20]
+    0xe2, 0x0f, 0x41, 0xa9, // 0x0054: ldp x2, x3, [sp, #0x10]
+    0xe0, 0x07, 0xcd, 0xa8, // 0x0058: ldp x0, x1, [sp], #0xd0
+    0xff, 0x83, 0x01, 0xd1, // 0x005c: sub sp, sp, #0x60
+    0xfe, 0x03, 0x00, 0xf9, // 0x0060: str lr, [sp]
+    0x20, 0x02, 0x3f, 0xd6, // 0x0064: blr x17
+    0xe0, 0x87, 0x00, 0xa9, // 0x0068: stp x0, x1, [sp, #0x08]
+    0xe0, 0x07, 0x01, 0xad, // 0x006c: stp q0, q1, [sp, #0x20]
+    0xe2, 0x0f, 0x02, 0xad, // 0x0070: stp q2, q3, [sp, #0x40]
+    0xa0, 0x01, 0x00, 0x58, // 0x0074: ldr x0, hub_ptr
+    0x50, 0x01, 0x00, 0x58, // 0x0078: ldr x16, pop_stack
+    0x00, 0x02, 0x3f, 0xd6, // 0x007c: blr x16
+    0xe0, 0x87, 0x40, 0xa9, // 0x0080: ldp x0, x1, [sp, #0x08]
+    0xe0, 0x07, 0x41, 0xad, // 0x0084: ldp q0, q1, [sp, #0x20]
+    0xe2, 0x0f, 0x42, 0xad, // 0x0088: ldp q2, q3, [sp, #0x40]
+    0xfe, 0x03, 0x40, 0xf9, // 0x008c: ldr lr, [sp]
+    0xff, 0x83, 0x01, 0x91, // 0x0090: add sp, sp, #0x60
+    0xc0, 0x03, 0x5f, 0xd6, // 0x0094: ret
+];
+
+pub(super) const TEMPLATE: TrampoTemplate = TrampoTemplate {
+    name: "aarch64",
+    code: &CODE,
+};
@@ -1,27 +1,89 @@
 // Trampoline 内存页池管理器
-// 通过 mmap 分配可执行页，以位图追踪槽位占用，支持延迟回收和空闲页释放
+// 多个 trampoline 共享同一页：默认用 memfd 双映射，执行视图常驻 RX，经 RW 别名写入，
+// 释放槽位不会让同页其他活跃 trampoline 变为可写；双映射不可用时退回每页一个槽位、写完切 RX
+// 以位图追踪槽位占用，支持延迟回收和空闲页释放
+use crate::android::memory;
+use crate::api::TRAMPOLINE_VMA_NAME;
 use crate::errno::Errno;
+use crate::log;
+use crate::runtime::artifact;
 use crate::runtime::state::MutexPoisonRecover;
 use once_cell::sync::Lazy;
+use std::ffi::CString;
 use std::ptr;
 use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
 
-use super::{TRAMPO_DELAY_SEC, now_sec, trampo_size};
+use super::{TRAMPO_DELAY_SEC, TrampoSlot, is_rwx_mode, now_sec, trampo_size, writable_prot};
+
+// 映射命名常驻内存，旧内核只引用名称指针
+static VMA_NAME: Lazy<CString> =
+    Lazy::new(|| CString::new(TRAMPOLINE_VMA_NAME).unwrap_or_default());
+// fork 代数，由 atfork prepare 递增；双映射页只在创建它的代数内分配槽位
+static FORK_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+// 页面的映射形态
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PageView {
+    // memfd 双映射：ptr 为 RX 执行视图，alias 为同一物理页的 RW 视图
+    Aliased { alias: usize },
+    // 兼容模式的 RWX 匿名页，直接写入
+    Rwx,
+    // 双映射不可用时每页一个槽位，分配时切为可写，init_trampo 写完切为 RX
+    Exclusive,
+}
 
 // 单个内存页的管理信息
 struct TrampoPage {
     ptr: usize,
+    view: PageView,
+    // 创建该页的进程与 fork 代数；fork 后 MAP_SHARED 的双映射页在父子进程间共享，
+    // 一方复用空闲槽位会改写另一方仍在执行的 trampoline，因此双方都不再向 fork 前的页分配
+    owner_pid: i32,
+    fork_gen: u64,
     // 位图标记槽位占用状态
     flags: Vec<u32>,
     // 每个槽位的释放时间戳，用于冷却期判定
     timestamps: Vec<u64>,
 }
 
+impl TrampoPage {
+    fn slot_count(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    // 当前模式、进程与 fork 代数下可继续分配的页；pid 检查兜底不经过 atfork 的 fork
+    fn usable(&self, rwx: bool, pid: i32, fork_gen: u64) -> bool {
+        match self.view {
+            PageView::Aliased { .. } => !rwx && self.owner_pid == pid && self.fork_gen == fork_gen,
+            PageView::Rwx => rwx,
+            PageView::Exclusive => !rwx,
+        }
+    }
+
+    fn write_addr(&self, exec: usize) -> usize {
+        match self.view {
+            PageView::Aliased { alias } => alias + (exec - self.ptr),
+            PageView::Rwx | PageView::Exclusive => exec,
+        }
+    }
+}
+
+// 单页可容纳的槽位数
+pub(super) fn slots_per_page(page_size: usize, trampo_size: usize) -> usize {
+    if trampo_size == 0 {
+        return 0;
+    }
+    page_size / trampo_size
+}
+
 // 页池管理器
 struct TrampoMgr {
     page_size: usize,
     trampo_size: usize,
     pages: Vec<TrampoPage>,
+    // memfd 双映射失败过一次后不再尝试
+    alias_unavailable: bool,
 }
 
 impl TrampoMgr {
@@ -33,25 +95,26 @@ impl TrampoMgr {
             page_size,
             trampo_size,
             pages: Vec::new(),
+            alias_unavailable: false,
         }
     }
 
-    fn count_per_page(&self) -> usize {
-        // 每个 trampoline 独占一页，避免 RX/RW 切换影响同页的其他活跃 trampoline。
-        1
-    }
-
-    // 优先复用已有页中的空闲槽位，无可用时 mmap 新页
-    fn alloc(&mut self) -> Result<usize, Errno> {
-        let count = self.count_per_page();
-        if count == 0 {
+    // 优先复用已有页中的空闲槽位，无可用时映射新页
+    fn alloc(&mut self) -> Result<TrampoSlot, Errno> {
+        if slots_per_page(self.page_size, self.trampo_size) == 0 {
             return Err(Errno::NewTrampo);
         }
         let now = now_sec();
         self.reclaim_idle_pages(now);
 
+        let rwx = is_rwx_mode();
+        let pid = unsafe { libc::getpid() };
+        let fork_gen = FORK_GENERATION.load(Ordering::Acquire);
         for page in &mut self.pages {
-            for idx in 0..count {
+            if !page.usable(rwx, pid, fork_gen) {
+                continue;
+            }
+            for idx in 0..page.slot_count() {
                 let flags_idx = idx / 32;
                 let mask = 1u32 << (idx % 32);
                 if page.flags[flags_idx] & mask != 0 {
@@ -62,30 +125,70 @@ impl TrampoMgr {
                     continue;
                 }
 
-                let mprotect_result = unsafe {
-                    libc::mprotect(
-                        page.ptr as *mut libc::c_void,
-                        self.page_size,
-                        libc::PROT_READ | libc::PROT_WRITE,
-                    )
-                };
-                if mprotect_result != 0 {
-                    continue;
+                if page.view == PageView::Exclusive {
+                    let mprotect_result = unsafe {
+                        libc::mprotect(
+                            page.ptr as *mut libc::c_void,
+                            self.page_size,
+                            writable_prot(),
+                        )
+                    };
+                    if mprotect_result != 0 {
+                        continue;
+                    }
                 }
                 page.flags[flags_idx] |= mask;
-                let addr = page.ptr + idx * self.trampo_size;
+                let exec = page.ptr + idx * self.trampo_size;
+                let write = page.write_addr(exec);
                 unsafe {
-                    ptr::write_bytes(addr as *mut u8, 0, self.trampo_size);
+                    ptr::write_bytes(write as *mut u8, 0, self.trampo_size);
                 }
-                return Ok(addr);
+                return Ok(TrampoSlot {
+                    exec,
+                    write,
+                    seal: page.view == PageView::Exclusive,
+                });
             }
         }
 
+        let mut page = self.map_page(rwx, pid, fork_gen)?;
+        page.flags[0] |= 1;
+        let slot = TrampoSlot {
+            exec: page.ptr,
+            write: page.write_addr(page.ptr),
+            seal: page.view == PageView::Exclusive,
+        };
+        self.pages.push(page);
+        self.publish_pages();
+        Ok(slot)
+    }
+
+    // 映射新页：W^X 模式优先 memfd 双映射，失败则退回独占页
+    fn map_page(&mut self, rwx: bool, pid: i32, fork_gen: u64) -> Result<TrampoPage, Errno> {
+        let shared = slots_per_page(self.page_size, self.trampo_size);
+        let (ptr, view, count) = if rwx {
+            (self.map_anon(writable_prot())?, PageView::Rwx, shared)
+        } else if let Some((ptr, alias)) = self.map_aliased() {
+            (ptr, PageView::Aliased { alias }, shared)
+        } else {
+            (self.map_anon(writable_prot())?, PageView::Exclusive, 1)
+        };
+        Ok(TrampoPage {
+            ptr,
+            view,
+            owner_pid: pid,
+            fork_gen,
+            flags: vec![0u32; count.div_ceil(32)],
+            timestamps: vec![0u64; count],
+        })
+    }
+
+    fn map_anon(&self, prot: i32) -> Result<usize, Errno> {
         let raw = unsafe {
             libc::mmap(
                 ptr::null_mut(),
                 self.page_size,
-                libc::PROT_READ | libc::PROT_WRITE,
+                prot,
                 libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
                 -1,
                 0,
@@ -94,21 +197,84 @@ impl TrampoMgr {
         if raw == libc::MAP_FAILED {
             return Err(Errno::NewTrampo);
         }
+        memory::name_anon_region(raw as usize, self.page_size, VMA_NAME.as_c_str());
+        Ok(raw as usize)
+    }
 
-        let mut page = TrampoPage {
-            ptr: raw as usize,
-            flags: vec![0u32; count.div_ceil(32)],
-            timestamps: vec![0u64; count],
+    // 同一 memfd 映射两次：RX 视图供执行，RW 视图供写入；memfd 名称即 maps 中显示的标签
+    fn map_aliased(&mut self) -> Option<(usize, usize)> {
+        if self.alias_unavailable {
+            return None;
+        }
+        let result = unsafe { self.map_aliased_views() };
+        if result.is_none() {
+            self.alias_unavailable = true;
+            let err = std::io::Error::last_os_error().raw_os_error().unwrap_or(0);
+            log::warn(format_args!(
+                "trampoline memfd alias unavailable: {err}, fallback to one trampoline per page"
+            ));
+        }
+        result
+    }
+
+    unsafe fn map_aliased_views(&self) -> Option<(usize, usize)> {
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_memfd_create,
+                VMA_NAME.as_ptr(),
+                libc::MFD_CLOEXEC as libc::c_uint,
+            )
+        } as libc::c_int;
+        if fd < 0 {
+            return None;
+        }
+        let map_view = |prot: i32| {
+            let raw = unsafe {
+                libc::mmap(
+                    ptr::null_mut(),
+                    self.page_size,
+                    prot,
+                    libc::MAP_SHARED,
+                    fd,
+                    0,
+                )
+            };
+            (raw != libc::MAP_FAILED).then_some(raw as usize)
         };
-        page.flags[0] |= 1;
-        let addr = page.ptr;
-        self.pages.push(page);
-        Ok(addr)
+        let mut views = None;
+        if unsafe { libc::ftruncate(fd, self.page_size as libc::off_t) } == 0
+            && let Some(exec) = map_view(libc::PROT_READ | libc::PROT_EXEC)
+        {
+            match map_view(libc::PROT_READ | libc::PROT_WRITE) {
+                Some(alias) => views = Some((exec, alias)),
+                None => unsafe {
+                    libc::munmap(exec as *mut libc::c_void, self.page_size);
+                },
+            }
+        }
+        unsafe {
+            libc::close(fd);
+        }
+        views
+    }
+
+    fn unmap_page(&self, page: &TrampoPage) {
+        unsafe {
+            libc::munmap(page.ptr as *mut libc::c_void, self.page_size);
+            if let PageView::Aliased { alias } = page.view {
+                libc::munmap(alias as *mut libc::c_void, self.page_size);
+            }
+        }
+    }
+
+    // 页集合变化后发布给 is_hook_artifact 的无锁查询，只登记执行视图
+    fn publish_pages(&self) {
+        let pages = self.pages.iter().map(|page| page.ptr).collect();
+        artifact::publish_trampo_pages(pages, self.page_size);
     }
 
     // 标记槽位为空闲并记录释放时间戳，随后尝试回收空闲页
     fn free(&mut self, trampo: usize) {
-        let count = self.count_per_page();
         let now = now_sec();
 
         for page in &mut self.pages {
@@ -116,13 +282,23 @@ impl TrampoMgr {
                 continue;
             }
             let idx = (trampo - page.ptr) / self.trampo_size;
-            if idx >= count {
+            if idx >= page.slot_count() {
                 continue;
             }
             let flags_idx = idx / 32;
             let mask = 1u32 << (idx % 32);
             page.flags[flags_idx] &= !mask;
             page.timestamps[idx] = now;
+            // 独占页释放后切回分配时的可写保护，延迟销毁已保证不再有执行中的帧；共享页执行视图保持不变
+            if page.view == PageView::Exclusive {
+                unsafe {
+                    libc::mprotect(
+                        page.ptr as *mut libc::c_void,
+                        self.page_size,
+                        writable_prot(),
+                    );
+                }
+            }
             break;
         }
 
@@ -135,6 +311,7 @@ impl TrampoMgr {
             return;
         }
 
+        let mut removed = false;
         let mut idx = 0;
         while idx < self.pages.len() {
             if self.pages.len() <= 1 {
@@ -153,20 +330,27 @@ impl TrampoMgr {
 
             if should_remove {
                 let page = self.pages.swap_remove(idx);
-                unsafe {
-                    libc::munmap(page.ptr as *mut libc::c_void, self.page_size);
-                }
+                removed = true;
+                self.unmap_page(&page);
                 continue;
             }
 
             idx += 1;
         }
+        if removed {
+            self.publish_pages();
+        }
     }
 }
 
 static TRAMPO_MGR: Lazy<Mutex<TrampoMgr>> = Lazy::new(|| Mutex::new(TrampoMgr::new()));
 
-pub(super) fn alloc_trampo() -> Result<usize, Errno> {
+// fork 前调用：此前创建的双映射页此后只释放不再分配；不取页池锁，prepare 中不会阻塞
+pub(super) fn note_fork() {
+    FORK_GENERATION.fetch_add(1, Ordering::AcqRel);
+}
+
+pub(super) fn alloc_trampo() -> Result<TrampoSlot, Errno> {
     let mut mgr = TRAMPO_MGR.lock_or_poison();
     mgr.alloc()
 }
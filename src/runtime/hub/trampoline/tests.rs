@@ -0,0 +1,237 @@
+// Trampoline 机器码生成与页池分配的单元测试，两种架构的模板在任意主机上均可校验
+use super::manager::slots_per_page;
+use super::{
+    DATA_SLOT_COUNT, DATA_SLOT_HUB_PTR, DATA_SLOT_POP_STACK, DATA_SLOT_PUSH_STACK, NATIVE_TEMPLATE,
+    TrampoTemplate, aarch64, emit_trampoline, emit_trampoline_with, trampo_size, x86_64,
+};
+use crate::errno::Errno;
+
+const HUB_ADDR: usize = 0x0000_7f12_3456_7890;
+const PUSH_FN: usize = 0x0000_7f00_aaaa_1000;
+const POP_FN: usize = 0x0000_7f00_bbbb_2000;
+// Android 上最小页大小
+const MIN_PAGE_SIZE: usize = 4096;
+
+fn emit(template: &TrampoTemplate) -> Vec<u8> {
+    let mut buf = vec![0xccu8; template.slot_size()];
+    let written = emit_trampoline_with(template, &mut buf, HUB_ADDR, PUSH_FN, POP_FN)
+        .unwrap_or_else(|err| panic!("{} 模板生成失败: {err:?}", template.name));
+    assert_eq!(written, template.emit_size());
+    buf
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap())
+}
+
+fn data_slot_offset(template: &TrampoTemplate, idx: usize) -> usize {
+    template.code.len() + idx * 8
+}
+
+// 解码 aarch64 `ldr Xt, <literal>`，返回 (Rt, 目标偏移)
+fn decode_aarch64_ldr_literal(buf: &[u8], offset: usize) -> (u32, usize) {
+    let inst = read_u32(buf, offset);
+    assert_eq!(
+        inst & 0xff00_0000,
+        0x5800_0000,
+        "0x{offset:x} 不是 ldr literal"
+    );
+    let imm19 = ((inst >> 5) & 0x7ffff) as usize;
+    (inst & 0x1f, offset + imm19 * 4)
+}
+
+// 解码 x86_64 RIP 相对寻址指令，disp32 位于指令末尾，返回目标偏移
+fn decode_x86_64_rip_target(buf: &[u8], offset: usize, len: usize) -> usize {
+    let disp_offset = offset + len - 4;
+    let disp = i32::from_le_bytes(buf[disp_offset..disp_offset + 4].try_into().unwrap());
+    (offset + len).wrapping_add_signed(disp as isize)
+}
+
+#[test]
+fn aarch64_golden_encoding() {
+    let buf = emit(&aarch64::TEMPLATE);
+    assert_eq!(aarch64::TEMPLATE.code.len(), 0x98);
+    // stp x0, x1, [sp, #-0xd0]!
+    assert_eq!(read_u32(&buf, 0x00), 0xa9b3_07e0);
+    // stp x8, lr, [sp, #0x40]
+    assert_eq!(read_u32(&buf, 0x10), 0xa904_7be8);
+    // mov x1, lr
+    assert_eq!(read_u32(&buf, 0x28), 0xaa1e_03e1);
+    // blr x16
+    assert_eq!(read_u32(&buf, 0x30), 0xd63f_0200);
+    // ldp x0, x1, [sp], #0xd0
+    assert_eq!(read_u32(&buf, 0x58), 0xa8cd_07e0);
+    // sub sp, sp, #0x60
+    assert_eq!(read_u32(&buf, 0x5c), 0xd101_83ff);
+    // blr x17
+    assert_eq!(read_u32(&buf, 0x64), 0xd63f_0220);
+    // stp q2, q3, [sp, #0x40]：HFA/HVA 返回值跨 pop_stack 保留
+    assert_eq!(read_u32(&buf, 0x70), 0xad02_0fe2);
+    // ldp q2, q3, [sp, #0x40]
+    assert_eq!(read_u32(&buf, 0x88), 0xad42_0fe2);
+    // add sp, sp, #0x60
+    assert_eq!(read_u32(&buf, 0x90), 0x9101_83ff);
+    // ret
+    assert_eq!(read_u32(&buf, 0x94), 0xd65f_03c0);
+    assert_eq!(&buf[..0x98], aarch64::TEMPLATE.code);
+}
+
+#[test]
+fn x86_64_golden_encoding() {
+    let buf = emit(&x86_64::TEMPLATE);
+    assert_eq!(x86_64::TEMPLATE.code.len(), 0x13b);
+    // pushq %rbp; movq %rsp, %rbp
+    assert_eq!(&buf[0x00..0x04], &[0x55, 0x48, 0x89, 0xe5]);
+    // subq $192, %rsp
+    assert_eq!(
+        &buf[0x04..0x0b],
+        &[0x48, 0x81, 0xec, 0xc0, 0x00, 0x00, 0x00]
+    );
+    // movq 8(%rbp), %rsi
+    assert_eq!(&buf[0x75..0x79], &[0x48, 0x8b, 0x75, 0x08]);
+    // callq *%r11
+    assert_eq!(&buf[0xf4..0xf7], &[0x41, 0xff, 0xd3]);
+    // subq $72, %rsp：进入 pop_stack 时栈 16 字节对齐
+    assert_eq!(&buf[0xfb..0xff], &[0x48, 0x83, 0xec, 0x48]);
+    // addq $72, %rsp
+    assert_eq!(&buf[0x136..0x13a], &[0x48, 0x83, 0xc4, 0x48]);
+    // retq
+    assert_eq!(buf[0x13a], 0xc3);
+    assert_eq!(&buf[..0x13b], x86_64::TEMPLATE.code);
+}
+
+#[test]
+fn data_slots_hold_relocated_constants() {
+    for template in [&aarch64::TEMPLATE, &x86_64::TEMPLATE] {
+        let buf = emit(template);
+        let slot = |idx| read_u64(&buf, data_slot_offset(template, idx));
+        assert_eq!(
+            slot(DATA_SLOT_PUSH_STACK),
+            PUSH_FN as u64,
+            "{}",
+            template.name
+        );
+        assert_eq!(
+            slot(DATA_SLOT_POP_STACK),
+            POP_FN as u64,
+            "{}",
+            template.name
+        );
+        assert_eq!(
+            slot(DATA_SLOT_HUB_PTR),
+            HUB_ADDR as u64,
+            "{}",
+            template.name
+        );
+        // 数据槽之后的填充区域保持原样
+        let tail = &buf[template.emit_size()..];
+        assert!(tail.iter().all(|byte| *byte == 0xcc), "{}", template.name);
+    }
+}
+
+#[test]
+fn aarch64_literal_loads_target_data_slots() {
+    let template = &aarch64::TEMPLATE;
+    let buf = emit(template);
+    let cases = [
+        (0x24, 0, DATA_SLOT_HUB_PTR),
+        (0x2c, 16, DATA_SLOT_PUSH_STACK),
+        (0x74, 0, DATA_SLOT_HUB_PTR),
+        (0x78, 16, DATA_SLOT_POP_STACK),
+    ];
+    for (offset, reg, slot) in cases {
+        let (rt, target) = decode_aarch64_ldr_literal(&buf, offset);
+        assert_eq!(rt, reg, "0x{offset:x} 目标寄存器错误");
+        assert_eq!(
+            target,
+            data_slot_offset(template, slot),
+            "0x{offset:x} 偏移错误"
+        );
+    }
+}
+
+#[test]
+fn x86_64_rip_relative_loads_target_data_slots() {
+    let template = &x86_64::TEMPLATE;
+    let buf = emit(template);
+    let cases = [
+        (0x6e, 7, DATA_SLOT_HUB_PTR),
+        (0x79, 6, DATA_SLOT_PUSH_STACK),
+        (0x114, 7, DATA_SLOT_HUB_PTR),
+        (0x11b, 6, DATA_SLOT_POP_STACK),
+    ];
+    for (offset, len, slot) in cases {
+        let target = decode_x86_64_rip_target(&buf, offset, len);
+        assert_eq!(
+            target,
+            data_slot_offset(template, slot),
+            "0x{offset:x} 偏移错误"
+        );
+    }
+}
+
+#[test]
+fn emitted_size_fits_pool_slot() {
+    for template in [&aarch64::TEMPLATE, &x86_64::TEMPLATE] {
+        let slot_size = template.slot_size();
+        assert_eq!(
+            template.emit_size(),
+            template.code.len() + DATA_SLOT_COUNT * 8
+        );
+        assert!(template.emit_size() <= slot_size, "{}", template.name);
+        assert_eq!(slot_size % super::TRAMPO_ALIGN, 0, "{}", template.name);
+        assert!(slot_size <= MIN_PAGE_SIZE, "{}", template.name);
+    }
+    assert_eq!(trampo_size(), NATIVE_TEMPLATE.slot_size());
+}
+
+#[test]
+fn shared_page_holds_many_slots() {
+    for template in [&aarch64::TEMPLATE, &x86_64::TEMPLATE] {
+        // x86_64 模板保存 FP/SIMD 返回寄存器后变长，每页仍应容纳多个 Hub
+        let count = slots_per_page(MIN_PAGE_SIZE, template.slot_size());
+        assert!(count >= 8, "{} 每页仅 {count} 个槽位", template.name);
+        assert!(count * template.slot_size() <= MIN_PAGE_SIZE);
+    }
+    assert_eq!(slots_per_page(MIN_PAGE_SIZE, 0), 0);
+}
+
+#[test]
+fn emit_rejects_short_buffer_and_null_addrs() {
+    let mut short = vec![0u8; NATIVE_TEMPLATE.emit_size() - 1];
+    assert_eq!(
+        emit_trampoline(&mut short, HUB_ADDR, PUSH_FN, POP_FN),
+        Err(Errno::InitErrTrampo)
+    );
+
+    let mut buf = vec![0u8; trampo_size()];
+    assert_eq!(
+        emit_trampoline(&mut buf, 0, PUSH_FN, POP_FN),
+        Err(Errno::InvalidArg)
+    );
+    assert_eq!(
+        emit_trampoline(&mut buf, HUB_ADDR, 0, POP_FN),
+        Err(Errno::InvalidArg)
+    );
+    assert_eq!(
+        emit_trampoline(&mut buf, HUB_ADDR, PUSH_FN, 0),
+        Err(Errno::InvalidArg)
+    );
+    assert!(buf.iter().all(|byte| *byte == 0));
+}
+
+// fork 之后不再向 fork 前的共享页分配槽位，新槽位总在新页上
+#[test]
+fn fork_moves_allocation_to_new_page() {
+    let page_of = |addr: usize| addr & !(MIN_PAGE_SIZE - 1);
+    let before = super::alloc_trampo().expect("alloc before fork");
+    super::note_fork();
+    let after = super::alloc_trampo().expect("alloc after fork");
+    assert_ne!(page_of(before.exec), page_of(after.exec));
+    super::free_trampo(before.exec);
+    super::free_trampo(after.exec);
+}
@@ -0,0 +1,73 @@
+// x86_64 trampoline 模板机器码（AT&T 语法注释）
+// 逻辑同 aarch64：保存寄存器 -> push_stack -> 调用 proxy -> pop_stack -> 恢复并返回
+// push_stack 前保存 rdi/rsi/rdx/rcx/r8/r9、rax（变参向量寄存器个数）、r10（静态链）与 xmm0-xmm7；
+// pop_stack 前保存返回值 rax/rdx 与 xmm0/xmm1，预留 72 字节使进入 pop_stack 时栈满足 16 字节对齐
+// 数据槽紧跟代码末尾（0x13b），通过 RIP 相对寻址读取，修改指令时需同步调整 disp32
+use super::TrampoTemplate;
+
+#[rustfmt::skip]
+const CODE: [u8; 0x13b] = [
+    0x55, // 0x0000: pushq %rbp
+    0x48, 0x89, 0xe5, // 0x0001: movq %rsp, %rbp
+    0x48, 0x81, 0xec, 0xc0, 0x00, 0x00, 0x00, // 0x0004: subq $192, %rsp
+    0x66, 0x0f, 0x11, 0x84, 0x24, 0xb0, 0x00, 0x00, 0x00, // 0x000b: movupd %xmm0, 176(%rsp)
+    0x66, 0x0f, 0x11, 0x8c, 0x24, 0xa0, 0x00, 0x00, 0x00, // 0x0014: movupd %xmm1, 160(%rsp)
+    0x66, 0x0f, 0x11, 0x94, 0x24, 0x90, 0x00, 0x00, 0x00, // 0x001d: movupd %xmm2, 144(%rsp)
+    0x66, 0x0f, 0x11, 0x9c, 0x24, 0x80, 0x00, 0x00, 0x00, // 0x0026: movupd %xmm3, 128(%rsp)
+    0x66, 0x0f, 0x11, 0x64, 0x24, 0x70, // 0x002f: movupd %xmm4, 112(%rsp)
+    0x66, 0x0f, 0x11, 0x6c, 0x24, 0x60, // 0x0035: movupd %xmm5, 96(%rsp)
+    0x66, 0x0f, 0x11, 0x74, 0x24, 0x50, // 0x003b: movupd %xmm6, 80(%rsp)
+    0x66, 0x0f, 0x11, 0x7c, 0x24, 0x40, // 0x0041: movupd %xmm7, 64(%rsp)
+    0x48, 0x89, 0x44, 0x24, 0x38, // 0x0047: movq %rax, 56(%rsp)
+    0x48, 0x89, 0x7c, 0x24, 0x30, // 0x004c: movq %rdi, 48(%rsp)
+    0x48, 0x89, 0x74, 0x24, 0x28, // 0x0051: movq %rsi, 40(%rsp)
+    0x48, 0x89, 0x54, 0x24, 0x20, // 0x0056: movq %rdx, 32(%rsp)
+    0x48, 0x89, 0x4c, 0x24, 0x18, // 0x005b: movq %rcx, 24(%rsp)
+    0x4c, 0x89, 0x44, 0x24, 0x10, // 0x0060: movq %r8, 16(%rsp)
+    0x4c, 0x89, 0x4c, 0x24, 0x08, // 0x0065: movq %r9, 8(%rsp)
+    0x4c, 0x89, 0x14, 0x24, // 0x006a: movq %r10, (%rsp)
+    0x48, 0x8b, 0x3d, 0xd6, 0x00, 0x00, 0x00, // 0x006e: movq hub_ptr(%rip), %rdi
+    0x48, 0x8b, 0x75, 0x08, // 0x0075: movq 8(%rbp), %rsi
+    0xff, 0x15, 0xbc, 0x00, 0x00, 0x00, // 0x0079: call *push_stack(%rip)
+    0x49, 0x89, 0xc3, // 0x007f: movq %rax, %r11
+    0x66, 0x0f, 0x10, 0x84, 0x24, 0xb0, 0x00, 0x00, 0x00, // 0x0082: movupd 176(%rsp), %xmm0
+    0x66, 0x0f, 0x10, 0x8c, 0x24, 0xa0, 0x00, 0x00, 0x00, // 0x008b: movupd 160(%rsp), %xmm1
+    0x66, 0x0f, 0x10, 0x94, 0x24, 0x90, 0x00, 0x00, 0x00, // 0x0094: movupd 144(%rsp), %xmm2
+    0x66, 0x0f, 0x10, 0x9c, 0x24, 0x80, 0x00, 0x00, 0x00, // 0x009d: movupd 128(%rsp), %xmm3
+    0x66, 0x0f, 0x10, 0x64, 0x24, 0x70, // 0x00a6: movupd 112(%rsp), %xmm4
+    0x66, 0x0f, 0x10, 0x6c, 0x24, 0x60, // 0x00ac: movupd 96(%rsp), %xmm5
+    0x66, 0x0f, 0x10, 0x74, 0x24, 0x50, // 0x00b2: movupd 80(%rsp), %xmm6
+    0x66, 0x0f, 0x10, 0x7c, 0x24, 0x40, // 0x00b8: movupd 64(%rsp), %xmm7
+    0x48, 0x8b, 0x44, 0x24, 0x38, // 0x00be: movq 56(%rsp), %rax
+    0x48, 0x8b, 0x7c, 0x24, 0x30, // 0x00c3: movq 48(%rsp), %rdi
+    0x48, 0x8b, 0x74, 0x24, 0x28, // 0x00c8: movq 40(%rsp), %rsi
+    0x48, 0x8b, 0x54, 0x24, 0x20, // 0x00cd: movq 32(%rsp), %rdx
+    0x48, 0x8b, 0x4c, 0x24, 0x18, // 0x00d2: movq 24(%rsp), %rcx
+    0x4c, 0x8b, 0x44, 0x24, 0x10, // 0x00d7: movq 16(%rsp), %r8
+    0x4c, 0x8b, 0x4c, 0x24, 0x08, // 0x00dc: movq 8(%rsp), %r9
+    0x4c, 0x8b, 0x14, 0x24, // 0x00e1: movq (%rsp), %r10
+    0x48, 0x81, 0xc4, 0xc0, 0x00, 0x00, 0x00, // 0x00e5: addq $192, %rsp
+    0x48, 0x89, 0xec, // 0x00ec: movq %rbp, %rsp
+    0x5d, // 0x00ef: popq %rbp
+    0x48, 0x83, 0xec, 0x08, // 0x00f0: subq $8, %rsp
+    0x41, 0xff, 0xd3, // 0x00f4: call *%r11
+    0x48, 0x83, 0xc4, 0x08, // 0x00f7: addq $8, %rsp
+    0x48, 0x83, 0xec, 0x48, // 0x00fb: subq $72, %rsp
+    0x48, 0x89, 0x04, 0x24, // 0x00ff: movq %rax, (%rsp)
+    0x48, 0x89, 0x54, 0x24, 0x08, // 0x0103: movq %rdx, 8(%rsp)
+    0x66, 0x0f, 0x11, 0x44, 0x24, 0x10, // 0x0108: movupd %xmm0, 16(%rsp)
+    0x66, 0x0f, 0x11, 0x4c, 0x24, 0x20, // 0x010e: movupd %xmm1, 32(%rsp)
+    0x48, 0x8b, 0x3d, 0x30, 0x00, 0x00, 0x00, // 0x0114: movq hub_ptr(%rip), %rdi
+    0xff, 0x15, 0x22, 0x00, 0x00, 0x00, // 0x011b: call *pop_stack(%rip)
+    0x48, 0x8b, 0x04, 0x24, // 0x0121: movq (%rsp), %rax
+    0x48, 0x8b, 0x54, 0x24, 0x08, // 0x0125: movq 8(%rsp), %rdx
+    0x66, 0x0f, 0x10, 0x44, 0x24, 0x10, // 0x012a: movupd 16(%rsp), %xmm0
+    0x66, 0x0f, 0x10, 0x4c, 0x24, 0x20, // 0x0130: movupd 32(%rsp), %xmm1
+    0x48, 0x83, 0xc4, 0x48, // 0x0136: addq $72, %rsp
+    0xc3, // 0x013a: ret
+];
+
+pub(super) const TEMPLATE: TrampoTemplate = TrampoTemplate {
+    name: "x86_64",
+    code: &CODE,
+};
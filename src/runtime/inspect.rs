@@ -0,0 +1,389 @@
+// 模块只读检视：打开时持有 dlopen 引用防止模块卸载，ELF 只解析一次，之后的查询复用解析结果
+use crate::android::signal_guard::{self, GuardPhase};
+use crate::api::{
+    ChainPreview, HookStub, ModuleIdentity, ProxyChain, ProxyChainEntry, ResolvedSymbol,
+};
+use crate::elf::Elf;
+use crate::errno::Errno;
+use std::ffi::{CString, c_void};
+
+use super::hub;
+use super::refresh;
+use super::state::{
+    CoreState, GLOBAL, ModuleInfo, MutexPoisonRecover, RwLockPoisonRecover, SlotEntry, SlotKey,
+    is_resetting,
+};
+
+// 单次符号遍历的条目上限，防止损坏的 hash 表导致超长遍历
+const INSPECT_SYMBOL_LIMIT: u32 = 1 << 20;
+
+pub(crate) struct Inspection {
+    module: ModuleInfo,
+    elf: Elf,
+    pin: *mut c_void,
+}
+
+impl Drop for Inspection {
+    fn drop(&mut self) {
+        unsafe {
+            libc::dlclose(self.pin);
+        }
+    }
+}
+
+pub(crate) fn open_module(rule: &str) -> Result<Inspection, Errno> {
+    let module = refresh::find_loaded_module(rule).ok_or(Errno::NotFound)?;
+    pin_module(module)
+}
+
+fn pin_module(module: ModuleInfo) -> Result<Inspection, Errno> {
+    let pathname = CString::new(module.pathname.as_str()).map_err(|_| Errno::InvalidArg)?;
+    // RTLD_NOLOAD 只增加已加载模块的引用计数，不会触发新的加载
+    let pin = unsafe { libc::dlopen(pathname.as_ptr(), libc::RTLD_NOW | libc::RTLD_NOLOAD) };
+    if pin.is_null() {
+        return Err(Errno::NotFound);
+    }
+    // 同一路径可能在其他 linker namespace 中另有实例，RTLD_NOLOAD 返回的 handle 须解析回同一基址；
+    // 持有引用后再确认实例未变，排除枚举与 dlopen 之间被卸载并重新加载的情况
+    let pinned_same_instance = refresh::module_identity_from_handle(pin)
+        .is_some_and(|pinned| pinned.base_addr == module.base_addr);
+    if !pinned_same_instance || !refresh::is_module_loaded(&module) {
+        unsafe {
+            libc::dlclose(pin);
+        }
+        return Err(Errno::NotFound);
+    }
+    match refresh::open_module_elf(&module) {
+        Ok(elf) => Ok(Inspection { module, elf, pin }),
+        Err(err) => {
+            unsafe {
+                libc::dlclose(pin);
+            }
+            Err(err)
+        }
+    }
+}
+
+// 逐个打开匹配规则的模块实例并投递其导入或导出符号名，回调期间持有模块引用，返回 false 时停止
+pub(crate) fn for_each_module_symbol(
+    rule: &str,
+    exports: bool,
+    callback: &mut dyn FnMut(&ModuleIdentity, &str) -> bool,
+) -> Errno {
+    visit_modules(rule, exports, &mut |identity, names| {
+        names.all(|name| callback(&identity, &name))
+    })
+}
+
+pub(crate) fn list_module_symbols(rule: &str, exports: bool) -> Vec<(ModuleIdentity, Vec<String>)> {
+    let mut modules = Vec::new();
+    visit_modules(rule, exports, &mut |identity, names| {
+        modules.push((identity, names.collect()));
+        true
+    });
+    modules
+}
+
+// 枚举后被卸载或无法解析的实例跳过，没有任何实例可读时返回 NotFound
+fn visit_modules(
+    rule: &str,
+    exports: bool,
+    visit: &mut dyn FnMut(ModuleIdentity, &mut SymbolNames<'_>) -> bool,
+) -> Errno {
+    let mut visited = false;
+    for module in refresh::find_loaded_modules(rule) {
+        let Ok(inspection) = pin_module(module) else {
+            continue;
+        };
+        let identity = inspection.identity();
+        let mut names = if exports {
+            SymbolNames::Exports(inspection.symbols(true))
+        } else {
+            match inspection.import_names() {
+                Ok(names) => SymbolNames::Imports(names.into_iter()),
+                Err(_) => continue,
+            }
+        };
+        let completed = visit(identity, &mut names);
+        visited = true;
+        if !completed {
+            break;
+        }
+    }
+    if visited { Errno::Ok } else { Errno::NotFound }
+}
+
+// 匹配 caller 规则的各模块实例中该符号的 GOT slot 及其当前值，只读，不要求 init
+// 持有 dlclose 读锁；解析或读取失败的实例跳过
+pub(crate) fn import_slot_values(
+    caller_rule: &str,
+    sym_name: &str,
+) -> Result<Vec<(ModuleIdentity, usize, usize)>, Errno> {
+    let _dlclose_guard = read_guard()?;
+    let mut values = Vec::new();
+    for module in refresh::find_loaded_modules(caller_rule) {
+        let Ok(elf) = refresh::open_module_elf(&module) else {
+            continue;
+        };
+        let Ok(slots) = refresh::find_module_slots(&elf, sym_name) else {
+            continue;
+        };
+        for slot in slots.into_vec() {
+            if let Ok(value) = refresh::read_module_slot(slot) {
+                values.push((module_identity(&module), slot, value));
+            }
+        }
+    }
+    Ok(values)
+}
+
+// 匹配 caller 规则的各模块实例中已由 hub 接管的 slot 的调用链，未挂载的 slot 跳过
+// 持有 dlclose 读锁与 state 锁，hub 节点在 hub 锁内读取
+pub(crate) fn proxy_chains(caller_rule: &str, sym_name: &str) -> Result<Vec<ProxyChain>, Errno> {
+    let _dlclose_guard = read_guard()?;
+    let state = GLOBAL.state.lock_or_poison();
+    let mut chains = Vec::new();
+    for module in refresh::find_loaded_modules(caller_rule) {
+        let Ok(elf) = refresh::open_module_elf(&module) else {
+            continue;
+        };
+        let Ok(slots) = refresh::find_module_slots(&elf, sym_name) else {
+            continue;
+        };
+        for slot_addr in slots.into_vec() {
+            let key = SlotKey {
+                caller_path_name: module.pathname.clone(),
+                caller_base_addr: module.base_addr,
+                caller_instance_id: module.instance_id,
+                caller_namespace_id: module.namespace_id,
+                slot_addr,
+            };
+            let Some(slot) = state.slots.get(&key).filter(|slot| slot.hub_ptr != 0) else {
+                continue;
+            };
+            chains.push(proxy_chain(&state, &module, slot_addr, slot));
+        }
+    }
+    Ok(chains)
+}
+
+fn proxy_chain(
+    state: &CoreState,
+    module: &ModuleInfo,
+    slot_addr: usize,
+    slot: &SlotEntry,
+) -> ProxyChain {
+    let hub_ptr = slot.hub_ptr as *mut hub::Hub;
+    let entries = hub::proxy_chain(hub_ptr)
+        .into_iter()
+        .map(
+            |(proxy_addr, enabled, ref_count, priority)| ProxyChainEntry {
+                proxy_addr,
+                enabled,
+                ref_count,
+                priority,
+                stub: slot.task_chain.iter().copied().find(|stub| {
+                    state
+                        .tasks
+                        .get(stub)
+                        .is_some_and(|task| task.new_func == proxy_addr)
+                }),
+            },
+        )
+        .collect();
+    ProxyChain {
+        module: module_identity(module),
+        slot_addr,
+        entries,
+        orig_func: hub::hub_tail(hub_ptr),
+    }
+}
+
+// 匹配 callee 规则的各模块实例中该符号的定义地址，全局定义在前、弱定义在后
+pub(crate) fn export_addresses(
+    callee_rule: &str,
+    sym_name: &str,
+) -> Result<Vec<(ModuleIdentity, usize)>, Errno> {
+    let _dlclose_guard = read_guard()?;
+    let mut addrs = Vec::new();
+    for module in refresh::find_loaded_modules(callee_rule) {
+        let Ok(elf) = refresh::open_module_elf(&module) else {
+            continue;
+        };
+        let Ok(exports) = refresh::find_module_exports(&elf, sym_name) else {
+            continue;
+        };
+        let identity = module_identity(&module);
+        addrs.extend(exports.into_iter().map(|addr| (identity.clone(), addr)));
+    }
+    Ok(addrs)
+}
+
+// 复位进行中时拒绝；复位持有 dlclose 读锁期间同样可读，因此在取锁前后各检查一次
+fn read_guard() -> Result<std::sync::RwLockReadGuard<'static, ()>, Errno> {
+    if is_resetting() {
+        return Err(Errno::Resetting);
+    }
+    let guard = GLOBAL.dlclose_lock.read_or_poison();
+    if is_resetting() {
+        return Err(Errno::Resetting);
+    }
+    Ok(guard)
+}
+
+// 持有 dlclose 读锁期间预览调用链，避免读取过程中模块被卸载
+pub(crate) fn inspect_chain(
+    caller_rule: &str,
+    callee_rule: Option<&str>,
+    sym_name: &str,
+) -> Result<Vec<ChainPreview>, Errno> {
+    let _dlclose_guard = GLOBAL.dlclose_lock.read_or_poison();
+    let state = GLOBAL.state.lock_or_poison();
+    refresh::preview_chain(&state, caller_rule, callee_rule, sym_name)
+}
+
+pub(crate) fn resolve_address(addr: usize) -> Option<ResolvedSymbol> {
+    let _dlclose_guard = GLOBAL.dlclose_lock.read_or_poison();
+    resolve_address_locked(addr)
+}
+
+// 调用方需持有 dlclose 读锁：dladdr 只定位所属模块，符号由动态符号表按范围查找
+pub(super) fn resolve_address_locked(addr: usize) -> Option<ResolvedSymbol> {
+    let module = refresh::module_identity_from_addr(addr as *const c_void)?;
+    let elf = refresh::open_module_elf(&module).ok()?;
+    let (name, start, size) = signal_guard::with_guard_tagged(GuardPhase::Inspect, || unsafe {
+        elf.find_symbol_by_addr(addr)
+            .map(|(name, start, size)| (name.to_string(), start, size))
+    })
+    .ok()
+    .flatten()?;
+    Some(ResolvedSymbol {
+        module: module_identity(&module),
+        name,
+        addr: start,
+        size,
+    })
+}
+
+fn module_identity(module: &ModuleInfo) -> ModuleIdentity {
+    ModuleIdentity {
+        pathname: module.pathname.clone(),
+        base_addr: module.base_addr,
+        instance_id: module.instance_id,
+        namespace_id: module.namespace_id,
+    }
+}
+
+impl Inspection {
+    pub(crate) fn identity(&self) -> ModuleIdentity {
+        module_identity(&self.module)
+    }
+
+    pub(crate) fn symbols(&self, defined: bool) -> SymbolIter<'_> {
+        let count = self.elf.dynsym_count();
+        SymbolIter {
+            elf: &self.elf,
+            next_idx: 1,
+            count: count.min(INSPECT_SYMBOL_LIMIT),
+            defined,
+        }
+    }
+
+    // 重定位表（含 Android 压缩重定位）引用的符号名，去重并排序
+    pub(crate) fn import_names(&self) -> Result<Vec<String>, Errno> {
+        refresh::find_module_imports(&self.elf)
+    }
+
+    pub(crate) fn got_slots_for(&self, symbol: &str) -> Result<Vec<usize>, Errno> {
+        refresh::find_module_slots(&self.elf, symbol).map(crate::elf::GotSlots::into_vec)
+    }
+
+    pub(crate) fn relro_range(&self) -> Option<(usize, usize)> {
+        self.elf.relro_range()
+    }
+
+    pub(crate) fn is_addr_in_relro(&self, addr: usize) -> bool {
+        self.elf.is_addr_in_relro(addr)
+    }
+
+    pub(crate) fn build_id(&self) -> Option<Vec<u8>> {
+        signal_guard::with_guard_tagged(GuardPhase::Inspect, || unsafe { self.elf.build_id() })
+            .ok()
+            .flatten()
+    }
+
+    // 当前在本模块该符号 slot 上生效的任务，按 slot 内的链顺序去重
+    pub(crate) fn hooked_by(&self, symbol: &str) -> Vec<HookStub> {
+        let state = GLOBAL.state.lock_or_poison();
+        let mut stubs = Vec::new();
+        for (key, slot) in &state.slots {
+            if key.caller_path_name != self.module.pathname
+                || key.caller_base_addr != self.module.base_addr
+                || key.caller_instance_id != self.module.instance_id
+                || key.caller_namespace_id != self.module.namespace_id
+            {
+                continue;
+            }
+            for stub in &slot.task_chain {
+                let matched = state
+                    .tasks
+                    .get(stub)
+                    .is_some_and(|task| task.sym_name == symbol);
+                if matched && !stubs.contains(stub) {
+                    stubs.push(*stub);
+                }
+            }
+        }
+        stubs
+    }
+}
+
+// 导入名已在信号保护下一次读出，导出名按条目惰性读取
+enum SymbolNames<'a> {
+    Imports(std::vec::IntoIter<String>),
+    Exports(SymbolIter<'a>),
+}
+
+impl Iterator for SymbolNames<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        match self {
+            Self::Imports(names) => names.next(),
+            Self::Exports(names) => names.next(),
+        }
+    }
+}
+
+// 按需读取动态符号表，每个条目在信号保护下读取，读取失败时结束遍历
+pub(crate) struct SymbolIter<'a> {
+    elf: &'a Elf,
+    next_idx: u32,
+    count: u32,
+    defined: bool,
+}
+
+impl Iterator for SymbolIter<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        while self.next_idx < self.count {
+            let idx = self.next_idx;
+            self.next_idx += 1;
+            let entry = signal_guard::with_guard_tagged(GuardPhase::Inspect, || unsafe {
+                self.elf
+                    .dynsym_entry(idx)
+                    .map(|(name, defined)| (name.to_string(), defined))
+            });
+            match entry {
+                Ok(Some((name, defined))) if defined == self.defined => return Some(name),
+                Ok(_) => {}
+                Err(_) => {
+                    self.next_idx = self.count;
+                    return None;
+                }
+            }
+        }
+        None
+    }
+}
@@ -1,17 +1,28 @@
 // 生命周期管理模块，作为 runtime 子模块的统一入口
 // 将 hook/unhook/refresh/控制/回调等操作分发到各子模块
 use crate::api::{
-    CallerAllowFilter, HookMode, HookStub, HookedCallback, ModuleIdentity, PostDlopenCallback,
-    PreDlopenCallback,
+    CallbackDispatch, CalleeSelection, CallerAllowFilter, CallerAllowFilterEx, Capabilities,
+    CfiPatchEntry, DlopenFilterCallback, GuardFaultStats, GuardSlotStats, HookEventCallback,
+    HookMode, HookSpec, HookStats, HookStub, HookedCallback, HubStackStats, InitOptions,
+    LinkerFallbackStats, LogLevel, LogSink, ModuleIdentity, MonitorRefreshCounts, MonitorStatus,
+    PostDlcloseCallback, PostDlopenCallback, PreDlcloseCallback, PreDlopenCallback, RecordCallback,
+    RecordEntry, SafeReadStats, SingleBindPolicy, TaskInfo, ThreadStateStats,
 };
 use crate::errno::Errno;
 use std::ffi::{c_char, c_void};
+use std::time::Duration;
 
+mod atfork;
+mod callback_dispatch;
+mod capabilities;
+mod dlclose_callbacks;
 mod dlopen_callbacks;
 mod monitor;
 mod monitor_calls;
 mod process;
 mod proxy;
+mod record_listener;
+mod state_dump;
 mod task_ops;
 
 mod entry_control;
@@ -22,8 +33,12 @@ pub(super) fn get_version() -> String {
     entry_init::get_version()
 }
 
-pub(super) fn init(mode: HookMode, debug: bool) -> Errno {
-    entry_init::init(mode, debug)
+pub(super) fn init_with_options(opts: InitOptions) -> Errno {
+    entry_init::init(opts)
+}
+
+pub(super) fn reinit_after_fork() -> Errno {
+    entry_init::reinit_after_fork()
 }
 
 pub(super) fn hook_single(
@@ -33,7 +48,7 @@ pub(super) fn hook_single(
     new_func: *mut c_void,
     hooked: Option<HookedCallback>,
     hooked_arg: *mut c_void,
-) -> Option<HookStub> {
+) -> Result<HookStub, Errno> {
     entry_hook::hook_single(
         caller_path_name,
         callee_path_name,
@@ -44,6 +59,88 @@ pub(super) fn hook_single(
     )
 }
 
+pub(super) fn hook_single_with_priority(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    priority: i32,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    entry_hook::hook_single_with_priority(
+        caller_path_name,
+        callee_path_name,
+        sym_name,
+        new_func,
+        priority,
+        hooked,
+        hooked_arg,
+    )
+}
+
+pub(super) fn hook_single_for_threads(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    tids: &[i32],
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    entry_hook::hook_single_for_threads(
+        caller_path_name,
+        callee_path_name,
+        sym_name,
+        new_func,
+        tids,
+        hooked,
+        hooked_arg,
+    )
+}
+
+pub(super) fn hook_single_with_policy(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    policy: SingleBindPolicy,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    entry_hook::hook_single_with_policy(
+        caller_path_name,
+        callee_path_name,
+        sym_name,
+        new_func,
+        policy,
+        hooked,
+        hooked_arg,
+    )
+}
+
+pub(super) fn hook_batch(specs: &[HookSpec]) -> Vec<Result<HookStub, Errno>> {
+    entry_hook::hook_batch(specs)
+}
+
+pub(super) fn hook_single_pattern(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_pattern: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    entry_hook::hook_single_pattern(
+        caller_path_name,
+        callee_path_name,
+        sym_pattern,
+        new_func,
+        hooked,
+        hooked_arg,
+    )
+}
+
 pub(super) fn hook_partial(
     caller_allow_filter: CallerAllowFilter,
     caller_allow_filter_arg: *mut c_void,
@@ -52,7 +149,7 @@ pub(super) fn hook_partial(
     new_func: *mut c_void,
     hooked: Option<HookedCallback>,
     hooked_arg: *mut c_void,
-) -> Option<HookStub> {
+) -> Result<HookStub, Errno> {
     entry_hook::hook_partial(
         caller_allow_filter,
         caller_allow_filter_arg,
@@ -64,24 +161,118 @@ pub(super) fn hook_partial(
     )
 }
 
+pub(super) fn hook_partial_ex(
+    caller_allow_filter: CallerAllowFilterEx,
+    caller_allow_filter_arg: *mut c_void,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    entry_hook::hook_partial_ex(
+        caller_allow_filter,
+        caller_allow_filter_arg,
+        callee_path_name,
+        sym_name,
+        new_func,
+        hooked,
+        hooked_arg,
+    )
+}
+
 pub(super) fn hook_all(
     callee_path_name: Option<&str>,
     sym_name: &str,
     new_func: *mut c_void,
     hooked: Option<HookedCallback>,
     hooked_arg: *mut c_void,
-) -> Option<HookStub> {
+) -> Result<HookStub, Errno> {
     entry_hook::hook_all(callee_path_name, sym_name, new_func, hooked, hooked_arg)
 }
 
+pub(super) fn hook_all_with_callers(
+    callers: &[&str],
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    entry_hook::hook_all_with_callers(
+        callers,
+        callee_path_name,
+        sym_name,
+        new_func,
+        hooked,
+        hooked_arg,
+    )
+}
+
+pub(super) fn get_hook_tasks() -> Vec<TaskInfo> {
+    entry_hook::get_hook_tasks()
+}
+
 pub(super) fn unhook(stub: HookStub) -> Errno {
     entry_hook::unhook(stub)
 }
 
+pub(super) fn unhook_symbol(sym_name: &str, caller_rule: Option<&str>) -> (usize, Errno) {
+    entry_hook::unhook_symbol(sym_name, caller_rule)
+}
+
+pub(super) fn pause(stub: HookStub) -> Errno {
+    entry_hook::set_paused(stub, true)
+}
+
+pub(super) fn resume(stub: HookStub) -> Errno {
+    entry_hook::set_paused(stub, false)
+}
+
+pub(super) fn set_hook_event_callback(
+    stub: HookStub,
+    callback: Option<HookEventCallback>,
+    arg: *mut c_void,
+) -> Errno {
+    entry_hook::set_hook_event_callback(stub, callback, arg)
+}
+
+pub(super) fn set_task_threads(stub: HookStub, tids: &[i32]) -> Errno {
+    entry_hook::set_task_threads(stub, tids)
+}
+
+pub(super) fn set_hook_stats_enabled(stub: HookStub, enabled: bool) -> Errno {
+    entry_hook::set_hook_stats_enabled(stub, enabled)
+}
+
+pub(super) fn get_hook_stats(stub: HookStub) -> Option<HookStats> {
+    entry_hook::get_hook_stats(stub)
+}
+
+pub(super) fn get_orig_func(stub: HookStub) -> Option<*mut c_void> {
+    entry_hook::get_orig_func(stub)
+}
+
+pub(super) fn get_orig_funcs(stub: HookStub) -> Vec<(String, *mut c_void)> {
+    entry_hook::get_orig_funcs(stub)
+}
+
+pub(super) fn set_callee_selection(stub: HookStub, selection: CalleeSelection) -> Errno {
+    entry_hook::set_callee_selection(stub, selection)
+}
+
 pub(super) fn add_ignore(caller_path_name: &str) -> Errno {
     entry_hook::add_ignore(caller_path_name)
 }
 
+pub(super) fn del_ignore(caller_path_name: &str) -> Errno {
+    entry_hook::del_ignore(caller_path_name)
+}
+
+pub(super) fn get_ignores() -> Vec<String> {
+    entry_hook::get_ignores()
+}
+
 pub(super) fn get_module_identity(handle: *mut c_void) -> Option<ModuleIdentity> {
     entry_hook::get_module_identity(handle)
 }
@@ -97,10 +288,42 @@ pub(super) fn refresh() -> Errno {
     entry_hook::refresh()
 }
 
+pub(super) fn refresh_handle(handle: *mut c_void) -> Errno {
+    entry_hook::refresh_handle(handle)
+}
+
+pub(super) fn request_monitor_refresh(handle: Option<*mut c_void>) -> Errno {
+    entry_hook::request_monitor_refresh(handle)
+}
+
 pub(super) fn clear() {
     entry_control::clear();
 }
 
+pub(super) fn shutdown(revert_cfi: bool) -> Errno {
+    entry_control::shutdown(revert_cfi)
+}
+
+pub(super) fn restore_cfi_slowpath() -> Errno {
+    entry_control::restore_cfi_slowpath()
+}
+
+pub(super) fn cfi_patch_report() -> Vec<CfiPatchEntry> {
+    entry_control::cfi_patch_report()
+}
+
+pub(super) fn clear_and_drain(timeout: Duration) -> bool {
+    entry_control::clear_and_drain(timeout)
+}
+
+pub(super) fn drain_retired_hubs(timeout: Duration) -> bool {
+    entry_control::drain_retired_hubs(timeout)
+}
+
+pub(super) fn set_mode(mode: HookMode) -> Errno {
+    entry_init::set_mode(mode)
+}
+
 pub(super) fn get_mode() -> HookMode {
     entry_control::get_mode()
 }
@@ -125,6 +348,46 @@ pub(super) fn get_records(item_flags: u32) -> Option<String> {
     entry_control::get_records(item_flags)
 }
 
+pub(super) fn get_record_entries(item_flags: u32) -> Vec<RecordEntry> {
+    entry_control::get_record_entries(item_flags)
+}
+
+pub(super) fn set_log_level(level: LogLevel) {
+    entry_control::set_log_level(level);
+}
+
+pub(super) fn get_log_level() -> LogLevel {
+    entry_control::get_log_level()
+}
+
+pub(super) fn set_log_sink(sink: LogSink, arg: *mut c_void, keep_logcat: bool) {
+    entry_control::set_log_sink(sink, arg, keep_logcat);
+}
+
+pub(super) fn clear_log_sink() {
+    entry_control::clear_log_sink();
+}
+
+pub(super) fn set_record_capacity(max_entries: usize) -> Errno {
+    entry_control::set_record_capacity(max_entries)
+}
+
+pub(super) fn get_record_capacity() -> usize {
+    entry_control::get_record_capacity()
+}
+
+pub(super) fn get_record_dropped_count() -> u64 {
+    entry_control::get_record_dropped_count()
+}
+
+pub(super) fn set_record_listener(callback: RecordCallback, arg: *mut c_void) -> Errno {
+    record_listener::set_listener(callback, arg)
+}
+
+pub(super) fn clear_record_listener() -> Errno {
+    record_listener::clear_listener()
+}
+
 pub(super) fn dump_records(fd: i32, item_flags: u32) -> Errno {
     entry_control::dump_records(fd, item_flags)
 }
@@ -133,6 +396,146 @@ pub(super) fn enable_sigsegv_protection(flag: bool) {
     entry_control::enable_sigsegv_protection(flag)
 }
 
+pub(super) fn enable_extended_signal_guard(flag: bool) {
+    entry_control::enable_extended_signal_guard(flag)
+}
+
+pub(super) fn enable_sigchain_front_reassert(flag: bool) {
+    entry_control::enable_sigchain_front_reassert(flag)
+}
+
+pub(super) fn sigchain_reassert_count() -> u64 {
+    entry_control::sigchain_reassert_count()
+}
+
+pub(super) fn fork_prev_fallback_count() -> u64 {
+    entry_control::fork_prev_fallback_count()
+}
+
+pub(super) fn hub_stack_stats() -> HubStackStats {
+    entry_control::hub_stack_stats()
+}
+
+pub(super) fn thread_state_stats() -> ThreadStateStats {
+    entry_control::thread_state_stats()
+}
+
+pub(super) fn reset_thread_state_stats() {
+    entry_control::reset_thread_state_stats()
+}
+
+pub(super) fn enable_header_file_fallback(flag: bool) {
+    entry_control::enable_header_file_fallback(flag)
+}
+
+pub(super) fn enable_write_verification(flag: bool) {
+    entry_control::enable_write_verification(flag)
+}
+
+pub(super) fn enable_raw_mprotect(flag: bool) {
+    entry_control::enable_raw_mprotect(flag)
+}
+
+pub(super) fn set_slot_guard(flag: bool) {
+    entry_control::set_slot_guard(flag)
+}
+
+pub(super) fn is_slot_guard_enabled() -> bool {
+    entry_control::is_slot_guard_enabled()
+}
+
+pub(super) fn set_pattern_match_limit(limit: usize) -> Errno {
+    entry_control::set_pattern_match_limit(limit)
+}
+
+pub(super) fn get_pattern_match_limit() -> usize {
+    entry_control::get_pattern_match_limit()
+}
+
+pub(super) fn enable_linker_internal_fallback(flag: bool) {
+    entry_control::enable_linker_internal_fallback(flag)
+}
+
+pub(super) fn linker_fallback_stats() -> LinkerFallbackStats {
+    entry_control::linker_fallback_stats()
+}
+
+pub(super) fn write_rejected_page_count() -> u64 {
+    entry_control::write_rejected_page_count()
+}
+
+pub(super) fn patch_verify_failure_count() -> u64 {
+    entry_control::patch_verify_failure_count()
+}
+
+pub(super) fn mprotect_count() -> u64 {
+    entry_control::mprotect_count()
+}
+
+pub(super) fn protect_restore_failure_count() -> u64 {
+    entry_control::protect_restore_failure_count()
+}
+
+pub(super) fn safe_read_stats() -> SafeReadStats {
+    entry_control::safe_read_stats()
+}
+
+pub(super) fn guard_slot_stats() -> GuardSlotStats {
+    entry_control::guard_slot_stats()
+}
+
+pub(super) fn guard_fault_stats() -> GuardFaultStats {
+    entry_control::guard_fault_stats()
+}
+
+pub(super) fn enable_scan_snapshot_capture(flag: bool) {
+    entry_control::enable_scan_snapshot_capture(flag)
+}
+
+pub(super) fn dump_scan_snapshots(fd: i32) -> Errno {
+    entry_control::dump_scan_snapshots(fd)
+}
+
+pub(super) fn dump_state(fd: i32) -> Errno {
+    entry_control::dump_state(fd)
+}
+
+pub(super) fn state_dump_text() -> String {
+    entry_control::state_dump_text()
+}
+
+pub(super) fn set_callback_dispatch(mode: CallbackDispatch) {
+    entry_control::set_callback_dispatch(mode)
+}
+
+pub(super) fn set_monitor_debounce(window: Duration) {
+    entry_control::set_monitor_debounce(window)
+}
+
+pub(super) fn get_callback_dispatch() -> CallbackDispatch {
+    entry_control::get_callback_dispatch()
+}
+
+pub(super) fn dropped_callback_count() -> u64 {
+    entry_control::dropped_callback_count()
+}
+
+pub(super) fn event_refresh_count() -> u64 {
+    entry_control::event_refresh_count()
+}
+
+pub(super) fn monitor_status() -> MonitorStatus {
+    entry_control::monitor_status()
+}
+
+pub(super) fn monitor_refresh_counts() -> MonitorRefreshCounts {
+    entry_control::monitor_refresh_counts()
+}
+
+pub(super) fn capabilities() -> Option<Capabilities> {
+    capabilities::current()
+}
+
 pub(super) fn get_prev_func(func: *mut c_void) -> *mut c_void {
     entry_control::get_prev_func(func)
 }
@@ -148,6 +551,14 @@ pub(super) fn get_return_address() -> *mut c_void {
     entry_control::get_return_address()
 }
 
+pub(super) fn get_hub_backtrace(buf: &mut [usize]) -> usize {
+    entry_control::get_hub_backtrace(buf)
+}
+
+pub(super) fn get_caller_module() -> Option<ModuleIdentity> {
+    entry_control::get_caller_module()
+}
+
 pub(super) fn pop_stack(return_address: *mut c_void) {
     entry_control::pop_stack(return_address)
 }
@@ -160,6 +571,30 @@ pub(super) fn proxy_leave(func: *mut c_void) {
     entry_control::proxy_leave(func)
 }
 
+pub(super) fn proxy_scope_leave(func: *mut c_void, entered: bool) {
+    entry_control::proxy_scope_leave(func, entered)
+}
+
+pub(super) fn suppress_begin() {
+    entry_control::suppress_begin()
+}
+
+pub(super) fn suppress_end() {
+    entry_control::suppress_end()
+}
+
+pub(super) fn is_suppressed() -> bool {
+    entry_control::is_suppressed()
+}
+
+pub(super) fn set_thread_hook_enabled(enabled: bool) -> bool {
+    entry_control::set_thread_hook_enabled(enabled)
+}
+
+pub(super) fn is_thread_hook_enabled() -> bool {
+    entry_control::is_thread_hook_enabled()
+}
+
 pub(super) fn add_dlopen_callback(
     pre: Option<PreDlopenCallback>,
     post: Option<PostDlopenCallback>,
@@ -176,18 +611,57 @@ pub(super) fn del_dlopen_callback(
     entry_control::del_dlopen_callback(pre, post, data)
 }
 
-pub(super) fn invoke_dlopen_callbacks_pre(filename: *const c_char) {
-    entry_control::invoke_dlopen_callbacks_pre(filename)
+fn begin_dlopen_callbacks(filename: *const c_char) -> dlopen_callbacks::DlopenCallbackScope {
+    entry_control::begin_dlopen_callbacks(filename)
+}
+
+pub(super) fn add_dlopen_filter(filter: DlopenFilterCallback, data: *mut c_void) -> Errno {
+    entry_control::add_dlopen_filter(filter, data)
+}
+
+pub(super) fn del_dlopen_filter(filter: DlopenFilterCallback, data: *mut c_void) -> Errno {
+    entry_control::del_dlopen_filter(filter, data)
+}
+
+fn apply_dlopen_filters(filename: *const c_char) -> dlopen_callbacks::DlopenFilterOutcome {
+    entry_control::apply_dlopen_filters(filename)
+}
+
+pub(super) fn add_dlclose_callback(
+    pre: Option<PreDlcloseCallback>,
+    post: Option<PostDlcloseCallback>,
+    data: *mut c_void,
+) -> Errno {
+    entry_control::add_dlclose_callback(pre, post, data)
+}
+
+pub(super) fn del_dlclose_callback(
+    pre: Option<PreDlcloseCallback>,
+    post: Option<PostDlcloseCallback>,
+    data: *mut c_void,
+) -> Errno {
+    entry_control::del_dlclose_callback(pre, post, data)
 }
 
-pub(super) fn invoke_dlopen_callbacks_post(filename: *const c_char, result: i32) {
-    entry_control::invoke_dlopen_callbacks_post(filename, result)
+fn begin_dlclose_callbacks(
+    handle: *mut c_void,
+    module: Option<&super::state::ModuleInfo>,
+) -> dlclose_callbacks::DlcloseCallbackScope {
+    entry_control::begin_dlclose_callbacks(handle, module)
 }
 
-fn add_task(task: super::state::Task) -> Option<HookStub> {
+fn add_task(task: super::state::Task) -> Result<HookStub, Errno> {
     task_ops::add_task(task)
 }
 
+fn merge_pending_tasks(state: &mut super::state::CoreState) -> task_ops::MergedTasks {
+    task_ops::merge_pending_tasks(state)
+}
+
+fn apply_pending_tasks() {
+    task_ops::apply_pending_tasks();
+}
+
 pub(super) fn request_refresh_async() {
     task_ops::request_refresh_async();
 }
@@ -200,6 +674,10 @@ pub(super) fn request_refresh_async_full() {
     task_ops::request_refresh_async_full();
 }
 
+fn request_refresh_async_unloaded(module: super::state::ModuleInfo) {
+    task_ops::request_refresh_async_unloaded(module);
+}
+
 fn invoke_callbacks(events: Vec<super::refresh::CallbackEvent>) {
     task_ops::invoke_callbacks(events);
 }
@@ -0,0 +1,103 @@
+// pthread_atfork 回调：fork 前静默 slot 写入，fork 后释放；子进程中标记 fork 状态并按需恢复 GOT
+// 子进程回调只做原子操作、解锁与原始系统调用，不分配内存也不获取锁
+use crate::log;
+use crate::runtime::state::{MutexPoisonRecover, RwLockPoisonRecover};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::sync::{MutexGuard, Once, RwLockReadGuard};
+use std::time::{Duration, Instant};
+
+use super::super::callback_ctx;
+use super::super::hub;
+use super::super::refresh;
+use super::super::state::{self, GLOBAL};
+
+// prepare 等待进行中的刷新结束的上限，超时则不静默直接 fork
+const QUIESCE_TIMEOUT: Duration = Duration::from_millis(200);
+const QUIESCE_RETRY_INTERVAL: Duration = Duration::from_millis(1);
+
+static REGISTER: Once = Once::new();
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static RESTORE_IN_CHILD: AtomicBool = AtomicBool::new(false);
+// prepare 取得的锁，由同一线程上的 parent / child 回调取出释放
+static QUIESCE_GUARDS: AtomicPtr<QuiesceGuards> = AtomicPtr::new(ptr::null_mut());
+
+// 字段按声明顺序析构，与加锁顺序相反
+struct QuiesceGuards {
+    _refresh: MutexGuard<'static, ()>,
+    _dlclose: RwLockReadGuard<'static, ()>,
+}
+
+// 回调只注册一次，clear 后由 ACTIVE 关闭
+pub(super) fn install(restore_in_child: bool) {
+    RESTORE_IN_CHILD.store(restore_in_child, Ordering::Release);
+    refresh::set_fork_child_restore(restore_in_child);
+    REGISTER.call_once(|| {
+        let ret = unsafe { libc::pthread_atfork(Some(prepare), Some(parent), Some(child)) };
+        if ret != 0 {
+            log::warn(format_args!("pthread_atfork failed: {ret}"));
+        }
+    });
+    ACTIVE.store(true, Ordering::Release);
+}
+
+pub(super) fn uninstall() {
+    ACTIVE.store(false, Ordering::Release);
+    RESTORE_IN_CHILD.store(false, Ordering::Release);
+    refresh::set_fork_child_restore(false);
+}
+
+// 先发布子进程使用的 proxy 索引并推进 trampoline 页的 fork 代数，再按 dlclose_lock -> refresh_mutex 的顺序非阻塞加锁
+// 子进程按 pid 变化也会走索引旁路，因此 clear 之后与外部回调中同样处理；外部回调中可能已持有这些锁，跳过加锁
+unsafe extern "C" fn prepare() {
+    hub::prepare_fork();
+    if !ACTIVE.load(Ordering::Acquire) || callback_ctx::is_in_external_callback() {
+        return;
+    }
+    let deadline = Instant::now() + QUIESCE_TIMEOUT;
+    let Some(dlclose) = retry_until(deadline, || GLOBAL.dlclose_lock.try_read_or_poison()) else {
+        return;
+    };
+    let Some(refresh) = retry_until(deadline, || GLOBAL.refresh_mutex.try_lock_or_poison()) else {
+        return;
+    };
+    let guards = Box::new(QuiesceGuards {
+        _refresh: refresh,
+        _dlclose: dlclose,
+    });
+    QUIESCE_GUARDS.store(Box::into_raw(guards), Ordering::Release);
+}
+
+fn retry_until<T>(deadline: Instant, mut acquire: impl FnMut() -> Option<T>) -> Option<T> {
+    loop {
+        if let Some(value) = acquire() {
+            return Some(value);
+        }
+        if Instant::now() >= deadline {
+            return None;
+        }
+        std::thread::sleep(QUIESCE_RETRY_INTERVAL);
+    }
+}
+
+unsafe extern "C" fn parent() {
+    let guards = QUIESCE_GUARDS.swap(ptr::null_mut(), Ordering::AcqRel);
+    if !guards.is_null() {
+        drop(unsafe { Box::from_raw(guards) });
+    }
+}
+
+// 只析构锁守卫完成解锁，Box 内存不释放，避免在子进程中调用 free
+unsafe extern "C" fn child() {
+    let guards = QUIESCE_GUARDS.swap(ptr::null_mut(), Ordering::AcqRel);
+    if !guards.is_null() {
+        unsafe { ptr::drop_in_place(guards) };
+    }
+    if !ACTIVE.load(Ordering::Acquire) {
+        return;
+    }
+    state::mark_forked_child();
+    if RESTORE_IN_CHILD.load(Ordering::Acquire) {
+        let _ = refresh::restore_slots_in_child();
+    }
+}
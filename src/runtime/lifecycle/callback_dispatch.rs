@@ -0,0 +1,204 @@
+// HookedCallback 分发：默认在刷新线程内联调用，可切换为专用回调线程异步投递
+// 异步模式下事件进入有界队列，由单个消费线程按入队顺序调用，队列满时丢弃并计数，不阻塞刷新
+use crate::api::CallbackDispatch;
+use crate::log;
+use crate::runtime::state::MutexPoisonRecover;
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::ffi::{CString, c_void};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex};
+
+use super::super::callback_ctx;
+use super::super::refresh::CallbackEvent;
+
+const CALLBACK_QUEUE_CAPACITY: usize = 1024;
+const CALLBACK_THREAD_NAME: &str = "srx_hook_callbacks";
+
+static DISPATCH_MODE: AtomicU8 = AtomicU8::new(CallbackDispatch::Inline as u8);
+static DROPPED_EVENTS: AtomicU64 = AtomicU64::new(0);
+static OVERFLOW_WARNED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Default)]
+struct DispatchQueue {
+    events: VecDeque<CallbackEvent>,
+    in_flight: bool,
+    thread_started: bool,
+}
+
+struct Dispatcher {
+    queue: Mutex<DispatchQueue>,
+    // 有新事件可消费
+    ready: Condvar,
+    // 队列清空且无正在执行的回调
+    idle: Condvar,
+}
+
+static DISPATCHER: Lazy<Dispatcher> = Lazy::new(|| Dispatcher {
+    queue: Mutex::new(DispatchQueue::default()),
+    ready: Condvar::new(),
+    idle: Condvar::new(),
+});
+
+pub(super) fn get_dispatch() -> CallbackDispatch {
+    match DISPATCH_MODE.load(Ordering::Acquire) {
+        mode if mode == CallbackDispatch::DedicatedThread as u8 => {
+            CallbackDispatch::DedicatedThread
+        }
+        _ => CallbackDispatch::Inline,
+    }
+}
+
+// 切回 Inline 时等待队列中已有事件投递完毕，保证与之后内联回调的先后顺序
+pub(super) fn set_dispatch(mode: CallbackDispatch) {
+    let prev = DISPATCH_MODE.swap(mode as u8, Ordering::AcqRel);
+    if prev == CallbackDispatch::DedicatedThread as u8 && mode == CallbackDispatch::Inline {
+        flush();
+    }
+}
+
+pub(super) fn dropped_count() -> u64 {
+    DROPPED_EVENTS.load(Ordering::Relaxed)
+}
+
+pub(super) fn dispatch(events: Vec<CallbackEvent>) {
+    if events.is_empty() {
+        return;
+    }
+    if get_dispatch() == CallbackDispatch::Inline {
+        invoke_inline(events);
+        return;
+    }
+
+    let mut queue = DISPATCHER.queue.lock_or_poison();
+    if !queue.thread_started {
+        if !spawn_worker() {
+            // 线程创建失败时退回内联调用，避免事件丢失
+            drop(queue);
+            invoke_inline(events);
+            return;
+        }
+        queue.thread_started = true;
+    }
+    let mut dropped = 0u64;
+    for event in events {
+        if queue.events.len() >= CALLBACK_QUEUE_CAPACITY {
+            dropped += 1;
+            continue;
+        }
+        queue.events.push_back(event);
+    }
+    drop(queue);
+    DISPATCHER.ready.notify_one();
+
+    if dropped > 0 {
+        DROPPED_EVENTS.fetch_add(dropped, Ordering::Relaxed);
+        if !OVERFLOW_WARNED.swap(true, Ordering::Relaxed) {
+            log::warn(format_args!(
+                "callback queue full (capacity {}), dropping hooked callbacks",
+                CALLBACK_QUEUE_CAPACITY
+            ));
+        }
+    }
+}
+
+// 在当前线程上按序投递，不经过回调线程队列
+pub(super) fn invoke_inline(events: Vec<CallbackEvent>) {
+    for event in events {
+        invoke_one(event);
+    }
+}
+
+// 丢弃尚未投递的事件，正在执行的回调不受影响；clear() 返回后不会再投递旧事件
+pub(super) fn discard_pending() {
+    let mut queue = DISPATCHER.queue.lock_or_poison();
+    queue.events.clear();
+    if !queue.in_flight {
+        DISPATCHER.idle.notify_all();
+    }
+}
+
+// fork 子进程中回调线程不存在：丢弃继承的队列并允许按需重新创建线程
+pub(super) fn reset_after_fork() {
+    let mut queue = DISPATCHER.queue.lock_or_poison();
+    *queue = DispatchQueue::default();
+}
+
+// 阻塞到队列清空，API 层已拒绝回调内的调用，不会在回调线程上自等待
+fn flush() {
+    let mut queue = DISPATCHER.queue.lock_or_poison();
+    while !queue.events.is_empty() || queue.in_flight {
+        queue = DISPATCHER
+            .idle
+            .wait(queue)
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+    }
+}
+
+fn spawn_worker() -> bool {
+    match std::thread::Builder::new()
+        .name(CALLBACK_THREAD_NAME.to_string())
+        .spawn(worker_loop)
+    {
+        Ok(_) => true,
+        Err(err) => {
+            log::warn(format_args!("spawn callback thread failed: {}", err));
+            false
+        }
+    }
+}
+
+fn worker_loop() {
+    let mut queue = DISPATCHER.queue.lock_or_poison();
+    loop {
+        let Some(event) = queue.events.pop_front() else {
+            DISPATCHER.idle.notify_all();
+            queue = DISPATCHER
+                .ready
+                .wait(queue)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            continue;
+        };
+        queue.in_flight = true;
+        drop(queue);
+        callback_ctx::run_in_external_callback(|| invoke_one(event));
+        queue = DISPATCHER.queue.lock_or_poison();
+        queue.in_flight = false;
+    }
+}
+
+fn invoke_one(event: CallbackEvent) {
+    let Ok(caller_path_name) = CString::new(event.caller_path_name) else {
+        return;
+    };
+    let Ok(sym_name) = CString::new(event.sym_name) else {
+        return;
+    };
+    if let Some(hooked) = event.hooked {
+        unsafe {
+            (hooked.callback)(
+                event.task_stub,
+                event.status.as_i32(),
+                caller_path_name.as_ptr(),
+                sym_name.as_ptr(),
+                event.new_func as *mut c_void,
+                event.prev_func as *mut c_void,
+                hooked.arg as *mut c_void,
+            );
+        }
+    }
+    if let Some(entry) = event.event_callback {
+        unsafe {
+            (entry.callback)(
+                event.task_stub,
+                event.kind,
+                event.status.as_i32(),
+                caller_path_name.as_ptr(),
+                sym_name.as_ptr(),
+                event.new_func as *mut c_void,
+                event.prev_func as *mut c_void,
+                entry.arg as *mut c_void,
+            );
+        }
+    }
+}
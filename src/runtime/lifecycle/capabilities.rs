@@ -0,0 +1,80 @@
+// 运行时能力探测：首次 init 成功时记录一次性探测结果，查询时合并 monitor / 信号守卫 / CFI 的实时状态
+// 探测结果只与进程环境有关，clear 后重新 init 沿用首次结果
+use crate::android::signal_guard;
+use crate::api::Capabilities;
+use crate::errno::Errno;
+use std::fs::File;
+use std::sync::OnceLock;
+
+use super::super::cfi;
+use super::super::refresh;
+use super::super::state::{GLOBAL, MutexPoisonRecover};
+use super::monitor;
+
+#[derive(Clone, Copy, Debug)]
+struct InitProbe {
+    android_api_level: i32,
+    loader_hooks_available: bool,
+    dlinfo_available: bool,
+    dladdr1_available: bool,
+    hw_mte: bool,
+    hw_bti: bool,
+    hw_pac: bool,
+    maps_readable: bool,
+}
+
+static INIT_PROBE: OnceLock<InitProbe> = OnceLock::new();
+
+pub(super) fn probe() {
+    INIT_PROBE.get_or_init(|| {
+        let (hw_mte, hw_bti, hw_pac) = hw_features();
+        InitProbe {
+            android_api_level: monitor::android_api_level(),
+            loader_hooks_available: monitor::should_use_loader_hooks(),
+            dlinfo_available: refresh::dlinfo_available(),
+            dladdr1_available: refresh::dladdr1_available(),
+            hw_mte,
+            hw_bti,
+            hw_pac,
+            maps_readable: File::open("/proc/self/maps").is_ok(),
+        }
+    });
+}
+
+pub(super) fn current() -> Option<Capabilities> {
+    if GLOBAL.state.lock_or_poison().init.status != Errno::Ok {
+        return None;
+    }
+    let probe = INIT_PROBE.get()?;
+    Some(Capabilities {
+        android_api_level: probe.android_api_level,
+        loader_hooks_available: probe.loader_hooks_available,
+        monitor_strategy: monitor::strategy(),
+        periodic_fallback: monitor::is_periodic_enabled(),
+        signal_handler_mode: signal_guard::handler_mode(),
+        dlinfo_available: probe.dlinfo_available,
+        dladdr1_available: probe.dladdr1_available,
+        cfi: cfi::patch_state(),
+        hw_mte: probe.hw_mte,
+        hw_bti: probe.hw_bti,
+        hw_pac: probe.hw_pac,
+        maps_readable: probe.maps_readable,
+    })
+}
+
+// (MTE, BTI, PAC)，按内核 HWCAP 上报，不代表当前进程已启用
+#[cfg(target_arch = "aarch64")]
+fn hw_features() -> (bool, bool, bool) {
+    let hwcap = unsafe { libc::getauxval(libc::AT_HWCAP) };
+    let hwcap2 = unsafe { libc::getauxval(libc::AT_HWCAP2) };
+    (
+        hwcap2 & libc::HWCAP2_MTE != 0,
+        hwcap2 & libc::HWCAP2_BTI != 0,
+        hwcap & libc::HWCAP_PACA != 0,
+    )
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+fn hw_features() -> (bool, bool, bool) {
+    (false, false, false)
+}
@@ -0,0 +1,117 @@
+// dlclose 回调管理，支持注册 pre/post 回调以监听动态库卸载事件
+// 回调在 dlclose_lock 之外、外部回调上下文中执行，其中调用 hook 接口会被拒绝而不是死锁
+use crate::api::{PostDlcloseCallback, PreDlcloseCallback};
+use crate::errno::Errno;
+use std::ffi::{CString, c_void};
+
+use super::super::callback_ctx;
+use super::super::state::{DlcloseCallbackEntry, GLOBAL, ModuleInfo};
+use crate::runtime::state::MutexPoisonRecover;
+
+pub(super) fn add_dlclose_callback(
+    pre: Option<PreDlcloseCallback>,
+    post: Option<PostDlcloseCallback>,
+    data: *mut c_void,
+) -> Errno {
+    if pre.is_none() && post.is_none() {
+        return Errno::InvalidArg;
+    }
+
+    let mut state = GLOBAL.state.lock_or_poison();
+    let data = data as usize;
+    if state
+        .dlclose_callbacks
+        .iter()
+        .any(|entry| is_same_dlclose_callback(entry, pre, post, data))
+    {
+        return Errno::Ok;
+    }
+    state.dlclose_callbacks.push(DlcloseCallbackEntry {
+        pre,
+        post,
+        arg: data,
+    });
+    Errno::Ok
+}
+
+pub(super) fn del_dlclose_callback(
+    pre: Option<PreDlcloseCallback>,
+    post: Option<PostDlcloseCallback>,
+    data: *mut c_void,
+) -> Errno {
+    if pre.is_none() && post.is_none() {
+        return Errno::InvalidArg;
+    }
+
+    let mut state = GLOBAL.state.lock_or_poison();
+    let data = data as usize;
+    state
+        .dlclose_callbacks
+        .retain(|entry| !is_same_dlclose_callback(entry, pre, post, data));
+    Errno::Ok
+}
+
+// 一次 dlclose 的回调作用域：创建时投递 pre，drop 时向同一批回调投递 post
+// 转发路径提前返回或 panic 时同样会投递 post，结果默认按失败（-1）上报
+pub(super) struct DlcloseCallbackScope {
+    handle: *mut c_void,
+    callbacks: Vec<DlcloseCallbackEntry>,
+    result: i32,
+}
+
+impl DlcloseCallbackScope {
+    // module 为卸载前按 handle 解析出的模块，无法解析时 pre 收到空路径指针
+    pub(super) fn begin(handle: *mut c_void, module: Option<&ModuleInfo>) -> Self {
+        let callbacks = {
+            let state = GLOBAL.state.lock_or_poison();
+            state.dlclose_callbacks.clone()
+        };
+        let pathname = callbacks
+            .iter()
+            .any(|entry| entry.pre.is_some())
+            .then(|| module.and_then(|module| CString::new(module.pathname.as_str()).ok()))
+            .flatten();
+        let pathname_ptr = pathname
+            .as_ref()
+            .map_or(std::ptr::null(), |pathname| pathname.as_ptr());
+        for entry in &callbacks {
+            if let Some(pre) = entry.pre {
+                callback_ctx::run_in_external_callback(|| unsafe {
+                    pre(handle, pathname_ptr, entry.arg as *mut c_void);
+                });
+            }
+        }
+        Self {
+            handle,
+            callbacks,
+            result: -1,
+        }
+    }
+
+    pub(super) fn set_result(&mut self, result: libc::c_int) {
+        self.result = result;
+    }
+}
+
+impl Drop for DlcloseCallbackScope {
+    fn drop(&mut self) {
+        for entry in &self.callbacks {
+            if let Some(post) = entry.post {
+                callback_ctx::run_in_external_callback(|| unsafe {
+                    post(self.handle, self.result, entry.arg as *mut c_void);
+                });
+            }
+        }
+    }
+}
+
+fn is_same_dlclose_callback(
+    entry: &DlcloseCallbackEntry,
+    pre: Option<PreDlcloseCallback>,
+    post: Option<PostDlcloseCallback>,
+    data: usize,
+) -> bool {
+    entry.pre.map(|callback| callback as usize) == pre.map(|callback| callback as usize)
+        && entry.post.map(|callback| callback as usize) == post.map(|callback| callback as usize)
+        && entry.arg == data
+}
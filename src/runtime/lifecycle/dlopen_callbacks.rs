@@ -1,9 +1,16 @@
 // dlopen 回调管理，支持注册 pre/post 回调以监听动态库加载事件
-use crate::api::{PostDlopenCallback, PreDlopenCallback};
+// 以及可拒绝或改写加载路径的过滤器
+use crate::android::signal_guard;
+use crate::api::{
+    DLOPEN_RESULT_NOLOAD, DlopenFilterAction, DlopenFilterCallback, PostDlopenCallback,
+    PreDlopenCallback,
+};
 use crate::errno::Errno;
-use std::ffi::{c_char, c_void};
+use crate::log;
+use std::ffi::{CStr, CString, c_char, c_void};
 
-use super::super::state::{DlopenCallbackEntry, GLOBAL};
+use super::super::callback_ctx;
+use super::super::state::{DlopenCallbackEntry, DlopenFilterEntry, GLOBAL};
 use crate::runtime::state::MutexPoisonRecover;
 
 pub(super) fn add_dlopen_callback(
@@ -49,35 +56,161 @@ pub(super) fn del_dlopen_callback(
     Errno::Ok
 }
 
-// 先复制回调列表再释放锁，避免持锁期间调用外部回调导致死锁
-pub(super) fn invoke_dlopen_callbacks_pre(filename: *const c_char) {
-    let callbacks = {
+// 重定向路径缓冲区容量，与 PATH_MAX 一致
+const REDIRECT_PATH_MAX: usize = 4096;
+
+pub(super) fn add_dlopen_filter(filter: DlopenFilterCallback, data: *mut c_void) -> Errno {
+    let mut state = GLOBAL.state.lock_or_poison();
+    let data = data as usize;
+    if state
+        .dlopen_filters
+        .iter()
+        .any(|entry| entry.filter as usize == filter as usize && entry.arg == data)
+    {
+        return Errno::Ok;
+    }
+    state.dlopen_filters.push(DlopenFilterEntry { filter, arg: data });
+    Errno::Ok
+}
+
+pub(super) fn del_dlopen_filter(filter: DlopenFilterCallback, data: *mut c_void) -> Errno {
+    let mut state = GLOBAL.state.lock_or_poison();
+    let data = data as usize;
+    state
+        .dlopen_filters
+        .retain(|entry| entry.filter as usize != filter as usize || entry.arg != data);
+    Errno::Ok
+}
+
+// 过滤链的裁决：按原路径加载、改为加载另一路径，或不实际加载直接失败
+// Deny 携带带有拒绝原因的名称，以 RTLD_NOLOAD 交给加载器以设置 dlerror
+pub(super) enum DlopenFilterOutcome {
+    Load,
+    Redirect(CString),
+    Deny(CString),
+}
+
+// 按注册顺序询问过滤器，首个非 Allow 的决定生效
+// 拒绝时不实际加载，原因写入日志与 dlerror；Redirect 但缓冲区中没有合法路径时按拒绝处理
+// 过滤器内部发起的 dlopen 直接放行，不再进入过滤链
+pub(super) fn apply_dlopen_filters(filename: *const c_char) -> DlopenFilterOutcome {
+    if callback_ctx::is_in_dlopen_filter() {
+        return DlopenFilterOutcome::Load;
+    }
+    let filters = {
         let state = GLOBAL.state.lock_or_poison();
-        state.dlopen_callbacks.clone()
+        state.dlopen_filters.clone()
     };
-    for entry in callbacks {
-        if let Some(pre) = entry.pre {
-            unsafe {
-                pre(filename, entry.arg as *mut c_void);
+    if filters.is_empty() {
+        return DlopenFilterOutcome::Load;
+    }
+
+    let mut redirect_buf = vec![0u8; REDIRECT_PATH_MAX];
+    for entry in &filters {
+        redirect_buf[0] = 0;
+        let action = callback_ctx::run_in_dlopen_filter(|| unsafe {
+            (entry.filter)(
+                filename,
+                redirect_buf.as_mut_ptr() as *mut c_char,
+                redirect_buf.len(),
+                entry.arg as *mut c_void,
+            )
+        });
+        match action {
+            DlopenFilterAction::Allow => continue,
+            DlopenFilterAction::Deny => {
+                return deny(filename, "filter");
+            }
+            DlopenFilterAction::Redirect => {
+                let redirect = CStr::from_bytes_until_nul(&redirect_buf)
+                    .ok()
+                    .filter(|path| !path.is_empty());
+                let Some(redirect) = redirect else {
+                    return deny(filename, "redirect without path");
+                };
+                return DlopenFilterOutcome::Redirect(redirect.to_owned());
             }
         }
     }
+    DlopenFilterOutcome::Load
 }
 
-pub(super) fn invoke_dlopen_callbacks_post(filename: *const c_char, result: i32) {
-    let callbacks = {
-        let state = GLOBAL.state.lock_or_poison();
-        state.dlopen_callbacks.clone()
-    };
-    for entry in callbacks {
-        if let Some(post) = entry.post {
-            unsafe {
-                post(filename, result, entry.arg as *mut c_void);
+// 名称不会与已加载模块重名，加载器以 RTLD_NOLOAD 查找失败后把它连同原因写入 dlerror
+fn deny(filename: *const c_char, reason: &str) -> DlopenFilterOutcome {
+    let filename = unsafe { CStr::from_ptr(filename) }.to_string_lossy();
+    log::warn(format_args!(
+        "dlopen {filename} denied ({reason}), return null"
+    ));
+    let name = format!("{filename} (denied by srx_hook: {reason})");
+    DlopenFilterOutcome::Deny(CString::new(name).unwrap_or_default())
+}
+
+// 一次 dlopen 的回调作用域：创建时投递 pre，drop 时向同一批回调投递 post
+// 转发路径提前返回或 panic 时同样会投递 post，结果默认按失败（-1）上报
+pub(super) struct DlopenCallbackScope {
+    filename: *const c_char,
+    callbacks: Vec<DlopenCallbackEntry>,
+    result: i32,
+}
+
+impl DlopenCallbackScope {
+    // 先复制回调列表再释放锁，避免持锁期间调用外部回调导致死锁
+    pub(super) fn begin(filename: *const c_char) -> Self {
+        let callbacks = {
+            let state = GLOBAL.state.lock_or_poison();
+            state.dlopen_callbacks.clone()
+        };
+        for entry in &callbacks {
+            if let Some(pre) = entry.pre {
+                unsafe {
+                    pre(filename, entry.arg as *mut c_void);
+                }
+            }
+        }
+        Self {
+            filename,
+            callbacks,
+            result: -1,
+        }
+    }
+
+    pub(super) fn set_handle(&mut self, handle: *mut c_void, noload: bool) {
+        self.result = match (handle.is_null(), noload) {
+            (true, _) => -1,
+            (false, true) => DLOPEN_RESULT_NOLOAD,
+            (false, false) => 0,
+        };
+    }
+}
+
+impl Drop for DlopenCallbackScope {
+    fn drop(&mut self) {
+        if self.result == 0 && is_sigchain_provider(self.filename) {
+            signal_guard::reassert_front_of_chain(true);
+        }
+        for entry in &self.callbacks {
+            if let Some(post) = entry.post {
+                unsafe {
+                    post(self.filename, self.result, entry.arg as *mut c_void);
+                }
             }
         }
     }
 }
 
+// libart / libsigchain 新加载时 ART 会重新注册 fault manager，守卫 handler 需立即提升回链首
+fn is_sigchain_provider(filename: *const c_char) -> bool {
+    if filename.is_null() {
+        return false;
+    }
+    let filename = unsafe { CStr::from_ptr(filename) }.to_bytes();
+    let basename = filename
+        .rsplit(|byte| *byte == b'/')
+        .next()
+        .unwrap_or(filename);
+    matches!(basename, b"libart.so" | b"libsigchain.so")
+}
+
 fn is_same_dlopen_callback(
     entry: &DlopenCallbackEntry,
     pre: Option<PreDlopenCallback>,
@@ -1,19 +1,78 @@
-// 运行时控制入口，提供 clear/debug/record/proxy 等控制操作的实现
-use crate::api::{HookMode, PostDlopenCallback, PreDlopenCallback};
+// 运行时控制入口，提供 clear/shutdown/debug/record/proxy 等控制操作的实现
+use crate::api::{
+    CallbackDispatch, CfiPatchEntry, DlopenFilterCallback, GuardFaultStats, GuardSlotStats,
+    HookMode, HubStackStats, LinkerFallbackStats, LogLevel, LogSink, ModuleIdentity,
+    MonitorRefreshCounts, MonitorStatus, PostDlcloseCallback, PostDlopenCallback,
+    PreDlcloseCallback, PreDlopenCallback, RecordEntry, SafeReadStats, ThreadStateStats,
+};
 use crate::android::signal_guard;
 use crate::errno::Errno;
 use std::ffi::{c_char, c_void};
+use std::time::Duration;
 
+use super::atfork;
+use super::callback_dispatch;
+use super::dlclose_callbacks;
 use super::dlopen_callbacks;
 use super::monitor;
+use super::monitor_calls;
 use super::proxy;
+use super::record_listener;
+use super::state_dump;
+use super::task_ops;
+use super::super::artifact;
+use super::super::cfi;
 use super::super::hub;
+use super::super::inspect;
 use super::super::refresh;
-use super::super::state::GLOBAL;
+use super::super::state::{GLOBAL, ModuleInfo, set_resetting};
+use super::super::thread_state;
 use crate::runtime::state::{MutexPoisonRecover, RwLockPoisonRecover};
 
 // 完全重置运行时状态：停止 monitor 线程、恢复所有 hook、清空全部数据
 pub(super) fn clear() {
+    let _ = reset_runtime(ResetScope::Clear {
+        keep_retired: false,
+    });
+}
+
+// 同 clear，但 retired hub 不强制释放：复位后等待活跃栈帧归零再回收，超时返回 false
+pub(super) fn clear_and_drain(timeout: Duration) -> bool {
+    let _ = reset_runtime(ResetScope::Clear { keep_retired: true });
+    hub::drain_retired(timeout)
+}
+
+pub(super) fn drain_retired_hubs(timeout: Duration) -> bool {
+    hub::drain_retired(timeout)
+}
+
+// 在 clear 基础上释放全部信号 handler 引用并移除记录监听器与日志 sink，可选回滚 CFI 补丁
+// 返回 CFI 回滚结果，其余步骤总会执行；之后可再次 init
+pub(super) fn shutdown(revert_cfi: bool) -> Errno {
+    let status = reset_runtime(ResetScope::Shutdown { revert_cfi });
+    let _ = record_listener::clear_listener();
+    crate::log::clear_sink();
+    status
+}
+
+// 持有 state 锁恢复 slowpath 指令，与刷新中的 CFI 补丁互斥
+pub(super) fn restore_cfi_slowpath() -> Errno {
+    let _state = GLOBAL.state.lock_or_poison();
+    cfi::restore_slowpath()
+}
+
+pub(super) fn cfi_patch_report() -> Vec<CfiPatchEntry> {
+    cfi::patch_report()
+}
+
+enum ResetScope {
+    // keep_retired 为 true 时不强制释放 retired hub，由调用方随后 drain
+    Clear { keep_retired: bool },
+    Shutdown { revert_cfi: bool },
+}
+
+fn reset_runtime(scope: ResetScope) -> Errno {
+    set_resetting(true);
     let thread = {
         let mut state = GLOBAL.state.lock_or_poison();
         state.monitor_running = false;
@@ -25,33 +84,76 @@ pub(super) fn clear() {
         let _ = handle.join();
     }
 
-    let _dlclose_guard = GLOBAL.dlclose_lock.read_or_poison();
-    let _refresh_guard = GLOBAL.refresh_mutex.lock_or_poison();
+    let dlclose_guard = GLOBAL.dlclose_lock.read_or_poison();
+    let refresh_guard = GLOBAL.refresh_mutex.lock_or_poison();
     let mut state = GLOBAL.state.lock_or_poison();
+    let events: Vec<_> = state
+        .task_order
+        .iter()
+        .flat_map(|stub| refresh::unhook_events(&state, *stub))
+        .collect();
     let _ = refresh::restore_all(&mut state);
+    super::super::record::add_clear_record(&mut state);
     state.tasks.clear();
     state.task_order.clear();
     state.task_slots.clear();
     state.slots.clear();
     state.single_task_targets.clear();
+    state.callee_candidates.clear();
+    for stats in std::mem::take(&mut state.hit_stats).into_values() {
+        hub::release_hit_stats(stats);
+    }
+    for filter in std::mem::take(&mut state.tid_filters).into_values() {
+        hub::retire_tid_filter(filter);
+    }
     state.ignore_callers.clear();
+    refresh::reset_soname_scan();
     state.known_modules.clear();
+    state.write_guard = Default::default();
     state.recordable = false;
     state.records.clear();
+    state.records_dropped = 0;
     state.dlopen_callbacks.clear();
+    state.dlopen_filters.clear();
+    state.dlclose_callbacks.clear();
     state.pending_module_handles.clear();
     state.pending_module_handle_set.clear();
+    state.pending_unloaded_modules.clear();
     state.refresh_requested = false;
     state.process_id = 0;
     state.init.status = Errno::Uninit;
     state.init.mode = HookMode::Automatic;
-    state.next_stub = 1;
+    task_ops::reset_registry();
 
+    callback_dispatch::discard_pending();
     monitor::reset_auto_monitor_installed();
-    signal_guard::remove_handler();
+    atfork::uninstall();
+    let mut status = Errno::Ok;
+    let force_collect = !matches!(scope, ResetScope::Clear { keep_retired: true });
+    match scope {
+        ResetScope::Clear { .. } => signal_guard::remove_handler(),
+        ResetScope::Shutdown { revert_cfi } => {
+            // 回滚写入依赖信号守卫，须在卸载 handler 之前
+            if revert_cfi {
+                status = cfi::revert_slowpath_patch();
+            }
+            signal_guard::remove_all_handlers();
+        }
+    }
     proxy::clear_proxy_stack();
     hub::clear_stack();
-    hub::collect_retired(true);
+    if force_collect {
+        hub::collect_retired(true);
+    }
+    artifact::reset_static();
+    drop(state);
+    drop(refresh_guard);
+    drop(dlclose_guard);
+    set_resetting(false);
+    // 运行时已复位，Unhooked 事件在线程上直接投递
+    callback_dispatch::invoke_inline(events);
+    record_listener::deliver_pending();
+    status
 }
 
 pub(super) fn get_mode() -> HookMode {
@@ -60,16 +162,21 @@ pub(super) fn get_mode() -> HookMode {
 }
 
 pub(super) fn get_debug() -> bool {
-    let state = GLOBAL.state.lock_or_poison();
-    state.debug
+    crate::log::level() == LogLevel::Debug
 }
 
 pub(super) fn set_debug(debug: bool) {
-    let mut state = GLOBAL.state.lock_or_poison();
-    state.debug = debug;
     crate::log::set_debug_enabled(debug);
 }
 
+pub(super) fn set_log_level(level: LogLevel) {
+    crate::log::set_level(level);
+}
+
+pub(super) fn get_log_level() -> LogLevel {
+    crate::log::level()
+}
+
 pub(super) fn get_recordable() -> bool {
     let state = GLOBAL.state.lock_or_poison();
     state.recordable
@@ -81,16 +188,55 @@ pub(super) fn set_recordable(recordable: bool) {
 }
 
 pub(super) fn get_records(item_flags: u32) -> Option<String> {
-    let state = GLOBAL.state.lock_or_poison();
-    super::super::record::get_records_text(&state, item_flags)
+    records_text(item_flags, false)
+}
+
+// debug 开启时为 NEW_ADDR 标注符号；反查要读取模块 ELF，先于 state 锁持有 dlclose 读锁
+fn records_text(item_flags: u32, with_header: bool) -> Option<String> {
+    let annotate = get_debug();
+    let _dlclose_guard = annotate.then(|| GLOBAL.dlclose_lock.read_or_poison());
+    let mut state = GLOBAL.state.lock_or_poison();
+    task_ops::flush_deferred_records(&mut state);
+    let resolve: super::super::record::AddrResolver<'_> = &inspect::resolve_address_locked;
+    let text =
+        super::super::record::get_records_text(&state, item_flags, annotate.then_some(resolve))?;
+    if with_header {
+        return Some(super::super::record::dump_header(&state) + &text);
+    }
+    Some(text)
+}
+
+pub(super) fn set_log_sink(sink: LogSink, arg: *mut c_void, keep_logcat: bool) {
+    crate::log::set_sink(sink, arg, keep_logcat);
+}
+
+pub(super) fn clear_log_sink() {
+    crate::log::clear_sink();
+}
+
+pub(super) fn get_record_entries(item_flags: u32) -> Vec<RecordEntry> {
+    let mut state = GLOBAL.state.lock_or_poison();
+    task_ops::flush_deferred_records(&mut state);
+    super::super::record::get_record_entries(&state, item_flags)
+}
+
+pub(super) fn set_record_capacity(max_entries: usize) -> Errno {
+    let mut state = GLOBAL.state.lock_or_poison();
+    super::super::record::set_record_capacity(&mut state, max_entries)
+}
+
+pub(super) fn get_record_capacity() -> usize {
+    super::super::record::record_capacity()
+}
+
+pub(super) fn get_record_dropped_count() -> u64 {
+    let mut state = GLOBAL.state.lock_or_poison();
+    task_ops::flush_deferred_records(&mut state);
+    state.records_dropped
 }
 
 pub(super) fn dump_records(fd: i32, item_flags: u32) -> Errno {
-    let text = {
-        let state = GLOBAL.state.lock_or_poison();
-        super::super::record::get_records_text(&state, item_flags)
-    };
-    let Some(text) = text else {
+    let Some(text) = records_text(item_flags, true) else {
         return Errno::Ok;
     };
     match super::super::record::dump_records_text(fd, &text) {
@@ -103,6 +249,150 @@ pub(super) fn enable_sigsegv_protection(flag: bool) {
     signal_guard::enable(flag);
 }
 
+pub(super) fn enable_extended_signal_guard(flag: bool) {
+    signal_guard::enable_extended(flag);
+}
+
+pub(super) fn enable_sigchain_front_reassert(flag: bool) {
+    signal_guard::enable_front_reassert(flag);
+}
+
+pub(super) fn sigchain_reassert_count() -> u64 {
+    signal_guard::front_reassert_count()
+}
+
+pub(super) fn enable_header_file_fallback(flag: bool) {
+    refresh::set_header_file_fallback(flag);
+}
+
+pub(super) fn enable_write_verification(flag: bool) {
+    refresh::set_write_verify(flag);
+}
+
+pub(super) fn enable_raw_mprotect(flag: bool) {
+    crate::android::memory::set_prefer_syscall_mprotect(flag);
+}
+
+pub(super) fn set_slot_guard(flag: bool) {
+    refresh::set_slot_guard(flag);
+}
+
+pub(super) fn is_slot_guard_enabled() -> bool {
+    refresh::is_slot_guard_enabled()
+}
+
+pub(super) fn set_pattern_match_limit(limit: usize) -> Errno {
+    if limit == 0 {
+        return Errno::InvalidArg;
+    }
+    refresh::set_pattern_match_limit(limit);
+    Errno::Ok
+}
+
+pub(super) fn get_pattern_match_limit() -> usize {
+    refresh::pattern_match_limit()
+}
+
+pub(super) fn enable_linker_internal_fallback(flag: bool) {
+    monitor_calls::set_linker_internal_fallback(flag);
+}
+
+pub(super) fn linker_fallback_stats() -> LinkerFallbackStats {
+    monitor_calls::linker_fallback_stats()
+}
+
+pub(super) fn write_rejected_page_count() -> u64 {
+    refresh::write_rejected_page_count()
+}
+
+pub(super) fn patch_verify_failure_count() -> u64 {
+    refresh::patch_verify_failure_count()
+}
+
+pub(super) fn mprotect_count() -> u64 {
+    refresh::mprotect_count()
+}
+
+pub(super) fn protect_restore_failure_count() -> u64 {
+    refresh::protect_restore_failure_count()
+}
+
+pub(super) fn safe_read_stats() -> SafeReadStats {
+    refresh::safe_read_stats()
+}
+
+pub(super) fn guard_slot_stats() -> GuardSlotStats {
+    signal_guard::slot_stats()
+}
+
+pub(super) fn guard_fault_stats() -> GuardFaultStats {
+    signal_guard::fault_stats()
+}
+
+pub(super) fn enable_scan_snapshot_capture(flag: bool) {
+    refresh::set_scan_snapshot_capture(flag);
+}
+
+pub(super) fn dump_scan_snapshots(fd: i32) -> Errno {
+    let text = refresh::scan_snapshot_text();
+    match super::super::record::dump_records_text(fd, &text) {
+        Ok(()) => Errno::Ok,
+        Err(err) => err,
+    }
+}
+
+pub(super) fn dump_state(fd: i32) -> Errno {
+    state_dump::dump_state(fd)
+}
+
+pub(super) fn state_dump_text() -> String {
+    state_dump::state_dump_text()
+}
+
+pub(super) fn fork_prev_fallback_count() -> u64 {
+    hub::fork_prev_fallback_count()
+}
+
+pub(super) fn hub_stack_stats() -> HubStackStats {
+    hub::stack_stats()
+}
+
+pub(super) fn thread_state_stats() -> ThreadStateStats {
+    thread_state::thread_state_stats()
+}
+
+pub(super) fn reset_thread_state_stats() {
+    thread_state::reset_thread_state_stats()
+}
+
+pub(super) fn set_callback_dispatch(mode: CallbackDispatch) {
+    callback_dispatch::set_dispatch(mode);
+}
+
+pub(super) fn set_monitor_debounce(window: Duration) {
+    monitor::set_debounce_window(window);
+}
+
+pub(super) fn get_callback_dispatch() -> CallbackDispatch {
+    callback_dispatch::get_dispatch()
+}
+
+pub(super) fn dropped_callback_count() -> u64 {
+    callback_dispatch::dropped_count()
+}
+
+pub(super) fn event_refresh_count() -> u64 {
+    monitor::event_refresh_count()
+}
+
+pub(super) fn monitor_status() -> MonitorStatus {
+    monitor::status()
+}
+
+pub(super) fn monitor_refresh_counts() -> MonitorRefreshCounts {
+    monitor::refresh_counts()
+}
+
 pub(super) fn get_prev_func(func: *mut c_void) -> *mut c_void {
     proxy::get_prev_func(func)
 }
@@ -118,6 +408,14 @@ pub(super) fn get_return_address() -> *mut c_void {
     proxy::get_return_address()
 }
 
+pub(super) fn get_hub_backtrace(buf: &mut [usize]) -> usize {
+    proxy::get_hub_backtrace(buf)
+}
+
+pub(super) fn get_caller_module() -> Option<ModuleIdentity> {
+    proxy::get_caller_module()
+}
+
 pub(super) fn pop_stack(return_address: *mut c_void) {
     proxy::pop_stack(return_address)
 }
@@ -130,6 +428,30 @@ pub(super) fn proxy_leave(func: *mut c_void) {
     proxy::proxy_leave(func)
 }
 
+pub(super) fn proxy_scope_leave(func: *mut c_void, entered: bool) {
+    proxy::proxy_scope_leave(func, entered)
+}
+
+pub(super) fn suppress_begin() {
+    proxy::suppress_begin()
+}
+
+pub(super) fn suppress_end() {
+    proxy::suppress_end()
+}
+
+pub(super) fn is_suppressed() -> bool {
+    proxy::is_suppressed()
+}
+
+pub(super) fn set_thread_hook_enabled(enabled: bool) -> bool {
+    proxy::set_thread_hook_enabled(enabled)
+}
+
+pub(super) fn is_thread_hook_enabled() -> bool {
+    proxy::is_thread_hook_enabled()
+}
+
 pub(super) fn add_dlopen_callback(
     pre: Option<PreDlopenCallback>,
     post: Option<PostDlopenCallback>,
@@ -146,10 +468,45 @@ pub(super) fn del_dlopen_callback(
     dlopen_callbacks::del_dlopen_callback(pre, post, data)
 }
 
-pub(super) fn invoke_dlopen_callbacks_pre(filename: *const c_char) {
-    dlopen_callbacks::invoke_dlopen_callbacks_pre(filename)
+pub(super) fn begin_dlopen_callbacks(
+    filename: *const c_char,
+) -> dlopen_callbacks::DlopenCallbackScope {
+    dlopen_callbacks::DlopenCallbackScope::begin(filename)
+}
+
+pub(super) fn add_dlopen_filter(filter: DlopenFilterCallback, data: *mut c_void) -> Errno {
+    dlopen_callbacks::add_dlopen_filter(filter, data)
+}
+
+pub(super) fn del_dlopen_filter(filter: DlopenFilterCallback, data: *mut c_void) -> Errno {
+    dlopen_callbacks::del_dlopen_filter(filter, data)
+}
+
+pub(super) fn apply_dlopen_filters(
+    filename: *const c_char,
+) -> dlopen_callbacks::DlopenFilterOutcome {
+    dlopen_callbacks::apply_dlopen_filters(filename)
+}
+
+pub(super) fn add_dlclose_callback(
+    pre: Option<PreDlcloseCallback>,
+    post: Option<PostDlcloseCallback>,
+    data: *mut c_void,
+) -> Errno {
+    dlclose_callbacks::add_dlclose_callback(pre, post, data)
+}
+
+pub(super) fn del_dlclose_callback(
+    pre: Option<PreDlcloseCallback>,
+    post: Option<PostDlcloseCallback>,
+    data: *mut c_void,
+) -> Errno {
+    dlclose_callbacks::del_dlclose_callback(pre, post, data)
 }
 
-pub(super) fn invoke_dlopen_callbacks_post(filename: *const c_char, result: i32) {
-    dlopen_callbacks::invoke_dlopen_callbacks_post(filename, result)
+pub(super) fn begin_dlclose_callbacks(
+    handle: *mut c_void,
+    module: Option<&ModuleInfo>,
+) -> dlclose_callbacks::DlcloseCallbackScope {
+    dlclose_callbacks::DlcloseCallbackScope::begin(handle, module)
 }
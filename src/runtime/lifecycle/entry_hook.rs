@@ -1,15 +1,28 @@
 // hook 操作入口，提供 hook_single/hook_partial/hook_all/unhook 等 API 的实现
+use crate::android::signal_guard;
 use crate::api::{
-    CallerAllowFilter, HookStub, HookedCallback, ModuleIdentity,
+    CalleeSelection, CallerAllowFilter, CallerAllowFilterEx, HookEventCallback, HookMode, HookSpec,
+    HookStats, HookStub, HookTaskType, HookedCallback, ModuleIdentity, SingleBindPolicy, TaskInfo,
 };
+use crate::elf;
 use crate::errno::Errno;
+use crate::log;
+use std::collections::BTreeSet;
 use std::ffi::c_void;
 
 use super::super::refresh::{self, CallbackEvent};
-use super::super::state::{AllowFilterEntry, GLOBAL, HookedEntry, Task, TaskType};
+use super::super::rules;
+use super::super::state::{
+    AllowFilterEntry, CallerFilter, CoreState, DEFAULT_PROXY_PRIORITY, GLOBAL, HookEventEntry,
+    HookedEntry, INTERNAL_PROXY_PRIORITY, Task, TaskType,
+};
+use super::monitor;
 use super::process;
-use super::{add_task, invoke_callbacks};
+use super::record_listener;
+use super::task_ops;
+use super::{add_task, apply_pending_tasks, invoke_callbacks, merge_pending_tasks};
 use crate::runtime::state::{MutexPoisonRecover, RwLockPoisonRecover};
+use crate::runtime::thread_state;
 
 pub(super) fn hook_single(
     caller_path_name: &str,
@@ -18,15 +31,217 @@ pub(super) fn hook_single(
     new_func: *mut c_void,
     hooked: Option<HookedCallback>,
     hooked_arg: *mut c_void,
-) -> Option<HookStub> {
-    if caller_path_name.is_empty() || sym_name.is_empty() || new_func.is_null() {
+) -> Result<HookStub, Errno> {
+    let task = single_task(
+        caller_path_name,
+        callee_path_name,
+        sym_name,
+        new_func,
+        hooked,
+        hooked_arg,
+    )?;
+    add_task(task)
+}
+
+// 优先级保存在任务上，module 重新加载后再次挂载时按同一优先级插入 hub 链
+// INTERNAL_PROXY_PRIORITY 保留给 monitor 内部 proxy
+pub(super) fn hook_single_with_priority(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    priority: i32,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    if priority == INTERNAL_PROXY_PRIORITY {
+        return Err(Errno::InvalidArg);
+    }
+    let mut task = single_task(
+        caller_path_name,
+        callee_path_name,
+        sym_name,
+        new_func,
+        hooked,
+        hooked_arg,
+    )?;
+    task.priority = priority;
+    add_task(task)
+}
+
+// 线程集合保存在任务上，module 重新加载后再次挂载时沿用；集合为空等价于 hook_single
+pub(super) fn hook_single_for_threads(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    tids: &[i32],
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    let threads = normalize_threads(tids).ok_or(Errno::InvalidArg)?;
+    let mut task = single_task(
+        caller_path_name,
+        callee_path_name,
+        sym_name,
+        new_func,
+        hooked,
+        hooked_arg,
+    )?;
+    task.threads = threads;
+    add_task(task)
+}
+
+// 绑定策略保存在任务上，绑定的实例卸载后按同一策略重新挑选
+pub(super) fn hook_single_with_policy(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    policy: SingleBindPolicy,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    let mut task = single_task(
+        caller_path_name,
+        callee_path_name,
+        sym_name,
+        new_func,
+        hooked,
+        hooked_arg,
+    )?;
+    task.bind_policy = policy;
+    add_task(task)
+}
+
+// 尚未合并的任务只更新 registry 中的集合，首次挂载时生效
+pub(super) fn set_task_threads(stub: HookStub, tids: &[i32]) -> Errno {
+    let Some(threads) = normalize_threads(tids).filter(|_| stub != 0) else {
+        return Errno::InvalidArg;
+    };
+    if task_ops::set_pending_threads(stub, &threads) {
+        return Errno::Ok;
+    }
+
+    let mut state = GLOBAL.state.lock_or_poison();
+    if state.init.status != Errno::Ok {
+        return state.init.status;
+    }
+    if refresh::set_task_threads(&mut state, stub, threads) {
+        Errno::Ok
+    } else {
+        Errno::InvalidArg
+    }
+}
+
+// tid 必须为正数，结果升序去重
+fn normalize_threads(tids: &[i32]) -> Option<Vec<i32>> {
+    if tids.iter().any(|tid| *tid <= 0) {
         return None;
     }
-    let task = Task {
+    let mut threads = tids.to_vec();
+    threads.sort_unstable();
+    threads.dedup();
+    Some(threads)
+}
+
+// 同一批次中重复的 proxy 地址无法区分各自的调用链，后出现的条目以 RepeatedFunc 拒绝
+pub(super) fn hook_batch(specs: &[HookSpec]) -> Vec<Result<HookStub, Errno>> {
+    let mut seen_funcs = BTreeSet::new();
+    let tasks = specs
+        .iter()
+        .map(|spec| {
+            if !seen_funcs.insert(spec.new_func as usize) {
+                log::warn(format_args!(
+                    "hook batch entry {} rejected: duplicate proxy {:p}",
+                    spec.sym_name, spec.new_func
+                ));
+                return Err(Errno::RepeatedFunc);
+            }
+            single_task(
+                spec.caller_path_name,
+                spec.callee_path_name,
+                spec.sym_name,
+                spec.new_func,
+                spec.hooked,
+                spec.hooked_arg,
+            )
+        })
+        .collect();
+    task_ops::add_tasks(tasks)
+}
+
+// sym_pattern 不含 '*' 时等同 hook_single；通配模式不支持版本后缀
+pub(super) fn hook_single_pattern(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_pattern: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    if !rules::is_sym_pattern(sym_pattern) {
+        return hook_single(
+            caller_path_name,
+            callee_path_name,
+            sym_pattern,
+            new_func,
+            hooked,
+            hooked_arg,
+        );
+    }
+    if sym_pattern.contains('@') {
+        return Err(Errno::InvalidArg);
+    }
+    let task = new_single_task(
+        caller_path_name,
+        callee_path_name,
+        sym_pattern,
+        new_func,
+        hooked,
+        hooked_arg,
+    )?;
+    add_task(task)
+}
+
+fn single_task(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<Task, Errno> {
+    if !is_valid_sym_name(sym_name) {
+        return Err(Errno::InvalidArg);
+    }
+    new_single_task(
+        caller_path_name,
+        callee_path_name,
+        sym_name,
+        new_func,
+        hooked,
+        hooked_arg,
+    )
+}
+
+fn new_single_task(
+    caller_path_name: &str,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<Task, Errno> {
+    if caller_path_name.is_empty() || new_func.is_null() {
+        return Err(Errno::InvalidArg);
+    }
+    Ok(Task {
         stub: 0,
         task_type: TaskType::Single,
         caller_path_name: Some(caller_path_name.to_string()),
         caller_allow_filter: None,
+        caller_allow_list: Vec::new(),
         callee_path_name: callee_path_name.map(ToString::to_string),
         sym_name: sym_name.to_string(),
         new_func: new_func as usize,
@@ -34,8 +249,19 @@ pub(super) fn hook_single(
             callback: cb,
             arg: hooked_arg as usize,
         }),
-    };
-    add_task(task)
+        event_callback: None,
+        callee_selection: CalleeSelection::All,
+        bind_policy: SingleBindPolicy::FirstMatch,
+        paused: false,
+        priority: DEFAULT_PROXY_PRIORITY,
+        threads: Vec::new(),
+    })
+}
+
+// 符号名可带版本后缀 "name@VERSION"，名称与版本都不能为空；含 '*' 的通配模式只能经 hook_single_pattern 注册
+fn is_valid_sym_name(sym_name: &str) -> bool {
+    let (name, version) = elf::split_symbol_version(sym_name);
+    !name.is_empty() && version != Some("") && !rules::is_sym_pattern(sym_name)
 }
 
 pub(super) fn hook_partial(
@@ -46,9 +272,49 @@ pub(super) fn hook_partial(
     new_func: *mut c_void,
     hooked: Option<HookedCallback>,
     hooked_arg: *mut c_void,
-) -> Option<HookStub> {
-    if sym_name.is_empty() || new_func.is_null() {
-        return None;
+) -> Result<HookStub, Errno> {
+    add_partial_task(
+        CallerFilter::Path(caller_allow_filter),
+        caller_allow_filter_arg,
+        callee_path_name,
+        sym_name,
+        new_func,
+        hooked,
+        hooked_arg,
+    )
+}
+
+pub(super) fn hook_partial_ex(
+    caller_allow_filter: CallerAllowFilterEx,
+    caller_allow_filter_arg: *mut c_void,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    add_partial_task(
+        CallerFilter::Instance(caller_allow_filter),
+        caller_allow_filter_arg,
+        callee_path_name,
+        sym_name,
+        new_func,
+        hooked,
+        hooked_arg,
+    )
+}
+
+fn add_partial_task(
+    caller_allow_filter: CallerFilter,
+    caller_allow_filter_arg: *mut c_void,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    if !is_valid_sym_name(sym_name) || new_func.is_null() {
+        return Err(Errno::InvalidArg);
     }
     let task = Task {
         stub: 0,
@@ -58,6 +324,7 @@ pub(super) fn hook_partial(
             filter: caller_allow_filter,
             arg: caller_allow_filter_arg as usize,
         }),
+        caller_allow_list: Vec::new(),
         callee_path_name: callee_path_name.map(ToString::to_string),
         sym_name: sym_name.to_string(),
         new_func: new_func as usize,
@@ -65,6 +332,12 @@ pub(super) fn hook_partial(
             callback: cb,
             arg: hooked_arg as usize,
         }),
+        event_callback: None,
+        callee_selection: CalleeSelection::All,
+        bind_policy: SingleBindPolicy::FirstMatch,
+        paused: false,
+        priority: DEFAULT_PROXY_PRIORITY,
+        threads: Vec::new(),
     };
     add_task(task)
 }
@@ -75,15 +348,56 @@ pub(super) fn hook_all(
     new_func: *mut c_void,
     hooked: Option<HookedCallback>,
     hooked_arg: *mut c_void,
-) -> Option<HookStub> {
-    if sym_name.is_empty() || new_func.is_null() {
-        return None;
+) -> Result<HookStub, Errno> {
+    hook_all_task(
+        Vec::new(),
+        callee_path_name,
+        sym_name,
+        new_func,
+        hooked,
+        hooked_arg,
+    )
+}
+
+// 空白名单与 hook_all 无法区分，按参数无效拒绝；每条规则按 @base / %instance / ^namespace 语义校验
+pub(super) fn hook_all_with_callers(
+    callers: &[&str],
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    if callers.is_empty() || !callers.iter().all(|rule| rules::is_valid_rule(rule)) {
+        return Err(Errno::InvalidArg);
+    }
+    hook_all_task(
+        callers.iter().map(ToString::to_string).collect(),
+        callee_path_name,
+        sym_name,
+        new_func,
+        hooked,
+        hooked_arg,
+    )
+}
+
+fn hook_all_task(
+    caller_allow_list: Vec<String>,
+    callee_path_name: Option<&str>,
+    sym_name: &str,
+    new_func: *mut c_void,
+    hooked: Option<HookedCallback>,
+    hooked_arg: *mut c_void,
+) -> Result<HookStub, Errno> {
+    if !is_valid_sym_name(sym_name) || new_func.is_null() {
+        return Err(Errno::InvalidArg);
     }
     let task = Task {
         stub: 0,
         task_type: TaskType::All,
         caller_path_name: None,
         caller_allow_filter: None,
+        caller_allow_list,
         callee_path_name: callee_path_name.map(ToString::to_string),
         sym_name: sym_name.to_string(),
         new_func: new_func as usize,
@@ -91,38 +405,271 @@ pub(super) fn hook_all(
             callback: cb,
             arg: hooked_arg as usize,
         }),
+        event_callback: None,
+        callee_selection: CalleeSelection::All,
+        bind_policy: SingleBindPolicy::FirstMatch,
+        paused: false,
+        priority: DEFAULT_PROXY_PRIORITY,
+        threads: Vec::new(),
     };
     add_task(task)
 }
 
-// unhook 需要持有 dlclose_lock 和 refresh_mutex 防止与 refresh 并发冲突
+// 尚未合并的任务直接从 registry 撤回；已生效的任务需持有 dlclose_lock 和 refresh_mutex
+// 等待其 slot 恢复完成后才返回，保证返回后 proxy 不再被调用
 pub(super) fn unhook(stub: HookStub) -> Errno {
     if stub == 0 {
         return Errno::InvalidArg;
     }
+    if task_ops::withdraw_pending_task(stub) {
+        refresh::set_task_hit_stats(&mut GLOBAL.state.lock_or_poison(), stub, false);
+        return Errno::Ok;
+    }
+
+    let mut events = Vec::new();
+    let (status, merged) = {
+        let _dlclose_guard = GLOBAL.dlclose_lock.read_or_poison();
+        let _refresh_guard = GLOBAL.refresh_mutex.lock_or_poison();
+        let mut state = GLOBAL.state.lock_or_poison();
+        if state.init.status != Errno::Ok {
+            return state.init.status;
+        }
+        process::ensure_process_context(&mut state);
+        let merged = merge_pending_tasks(&mut state);
+
+        let status = if state.tasks.contains_key(&stub) {
+            remove_task(&mut state, stub, &mut events)
+        } else {
+            Errno::InvalidArg
+        };
+        (status, merged)
+    };
+    merged.finish();
+    invoke_callbacks(events);
+    apply_pending_tasks();
+    status
+}
+
+// 在同一次持锁内卸载 hook 指定符号的全部任务，monitor 内部任务除外
+// 返回卸载的任务数与首个失败的状态
+pub(super) fn unhook_symbol(sym_name: &str, caller_rule: Option<&str>) -> (usize, Errno) {
+    if sym_name.is_empty() || caller_rule.is_some_and(str::is_empty) {
+        return (0, Errno::InvalidArg);
+    }
+    let internal = monitor::proxy_addrs();
+
+    let mut events = Vec::new();
+    let (result, merged) = {
+        let _dlclose_guard = GLOBAL.dlclose_lock.read_or_poison();
+        let _refresh_guard = GLOBAL.refresh_mutex.lock_or_poison();
+        let mut state = GLOBAL.state.lock_or_poison();
+        if state.init.status != Errno::Ok {
+            return (0, state.init.status);
+        }
+        process::ensure_process_context(&mut state);
+        let merged = merge_pending_tasks(&mut state);
+
+        let stubs: Vec<HookStub> = state
+            .task_order
+            .iter()
+            .copied()
+            .filter(|stub| {
+                state.tasks.get(stub).is_some_and(|task| {
+                    task.sym_name == sym_name
+                        && !internal.contains(&task.new_func)
+                        && caller_rule.is_none_or(|rule| {
+                            task.caller_path_name
+                                .as_deref()
+                                .is_some_and(|caller| rules::rule_match(caller, rule))
+                        })
+                })
+            })
+            .collect();
+        let mut first_err = Errno::Ok;
+        for stub in &stubs {
+            let status = remove_task(&mut state, *stub, &mut events);
+            if status != Errno::Ok && first_err.is_ok() {
+                first_err = status;
+            }
+        }
+        ((stubs.len(), first_err), merged)
+    };
+    merged.finish();
+    invoke_callbacks(events);
+    apply_pending_tasks();
+    result
+}
+
+// 恢复任务的全部 slot 并从 state 中移除，调用方需持有 dlclose_lock 与 refresh_mutex
+// Unhooked 事件写入 events，由调用方释放锁后投递
+fn remove_task(state: &mut CoreState, stub: HookStub, events: &mut Vec<CallbackEvent>) -> Errno {
+    events.extend(refresh::unhook_events(state, stub));
+    refresh::set_task_hit_stats(state, stub, false);
+    refresh::release_task_tid_filter(state, stub);
+    let caller = super::super::record::unhook_record_caller(state, stub);
+    let status = refresh::unhook_task(state, stub);
+    super::super::record::add_unhook_record(state, status.as_i32(), stub, caller.as_ref());
+    state.tasks.remove(&stub);
+    state.task_order.retain(|value| *value != stub);
+    state.task_slots.remove(&stub);
+    task_ops::forget_task(stub);
+    status
+}
+
+// 暂停期间任务的 slot 与 hub 保持不变，调用直接落到链上下一个 proxy 或原函数
+pub(super) fn set_paused(stub: HookStub, paused: bool) -> Errno {
+    if stub == 0 {
+        return Errno::InvalidArg;
+    }
+    let pending = task_ops::set_pending_paused(stub, paused);
 
-    let _dlclose_guard = GLOBAL.dlclose_lock.read_or_poison();
-    let _refresh_guard = GLOBAL.refresh_mutex.lock_or_poison();
     let mut state = GLOBAL.state.lock_or_poison();
     if state.init.status != Errno::Ok {
         return state.init.status;
     }
-    process::ensure_process_context(&mut state);
+    let status = if pending {
+        Errno::Ok
+    } else {
+        refresh::set_task_paused(&mut state, stub, paused)
+    };
+    super::super::record::add_pause_record(&mut state, paused, status.as_i32(), stub);
+    drop(state);
+    record_listener::deliver_pending();
+    status
+}
 
-    if !state.tasks.contains_key(&stub) {
+// 统计块挂在任务名下，尚未合并的任务在首次 refresh 时挂到新 slot
+pub(super) fn set_hook_stats_enabled(stub: HookStub, enabled: bool) -> Errno {
+    if stub == 0 {
+        return Errno::InvalidArg;
+    }
+    let mut state = GLOBAL.state.lock_or_poison();
+    if state.init.status != Errno::Ok {
+        return state.init.status;
+    }
+    if !state.tasks.contains_key(&stub) && !task_ops::is_pending_task(stub) {
         return Errno::InvalidArg;
     }
+    refresh::set_task_hit_stats(&mut state, stub, enabled);
+    Errno::Ok
+}
 
-    let status = refresh::unhook_task(&mut state, stub);
-    super::super::record::add_unhook_record(&mut state, status.as_i32(), stub);
-    state.tasks.remove(&stub);
-    state.task_order.retain(|value| *value != stub);
-    state.task_slots.remove(&stub);
-    status
+pub(super) fn get_hook_stats(stub: HookStub) -> Option<HookStats> {
+    let state = GLOBAL.state.lock_or_poison();
+    refresh::task_hit_stats(&state, stub)
+}
+
+pub(super) fn get_orig_func(stub: HookStub) -> Option<*mut c_void> {
+    get_orig_funcs(stub)
+        .into_iter()
+        .next()
+        .map(|(_, orig_func)| orig_func)
+}
+
+pub(super) fn get_orig_funcs(stub: HookStub) -> Vec<(String, *mut c_void)> {
+    let state = GLOBAL.state.lock_or_poison();
+    refresh::task_orig_funcs(&state, stub)
+        .into_iter()
+        .map(|(caller, orig_func)| (caller, orig_func as *mut c_void))
+        .collect()
 }
 
+// 已合并任务按 task_order 排列，其后是尚未合并的任务（slot 数为 0）
+pub(super) fn get_hook_tasks() -> Vec<TaskInfo> {
+    let internal = monitor::proxy_addrs();
+    let state = GLOBAL.state.lock_or_poison();
+    let merged = state.task_order.iter().filter_map(|stub| {
+        let task = state.tasks.get(stub)?;
+        let slot_count = state.task_slots.get(stub).map_or(0, |slots| slots.len());
+        Some(task_info(task, slot_count))
+    });
+    let mut tasks: Vec<TaskInfo> = merged.collect();
+    tasks.extend(
+        task_ops::pending_tasks()
+            .iter()
+            .map(|task| task_info(task, 0)),
+    );
+    tasks.retain(|task| !internal.contains(&task.new_func));
+    tasks
+}
+
+fn task_info(task: &Task, slot_count: usize) -> TaskInfo {
+    TaskInfo {
+        stub: task.stub,
+        task_type: match task.task_type {
+            TaskType::Single => HookTaskType::Single,
+            TaskType::Partial => HookTaskType::Partial,
+            TaskType::All => HookTaskType::All,
+        },
+        caller_path_name: task.caller_path_name.clone(),
+        caller_allow_list: task.caller_allow_list.clone(),
+        callee_path_name: task.callee_path_name.clone(),
+        sym_name: task.sym_name.clone(),
+        new_func: task.new_func,
+        slot_count,
+        paused: task.paused,
+        priority: task.priority,
+        threads: task.threads.clone(),
+    }
+}
+
+pub(super) fn set_callee_selection(stub: HookStub, selection: CalleeSelection) -> Errno {
+    if stub == 0 {
+        return Errno::InvalidArg;
+    }
+    if task_ops::set_pending_callee_selection(stub, selection) {
+        return Errno::Ok;
+    }
+
+    let mut state = GLOBAL.state.lock_or_poison();
+    if state.init.status != Errno::Ok {
+        return state.init.status;
+    }
+    match state.tasks.get_mut(&stub) {
+        Some(task) => {
+            task.callee_selection = selection;
+            Errno::Ok
+        }
+        None => Errno::InvalidArg,
+    }
+}
+
+// 注册前已产生的 Hooked 事件不会补发
+pub(super) fn set_hook_event_callback(
+    stub: HookStub,
+    callback: Option<HookEventCallback>,
+    arg: *mut c_void,
+) -> Errno {
+    if stub == 0 {
+        return Errno::InvalidArg;
+    }
+    let entry = callback.map(|callback| HookEventEntry {
+        callback,
+        arg: arg as usize,
+    });
+    if task_ops::set_pending_event_callback(stub, entry) {
+        return Errno::Ok;
+    }
+
+    let mut state = GLOBAL.state.lock_or_poison();
+    if state.init.status != Errno::Ok {
+        return state.init.status;
+    }
+    match state.tasks.get_mut(&stub) {
+        Some(task) => {
+            task.event_callback = entry;
+            Errno::Ok
+        }
+        None => Errno::InvalidArg,
+    }
+}
+
+// '!' 前缀的规则为例外，命中的模块即使匹配其他 ignore 规则也不跳过
 pub(super) fn add_ignore(caller_path_name: &str) -> Errno {
-    if caller_path_name.is_empty() {
+    let rule = caller_path_name
+        .strip_prefix('!')
+        .unwrap_or(caller_path_name);
+    if rule.is_empty() {
         return Errno::InvalidArg;
     }
 
@@ -134,10 +681,32 @@ pub(super) fn add_ignore(caller_path_name: &str) -> Errno {
     {
         return Errno::Ok;
     }
+    refresh::note_module_rules([caller_path_name]);
     state.ignore_callers.push(caller_path_name.to_string());
     Errno::Ok
 }
 
+// 只删除文本完全相同的规则；monitor 运行时请求全量刷新，让此前跳过的模块补挂
+pub(super) fn del_ignore(caller_path_name: &str) -> Errno {
+    {
+        let mut state = GLOBAL.state.lock_or_poison();
+        let Some(index) = state
+            .ignore_callers
+            .iter()
+            .position(|value| value == caller_path_name)
+        else {
+            return Errno::NotFound;
+        };
+        state.ignore_callers.remove(index);
+    }
+    task_ops::request_refresh_async_full();
+    Errno::Ok
+}
+
+pub(super) fn get_ignores() -> Vec<String> {
+    GLOBAL.state.lock_or_poison().ignore_callers.clone()
+}
+
 // 通过 dlinfo 从 handle 解析模块身份信息并缓存到 hint 系统
 pub(super) fn get_module_identity(handle: *mut c_void) -> Option<ModuleIdentity> {
     if handle.is_null() {
@@ -170,16 +739,77 @@ pub(super) fn get_module_identity_with_symbol(
     })
 }
 
-pub(super) fn refresh() -> Errno {
-    let _dlclose_guard = GLOBAL.dlclose_lock.read_or_poison();
-    let _refresh_guard = GLOBAL.refresh_mutex.lock_or_poison();
-    let mut state = GLOBAL.state.lock_or_poison();
-    if state.init.status != Errno::Ok {
-        return state.init.status;
+// 只对 handle 对应的模块应用全部任务，随其一同加载的依赖库不在范围内
+// handle 无法解析出模块身份时退回全量 refresh
+pub(super) fn refresh_handle(handle: *mut c_void) -> Errno {
+    if handle.is_null() {
+        return Errno::InvalidArg;
     }
-    process::ensure_process_context(&mut state);
-    let (status, events): (Errno, Vec<CallbackEvent>) = refresh::refresh_all(&mut state);
-    drop(state);
+    let Some(module) = refresh::module_identity_from_handle(handle) else {
+        log::debug(format_args!(
+            "refresh handle 0x{:x} unresolved, fall back to full refresh",
+            handle as usize
+        ));
+        return refresh();
+    };
+
+    let (status, merged, events) = {
+        let _dlclose_guard = GLOBAL.dlclose_lock.read_or_poison();
+        let _refresh_guard = GLOBAL.refresh_mutex.lock_or_poison();
+        let mut state = GLOBAL.state.lock_or_poison();
+        if state.init.status != Errno::Ok {
+            return state.init.status;
+        }
+        process::ensure_process_context(&mut state);
+        let merged = merge_pending_tasks(&mut state);
+        refresh::observe_module_identity(&module);
+        let (status, events) = refresh::refresh_module(&mut state, &module);
+        (status, merged, events)
+    };
+    merged.finish();
     invoke_callbacks(events);
+    apply_pending_tasks();
     status
 }
+
+pub(super) fn refresh() -> Errno {
+    // 手动模式观察不到 dlclose，以主动刷新作为调用方模块缓存的失效点
+    thread_state::invalidate_caller_module_cache();
+    // 没有 monitor 线程时由主动刷新按间隔把守卫 handler 提升回 sigchain 链首
+    signal_guard::reassert_front_of_chain(false);
+    let (status, merged, events) = {
+        let _dlclose_guard = GLOBAL.dlclose_lock.read_or_poison();
+        let _refresh_guard = GLOBAL.refresh_mutex.lock_or_poison();
+        let mut state = GLOBAL.state.lock_or_poison();
+        if state.init.status != Errno::Ok {
+            return state.init.status;
+        }
+        process::ensure_process_context(&mut state);
+        let merged = merge_pending_tasks(&mut state);
+        let (status, events): (Errno, Vec<CallbackEvent>) = refresh::refresh_all(&mut state);
+        (status, merged, events)
+    };
+    merged.finish();
+    invoke_callbacks(events);
+    apply_pending_tasks();
+    status
+}
+
+// 只唤醒 monitor 线程，不在调用线程上刷新；带 handle 时 monitor 先经 handle 观察模块身份
+// monitor 线程未启动时静默忽略
+pub(super) fn request_monitor_refresh(handle: Option<*mut c_void>) -> Errno {
+    if handle.is_some_and(|handle| handle.is_null()) {
+        return Errno::InvalidArg;
+    }
+    {
+        let state = GLOBAL.state.lock_or_poison();
+        if state.init.status != Errno::Ok || state.init.mode != HookMode::Automatic {
+            return Errno::InitErrSafe;
+        }
+    }
+    match handle {
+        Some(handle) => task_ops::request_refresh_async_with_handle(handle),
+        None => task_ops::request_refresh_async_full(),
+    }
+    Errno::Ok
+}
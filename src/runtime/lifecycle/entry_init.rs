@@ -1,30 +1,48 @@
 // 运行时初始化入口，负责信号处理器安装、CFI 禁用、monitor 线程启动
-use crate::api::HookMode;
+use crate::api::{HUB_STACK_FIXED_DEPTH, HookMode, InitOptions, SignalHandlerMode};
 use crate::android::signal_guard;
 use crate::errno::Errno;
 use crate::log;
 use crate::version;
+use std::sync::Mutex;
 
+use super::atfork;
+use super::callback_dispatch;
+use super::capabilities;
+use super::entry_hook;
 use super::monitor;
+use super::process;
+use super::task_ops;
+use super::super::artifact;
 use super::super::cfi;
-use super::super::state::GLOBAL;
+use super::super::hub;
+use super::super::record;
+use super::super::refresh;
+use super::super::state::{self, CoreState, GLOBAL};
 use super::super::thread_state;
-use crate::runtime::state::{MutexPoisonRecover, set_install_pid};
+use crate::runtime::state::{MutexPoisonRecover, RwLockPoisonRecover, set_install_pid};
+
+// 串行化 set_mode，避免并发切换交错安装/卸载 monitor 任务
+static MODE_SWITCH_LOCK: Mutex<()> = Mutex::new(());
 
 pub(super) fn get_version() -> String {
     version::version_str_full()
 }
 
-pub(super) fn init(mode: HookMode, debug: bool) -> Errno {
+pub(super) fn init(opts: InitOptions) -> Errno {
+    let mode = opts.mode;
     let mut should_start_monitor = false;
     let status = {
         let mut state = GLOBAL.state.lock_or_poison();
         if state.init.status != Errno::Uninit {
             return state.init.status;
         }
+        if !is_valid_options(&opts) {
+            return Errno::InitErrInvalidArg;
+        }
+        apply_options(&mut state, &opts);
 
-        state.debug = debug;
-        log::set_debug_enabled(debug);
+        log::set_debug_enabled(opts.debug);
         state.init.mode = mode;
         let pid = unsafe { libc::getpid() };
         state.process_id = pid as usize;
@@ -42,7 +60,8 @@ pub(super) fn init(mode: HookMode, debug: bool) -> Errno {
             Err(_) => Errno::InitErrSig,
         };
         if state.init.status == Errno::Ok {
-            state.init.status = cfi::disable_slowpath();
+            let ignored = refresh::ignored_loaded_modules(&state.ignore_callers);
+            state.init.status = cfi::disable_slowpath(&ignored);
         }
 
         if state.init.status == Errno::Ok && mode == HookMode::Automatic {
@@ -55,6 +74,11 @@ pub(super) fn init(mode: HookMode, debug: bool) -> Errno {
     if status != Errno::Ok {
         return status;
     }
+    let mut proxies = monitor::proxy_addrs();
+    proxies.extend(cfi::proxy_addrs());
+    artifact::publish_static(proxies);
+    task_ops::open_registry();
+    capabilities::probe();
 
     if should_start_monitor {
         monitor::start_monitor_thread();
@@ -69,3 +93,110 @@ pub(super) fn init(mode: HookMode, debug: bool) -> Errno {
     log::info(format_args!("{}", version::version_str_full()));
     Errno::Ok
 }
+
+// fork 后继续运行的子进程调用：先按新 PID 恢复并清空继承的 slot/hub，再以已注册任务重新刷新
+// 父进程的 monitor 线程与回调线程不存在于子进程，句柄只能遗弃，按初始化时的模式重新启动
+pub(super) fn reinit_after_fork() -> Errno {
+    if !state::is_forked_child() {
+        return Errno::Invalid;
+    }
+    let mode = {
+        let _dlclose_guard = GLOBAL.dlclose_lock.read_or_poison();
+        let _refresh_guard = GLOBAL.refresh_mutex.lock_or_poison();
+        let mut state = GLOBAL.state.lock_or_poison();
+        if state.init.status != Errno::Ok {
+            return state.init.status;
+        }
+        std::mem::forget(state.monitor_thread.take());
+        state.monitor_running = false;
+        process::ensure_process_context(&mut state);
+        set_install_pid(state.process_id as i32);
+        if !thread_state::rebind_current_thread_state() {
+            log::warn(format_args!(
+                "fork 子进程线程状态重建失败，调用线程退化到无栈路径"
+            ));
+        }
+        if signal_guard::handler_mode() == SignalHandlerMode::None
+            && signal_guard::add_handler().is_err()
+        {
+            return Errno::InitErrSig;
+        }
+        callback_dispatch::reset_after_fork();
+        state.init.mode
+    };
+
+    if mode == HookMode::Automatic {
+        // 继承的内部 monitor 任务按普通任务撤下后重新安装，同时复位 monitor 原子状态
+        monitor::uninstall_auto_loader_monitor_hooks();
+        monitor::start_monitor_thread();
+        monitor::install_auto_loader_monitor_hooks();
+    } else {
+        monitor::reset_auto_monitor_installed();
+    }
+    log::info(format_args!(
+        "runtime reinitialized after fork pid={}",
+        unsafe { libc::getpid() }
+    ));
+    entry_hook::refresh()
+}
+
+fn is_valid_options(opts: &InitOptions) -> bool {
+    opts.monitor_poll
+        .is_none_or(|(min, max)| min.as_millis() != 0 && min <= max)
+        && opts.loader_stable_threshold.is_none_or(|count| count != 0)
+        && opts.hub_destroy_delay_secs.is_none_or(|secs| secs != 0)
+        && opts
+            .record_capacity
+            .is_none_or(record::is_valid_record_capacity)
+        && opts.hub_stack_spill.is_none_or(|(threshold, hard_cap)| {
+            (1..=HUB_STACK_FIXED_DEPTH).contains(&threshold) && hard_cap >= threshold
+        })
+}
+
+// 在 monitor 线程启动与 CFI 补丁之前写入，首轮刷新即按新参数运行
+fn apply_options(state: &mut CoreState, opts: &InitOptions) {
+    monitor::set_poll_interval(opts.monitor_poll);
+    monitor::set_loader_stable_threshold(opts.loader_stable_threshold);
+    hub::set_destroy_delay(opts.hub_destroy_delay_secs);
+    hub::set_rwx_trampolines(opts.rwx_trampolines);
+    hub::set_stack_limits(opts.hub_stack_spill);
+    cfi::set_patch_scope(opts.cfi_patch_scope);
+    refresh::set_slot_guard(opts.slot_guard);
+    atfork::install(opts.fork_child_restore);
+    if let Some(max_entries) = opts.record_capacity {
+        let _ = record::set_record_capacity(state, max_entries);
+    }
+}
+
+// 切到 Automatic：启动或唤醒 monitor 线程并安装 monitor 任务，再请求一轮全量刷新补上 Manual 期间的变化
+// 切到 Manual：unhook 内部 monitor 任务，monitor 线程挂起；切换期间发生的 dlopen 可能需要一次手动 refresh
+pub(super) fn set_mode(mode: HookMode) -> Errno {
+    let _switch_guard = MODE_SWITCH_LOCK.lock_or_poison();
+    {
+        let mut state = GLOBAL.state.lock_or_poison();
+        if state.init.status != Errno::Ok {
+            return state.init.status;
+        }
+        if state.init.mode == mode {
+            return Errno::Ok;
+        }
+        state.init.mode = mode;
+        GLOBAL.condvar.notify_all();
+    }
+
+    match mode {
+        HookMode::Automatic => {
+            monitor::start_monitor_thread();
+            let state = GLOBAL.state.lock_or_poison();
+            if state.init.status != Errno::Ok {
+                return state.init.status;
+            }
+            drop(state);
+            monitor::install_auto_loader_monitor_hooks();
+            task_ops::request_refresh_async_full();
+        }
+        HookMode::Manual => monitor::uninstall_auto_loader_monitor_hooks(),
+    }
+    log::info(format_args!("hook mode switched to {:?}", mode));
+    Errno::Ok
+}
@@ -1,17 +1,22 @@
 // dlopen/dlclose 监控模块，自动检测动态库加载卸载并触发 hook 刷新
 // 支持 loader hook (API >= 26) 和 legacy hook 两种策略，可自动降级
+use crate::api::{
+    CalleeSelection, HookMode, HookStub, MonitorRefreshCounts, MonitorStatus, MonitorStrategy,
+    SingleBindPolicy,
+};
 use crate::errno::Errno;
 use crate::log;
 use std::ffi::c_void;
 use std::ffi::{CStr, c_char};
 use std::env;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use super::super::hub;
 use super::super::refresh;
 use super::super::state::GLOBAL;
-use super::super::state::{Task, TaskType};
+use super::super::state::{INTERNAL_PROXY_PRIORITY, Task, TaskType};
 use crate::runtime::state::MutexPoisonRecover;
 mod poll;
 mod proxies;
@@ -28,6 +33,25 @@ static MONITOR_LOADER_SUCCESS_STREAK: AtomicUsize = AtomicUsize::new(0);
 static MONITOR_LEGACY_HOOK_INSTALLED: AtomicBool = AtomicBool::new(false);
 // 是否有待处理的 legacy hook 安装请求
 static MONITOR_LEGACY_HOOK_REQUESTED: AtomicBool = AtomicBool::new(false);
+// 由 dlopen/dlclose 事件触发的刷新轮数
+static MONITOR_EVENT_REFRESH_COUNT: AtomicU64 = AtomicU64::new(0);
+// 枚举全部模块的刷新轮数与 dlclose 后只清理卸载模块的刷新轮数
+static MONITOR_FULL_REFRESH_COUNT: AtomicU64 = AtomicU64::new(0);
+static MONITOR_SCOPED_REFRESH_COUNT: AtomicU64 = AtomicU64::new(0);
+// 周期性兜底巡检间隔上下限（毫秒）与 loader 稳定阈值，由 init_with_options 配置
+static MONITOR_POLL_MIN_MS: AtomicU64 =
+    AtomicU64::new(MONITOR_FALLBACK_REFRESH_INTERVAL_MIN.as_millis() as u64);
+static MONITOR_POLL_MAX_MS: AtomicU64 =
+    AtomicU64::new(MONITOR_FALLBACK_REFRESH_INTERVAL_MAX.as_millis() as u64);
+static LOADER_STABLE_THRESHOLD: AtomicUsize = AtomicUsize::new(LOADER_STABLE_SUCCESS_THRESHOLD);
+// monitor 线程当前的巡检等待间隔（毫秒），由 poll 状态同步
+static MONITOR_POLL_INTERVAL_MS: AtomicU64 =
+    AtomicU64::new(MONITOR_FALLBACK_REFRESH_INTERVAL_MIN.as_millis() as u64);
+// 最近一次事件触发 / 周期巡检刷新的 Unix 毫秒，0 表示尚未发生
+static MONITOR_LAST_EVENT_REFRESH_MS: AtomicU64 = AtomicU64::new(0);
+static MONITOR_LAST_PERIODIC_REFRESH_MS: AtomicU64 = AtomicU64::new(0);
+// dlopen 事件合并窗口（毫秒），0 表示不合并
+static MONITOR_DEBOUNCE_MS: AtomicU64 = AtomicU64::new(MONITOR_DEBOUNCE_DEFAULT.as_millis() as u64);
 const RTLD_NEXT_FALLBACK: *mut c_void = (-1isize) as *mut c_void;
 const ANDROID_API_LEVEL_N: i32 = 24;
 const ANDROID_API_LEVEL_N_MR1: i32 = 25;
@@ -36,6 +60,8 @@ const SYSTEM_PROP_VALUE_MAX: usize = 92;
 const MONITOR_FALLBACK_REFRESH_INTERVAL_MIN: Duration = Duration::from_millis(500);
 const MONITOR_FALLBACK_REFRESH_INTERVAL_MAX: Duration = Duration::from_secs(8);
 const MONITOR_FALLBACK_BURST_ROUNDS: u8 = 3;
+const MONITOR_DEBOUNCE_DEFAULT: Duration = Duration::from_millis(30);
+const MONITOR_DEBOUNCE_MAX: Duration = Duration::from_millis(500);
 const LIBDL_BASENAME: &str = "libdl.so";
 const MONITOR_PERIODIC_ENV: &str = "SRX_HOOK_MONITOR_PERIODIC";
 const LOADER_STABLE_SUCCESS_THRESHOLD: usize = 64;
@@ -47,10 +73,17 @@ enum PeriodicPolicy {
     Force(bool),
 }
 
+#[cfg(target_os = "android")]
 unsafe extern "C" {
     fn __system_property_get(name: *const c_char, value: *mut c_char) -> libc::c_int;
 }
 
+// 宿主机测试构建没有系统属性，按未读到处理
+#[cfg(not(target_os = "android"))]
+unsafe fn __system_property_get(_name: *const c_char, _value: *mut c_char) -> libc::c_int {
+    0
+}
+
 pub(super) fn reset_auto_monitor_installed() {
     AUTO_MONITOR_INSTALLED.store(false, Ordering::SeqCst);
     MONITOR_PERIODIC_ESCALATED.store(false, Ordering::SeqCst);
@@ -60,6 +93,120 @@ pub(super) fn reset_auto_monitor_installed() {
     MONITOR_PERIODIC_ENABLED.store(should_enable_periodic_fallback(false), Ordering::SeqCst);
 }
 
+pub(super) fn event_refresh_count() -> u64 {
+    MONITOR_EVENT_REFRESH_COUNT.load(Ordering::Relaxed)
+}
+
+pub(super) fn set_debounce_window(window: Duration) {
+    let window = window.min(MONITOR_DEBOUNCE_MAX);
+    MONITOR_DEBOUNCE_MS.store(window.as_millis() as u64, Ordering::Relaxed);
+}
+
+fn debounce_window() -> Duration {
+    Duration::from_millis(MONITOR_DEBOUNCE_MS.load(Ordering::Relaxed))
+}
+
+// None 恢复默认值；调用方已校验 0 < min <= max
+pub(super) fn set_poll_interval(interval: Option<(Duration, Duration)>) {
+    let (min, max) = interval.unwrap_or((
+        MONITOR_FALLBACK_REFRESH_INTERVAL_MIN,
+        MONITOR_FALLBACK_REFRESH_INTERVAL_MAX,
+    ));
+    MONITOR_POLL_MIN_MS.store(min.as_millis() as u64, Ordering::Relaxed);
+    MONITOR_POLL_MAX_MS.store(max.as_millis() as u64, Ordering::Relaxed);
+}
+
+fn poll_interval_min() -> Duration {
+    Duration::from_millis(MONITOR_POLL_MIN_MS.load(Ordering::Relaxed))
+}
+
+fn poll_interval_max() -> Duration {
+    Duration::from_millis(MONITOR_POLL_MAX_MS.load(Ordering::Relaxed))
+}
+
+pub(super) fn set_loader_stable_threshold(threshold: Option<usize>) {
+    LOADER_STABLE_THRESHOLD.store(
+        threshold.unwrap_or(LOADER_STABLE_SUCCESS_THRESHOLD),
+        Ordering::Relaxed,
+    );
+}
+
+pub(super) fn refresh_counts() -> MonitorRefreshCounts {
+    MonitorRefreshCounts {
+        full: MONITOR_FULL_REFRESH_COUNT.load(Ordering::Relaxed),
+        scoped: MONITOR_SCOPED_REFRESH_COUNT.load(Ordering::Relaxed),
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn publish_poll_interval(interval: Duration) {
+    MONITOR_POLL_INTERVAL_MS.store(interval.as_millis() as u64, Ordering::Relaxed);
+}
+
+fn note_refresh_time(periodic: bool) {
+    let slot = if periodic {
+        &MONITOR_LAST_PERIODIC_REFRESH_MS
+    } else {
+        &MONITOR_LAST_EVENT_REFRESH_MS
+    };
+    slot.store(now_ms(), Ordering::Relaxed);
+}
+
+pub(super) fn status() -> MonitorStatus {
+    let (thread_running, parked) = {
+        let state = GLOBAL.state.lock_or_poison();
+        (
+            state.monitor_running,
+            state.monitor_running && state.init.mode == HookMode::Manual,
+        )
+    };
+    MonitorStatus {
+        thread_running,
+        parked,
+        strategy: strategy(),
+        periodic_enabled: is_periodic_enabled(),
+        periodic_escalated: MONITOR_PERIODIC_ESCALATED.load(Ordering::Acquire),
+        loader_success_streak: MONITOR_LOADER_SUCCESS_STREAK.load(Ordering::Acquire),
+        poll_interval: Duration::from_millis(MONITOR_POLL_INTERVAL_MS.load(Ordering::Relaxed)),
+        last_event_refresh_ms: MONITOR_LAST_EVENT_REFRESH_MS.load(Ordering::Relaxed),
+        last_periodic_refresh_ms: MONITOR_LAST_PERIODIC_REFRESH_MS.load(Ordering::Relaxed),
+    }
+}
+
+// 已安装 legacy hook 时（含 loader 回退后的补装）按 legacy 上报
+pub(super) fn strategy() -> MonitorStrategy {
+    if !AUTO_MONITOR_INSTALLED.load(Ordering::Acquire) {
+        MonitorStrategy::Inactive
+    } else if MONITOR_LEGACY_HOOK_INSTALLED.load(Ordering::Acquire) {
+        MonitorStrategy::LegacyHooks
+    } else {
+        MonitorStrategy::LoaderHooks
+    }
+}
+
+pub(super) fn is_periodic_enabled() -> bool {
+    AUTO_MONITOR_INSTALLED.load(Ordering::Acquire)
+        && MONITOR_PERIODIC_ENABLED.load(Ordering::Acquire)
+}
+
+// 供 is_hook_artifact 识别的 dlopen/dlclose 代理入口
+pub(super) fn proxy_addrs() -> Vec<usize> {
+    vec![
+        proxies::monitor_dlopen as *mut c_void as usize,
+        proxies::monitor_android_dlopen_ext as *mut c_void as usize,
+        proxies::monitor_dlclose as *mut c_void as usize,
+        proxies::monitor_loader_dlopen as *mut c_void as usize,
+        proxies::monitor_loader_android_dlopen_ext as *mut c_void as usize,
+        proxies::monitor_loader_dlclose as *mut c_void as usize,
+    ]
+}
+
 pub(super) fn install_auto_loader_monitor_hooks() {
     if AUTO_MONITOR_INSTALLED.swap(true, Ordering::SeqCst) {
         return;
@@ -80,6 +227,29 @@ pub(super) fn install_auto_loader_monitor_hooks() {
     install_legacy_hooks_for_all_modules();
 }
 
+// 切回 Manual 时卸载内部 monitor 任务，按普通任务 unhook，并复位安装状态以便再次安装
+pub(super) fn uninstall_auto_loader_monitor_hooks() {
+    reset_auto_monitor_installed();
+    let internal = proxy_addrs();
+    let stubs: Vec<HookStub> = {
+        let state = GLOBAL.state.lock_or_poison();
+        state
+            .task_order
+            .iter()
+            .copied()
+            .filter(|stub| {
+                state
+                    .tasks
+                    .get(stub)
+                    .is_some_and(|task| internal.contains(&task.new_func))
+            })
+            .collect()
+    };
+    for stub in stubs {
+        let _ = super::unhook(stub);
+    }
+}
+
 // loader 调用回退时触发：重置成功计数、请求安装 legacy hook、升级到周期性轮询
 pub(super) fn note_loader_call_fallback(reason: &str) {
     if !AUTO_MONITOR_INSTALLED.load(Ordering::Acquire) {
@@ -123,7 +293,7 @@ pub(super) fn note_loader_call_success() {
     let streak = MONITOR_LOADER_SUCCESS_STREAK
         .fetch_add(1, Ordering::AcqRel)
         .saturating_add(1);
-    if streak < LOADER_STABLE_SUCCESS_THRESHOLD {
+    if streak < LOADER_STABLE_THRESHOLD.load(Ordering::Relaxed) {
         return;
     }
 
@@ -136,7 +306,9 @@ pub(super) fn note_loader_call_success() {
 }
 
 pub(super) fn maybe_install_legacy_hooks_on_demand() {
-    if !MONITOR_LEGACY_HOOK_REQUESTED.swap(false, Ordering::SeqCst) {
+    if !AUTO_MONITOR_INSTALLED.load(Ordering::Acquire)
+        || !MONITOR_LEGACY_HOOK_REQUESTED.swap(false, Ordering::SeqCst)
+    {
         return;
     }
     install_legacy_hooks_for_all_modules();
@@ -200,10 +372,17 @@ fn install_legacy_hooks_for_all_modules() {
             task_type: TaskType::All,
             caller_path_name: None,
             caller_allow_filter: None,
+            caller_allow_list: Vec::new(),
             callee_path_name: None,
             sym_name: symbol.to_string(),
             new_func: proxy as usize,
             hooked: None,
+            event_callback: None,
+            callee_selection: CalleeSelection::All,
+            bind_policy: SingleBindPolicy::FirstMatch,
+            paused: false,
+            priority: INTERNAL_PROXY_PRIORITY,
+            threads: Vec::new(),
         };
         let _ = super::add_task(task);
     }
@@ -228,16 +407,23 @@ fn install_loader_hooks_for_libdl() {
             task_type: TaskType::Single,
             caller_path_name: Some(LIBDL_BASENAME.to_string()),
             caller_allow_filter: None,
+            caller_allow_list: Vec::new(),
             callee_path_name: None,
             sym_name: symbol.to_string(),
             new_func: proxy as usize,
             hooked: None,
+            event_callback: None,
+            callee_selection: CalleeSelection::All,
+            bind_policy: SingleBindPolicy::FirstMatch,
+            paused: false,
+            priority: INTERNAL_PROXY_PRIORITY,
+            threads: Vec::new(),
         };
         let _ = super::add_task(task);
     }
 }
 
-fn should_use_loader_hooks() -> bool {
+pub(super) fn should_use_loader_hooks() -> bool {
     let api_level = android_api_level();
     if api_level < ANDROID_API_LEVEL_LOADER {
         return false;
@@ -254,7 +440,14 @@ fn should_use_loader_hooks() -> bool {
         .all(|name| !unsafe { libc::dlsym(RTLD_NEXT_FALLBACK, name.as_ptr()) }.is_null())
 }
 
-fn android_api_level() -> i32 {
+pub(super) fn is_android_n() -> bool {
+    matches!(
+        android_api_level(),
+        ANDROID_API_LEVEL_N | ANDROID_API_LEVEL_N_MR1
+    )
+}
+
+pub(super) fn android_api_level() -> i32 {
     let prop_name = c"ro.build.version.sdk";
     let mut prop_value = [0 as c_char; SYSTEM_PROP_VALUE_MAX];
     let len = unsafe { __system_property_get(prop_name.as_ptr(), prop_value.as_mut_ptr()) };
@@ -1,12 +1,17 @@
 // monitor 线程轮询循环，结合事件驱动与周期性轮询两种刷新策略
+use crate::android::signal_guard;
+use crate::api::HookMode;
 use std::sync::atomic::Ordering;
 use std::ffi::c_void;
-use std::time::Duration;
+use std::sync::MutexGuard;
+use std::time::{Duration, Instant};
 
 use super::{
-    MONITOR_FALLBACK_BURST_ROUNDS, MONITOR_FALLBACK_REFRESH_INTERVAL_MAX,
-    MONITOR_FALLBACK_REFRESH_INTERVAL_MIN, MONITOR_PERIODIC_ENABLED,
+    MONITOR_EVENT_REFRESH_COUNT, MONITOR_FALLBACK_BURST_ROUNDS, MONITOR_FULL_REFRESH_COUNT,
+    MONITOR_PERIODIC_ENABLED, MONITOR_SCOPED_REFRESH_COUNT, note_refresh_time, poll_interval_max,
+    poll_interval_min, publish_poll_interval,
 };
+use crate::runtime::state::CoreState;
 use crate::runtime::state::{MutexPoisonRecover, RwLockPoisonRecover};
 
 // 周期性轮询状态，管理退避间隔和 burst 轮次
@@ -34,19 +39,27 @@ enum PeriodicRefreshKind {
 
 impl FallbackPollState {
     fn new() -> Self {
+        let interval = poll_interval_min();
+        publish_poll_interval(interval);
         Self {
-            interval: MONITOR_FALLBACK_REFRESH_INTERVAL_MIN,
+            interval,
             burst_rounds: MONITOR_FALLBACK_BURST_ROUNDS,
             last_module_epoch: super::refresh::module_epoch(),
         }
     }
 
+    // 间隔变化同步到共享原子量，供 get_monitor_status 读取
+    fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+        publish_poll_interval(interval);
+    }
+
     fn timeout(&self) -> Duration {
         self.interval
     }
 
     fn on_event_refresh(&mut self) {
-        self.interval = MONITOR_FALLBACK_REFRESH_INTERVAL_MIN;
+        self.set_interval(poll_interval_min());
         self.burst_rounds = MONITOR_FALLBACK_BURST_ROUNDS;
         self.last_module_epoch = super::refresh::module_epoch();
     }
@@ -66,13 +79,13 @@ impl FallbackPollState {
             .interval
             .as_millis()
             .saturating_mul(2)
-            .min(MONITOR_FALLBACK_REFRESH_INTERVAL_MAX.as_millis());
+            .min(poll_interval_max().as_millis());
         let doubled_ms = doubled_ms as u64;
-        self.interval = Duration::from_millis(doubled_ms);
+        self.set_interval(Duration::from_millis(doubled_ms));
     }
 
     fn reset_for_event_mode(&mut self) {
-        self.interval = MONITOR_FALLBACK_REFRESH_INTERVAL_MIN;
+        self.set_interval(poll_interval_min());
         self.burst_rounds = MONITOR_FALLBACK_BURST_ROUNDS;
     }
 
@@ -101,15 +114,55 @@ fn classify_epoch_delta(last: Option<(u64, u64)>, current: (u64, u64)) -> EpochD
     EpochDelta::Changed
 }
 
+fn has_refresh_request(state: &CoreState) -> bool {
+    state.refresh_requested || !state.pending_unloaded_modules.is_empty()
+}
+
+// Manual 模式下挂起：不消费刷新请求也不周期轮询，set_mode 切回 Automatic 时唤醒
+fn is_parked(state: &CoreState) -> bool {
+    state.init.mode == HookMode::Manual
+}
+
+// 被 dlopen 唤醒后再等待一个合并窗口，让紧接着加载的模块并入同一轮刷新
+// 窗口从唤醒时起算，安静期后的首个 dlopen 最多延后一个窗口
+fn coalesce_dlopen_events(
+    mut state: MutexGuard<'static, CoreState>,
+) -> MutexGuard<'static, CoreState> {
+    let window = super::debounce_window();
+    if window.is_zero() {
+        return state;
+    }
+    let deadline = Instant::now() + window;
+    while state.monitor_running {
+        let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+            break;
+        };
+        if remaining.is_zero() {
+            break;
+        }
+        state = super::GLOBAL
+            .condvar
+            .wait_timeout(state, remaining)
+            .unwrap_or_else(|e| e.into_inner())
+            .0;
+    }
+    state
+}
+
 pub(super) fn monitor_loop() {
     let mut fallback_poll = FallbackPollState::new();
 
     loop {
         super::maybe_install_legacy_hooks_on_demand();
+        signal_guard::reassert_front_of_chain(false);
 
         let mut state = super::GLOBAL.state.lock_or_poison();
         let mut periodic_refresh = false;
-        while state.monitor_running && !state.refresh_requested {
+        while state.monitor_running && (is_parked(&state) || !has_refresh_request(&state)) {
+            if is_parked(&state) {
+                state = super::GLOBAL.condvar.wait(state).unwrap_or_else(|e| e.into_inner());
+                continue;
+            }
             if MONITOR_PERIODIC_ENABLED.load(Ordering::Acquire) {
                 let timeout = fallback_poll.timeout();
                 let (next_state, wait_result) = super::GLOBAL
@@ -117,7 +170,7 @@ pub(super) fn monitor_loop() {
                     .wait_timeout(state, timeout)
                     .unwrap_or_else(|e| e.into_inner());
                 state = next_state;
-                if state.refresh_requested {
+                if has_refresh_request(&state) {
                     break;
                 }
                 if wait_result.timed_out() {
@@ -129,11 +182,17 @@ pub(super) fn monitor_loop() {
                 state = super::GLOBAL.condvar.wait(state).unwrap_or_else(|e| e.into_inner());
             }
         }
+        if !periodic_refresh && !state.pending_module_handles.is_empty() {
+            state = coalesce_dlopen_events(state);
+        }
         if !state.monitor_running {
             break;
         }
         let known_module_count_before = state.known_modules.len();
-        let event_refresh = state.refresh_requested;
+        // 只有 dlclose 卸载请求时不枚举模块，仅清理对应模块
+        let scan_requested = state.refresh_requested;
+        let unloaded_modules = std::mem::take(&mut state.pending_unloaded_modules);
+        let event_refresh = scan_requested || !unloaded_modules.is_empty();
         let pending_handles = std::mem::take(&mut state.pending_module_handles);
         state.pending_module_handle_set.clear();
         state.refresh_requested = false;
@@ -149,14 +208,19 @@ pub(super) fn monitor_loop() {
             }
         }
 
+        let mut scan = scan_requested || periodic_refresh;
         let mut periodic_epoch_changed = false;
         let mut periodic_refresh_kind = PeriodicRefreshKind::Full;
         if periodic_refresh {
             match fallback_poll.poll_epoch_delta() {
-                EpochDelta::Unchanged => {
+                EpochDelta::Unchanged if unloaded_modules.is_empty() => {
+                    // 跳过 refresh 时仍回收到期的 retired hub
+                    super::hub::collect_retired(false);
+                    verify_slots_idle();
                     fallback_poll.on_periodic_refresh(false);
                     continue;
                 }
+                EpochDelta::Unchanged => scan = false,
                 EpochDelta::AddedOnly => {
                     periodic_epoch_changed = true;
                     periodic_refresh_kind = PeriodicRefreshKind::NewModulesOnly;
@@ -178,15 +242,33 @@ pub(super) fn monitor_loop() {
             }
         }
 
-        let _dlclose_guard = super::GLOBAL.dlclose_lock.read_or_poison();
-        let _refresh_guard = super::GLOBAL.refresh_mutex.lock_or_poison();
+        if !periodic_refresh {
+            MONITOR_EVENT_REFRESH_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+        note_refresh_time(periodic_refresh);
+        let dlclose_guard = super::GLOBAL.dlclose_lock.read_or_poison();
+        let refresh_guard = super::GLOBAL.refresh_mutex.lock_or_poison();
         let mut state = super::GLOBAL.state.lock_or_poison();
-        let (status, events) = if periodic_refresh {
+        let mut merged_events = super::super::merge_pending_tasks(&mut state).into_events();
+        if !unloaded_modules.is_empty() {
+            MONITOR_SCOPED_REFRESH_COUNT.fetch_add(1, Ordering::Relaxed);
+            let (status, events) =
+                super::refresh::refresh_unloaded_modules(&mut state, &unloaded_modules);
+            if status != super::Errno::Ok {
+                super::log::warn(format_args!("unloaded module refresh status {:?}", status));
+            }
+            merged_events.extend(events);
+        }
+        let (status, events) = if !scan {
+            (super::Errno::Ok, Vec::new())
+        } else if periodic_refresh {
+            MONITOR_FULL_REFRESH_COUNT.fetch_add(1, Ordering::Relaxed);
             match periodic_refresh_kind {
                 PeriodicRefreshKind::NewModulesOnly => super::refresh::refresh_new_modules(&mut state),
                 PeriodicRefreshKind::Full => super::refresh::refresh_all(&mut state),
             }
         } else {
+            MONITOR_FULL_REFRESH_COUNT.fetch_add(1, Ordering::Relaxed);
             super::refresh::refresh_new_modules(&mut state)
         };
         if status != super::Errno::Ok {
@@ -194,7 +276,11 @@ pub(super) fn monitor_loop() {
         }
         let known_module_count_after = state.known_modules.len();
         drop(state);
-        super::super::invoke_callbacks(events);
+        drop(refresh_guard);
+        drop(dlclose_guard);
+        merged_events.extend(events);
+        super::super::invoke_callbacks(merged_events);
+        super::super::apply_pending_tasks();
 
         if !MONITOR_PERIODIC_ENABLED.load(Ordering::Acquire) {
             continue;
@@ -211,6 +297,24 @@ pub(super) fn monitor_loop() {
     }
 }
 
+// 模块未变化而跳过刷新的周期轮次，开启 slot 巡检时仍回读已挂载的 slot
+fn verify_slots_idle() {
+    if !super::refresh::is_slot_guard_enabled() {
+        return;
+    }
+    let events = {
+        let _dlclose_guard = super::GLOBAL.dlclose_lock.read_or_poison();
+        let _refresh_guard = super::GLOBAL.refresh_mutex.lock_or_poison();
+        let mut state = super::GLOBAL.state.lock_or_poison();
+        let (status, events) = super::refresh::verify_slots(&mut state);
+        if status != super::Errno::Ok {
+            super::log::warn(format_args!("slot guard status {:?}", status));
+        }
+        events
+    };
+    super::super::invoke_callbacks(events);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -222,12 +326,12 @@ mod tests {
             state.on_periodic_refresh(false);
         }
 
-        assert_eq!(state.timeout(), MONITOR_FALLBACK_REFRESH_INTERVAL_MIN);
+        assert_eq!(state.timeout(), poll_interval_min());
         state.on_periodic_refresh(false);
 
         assert_eq!(
             state.timeout(),
-            Duration::from_millis(MONITOR_FALLBACK_REFRESH_INTERVAL_MIN.as_millis() as u64 * 2),
+            Duration::from_millis(poll_interval_min().as_millis() as u64 * 2),
         );
     }
 
@@ -237,10 +341,10 @@ mod tests {
         for _ in 0..8 {
             state.on_periodic_refresh(false);
         }
-        assert!(state.timeout() > MONITOR_FALLBACK_REFRESH_INTERVAL_MIN);
+        assert!(state.timeout() > poll_interval_min());
 
         state.on_event_refresh();
-        assert_eq!(state.timeout(), MONITOR_FALLBACK_REFRESH_INTERVAL_MIN);
+        assert_eq!(state.timeout(), poll_interval_min());
     }
 
     #[test]
@@ -249,10 +353,10 @@ mod tests {
         for _ in 0..8 {
             state.on_periodic_refresh(false);
         }
-        assert!(state.timeout() > MONITOR_FALLBACK_REFRESH_INTERVAL_MIN);
+        assert!(state.timeout() > poll_interval_min());
 
         state.on_periodic_refresh(true);
-        assert_eq!(state.timeout(), MONITOR_FALLBACK_REFRESH_INTERVAL_MIN);
+        assert_eq!(state.timeout(), poll_interval_min());
     }
 
     #[test]
@@ -1,9 +1,13 @@
 // dlopen/dlclose 的 monitor proxy 函数，拦截动态库加载卸载并触发 hook 刷新
 use std::ffi::{c_char, c_void};
+use std::panic::{AssertUnwindSafe, catch_unwind};
 
 use super::super::super::hub;
-use super::super::super::state::GLOBAL;
+use super::super::super::refresh;
+use super::super::super::state::{GLOBAL, ModuleInfo};
 use crate::runtime::state::RwLockPoisonRecover;
+use crate::runtime::thread_state;
+use super::super::dlopen_callbacks::DlopenFilterOutcome;
 use super::super::monitor_calls::{
     call_android_dlopen_ext_fn, call_dlclose_fn, call_dlopen_fn, call_loader_android_dlopen_ext_fn,
     call_loader_dlclose_fn, call_loader_dlopen_fn, call_real_android_dlopen_ext, call_real_dlclose,
@@ -15,26 +19,22 @@ pub(super) unsafe extern "C" fn monitor_dlopen(
     filename: *const c_char,
     flags: libc::c_int,
 ) -> *mut c_void {
-    super::super::invoke_dlopen_callbacks_pre(filename);
-    let result = if should_use_android_n_linker_fallback() {
-        let caller_addr = hub::get_return_address() as *const c_void;
-        unsafe { call_real_dlopen_with_caller(filename, flags, std::ptr::null(), caller_addr) }
-    } else {
-        let self_ptr = monitor_dlopen as *mut c_void;
-        super::super::with_prev_func(self_ptr, |prev| {
-            if prev.is_null() {
-                unsafe { call_real_dlopen(filename, flags) }
-            } else {
-                unsafe { call_dlopen_fn(prev as usize, filename, flags) }
-            }
-        })
-        .unwrap_or_else(|| unsafe { call_real_dlopen(filename, flags) })
-    };
-    super::super::invoke_dlopen_callbacks_post(filename, if result.is_null() { -1 } else { 0 });
-    if !result.is_null() {
-        super::super::request_refresh_async_with_handle(result);
-    }
-    result
+    forward_dlopen(filename, flags, |filename, flags| {
+        if should_use_android_n_linker_fallback() {
+            let caller_addr = hub::get_return_address() as *const c_void;
+            unsafe { call_real_dlopen_with_caller(filename, flags, std::ptr::null(), caller_addr) }
+        } else {
+            let self_ptr = monitor_dlopen as *mut c_void;
+            super::super::with_prev_func(self_ptr, |prev| {
+                if prev.is_null() {
+                    unsafe { call_real_dlopen(filename, flags) }
+                } else {
+                    unsafe { call_dlopen_fn(prev as usize, filename, flags) }
+                }
+            })
+            .unwrap_or_else(|| unsafe { call_real_dlopen(filename, flags) })
+        }
+    })
 }
 
 pub(super) unsafe extern "C" fn monitor_android_dlopen_ext(
@@ -42,32 +42,32 @@ pub(super) unsafe extern "C" fn monitor_android_dlopen_ext(
     flags: libc::c_int,
     extinfo: *const c_void,
 ) -> *mut c_void {
-    super::super::invoke_dlopen_callbacks_pre(filename);
-    let result = if should_use_android_n_linker_fallback() {
-        let caller_addr = hub::get_return_address() as *const c_void;
-        unsafe { call_real_dlopen_with_caller(filename, flags, extinfo, caller_addr) }
-    } else {
-        let self_ptr = monitor_android_dlopen_ext as *mut c_void;
-        super::super::with_prev_func(self_ptr, |prev| {
-            if prev.is_null() {
-                unsafe { call_real_android_dlopen_ext(filename, flags, extinfo) }
-            } else {
-                unsafe { call_android_dlopen_ext_fn(prev as usize, filename, flags, extinfo) }
-            }
-        })
-        .unwrap_or_else(|| unsafe { call_real_android_dlopen_ext(filename, flags, extinfo) })
-    };
-    super::super::invoke_dlopen_callbacks_post(filename, if result.is_null() { -1 } else { 0 });
-    if !result.is_null() {
-        super::super::request_refresh_async_with_handle(result);
-    }
-    result
+    forward_dlopen(filename, flags, |filename, flags| {
+        if should_use_android_n_linker_fallback() {
+            let caller_addr = hub::get_return_address() as *const c_void;
+            unsafe { call_real_dlopen_with_caller(filename, flags, extinfo, caller_addr) }
+        } else {
+            let self_ptr = monitor_android_dlopen_ext as *mut c_void;
+            super::super::with_prev_func(self_ptr, |prev| {
+                if prev.is_null() {
+                    unsafe { call_real_android_dlopen_ext(filename, flags, extinfo) }
+                } else {
+                    unsafe { call_android_dlopen_ext_fn(prev as usize, filename, flags, extinfo) }
+                }
+            })
+            .unwrap_or_else(|| unsafe { call_real_android_dlopen_ext(filename, flags, extinfo) })
+        }
+    })
 }
 
 // dlclose proxy 需要持有 dlclose_lock 写锁，防止 refresh 期间模块被卸载
+// pre/post 回调在写锁之外投递，pre 时模块仍可按 handle 解析
 pub(super) unsafe extern "C" fn monitor_dlclose(handle: *mut c_void) -> libc::c_int {
     let self_ptr = monitor_dlclose as *mut c_void;
+    let unloading = refresh::module_identity_from_handle(handle);
+    let mut scope = super::super::begin_dlclose_callbacks(handle, unloading.as_ref());
     let dlclose_guard = GLOBAL.dlclose_lock.write_or_poison();
+    let epoch_before = refresh::module_epoch();
     let result = super::super::with_prev_func(self_ptr, |prev| {
         if prev.is_null() {
             unsafe { call_real_dlclose(handle) }
@@ -76,10 +76,13 @@ pub(super) unsafe extern "C" fn monitor_dlclose(handle: *mut c_void) -> libc::c_
         }
     })
     .unwrap_or_else(|| unsafe { call_real_dlclose(handle) });
+    let epoch_after = refresh::module_epoch();
     drop(dlclose_guard);
+    scope.set_result(result);
+    drop(scope);
 
     if result == 0 {
-        super::super::request_refresh_async_full();
+        on_module_closed(unloading, unloaded_count(epoch_before, epoch_after));
     }
     result
 }
@@ -89,21 +92,17 @@ pub(super) unsafe extern "C" fn monitor_loader_dlopen(
     flags: libc::c_int,
     caller_addr: *const c_void,
 ) -> *mut c_void {
-    super::super::invoke_dlopen_callbacks_pre(filename);
-    let self_ptr = monitor_loader_dlopen as *mut c_void;
-    let result = super::super::with_prev_func(self_ptr, |prev| {
-        if prev.is_null() {
-            unsafe { call_real_loader_dlopen(filename, flags, caller_addr) }
-        } else {
-            unsafe { call_loader_dlopen_fn(prev as usize, filename, flags, caller_addr) }
-        }
+    forward_dlopen(filename, flags, |filename, flags| {
+        let self_ptr = monitor_loader_dlopen as *mut c_void;
+        super::super::with_prev_func(self_ptr, |prev| {
+            if prev.is_null() {
+                unsafe { call_real_loader_dlopen(filename, flags, caller_addr) }
+            } else {
+                unsafe { call_loader_dlopen_fn(prev as usize, filename, flags, caller_addr) }
+            }
+        })
+        .unwrap_or_else(|| unsafe { call_real_loader_dlopen(filename, flags, caller_addr) })
     })
-    .unwrap_or_else(|| unsafe { call_real_loader_dlopen(filename, flags, caller_addr) });
-    super::super::invoke_dlopen_callbacks_post(filename, if result.is_null() { -1 } else { 0 });
-    if !result.is_null() {
-        super::super::request_refresh_async_with_handle(result);
-    }
-    result
 }
 
 pub(super) unsafe extern "C" fn monitor_loader_android_dlopen_ext(
@@ -112,36 +111,37 @@ pub(super) unsafe extern "C" fn monitor_loader_android_dlopen_ext(
     extinfo: *const c_void,
     caller_addr: *const c_void,
 ) -> *mut c_void {
-    super::super::invoke_dlopen_callbacks_pre(filename);
-    let self_ptr = monitor_loader_android_dlopen_ext as *mut c_void;
-    let result = super::super::with_prev_func(self_ptr, |prev| {
-        if prev.is_null() {
-            unsafe { call_real_loader_android_dlopen_ext(filename, flags, extinfo, caller_addr) }
-        } else {
-            unsafe {
-                call_loader_android_dlopen_ext_fn(
-                    prev as usize,
-                    filename,
-                    flags,
-                    extinfo,
-                    caller_addr,
-                )
+    forward_dlopen(filename, flags, |filename, flags| {
+        let self_ptr = monitor_loader_android_dlopen_ext as *mut c_void;
+        super::super::with_prev_func(self_ptr, |prev| {
+            if prev.is_null() {
+                unsafe {
+                    call_real_loader_android_dlopen_ext(filename, flags, extinfo, caller_addr)
+                }
+            } else {
+                unsafe {
+                    call_loader_android_dlopen_ext_fn(
+                        prev as usize,
+                        filename,
+                        flags,
+                        extinfo,
+                        caller_addr,
+                    )
+                }
             }
-        }
+        })
+        .unwrap_or_else(|| unsafe {
+            call_real_loader_android_dlopen_ext(filename, flags, extinfo, caller_addr)
+        })
     })
-    .unwrap_or_else(|| unsafe {
-        call_real_loader_android_dlopen_ext(filename, flags, extinfo, caller_addr)
-    });
-    super::super::invoke_dlopen_callbacks_post(filename, if result.is_null() { -1 } else { 0 });
-    if !result.is_null() {
-        super::super::request_refresh_async_with_handle(result);
-    }
-    result
 }
 
 pub(super) unsafe extern "C" fn monitor_loader_dlclose(handle: *mut c_void) -> libc::c_int {
     let self_ptr = monitor_loader_dlclose as *mut c_void;
+    let unloading = refresh::module_identity_from_handle(handle);
+    let mut scope = super::super::begin_dlclose_callbacks(handle, unloading.as_ref());
     let dlclose_guard = GLOBAL.dlclose_lock.write_or_poison();
+    let epoch_before = refresh::module_epoch();
     let result = super::super::with_prev_func(self_ptr, |prev| {
         if prev.is_null() {
             unsafe { call_real_loader_dlclose(handle) }
@@ -150,10 +150,76 @@ pub(super) unsafe extern "C" fn monitor_loader_dlclose(handle: *mut c_void) -> l
         }
     })
     .unwrap_or_else(|| unsafe { call_real_loader_dlclose(handle) });
+    let epoch_after = refresh::module_epoch();
     drop(dlclose_guard);
+    scope.set_result(result);
+    drop(scope);
 
     if result == 0 {
-        super::super::request_refresh_async_full();
+        on_module_closed(unloading, unloaded_count(epoch_before, epoch_after));
+    }
+    result
+}
+
+// 真实 dlclose 前后 dl_iterate_phdr 卸载计数之差，取不到计数时为 None
+fn unloaded_count(before: Option<(u64, u64)>, after: Option<(u64, u64)>) -> Option<u64> {
+    let ((_, subs_before), (_, subs_after)) = (before?, after?);
+    subs_after.checked_sub(subs_before)
+}
+
+// dlclose 成功后按关闭前解析出的模块做局部清理
+// 身份未能解析，或同一次 dlclose 连带卸载了依赖（卸载计数超过 1）时退回全量刷新
+// 计数为 0 可能是引用计数未归零，也可能是 linker 不维护 dlpi_subs，仍按局部清理处理
+fn on_module_closed(unloading: Option<ModuleInfo>, unloaded: Option<u64>) {
+    thread_state::invalidate_caller_module_cache();
+    match unloading {
+        Some(module) if unloaded.is_some_and(|count| count <= 1) => {
+            super::super::request_refresh_async_unloaded(module)
+        }
+        _ => super::super::request_refresh_async_full(),
+    }
+}
+
+// 包裹一次 dlopen 转发：pre/post 回调由作用域对象成对投递，转发 panic 时先投递 post(-1) 再中止进程
+// filename 为空时只返回主程序 handle，不投递回调也不请求刷新
+// RTLD_NOLOAD 不会加载新模块，post 以 DLOPEN_RESULT_NOLOAD 区分，同样不请求刷新
+// pre 之后由过滤器决定实际加载的路径；拒绝时只以 RTLD_NOLOAD 转发带原因的名称，
+// 加载器查找失败并设置 dlerror，返回 NULL 并投递 post(-1)
+fn forward_dlopen<F>(filename: *const c_char, flags: libc::c_int, forward: F) -> *mut c_void
+where
+    F: FnOnce(*const c_char, libc::c_int) -> *mut c_void,
+{
+    if filename.is_null() {
+        return forward(filename, flags);
+    }
+    let noload = flags & libc::RTLD_NOLOAD != 0;
+    let mut scope = super::super::begin_dlopen_callbacks(filename);
+    let (target, denied) = match super::super::apply_dlopen_filters(filename) {
+        DlopenFilterOutcome::Load => (None, false),
+        DlopenFilterOutcome::Redirect(path) => (Some(path), false),
+        DlopenFilterOutcome::Deny(name) => (Some(name), true),
+    };
+    let target_ptr = target.as_ref().map_or(filename, |path| path.as_ptr());
+    let target_flags = if denied {
+        flags | libc::RTLD_NOLOAD
+    } else {
+        flags
+    };
+    let Ok(result) = catch_unwind(AssertUnwindSafe(|| forward(target_ptr, target_flags))) else {
+        drop(scope);
+        std::process::abort();
+    };
+    if denied {
+        // 拒绝名称不应命中已加载模块，命中时归还引用并照常按拒绝处理
+        if !result.is_null() {
+            unsafe { libc::dlclose(result) };
+        }
+        return std::ptr::null_mut();
+    }
+    scope.set_handle(result, noload);
+    drop(scope);
+    if !result.is_null() && !noload {
+        super::super::request_refresh_async_with_handle(result);
     }
     result
 }
@@ -161,6 +227,20 @@ pub(super) unsafe extern "C" fn monitor_loader_dlclose(handle: *mut c_void) -> l
 // Android N (API 24-25) 的 linker 不支持 PLT hook 拦截 dlopen
 // 需要直接调用 linker 内部函数并传递 caller_addr
 fn should_use_android_n_linker_fallback() -> bool {
-    let api_level = super::android_api_level();
-    api_level == super::ANDROID_API_LEVEL_N || api_level == super::ANDROID_API_LEVEL_N_MR1
+    super::is_android_n()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unloaded_count;
+
+    #[test]
+    fn unloaded_count_follows_subs_delta() {
+        assert_eq!(unloaded_count(Some((5, 2)), Some((5, 3))), Some(1));
+        // 连带卸载的依赖同样计入
+        assert_eq!(unloaded_count(Some((5, 2)), Some((5, 5))), Some(3));
+        assert_eq!(unloaded_count(Some((5, 2)), Some((5, 2))), Some(0));
+        assert_eq!(unloaded_count(None, Some((5, 2))), None);
+        assert_eq!(unloaded_count(Some((5, 3)), Some((5, 2))), None);
+    }
 }
@@ -1,4 +1,8 @@
-use super::parse_periodic_env_value;
+use crate::api::ArtifactKind;
+use std::ffi::c_void;
+
+use super::super::super::artifact;
+use super::{parse_periodic_env_value, proxies, proxy_addrs};
 
 #[test]
 fn parse_periodic_env_true_values() {
@@ -21,3 +25,24 @@ fn parse_periodic_env_invalid_value() {
     assert_eq!(parse_periodic_env_value("maybe"), None);
     assert_eq!(parse_periodic_env_value(""), None);
 }
+
+#[test]
+fn monitor_proxies_are_hook_artifacts() {
+    let monitor_dlopen = proxies::monitor_dlopen as *mut c_void as usize;
+    assert_eq!(
+        artifact::classify_static(proxy_addrs(), monitor_dlopen),
+        Some(ArtifactKind::InternalProxy)
+    );
+    // 栈上的返回地址位于 proxy 函数体内部
+    assert_eq!(
+        artifact::classify_static(proxy_addrs(), monitor_dlopen + 4),
+        Some(ArtifactKind::InternalProxy)
+    );
+    let own_text = parse_periodic_env_value as *const () as usize;
+    assert_eq!(
+        artifact::classify_static(proxy_addrs(), own_text),
+        Some(ArtifactKind::OwnModuleCode)
+    );
+    let libc_puts = libc::puts as *const () as usize;
+    assert_eq!(artifact::classify_static(proxy_addrs(), libc_puts), None);
+}
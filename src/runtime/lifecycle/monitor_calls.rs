@@ -1,8 +1,13 @@
 // dlopen/dlclose 系列函数的真实地址解析与调用封装
 // 支持 loader 符号、linker 内部符号和标准 libc 符号三级回退
+use crate::api::LinkerFallbackStats;
 use crate::log;
+use crate::runtime::state::MutexPoisonRecover;
 use once_cell::sync::OnceCell;
 use std::ffi::{CStr, c_char, c_void};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU64, Ordering};
+use std::time::Duration;
 
 use super::monitor;
 
@@ -18,6 +23,17 @@ static REAL_LINKER_DO_DLOPEN: OnceCell<usize> = OnceCell::new();
 static REAL_LINKER_G_DL_MUTEX: OnceCell<usize> = OnceCell::new();
 static REAL_LINKER_GET_ERROR_BUFFER: OnceCell<usize> = OnceCell::new();
 const RTLD_NEXT_FALLBACK: *mut c_void = (-1isize) as *mut c_void;
+// do_dlopen 回退开关：未设置时按 API level 决定，仅 24/25 需要
+const LINKER_INTERNAL_AUTO: u8 = 0;
+const LINKER_INTERNAL_ON: u8 = 1;
+const LINKER_INTERNAL_OFF: u8 = 2;
+// 等待 g_dl_mutex 的上限，linker 卡死时放弃内部路径而不是挂住调用方
+const LINKER_MUTEX_TIMEOUT: Duration = Duration::from_secs(2);
+static LINKER_INTERNAL_FALLBACK: AtomicU8 = AtomicU8::new(LINKER_INTERNAL_AUTO);
+static LINKER_INTERNAL_USED: AtomicU64 = AtomicU64::new(0);
+static LINKER_INTERNAL_LOCK_TIMEOUTS: AtomicU64 = AtomicU64::new(0);
+static LINKER_INTERNAL_WARNED: AtomicBool = AtomicBool::new(false);
+static LINKER_INTERNAL_LAST_ERROR: Mutex<Option<String>> = Mutex::new(None);
 
 pub(super) unsafe fn call_dlopen_fn(
     addr: usize,
@@ -152,13 +168,93 @@ unsafe fn linker_error_message() -> Option<String> {
     }
 }
 
+pub(super) fn set_linker_internal_fallback(flag: bool) {
+    let value = if flag {
+        LINKER_INTERNAL_ON
+    } else {
+        LINKER_INTERNAL_OFF
+    };
+    LINKER_INTERNAL_FALLBACK.store(value, Ordering::Relaxed);
+}
+
+pub(super) fn linker_fallback_stats() -> LinkerFallbackStats {
+    LinkerFallbackStats {
+        used: LINKER_INTERNAL_USED.load(Ordering::Relaxed),
+        lock_timeouts: LINKER_INTERNAL_LOCK_TIMEOUTS.load(Ordering::Relaxed),
+        last_error: LINKER_INTERNAL_LAST_ERROR.lock_or_poison().clone(),
+    }
+}
+
+fn linker_internal_fallback_allowed() -> bool {
+    static ANDROID_N: OnceCell<bool> = OnceCell::new();
+    match LINKER_INTERNAL_FALLBACK.load(Ordering::Relaxed) {
+        LINKER_INTERNAL_ON => true,
+        LINKER_INTERNAL_OFF => false,
+        _ => *ANDROID_N.get_or_init(monitor::is_android_n),
+    }
+}
+
+// 限时获取 linker 的 g_dl_mutex，超时返回 false
+unsafe fn lock_linker_mutex(mutex: *mut libc::pthread_mutex_t) -> bool {
+    let mut deadline = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    if unsafe { libc::clock_gettime(libc::CLOCK_REALTIME, &mut deadline) } != 0 {
+        return unsafe { libc::pthread_mutex_trylock(mutex) } == 0;
+    }
+    deadline.tv_sec += LINKER_MUTEX_TIMEOUT.as_secs() as libc::time_t;
+    unsafe { libc::pthread_mutex_timedlock(mutex, &deadline) == 0 }
+}
+
+// 持 g_dl_mutex 调用 do_dlopen；锁符号缺失或等锁超时时不进入该路径
+unsafe fn call_linker_do_dlopen(
+    do_dlopen: usize,
+    filename: *const c_char,
+    flags: libc::c_int,
+    extinfo: *const c_void,
+    caller_addr: *const c_void,
+) -> *mut c_void {
+    let linker_mutex = *REAL_LINKER_G_DL_MUTEX
+        .get_or_init(|| unsafe { resolve_symbols(&[c"__dl__ZL10g_dl_mutex", c"__dl_g_dl_mutex"]) })
+        as *mut libc::pthread_mutex_t;
+    if linker_mutex.is_null() {
+        log::debug(format_args!(
+            "linker g_dl_mutex unavailable, skip do_dlopen fallback"
+        ));
+        return std::ptr::null_mut();
+    }
+    if !unsafe { lock_linker_mutex(linker_mutex) } {
+        LINKER_INTERNAL_LOCK_TIMEOUTS.fetch_add(1, Ordering::Relaxed);
+        log::warn(format_args!(
+            "linker g_dl_mutex busy for {:?}, skip do_dlopen fallback",
+            LINKER_MUTEX_TIMEOUT
+        ));
+        return std::ptr::null_mut();
+    }
+    LINKER_INTERNAL_USED.fetch_add(1, Ordering::Relaxed);
+    if !LINKER_INTERNAL_WARNED.swap(true, Ordering::Relaxed) {
+        log::warn(format_args!("linker internal do_dlopen fallback in use"));
+    }
+    let handle =
+        unsafe { call_linker_dlopen_ext_fn(do_dlopen, filename, flags, extinfo, caller_addr) };
+    let _ = unsafe { libc::pthread_mutex_unlock(linker_mutex) };
+    if handle.is_null()
+        && let Some(err) = unsafe { linker_error_message() }
+    {
+        log::debug(format_args!("linker do_dlopen fallback failed: {}", err));
+        *LINKER_INTERNAL_LAST_ERROR.lock_or_poison() = Some(err);
+    }
+    handle
+}
+
 pub(super) unsafe fn call_real_dlopen(filename: *const c_char, flags: libc::c_int) -> *mut c_void {
     let name = c"dlopen";
     let addr = *REAL_DLOPEN.get_or_init(|| unsafe { resolve_symbol(name) });
     call_dlopen_fn(addr, filename, flags)
 }
 
-// 带 caller_addr 的 dlopen 调用，依次尝试 dlopen_ext -> do_dlopen -> 标准 dlopen
+// 带 caller_addr 的 dlopen 调用，依次尝试 dlopen_ext -> do_dlopen（需启用）-> 标准 dlopen
 pub(super) unsafe fn call_real_dlopen_with_caller(
     filename: *const c_char,
     flags: libc::c_int,
@@ -175,26 +271,17 @@ pub(super) unsafe fn call_real_dlopen_with_caller(
         }
     }
 
-    let linker_do_dlopen = *REAL_LINKER_DO_DLOPEN
-        .get_or_init(|| unsafe { resolve_symbol(c"__dl__Z9do_dlopenPKciPK17android_dlextinfoPv") });
-    if linker_do_dlopen != 0 {
-        let linker_mutex = *REAL_LINKER_G_DL_MUTEX.get_or_init(|| unsafe {
-            resolve_symbols(&[c"__dl__ZL10g_dl_mutex", c"__dl_g_dl_mutex"])
+    if linker_internal_fallback_allowed() {
+        let linker_do_dlopen = *REAL_LINKER_DO_DLOPEN.get_or_init(|| unsafe {
+            resolve_symbol(c"__dl__Z9do_dlopenPKciPK17android_dlextinfoPv")
         });
-        if linker_mutex != 0 {
-            let _ = unsafe { libc::pthread_mutex_lock(linker_mutex as *mut libc::pthread_mutex_t) };
-        }
-        let handle = unsafe {
-            call_linker_dlopen_ext_fn(linker_do_dlopen, filename, flags, extinfo, caller_addr)
-        };
-        if linker_mutex != 0 {
-            let _ = unsafe { libc::pthread_mutex_unlock(linker_mutex as *mut libc::pthread_mutex_t) };
-        }
-        if !handle.is_null() {
-            return handle;
-        }
-        if let Some(err) = unsafe { linker_error_message() } {
-            log::debug(format_args!("linker do_dlopen fallback failed: {}", err));
+        if linker_do_dlopen != 0 {
+            let handle = unsafe {
+                call_linker_do_dlopen(linker_do_dlopen, filename, flags, extinfo, caller_addr)
+            };
+            if !handle.is_null() {
+                return handle;
+            }
         }
     }
 
@@ -29,9 +29,11 @@ pub(super) fn ensure_process_context(state: &mut CoreState) {
     state.task_slots.clear();
     state.slots.clear();
     state.single_task_targets.clear();
+    state.callee_candidates.clear();
     state.known_modules.clear();
     state.pending_module_handles.clear();
     state.pending_module_handle_set.clear();
+    state.pending_unloaded_modules.clear();
     state.refresh_requested = false;
     state.monitor_running = false;
     state.monitor_thread = None;
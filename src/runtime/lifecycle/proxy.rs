@@ -1,10 +1,15 @@
 // proxy 调用栈管理，通过栈指针检测防止 hook 重入
 use super::super::hub;
+use super::super::refresh;
+use crate::api::ModuleIdentity;
 use crate::runtime::thread_state;
 use crate::runtime::thread_state::ProxyFrame;
 use std::ffi::c_void;
 use std::ptr;
 
+// 模块按 4K 对齐映射，同一 4K 页内的返回地址必然属于同一模块
+const CALLER_PAGE_MASK: usize = !0xfff;
+
 type ProxyStack = thread_state::FixedStack<ProxyFrame, { thread_state::PROXY_STACK_CAP }>;
 
 #[inline]
@@ -49,10 +54,7 @@ fn current_stack_pointer() -> usize {
 // 清理已返回的栈帧，栈向低地址增长，sp <= current_sp 的帧已失效
 #[inline]
 fn prune_stale_proxy_frames(stack: &mut ProxyStack, current_sp: usize) {
-    loop {
-        let Some(frame) = stack.last() else {
-            break;
-        };
+    while let Some(frame) = stack.last() {
         if frame.stack_sp > current_sp {
             break;
         }
@@ -80,11 +82,7 @@ where
 
     impl Drop for ProxyLeaveGuard {
         fn drop(&mut self) {
-            if self.entered {
-                proxy_leave(self.func);
-            } else {
-                hub::proxy_leave(self.func);
-            }
+            proxy_scope_leave(self.func, self.entered);
         }
     }
 
@@ -103,6 +101,39 @@ pub(super) fn get_return_address() -> *mut c_void {
     hub::get_return_address()
 }
 
+pub(super) fn get_hub_backtrace(buf: &mut [usize]) -> usize {
+    hub::backtrace(buf)
+}
+
+// 按当前 hub 栈顶返回地址反查调用方模块，结果按页缓存在线程状态中
+pub(super) fn get_caller_module() -> Option<ModuleIdentity> {
+    let return_addr = hub::get_return_address();
+    if return_addr.is_null() {
+        return None;
+    }
+    let page = return_addr as usize & CALLER_PAGE_MASK;
+    let (cached, generation) =
+        thread_state::with_thread_state(|state| state.caller_modules_mut().lookup(page))
+            .unwrap_or((None, 0));
+    if cached.is_some() {
+        return cached;
+    }
+
+    let module = refresh::module_identity_from_addr(return_addr)?;
+    let identity = ModuleIdentity {
+        pathname: module.pathname,
+        base_addr: module.base_addr,
+        instance_id: module.instance_id,
+        namespace_id: module.namespace_id,
+    };
+    let _ = thread_state::with_thread_state(|state| {
+        state
+            .caller_modules_mut()
+            .insert(page, generation, identity.clone())
+    });
+    Some(identity)
+}
+
 pub(super) fn pop_stack(return_address: *mut c_void) {
     hub::pop_stack(return_address)
 }
@@ -146,8 +177,53 @@ pub(super) fn proxy_leave(func: *mut c_void) {
     hub::proxy_leave(func);
 }
 
+// 未进入（命中递归环）时 proxy 栈中的帧属于外层调用，只释放本次 trampoline 压入的 hub 帧
+pub(super) fn proxy_scope_leave(func: *mut c_void, entered: bool) {
+    if entered {
+        proxy_leave(func);
+    } else {
+        hub::proxy_leave(func);
+    }
+}
+
+// 同时清除观测抑制深度，防止未配对的 suppress_begin 在 clear/fork 后残留
 pub(super) fn clear_proxy_stack() {
     let _ = with_proxy_stack_mut("clear_proxy_stack", |stack| {
         let _ = stack.clear();
     });
+    let _ = thread_state::with_thread_state(|state| *state.suppress_depth_mut() = 0);
+}
+
+// 观测抑制按深度计数，可嵌套；线程状态不可用时视为未抑制
+pub(super) fn suppress_begin() {
+    let _ = thread_state::with_thread_state(|state| {
+        let depth = state.suppress_depth_mut();
+        *depth = depth.saturating_add(1);
+    });
+}
+
+pub(super) fn suppress_end() {
+    let _ = thread_state::with_thread_state(|state| {
+        let depth = state.suppress_depth_mut();
+        *depth = depth.saturating_sub(1);
+    });
+}
+
+pub(super) fn is_suppressed() -> bool {
+    thread_state::with_thread_state(|state| *state.suppress_depth_mut() > 0).unwrap_or(false)
+}
+
+// 设置当前线程是否经过 hub，返回设置前的状态；线程状态不可用（含 fork 子进程）时不生效并返回 true
+pub(super) fn set_thread_hook_enabled(enabled: bool) -> bool {
+    thread_state::with_thread_state(|state| {
+        let disabled = state.hook_disabled_mut();
+        let prev = !*disabled;
+        *disabled = !enabled;
+        prev
+    })
+    .unwrap_or(true)
+}
+
+pub(super) fn is_thread_hook_enabled() -> bool {
+    !thread_state::is_thread_hook_disabled()
 }
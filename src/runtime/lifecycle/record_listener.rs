@@ -0,0 +1,77 @@
+// 记录监听器：记录在持有 state 时排队，由释放全部锁的线程取出后投递，投递在外部回调上下文中执行
+// 投递锁串行化各线程的投递以保持写入顺序，获取时不持有其他锁
+use crate::api::RecordCallback;
+use crate::errno::Errno;
+use crate::runtime::state::MutexPoisonRecover;
+use std::ffi::{CString, c_void};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::super::callback_ctx;
+use super::super::state::{GLOBAL, RecordListenerEntry, StoredRecord};
+use super::task_ops;
+
+static LISTENER_SET: AtomicBool = AtomicBool::new(false);
+static DELIVERY_LOCK: Mutex<()> = Mutex::new(());
+
+pub(super) fn set_listener(callback: RecordCallback, arg: *mut c_void) -> Errno {
+    let mut state = GLOBAL.state.lock_or_poison();
+    state.record_listener = Some(RecordListenerEntry {
+        callback,
+        arg: arg as usize,
+    });
+    LISTENER_SET.store(true, Ordering::Release);
+    Errno::Ok
+}
+
+pub(super) fn clear_listener() -> Errno {
+    let mut state = GLOBAL.state.lock_or_poison();
+    state.record_listener = None;
+    state.pending_record_notifications.clear();
+    LISTENER_SET.store(false, Ordering::Release);
+    Errno::Ok
+}
+
+// 调用方不得持有 dlclose_lock / refresh_mutex / state；回调内触发的投递留给外层循环处理
+pub(super) fn deliver_pending() {
+    if !LISTENER_SET.load(Ordering::Acquire) || callback_ctx::is_in_external_callback() {
+        return;
+    }
+    let _delivery = DELIVERY_LOCK.lock_or_poison();
+    loop {
+        let (listener, records) = {
+            let mut state = GLOBAL.state.lock_or_poison();
+            task_ops::flush_deferred_records(&mut state);
+            let records = std::mem::take(&mut state.pending_record_notifications);
+            (state.record_listener, records)
+        };
+        let Some(listener) = listener else {
+            return;
+        };
+        if records.is_empty() {
+            return;
+        }
+        for record in &records {
+            invoke_one(listener, record);
+        }
+    }
+}
+
+fn invoke_one(listener: RecordListenerEntry, record: &StoredRecord) {
+    let caller_lib_name = CString::new(record.caller_lib_name.as_str()).unwrap_or_default();
+    let lib_name = CString::new(record.lib_name.as_str()).unwrap_or_default();
+    let sym_name = CString::new(record.sym_name.as_str()).unwrap_or_default();
+    callback_ctx::run_in_external_callback(|| unsafe {
+        (listener.callback)(
+            record.ts_ms,
+            record.op,
+            caller_lib_name.as_ptr(),
+            lib_name.as_ptr(),
+            sym_name.as_ptr(),
+            record.new_addr as *mut c_void,
+            record.status_code,
+            record.stub,
+            listener.arg as *mut c_void,
+        );
+    });
+}
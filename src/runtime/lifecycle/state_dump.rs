@@ -0,0 +1,204 @@
+// 运行时状态转储：slot、hub 调用链与任务，仅用于本地诊断，内容不做脱敏
+// state 锁内只复制转储所需的字段，格式化与写出在锁外按固定大小缓冲分段进行
+use crate::api::HookStub;
+use crate::errno::Errno;
+use std::fmt::{self, Write};
+
+use super::super::hub;
+use super::super::state::{CoreState, GLOBAL, SlotKey, TaskType};
+use crate::runtime::state::MutexPoisonRecover;
+
+// 写 fd 时的缓冲上限，满后立即写出
+const FD_CHUNK: usize = 4096;
+
+struct SlotDump {
+    key: SlotKey,
+    orig_func: usize,
+    trampo: usize,
+    // (proxy 地址, 是否启用, 引用该 proxy 的任务)
+    proxies: Vec<(usize, bool, Vec<HookStub>)>,
+}
+
+struct TaskDump {
+    stub: HookStub,
+    task_type: TaskType,
+    caller: String,
+    callee: String,
+    sym_name: String,
+    new_func: usize,
+    paused: bool,
+    slot_count: usize,
+}
+
+struct StateDump {
+    slots: Vec<SlotDump>,
+    tasks: Vec<TaskDump>,
+    retired_hubs: usize,
+    active_frames: usize,
+}
+
+pub(super) fn dump_state(fd: i32) -> Errno {
+    if fd < 0 {
+        return Errno::InvalidArg;
+    }
+    let dump = snapshot();
+    let mut sink = FdSink {
+        fd,
+        buf: String::with_capacity(FD_CHUNK),
+        status: Errno::Ok,
+    };
+    if render(&dump, &mut sink).is_ok() {
+        sink.flush();
+    }
+    sink.status
+}
+
+pub(super) fn state_dump_text() -> String {
+    let dump = snapshot();
+    let mut text = String::new();
+    let _ = render(&dump, &mut text);
+    text
+}
+
+fn snapshot() -> StateDump {
+    let state = GLOBAL.state.lock_or_poison();
+    StateDump {
+        slots: state
+            .slots
+            .iter()
+            .map(|(key, slot)| {
+                let hub_ptr = slot.hub_ptr as *mut hub::Hub;
+                let proxies = hub::proxy_nodes(hub_ptr)
+                    .into_iter()
+                    .map(|(func, enabled)| {
+                        (func, enabled, chain_stubs(&state, &slot.task_chain, func))
+                    })
+                    .collect();
+                SlotDump {
+                    key: key.clone(),
+                    orig_func: slot.orig_func,
+                    trampo: hub::hub_trampo(hub_ptr),
+                    proxies,
+                }
+            })
+            .collect(),
+        tasks: state
+            .task_order
+            .iter()
+            .filter_map(|stub| {
+                let task = state.tasks.get(stub)?;
+                let caller = match (&task.caller_path_name, &task.caller_allow_filter) {
+                    (Some(rule), _) => rule.clone(),
+                    (None, Some(_)) => "<allow filter>".to_string(),
+                    (None, None) if !task.caller_allow_list.is_empty() => {
+                        task.caller_allow_list.join(",")
+                    }
+                    (None, None) => "*".to_string(),
+                };
+                Some(TaskDump {
+                    stub: *stub,
+                    task_type: task.task_type,
+                    caller,
+                    callee: task
+                        .callee_path_name
+                        .clone()
+                        .unwrap_or_else(|| "*".to_string()),
+                    sym_name: task.sym_name.clone(),
+                    new_func: task.new_func,
+                    paused: task.paused,
+                    slot_count: state.task_slots.get(stub).map_or(0, |keys| keys.len()),
+                })
+            })
+            .collect(),
+        retired_hubs: hub::retired_count(),
+        active_frames: hub::active_frame_count(),
+    }
+}
+
+// slot 任务链中 new_func 等于该 proxy 的任务
+fn chain_stubs(state: &CoreState, task_chain: &[HookStub], func: usize) -> Vec<HookStub> {
+    task_chain
+        .iter()
+        .copied()
+        .filter(|stub| {
+            state
+                .tasks
+                .get(stub)
+                .is_some_and(|task| task.new_func == func)
+        })
+        .collect()
+}
+
+fn render(dump: &StateDump, out: &mut impl Write) -> fmt::Result {
+    writeln!(out, "# retired hubs: {}", dump.retired_hubs)?;
+    writeln!(out, "# active stack frames: {}", dump.active_frames)?;
+    writeln!(out, "# slots: {}", dump.slots.len())?;
+    for slot in &dump.slots {
+        let key = &slot.key;
+        writeln!(
+            out,
+            "slot {} base=0x{:x} instance={} namespace=0x{:x} addr=0x{:x} orig=0x{:x} trampo=0x{:x}",
+            key.caller_path_name,
+            key.caller_base_addr,
+            key.caller_instance_id,
+            key.caller_namespace_id,
+            key.slot_addr,
+            slot.orig_func,
+            slot.trampo
+        )?;
+        for (func, enabled, stubs) in &slot.proxies {
+            writeln!(
+                out,
+                "  proxy 0x{:x} enabled={} stubs={:?}",
+                func, enabled, stubs
+            )?;
+        }
+    }
+    writeln!(out, "# tasks: {}", dump.tasks.len())?;
+    for task in &dump.tasks {
+        writeln!(
+            out,
+            "task {} type={:?} caller={} callee={} sym={} new=0x{:x} paused={} slots={}",
+            task.stub,
+            task.task_type,
+            task.caller,
+            task.callee,
+            task.sym_name,
+            task.new_func,
+            task.paused,
+            task.slot_count
+        )?;
+    }
+    Ok(())
+}
+
+// 按块写 fd 的输出端，写入失败后记录错误并中止格式化
+struct FdSink {
+    fd: i32,
+    buf: String,
+    status: Errno,
+}
+
+impl FdSink {
+    fn flush(&mut self) -> bool {
+        let result = super::super::record::dump_records_text(self.fd, &self.buf);
+        self.buf.clear();
+        match result {
+            Ok(()) => true,
+            Err(err) => {
+                self.status = err;
+                false
+            }
+        }
+    }
+}
+
+impl Write for FdSink {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.buf.len() + s.len() > FD_CHUNK && !self.buf.is_empty() && !self.flush() {
+            return Err(fmt::Error);
+        }
+        self.buf.push_str(s);
+        Ok(())
+    }
+}
@@ -1,34 +1,99 @@
 // hook 任务的添加、异步刷新请求与回调分发
-use crate::api::{HookMode, HookStub};
+use crate::api::{CalleeSelection, HookMode, HookStub};
 use crate::errno::Errno;
 use crate::log;
+use std::collections::BTreeMap;
 use std::ffi::c_void;
 
+use super::super::client_abi;
 use super::super::record;
 use super::super::refresh::{self, CallbackEvent};
-use super::super::state::{GLOBAL, Task, TaskType};
+use super::super::state::{
+    CoreState, GLOBAL, HookEventEntry, ModuleInfo, Task, TaskRegistry, TaskType,
+    debug_assert_registry_released, lock_registry,
+};
+use super::callback_dispatch;
 use super::monitor;
 use super::process;
+use super::record_listener;
 use crate::runtime::state::{MutexPoisonRecover, RwLockPoisonRecover};
 
-// 注册 hook 任务：分配 stub、立即应用、记录结果，自动模式下唤醒 monitor
-pub(super) fn add_task(mut task: Task) -> Option<HookStub> {
-    let _dlclose_guard = GLOBAL.dlclose_lock.read_or_poison();
-    let _refresh_guard = GLOBAL.refresh_mutex.lock_or_poison();
-    let mut state = GLOBAL.state.lock_or_poison();
-    if state.init.status != Errno::Ok {
-        return None;
+// 注册阶段缓存的拒绝记录上限，超出后淘汰最早的条目
+const REJECTED_RECORDS_MAX: usize = 64;
+
+// 注册 hook 任务：仅在 registry 中登记并分配 stub，随后尝试立即合并应用
+// 刷新进行中时直接返回，由刷新持有者在释放锁后合并
+pub(super) fn add_task(task: Task) -> Result<HookStub, Errno> {
+    add_tasks(vec![Ok(task)])
+        .pop()
+        .unwrap_or(Err(Errno::InvalidArg))
+}
+
+// 批量注册：一次持有 registry 登记全部有效条目，合并时共用一轮扫描
+// 结果与输入一一对应，被拒绝的条目不影响其他条目的注册
+// ABI 检查按 proxy 所在模块经 dladdr 解析，须在持有 registry 之前完成
+pub(super) fn add_tasks(tasks: Vec<Result<Task, Errno>>) -> Vec<Result<HookStub, Errno>> {
+    let abi_allowed: Vec<bool> = tasks
+        .iter()
+        .map(|task| {
+            task.as_ref()
+                .is_ok_and(|task| client_abi::registration_allowed(task.new_func))
+        })
+        .collect();
+    let stubs: Vec<_> = {
+        let mut registry = lock_registry();
+        tasks
+            .into_iter()
+            .zip(abi_allowed)
+            .map(|(task, abi_allowed)| {
+                let mut task = task?;
+                let key = task.key();
+                let status = if !abi_allowed {
+                    Errno::AbiMismatch
+                } else if !registry.open {
+                    Errno::Uninit
+                } else if registry.keys.values().any(|registered| *registered == key) {
+                    Errno::RepeatedFunc
+                } else {
+                    Errno::Ok
+                };
+                if status != Errno::Ok {
+                    reject_task(&mut registry, &task, status);
+                    return Err(status);
+                }
+                let stub = alloc_stub(&mut registry);
+                task.stub = stub;
+                registry.keys.insert(stub, key);
+                refresh::note_module_rules(task.module_rules());
+                registry.pending.push(task);
+                Ok(stub)
+            })
+            .collect()
+    };
+    if stubs.iter().any(Result::is_ok) {
+        apply_pending_tasks();
     }
-    process::ensure_process_context(&mut state);
+    stubs
+}
 
-    let stub = state.next_stub;
-    state.next_stub = state.next_stub.saturating_add(1);
-    if state.next_stub == 0 {
-        state.next_stub = 1;
+fn reject_task(registry: &mut TaskRegistry, task: &Task, status: Errno) {
+    log::warn(format_args!(
+        "hook {} rejected: {:?}, proxy 0x{:x}",
+        task.sym_name, status, task.new_func
+    ));
+    if registry.rejected.len() >= REJECTED_RECORDS_MAX {
+        registry.rejected.remove(0);
     }
+    registry.rejected.push(record::rejected_hook_record(
+        status,
+        &record_lib_name(task),
+        &task.sym_name,
+        task.new_func,
+    ));
+}
 
-    task.stub = stub;
-    let record_lib_name = match task.task_type {
+fn record_lib_name(task: &Task) -> String {
+    match task.task_type {
         TaskType::Single => task
             .caller_path_name
             .as_deref()
@@ -36,50 +101,217 @@ pub(super) fn add_task(mut task: Task) -> Option<HookStub> {
             .to_string(),
         TaskType::Partial => "PARTIAL".to_string(),
         TaskType::All => "ALL".to_string(),
+    }
+}
+
+// 将注册阶段缓存的拒绝记录、信号守卫新捕获的访问异常与 CFI 补丁报告的变化写入 records，调用方需持有 state
+pub(super) fn flush_deferred_records(state: &mut CoreState) {
+    let rejected = std::mem::take(&mut lock_registry().rejected);
+    record::add_records(state, rejected);
+    record::add_guard_fault_records(state);
+    record::add_cfi_patch_records(state);
+}
+
+// 任务从 CoreState 移除后释放其去重键
+pub(super) fn forget_task(stub: HookStub) {
+    lock_registry().keys.remove(&stub);
+}
+
+fn alloc_stub(registry: &mut TaskRegistry) -> HookStub {
+    let stub = registry.next_stub;
+    registry.next_stub = registry.next_stub.saturating_add(1);
+    if registry.next_stub == 0 {
+        registry.next_stub = 1;
+    }
+    stub
+}
+
+// 合并结果：需在释放全部锁后投递回调、按需启动 monitor
+pub(super) struct MergedTasks {
+    events: Vec<CallbackEvent>,
+    start_monitor: bool,
+}
+
+impl MergedTasks {
+    pub(super) fn into_events(self) -> Vec<CallbackEvent> {
+        self.events
+    }
+
+    pub(super) fn finish(self) {
+        if self.start_monitor {
+            monitor::start_monitor_thread();
+            monitor::install_auto_loader_monitor_hooks();
+        }
+        invoke_callbacks(self.events);
+    }
+}
+
+// 将 registry 中待合并的任务移入 CoreState 并应用，调用方需持有 refresh_mutex 与 state
+pub(super) fn merge_pending_tasks(state: &mut CoreState) -> MergedTasks {
+    flush_deferred_records(state);
+    let pending = std::mem::take(&mut lock_registry().pending);
+    let mut merged = MergedTasks {
+        events: Vec::new(),
+        start_monitor: false,
     };
-    let record_sym_name = task.sym_name.clone();
-    let record_new_func = task.new_func;
-    let record_use_real_status = task.task_type == TaskType::Single;
-    state.task_order.push(stub);
-    state.tasks.insert(stub, task);
+    if pending.is_empty() {
+        return merged;
+    }
+    if state.init.status != Errno::Ok {
+        log::warn(format_args!(
+            "drop {} pending hook tasks, init status {:?}",
+            pending.len(),
+            state.init.status
+        ));
+        let mut registry = lock_registry();
+        for task in &pending {
+            registry.keys.remove(&task.stub);
+        }
+        return merged;
+    }
+    process::ensure_process_context(state);
 
     // Manual 模式下只入队，由后续 refresh() 统一应用
+    // 同一次合并的任务共用一轮扫描，批量注册不会按任务数重复遍历模块
     let is_manual = state.init.mode == HookMode::Manual;
-    let (status, events) = if is_manual {
-        (Errno::Ok, Vec::new())
-    } else {
-        refresh::apply_new_task(&mut state, stub)
-    };
-    let status_code = if record_use_real_status {
-        status.as_i32()
+    let mut stubs = Vec::with_capacity(pending.len());
+    let mut record_items = Vec::with_capacity(pending.len());
+    for task in pending {
+        let stub = task.stub;
+        let record_lib_name = record_lib_name(&task);
+        let record_use_real_status = task.task_type == TaskType::Single;
+        record_items.push((
+            stub,
+            record_lib_name,
+            task.sym_name.clone(),
+            task.new_func,
+            record_use_real_status,
+        ));
+        stubs.push(stub);
+        state.task_order.push(stub);
+        state.tasks.insert(stub, task);
+    }
+
+    let (task_status, events) = if is_manual {
+        (BTreeMap::new(), Vec::new())
     } else {
-        Errno::Max.as_i32()
+        refresh::apply_new_tasks(state, &stubs)
     };
-    record::add_hook_record(
-        &mut state,
-        status_code,
-        &record_lib_name,
-        &record_sym_name,
-        record_new_func,
-        stub,
-    );
-    if status != Errno::Ok && status != Errno::NoSym {
-        log::warn(format_args!("hook task {} apply status {:?}", stub, status));
-    }
-
-    let need_start_monitor = !is_manual && !state.monitor_running;
+    for (stub, lib_name, sym_name, new_func, use_real_status) in record_items {
+        let status = task_status.get(&stub).copied().unwrap_or(Errno::Ok);
+        let status_code = if use_real_status {
+            status.as_i32()
+        } else {
+            Errno::Max.as_i32()
+        };
+        record::add_hook_record(state, status_code, &lib_name, &sym_name, new_func, stub);
+        if status != Errno::Ok && status != Errno::NoSym {
+            log::warn(format_args!("hook task {} apply status {:?}", stub, status));
+        }
+    }
+    merged.events.extend(events);
+
     if !is_manual {
+        merged.start_monitor = !state.monitor_running;
         state.refresh_requested = true;
         GLOBAL.condvar.notify_one();
     }
+    merged
+}
 
-    drop(state);
-    if need_start_monitor {
-        monitor::start_monitor_thread();
-        monitor::install_auto_loader_monitor_hooks();
+// 以非阻塞方式成为 refresh_mutex 持有者并合并待处理任务；锁被占用时由当前持有者负责
+// 持有者释放 refresh_mutex 后必须调用本函数，避免在其释放前入队的任务滞留
+pub(super) fn apply_pending_tasks() {
+    loop {
+        if lock_registry().pending.is_empty() {
+            return;
+        }
+        debug_assert_registry_released();
+        let dlclose_guard = GLOBAL.dlclose_lock.read_or_poison();
+        let Some(refresh_guard) = GLOBAL.refresh_mutex.try_lock_or_poison() else {
+            return;
+        };
+        let mut state = GLOBAL.state.lock_or_poison();
+        let merged = merge_pending_tasks(&mut state);
+        drop(state);
+        drop(refresh_guard);
+        drop(dlclose_guard);
+        merged.finish();
     }
-    invoke_callbacks(events);
-    Some(stub)
+}
+
+// 从 registry 撤回尚未合并的任务，返回是否命中
+pub(super) fn withdraw_pending_task(stub: HookStub) -> bool {
+    let mut registry = lock_registry();
+    let before = registry.pending.len();
+    registry.pending.retain(|task| task.stub != stub);
+    if registry.pending.len() == before {
+        return false;
+    }
+    registry.keys.remove(&stub);
+    true
+}
+
+// 尚未合并任务的副本，调用方可持有 state
+pub(super) fn pending_tasks() -> Vec<Task> {
+    lock_registry().pending.clone()
+}
+
+// 修改尚未合并任务的 callee 选择策略，返回是否命中
+pub(super) fn set_pending_callee_selection(stub: HookStub, selection: CalleeSelection) -> bool {
+    let mut registry = lock_registry();
+    let Some(task) = registry.pending.iter_mut().find(|task| task.stub == stub) else {
+        return false;
+    };
+    task.callee_selection = selection;
+    true
+}
+
+pub(super) fn set_pending_threads(stub: HookStub, threads: &[i32]) -> bool {
+    let mut registry = lock_registry();
+    let Some(task) = registry.pending.iter_mut().find(|task| task.stub == stub) else {
+        return false;
+    };
+    task.threads = threads.to_vec();
+    true
+}
+
+pub(super) fn is_pending_task(stub: HookStub) -> bool {
+    lock_registry().pending.iter().any(|task| task.stub == stub)
+}
+
+// 修改尚未合并任务的暂停状态，返回是否命中
+pub(super) fn set_pending_paused(stub: HookStub, paused: bool) -> bool {
+    let mut registry = lock_registry();
+    let Some(task) = registry.pending.iter_mut().find(|task| task.stub == stub) else {
+        return false;
+    };
+    task.paused = paused;
+    true
+}
+
+pub(super) fn set_pending_event_callback(stub: HookStub, entry: Option<HookEventEntry>) -> bool {
+    let mut registry = lock_registry();
+    let Some(task) = registry.pending.iter_mut().find(|task| task.stub == stub) else {
+        return false;
+    };
+    task.event_callback = entry;
+    true
+}
+
+// init 成功后开放注册
+pub(super) fn open_registry() {
+    lock_registry().open = true;
+}
+
+// clear 时关闭注册并丢弃未合并的任务与去重键，stub 重新从 1 开始
+pub(super) fn reset_registry() {
+    let mut registry = lock_registry();
+    registry.open = false;
+    registry.next_stub = 1;
+    registry.pending.clear();
+    registry.keys.clear();
+    registry.rejected.clear();
 }
 
 pub(super) fn request_refresh_async() {
@@ -131,24 +363,24 @@ pub(super) fn request_refresh_async_full() {
     }
 }
 
-pub(super) fn invoke_callbacks(events: Vec<CallbackEvent>) {
-    for event in events {
-        let Ok(caller_path_name) = std::ffi::CString::new(event.caller_path_name) else {
-            continue;
-        };
-        let Ok(sym_name) = std::ffi::CString::new(event.sym_name) else {
-            continue;
-        };
-        unsafe {
-            (event.hooked.callback)(
-                event.task_stub,
-                event.status.as_i32(),
-                caller_path_name.as_ptr(),
-                sym_name.as_ptr(),
-                event.new_func as *mut c_void,
-                event.prev_func as *mut c_void,
-                event.hooked.arg as *mut c_void,
-            );
-        }
+// 记录 dlclose 卸载的模块，monitor 只清理这些模块；积压过多时退化为全量刷新
+pub(super) fn request_refresh_async_unloaded(module: ModuleInfo) {
+    const PENDING_UNLOADED_LIMIT: usize = 256;
+    let mut state = GLOBAL.state.lock_or_poison();
+    if !state.monitor_running {
+        return;
+    }
+    if state.pending_unloaded_modules.len() >= PENDING_UNLOADED_LIMIT {
+        state.pending_unloaded_modules.clear();
+        state.known_modules.clear();
+        state.refresh_requested = true;
+    } else {
+        state.pending_unloaded_modules.push(module);
     }
+    GLOBAL.condvar.notify_one();
+}
+
+pub(super) fn invoke_callbacks(events: Vec<CallbackEvent>) {
+    callback_dispatch::dispatch(events);
+    record_listener::deliver_pending();
 }
@@ -1,19 +1,39 @@
 // hook 操作审计记录的写入、格式化与导出
+use crate::android::signal_guard;
 use crate::api::{
-    HookStub, RECORD_ITEM_CALLER_LIB_NAME, RECORD_ITEM_ERRNO, RECORD_ITEM_LIB_NAME,
+    CfiAddrSource, CfiPatchStatus, CfiSymbolKind, HookStub, RECORD_ITEM_CALLER_LIB_NAME,
+    RECORD_ITEM_ERRNO, RECORD_ITEM_INSTANCE, RECORD_ITEM_LIB_NAME, RECORD_ITEM_NAMESPACE,
     RECORD_ITEM_NEW_ADDR, RECORD_ITEM_OP, RECORD_ITEM_STUB, RECORD_ITEM_SYM_NAME,
-    RECORD_ITEM_TIMESTAMP,
+    RECORD_ITEM_TIMESTAMP, RecordEntry, RecordOp, ResolvedSymbol,
 };
 use crate::errno::Errno;
+use std::collections::BTreeMap;
 use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use super::state::{CoreState, RecordEntry, RecordOp};
+use super::cfi;
+use super::state::{
+    CoreState, ModuleInfo, MutexPoisonRecover, RecordModule, SlotKey, StoredRecord,
+};
 
-// 环形缓冲区上限，超出后淘汰最早的记录
-const MAX_RECORDS: usize = 4096;
+// 环形缓冲区默认容量与可设置的上限，满时淘汰最早的记录
+const DEFAULT_RECORD_CAPACITY: usize = 4096;
+const MAX_RECORD_CAPACITY: usize = 1 << 20;
+// 待投递给监听器的记录上限
+const MAX_PENDING_NOTIFICATIONS: usize = 4096;
 const CALLER_LIB_UNKNOWN: &str = "unknown";
 
+// 容量属于运行时配置，clear() 不复位
+static RECORD_CAPACITY: AtomicUsize = AtomicUsize::new(DEFAULT_RECORD_CAPACITY);
+// 已写入 records 的最大访问异常序号；clear() 不复位，避免清空后重放守卫缓冲区中的旧条目
+static GUARD_FAULT_CURSOR: AtomicU64 = AtomicU64::new(0);
+// 已导出的 CFI 补丁报告代数与各地址状态（含是否属于忽略模块）；clear() 不复位，只导出之后的变化
+static CFI_REPORT_GENERATION: AtomicU64 = AtomicU64::new(0);
+static CFI_REPORT_RECORDED: Mutex<BTreeMap<usize, (CfiPatchStatus, bool)>> =
+    Mutex::new(BTreeMap::new());
+
 #[inline]
 fn now_ms() -> u64 {
     SystemTime::now()
@@ -22,16 +42,50 @@ fn now_ms() -> u64 {
         .unwrap_or(0)
 }
 
-// recordable 关闭时静默丢弃，满时淘汰队首
+// 设置了监听器时先排入待投递队列；recordable 关闭时不写入缓冲区，满时淘汰队首
 #[inline]
-fn push_record(state: &mut CoreState, entry: RecordEntry) {
+fn push_record(state: &mut CoreState, entry: StoredRecord) {
+    if state.record_listener.is_some()
+        && state.pending_record_notifications.len() < MAX_PENDING_NOTIFICATIONS
+    {
+        state.pending_record_notifications.push(entry.clone());
+    }
     if !state.recordable {
         return;
     }
-    if state.records.len() >= MAX_RECORDS {
-        state.records.remove(0);
+    evict_records(state, RECORD_CAPACITY.load(Ordering::Relaxed) - 1);
+    state.records.push_back(entry);
+}
+
+// 淘汰最早的记录直到不超过 keep 条
+fn evict_records(state: &mut CoreState, keep: usize) {
+    while state.records.len() > keep {
+        state.records.pop_front();
+        state.records_dropped += 1;
+    }
+}
+
+pub(super) fn is_valid_record_capacity(max_entries: usize) -> bool {
+    max_entries != 0 && max_entries <= MAX_RECORD_CAPACITY
+}
+
+// 缩容时保留最新的记录，被截掉的计入丢弃数
+pub(super) fn set_record_capacity(state: &mut CoreState, max_entries: usize) -> Errno {
+    if !is_valid_record_capacity(max_entries) {
+        return Errno::InvalidArg;
     }
-    state.records.push(entry);
+    RECORD_CAPACITY.store(max_entries, Ordering::Relaxed);
+    evict_records(state, max_entries);
+    Errno::Ok
+}
+
+pub(super) fn record_capacity() -> usize {
+    RECORD_CAPACITY.load(Ordering::Relaxed)
+}
+
+// dump 输出的首行，标明被淘汰的条数，供离线分析判断记录是否被截断
+pub(super) fn dump_header(state: &CoreState) -> String {
+    format!("# dropped: {}\n", state.records_dropped)
 }
 
 pub(super) fn add_hook_record(
@@ -44,7 +98,7 @@ pub(super) fn add_hook_record(
 ) {
     push_record(
         state,
-        RecordEntry {
+        StoredRecord {
             op: RecordOp::Hook,
             ts_ms: now_ms(),
             status_code,
@@ -52,23 +106,302 @@ pub(super) fn add_hook_record(
             lib_name: lib_name.to_string(),
             sym_name: sym_name.to_string(),
             new_addr,
+            observed_addr: None,
+            stub,
+            module: None,
+        },
+    );
+}
+
+// 刷新阶段针对具体 caller 模块的 hook 记录，带上模块实例
+pub(super) fn add_module_hook_record(
+    state: &mut CoreState,
+    op: RecordOp,
+    status_code: i32,
+    caller: &ModuleInfo,
+    sym_name: &str,
+    new_addr: usize,
+    stub: HookStub,
+) {
+    push_record(
+        state,
+        StoredRecord {
+            op,
+            ts_ms: now_ms(),
+            status_code,
+            caller_lib_name: CALLER_LIB_UNKNOWN.to_string(),
+            lib_name: caller.pathname.clone(),
+            sym_name: sym_name.to_string(),
+            new_addr,
+            observed_addr: None,
             stub,
+            module: Some(record_module(caller)),
         },
     );
 }
 
-pub(super) fn add_unhook_record(state: &mut CoreState, status_code: i32, stub: HookStub) {
+// 写入后回读不一致的 slot：new_addr 为期望写入的值，observed_addr 为实际读到的值
+pub(super) fn add_verify_failed_record(
+    state: &mut CoreState,
+    caller: &ModuleInfo,
+    sym_name: &str,
+    expected: usize,
+    observed: usize,
+    stub: HookStub,
+) {
+    push_record(
+        state,
+        StoredRecord {
+            op: RecordOp::Hook,
+            ts_ms: now_ms(),
+            status_code: Errno::PatchVerifyFailed.as_i32(),
+            caller_lib_name: CALLER_LIB_UNKNOWN.to_string(),
+            lib_name: caller.pathname.clone(),
+            sym_name: sym_name.to_string(),
+            new_addr: expected,
+            observed_addr: Some(observed),
+            stub,
+            module: Some(record_module(caller)),
+        },
+    );
+}
+
+fn record_module(caller: &ModuleInfo) -> RecordModule {
+    RecordModule {
+        base_addr: caller.base_addr,
+        instance_id: caller.instance_id,
+        namespace_id: caller.namespace_id,
+    }
+}
+
+// 注册阶段被拒绝的 hook 没有 stub，先缓存记录条目，持有 state 时再写入
+pub(super) fn rejected_hook_record(
+    status: Errno,
+    lib_name: &str,
+    sym_name: &str,
+    new_addr: usize,
+) -> StoredRecord {
+    StoredRecord {
+        op: RecordOp::Hook,
+        ts_ms: now_ms(),
+        status_code: status.as_i32(),
+        caller_lib_name: CALLER_LIB_UNKNOWN.to_string(),
+        lib_name: lib_name.to_string(),
+        sym_name: sym_name.to_string(),
+        new_addr,
+        observed_addr: None,
+        stub: 0,
+        module: None,
+    }
+}
+
+pub(super) fn add_records(state: &mut CoreState, entries: Vec<StoredRecord>) {
+    for entry in entries {
+        push_record(state, entry);
+    }
+}
+
+// 任务的 slot 全部位于同一 caller 模块实例时返回该模块，需在卸载 slot 前调用
+pub(super) fn unhook_record_caller(state: &CoreState, stub: HookStub) -> Option<ModuleInfo> {
+    let mut keys = state.task_slots.get(&stub)?.iter();
+    let first = keys.next()?;
+    let same_module = |key: &SlotKey| {
+        key.caller_path_name == first.caller_path_name
+            && key.caller_base_addr == first.caller_base_addr
+            && key.caller_instance_id == first.caller_instance_id
+            && key.caller_namespace_id == first.caller_namespace_id
+    };
+    if !keys.all(same_module) {
+        return None;
+    }
+    Some(ModuleInfo {
+        pathname: first.caller_path_name.clone(),
+        base_addr: first.caller_base_addr,
+        instance_id: first.caller_instance_id,
+        namespace_id: first.caller_namespace_id,
+        soname: None,
+    })
+}
+
+pub(super) fn add_unhook_record(
+    state: &mut CoreState,
+    status_code: i32,
+    stub: HookStub,
+    caller: Option<&ModuleInfo>,
+) {
+    let Some(caller) = caller else {
+        add_stub_record(state, RecordOp::Unhook, status_code, stub);
+        return;
+    };
     push_record(
         state,
-        RecordEntry {
+        StoredRecord {
             op: RecordOp::Unhook,
             ts_ms: now_ms(),
             status_code,
             caller_lib_name: CALLER_LIB_UNKNOWN.to_string(),
+            lib_name: caller.pathname.clone(),
+            sym_name: String::new(),
+            new_addr: 0,
+            observed_addr: None,
+            stub,
+            module: Some(record_module(caller)),
+        },
+    );
+}
+
+pub(super) fn add_pause_record(
+    state: &mut CoreState,
+    paused: bool,
+    status_code: i32,
+    stub: HookStub,
+) {
+    let op = if paused {
+        RecordOp::Pause
+    } else {
+        RecordOp::Resume
+    };
+    add_stub_record(state, op, status_code, stub);
+}
+
+// clear() 复位前写入，随后缓冲区被清空，只有监听器能收到
+pub(super) fn add_clear_record(state: &mut CoreState) {
+    add_stub_record(state, RecordOp::Clear, Errno::Ok.as_i32(), 0);
+}
+
+// 信号处理器中不能加锁，访问异常先进入守卫的环形缓冲区，持有 state 时补写为 GUARD_FAULT 记录
+// 两次补写之间溢出缓冲区的条目只计入 get_guard_fault_stats 的总数
+pub(super) fn add_guard_fault_records(state: &mut CoreState) {
+    let faults = signal_guard::faults_after(GUARD_FAULT_CURSOR.load(Ordering::Relaxed));
+    let Some(last) = faults.last() else {
+        return;
+    };
+    GUARD_FAULT_CURSOR.store(last.seq, Ordering::Relaxed);
+    for entry in faults {
+        let fault = entry.fault;
+        push_record(
+            state,
+            StoredRecord {
+                op: RecordOp::GuardFault,
+                ts_ms: entry.ts_ms,
+                status_code: Errno::SegvErr.as_i32(),
+                caller_lib_name: CALLER_LIB_UNKNOWN.to_string(),
+                lib_name: fault.phase.to_string(),
+                sym_name: format!("{}:{}", signal_name(fault.signum), fault.code),
+                new_addr: fault.addr,
+                observed_addr: None,
+                stub: 0,
+                module: None,
+            },
+        );
+    }
+}
+
+// CFI 补丁报告中新增或状态变化的地址写为 CFI_PATCH 记录
+// 既未开启记录也没有监听器时不推进导出进度，之后开启仍能导出完整报告
+pub(super) fn add_cfi_patch_records(state: &mut CoreState) {
+    if !state.recordable && state.record_listener.is_none() {
+        return;
+    }
+    let generation = cfi::patch_report_generation();
+    if CFI_REPORT_GENERATION.swap(generation, Ordering::Relaxed) == generation {
+        return;
+    }
+    let report = cfi::patch_report();
+    let mut recorded = CFI_REPORT_RECORDED.lock_or_poison();
+    for entry in &report {
+        if recorded.get(&entry.addr) == Some(&(entry.status, entry.ignored)) {
+            continue;
+        }
+        let (status_code, sym_name) = match entry.status {
+            CfiPatchStatus::Patched => (
+                Errno::Ok,
+                format!(
+                    "{}:{}",
+                    cfi_symbol_name(entry.kind),
+                    cfi_source_name(entry.source)
+                ),
+            ),
+            CfiPatchStatus::Failed(count) => (
+                Errno::InitErrCfi,
+                format!(
+                    "{}:{}:failed={}",
+                    cfi_symbol_name(entry.kind),
+                    cfi_source_name(entry.source),
+                    count
+                ),
+            ),
+        };
+        // 属于忽略模块、保持原样的地址
+        let sym_name = if entry.ignored {
+            sym_name + ":ignored"
+        } else {
+            sym_name
+        };
+        push_record(
+            state,
+            StoredRecord {
+                op: RecordOp::CfiPatch,
+                ts_ms: now_ms(),
+                status_code: status_code.as_i32(),
+                caller_lib_name: CALLER_LIB_UNKNOWN.to_string(),
+                lib_name: entry
+                    .module
+                    .clone()
+                    .unwrap_or_else(|| CALLER_LIB_UNKNOWN.to_string()),
+                sym_name,
+                new_addr: entry.addr,
+                observed_addr: None,
+                stub: 0,
+                module: None,
+            },
+        );
+    }
+    *recorded = report
+        .iter()
+        .map(|entry| (entry.addr, (entry.status, entry.ignored)))
+        .collect();
+}
+
+fn cfi_symbol_name(kind: CfiSymbolKind) -> &'static str {
+    match kind {
+        CfiSymbolKind::Slowpath => "__cfi_slowpath",
+        CfiSymbolKind::SlowpathDiag => "__cfi_slowpath_diag",
+    }
+}
+
+fn cfi_source_name(source: CfiAddrSource) -> &'static str {
+    match source {
+        CfiAddrSource::Dlsym => "dlsym",
+        CfiAddrSource::ElfExport => "elf_export",
+        CfiAddrSource::ImportSlot => "import_slot",
+    }
+}
+
+fn signal_name(signum: i32) -> String {
+    match signum {
+        libc::SIGSEGV => "SIGSEGV".to_string(),
+        libc::SIGBUS => "SIGBUS".to_string(),
+        libc::SIGILL => "SIGILL".to_string(),
+        libc::SIGTRAP => "SIGTRAP".to_string(),
+        _ => format!("SIG{}", signum),
+    }
+}
+
+fn add_stub_record(state: &mut CoreState, op: RecordOp, status_code: i32, stub: HookStub) {
+    push_record(
+        state,
+        StoredRecord {
+            op,
+            ts_ms: now_ms(),
+            status_code,
+            caller_lib_name: CALLER_LIB_UNKNOWN.to_string(),
             lib_name: String::new(),
             sym_name: String::new(),
             new_addr: 0,
+            observed_addr: None,
             stub,
+            module: None,
         },
     );
 }
@@ -77,11 +410,43 @@ fn op_name(op: RecordOp) -> &'static str {
     match op {
         RecordOp::Hook => "HOOK",
         RecordOp::Unhook => "UNHOOK",
+        RecordOp::Pause => "PAUSE",
+        RecordOp::Resume => "RESUME",
+        RecordOp::Rehook => "REHOOK",
+        RecordOp::Clear => "CLEAR",
+        RecordOp::GuardFault => "GUARD_FAULT",
+        RecordOp::CfiPatch => "CFI_PATCH",
+    }
+}
+
+// 地址反查函数，debug 开启时用于在 NEW_ADDR 后标注符号
+pub(super) type AddrResolver<'a> = &'a dyn Fn(usize) -> Option<ResolvedSymbol>;
+
+// 同一地址只反查一次，结果按地址缓存在本次输出内
+struct NewAddrNotes<'a> {
+    resolve: Option<AddrResolver<'a>>,
+    cache: BTreeMap<usize, Option<String>>,
+}
+
+impl NewAddrNotes<'_> {
+    // 形如 libfoo.so!my_puts+0x0，与调用链预览的 symbolized 写法一致
+    fn note(&mut self, addr: usize) -> Option<&str> {
+        let resolve = self.resolve.filter(|_| addr != 0)?;
+        self.cache
+            .entry(addr)
+            .or_insert_with(|| {
+                let symbol = resolve(addr)?;
+                let pathname = &symbol.module.pathname;
+                let module = pathname.rsplit('/').next().unwrap_or(pathname);
+                let offset = addr - symbol.addr;
+                Some(format!("{}!{}+0x{:x}", module, symbol.name, offset))
+            })
+            .as_deref()
     }
 }
 
 // 按 item_flags 位掩码选择性输出字段，CSV 格式
-fn format_entry(entry: &RecordEntry, item_flags: u32) -> String {
+fn format_entry(entry: &StoredRecord, item_flags: u32, notes: &mut NewAddrNotes<'_>) -> String {
     let mut line = String::new();
     if item_flags & RECORD_ITEM_TIMESTAMP != 0 {
         let _ = write!(line, "{},", entry.ts_ms);
@@ -92,14 +457,33 @@ fn format_entry(entry: &RecordEntry, item_flags: u32) -> String {
     if item_flags & RECORD_ITEM_OP != 0 {
         let _ = write!(line, "{},", op_name(entry.op));
     }
-    if item_flags & RECORD_ITEM_LIB_NAME != 0 {
-        let _ = write!(line, "{},", entry.lib_name);
+    // 模块实例以 caller 规则后缀接在 lib_name 后，可直接作为 hook_single 的 caller 规则
+    if item_flags & (RECORD_ITEM_LIB_NAME | RECORD_ITEM_INSTANCE | RECORD_ITEM_NAMESPACE) != 0 {
+        if item_flags & RECORD_ITEM_LIB_NAME != 0 {
+            line.push_str(&entry.lib_name);
+        }
+        if let Some(module) = entry.module {
+            if item_flags & RECORD_ITEM_INSTANCE != 0 {
+                let _ = write!(line, "@0x{:x}%0x{:x}", module.base_addr, module.instance_id);
+            }
+            if item_flags & RECORD_ITEM_NAMESPACE != 0 && module.namespace_id != 0 {
+                let _ = write!(line, "^0x{:x}", module.namespace_id);
+            }
+        }
+        line.push(',');
     }
     if item_flags & RECORD_ITEM_SYM_NAME != 0 {
         let _ = write!(line, "{},", entry.sym_name);
     }
     if item_flags & RECORD_ITEM_NEW_ADDR != 0 {
-        let _ = write!(line, "0x{:x},", entry.new_addr);
+        let _ = write!(line, "0x{:x}", entry.new_addr);
+        if let Some(note) = notes.note(entry.new_addr) {
+            let _ = write!(line, "({})", note);
+        }
+        if let Some(observed) = entry.observed_addr {
+            let _ = write!(line, "[observed:0x{:x}]", observed);
+        }
+        line.push(',');
     }
     if item_flags & RECORD_ITEM_ERRNO != 0 {
         let _ = write!(line, "{},", entry.status_code);
@@ -111,17 +495,60 @@ fn format_entry(entry: &RecordEntry, item_flags: u32) -> String {
     line
 }
 
-pub(super) fn get_records_text(state: &CoreState, item_flags: u32) -> Option<String> {
+// resolve 非空时在 NEW_ADDR 后以括号标注其所属符号
+pub(super) fn get_records_text(
+    state: &CoreState,
+    item_flags: u32,
+    resolve: Option<AddrResolver<'_>>,
+) -> Option<String> {
     if !state.recordable || state.records.is_empty() {
         return None;
     }
+    let mut notes = NewAddrNotes {
+        resolve: resolve.filter(|_| item_flags & RECORD_ITEM_NEW_ADDR != 0),
+        cache: BTreeMap::new(),
+    };
     let mut output = String::new();
     for entry in &state.records {
-        output.push_str(&format_entry(entry, item_flags));
+        output.push_str(&format_entry(entry, item_flags, &mut notes));
     }
     Some(output)
 }
 
+// 按 item_flags 只填充选中的字段，其余为 None
+fn to_entry(record: &StoredRecord, item_flags: u32) -> RecordEntry {
+    let pick = |flag: u32| item_flags & flag != 0;
+    let instance = record.module.filter(|_| pick(RECORD_ITEM_INSTANCE));
+    RecordEntry {
+        timestamp_ms: pick(RECORD_ITEM_TIMESTAMP).then_some(record.ts_ms),
+        caller_lib_name: pick(RECORD_ITEM_CALLER_LIB_NAME).then(|| record.caller_lib_name.clone()),
+        op: pick(RECORD_ITEM_OP).then_some(record.op),
+        lib_name: pick(RECORD_ITEM_LIB_NAME).then(|| record.lib_name.clone()),
+        sym_name: pick(RECORD_ITEM_SYM_NAME).then(|| record.sym_name.clone()),
+        new_addr: pick(RECORD_ITEM_NEW_ADDR).then_some(record.new_addr),
+        observed_addr: record.observed_addr.filter(|_| pick(RECORD_ITEM_NEW_ADDR)),
+        errno: pick(RECORD_ITEM_ERRNO).then_some(record.status_code),
+        stub: pick(RECORD_ITEM_STUB).then_some(record.stub),
+        caller_base_addr: instance.map(|module| module.base_addr),
+        caller_instance_id: instance.map(|module| module.instance_id),
+        caller_namespace_id: record
+            .module
+            .filter(|_| pick(RECORD_ITEM_NAMESPACE))
+            .map(|module| module.namespace_id),
+    }
+}
+
+pub(super) fn get_record_entries(state: &CoreState, item_flags: u32) -> Vec<RecordEntry> {
+    if !state.recordable {
+        return Vec::new();
+    }
+    state
+        .records
+        .iter()
+        .map(|record| to_entry(record, item_flags))
+        .collect()
+}
+
 // 循环写入直到全部字节落盘，处理 short write
 pub(super) fn dump_records_text(fd: i32, text: &str) -> Result<(), Errno> {
     if fd < 0 {
@@ -1,28 +1,39 @@
 // hook 刷新核心模块，负责模块扫描、任务匹配、GOT slot 写入与恢复
-use crate::api::HookStub;
+use crate::api::{ChainPreview, HookEventKind, HookStats, HookStub, SafeReadStats};
 use crate::errno::Errno;
 use crate::log;
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::ffi::c_void;
 
-use super::cfi;
 use super::hub;
-use super::rules::should_ignore;
-use super::state::{CoreState, HookedEntry, ModuleInfo};
+use super::rules::{ignored_module_keys, is_soname_rule, module_match, should_ignore};
+use super::state::{CoreState, HookEventEntry, HookedEntry, ModuleInfo, Task};
 use apply::apply_task_for_module;
+use env::{LiveEnv, RefreshEnv};
 use matcher::{
-    CalleeResolve, is_single_task_bound_to_other_module, is_task_match_caller, resolve_callee_addrs,
+    CalleeResolve, is_single_task_bound_to_other_module, is_task_match_caller,
+    newest_instance_targets, note_callee_candidates, resolve_callee_addrs,
 };
 use module_registry::{module_key, prune_dead_single_task_targets, prune_dead_slots};
 mod apply;
+mod env;
+mod fork_restore;
 mod matcher;
 mod module_registry;
 mod ops;
+mod pattern;
+mod preview;
+mod slot_guard;
+mod slot_write;
+#[cfg(test)]
+mod tests;
 
-// hook 操作完成后的回调事件，携带状态码和前一个函数地址
+// hook 生命周期回调事件，携带状态码和前一个函数地址
 pub(super) struct CallbackEvent {
-    pub(super) hooked: HookedEntry,
+    pub(super) kind: HookEventKind,
+    pub(super) hooked: Option<HookedEntry>,
+    pub(super) event_callback: Option<HookEventEntry>,
     pub(super) task_stub: HookStub,
     pub(super) status: Errno,
     pub(super) caller_path_name: String,
@@ -31,19 +42,186 @@ pub(super) struct CallbackEvent {
     pub(super) prev_func: usize,
 }
 
+impl CallbackEvent {
+    // HookedCallback 只接收挂载类事件，任务没有可接收该事件的回调时返回 None
+    pub(super) fn for_task(
+        task: &Task,
+        kind: HookEventKind,
+        status: Errno,
+        caller_path_name: &str,
+        prev_func: usize,
+    ) -> Option<Self> {
+        let hooked = task
+            .hooked
+            .filter(|_| matches!(kind, HookEventKind::Hooked | HookEventKind::Rehooked));
+        if hooked.is_none() && task.event_callback.is_none() {
+            return None;
+        }
+        Some(Self {
+            kind,
+            hooked,
+            event_callback: task.event_callback,
+            task_stub: task.stub,
+            status,
+            caller_path_name: caller_path_name.to_string(),
+            sym_name: task.sym_name.clone(),
+            new_func: task.new_func,
+            prev_func,
+        })
+    }
+}
+
 pub(super) fn refresh_all(state: &mut CoreState) -> (Errno, Vec<CallbackEvent>) {
-    refresh_internal(state, false, None)
+    let result = refresh_internal(&LiveEnv, state, false, None);
+    fork_restore::publish(&state.slots);
+    result
 }
 
 pub(super) fn refresh_new_modules(state: &mut CoreState) -> (Errno, Vec<CallbackEvent>) {
-    refresh_internal(state, true, None)
+    let result = refresh_internal(&LiveEnv, state, true, None);
+    fork_restore::publish(&state.slots);
+    result
 }
 
-pub(super) fn apply_new_task(
+pub(super) fn refresh_module(
     state: &mut CoreState,
-    task_stub: HookStub,
+    module: &ModuleInfo,
+) -> (Errno, Vec<CallbackEvent>) {
+    let result = refresh_module_with(&LiveEnv, state, module);
+    fork_restore::publish(&state.slots);
+    result
+}
+
+pub(super) fn refresh_unloaded_modules(
+    state: &mut CoreState,
+    modules: &[ModuleInfo],
 ) -> (Errno, Vec<CallbackEvent>) {
-    refresh_internal(state, false, Some(task_stub))
+    let result = refresh_unloaded_with(&LiveEnv, state, modules);
+    fork_restore::publish(&state.slots);
+    result
+}
+
+// 一次扫描应用一批新任务，返回每个任务的首个错误
+pub(super) fn apply_new_tasks(
+    state: &mut CoreState,
+    task_stubs: &[HookStub],
+) -> (BTreeMap<HookStub, Errno>, Vec<CallbackEvent>) {
+    let pass = refresh_pass(&LiveEnv, state, false, Some(task_stubs));
+    fork_restore::publish(&state.slots);
+    (pass.task_status, pass.events)
+}
+
+// 开启后在 slot 集合变化时维护 fork 子进程恢复表，关闭时撤下已发布的表
+pub(super) fn set_fork_child_restore(flag: bool) {
+    fork_restore::set_enabled(flag);
+}
+
+// 仅供 fork 子进程的 atfork 回调调用，不分配内存也不加锁
+pub(super) fn restore_slots_in_child() -> usize {
+    fork_restore::restore_in_child()
+}
+
+// 只读预览新 hook 将加入的调用链，不修改状态也不写入任何 slot
+pub(super) fn preview_chain(
+    state: &CoreState,
+    caller_rule: &str,
+    callee_rule: Option<&str>,
+    sym_name: &str,
+) -> Result<Vec<ChainPreview>, Errno> {
+    note_module_rules([caller_rule].into_iter().chain(callee_rule));
+    let modules = ops::enumerate_modules();
+    preview::preview_chain(
+        &LiveEnv,
+        state,
+        &modules,
+        caller_rule,
+        callee_rule,
+        sym_name,
+    )
+}
+
+// 规则中出现 soname: 时开启模块扫描的 SONAME 解析，clear 前保持开启
+pub(super) fn note_module_rules<'a>(rules: impl IntoIterator<Item = &'a str>) {
+    if rules.into_iter().any(is_soname_rule) {
+        ops::set_soname_scan(true);
+    }
+}
+
+pub(super) fn reset_soname_scan() {
+    ops::set_soname_scan(false);
+}
+
+pub(super) fn set_header_file_fallback(flag: bool) {
+    ops::set_header_file_fallback(flag);
+}
+
+pub(super) fn set_write_verify(flag: bool) {
+    ops::set_write_verify(flag);
+}
+
+pub(super) fn write_rejected_page_count() -> u64 {
+    slot_write::rejected_page_count()
+}
+
+pub(super) fn patch_verify_failure_count() -> u64 {
+    slot_write::verify_failure_count()
+}
+
+pub(super) fn mprotect_count() -> u64 {
+    crate::android::memory::mprotect_count()
+}
+
+pub(super) fn protect_restore_failure_count() -> u64 {
+    crate::android::memory::protect_restore_failure_count()
+}
+
+pub(super) fn safe_read_stats() -> SafeReadStats {
+    let (syscall_reads, syscall_faults, guard_reads) = crate::android::memory::safe_read_counts();
+    SafeReadStats {
+        syscall_reads,
+        syscall_faults,
+        guard_reads,
+    }
+}
+
+pub(super) fn set_slot_guard(flag: bool) {
+    slot_guard::set_enabled(flag);
+}
+
+pub(super) fn is_slot_guard_enabled() -> bool {
+    slot_guard::is_enabled()
+}
+
+pub(super) fn set_pattern_match_limit(limit: usize) {
+    pattern::set_match_limit(limit);
+}
+
+pub(super) fn pattern_match_limit() -> usize {
+    pattern::match_limit()
+}
+
+// 不扫描模块，只回读已挂载的 slot 并修复被改写的部分，供跳过刷新的周期巡检使用
+pub(super) fn verify_slots(state: &mut CoreState) -> (Errno, Vec<CallbackEvent>) {
+    let mut events = Vec::new();
+    let status = slot_guard::verify_slots(&LiveEnv, state, &mut events);
+    fork_restore::publish(&state.slots);
+    (status, events)
+}
+
+pub(super) fn set_scan_snapshot_capture(flag: bool) {
+    ops::set_scan_snapshot_capture(flag);
+}
+
+pub(super) fn scan_snapshot_text() -> String {
+    ops::scan_snapshot_text()
+}
+
+pub(super) fn dlinfo_available() -> bool {
+    ops::dlinfo_available()
+}
+
+pub(super) fn dladdr1_available() -> bool {
+    ops::dladdr1_available()
 }
 
 pub(super) fn module_epoch() -> Option<(u64, u64)> {
@@ -69,19 +247,130 @@ pub(super) fn module_identity_from_handle_with_symbol(
     ops::module_identity_from_handle_with_symbol(handle, probe_symbol)
 }
 
+pub(super) fn module_identity_from_addr(addr: *const c_void) -> Option<ModuleInfo> {
+    ops::module_identity_from_addr(addr)
+}
+
+// 按路径规则查找首个匹配的已加载模块
+pub(super) fn find_loaded_module(rule: &str) -> Option<ModuleInfo> {
+    note_module_rules([rule]);
+    ops::enumerate_modules().into_iter().find(|module| {
+        module_match(
+            &module.pathname,
+            module.soname.as_deref(),
+            module.base_addr,
+            module.instance_id,
+            module.namespace_id,
+            rule,
+        )
+    })
+}
+
+// 匹配规则的全部已加载模块，同名 so 的不同实例分别返回
+pub(super) fn find_loaded_modules(rule: &str) -> Vec<ModuleInfo> {
+    note_module_rules([rule]);
+    ops::enumerate_modules()
+        .into_iter()
+        .filter(|module| {
+            module_match(
+                &module.pathname,
+                module.soname.as_deref(),
+                module.base_addr,
+                module.instance_id,
+                module.namespace_id,
+                rule,
+            )
+        })
+        .collect()
+}
+
+// 当前命中 ignore 规则的已加载模块，无规则时不枚举
+pub(super) fn ignored_loaded_modules(ignores: &[String]) -> BTreeSet<(usize, usize)> {
+    if ignores.is_empty() {
+        return BTreeSet::new();
+    }
+    ignored_module_keys(&ops::enumerate_modules(), ignores)
+}
+
+pub(super) fn is_module_loaded(module: &ModuleInfo) -> bool {
+    ops::enumerate_modules().contains(module)
+}
+
+pub(super) fn open_module_elf(module: &ModuleInfo) -> Result<crate::elf::Elf, Errno> {
+    init_module_elf(module.base_addr, &module.pathname)
+}
+
+pub(super) fn init_module_elf(base_addr: usize, pathname: &str) -> Result<crate::elf::Elf, Errno> {
+    ops::init_elf_guard(base_addr, pathname)
+}
+
+pub(super) fn find_module_slots(
+    elf: &crate::elf::Elf,
+    symbol: &str,
+) -> Result<crate::elf::GotSlots, Errno> {
+    ops::find_slots_guard(elf, symbol, None)
+}
+
+pub(super) fn find_module_exports(
+    elf: &crate::elf::Elf,
+    symbol: &str,
+) -> Result<Vec<usize>, Errno> {
+    ops::find_exports_guard(elf, symbol)
+}
+
+pub(super) fn read_module_slot(addr: usize) -> Result<usize, Errno> {
+    ops::read_slot(addr)
+}
+
+pub(super) fn find_module_imports(elf: &crate::elf::Elf) -> Result<Vec<String>, Errno> {
+    ops::import_names_guard(elf, |_| true)
+}
+
+// 任务被移除前生成 Unhooked 事件：每个已挂载的 caller 模块一次，从未挂上任何 slot 时以空路径通知一次
+pub(super) fn unhook_events(state: &CoreState, task_stub: HookStub) -> Vec<CallbackEvent> {
+    let Some(task) = state.tasks.get(&task_stub) else {
+        return Vec::new();
+    };
+    let mut callers: BTreeSet<&str> = state
+        .task_slots
+        .get(&task_stub)
+        .map(|keys| {
+            keys.iter()
+                .map(|key| key.caller_path_name.as_str())
+                .collect()
+        })
+        .unwrap_or_default();
+    if callers.is_empty() {
+        callers.insert("");
+    }
+    callers
+        .into_iter()
+        .filter_map(|caller| {
+            CallbackEvent::for_task(task, HookEventKind::Unhooked, Errno::Ok, caller, 0)
+        })
+        .collect()
+}
+
 // 移除指定 task 的所有 GOT slot hook，无活跃 proxy 时销毁 hub
 pub(super) fn unhook_task(state: &mut CoreState, task_stub: HookStub) -> Errno {
+    let status = unhook_task_with(&LiveEnv, state, task_stub);
+    fork_restore::publish(&state.slots);
+    status
+}
+
+fn unhook_task_with<E: RefreshEnv>(env: &E, state: &mut CoreState, task_stub: HookStub) -> Errno {
     let slot_keys = match state.task_slots.remove(&task_stub) {
         Some(keys) => keys,
         None => return Errno::Ok,
     };
-    let target_func = state
+    let (target_func, paused) = state
         .tasks
         .get(&task_stub)
-        .map(|task| task.new_func)
+        .map(|task| (task.new_func, task.paused))
         .unwrap_or_default();
 
     let mut first_err = Errno::Ok;
+    env.begin_patch_batch();
     for key in slot_keys {
         let Some(slot) = state.slots.get_mut(&key) else {
             continue;
@@ -90,7 +379,7 @@ pub(super) fn unhook_task(state: &mut CoreState, task_stub: HookStub) -> Errno {
         slot.task_chain.retain(|stub| *stub != task_stub);
 
         if slot.hub_ptr == 0 {
-            if let Err(err) = ops::patch_slot(key.slot_addr, slot.orig_func, &key.caller_path_name)
+            if let Err(err) = env.patch_slot(key.slot_addr, slot.orig_func, &key.caller_path_name)
                 && first_err.is_ok()
             {
                 first_err = err;
@@ -101,19 +390,29 @@ pub(super) fn unhook_task(state: &mut CoreState, task_stub: HookStub) -> Errno {
             continue;
         }
 
+        // 先恢复本任务暂停的引用，del_proxy 才能按未暂停的引用计数移除
+        if paused {
+            hub::set_proxy_paused(slot.hub_ptr as *mut hub::Hub, target_func, false);
+        }
         let (_, have_enabled_proxy) = hub::del_proxy(slot.hub_ptr as *mut hub::Hub, target_func);
-        let target_addr = if have_enabled_proxy {
+        // 链尾接入了外来 hook 时保留 hub 作为直通层，slot 继续指向 trampoline；运行时退出时才交还
+        let foreign_tail = hub::has_foreign_tail(slot.hub_ptr as *mut hub::Hub);
+        let target_addr = if have_enabled_proxy || foreign_tail {
             hub::hub_trampo(slot.hub_ptr as *mut hub::Hub)
         } else {
             slot.orig_func
         };
 
-        if let Err(err) = ops::patch_slot(key.slot_addr, target_addr, &key.caller_path_name)
+        if let Err(err) = env.patch_slot(key.slot_addr, target_addr, &key.caller_path_name)
             && first_err.is_ok()
         {
             first_err = err;
         }
 
+        if foreign_tail {
+            continue;
+        }
+
         if !have_enabled_proxy {
             hub::destroy_hub(slot.hub_ptr as *mut hub::Hub, true);
             slot.hub_ptr = 0;
@@ -123,26 +422,205 @@ pub(super) fn unhook_task(state: &mut CoreState, task_stub: HookStub) -> Errno {
             state.slots.remove(&key);
         }
     }
+    env.finish_patch_batch();
 
     state.single_task_targets.remove(&task_stub);
+    state.callee_candidates.remove(&task_stub);
+    state.rehook_targets.retain(|(stub, _)| *stub != task_stub);
     first_err
 }
 
+// 切换任务的暂停状态：只翻转其 slot 所在 hub 中的 proxy 节点，GOT 与引用计数保持不变
+pub(super) fn set_task_paused(state: &mut CoreState, task_stub: HookStub, paused: bool) -> Errno {
+    let Some(task) = state.tasks.get_mut(&task_stub) else {
+        return Errno::InvalidArg;
+    };
+    if task.paused == paused {
+        return Errno::Ok;
+    }
+    task.paused = paused;
+    let target_func = task.new_func;
+
+    let Some(slot_keys) = state.task_slots.get(&task_stub) else {
+        return Errno::Ok;
+    };
+    let mut first_err = Errno::Ok;
+    for key in slot_keys {
+        let Some(slot) = state.slots.get(key) else {
+            continue;
+        };
+        if slot.hub_ptr == 0 {
+            continue;
+        }
+        let status = hub::set_proxy_paused(slot.hub_ptr as *mut hub::Hub, target_func, paused);
+        if status != Errno::Ok && first_err.is_ok() {
+            first_err = status;
+        }
+    }
+    first_err
+}
+
+// 开关任务的命中统计：统计块挂到其 slot 所在 hub 的 proxy 节点上，关闭时摘下并回收
+pub(super) fn set_task_hit_stats(state: &mut CoreState, task_stub: HookStub, enabled: bool) {
+    let stats = if enabled {
+        if state.hit_stats.contains_key(&task_stub) {
+            return;
+        }
+        let stats = hub::acquire_hit_stats();
+        state.hit_stats.insert(task_stub, stats);
+        stats
+    } else {
+        let Some(stats) = state.hit_stats.remove(&task_stub) else {
+            return;
+        };
+        stats
+    };
+
+    if let (Some(task), Some(slot_keys)) = (
+        state.tasks.get(&task_stub),
+        state.task_slots.get(&task_stub),
+    ) {
+        for key in slot_keys {
+            let Some(slot) = state.slots.get(key) else {
+                continue;
+            };
+            if slot.hub_ptr != 0 {
+                hub::set_proxy_hit_stats(
+                    slot.hub_ptr as *mut hub::Hub,
+                    task.new_func,
+                    stats,
+                    enabled,
+                );
+            }
+        }
+    }
+    if !enabled {
+        hub::release_hit_stats(stats);
+    }
+}
+
+// 任务的线程集合地址，限定了线程的任务在首次挂载时创建；未限定线程时返回 None
+// 只借用 tid_filters，挂载 slot 期间仍可持有 slots 中的条目
+pub(super) fn task_tid_filter(
+    tid_filters: &mut BTreeMap<HookStub, usize>,
+    task: &Task,
+) -> Option<usize> {
+    if task.threads.is_empty() {
+        return None;
+    }
+    Some(
+        *tid_filters
+            .entry(task.stub)
+            .or_insert_with(|| hub::create_tid_filter(&task.threads)),
+    )
+}
+
+// 替换已合并任务的线程集合：新集合直接挂到已绑定 slot 的 proxy 节点上，旧集合摘下后延迟释放
+// threads 为空时恢复对所有线程生效；任务不存在时返回 false
+pub(super) fn set_task_threads(
+    state: &mut CoreState,
+    task_stub: HookStub,
+    threads: Vec<i32>,
+) -> bool {
+    let Some(task) = state.tasks.get_mut(&task_stub) else {
+        return false;
+    };
+    task.threads = threads;
+    let new_func = task.new_func;
+    let filter = (!task.threads.is_empty()).then(|| hub::create_tid_filter(&task.threads));
+    let old = match filter {
+        Some(filter) => state.tid_filters.insert(task_stub, filter),
+        None => state.tid_filters.remove(&task_stub),
+    };
+    for hub_ptr in task_hub_ptrs(state, task_stub) {
+        match (filter, old) {
+            (Some(filter), _) => hub::set_proxy_tid_filter(hub_ptr, new_func, filter, true),
+            (None, Some(old)) => hub::set_proxy_tid_filter(hub_ptr, new_func, old, false),
+            (None, None) => {}
+        }
+    }
+    if let Some(old) = old {
+        hub::retire_tid_filter(old);
+    }
+    true
+}
+
+// 任务移除前摘下其线程集合
+pub(super) fn release_task_tid_filter(state: &mut CoreState, task_stub: HookStub) {
+    let Some(filter) = state.tid_filters.remove(&task_stub) else {
+        return;
+    };
+    if let Some(task) = state.tasks.get(&task_stub) {
+        for hub_ptr in task_hub_ptrs(state, task_stub) {
+            hub::set_proxy_tid_filter(hub_ptr, task.new_func, filter, false);
+        }
+    }
+    hub::retire_tid_filter(filter);
+}
+
+fn task_hub_ptrs(state: &CoreState, task_stub: HookStub) -> Vec<*mut hub::Hub> {
+    let Some(slot_keys) = state.task_slots.get(&task_stub) else {
+        return Vec::new();
+    };
+    slot_keys
+        .iter()
+        .filter_map(|key| state.slots.get(key))
+        .filter(|slot| slot.hub_ptr != 0)
+        .map(|slot| slot.hub_ptr as *mut hub::Hub)
+        .collect()
+}
+
+// 按 slot 顺序列出任务已绑定 slot 的原始函数，已卸载模块的 slot 在刷新时随 prune 移除
+pub(super) fn task_orig_funcs(state: &CoreState, task_stub: HookStub) -> Vec<(String, usize)> {
+    let Some(slot_keys) = state.task_slots.get(&task_stub) else {
+        return Vec::new();
+    };
+    slot_keys
+        .iter()
+        .filter_map(|key| {
+            let slot = state.slots.get(key)?;
+            (slot.orig_func != 0).then(|| (key.caller_path_name.clone(), slot.orig_func))
+        })
+        .collect()
+}
+
+pub(super) fn task_hit_stats(state: &CoreState, task_stub: HookStub) -> Option<HookStats> {
+    state
+        .hit_stats
+        .get(&task_stub)
+        .map(|stats| hub::hit_stats_snapshot(*stats))
+}
+
 // 恢复所有 GOT slot 为原始值并销毁全部 hub，用于进程 fork 后重建
 pub(super) fn restore_all(state: &mut CoreState) -> Errno {
+    let status = restore_all_with(&LiveEnv, state);
+    fork_restore::publish(&state.slots);
+    status
+}
+
+fn restore_all_with<E: RefreshEnv>(env: &E, state: &mut CoreState) -> Errno {
     let mut first_err = Errno::Ok;
     let slot_keys: Vec<_> = state.slots.keys().cloned().collect();
 
+    env.begin_patch_batch();
     for key in slot_keys {
         let Some(slot) = state.slots.get(&key) else {
             continue;
         };
-        if let Err(err) = ops::patch_slot(key.slot_addr, slot.orig_func, &key.caller_path_name)
+        // 接入了外来链尾的 slot 交还给外来 hook，运行时退出后不再留下指向本库的入口
+        let hub_ptr = slot.hub_ptr as *mut hub::Hub;
+        let restore = if hub::has_foreign_tail(hub_ptr) {
+            hub::hub_tail(hub_ptr)
+        } else {
+            slot.orig_func
+        };
+        if let Err(err) = env.patch_slot(key.slot_addr, restore, &key.caller_path_name)
             && first_err.is_ok()
         {
             first_err = err;
         }
     }
+    env.finish_patch_batch();
 
     for slot in state.slots.values_mut() {
         if slot.hub_ptr != 0 {
@@ -156,109 +634,304 @@ pub(super) fn restore_all(state: &mut CoreState) -> Errno {
     first_err
 }
 
-// 刷新核心流程：扫描模块 -> 清理失效 slot -> 匹配任务 -> 应用 hook
-fn refresh_internal(
+// 一轮刷新的结果：status 为首个错误，task_status 为各任务自己的首个错误
+struct RefreshPass {
+    status: Errno,
+    task_status: BTreeMap<HookStub, Errno>,
+    events: Vec<CallbackEvent>,
+}
+
+fn refresh_internal<E: RefreshEnv>(
+    env: &E,
     state: &mut CoreState,
     only_new: bool,
-    target_task: Option<HookStub>,
+    target_tasks: Option<&[HookStub]>,
 ) -> (Errno, Vec<CallbackEvent>) {
+    let mut pass = refresh_pass(env, state, only_new, target_tasks);
+    if slot_guard::is_enabled() {
+        let status = slot_guard::verify_slots(env, state, &mut pass.events);
+        if pass.status.is_ok() {
+            pass.status = status;
+        }
+    }
+    (pass.status, pass.events)
+}
+
+// 刷新核心流程：扫描模块 -> 清理失效 slot -> 匹配任务 -> 应用 hook
+fn refresh_pass<E: RefreshEnv>(
+    env: &E,
+    state: &mut CoreState,
+    only_new: bool,
+    target_tasks: Option<&[HookStub]>,
+) -> RefreshPass {
     hub::collect_retired(false);
-    let modules = ops::enumerate_modules();
-    cfi::retain_module_cfi_hook_state(&modules);
+    let modules = env.enumerate_modules();
     let mut module_keys = BTreeSet::new();
     for module in &modules {
         module_keys.insert(module_key(module));
     }
     let modules_changed = state.known_modules != module_keys;
-    if modules_changed {
-        let cfi_status = cfi::refresh_slowpath_patch();
-        if cfi_status != Errno::Ok {
-            log::warn(format_args!("refresh cfi patch status {:?}", cfi_status));
-        }
-    }
-    prune_dead_slots(state, &module_keys);
-    prune_dead_single_task_targets(state, &module_keys);
-
+    env.on_modules_scanned(&modules, modules_changed, &state.ignore_callers);
     let mut events = Vec::new();
-    let mut first_err = Errno::Ok;
+    let is_alive = |key: &str| module_keys.contains(key);
+    prune_dead_slots(state, is_alive, &mut events);
+    prune_dead_single_task_targets(state, is_alive);
+    slot_write::prune_dead_pages(&mut state.write_guard, is_alive);
+
+    let mut pass = RefreshPass {
+        status: Errno::Ok,
+        task_status: BTreeMap::new(),
+        events,
+    };
 
-    let task_list: Vec<HookStub> = match target_task {
-        Some(stub) => vec![stub],
+    let task_list: Vec<HookStub> = match target_tasks {
+        Some(stubs) => stubs.to_vec(),
         None => state.task_order.clone(),
     };
     log::debug(format_args!(
-        "refresh begin only_new={} target_task={} modules={} tasks={}",
+        "refresh begin only_new={} targeted={} modules={} tasks={}",
         only_new,
-        target_task.unwrap_or(0),
+        target_tasks.is_some(),
         modules.len(),
         task_list.len()
     ));
-    let mut callee_cache = BTreeMap::<HookStub, Result<CalleeResolve, Errno>>::new();
-    for task_stub in &task_list {
+    let callee_cache = resolve_callee_cache(env, state, &task_list, &modules);
+    let newest = newest_instance_targets(env, state, &task_list, &modules);
+
+    env.begin_patch_batch();
+    for module in &modules {
+        if only_new && state.known_modules.contains(&module_key(module)) {
+            continue;
+        }
+        apply_tasks_for_module(
+            env,
+            state,
+            module,
+            &task_list,
+            &callee_cache,
+            &newest,
+            &mut pass,
+        );
+    }
+    env.finish_patch_batch();
+    let hooked_callers: BTreeSet<(usize, usize)> = state
+        .slots
+        .keys()
+        .map(|key| (key.caller_base_addr, key.caller_instance_id))
+        .collect();
+    env.on_slots_settled(&modules, &hooked_callers);
+
+    state.known_modules = module_keys;
+    log::debug(format_args!(
+        "refresh end only_new={} targeted={} status={:?} events={} modules_changed={}",
+        only_new,
+        target_tasks.is_some(),
+        pass.status,
+        pass.events.len(),
+        modules_changed
+    ));
+    pass
+}
+
+// 只对单个新加载模块应用全部任务，不做全量扫描与失效 slot 清理
+// 仅当有任务带 callee 规则时才枚举模块解析导出地址
+fn refresh_module_with<E: RefreshEnv>(
+    env: &E,
+    state: &mut CoreState,
+    module: &ModuleInfo,
+) -> (Errno, Vec<CallbackEvent>) {
+    hub::collect_retired(false);
+    let task_list = state.task_order.clone();
+    let needs_callee = task_list.iter().any(|stub| {
+        state
+            .tasks
+            .get(stub)
+            .is_some_and(|task| task.callee_path_name.is_some())
+    });
+    let modules = if needs_callee {
+        env.enumerate_modules()
+    } else {
+        Vec::new()
+    };
+    let callee_cache = resolve_callee_cache(env, state, &task_list, &modules);
+    // 未枚举时不限定 NewestInstance：刚加载的模块即为最新实例
+    let newest = newest_instance_targets(env, state, &task_list, &modules);
+
+    let mut pass = RefreshPass {
+        status: Errno::Ok,
+        task_status: BTreeMap::new(),
+        events: Vec::new(),
+    };
+    env.begin_patch_batch();
+    apply_tasks_for_module(
+        env,
+        state,
+        module,
+        &task_list,
+        &callee_cache,
+        &newest,
+        &mut pass,
+    );
+    env.finish_patch_batch();
+    state.known_modules.insert(module_key(module));
+    log::debug(format_args!(
+        "refresh module {} status={:?} events={}",
+        module.pathname,
+        pass.status,
+        pass.events.len()
+    ));
+    (pass.status, pass.events)
+}
+
+// dlclose 后只清理被卸载模块的 slot，引用计数未归零仍在加载中的模块跳过
+// 原先绑定在这些模块上的 single 任务需要重新挑选目标，此时才枚举全部模块
+fn refresh_unloaded_with<E: RefreshEnv>(
+    env: &E,
+    state: &mut CoreState,
+    modules: &[ModuleInfo],
+) -> (Errno, Vec<CallbackEvent>) {
+    hub::collect_retired(false);
+    let unloaded: BTreeSet<String> = modules
+        .iter()
+        .filter(|module| env.is_module_unloading(module))
+        .map(module_key)
+        .collect();
+    let mut pass = RefreshPass {
+        status: Errno::Ok,
+        task_status: BTreeMap::new(),
+        events: Vec::new(),
+    };
+    if unloaded.is_empty() {
+        return (pass.status, pass.events);
+    }
+
+    let rebind_tasks: Vec<HookStub> = state
+        .single_task_targets
+        .iter()
+        .filter(|(_, key)| unloaded.contains(*key))
+        .map(|(stub, _)| *stub)
+        .collect();
+    let is_alive = |key: &str| !unloaded.contains(key);
+    prune_dead_slots(state, is_alive, &mut pass.events);
+    prune_dead_single_task_targets(state, is_alive);
+    slot_write::prune_dead_pages(&mut state.write_guard, is_alive);
+    state.known_modules.retain(|key| !unloaded.contains(key));
+
+    if !rebind_tasks.is_empty() {
+        let live_modules = env.enumerate_modules();
+        let callee_cache = resolve_callee_cache(env, state, &rebind_tasks, &live_modules);
+        let newest = newest_instance_targets(env, state, &rebind_tasks, &live_modules);
+        for module in &live_modules {
+            apply_tasks_for_module(
+                env,
+                state,
+                module,
+                &rebind_tasks,
+                &callee_cache,
+                &newest,
+                &mut pass,
+            );
+        }
+    }
+    log::debug(format_args!(
+        "refresh unloaded modules={} rebind={} status={:?} events={}",
+        unloaded.len(),
+        rebind_tasks.len(),
+        pass.status,
+        pass.events.len()
+    ));
+    (pass.status, pass.events)
+}
+
+fn resolve_callee_cache<E: RefreshEnv>(
+    env: &E,
+    state: &mut CoreState,
+    task_list: &[HookStub],
+    modules: &[ModuleInfo],
+) -> BTreeMap<HookStub, Result<CalleeResolve, Errno>> {
+    let mut callee_cache = BTreeMap::new();
+    for task_stub in task_list {
         let Some(task) = state.tasks.get(task_stub) else {
             continue;
         };
-        callee_cache.insert(*task_stub, resolve_callee_addrs(task, &modules));
+        callee_cache.insert(*task_stub, resolve_callee_addrs(env, task, modules));
     }
+    for (task_stub, callee) in &callee_cache {
+        if let Ok(callee) = callee {
+            note_callee_candidates(state, *task_stub, callee.candidates.len());
+        }
+    }
+    callee_cache
+}
 
-    for module in &modules {
-        if should_ignore(
-            &module.pathname,
-            module.base_addr,
-            module.instance_id,
-            module.namespace_id,
-            &state.ignore_callers,
-        ) {
+// 对一个 caller 模块依次应用任务列表，错误与事件累积到 pass
+fn apply_tasks_for_module<E: RefreshEnv>(
+    env: &E,
+    state: &mut CoreState,
+    module: &ModuleInfo,
+    task_list: &[HookStub],
+    callee_cache: &BTreeMap<HookStub, Result<CalleeResolve, Errno>>,
+    newest: &BTreeMap<HookStub, String>,
+    pass: &mut RefreshPass,
+) {
+    if should_ignore(
+        &module.pathname,
+        module.soname.as_deref(),
+        module.base_addr,
+        module.instance_id,
+        module.namespace_id,
+        &state.ignore_callers,
+    ) {
+        return;
+    }
+
+    for task_stub in task_list {
+        let Some(task) = state.tasks.get(task_stub).cloned() else {
+            continue;
+        };
+        if is_single_task_bound_to_other_module(state, &task, module, newest) {
             continue;
         }
+        let Some(callee) = callee_cache.get(task_stub) else {
+            continue;
+        };
+        let callee = match callee {
+            Ok(callee) => callee,
+            Err(err) => {
+                if pass.status.is_ok() {
+                    pass.status = *err;
+                }
+                pass.task_status.entry(*task_stub).or_insert(*err);
+                continue;
+            }
+        };
 
-        if only_new && state.known_modules.contains(&module_key(module)) {
+        if !is_task_match_caller(&task, module) {
             continue;
         }
 
-        for task_stub in &task_list {
-            let Some(task) = state.tasks.get(task_stub).cloned() else {
-                continue;
-            };
-            if is_single_task_bound_to_other_module(state, &task, module) {
-                continue;
+        let mut task_events = Vec::new();
+        let result = apply_task_for_module(env, state, &task, module, callee, &mut task_events);
+        pass.events.extend(task_events);
+        match result {
+            // 卸载中的模块不计为刷新失败，其余任务也不再尝试
+            Err(Errno::ModuleUnloading) => break,
+            // ELF 结构损坏（如 hash 链成环）时整个模块跳过，不影响其他模块的刷新结果
+            Err(Errno::Format) => {
+                log::warn(format_args!(
+                    "{} has malformed ELF tables, skipped",
+                    module.pathname
+                ));
+                pass.task_status.entry(*task_stub).or_insert(Errno::Format);
+                break;
             }
-            let Some(callee) = callee_cache.get(task_stub) else {
-                continue;
-            };
-            let callee = match callee {
-                Ok(callee) => callee,
-                Err(err) => {
-                    if first_err.is_ok() {
-                        first_err = *err;
-                    }
-                    continue;
+            Err(err) => {
+                if pass.status.is_ok() {
+                    pass.status = err;
                 }
-            };
-
-            if !is_task_match_caller(&task, module) {
-                continue;
+                pass.task_status.entry(*task_stub).or_insert(err);
             }
-
-            let mut task_events = Vec::new();
-            if let Err(err) =
-                apply_task_for_module(state, &task, module, callee, &mut task_events)
-                && first_err.is_ok()
-            {
-                first_err = err;
-            }
-            events.extend(task_events);
+            Ok(()) => {}
         }
     }
-
-    state.known_modules = module_keys;
-    log::debug(format_args!(
-        "refresh end only_new={} target_task={} status={:?} events={} modules_changed={}",
-        only_new,
-        target_task.unwrap_or(0),
-        first_err,
-        events.len(),
-        modules_changed
-    ));
-    (first_err, events)
 }
@@ -1,43 +1,190 @@
 // 单个模块的 hook 任务应用逻辑，完成 ELF 解析、CFI 处理、GOT slot 写入
-use crate::api::HookMode;
+use crate::api::{HookEventKind, HookMode, RecordOp, SingleBindPolicy};
 use crate::errno::Errno;
+use crate::log;
 use std::collections::BTreeSet;
 
-use super::super::cfi;
 use super::super::hub;
+use super::super::record;
+use super::super::rules::is_sym_pattern;
 use super::super::state::{CoreState, ModuleInfo, SlotEntry, SlotKey, Task, TaskType};
-use super::module_registry::module_key;
-use super::ops;
 use super::CallbackEvent;
+use super::env::RefreshEnv;
+use super::module_registry::module_key;
+use super::pattern;
+use super::slot_write;
 
-// 对指定 caller 模块应用 task：解析 ELF -> 确保 CFI hook -> 查找 GOT slot -> 创建 hub -> 写入
-pub(super) fn apply_task_for_module(
+// 对指定 caller 模块应用 task，失败或首次绑定 Single 任务前复核模块是否正被并发卸载
+// 卸载中的模块归类为 ModuleUnloading，不作为 Single 任务的绑定目标，交由下一轮刷新处理
+pub(super) fn apply_task_for_module<E: RefreshEnv>(
+    env: &E,
     state: &mut CoreState,
     task: &Task,
     caller: &ModuleInfo,
     callee: &super::matcher::CalleeResolve,
     events: &mut Vec<CallbackEvent>,
 ) -> Result<(), Errno> {
-    if task.callee_path_name.is_some() && callee.addrs.as_ref().is_some_and(BTreeSet::is_empty) {
+    match apply_task_slots(env, state, task, caller, callee, events) {
+        Ok(hooked_any) => {
+            let bind = hooked_any
+                && task.task_type == TaskType::Single
+                && task.bind_policy != SingleBindPolicy::AllMatches
+                && !state.single_task_targets.contains_key(&task.stub);
+            if !bind {
+                return Ok(());
+            }
+            if env.is_module_unloading(caller) {
+                return Err(report_unloading(state, task, caller, events));
+            }
+            state
+                .single_task_targets
+                .insert(task.stub, module_key(caller));
+            Ok(())
+        }
+        Err(err) if err != Errno::WriteRejected && env.is_module_unloading(caller) => {
+            Err(report_unloading(state, task, caller, events))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+fn report_unloading(
+    state: &mut CoreState,
+    task: &Task,
+    caller: &ModuleInfo,
+    events: &mut Vec<CallbackEvent>,
+) -> Errno {
+    log::info(format_args!(
+        "{} unloading while applying {}, left to next refresh",
+        caller.pathname, task.sym_name
+    ));
+    let status = Errno::ModuleUnloading;
+    let op = record_op(hook_event_kind(state, task, caller));
+    record::add_module_hook_record(
+        state,
+        op,
+        status.as_i32(),
+        caller,
+        &task.sym_name,
+        task.new_func,
+        task.stub,
+    );
+    emit_event(task, caller, status, 0, events);
+    status
+}
+
+// 解析 ELF -> 确保 CFI hook -> 查找 GOT slot -> 创建 hub -> 写入，返回是否有 slot 已挂上该任务
+fn apply_task_slots<E: RefreshEnv>(
+    env: &E,
+    state: &mut CoreState,
+    task: &Task,
+    caller: &ModuleInfo,
+    callee: &super::matcher::CalleeResolve,
+    events: &mut Vec<CallbackEvent>,
+) -> Result<bool, Errno> {
+    if is_sym_pattern(&task.sym_name) {
+        return pattern::apply_pattern_slots(env, state, task, caller, callee, events);
+    }
+    let expected = match callee.addrs_for_caller(task, caller) {
+        Ok(expected) => expected,
+        Err(err) => {
+            emit_event(task, caller, err, 0, events);
+            return Err(err);
+        }
+    };
+    if task.callee_path_name.is_some() && expected.as_ref().is_some_and(BTreeSet::is_empty) {
         emit_nosym_event(task, caller, events);
-        return Ok(());
+        return Ok(false);
     }
 
-    let elf = ops::init_elf_guard(caller.base_addr, &caller.pathname)?;
-    let cfi_status = cfi::ensure_module_cfi_hook(caller, &elf);
-    if cfi_status != Errno::Ok {
-        emit_event(task, caller, cfi_status, 0, events);
-        return Err(cfi_status);
+    let image = env.open(caller.base_addr, &caller.pathname)?;
+    let prepare_status = env.prepare_caller(caller, &image);
+    if prepare_status != Errno::Ok {
+        emit_event(task, caller, prepare_status, 0, events);
+        return Err(prepare_status);
     }
-    let got_slots = ops::find_slots_guard(&elf, &task.sym_name, callee.addrs.as_ref())?;
+    report_text_relocs(env.text_reloc_count(&image, &task.sym_name), task, caller);
+    let got_slots = match env.find_slots(&image, &task.sym_name, expected.as_ref()) {
+        Err(Errno::UnsupportedReloc) => {
+            report_unsupported_reloc(state, task, caller, events);
+            return Ok(false);
+        }
+        result => result?,
+    };
 
     if got_slots.is_empty() {
         emit_nosym_event(task, caller, events);
-        return Ok(());
+        return Ok(false);
+    }
+    log::debug(format_args!(
+        "{} in {}: call slots {}, data slots {}",
+        task.sym_name,
+        caller.pathname,
+        got_slots.plt.len(),
+        got_slots.data.len()
+    ));
+
+    let kind = hook_event_kind(state, task, caller);
+    let hooked_any = hook_slots(env, state, task, caller, got_slots.into_vec(), kind, events)?;
+    if hooked_any {
+        note_rehooked(state, task, caller);
+    }
+    Ok(hooked_any)
+}
+
+// 同一任务在同路径模块重新加载后再次挂载时以 Rehooked 上报
+pub(super) fn hook_event_kind(
+    state: &CoreState,
+    task: &Task,
+    caller: &ModuleInfo,
+) -> HookEventKind {
+    if state
+        .rehook_targets
+        .contains(&(task.stub, caller.pathname.clone()))
+    {
+        HookEventKind::Rehooked
+    } else {
+        HookEventKind::Hooked
+    }
+}
+
+// 重新挂载过程中写入的记录统一为 REHOOK，与首次绑定区分
+pub(super) fn record_op(kind: HookEventKind) -> RecordOp {
+    match kind {
+        HookEventKind::Rehooked => RecordOp::Rehook,
+        _ => RecordOp::Hook,
+    }
+}
+
+pub(super) fn note_rehooked(state: &mut CoreState, task: &Task, caller: &ModuleInfo) {
+    if state
+        .rehook_targets
+        .remove(&(task.stub, caller.pathname.clone()))
+    {
+        record::add_module_hook_record(
+            state,
+            RecordOp::Rehook,
+            Errno::Ok.as_i32(),
+            caller,
+            &task.sym_name,
+            task.new_func,
+            task.stub,
+        );
     }
+}
 
+// 把任务挂到给定的 GOT slot 上：按需创建 hub、加入 proxy 并写入 trampoline，返回是否有 slot 已挂上该任务
+pub(super) fn hook_slots<E: RefreshEnv>(
+    env: &E,
+    state: &mut CoreState,
+    task: &Task,
+    caller: &ModuleInfo,
+    slot_addrs: Vec<usize>,
+    kind: HookEventKind,
+    events: &mut Vec<CallbackEvent>,
+) -> Result<bool, Errno> {
     let mut hooked_any = false;
-    for slot_addr in got_slots {
+    for slot_addr in slot_addrs {
         let key = SlotKey {
             caller_path_name: caller.pathname.clone(),
             caller_base_addr: caller.base_addr,
@@ -45,9 +192,13 @@ pub(super) fn apply_task_for_module(
             caller_namespace_id: caller.namespace_id,
             slot_addr,
         };
+        if slot_write::is_rejected(&state.write_guard, &key) {
+            emit_event(task, caller, Errno::WriteRejected, 0, events);
+            continue;
+        }
 
         let slot = state.slots.entry(key.clone()).or_insert_with(|| SlotEntry {
-            orig_func: ops::read_slot(slot_addr).unwrap_or_default(),
+            orig_func: env.read_slot(slot_addr).unwrap_or_default(),
             task_chain: Vec::new(),
             hub_ptr: 0,
         });
@@ -56,62 +207,178 @@ pub(super) fn apply_task_for_module(
             continue;
         }
 
-        if slot.hub_ptr == 0 {
-            let hub_ptr = hub::create_hub(slot.orig_func)?;
+        // 新 hub 在写入 slot 前已挂好本任务的 proxy，已有 hub 才走加锁的 add_proxy
+        let fresh_hub = slot.hub_ptr == 0;
+        if fresh_hub {
+            let hub_ptr = hub::create_hub(slot.orig_func, task.new_func, task.priority)?;
             slot.hub_ptr = hub_ptr as usize;
         }
 
         let hub_ptr = slot.hub_ptr as *mut hub::Hub;
-        let prev_func = hub::first_enabled(hub_ptr);
+        let prev_func = if fresh_hub {
+            slot.orig_func
+        } else {
+            hub::first_enabled(hub_ptr)
+        };
 
         if state.init.mode == HookMode::Manual {
-            emit_event(task, caller, Errno::OrigAddr, prev_func, events);
+            emit_kind_event(task, caller, kind, Errno::OrigAddr, prev_func, events);
         }
 
-        let add_status = hub::add_proxy(hub_ptr, task.new_func);
-        if add_status != Errno::Ok && add_status != Errno::Dup {
-            return Err(add_status);
+        if !fresh_hub {
+            let add_status = hub::add_proxy(hub_ptr, task.new_func, task.priority);
+            if add_status != Errno::Ok && add_status != Errno::Dup {
+                return Err(add_status);
+            }
+        }
+        if task.paused {
+            let pause_status = hub::set_proxy_paused(hub_ptr, task.new_func, true);
+            if pause_status != Errno::Ok {
+                return Err(pause_status);
+            }
+        }
+        if let Some(&stats) = state.hit_stats.get(&task.stub) {
+            hub::set_proxy_hit_stats(hub_ptr, task.new_func, stats, true);
+        }
+        if let Some(filter) = super::task_tid_filter(&mut state.tid_filters, task) {
+            hub::set_proxy_tid_filter(hub_ptr, task.new_func, filter, true);
         }
 
-        ops::patch_slot(slot_addr, hub::hub_trampo(hub_ptr), &caller.pathname)?;
+        let write_result =
+            slot_write::write_slot(env, &mut state.write_guard, &key, hub::hub_trampo(hub_ptr));
+        if let Err(err) = write_result {
+            match err {
+                Errno::WriteRejected => {
+                    discard_unwritten_slot(state, &key);
+                    record::add_module_hook_record(
+                        state,
+                        record_op(kind),
+                        err.as_i32(),
+                        caller,
+                        &task.sym_name,
+                        task.new_func,
+                        task.stub,
+                    );
+                    emit_event(task, caller, err, prev_func, events);
+                }
+                // 记录期望写入的 trampoline 与实际回读到的值，便于定位别名映射
+                Errno::PatchVerifyFailed => {
+                    let expected = hub::hub_trampo(hub_ptr);
+                    let observed = env.read_slot(slot_addr).unwrap_or_default();
+                    discard_unwritten_slot(state, &key);
+                    record::add_verify_failed_record(
+                        state,
+                        caller,
+                        &task.sym_name,
+                        expected,
+                        observed,
+                        task.stub,
+                    );
+                    emit_event(task, caller, err, prev_func, events);
+                }
+                _ => {}
+            }
+            return Err(err);
+        }
         slot.task_chain.push(task.stub);
         state.task_slots.entry(task.stub).or_default().insert(key);
         hooked_any = true;
-        emit_event(task, caller, Errno::Ok, prev_func, events);
+        emit_kind_event(task, caller, kind, Errno::Ok, prev_func, events);
+    }
+    Ok(hooked_any)
+}
+
+// 写入被拒绝的新 slot 从未生效，撤销其 hub 与状态记录
+fn discard_unwritten_slot(state: &mut CoreState, key: &SlotKey) {
+    let Some(slot) = state.slots.get(key) else {
+        return;
+    };
+    if !slot.task_chain.is_empty() {
+        return;
     }
+    if slot.hub_ptr != 0 {
+        hub::destroy_hub(slot.hub_ptr as *mut hub::Hub, true);
+    }
+    state.slots.remove(key);
+}
 
-    if hooked_any && task.task_type == TaskType::Single {
-        state
-            .single_task_targets
-            .entry(task.stub)
-            .or_insert_with(|| module_key(caller));
+// DT_TEXTREL 模块中经 .text 绝对地址发起的调用不经过 GOT，仅做部分覆盖，输出盲区数量
+pub(super) fn report_text_relocs(count: usize, task: &Task, caller: &ModuleInfo) {
+    if count == 0 {
+        return;
     }
+    let action = if cfg!(feature = "textrel-patch") {
+        "patched"
+    } else {
+        "skipped"
+    };
+    log::warn(format_args!(
+        "text-relocated: partial coverage, {} {} text relocation(s) of {} in {}",
+        action, count, task.sym_name, caller.pathname
+    ));
+}
 
-    Ok(())
+// 符号只经 IRELATIVE / TLS 重定位引用，没有可挂载的 slot；写入记录说明未挂上的原因，不计为刷新失败
+fn report_unsupported_reloc(
+    state: &mut CoreState,
+    task: &Task,
+    caller: &ModuleInfo,
+    events: &mut Vec<CallbackEvent>,
+) {
+    log::info(format_args!(
+        "{} in {} only has unsupported relocations, not hooked",
+        task.sym_name, caller.pathname
+    ));
+    let status = Errno::UnsupportedReloc;
+    let op = record_op(hook_event_kind(state, task, caller));
+    record::add_module_hook_record(
+        state,
+        op,
+        status.as_i32(),
+        caller,
+        &task.sym_name,
+        task.new_func,
+        task.stub,
+    );
+    emit_event(task, caller, status, 0, events);
 }
 
-fn emit_nosym_event(task: &Task, caller: &ModuleInfo, events: &mut Vec<CallbackEvent>) {
+pub(super) fn emit_nosym_event(task: &Task, caller: &ModuleInfo, events: &mut Vec<CallbackEvent>) {
     if task.task_type == TaskType::Single {
         emit_event(task, caller, Errno::NoSym, 0, events);
     }
 }
 
-fn emit_event(
+pub(super) fn emit_event(
     task: &Task,
     caller: &ModuleInfo,
     status: Errno,
     prev_func: usize,
     events: &mut Vec<CallbackEvent>,
 ) {
-    if let Some(hooked) = task.hooked {
-        events.push(CallbackEvent {
-            hooked,
-            task_stub: task.stub,
-            status,
-            caller_path_name: caller.pathname.clone(),
-            sym_name: task.sym_name.clone(),
-            new_func: task.new_func,
-            prev_func,
-        });
-    }
+    emit_kind_event(
+        task,
+        caller,
+        HookEventKind::Hooked,
+        status,
+        prev_func,
+        events,
+    );
+}
+
+fn emit_kind_event(
+    task: &Task,
+    caller: &ModuleInfo,
+    kind: HookEventKind,
+    status: Errno,
+    prev_func: usize,
+    events: &mut Vec<CallbackEvent>,
+) {
+    events.extend(CallbackEvent::for_task(
+        task,
+        kind,
+        status,
+        &caller.pathname,
+        prev_func,
+    ));
 }
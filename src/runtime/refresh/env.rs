@@ -0,0 +1,186 @@
+// 刷新流程依赖的外部环境抽象：模块枚举、GOT slot 读写、ELF 查询
+// 生产环境使用 LiveEnv 直接访问当前进程，测试中以内存假实现替换
+use crate::elf::{self, GotSlots};
+use crate::errno::Errno;
+use std::collections::BTreeSet;
+
+use super::super::cfi;
+use super::super::rules;
+use super::super::state::ModuleInfo;
+use super::ops;
+
+pub(super) trait ModuleProvider {
+    fn enumerate_modules(&self) -> Vec<ModuleInfo>;
+
+    // 模块列表确定后的附加处理，如 CFI 状态同步；ignores 为当前的 ignore 规则
+    fn on_modules_scanned(
+        &self,
+        _modules: &[ModuleInfo],
+        _modules_changed: bool,
+        _ignores: &[String],
+    ) {
+    }
+
+    // 全量刷新写入结束后调用，hooked 为仍有活动 slot 的模块 (base_addr, instance_id)
+    fn on_slots_settled(&self, _modules: &[ModuleInfo], _hooked: &BTreeSet<(usize, usize)>) {}
+
+    // 实例被观察到的先后序号，越大越新；无记录时返回 None，由调用方按枚举顺序判断
+    fn observation_order(&self, _module: &ModuleInfo) -> Option<u64> {
+        None
+    }
+
+    // 枚举快照中的模块此刻是否已被卸载（或正在卸载）
+    fn is_module_unloading(&self, _module: &ModuleInfo) -> bool {
+        false
+    }
+
+    // 地址所属模块与最近符号，形如 libfoo.so!sym+0x10
+    fn symbolize(&self, _addr: usize) -> Option<String> {
+        None
+    }
+
+    // 地址是否仍位于已映射的可执行内存，用于判断接入的外来链尾是否已被卸载
+    fn is_code_mapped(&self, _addr: usize) -> bool {
+        true
+    }
+}
+
+pub(super) trait SlotPatcher {
+    fn read_slot(&self, addr: usize) -> Result<usize, Errno>;
+    fn patch_slot(&self, addr: usize, value: usize, pathname: &str) -> Result<(), Errno>;
+
+    // 批次内同页的写入只切换一次保护，finish 时统一恢复原始保护
+    fn begin_patch_batch(&self) {}
+    fn finish_patch_batch(&self) {}
+}
+
+pub(super) trait ElfReader {
+    type Image;
+
+    fn open(&self, base_addr: usize, pathname: &str) -> Result<Self::Image, Errno>;
+    // 同名导出的全部定义地址，全局定义在前、弱定义在后
+    fn find_exports(&self, image: &Self::Image, symbol: &str) -> Result<Vec<usize>, Errno>;
+    fn find_slots(
+        &self,
+        image: &Self::Image,
+        symbol: &str,
+        callee_addrs: Option<&BTreeSet<usize>>,
+    ) -> Result<GotSlots, Errno>;
+
+    // caller 重定位引用的符号中匹配通配模式的名称，按名称排序去重
+    fn find_import_names(&self, image: &Self::Image, pattern: &str) -> Result<Vec<String>, Errno>;
+
+    // 写入 caller 模块的 slot 前的准备，如安装模块级 CFI hook
+    fn prepare_caller(&self, _caller: &ModuleInfo, _image: &Self::Image) -> Errno {
+        Errno::Ok
+    }
+
+    // 经 .text 绝对地址引用该符号、无法通过 GOT 覆盖的调用点数量
+    fn text_reloc_count(&self, _image: &Self::Image, _symbol: &str) -> usize {
+        0
+    }
+}
+
+pub(super) trait RefreshEnv: ModuleProvider + SlotPatcher + ElfReader {}
+
+impl<T: ModuleProvider + SlotPatcher + ElfReader> RefreshEnv for T {}
+
+// 当前进程的真实环境
+pub(super) struct LiveEnv;
+
+impl ModuleProvider for LiveEnv {
+    fn enumerate_modules(&self) -> Vec<ModuleInfo> {
+        let modules = ops::enumerate_modules_for_refresh();
+        cfi::retain_module_cfi_hook_state(&modules, None);
+        modules
+    }
+
+    fn on_modules_scanned(
+        &self,
+        modules: &[ModuleInfo],
+        modules_changed: bool,
+        ignores: &[String],
+    ) {
+        let ignored = rules::ignored_module_keys(modules, ignores);
+        if modules_changed || cfi::has_pending_slowpath_scan(&ignored) {
+            let cfi_status = cfi::refresh_slowpath_patch(&ignored);
+            if cfi_status != Errno::Ok {
+                crate::log::warn(format_args!("refresh cfi patch status {:?}", cfi_status));
+            }
+        }
+    }
+
+    fn on_slots_settled(&self, modules: &[ModuleInfo], hooked: &BTreeSet<(usize, usize)>) {
+        cfi::retain_module_cfi_hook_state(modules, Some(hooked));
+    }
+
+    fn observation_order(&self, module: &ModuleInfo) -> Option<u64> {
+        ops::instance_observation_order(module)
+    }
+
+    fn is_module_unloading(&self, module: &ModuleInfo) -> bool {
+        !ops::is_module_present(module)
+    }
+
+    fn symbolize(&self, addr: usize) -> Option<String> {
+        ops::symbolize(addr)
+    }
+
+    fn is_code_mapped(&self, addr: usize) -> bool {
+        ops::is_code_mapped(addr)
+    }
+}
+
+impl SlotPatcher for LiveEnv {
+    fn read_slot(&self, addr: usize) -> Result<usize, Errno> {
+        ops::read_slot(addr)
+    }
+
+    fn patch_slot(&self, addr: usize, value: usize, pathname: &str) -> Result<(), Errno> {
+        ops::patch_slot(addr, value, pathname)
+    }
+
+    fn begin_patch_batch(&self) {
+        ops::begin_patch_batch();
+    }
+
+    fn finish_patch_batch(&self) {
+        ops::finish_patch_batch();
+    }
+}
+
+impl ElfReader for LiveEnv {
+    type Image = elf::Elf;
+
+    fn open(&self, base_addr: usize, pathname: &str) -> Result<elf::Elf, Errno> {
+        ops::init_elf_guard(base_addr, pathname)
+    }
+
+    fn find_exports(&self, image: &elf::Elf, symbol: &str) -> Result<Vec<usize>, Errno> {
+        ops::find_exports_guard(image, symbol)
+    }
+
+    fn find_slots(
+        &self,
+        image: &elf::Elf,
+        symbol: &str,
+        callee_addrs: Option<&BTreeSet<usize>>,
+    ) -> Result<GotSlots, Errno> {
+        ops::find_slots_guard(image, symbol, callee_addrs)
+    }
+
+    fn find_import_names(&self, image: &elf::Elf, pattern: &str) -> Result<Vec<String>, Errno> {
+        ops::import_names_guard(image, |name| rules::sym_pattern_match(pattern, name))
+    }
+
+    fn prepare_caller(&self, caller: &ModuleInfo, image: &elf::Elf) -> Errno {
+        cfi::ensure_module_cfi_hook(caller, image)
+    }
+
+    fn text_reloc_count(&self, image: &elf::Elf, symbol: &str) -> usize {
+        if !image.has_text_relocs() {
+            return 0;
+        }
+        ops::text_reloc_count_guard(image, symbol).unwrap_or(0)
+    }
+}
@@ -0,0 +1,102 @@
+// fork 子进程恢复 GOT 所用的扁平表：每个已改写 slot 的地址、原始值与所在页的保护
+// 父进程在 slot 集合变化后重建并发布；子进程 atfork 回调只读已发布的表，不加锁也不分配内存
+use crate::android::memory;
+use crate::runtime::state::MutexPoisonRecover;
+use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+use std::ptr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+
+use super::super::state::{SlotEntry, SlotKey};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct ForkSlot {
+    addr: usize,
+    orig: usize,
+    prot: u32,
+}
+
+type Table = Box<[ForkSlot]>;
+
+#[derive(Default)]
+struct Publisher {
+    // 上次发布时的 (slot 地址, 原始值)，未变化时跳过重建
+    pairs: Vec<(usize, usize)>,
+    // 页起始地址 -> 保护，只查询新出现的页
+    page_prots: BTreeMap<usize, u32>,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TABLE: AtomicPtr<Table> = AtomicPtr::new(ptr::null_mut());
+static PUBLISHER: Lazy<Mutex<Publisher>> = Lazy::new(|| Mutex::new(Publisher::default()));
+
+// 关闭时立即撤下已发布的表
+pub(super) fn set_enabled(flag: bool) {
+    ENABLED.store(flag, Ordering::Release);
+    if !flag {
+        let mut publisher = PUBLISHER.lock_or_poison();
+        *publisher = Publisher::default();
+        swap_table(None);
+    }
+}
+
+pub(super) fn publish(slots: &BTreeMap<SlotKey, SlotEntry>) {
+    if !ENABLED.load(Ordering::Acquire) {
+        return;
+    }
+    let pairs: Vec<(usize, usize)> = slots
+        .iter()
+        .map(|(key, slot)| (key.slot_addr, slot.orig_func))
+        .collect();
+    let mut publisher = PUBLISHER.lock_or_poison();
+    if publisher.pairs == pairs {
+        return;
+    }
+
+    let mut page_prots = BTreeMap::new();
+    let mut table = Vec::with_capacity(pairs.len());
+    for (key, slot) in slots {
+        let page = memory::page_start(key.slot_addr);
+        let prot = match publisher.page_prots.get(&page) {
+            Some(prot) => Some(*prot),
+            None => memory::get_addr_protect(key.slot_addr, Some(&key.caller_path_name)).ok(),
+        };
+        // 查不到保护的页无法安全写入，不进入表
+        let Some(prot) = prot else {
+            continue;
+        };
+        page_prots.insert(page, prot);
+        table.push(ForkSlot {
+            addr: key.slot_addr,
+            orig: slot.orig_func,
+            prot,
+        });
+    }
+    publisher.pairs = pairs;
+    publisher.page_prots = page_prots;
+    swap_table(Some(table.into_boxed_slice()));
+}
+
+// 旧表在父进程内释放；子进程看到的始终是 fork 时刻已发布的有效指针
+fn swap_table(table: Option<Table>) {
+    let next = table.map_or(ptr::null_mut(), |table| Box::into_raw(Box::new(table)));
+    let old = TABLE.swap(next, Ordering::AcqRel);
+    if !old.is_null() {
+        unsafe {
+            drop(Box::from_raw(old));
+        }
+    }
+}
+
+// 仅在 fork 子进程的 atfork 回调中调用：逐个写回原始值，返回成功写入的 slot 数
+pub(super) fn restore_in_child() -> usize {
+    let table = TABLE.load(Ordering::Acquire);
+    if table.is_null() {
+        return 0;
+    }
+    unsafe { &*table }
+        .iter()
+        .filter(|slot| memory::write_usize_raw(slot.addr, slot.orig, slot.prot))
+        .count()
+}
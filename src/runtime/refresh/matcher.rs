@@ -1,29 +1,89 @@
 // hook 任务与模块的匹配逻辑，包括 callee 地址解析和 caller 过滤
+use crate::android::memory;
+use crate::api::{CalleeSelection, HookStub, SingleBindPolicy};
 use crate::errno::Errno;
-use std::collections::BTreeSet;
+use crate::log;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ffi::{CString, c_void};
 
+use super::env::RefreshEnv;
 use super::module_registry::module_key;
-use super::ops;
 use super::super::callback_ctx;
-use super::super::rules::module_match;
-use super::super::state::{CoreState, ModuleInfo, Task, TaskType};
+use super::super::record;
+use super::super::rules::{is_sym_pattern, module_match, module_match_any, should_ignore};
+use super::super::state::{CallerFilter, CoreState, ModuleInfo, Task, TaskType};
 
 // callee 符号地址解析结果，None 表示不限定 callee
 pub(super) struct CalleeResolve {
     pub(super) addrs: Option<BTreeSet<usize>>,
+    // 匹配 callee 规则且导出该符号的实例：(namespace_id, 该实例的全部定义地址)
+    pub(super) candidates: Vec<(usize, Vec<usize>)>,
+    // 通配任务匹配 callee 规则的模块，导出地址随具体符号名变化，应用时逐个解析
+    pub(super) modules: Vec<ModuleInfo>,
+}
+
+impl CalleeResolve {
+    // 同一规则匹配到多个导出实例，如 APEX bionic 与 vendor 副本并存
+    pub(super) fn is_ambiguous(&self) -> bool {
+        self.candidates.len() > 1
+    }
+
+    // 按任务的 callee 选择策略确定该 caller 可接受的导出地址
+    pub(super) fn addrs_for_caller(
+        &self,
+        task: &Task,
+        caller: &ModuleInfo,
+    ) -> Result<Option<BTreeSet<usize>>, Errno> {
+        let Some(addrs) = self.addrs.as_ref() else {
+            return Ok(None);
+        };
+        if !self.is_ambiguous() {
+            return Ok(Some(addrs.clone()));
+        }
+        match task.callee_selection {
+            CalleeSelection::All => Ok(Some(addrs.clone())),
+            CalleeSelection::Error => Err(Errno::AmbiguousCallee),
+            CalleeSelection::CallerNamespace => {
+                let same_namespace: BTreeSet<usize> = self
+                    .candidates
+                    .iter()
+                    .filter(|(namespace_id, _)| {
+                        *namespace_id != 0 && *namespace_id == caller.namespace_id
+                    })
+                    .flat_map(|(_, addrs)| addrs.iter().copied())
+                    .collect();
+                if same_namespace.is_empty() {
+                    Ok(Some(addrs.clone()))
+                } else {
+                    Ok(Some(same_namespace))
+                }
+            }
+        }
+    }
 }
 
 // 遍历所有模块查找 callee 导出符号地址，用于 GOT slot 精确匹配
-pub(super) fn resolve_callee_addrs(task: &Task, modules: &[ModuleInfo]) -> Result<CalleeResolve, Errno> {
+pub(super) fn resolve_callee_addrs<E: RefreshEnv>(
+    env: &E,
+    task: &Task,
+    modules: &[ModuleInfo],
+) -> Result<CalleeResolve, Errno> {
     let Some(callee_path_name) = task.callee_path_name.as_deref() else {
-        return Ok(CalleeResolve { addrs: None });
+        return Ok(CalleeResolve {
+            addrs: None,
+            candidates: Vec::new(),
+            modules: Vec::new(),
+        });
     };
 
+    let is_pattern = is_sym_pattern(&task.sym_name);
     let mut addrs = BTreeSet::new();
+    let mut candidates = Vec::new();
+    let mut callee_modules = Vec::new();
     for module in modules {
         if !module_match(
             &module.pathname,
+            module.soname.as_deref(),
             module.base_addr,
             module.instance_id,
             module.namespace_id,
@@ -31,12 +91,57 @@ pub(super) fn resolve_callee_addrs(task: &Task, modules: &[ModuleInfo]) -> Resul
         ) {
             continue;
         }
-        let elf = ops::init_elf_guard(module.base_addr, &module.pathname)?;
-        if let Some(addr) = ops::find_export_guard(&elf, &task.sym_name)? {
-            addrs.insert(addr);
+        if is_pattern {
+            callee_modules.push(module.clone());
+            continue;
+        }
+        let image = env.open(module.base_addr, &module.pathname)?;
+        // 弱定义与全局定义都可能是 linker 实际绑定的地址，均作为可接受的 slot 值
+        // 统一去掉 PAC 签名与地址标签，与 slot 值比较时不受其差异影响
+        let exports: Vec<usize> = env
+            .find_exports(&image, &task.sym_name)?
+            .into_iter()
+            .map(memory::canonical_code_addr)
+            .collect();
+        if !exports.is_empty() {
+            addrs.extend(exports.iter().copied());
+            candidates.push((module.namespace_id, exports));
         }
     }
-    Ok(CalleeResolve { addrs: Some(addrs) })
+    Ok(CalleeResolve {
+        addrs: Some(addrs),
+        candidates,
+        modules: callee_modules,
+    })
+}
+
+// 记录 callee 规则匹配到多个导出实例的任务，实例数变化时输出一次说明
+pub(super) fn note_callee_candidates(state: &mut CoreState, task_stub: HookStub, count: usize) {
+    if count <= 1 {
+        state.callee_candidates.remove(&task_stub);
+        return;
+    }
+    if state.callee_candidates.insert(task_stub, count) == Some(count) {
+        return;
+    }
+    let Some(task) = state.tasks.get(&task_stub) else {
+        return;
+    };
+    let callee_rule = task.callee_path_name.clone().unwrap_or_default();
+    let (sym_name, new_func) = (task.sym_name.clone(), task.new_func);
+    log::info(format_args!(
+        "callee rule {} for {} matches {} instances, selection {:?}",
+        callee_rule, sym_name, count, task.callee_selection
+    ));
+    // 记录中以 AmbiguousCallee 标记多实例匹配，是否拒绝由任务策略决定
+    record::add_hook_record(
+        state,
+        Errno::AmbiguousCallee.as_i32(),
+        &callee_rule,
+        &sym_name,
+        new_func,
+        task_stub,
+    );
 }
 
 pub(super) fn is_task_match_caller(task: &Task, caller: &ModuleInfo) -> bool {
@@ -47,6 +152,7 @@ pub(super) fn is_task_match_caller(task: &Task, caller: &ModuleInfo) -> bool {
             .map(|name| {
                 module_match(
                     &caller.pathname,
+                    caller.soname.as_deref(),
                     caller.base_addr,
                     caller.instance_id,
                     caller.namespace_id,
@@ -55,31 +161,94 @@ pub(super) fn is_task_match_caller(task: &Task, caller: &ModuleInfo) -> bool {
             })
             .unwrap_or(false),
         TaskType::Partial => {
-            let Some(filter) = task.caller_allow_filter else {
+            let Some(entry) = task.caller_allow_filter else {
                 return false;
             };
             let Ok(caller_cstr) = CString::new(caller.pathname.as_str()) else {
                 return false;
             };
+            let arg = entry.arg as *mut c_void;
             callback_ctx::run_in_external_callback(|| unsafe {
-                (filter.filter)(caller_cstr.as_ptr(), filter.arg as *mut c_void)
+                match entry.filter {
+                    CallerFilter::Path(filter) => filter(caller_cstr.as_ptr(), arg),
+                    CallerFilter::Instance(filter) => filter(
+                        caller_cstr.as_ptr(),
+                        caller.base_addr,
+                        caller.instance_id,
+                        caller.namespace_id,
+                        arg,
+                    ),
+                }
             })
         }
-        TaskType::All => true,
+        TaskType::All => {
+            task.caller_allow_list.is_empty()
+                || module_match_any(
+                    &caller.pathname,
+                    caller.soname.as_deref(),
+                    caller.base_addr,
+                    caller.instance_id,
+                    caller.namespace_id,
+                    &task.caller_allow_list,
+                )
+        }
     }
 }
 
 // Single 任务已绑定到特定模块时，跳过其他模块避免重复 hook
+// AllMatches 从不绑定；尚未绑定的 NewestInstance 任务只接受 newest 中为其选出的实例
 pub(super) fn is_single_task_bound_to_other_module(
     state: &CoreState,
     task: &Task,
     caller: &ModuleInfo,
+    newest: &BTreeMap<HookStub, String>,
 ) -> bool {
-    if task.task_type != TaskType::Single {
+    if task.task_type != TaskType::Single || task.bind_policy == SingleBindPolicy::AllMatches {
         return false;
     }
-    let Some(target_key) = state.single_task_targets.get(&task.stub) else {
-        return false;
-    };
-    *target_key != module_key(caller)
+    state
+        .single_task_targets
+        .get(&task.stub)
+        .or_else(|| newest.get(&task.stub))
+        .is_some_and(|target_key| *target_key != module_key(caller))
+}
+
+// 为尚未绑定的 NewestInstance 任务在本轮模块列表中选出最新的匹配实例，忽略列表中的模块不参选
+// 有观察序号的实例按序号比较且晚于未被观察到的实例，序号相同或缺失时按枚举（加载）顺序
+pub(super) fn newest_instance_targets<E: RefreshEnv>(
+    env: &E,
+    state: &CoreState,
+    task_list: &[HookStub],
+    modules: &[ModuleInfo],
+) -> BTreeMap<HookStub, String> {
+    let mut targets = BTreeMap::new();
+    for stub in task_list {
+        let Some(task) = state.tasks.get(stub) else {
+            continue;
+        };
+        if task.bind_policy != SingleBindPolicy::NewestInstance
+            || state.single_task_targets.contains_key(stub)
+        {
+            continue;
+        }
+        let newest = modules
+            .iter()
+            .enumerate()
+            .filter(|(_, module)| {
+                is_task_match_caller(task, module)
+                    && !should_ignore(
+                        &module.pathname,
+                        module.soname.as_deref(),
+                        module.base_addr,
+                        module.instance_id,
+                        module.namespace_id,
+                        &state.ignore_callers,
+                    )
+            })
+            .max_by_key(|(index, module)| (env.observation_order(module), *index));
+        if let Some((_, module)) = newest {
+            targets.insert(*stub, module_key(module));
+        }
+    }
+    targets
 }
@@ -1,9 +1,11 @@
 // 模块唯一键生成与失效 slot/task 清理
-use crate::api::HookStub;
+use crate::api::{HookEventKind, HookStub};
+use crate::errno::Errno;
 use std::collections::BTreeSet;
 
 use super::super::hub;
 use super::super::state::{CoreState, ModuleInfo};
+use super::CallbackEvent;
 
 pub(super) fn module_key(module: &ModuleInfo) -> String {
     module_instance_key(
@@ -25,10 +27,16 @@ pub(super) fn module_instance_key(
 }
 
 // 清理已卸载模块对应的 slot，销毁关联 hub 并更新 task_slots 索引
-pub(super) fn prune_dead_slots(state: &mut CoreState, alive_modules: &BTreeSet<String>) {
+// 每个失去 slot 的 (任务, caller 路径) 通知一次 ModuleUnloaded，并登记为 Rehooked 候选
+// is_alive 按模块键判定存活，全量刷新传入扫描结果，按模块刷新只排除已卸载的键
+pub(super) fn prune_dead_slots(
+    state: &mut CoreState,
+    is_alive: impl Fn(&str) -> bool,
+    events: &mut Vec<CallbackEvent>,
+) {
     let mut stale = Vec::new();
     for key in state.slots.keys() {
-        if !alive_modules.contains(&module_instance_key(
+        if !is_alive(&module_instance_key(
             &key.caller_path_name,
             key.caller_base_addr,
             key.caller_instance_id,
@@ -37,6 +45,7 @@ pub(super) fn prune_dead_slots(state: &mut CoreState, alive_modules: &BTreeSet<S
             stale.push(key.clone());
         }
     }
+    let mut unloaded = BTreeSet::new();
     for key in stale {
         let Some(slot) = state.slots.remove(&key) else {
             continue;
@@ -45,6 +54,7 @@ pub(super) fn prune_dead_slots(state: &mut CoreState, alive_modules: &BTreeSet<S
             hub::destroy_hub(slot.hub_ptr as *mut hub::Hub, true);
         }
         for stub in slot.task_chain {
+            unloaded.insert((stub, key.caller_path_name.clone()));
             let Some(slot_set) = state.task_slots.get_mut(&stub) else {
                 continue;
             };
@@ -54,15 +64,29 @@ pub(super) fn prune_dead_slots(state: &mut CoreState, alive_modules: &BTreeSet<S
             }
         }
     }
+
+    for (stub, caller_path_name) in unloaded {
+        let Some(task) = state.tasks.get(&stub) else {
+            continue;
+        };
+        events.extend(CallbackEvent::for_task(
+            task,
+            HookEventKind::ModuleUnloaded,
+            Errno::Ok,
+            &caller_path_name,
+            0,
+        ));
+        state.rehook_targets.insert((stub, caller_path_name));
+    }
 }
 
-pub(super) fn prune_dead_single_task_targets(state: &mut CoreState, alive_modules: &BTreeSet<String>) {
+pub(super) fn prune_dead_single_task_targets(state: &mut CoreState, is_alive: impl Fn(&str) -> bool) {
     let stale_targets: Vec<HookStub> = state
         .single_task_targets
         .iter()
         .filter_map(|(stub, module)| {
             let has_live_slot = state.task_slots.get(stub).is_some_and(|slots| !slots.is_empty());
-            if !has_live_slot || !alive_modules.contains(module) {
+            if !has_live_slot || !is_alive(module) {
                 Some(*stub)
             } else {
                 None
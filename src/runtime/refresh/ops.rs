@@ -1,16 +1,23 @@
 // GOT slot 读写、ELF 解析和模块扫描的底层操作，所有操作均在信号保护下执行
 use crate::elf;
 use crate::errno::Errno;
+use crate::log;
 use crate::android::memory;
-use crate::android::signal_guard;
+use crate::android::signal_guard::{self, GuardPhase};
 use std::collections::BTreeSet;
-use std::ffi::c_void;
-use std::ptr;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::ffi::{CStr, c_void};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 use super::super::state::ModuleInfo;
 
 mod module_scan;
+mod patch_batch;
+mod soname;
+
+// 内存中 ELF 头部不可读时是否允许从磁盘文件读取程序头
+static HEADER_FILE_FALLBACK: AtomicBool = AtomicBool::new(true);
+// 写入后是否回读确认（enable_write_verification），同时控制 slot_write 的校验
+static WRITE_VERIFY: AtomicBool = AtomicBool::new(true);
 
 // 模块加载/卸载计数，用于检测模块列表是否发生变化
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -20,24 +27,21 @@ pub(super) struct ModuleEpoch {
 }
 
 pub(super) fn read_slot(addr: usize) -> Result<usize, Errno> {
-    signal_guard::with_guard(|| unsafe { ptr::read(addr as *const usize) })
-        .map_err(|_| Errno::ReadElf)
+    memory::safe_read_usize(addr).ok_or(Errno::ReadElf)
 }
 
-// 写入 GOT slot：修改内存保护 -> 原子写入 -> 验证 -> 恢复保护 -> 刷新缓存
+// 写入 GOT slot：修改内存保护 -> 原子写入 -> 验证 -> 恢复原始保护 -> 屏障与缓存刷新
+// 处于 PatchBatch 中时页面保护由批次统一切换与恢复
 pub(super) fn patch_slot(addr: usize, value: usize, pathname: &str) -> Result<(), Errno> {
-    let old_prot = memory::get_addr_protect(addr, Some(pathname)).map_err(|_| Errno::GetProt)?;
-    let writable_prot = memory::PROT_READ_FLAG | memory::PROT_WRITE_FLAG;
-    let changed_protect = old_prot != writable_prot;
-    if changed_protect {
-        memory::set_addr_protect(addr, writable_prot).map_err(|_| Errno::SetProt)?;
-    }
-
-    let write_result = signal_guard::with_guard(|| unsafe {
-        let atomic_slot = &*(addr as *const AtomicUsize);
-        atomic_slot.store(value, Ordering::SeqCst);
-        atomic_slot.load(Ordering::SeqCst)
-    });
+    let write_result = match patch_batch::open_page(addr, pathname) {
+        Some(opened) => {
+            opened?;
+            write_slot(addr, value)
+        }
+        None => memory::with_writable(addr, std::mem::size_of::<usize>(), || {
+            write_slot(addr, value)
+        })?,
+    };
 
     let mut patch_status = Errno::Ok;
     match write_result {
@@ -46,32 +50,153 @@ pub(super) fn patch_slot(addr: usize, value: usize, pathname: &str) -> Result<()
         Err(_) => patch_status = Errno::SetGot,
     }
 
-    if changed_protect {
-        let _ = memory::set_addr_protect(addr, old_prot);
-    }
     if patch_status != Errno::Ok {
         return Err(patch_status);
     }
     memory::flush_instruction_cache(addr);
-    Ok(())
+    if !is_write_verify_enabled() {
+        return Ok(());
+    }
+
+    // 同一物理页存在别名映射时写入可能落在过期副本上，刷新后再经安全读取确认一次
+    match read_slot(addr) {
+        Ok(observed) if observed == value => Ok(()),
+        Ok(observed) => {
+            log::warn(format_args!(
+                "slot 0x{:x} in {} reads 0x{:x} after patch, expected 0x{:x}",
+                addr, pathname, observed, value
+            ));
+            Err(Errno::PatchVerifyFailed)
+        }
+        Err(_) => Err(Errno::PatchVerifyFailed),
+    }
 }
 
+// 守卫下原子写入 slot 并回读
+fn write_slot(addr: usize, value: usize) -> Result<usize, Errno> {
+    signal_guard::with_guard_tagged(GuardPhase::PatchSlot, || unsafe {
+        let atomic_slot = &*(addr as *const AtomicUsize);
+        atomic_slot.store(value, Ordering::SeqCst);
+        atomic_slot.load(Ordering::SeqCst)
+    })
+}
+
+// 使 slot 所在页可写，返回写入后需要恢复的原始保护
+fn make_writable(addr: usize, pathname: &str) -> Result<Option<u32>, Errno> {
+    let old_prot = memory::get_addr_protect(addr, Some(pathname)).map_err(|_| Errno::GetProt)?;
+    // text relocation 位于代码页，保留执行权限避免其他线程正在执行该页时崩溃
+    let writable_prot =
+        memory::PROT_READ_FLAG | memory::PROT_WRITE_FLAG | (old_prot & memory::PROT_EXEC_FLAG);
+    if old_prot == writable_prot {
+        return Ok(None);
+    }
+    memory::set_addr_protect(addr, writable_prot).map_err(|_| Errno::SetProt)?;
+    Ok(Some(old_prot))
+}
+
+// 按写入前记录的保护原样恢复（RELRO 页恢复为只读），随后发出屏障
+fn restore_protect(page: usize, old_prot: u32) {
+    memory::restore_mem_protect(page, std::mem::size_of::<usize>(), old_prot);
+    memory::flush_instruction_cache(page);
+}
+
+// 开始按页分组的批量写入，同页的多个 slot 共用一次保护切换
+pub(super) fn begin_patch_batch() {
+    patch_batch::begin();
+}
+
+// 结束批量写入，逐页恢复批次内改动过的保护
+pub(super) fn finish_patch_batch() {
+    patch_batch::finish();
+}
+
+pub(super) fn set_header_file_fallback(flag: bool) {
+    HEADER_FILE_FALLBACK.store(flag, Ordering::Relaxed);
+}
+
+pub(super) fn set_write_verify(flag: bool) {
+    WRITE_VERIFY.store(flag, Ordering::Relaxed);
+}
+
+pub(super) fn is_write_verify_enabled() -> bool {
+    WRITE_VERIFY.load(Ordering::Relaxed)
+}
+
+// 内存解析触发访问异常时（首个 LOAD 段 execute-only），按开关改用文件中的程序头重试
 pub(super) fn init_elf_guard(base_addr: usize, pathname: &str) -> Result<elf::Elf, Errno> {
-    signal_guard::with_guard(|| unsafe { elf::Elf::init(base_addr, pathname) })
-        .map_err(|_| Errno::ReadElf)?
+    match signal_guard::with_guard_tagged(GuardPhase::ElfInit, || unsafe {
+        elf::Elf::init(base_addr, pathname)
+    }) {
+        Ok(result) => result,
+        Err(_) if HEADER_FILE_FALLBACK.load(Ordering::Relaxed) => {
+            init_elf_from_file_headers(base_addr, pathname)
+        }
+        Err(_) => Err(Errno::ReadElf),
+    }
+}
+
+fn init_elf_from_file_headers(base_addr: usize, pathname: &str) -> Result<elf::Elf, Errno> {
+    let headers = elf::FileHeaders::read(pathname).map_err(|err| {
+        log::warn(format_args!(
+            "{} headers unreadable in memory, file fallback failed: {:?}",
+            pathname, err
+        ));
+        Errno::ReadElf
+    })?;
+    let elf = signal_guard::with_guard_tagged(GuardPhase::ElfInit, || unsafe {
+        elf::Elf::init_with_file_headers(base_addr, pathname, &headers)
+    })
+    .map_err(|_| Errno::ReadElf)??;
+
+    // 内存中的 build-id 可读时必须与文件一致，防止路径已被替换
+    if let Some(file_id) = headers.build_id()
+        && let Ok(Some(mem_id)) =
+            signal_guard::with_guard_tagged(GuardPhase::ElfInit, || unsafe { elf.build_id() })
+        && mem_id != file_id
+    {
+        log::warn(format_args!("{} build-id differs from file on disk", pathname));
+        return Err(Errno::ReadElf);
+    }
+    log::info(format_args!("{} parsed with program headers from file", pathname));
+    Ok(elf)
 }
 
 pub(super) fn find_slots_guard(
     elf: &elf::Elf,
     symbol_name: &str,
     callee_addrs: Option<&BTreeSet<usize>>,
-) -> Result<Vec<usize>, Errno> {
-    signal_guard::with_guard(|| unsafe { elf.find_got_slots(symbol_name, callee_addrs) })
-        .map_err(|_| Errno::ReadElf)?
+) -> Result<elf::GotSlots, Errno> {
+    let (name, version) = elf::split_symbol_version(symbol_name);
+    signal_guard::with_guard_tagged(GuardPhase::CollectSlot, || unsafe {
+        elf.find_got_slots_by_class(name, version, callee_addrs)
+    })
+    .map_err(|_| Errno::ReadElf)?
 }
 
-pub(super) fn find_export_guard(elf: &elf::Elf, symbol_name: &str) -> Result<Option<usize>, Errno> {
-    signal_guard::with_guard(|| elf.find_export_function(symbol_name)).map_err(|_| Errno::ReadElf)
+pub(super) fn import_names_guard(
+    elf: &elf::Elf,
+    filter: impl Fn(&str) -> bool,
+) -> Result<Vec<String>, Errno> {
+    signal_guard::with_guard_tagged(GuardPhase::ElfLookup, || unsafe {
+        elf.find_import_names(filter)
+    })
+    .map_err(|_| Errno::ReadElf)?
+}
+
+pub(super) fn text_reloc_count_guard(elf: &elf::Elf, symbol_name: &str) -> Result<usize, Errno> {
+    let (name, version) = elf::split_symbol_version(symbol_name);
+    signal_guard::with_guard_tagged(GuardPhase::CollectSlot, || unsafe {
+        elf.count_text_relocs(name, version)
+    })
+    .map_err(|_| Errno::ReadElf)?
+}
+
+pub(super) fn find_exports_guard(elf: &elf::Elf, symbol_name: &str) -> Result<Vec<usize>, Errno> {
+    let (name, version) = elf::split_symbol_version(symbol_name);
+    signal_guard::with_guard_tagged(GuardPhase::ElfLookup, || {
+        elf.find_export_candidates(name, version)
+    })
+    .map_err(|_| Errno::ReadElf)
 }
 
 pub(super) fn module_epoch() -> Option<ModuleEpoch> {
@@ -79,28 +204,111 @@ pub(super) fn module_epoch() -> Option<ModuleEpoch> {
 }
 
 pub(super) fn observe_module_handle(handle: *mut c_void) {
-    let _ = signal_guard::with_guard(|| module_scan::observe_module_handle(handle));
+    let _ = signal_guard::with_guard_tagged(GuardPhase::ModuleIdentity, || {
+        module_scan::observe_module_handle(handle)
+    });
 }
 
 pub(super) fn observe_module_identity(module: &ModuleInfo) {
     module_scan::observe_module_identity(module);
 }
 
+pub(super) fn instance_observation_order(module: &ModuleInfo) -> Option<u64> {
+    module_scan::instance_observation_order(module)
+}
+
 pub(super) fn module_identity_from_handle(handle: *mut c_void) -> Option<ModuleInfo> {
-    signal_guard::with_guard(|| module_scan::module_identity_from_handle(handle))
-        .ok()
-        .flatten()
+    signal_guard::with_guard_tagged(GuardPhase::ModuleIdentity, || {
+        module_scan::module_identity_from_handle(handle)
+    })
+    .ok()
+    .flatten()
 }
 
 pub(super) fn module_identity_from_handle_with_symbol(
     handle: *mut c_void,
     probe_symbol: &str,
 ) -> Option<ModuleInfo> {
-    signal_guard::with_guard(|| module_scan::module_identity_from_handle_with_symbol(handle, probe_symbol))
-        .ok()
-        .flatten()
+    signal_guard::with_guard_tagged(GuardPhase::ModuleIdentity, || {
+        module_scan::module_identity_from_handle_with_symbol(handle, probe_symbol)
+    })
+    .ok()
+    .flatten()
+}
+
+pub(super) fn module_identity_from_addr(addr: *const c_void) -> Option<ModuleInfo> {
+    signal_guard::with_guard_tagged(GuardPhase::ModuleIdentity, || {
+        module_scan::module_identity_from_addr(addr)
+    })
+    .ok()
+    .flatten()
 }
 
 pub(super) fn enumerate_modules() -> Vec<ModuleInfo> {
-    module_scan::enumerate_modules()
+    let mut modules = module_scan::enumerate_modules();
+    soname::fill_sonames(&mut modules);
+    modules
+}
+
+// 基址页仍可读且模块身份仍可按基址找回时视为仍在加载中
+pub(super) fn is_module_present(module: &ModuleInfo) -> bool {
+    let readable = memory::get_addr_protect(module.base_addr, Some(&module.pathname))
+        .is_ok_and(|prot| prot & memory::PROT_READ_FLAG != 0);
+    readable && module_scan::is_module_present(module)
+}
+
+// 无法读取 maps 时按仍映射处理，只有确认缺失或不可执行才返回 false
+pub(super) fn is_code_mapped(addr: usize) -> bool {
+    match memory::get_addr_protect(memory::canonical_code_addr(addr), None) {
+        Ok(prot) => prot & memory::PROT_EXEC_FLAG != 0,
+        Err(err) => err == Errno::BadMaps,
+    }
+}
+
+pub(super) fn symbolize(addr: usize) -> Option<String> {
+    let mut info: libc::Dl_info = unsafe { std::mem::zeroed() };
+    let found = signal_guard::with_guard_tagged(GuardPhase::Symbolize, || unsafe {
+        libc::dladdr(addr as *const c_void, &mut info)
+    });
+    if !matches!(found, Ok(ret) if ret != 0) || info.dli_fname.is_null() {
+        return None;
+    }
+    let fname = unsafe { CStr::from_ptr(info.dli_fname) }.to_string_lossy();
+    let module = fname.rsplit('/').next().unwrap_or(&fname).to_string();
+    if info.dli_sname.is_null() {
+        return Some(format!("{}+0x{:x}", module, addr - info.dli_fbase as usize));
+    }
+    let sname = unsafe { CStr::from_ptr(info.dli_sname) }.to_string_lossy();
+    Some(format!(
+        "{}!{}+0x{:x}",
+        module,
+        sname,
+        addr - info.dli_saddr as usize
+    ))
+}
+
+pub(super) fn enumerate_modules_for_refresh() -> Vec<ModuleInfo> {
+    let mut modules = module_scan::enumerate_modules_for_refresh();
+    soname::fill_sonames(&mut modules);
+    modules
+}
+
+pub(super) fn set_soname_scan(flag: bool) {
+    soname::set_enabled(flag);
+}
+
+pub(super) fn set_scan_snapshot_capture(flag: bool) {
+    module_scan::set_scan_snapshot_capture(flag);
+}
+
+pub(super) fn scan_snapshot_text() -> String {
+    module_scan::scan_snapshot_text()
+}
+
+pub(super) fn dlinfo_available() -> bool {
+    module_scan::dlinfo_available()
+}
+
+pub(super) fn dladdr1_available() -> bool {
+    module_scan::dladdr1_available()
 }
@@ -45,18 +45,21 @@ mod hints;
 mod maps;
 mod noload;
 mod resolve;
+mod snapshot;
 
 #[cfg(test)]
 mod tests;
 
 use hints::{
     apply_observed_instance_hints, module_noload_key, noload_namespace_hints,
-    observe_path_namespace_hint, observed_identity_hints, observed_instance_namespace_hints,
-    trim_noload_namespace_hints, trim_observed_instance_hints,
-    trim_observed_instance_namespace_hints,
+    observe_instance_order, observe_path_namespace_hint, observed_identity_hints,
+    observed_instance_namespace_hints, resolve_instance_order, trim_noload_namespace_hints,
+    trim_observed_instance_hints, trim_observed_instance_namespace_hints,
+};
+use maps::{enumerate_modules_maps, enumerate_modules_maps_cached};
+use resolve::{
+    resolve_module_from_addr, resolve_module_from_handle, resolve_module_from_handle_symbol,
 };
-use maps::enumerate_modules_maps_cached;
-use resolve::{resolve_module_from_handle, resolve_module_from_handle_symbol};
 
 // 通过 dl_iterate_phdr 获取模块加载/卸载计数，仅需遍历第一个条目
 pub(super) fn module_epoch() -> Option<ModuleEpoch> {
@@ -103,6 +106,7 @@ pub(super) fn observe_module_identity(module: &ModuleInfo) {
         return;
     }
 
+    observe_instance_order(module);
     let mut hints = observed_identity_hints().lock_or_poison();
     let hint = ObservedIdentityHint {
         instance_id: module.instance_id,
@@ -133,6 +137,11 @@ pub(super) fn observe_module_identity(module: &ModuleInfo) {
     }
 }
 
+// 经 dlopen handle 观察到的先后顺序，未被观察过的实例返回 None
+pub(super) fn instance_observation_order(module: &ModuleInfo) -> Option<u64> {
+    resolve_instance_order(module)
+}
+
 pub(super) fn module_identity_from_handle(handle: *mut c_void) -> Option<ModuleInfo> {
     resolve_module_from_handle(handle)
 }
@@ -149,6 +158,13 @@ pub(super) fn module_identity_from_handle_with_symbol(
     merge_module_identity(primary, fallback)
 }
 
+pub(super) fn module_identity_from_addr(addr: *const c_void) -> Option<ModuleInfo> {
+    if addr.is_null() {
+        return None;
+    }
+    resolve_module_from_addr(addr, std::ptr::null_mut())
+}
+
 // 合并 primary 和 fallback 两个身份信息，优先取 primary 的非零字段
 fn merge_module_identity(primary: Option<ModuleInfo>, fallback: Option<ModuleInfo>) -> Option<ModuleInfo> {
     match (primary, fallback) {
@@ -186,12 +202,64 @@ fn merge_module_identity(primary: Option<ModuleInfo>, fallback: Option<ModuleInf
 
 // 合并 phdr 和 maps 两种数据源，应用 hint 缓存补全 namespace_id
 pub(super) fn enumerate_modules() -> Vec<ModuleInfo> {
+    scan_modules(false)
+}
+
+// 刷新流程的模块扫描，开启采集时记录本轮快照
+pub(super) fn enumerate_modules_for_refresh() -> Vec<ModuleInfo> {
+    scan_modules(snapshot::is_capture_enabled())
+}
+
+fn scan_modules(capture: bool) -> Vec<ModuleInfo> {
+    let phdr_modules = enumerate_modules_phdr();
+    let maps_modules = enumerate_modules_maps_cached();
+    let capture = capture.then(|| snapshot::begin(&phdr_modules, &maps_modules));
+    let mut modules = merge_module_sources(phdr_modules, maps_modules);
+    apply_observed_instance_hints(&mut modules);
+    let modules = sort_modules(modules);
+    if let Some(capture) = capture {
+        snapshot::commit(capture, &modules);
+    }
+    modules
+}
+
+pub(super) fn set_scan_snapshot_capture(flag: bool) {
+    snapshot::set_capture(flag);
+}
+
+pub(super) fn scan_snapshot_text() -> String {
+    snapshot::snapshot_text()
+}
+
+pub(super) fn dlinfo_available() -> bool {
+    resolve::resolve_dlinfo_fn().is_some()
+}
+
+pub(super) fn dladdr1_available() -> bool {
+    resolve::resolve_dladdr1_fn().is_some()
+}
+
+// 不经缓存复核模块是否仍在：linker 列表中仍有该基址，或 maps 中仍有同一路径的首段映射
+pub(super) fn is_module_present(module: &ModuleInfo) -> bool {
+    enumerate_modules_phdr()
+        .iter()
+        .any(|item| item.base_addr == module.base_addr)
+        || enumerate_modules_maps()
+            .iter()
+            .any(|item| item.base_addr == module.base_addr && item.pathname == module.pathname)
+}
+
+// 以 base_addr 合并两种数据源，bool 标记是否来自 phdr
+fn merge_module_sources(
+    phdr_modules: Vec<ModuleInfo>,
+    maps_modules: Vec<ModuleInfo>,
+) -> Vec<(ModuleInfo, bool)> {
     let mut modules_by_base = BTreeMap::<usize, (ModuleInfo, bool)>::new();
-    for module in enumerate_modules_phdr() {
+    for module in phdr_modules {
         modules_by_base.insert(module.base_addr, (module, true));
     }
 
-    for module in enumerate_modules_maps_cached() {
+    for module in maps_modules {
         modules_by_base
             .entry(module.base_addr)
             .and_modify(|(existing, _)| {
@@ -202,9 +270,10 @@ pub(super) fn enumerate_modules() -> Vec<ModuleInfo> {
             })
             .or_insert((module, false));
     }
+    modules_by_base.into_values().collect()
+}
 
-    let mut modules: Vec<_> = modules_by_base.into_values().collect();
-    apply_observed_instance_hints(&mut modules);
+fn sort_modules(modules: Vec<(ModuleInfo, bool)>) -> Vec<ModuleInfo> {
     let mut modules: Vec<_> = modules.into_iter().map(|(module, _)| module).collect();
     modules.sort_by(|left, right| {
         left.pathname
@@ -241,6 +310,7 @@ fn enumerate_modules_phdr() -> Vec<ModuleInfo> {
             base_addr: info.dlpi_addr as usize,
             instance_id: info.dlpi_name as usize,
             namespace_id: 0,
+            soname: None,
         });
         0
     }
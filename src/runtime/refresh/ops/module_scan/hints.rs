@@ -1,6 +1,7 @@
 // 模块身份 hint 缓存，提供 base_addr / instance_id / pathname / noload 四级 namespace 解析
 use crate::runtime::state::MutexPoisonRecover;
 use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Mutex, OnceLock};
 
 use super::{
@@ -32,6 +33,28 @@ pub(super) fn noload_namespace_hints() -> &'static Mutex<BTreeMap<(usize, usize)
     NOLOAD_NAMESPACE_HINTS.get_or_init(|| Mutex::new(BTreeMap::new()))
 }
 
+// (base, instance) -> 首次观察到的序号，序号越大越晚加载；随模块卸载在 hint 应用时清理
+pub(super) fn observed_instance_order() -> &'static Mutex<BTreeMap<(usize, usize), u64>> {
+    static OBSERVED_INSTANCE_ORDER: OnceLock<Mutex<BTreeMap<(usize, usize), u64>>> =
+        OnceLock::new();
+    OBSERVED_INSTANCE_ORDER.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+pub(super) fn observe_instance_order(module: &ModuleInfo) {
+    static NEXT_ORDER: AtomicU64 = AtomicU64::new(1);
+    observed_instance_order()
+        .lock_or_poison()
+        .entry(module_noload_key(module))
+        .or_insert_with(|| NEXT_ORDER.fetch_add(1, Ordering::Relaxed));
+}
+
+pub(super) fn resolve_instance_order(module: &ModuleInfo) -> Option<u64> {
+    observed_instance_order()
+        .lock_or_poison()
+        .get(&module_noload_key(module))
+        .copied()
+}
+
 pub(super) fn resolve_namespace_id_by_base(base_addr: usize) -> Option<usize> {
     observed_identity_hints()
         .lock()
@@ -94,11 +117,18 @@ fn upsert_path_namespace_hint(hints: &mut BTreeMap<String, usize>, key: &str, na
 }
 
 pub(super) fn resolve_namespace_id_by_path(pathname: &str) -> Option<usize> {
+    let hints = observed_path_namespace_hints().lock_or_poison();
+    resolve_namespace_id_in_paths(&hints, pathname)
+}
+
+pub(super) fn resolve_namespace_id_in_paths(
+    hints: &BTreeMap<String, usize>,
+    pathname: &str,
+) -> Option<usize> {
     let normalized = normalize_pathname(pathname);
     if normalized.is_empty() {
         return None;
     }
-    let hints = observed_path_namespace_hints().lock_or_poison();
     if let Some(namespace_id) = hints.get(normalized).copied().filter(|id| *id != 0) {
         return Some(namespace_id);
     }
@@ -217,14 +247,15 @@ pub(super) fn apply_instance_hints(
     modules: &mut [(ModuleInfo, bool)],
     hints: &mut BTreeMap<usize, ObservedIdentityHint>,
 ) {
-    let alive_bases: BTreeSet<usize> = modules.iter().map(|(module, _)| module.base_addr).collect();
-    hints.retain(|base, _| alive_bases.contains(base));
     let alive_noload_keys: BTreeSet<(usize, usize)> =
         modules.iter().map(|(module, _)| module_noload_key(module)).collect();
     noload_namespace_hints()
         .lock()
         .unwrap()
         .retain(|key, _| alive_noload_keys.contains(key));
+    observed_instance_order()
+        .lock_or_poison()
+        .retain(|key, _| alive_noload_keys.contains(key));
     let alive_paths: BTreeSet<String> = modules
         .iter()
         .flat_map(|(module, _)| {
@@ -241,33 +272,38 @@ pub(super) fn apply_instance_hints(
 
     let dlinfo = super::resolve::resolve_dlinfo_fn();
     let instance_namespaces = observed_instance_namespace_hints().lock_or_poison();
+    resolve_hinted_namespaces(
+        modules,
+        hints,
+        &instance_namespaces,
+        resolve_namespace_id_by_path,
+        |module| {
+            dlinfo.and_then(|dlinfo| {
+                super::noload::resolve_namespace_id_from_noload_cached(module, dlinfo)
+            })
+        },
+    );
+}
+
+// hint 应用的纯逻辑部分，路径与 noload 解析由调用方提供，供快照回放复用
+pub(super) fn resolve_hinted_namespaces(
+    modules: &mut [(ModuleInfo, bool)],
+    hints: &mut BTreeMap<usize, ObservedIdentityHint>,
+    instance_namespaces: &BTreeMap<usize, usize>,
+    by_path: impl Fn(&str) -> Option<usize>,
+    mut by_noload: impl FnMut(&ModuleInfo) -> Option<usize>,
+) {
+    let alive_bases: BTreeSet<usize> = modules.iter().map(|(module, _)| module.base_addr).collect();
+    hints.retain(|base, _| alive_bases.contains(base));
+
     for (module, from_phdr) in modules {
-        let Some(identity) = hints.get(&module.base_addr).copied() else {
-            if module.namespace_id == 0
-                && let Some(namespace_id) = instance_namespaces.get(&module.instance_id).copied()
-                && namespace_id != 0
-            {
-                module.namespace_id = namespace_id;
+        if let Some(identity) = hints.get(&module.base_addr).copied() {
+            if !*from_phdr && identity.instance_id != 0 {
+                module.instance_id = identity.instance_id;
             }
-            if module.namespace_id == 0
-                && let Some(namespace_id) = resolve_namespace_id_by_path(module.pathname.as_str())
-            {
-                module.namespace_id = namespace_id;
+            if module.namespace_id == 0 && identity.namespace_id != 0 {
+                module.namespace_id = identity.namespace_id;
             }
-            if module.namespace_id == 0
-                && let Some(dlinfo) = dlinfo
-                && let Some(namespace_id) =
-                    super::noload::resolve_namespace_id_from_noload_cached(module, dlinfo)
-            {
-                module.namespace_id = namespace_id;
-            }
-            continue;
-        };
-        if !*from_phdr && identity.instance_id != 0 {
-            module.instance_id = identity.instance_id;
-        }
-        if module.namespace_id == 0 && identity.namespace_id != 0 {
-            module.namespace_id = identity.namespace_id;
         }
         if module.namespace_id == 0
             && let Some(namespace_id) = instance_namespaces.get(&module.instance_id).copied()
@@ -276,14 +312,12 @@ pub(super) fn apply_instance_hints(
             module.namespace_id = namespace_id;
         }
         if module.namespace_id == 0
-            && let Some(namespace_id) = resolve_namespace_id_by_path(module.pathname.as_str())
+            && let Some(namespace_id) = by_path(module.pathname.as_str())
         {
             module.namespace_id = namespace_id;
         }
         if module.namespace_id == 0
-            && let Some(dlinfo) = dlinfo
-            && let Some(namespace_id) =
-                super::noload::resolve_namespace_id_from_noload_cached(module, dlinfo)
+            && let Some(namespace_id) = by_noload(module)
         {
             module.namespace_id = namespace_id;
         }
@@ -6,7 +6,7 @@ use std::sync::{Mutex, OnceLock};
 
 use super::{MAPS_CACHE_FORCE_REFRESH_INTERVAL, ModuleInfo, module_epoch};
 
-fn enumerate_modules_maps() -> Vec<ModuleInfo> {
+pub(super) fn enumerate_modules_maps() -> Vec<ModuleInfo> {
     let Ok(content) = fs::read_to_string("/proc/self/maps") else {
         return Vec::new();
     };
@@ -93,6 +93,7 @@ pub(super) fn parse_maps_line(line: &str) -> Option<ModuleInfo> {
         base_addr,
         instance_id,
         namespace_id: 0,
+        soname: None,
     })
 }
 
@@ -38,7 +38,7 @@ pub(super) fn resolve_dlinfo_fn() -> Option<DlinfoFn> {
     })
 }
 
-fn resolve_dladdr1_fn() -> Option<Dladdr1Fn> {
+pub(super) fn resolve_dladdr1_fn() -> Option<Dladdr1Fn> {
     static DLADDR1_FN: OnceLock<Option<Dladdr1Fn>> = OnceLock::new();
     *DLADDR1_FN.get_or_init(|| {
         let mut symbol = unsafe { libc::dlsym(RTLD_NEXT_FALLBACK, c"dladdr1".as_ptr()) };
@@ -97,6 +97,7 @@ pub(super) fn resolve_module_from_handle(handle: *mut c_void) -> Option<ModuleIn
         base_addr,
         instance_id,
         namespace_id,
+        soname: None,
     })
 }
 
@@ -177,9 +178,13 @@ pub(super) fn resolve_module_from_handle_symbol(
         ));
         return None;
     }
+    resolve_module_from_addr(symbol_addr as *const c_void, handle)
+}
 
+// dladdr 反查地址所属模块；handle 为空时只能经 dladdr1 取 link_map
+pub(super) fn resolve_module_from_addr(addr: *const c_void, handle: *mut c_void) -> Option<ModuleInfo> {
     let mut info: libc::Dl_info = unsafe { std::mem::zeroed() };
-    if unsafe { libc::dladdr(symbol_addr as *const c_void, &mut info) } == 0
+    if unsafe { libc::dladdr(addr, &mut info) } == 0
         || info.dli_fbase.is_null()
         || info.dli_fname.is_null()
     {
@@ -196,16 +201,17 @@ pub(super) fn resolve_module_from_handle_symbol(
 
     let base_addr = info.dli_fbase as usize;
     let dlinfo = resolve_dlinfo_fn();
-    let link_map_ptr = dlinfo
+    let handle_dlinfo = dlinfo.filter(|_| !handle.is_null());
+    let link_map_ptr = handle_dlinfo
         .and_then(|func| resolve_link_map_from_handle(func, handle))
-        .or_else(|| resolve_link_map_from_addr(symbol_addr as *const c_void));
+        .or_else(|| resolve_link_map_from_addr(addr));
     let instance_id = link_map_ptr
         .map(|ptr| ptr as usize)
         .filter(|id| *id != 0)
         .or_else(|| resolve_instance_id_by_base(base_addr))
         .or_else(|| resolve_instance_id_from_maps(base_addr, pathname))
         .unwrap_or(base_addr.max(1));
-    let namespace_id = dlinfo
+    let namespace_id = handle_dlinfo
         .and_then(|func| resolve_namespace_id_from_handle(func, handle))
         .filter(|id| *id != 0)
         .or_else(|| link_map_ptr.and_then(resolve_namespace_id_from_link_map))
@@ -219,6 +225,7 @@ pub(super) fn resolve_module_from_handle_symbol(
         base_addr,
         instance_id,
         namespace_id,
+        soname: None,
     })
 }
 
@@ -292,6 +299,7 @@ fn resolve_namespace_id_from_noload_cache(
         base_addr,
         instance_id,
         namespace_id: 0,
+        soname: None,
     };
     resolve_namespace_id_from_noload_cached(&module, dlinfo)
 }
@@ -0,0 +1,340 @@
+// 模块扫描快照：记录每轮刷新的原始输入（phdr / maps / hint 缓存 / dlinfo 可用性）与合并结果
+// 快照保留完整路径与地址，仅作为诊断数据导出；默认关闭，按轮数和总字节数限量保留
+use crate::runtime::state::MutexPoisonRecover;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use super::hints::{
+    noload_namespace_hints, observed_identity_hints, observed_instance_namespace_hints,
+    observed_path_namespace_hints,
+};
+use super::{ModuleInfo, ObservedIdentityHint};
+
+const SNAPSHOT_HEADER: &str = "srx-scan-snapshot 1";
+const SNAPSHOT_PASS_LIMIT: usize = 8;
+const SNAPSHOT_BYTES_LIMIT: usize = 512 * 1024;
+
+static CAPTURE: AtomicBool = AtomicBool::new(false);
+static PASS_SEQ: AtomicU64 = AtomicU64::new(0);
+
+fn captured_passes() -> &'static Mutex<VecDeque<String>> {
+    static PASSES: OnceLock<Mutex<VecDeque<String>>> = OnceLock::new();
+    PASSES.get_or_init(|| Mutex::new(VecDeque::new()))
+}
+
+// 单轮扫描的全部输入；noload 缓存在 hint 应用之后读取，包含本轮实时解析的结果
+#[derive(Default)]
+pub(super) struct ScanCapture {
+    pub(super) pass: u64,
+    pub(super) dlinfo: bool,
+    pub(super) phdr: Vec<ModuleInfo>,
+    pub(super) maps: Vec<ModuleInfo>,
+    pub(super) identities: BTreeMap<usize, ObservedIdentityHint>,
+    pub(super) instance_namespaces: BTreeMap<usize, usize>,
+    pub(super) paths: BTreeMap<String, usize>,
+    pub(super) noload: BTreeMap<(usize, usize), usize>,
+    pub(super) modules: Vec<ModuleInfo>,
+}
+
+pub(super) fn set_capture(flag: bool) {
+    // 重新开启时丢弃上一段采集，避免新旧快照混在一起
+    if flag && !CAPTURE.swap(true, Ordering::Relaxed) {
+        captured_passes().lock_or_poison().clear();
+        return;
+    }
+    CAPTURE.store(flag, Ordering::Relaxed);
+}
+
+pub(super) fn is_capture_enabled() -> bool {
+    CAPTURE.load(Ordering::Relaxed)
+}
+
+// hint 应用前记录原始输入，身份 / instance / 路径缓存会在应用时被裁剪
+pub(super) fn begin(phdr: &[ModuleInfo], maps: &[ModuleInfo]) -> ScanCapture {
+    ScanCapture {
+        pass: PASS_SEQ.fetch_add(1, Ordering::Relaxed) + 1,
+        dlinfo: super::resolve::resolve_dlinfo_fn().is_some(),
+        phdr: phdr.to_vec(),
+        maps: maps.to_vec(),
+        identities: observed_identity_hints().lock_or_poison().clone(),
+        instance_namespaces: observed_instance_namespace_hints().lock_or_poison().clone(),
+        paths: observed_path_namespace_hints().lock_or_poison().clone(),
+        ..ScanCapture::default()
+    }
+}
+
+pub(super) fn commit(mut capture: ScanCapture, modules: &[ModuleInfo]) {
+    capture.noload = noload_namespace_hints().lock_or_poison().clone();
+    capture.modules = modules.to_vec();
+    let text = encode(&capture);
+
+    let mut passes = captured_passes().lock_or_poison();
+    passes.push_back(text);
+    while passes.len() > SNAPSHOT_PASS_LIMIT {
+        passes.pop_front();
+    }
+    while passes.len() > 1 && passes.iter().map(String::len).sum::<usize>() > SNAPSHOT_BYTES_LIMIT {
+        passes.pop_front();
+    }
+}
+
+pub(super) fn snapshot_text() -> String {
+    captured_passes()
+        .lock_or_poison()
+        .iter()
+        .map(String::as_str)
+        .collect()
+}
+
+fn provenance(capture: &ScanCapture, module: &ModuleInfo) -> &'static str {
+    let in_phdr = capture
+        .phdr
+        .iter()
+        .any(|item| item.base_addr == module.base_addr);
+    let in_maps = capture
+        .maps
+        .iter()
+        .any(|item| item.base_addr == module.base_addr);
+    match (in_phdr, in_maps) {
+        (true, true) => "phdr+maps",
+        (true, false) => "phdr",
+        _ => "maps",
+    }
+}
+
+// 每行一条记录，路径放在行尾以保留其中的空格；单轮超过字节上限时截断并标记
+pub(super) fn encode(capture: &ScanCapture) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{}", SNAPSHOT_HEADER);
+    let _ = writeln!(out, "pass {}", capture.pass);
+    let _ = writeln!(out, "dlinfo {}", u8::from(capture.dlinfo));
+    for (tag, modules) in [("phdr", &capture.phdr), ("maps", &capture.maps)] {
+        for module in modules {
+            let _ = writeln!(
+                out,
+                "{} 0x{:x} 0x{:x} 0x{:x} {}",
+                tag, module.base_addr, module.instance_id, module.namespace_id, module.pathname
+            );
+        }
+    }
+    for (base, hint) in &capture.identities {
+        let _ = writeln!(
+            out,
+            "hint-identity 0x{:x} 0x{:x} 0x{:x}",
+            base, hint.instance_id, hint.namespace_id
+        );
+    }
+    for (instance, namespace) in &capture.instance_namespaces {
+        let _ = writeln!(out, "hint-instance 0x{:x} 0x{:x}", instance, namespace);
+    }
+    for ((base, instance), namespace) in &capture.noload {
+        let _ = writeln!(
+            out,
+            "hint-noload 0x{:x} 0x{:x} 0x{:x}",
+            base, instance, namespace
+        );
+    }
+    for (path, namespace) in &capture.paths {
+        let _ = writeln!(out, "hint-path 0x{:x} {}", namespace, path);
+    }
+    for module in &capture.modules {
+        let _ = writeln!(
+            out,
+            "module 0x{:x} 0x{:x} 0x{:x} {} {}",
+            module.base_addr,
+            module.instance_id,
+            module.namespace_id,
+            provenance(capture, module),
+            module.pathname
+        );
+    }
+    if out.len() > SNAPSHOT_BYTES_LIMIT {
+        let cut = out[..SNAPSHOT_BYTES_LIMIT]
+            .rfind('\n')
+            .map_or(0, |pos| pos + 1);
+        out.truncate(cut);
+        out.push_str("truncated\n");
+    }
+    out.push_str("end\n");
+    out
+}
+
+#[cfg(test)]
+fn parse_hex(field: &str) -> Result<usize, String> {
+    let digits = field.strip_prefix("0x").unwrap_or(field);
+    usize::from_str_radix(digits, 16).map_err(|_| format!("bad hex field {field}"))
+}
+
+// 按空格拆出 count 个十六进制字段，剩余部分原样作为尾部文本
+#[cfg(test)]
+fn split_fields(rest: &str, count: usize) -> Result<(Vec<usize>, &str), String> {
+    let mut values = Vec::with_capacity(count);
+    let mut rest = rest;
+    for _ in 0..count {
+        let (field, tail) = rest.split_once(' ').unwrap_or((rest, ""));
+        values.push(parse_hex(field)?);
+        rest = tail;
+    }
+    Ok((values, rest))
+}
+
+#[cfg(test)]
+fn module_record(rest: &str) -> Result<ModuleInfo, String> {
+    let (values, pathname) = split_fields(rest, 3)?;
+    Ok(ModuleInfo {
+        pathname: pathname.to_string(),
+        base_addr: values[0],
+        instance_id: values[1],
+        namespace_id: values[2],
+        soname: None,
+    })
+}
+
+// 解析导出文本中的全部轮次
+#[cfg(test)]
+pub(super) fn parse(text: &str) -> Result<Vec<ScanCapture>, String> {
+    let mut captures = Vec::new();
+    let mut current: Option<ScanCapture> = None;
+    for (lineno, line) in text.lines().enumerate() {
+        let (tag, rest) = line.split_once(' ').unwrap_or((line, ""));
+        if line == SNAPSHOT_HEADER {
+            current = Some(ScanCapture::default());
+            continue;
+        }
+        let capture = current
+            .as_mut()
+            .ok_or_else(|| format!("line {}: record outside snapshot", lineno + 1))?;
+        let result = match tag {
+            "pass" => rest
+                .parse()
+                .map(|pass| capture.pass = pass)
+                .map_err(|_| "bad pass".into()),
+            "dlinfo" => {
+                capture.dlinfo = rest == "1";
+                Ok(())
+            }
+            "phdr" => module_record(rest).map(|module| capture.phdr.push(module)),
+            "maps" => module_record(rest).map(|module| capture.maps.push(module)),
+            "hint-identity" => split_fields(rest, 3).map(|(values, _)| {
+                capture.identities.insert(
+                    values[0],
+                    ObservedIdentityHint {
+                        instance_id: values[1],
+                        namespace_id: values[2],
+                    },
+                );
+            }),
+            "hint-instance" => split_fields(rest, 2).map(|(values, _)| {
+                capture.instance_namespaces.insert(values[0], values[1]);
+            }),
+            "hint-noload" => split_fields(rest, 3).map(|(values, _)| {
+                capture.noload.insert((values[0], values[1]), values[2]);
+            }),
+            "hint-path" => split_fields(rest, 1).map(|(values, path)| {
+                capture.paths.insert(path.to_string(), values[0]);
+            }),
+            "module" => split_fields(rest, 3).and_then(|(values, tail)| {
+                let (_, pathname) = tail.split_once(' ').ok_or("missing provenance")?;
+                capture.modules.push(ModuleInfo {
+                    pathname: pathname.to_string(),
+                    base_addr: values[0],
+                    instance_id: values[1],
+                    namespace_id: values[2],
+                    soname: None,
+                });
+                Ok(())
+            }),
+            "truncated" => Err("snapshot truncated".into()),
+            "end" => {
+                captures.extend(current.take());
+                Ok(())
+            }
+            _ => Err(format!("unknown record {tag}")),
+        };
+        result.map_err(|err| format!("line {}: {}", lineno + 1, err))?;
+    }
+    if current.is_some() {
+        return Err("missing end record".into());
+    }
+    Ok(captures)
+}
+
+// 离线回放：以快照输入重走合并、hint 应用与规则匹配，输出每一步的判定
+// noload 解析只查快照中的缓存，不做实时 dlopen，结果可确定复现
+#[cfg(test)]
+pub(super) fn replay(text: &str, rules: &[&str]) -> Result<Vec<String>, String> {
+    use super::hints::{
+        module_noload_key, resolve_hinted_namespaces, resolve_namespace_id_in_paths,
+    };
+    use crate::runtime::rules::module_match;
+
+    let mut decisions = Vec::new();
+    for mut capture in parse(text)? {
+        let mut modules = super::merge_module_sources(capture.phdr.clone(), capture.maps.clone());
+        let noload = &capture.noload;
+        resolve_hinted_namespaces(
+            &mut modules,
+            &mut capture.identities,
+            &capture.instance_namespaces,
+            |pathname| resolve_namespace_id_in_paths(&capture.paths, pathname),
+            |module| {
+                capture
+                    .dlinfo
+                    .then(|| noload.get(&module_noload_key(module)).copied())
+                    .flatten()
+                    .filter(|namespace_id| *namespace_id != 0)
+            },
+        );
+        let modules = super::sort_modules(modules);
+
+        for module in &modules {
+            decisions.push(format!(
+                "pass {} module {}@0x{:x}%0x{:x} ns=0x{:x}",
+                capture.pass,
+                module.pathname,
+                module.base_addr,
+                module.instance_id,
+                module.namespace_id
+            ));
+        }
+        let diverged = capture
+            .modules
+            .iter()
+            .filter(|module| !modules.contains(module))
+            .chain(
+                modules
+                    .iter()
+                    .filter(|module| !capture.modules.contains(module)),
+            );
+        for module in diverged {
+            decisions.push(format!(
+                "pass {} diverged {}@0x{:x}%0x{:x} ns=0x{:x}",
+                capture.pass,
+                module.pathname,
+                module.base_addr,
+                module.instance_id,
+                module.namespace_id
+            ));
+        }
+        for rule in rules {
+            for module in &modules {
+                if module_match(
+                    &module.pathname,
+                    module.soname.as_deref(),
+                    module.base_addr,
+                    module.instance_id,
+                    module.namespace_id,
+                    rule,
+                ) {
+                    decisions.push(format!(
+                        "pass {} rule {} -> {}@0x{:x}",
+                        capture.pass, rule, module.pathname, module.base_addr
+                    ));
+                }
+            }
+        }
+    }
+    Ok(decisions)
+}
@@ -5,6 +5,7 @@ use super::hints::{
 use super::maps::{parse_maps_instance_id, parse_maps_line};
 use super::noload::noload_path_candidates;
 use super::resolve::resolve_namespace_id_from_link_map;
+use super::snapshot::{ScanCapture, encode, parse, replay};
 use super::{LinkMap, ObservedIdentityHint, merge_module_identity};
 use crate::runtime::state::ModuleInfo;
 use std::collections::BTreeMap;
@@ -48,6 +49,7 @@ fn apply_instance_hints_only_for_maps_modules() {
                 base_addr: 0x1000,
                 instance_id: 0xaaaa,
                 namespace_id: 0x1010,
+                soname: None,
             },
             true,
         ),
@@ -57,6 +59,7 @@ fn apply_instance_hints_only_for_maps_modules() {
                 base_addr: 0x2000,
                 instance_id: 0xbbbb,
                 namespace_id: 0,
+                soname: None,
             },
             false,
         ),
@@ -207,12 +210,14 @@ fn merge_identity_prefers_non_zero_namespace_from_fallback() {
         base_addr: 0x1000,
         instance_id: 0x10,
         namespace_id: 0,
+        soname: None,
     };
     let fallback = ModuleInfo {
         pathname: "/system/lib64/libfoo.so".to_string(),
         base_addr: 0x1000,
         instance_id: 0x10,
         namespace_id: 0x88,
+        soname: None,
     };
 
     let merged = merge_module_identity(Some(primary), Some(fallback)).expect("merged identity");
@@ -226,12 +231,14 @@ fn merge_identity_keeps_primary_for_mismatched_module() {
         base_addr: 0x1000,
         instance_id: 0x10,
         namespace_id: 0x20,
+        soname: None,
     };
     let fallback = ModuleInfo {
         pathname: "/system/lib64/libbar.so".to_string(),
         base_addr: 0x2000,
         instance_id: 0x30,
         namespace_id: 0x40,
+        soname: None,
     };
 
     let merged = merge_module_identity(Some(primary.clone()), Some(fallback)).expect("merged identity");
@@ -240,3 +247,78 @@ fn merge_identity_keeps_primary_for_mismatched_module() {
     assert_eq!(merged.instance_id, primary.instance_id);
     assert_eq!(merged.namespace_id, primary.namespace_id);
 }
+
+fn snapshot_module(pathname: &str, base_addr: usize, instance_id: usize) -> ModuleInfo {
+    ModuleInfo {
+        pathname: pathname.to_string(),
+        base_addr,
+        instance_id,
+        namespace_id: 0,
+        soname: None,
+    }
+}
+
+#[test]
+fn scan_snapshot_round_trips_paths_with_spaces() {
+    let capture = ScanCapture {
+        pass: 7,
+        dlinfo: true,
+        phdr: vec![snapshot_module("/data/app/a b/libx.so", 0x1000, 0x11)],
+        maps: vec![snapshot_module("/data/app/a b/libx.so (deleted)", 0x1000, 0x22)],
+        noload: BTreeMap::from([((0x1000, 0x11), 0x77)]),
+        ..ScanCapture::default()
+    };
+    let parsed = parse(&encode(&capture)).expect("snapshot should parse");
+    assert_eq!(parsed.len(), 1);
+    assert_eq!(parsed[0].pass, 7);
+    assert!(parsed[0].dlinfo);
+    assert_eq!(parsed[0].phdr, capture.phdr);
+    assert_eq!(parsed[0].maps, capture.maps);
+    assert_eq!(parsed[0].noload, capture.noload);
+    assert!(parse("pass 1\n").is_err());
+}
+
+#[test]
+fn scan_snapshot_replay_is_deterministic() {
+    // liba 仅 phdr 可见并由路径 hint 补全 namespace；libb 仅 maps 可见并采用身份 hint
+    let mut capture = ScanCapture {
+        pass: 1,
+        dlinfo: false,
+        phdr: vec![snapshot_module("/system/lib64/liba.so", 0x1000, 0xaaaa)],
+        maps: vec![snapshot_module("/vendor/lib64/libb.so", 0x2000, 0xbbbb)],
+        identities: BTreeMap::from([(
+            0x2000,
+            ObservedIdentityHint {
+                instance_id: 0x2222,
+                namespace_id: 0x2223,
+            },
+        )]),
+        paths: BTreeMap::from([("liba.so".to_string(), 0x1010)]),
+        ..ScanCapture::default()
+    };
+    capture.modules = vec![
+        ModuleInfo {
+            namespace_id: 0x1010,
+            soname: None,
+            ..snapshot_module("/system/lib64/liba.so", 0x1000, 0xaaaa)
+        },
+        ModuleInfo {
+            namespace_id: 0x2223,
+            soname: None,
+            ..snapshot_module("/vendor/lib64/libb.so", 0x2000, 0x2222)
+        },
+    ];
+    let text = encode(&capture);
+
+    let decisions = replay(&text, &["libb.so"]).expect("replay should succeed");
+    assert!(!decisions.iter().any(|line| line.contains("diverged")));
+    assert_eq!(
+        decisions,
+        vec![
+            "pass 1 module /system/lib64/liba.so@0x1000%0xaaaa ns=0x1010",
+            "pass 1 module /vendor/lib64/libb.so@0x2000%0x2222 ns=0x2223",
+            "pass 1 rule libb.so -> /vendor/lib64/libb.so@0x2000",
+        ]
+    );
+    assert_eq!(replay(&text, &["libb.so"]).unwrap(), decisions);
+}
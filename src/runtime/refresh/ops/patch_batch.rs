@@ -0,0 +1,71 @@
+// 按页分组的 slot 写入：批次内每页只在首次写入时 mprotect 一次，结束时按原始保护恢复一次
+// 某页切换失败只让该页上的 slot 失败，其余页面照常写入
+use crate::errno::Errno;
+use crate::runtime::state::MutexPoisonRecover;
+use std::sync::Mutex;
+
+use crate::android::memory;
+
+pub(super) struct PatchBatch {
+    // 已可写的页面与需要恢复的原始保护，None 表示本就可写
+    opened: Vec<(usize, Option<u32>)>,
+    // 切换保护失败的页面，本批次内不再重试
+    failed: Vec<(usize, Errno)>,
+}
+
+static ACTIVE_BATCH: Mutex<Option<PatchBatch>> = Mutex::new(None);
+
+impl PatchBatch {
+    fn open_page(&mut self, addr: usize, pathname: &str) -> Result<(), Errno> {
+        let page = memory::page_start(addr);
+        if self.opened.iter().any(|&(open, _)| open == page) {
+            return Ok(());
+        }
+        if let Some(&(_, err)) = self.failed.iter().find(|&&(failed, _)| failed == page) {
+            return Err(err);
+        }
+        match super::make_writable(addr, pathname) {
+            Ok(restore_prot) => {
+                self.opened.push((page, restore_prot));
+                Ok(())
+            }
+            Err(err) => {
+                self.failed.push((page, err));
+                Err(err)
+            }
+        }
+    }
+
+    fn close(self) {
+        for (page, restore_prot) in self.opened {
+            if let Some(old_prot) = restore_prot {
+                super::restore_protect(page, old_prot);
+            }
+        }
+    }
+}
+
+pub(super) fn begin() {
+    let mut active = ACTIVE_BATCH.lock_or_poison();
+    if active.is_none() {
+        *active = Some(PatchBatch {
+            opened: Vec::new(),
+            failed: Vec::new(),
+        });
+    }
+}
+
+// 没有进行中的批次时返回 None，由调用方自行切换并恢复保护
+pub(super) fn open_page(addr: usize, pathname: &str) -> Option<Result<(), Errno>> {
+    ACTIVE_BATCH
+        .lock_or_poison()
+        .as_mut()
+        .map(|batch| batch.open_page(addr, pathname))
+}
+
+pub(super) fn finish() {
+    let batch = ACTIVE_BATCH.lock_or_poison().take();
+    if let Some(batch) = batch {
+        batch.close();
+    }
+}
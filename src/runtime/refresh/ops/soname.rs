@@ -0,0 +1,47 @@
+// 模块 DT_SONAME 的按需解析：注册过 soname: 规则后，模块扫描才为每个模块读取 SONAME
+// 解析结果按 (base_addr, 路径) 缓存，扫描时丢弃已不在列表中的模块
+use crate::runtime::state::MutexPoisonRecover;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::super::super::state::ModuleInfo;
+use super::{GuardPhase, init_elf_guard, signal_guard};
+
+static SONAME_SCAN: AtomicBool = AtomicBool::new(false);
+static SONAME_CACHE: Mutex<BTreeMap<(usize, String), Option<String>>> = Mutex::new(BTreeMap::new());
+
+pub(super) fn set_enabled(flag: bool) {
+    SONAME_SCAN.store(flag, Ordering::Relaxed);
+    if !flag {
+        SONAME_CACHE.lock_or_poison().clear();
+    }
+}
+
+pub(super) fn fill_sonames(modules: &mut [ModuleInfo]) {
+    if !SONAME_SCAN.load(Ordering::Relaxed) {
+        return;
+    }
+    let live: BTreeSet<(usize, &str)> = modules
+        .iter()
+        .map(|module| (module.base_addr, module.pathname.as_str()))
+        .collect();
+    let mut cache = SONAME_CACHE.lock_or_poison();
+    cache.retain(|(base_addr, pathname), _| live.contains(&(*base_addr, pathname.as_str())));
+    for module in modules.iter_mut() {
+        module.soname = cache
+            .entry((module.base_addr, module.pathname.clone()))
+            .or_insert_with(|| read_soname(module.base_addr, &module.pathname))
+            .clone();
+    }
+}
+
+// 解析失败的模块缓存为 None，不在每轮扫描中重试
+fn read_soname(base_addr: usize, pathname: &str) -> Option<String> {
+    let elf = init_elf_guard(base_addr, pathname).ok()?;
+    signal_guard::with_guard_tagged(GuardPhase::ElfLookup, || unsafe {
+        elf.soname().map(ToString::to_string)
+    })
+    .ok()
+    .flatten()
+}
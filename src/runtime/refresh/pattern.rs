@@ -0,0 +1,161 @@
+// 通配符号任务：在 caller 的重定位表中展开为具体符号名后逐个挂载，回调、事件与记录都使用具体名称
+// 单个模块内匹配数超过上限时整个模块不挂载并以 PatternLimit 上报，避免 "*" 一类的模式接管全部导入
+use crate::errno::Errno;
+use crate::log;
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::super::record;
+use super::super::state::{CoreState, ModuleInfo, Task};
+use super::CallbackEvent;
+use super::apply::{
+    emit_event, emit_nosym_event, hook_event_kind, hook_slots, note_rehooked, record_op,
+    report_text_relocs,
+};
+use super::env::RefreshEnv;
+use super::matcher::CalleeResolve;
+
+pub(super) const DEFAULT_MATCH_LIMIT: usize = 64;
+
+static MATCH_LIMIT: AtomicUsize = AtomicUsize::new(DEFAULT_MATCH_LIMIT);
+
+pub(super) fn set_match_limit(limit: usize) {
+    MATCH_LIMIT.store(limit, Ordering::Relaxed);
+}
+
+pub(super) fn match_limit() -> usize {
+    MATCH_LIMIT.load(Ordering::Relaxed)
+}
+
+// 展开通配模式并逐个符号挂载，返回是否有 slot 已挂上该任务
+pub(super) fn apply_pattern_slots<E: RefreshEnv>(
+    env: &E,
+    state: &mut CoreState,
+    task: &Task,
+    caller: &ModuleInfo,
+    callee: &CalleeResolve,
+    events: &mut Vec<CallbackEvent>,
+) -> Result<bool, Errno> {
+    let image = env.open(caller.base_addr, &caller.pathname)?;
+    let prepare_status = env.prepare_caller(caller, &image);
+    if prepare_status != Errno::Ok {
+        emit_event(task, caller, prepare_status, 0, events);
+        return Err(prepare_status);
+    }
+
+    let kind = hook_event_kind(state, task, caller);
+    let names = env.find_import_names(&image, &task.sym_name)?;
+    let limit = match_limit();
+    if names.len() > limit {
+        log::warn(format_args!(
+            "pattern {} matches {} symbols in {}, limit {}",
+            task.sym_name,
+            names.len(),
+            caller.pathname,
+            limit
+        ));
+        let status = Errno::PatternLimit;
+        record::add_module_hook_record(
+            state,
+            record_op(kind),
+            status.as_i32(),
+            caller,
+            &task.sym_name,
+            task.new_func,
+            task.stub,
+        );
+        emit_event(task, caller, status, 0, events);
+        return Err(status);
+    }
+
+    // callee 模块只解析一次，各符号的导出地址在同一批映像中查找
+    let callee_images = callee
+        .modules
+        .iter()
+        .map(|module| {
+            Ok((
+                module.namespace_id,
+                env.open(module.base_addr, &module.pathname)?,
+            ))
+        })
+        .collect::<Result<Vec<_>, Errno>>()?;
+
+    let mut hooked_any = false;
+    for sym_name in names {
+        let concrete = Task {
+            sym_name,
+            ..task.clone()
+        };
+        let resolve = resolve_symbol_callee(env, &concrete, &callee_images)?;
+        let expected = match resolve.addrs_for_caller(&concrete, caller) {
+            Ok(Some(addrs)) if addrs.is_empty() => continue,
+            Ok(expected) => expected,
+            Err(err) => {
+                emit_event(&concrete, caller, err, 0, events);
+                return Err(err);
+            }
+        };
+
+        let text_relocs = env.text_reloc_count(&image, &concrete.sym_name);
+        report_text_relocs(text_relocs, &concrete, caller);
+        let got_slots = match env.find_slots(&image, &concrete.sym_name, expected.as_ref()) {
+            Err(Errno::UnsupportedReloc) => continue,
+            result => result?,
+        };
+        if got_slots.is_empty() {
+            continue;
+        }
+        let before = task_slot_count(state, task);
+        let slot_addrs = got_slots.into_vec();
+        hooked_any |= hook_slots(env, state, &concrete, caller, slot_addrs, kind, events)?;
+        if task_slot_count(state, task) > before {
+            record::add_module_hook_record(
+                state,
+                record_op(kind),
+                Errno::Ok.as_i32(),
+                caller,
+                &concrete.sym_name,
+                task.new_func,
+                task.stub,
+            );
+        }
+    }
+
+    if hooked_any {
+        note_rehooked(state, task, caller);
+    } else {
+        emit_nosym_event(task, caller, events);
+    }
+    Ok(hooked_any)
+}
+
+// 具体符号在 callee 映像中的导出地址，任务未限定 callee 时不过滤
+fn resolve_symbol_callee<E: RefreshEnv>(
+    env: &E,
+    task: &Task,
+    callee_images: &[(usize, E::Image)],
+) -> Result<CalleeResolve, Errno> {
+    let mut resolve = CalleeResolve {
+        addrs: task.callee_path_name.as_ref().map(|_| BTreeSet::new()),
+        candidates: Vec::new(),
+        modules: Vec::new(),
+    };
+    let Some(addrs) = resolve.addrs.as_mut() else {
+        return Ok(resolve);
+    };
+    for (namespace_id, image) in callee_images {
+        let exports = env.find_exports(image, &task.sym_name)?;
+        if !exports.is_empty() {
+            addrs.extend(exports.iter().copied());
+            resolve.candidates.push((*namespace_id, exports));
+        }
+    }
+    Ok(resolve)
+}
+
+fn task_slot_count(state: &CoreState, task: &Task) -> usize {
+    state
+        .task_slots
+        .get(&task.stub)
+        .map_or(0, |keys| keys.len())
+}
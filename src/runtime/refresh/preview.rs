@@ -0,0 +1,208 @@
+// 调用链预览：按注册 hook 时相同的匹配方式定位 slot，只读地报告新 hook 将加入的链
+// 已管理的 slot 列出 hub 中启用的 proxy；未管理的 slot 当前值不是解析出的导出地址时报告外部改写
+use crate::android::memory;
+use crate::api::{
+    CalleeSelection, ChainEntryInfo, ChainOwner, ChainPreview, ModuleIdentity, SingleBindPolicy,
+};
+use crate::errno::Errno;
+use std::collections::BTreeSet;
+
+use super::super::hub;
+use super::super::rules::{module_match, should_ignore};
+use super::super::state::{
+    CoreState, DEFAULT_PROXY_PRIORITY, ModuleInfo, SlotEntry, SlotKey, Task, TaskType,
+};
+use super::env::RefreshEnv;
+use super::matcher::resolve_callee_addrs;
+
+pub(super) fn preview_chain<E: RefreshEnv>(
+    env: &E,
+    state: &CoreState,
+    modules: &[ModuleInfo],
+    caller_rule: &str,
+    callee_rule: Option<&str>,
+    sym_name: &str,
+) -> Result<Vec<ChainPreview>, Errno> {
+    // 所有模块中该符号的导出地址；限定 callee 时期望值收窄到匹配 callee 的导出
+    let exports = all_exports(env, modules, sym_name);
+    let expected = match callee_rule {
+        Some(rule) => {
+            let probe = Task {
+                stub: 0,
+                task_type: TaskType::Single,
+                caller_path_name: Some(caller_rule.to_string()),
+                caller_allow_filter: None,
+                caller_allow_list: Vec::new(),
+                callee_path_name: Some(rule.to_string()),
+                sym_name: sym_name.to_string(),
+                new_func: 0,
+                hooked: None,
+                event_callback: None,
+                callee_selection: CalleeSelection::All,
+                bind_policy: SingleBindPolicy::FirstMatch,
+                paused: false,
+                priority: DEFAULT_PROXY_PRIORITY,
+                threads: Vec::new(),
+            };
+            resolve_callee_addrs(env, &probe, modules)?
+                .addrs
+                .unwrap_or_default()
+        }
+        None => exports.clone(),
+    };
+
+    let is_expected = |addr: usize| expected.contains(&memory::canonical_code_addr(addr));
+
+    let mut previews = Vec::new();
+    for caller in modules {
+        if !module_match(
+            &caller.pathname,
+            caller.soname.as_deref(),
+            caller.base_addr,
+            caller.instance_id,
+            caller.namespace_id,
+            caller_rule,
+        ) || should_ignore(
+            &caller.pathname,
+            caller.soname.as_deref(),
+            caller.base_addr,
+            caller.instance_id,
+            caller.namespace_id,
+            &state.ignore_callers,
+        ) {
+            continue;
+        }
+        let Ok(image) = env.open(caller.base_addr, &caller.pathname) else {
+            continue;
+        };
+        let Ok(slots) = env.find_slots(&image, sym_name, None) else {
+            continue;
+        };
+        for slot_addr in slots.into_vec() {
+            let key = SlotKey {
+                caller_path_name: caller.pathname.clone(),
+                caller_base_addr: caller.base_addr,
+                caller_instance_id: caller.instance_id,
+                caller_namespace_id: caller.namespace_id,
+                slot_addr,
+            };
+            let Ok(current) = env.read_slot(slot_addr) else {
+                continue;
+            };
+            let current = memory::canonical_code_addr(current);
+            let preview = match state.slots.get(&key) {
+                // 已管理 slot 的原函数属于其他 callee 时不在预览范围内
+                Some(slot) if callee_rule.is_some() && !is_expected(slot.orig_func) => {
+                    continue;
+                }
+                Some(slot) => managed_preview(env, state, caller, &key, slot, current),
+                // 指向其他 callee 导出的未管理 slot 同样跳过
+                None if exports.contains(&current) && !expected.contains(&current) => continue,
+                None => unmanaged_preview(env, caller, slot_addr, current, &expected),
+            };
+            previews.push(preview);
+        }
+    }
+    Ok(previews)
+}
+
+fn all_exports<E: RefreshEnv>(env: &E, modules: &[ModuleInfo], sym_name: &str) -> BTreeSet<usize> {
+    let mut exports = BTreeSet::new();
+    for module in modules {
+        let Ok(image) = env.open(module.base_addr, &module.pathname) else {
+            continue;
+        };
+        if let Ok(addrs) = env.find_exports(&image, sym_name) {
+            exports.extend(addrs.into_iter().map(memory::canonical_code_addr));
+        }
+    }
+    exports
+}
+
+fn identity(module: &ModuleInfo) -> ModuleIdentity {
+    ModuleIdentity {
+        pathname: module.pathname.clone(),
+        base_addr: module.base_addr,
+        instance_id: module.instance_id,
+        namespace_id: module.namespace_id,
+    }
+}
+
+fn foreign_entry<E: RefreshEnv>(env: &E, func: usize) -> ChainEntryInfo {
+    ChainEntryInfo {
+        func,
+        owner: ChainOwner::Foreign,
+        symbolized: env.symbolize(func),
+    }
+}
+
+// slot 被外部改写为非 trampoline 时，外部入口排在我们的链之前
+fn managed_preview<E: RefreshEnv>(
+    env: &E,
+    state: &CoreState,
+    caller: &ModuleInfo,
+    key: &SlotKey,
+    slot: &SlotEntry,
+    current: usize,
+) -> ChainPreview {
+    let hub_ptr = slot.hub_ptr as *mut hub::Hub;
+    let mut entries = Vec::new();
+    if current != hub::hub_trampo(hub_ptr) {
+        entries.push(foreign_entry(env, current));
+    }
+    for func in hub::enabled_proxies(hub_ptr) {
+        let owner = slot
+            .task_chain
+            .iter()
+            .find(|stub| {
+                state
+                    .tasks
+                    .get(stub)
+                    .is_some_and(|task| task.new_func == func)
+            })
+            .map_or(ChainOwner::Foreign, |stub| ChainOwner::OurTask(*stub));
+        let symbolized = match owner {
+            ChainOwner::Foreign => env.symbolize(func),
+            ChainOwner::OurTask(_) => None,
+        };
+        entries.push(ChainEntryInfo {
+            func,
+            owner,
+            symbolized,
+        });
+    }
+    ChainPreview {
+        module: identity(caller),
+        slot_addr: key.slot_addr,
+        entries,
+        orig: slot.orig_func,
+    }
+}
+
+fn unmanaged_preview<E: RefreshEnv>(
+    env: &E,
+    caller: &ModuleInfo,
+    slot_addr: usize,
+    current: usize,
+    exports: &BTreeSet<usize>,
+) -> ChainPreview {
+    if exports.contains(&current) {
+        return ChainPreview {
+            module: identity(caller),
+            slot_addr,
+            entries: Vec::new(),
+            orig: current,
+        };
+    }
+    // 无法确定外部 hook 之下的原函数，仅在导出地址唯一时给出
+    let orig = match exports.len() {
+        1 => exports.iter().next().copied().unwrap_or(0),
+        _ => 0,
+    };
+    ChainPreview {
+        module: identity(caller),
+        slot_addr,
+        entries: vec![foreign_entry(env, current)],
+        orig,
+    }
+}
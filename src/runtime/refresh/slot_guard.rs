@@ -0,0 +1,170 @@
+// 已挂载 slot 的完整性巡检：回读 GOT，被其他框架改写时重新写回 hub trampoline
+// 改写值不是解析出的原函数时接为链尾，由外来 hook 继续调用链，而不是直接覆盖
+use crate::api::{HookEventKind, RecordOp};
+use crate::errno::Errno;
+use crate::log;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::super::hub;
+use super::super::record;
+use super::super::state::{CoreState, ModuleInfo, SlotKey};
+use super::CallbackEvent;
+use super::env::RefreshEnv;
+use super::slot_write;
+
+static SLOT_GUARD: AtomicBool = AtomicBool::new(false);
+
+pub(super) fn set_enabled(flag: bool) {
+    SLOT_GUARD.store(flag, Ordering::Relaxed);
+}
+
+pub(super) fn is_enabled() -> bool {
+    SLOT_GUARD.load(Ordering::Relaxed)
+}
+
+// 逐个回读带 hub 的 slot，返回首个重新写入失败的错误；已拉黑的页不再重试
+// slot 仍指向 trampoline 的也检查接入的外来链尾：对方 unhook 时通常写回保存的值，即本 hub 的 trampoline
+pub(super) fn verify_slots<E: RefreshEnv>(
+    env: &E,
+    state: &mut CoreState,
+    events: &mut Vec<CallbackEvent>,
+) -> Errno {
+    let mut tampered = Vec::new();
+    let mut dead_tails = Vec::new();
+    for (key, slot) in &state.slots {
+        if slot.hub_ptr == 0 || slot_write::is_rejected(&state.write_guard, key) {
+            continue;
+        }
+        let hub_ptr = slot.hub_ptr as *mut hub::Hub;
+        let Ok(current) = env.read_slot(key.slot_addr) else {
+            continue;
+        };
+        if current != hub::hub_trampo(hub_ptr) {
+            tampered.push((key.clone(), current));
+        } else if hub::has_foreign_tail(hub_ptr) && !env.is_code_mapped(hub::hub_tail(hub_ptr)) {
+            dead_tails.push(key.clone());
+        }
+    }
+    for key in dead_tails {
+        drop_dead_tail(env, state, &key);
+    }
+
+    let mut first_err = Errno::Ok;
+    for (key, current) in tampered {
+        let status = repair_slot(env, state, &key, current);
+        if status != Errno::SlotTampered && first_err.is_ok() {
+            first_err = status;
+        }
+        report_repair(state, &key, status, events);
+    }
+    first_err
+}
+
+// 外来链尾已不在可执行映射中：链尾复原为原函数，不再有任务的直通 hub 随之撤下
+fn drop_dead_tail<E: RefreshEnv>(env: &E, state: &mut CoreState, key: &SlotKey) {
+    let Some(slot) = state.slots.get_mut(key) else {
+        return;
+    };
+    let hub_ptr = slot.hub_ptr as *mut hub::Hub;
+    let orig = hub::hub_orig(hub_ptr);
+    log::warn(format_args!(
+        "slot 0x{:x} in {} foreign tail 0x{:x} no longer mapped, reset to 0x{:x}",
+        key.slot_addr,
+        key.caller_path_name,
+        hub::hub_tail(hub_ptr),
+        orig
+    ));
+    hub::set_tail(hub_ptr, orig);
+    slot.orig_func = orig;
+    if !slot.task_chain.is_empty() {
+        return;
+    }
+    if slot_write::write_slot(env, &mut state.write_guard, key, orig).is_ok() {
+        hub::destroy_hub(hub_ptr, true);
+        state.slots.remove(key);
+    }
+}
+
+// 成功写回时返回 SlotTampered，否则返回写入错误；写入失败时撤销对链尾的修改
+fn repair_slot<E: RefreshEnv>(
+    env: &E,
+    state: &mut CoreState,
+    key: &SlotKey,
+    current: usize,
+) -> Errno {
+    let Some(slot) = state.slots.get_mut(key) else {
+        return Errno::NotFound;
+    };
+    let hub_ptr = slot.hub_ptr as *mut hub::Hub;
+    let prev_tail = hub::hub_tail(hub_ptr);
+    // 改回解析出的原函数说明外来 hook 已撤销，链尾随之复原
+    let new_tail = if current == 0 { prev_tail } else { current };
+    let action = if new_tail == hub::hub_orig(hub_ptr) {
+        "re-patch"
+    } else {
+        "chain as tail"
+    };
+    log::warn(format_args!(
+        "slot 0x{:x} in {} overwritten with 0x{:x}, {}",
+        key.slot_addr, key.caller_path_name, current, action
+    ));
+    let retail = new_tail != prev_tail;
+    if retail {
+        hub::set_tail(hub_ptr, new_tail);
+    }
+    match slot_write::write_slot(env, &mut state.write_guard, key, hub::hub_trampo(hub_ptr)) {
+        Ok(()) => {
+            slot.orig_func = new_tail;
+            Errno::SlotTampered
+        }
+        Err(err) => {
+            if retail {
+                hub::set_tail(hub_ptr, prev_tail);
+            }
+            err
+        }
+    }
+}
+
+// slot 上的每个任务各记一条 REHOOK 记录并投递一次 Rehooked 事件
+fn report_repair(
+    state: &mut CoreState,
+    key: &SlotKey,
+    status: Errno,
+    events: &mut Vec<CallbackEvent>,
+) {
+    let Some(slot) = state.slots.get(key) else {
+        return;
+    };
+    let prev_func = slot.orig_func;
+    let tasks: Vec<_> = slot
+        .task_chain
+        .iter()
+        .filter_map(|stub| state.tasks.get(stub).cloned())
+        .collect();
+    let caller = ModuleInfo {
+        pathname: key.caller_path_name.clone(),
+        base_addr: key.caller_base_addr,
+        instance_id: key.caller_instance_id,
+        namespace_id: key.caller_namespace_id,
+        soname: None,
+    };
+    for task in &tasks {
+        record::add_module_hook_record(
+            state,
+            RecordOp::Rehook,
+            status.as_i32(),
+            &caller,
+            &task.sym_name,
+            task.new_func,
+            task.stub,
+        );
+        events.extend(CallbackEvent::for_task(
+            task,
+            HookEventKind::Rehooked,
+            status,
+            &caller.pathname,
+            prev_func,
+        ));
+    }
+}
@@ -0,0 +1,105 @@
+// 带回读校验的 slot 写入：部分设备的受保护映射上 mprotect 成功但写入无效或触发异常
+// 确认写入被拒绝后将 (模块实例, 页) 加入黑名单，该实例存活期间不再重试
+use crate::android::memory;
+use crate::errno::Errno;
+use crate::log;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::super::state::{SlotKey, SlotWriteGuard};
+use super::env::SlotPatcher;
+use super::module_registry::module_instance_key;
+use super::ops;
+
+// 受保护写入连续失败达到该次数后判定为拒绝写入
+const WRITE_FAULT_LIMIT: u32 = 2;
+
+static WRITE_REJECTED_PAGES: AtomicU64 = AtomicU64::new(0);
+// 写入后回读不一致的次数，与触发保护的写入失败分开统计
+static PATCH_VERIFY_FAILURES: AtomicU64 = AtomicU64::new(0);
+
+pub(super) fn rejected_page_count() -> u64 {
+    WRITE_REJECTED_PAGES.load(Ordering::Relaxed)
+}
+
+pub(super) fn verify_failure_count() -> u64 {
+    PATCH_VERIFY_FAILURES.load(Ordering::Relaxed)
+}
+
+fn page_key(key: &SlotKey) -> (String, usize) {
+    (
+        module_instance_key(
+            &key.caller_path_name,
+            key.caller_base_addr,
+            key.caller_instance_id,
+            key.caller_namespace_id,
+        ),
+        memory::page_start(key.slot_addr),
+    )
+}
+
+pub(super) fn is_rejected(guard: &SlotWriteGuard, key: &SlotKey) -> bool {
+    !guard.rejected.is_empty() && guard.rejected.contains(&page_key(key))
+}
+
+// 写入后经安全读取回读比较，不一致或反复触发保护时拉黑该页并返回 WriteRejected
+pub(super) fn write_slot<E: SlotPatcher>(
+    env: &E,
+    guard: &mut SlotWriteGuard,
+    key: &SlotKey,
+    value: usize,
+) -> Result<(), Errno> {
+    if is_rejected(guard, key) {
+        return Err(Errno::WriteRejected);
+    }
+    match env.patch_slot(key.slot_addr, value, &key.caller_path_name) {
+        Ok(()) => {}
+        Err(Errno::SetGot) => {
+            let page = page_key(key);
+            let faults = guard.faults.entry(page.clone()).or_default();
+            *faults += 1;
+            if *faults < WRITE_FAULT_LIMIT {
+                return Err(Errno::SetGot);
+            }
+            reject_page(guard, page, key, "guarded fault");
+            return Err(Errno::WriteRejected);
+        }
+        // 写入落在过期别名上，重试同样无效，拉黑该页但保留 PatchVerifyFailed 供记录期望值与回读值
+        Err(Errno::PatchVerifyFailed) => {
+            PATCH_VERIFY_FAILURES.fetch_add(1, Ordering::Relaxed);
+            reject_page(guard, page_key(key), key, "stale alias");
+            return Err(Errno::PatchVerifyFailed);
+        }
+        Err(Errno::GotVerify) => {
+            reject_page(guard, page_key(key), key, "read-back mismatch");
+            return Err(Errno::WriteRejected);
+        }
+        Err(err) => return Err(err),
+    }
+    if !ops::is_write_verify_enabled() {
+        return Ok(());
+    }
+    match env.read_slot(key.slot_addr) {
+        Ok(current) if current == value => Ok(()),
+        _ => {
+            reject_page(guard, page_key(key), key, "read-back mismatch");
+            Err(Errno::WriteRejected)
+        }
+    }
+}
+
+fn reject_page(guard: &mut SlotWriteGuard, page: (String, usize), key: &SlotKey, reason: &str) {
+    guard.faults.remove(&page);
+    if guard.rejected.insert(page) {
+        WRITE_REJECTED_PAGES.fetch_add(1, Ordering::Relaxed);
+        log::warn(format_args!(
+            "slot write rejected ({}) at 0x{:x} in {}, page blacklisted",
+            reason, key.slot_addr, key.caller_path_name
+        ));
+    }
+}
+
+// 已卸载模块实例的黑名单与失败计数随之失效
+pub(super) fn prune_dead_pages(guard: &mut SlotWriteGuard, is_alive: impl Fn(&str) -> bool) {
+    guard.faults.retain(|(module, _), _| is_alive(module));
+    guard.rejected.retain(|(module, _)| is_alive(module));
+}
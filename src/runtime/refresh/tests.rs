@@ -0,0 +1,1344 @@
+use crate::api::{
+    CalleeSelection, ChainOwner, HookEventKind, HookStub, RecordOp, SingleBindPolicy,
+};
+use crate::elf::GotSlots;
+use crate::errno::Errno;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::{c_char, c_void};
+
+use super::super::hub;
+use super::super::rules::sym_pattern_match;
+use super::super::state::{
+    CoreState, DEFAULT_PROXY_PRIORITY, HookEventEntry, HookedEntry, ModuleInfo, SlotKey, Task,
+    TaskType,
+};
+use super::env::{ElfReader, ModuleProvider, SlotPatcher};
+use super::pattern::{DEFAULT_MATCH_LIMIT, set_match_limit};
+use super::preview::preview_chain;
+use super::slot_guard::verify_slots;
+use super::{
+    CallbackEvent, refresh_internal, refresh_module_with, refresh_pass, refresh_unloaded_with,
+    restore_all_with, set_task_paused, task_orig_funcs, unhook_events, unhook_task_with,
+};
+
+const SYMBOL: &str = "puts";
+const LIBC_PUTS: usize = 0xc0c0;
+const OTHER_PUTS: usize = 0xd0d0;
+
+struct FakeModule {
+    info: ModuleInfo,
+    exports: BTreeMap<&'static str, usize>,
+    // 与 exports 同名时排在全局定义之后，对应 STB_WEAK
+    weak_exports: BTreeMap<&'static str, usize>,
+    plt_slots: BTreeMap<&'static str, Vec<usize>>,
+}
+
+// 内存中的进程模型：模块列表与 slot 内存均可在两次刷新之间修改
+#[derive(Default)]
+struct FakeEnv {
+    modules: RefCell<Vec<FakeModule>>,
+    memory: RefCell<BTreeMap<usize, usize>>,
+    patch_count: RefCell<usize>,
+    // 写入静默无效的 slot，模拟受保护映射
+    ignored_writes: RefCell<BTreeSet<usize>>,
+    // 写入该基址模块的 slot 后立即将其移出模块列表，模拟 refresh 期间的并发 dlclose
+    unload_on_patch: RefCell<Option<usize>>,
+    // 位于已加载模块内、可被符号化的地址，其余地址视为匿名内存
+    module_code: RefCell<BTreeSet<usize>>,
+    // 已解除映射的代码地址，模拟外来 hook 所在库被卸载
+    unmapped_code: RefCell<BTreeSet<usize>>,
+    // ELF 结构损坏的模块基址，查找 slot 时返回 Format
+    corrupt_modules: RefCell<BTreeSet<usize>>,
+    // 只经 IRELATIVE / TLS 重定位引用的符号，查找 slot 时返回 UnsupportedReloc
+    unsupported_symbols: RefCell<BTreeSet<&'static str>>,
+    // 写入落在过期别名上的 slot：写入后回读仍为旧值，返回 PatchVerifyFailed
+    stale_alias_writes: RefCell<BTreeSet<usize>>,
+    // 当前是否处于批量写入中，以及批次外发生的写入次数
+    patch_batch_open: RefCell<bool>,
+    unbatched_patches: RefCell<usize>,
+    // 最近一次全量刷新结束时仍有活动 slot 的模块
+    settled_hooked: RefCell<BTreeSet<(usize, usize)>>,
+    // 模块基址 -> 经 dlopen 观察到的序号
+    observation_orders: RefCell<BTreeMap<usize, u64>>,
+}
+
+impl FakeEnv {
+    fn add_caller(&self, pathname: &str, base_addr: usize, instance_id: usize, slot_value: usize) {
+        let slot_addr = base_addr + 0x100;
+        self.memory.borrow_mut().insert(slot_addr, slot_value);
+        self.modules.borrow_mut().push(FakeModule {
+            info: module_info(pathname, base_addr, instance_id),
+            exports: BTreeMap::new(),
+            weak_exports: BTreeMap::new(),
+            plt_slots: BTreeMap::from([(SYMBOL, vec![slot_addr])]),
+        });
+    }
+
+    fn add_callee(&self, pathname: &str, base_addr: usize, export: usize) {
+        self.modules.borrow_mut().push(FakeModule {
+            info: module_info(pathname, base_addr, base_addr + 1),
+            exports: BTreeMap::from([(SYMBOL, export)]),
+            weak_exports: BTreeMap::new(),
+            plt_slots: BTreeMap::new(),
+        });
+    }
+
+    fn add_weak_export(&self, base_addr: usize, export: usize) {
+        for module in self.modules.borrow_mut().iter_mut() {
+            if module.info.base_addr == base_addr {
+                module.weak_exports.insert(SYMBOL, export);
+            }
+        }
+    }
+
+    // 为已添加的 caller 追加一个导入符号及其 slot
+    fn add_import(&self, base_addr: usize, symbol: &'static str, slot_addr: usize, value: usize) {
+        self.memory.borrow_mut().insert(slot_addr, value);
+        for module in self.modules.borrow_mut().iter_mut() {
+            if module.info.base_addr == base_addr {
+                module.plt_slots.insert(symbol, vec![slot_addr]);
+            }
+        }
+    }
+
+    fn unload(&self, base_addr: usize) {
+        self.modules
+            .borrow_mut()
+            .retain(|module| module.info.base_addr != base_addr);
+    }
+
+    fn slot_value(&self, base_addr: usize) -> usize {
+        self.memory.borrow()[&(base_addr + 0x100)]
+    }
+
+    fn patch_count(&self) -> usize {
+        *self.patch_count.borrow()
+    }
+
+    fn set_namespace(&self, base_addr: usize, namespace_id: usize) {
+        for module in self.modules.borrow_mut().iter_mut() {
+            if module.info.base_addr == base_addr {
+                module.info.namespace_id = namespace_id;
+            }
+        }
+    }
+
+    fn set_soname(&self, base_addr: usize, soname: &str) {
+        for module in self.modules.borrow_mut().iter_mut() {
+            if module.info.base_addr == base_addr {
+                module.info.soname = Some(soname.to_string());
+            }
+        }
+    }
+
+    fn ignore_writes(&self, base_addr: usize) {
+        self.ignored_writes.borrow_mut().insert(base_addr + 0x100);
+    }
+
+    // 模拟其他框架直接改写 slot
+    fn overwrite_slot(&self, base_addr: usize, value: usize) {
+        self.memory.borrow_mut().insert(base_addr + 0x100, value);
+    }
+}
+
+impl ModuleProvider for FakeEnv {
+    fn enumerate_modules(&self) -> Vec<ModuleInfo> {
+        self.modules
+            .borrow()
+            .iter()
+            .map(|module| module.info.clone())
+            .collect()
+    }
+
+    fn on_slots_settled(&self, _modules: &[ModuleInfo], hooked: &BTreeSet<(usize, usize)>) {
+        *self.settled_hooked.borrow_mut() = hooked.clone();
+    }
+
+    fn observation_order(&self, module: &ModuleInfo) -> Option<u64> {
+        self.observation_orders
+            .borrow()
+            .get(&module.base_addr)
+            .copied()
+    }
+
+    fn is_module_unloading(&self, module: &ModuleInfo) -> bool {
+        !self
+            .modules
+            .borrow()
+            .iter()
+            .any(|item| item.info == *module)
+    }
+
+    fn symbolize(&self, addr: usize) -> Option<String> {
+        self.module_code
+            .borrow()
+            .contains(&addr)
+            .then(|| format!("libfake.so+0x{addr:x}"))
+    }
+
+    fn is_code_mapped(&self, addr: usize) -> bool {
+        !self.unmapped_code.borrow().contains(&addr)
+    }
+}
+
+impl SlotPatcher for FakeEnv {
+    fn read_slot(&self, addr: usize) -> Result<usize, Errno> {
+        self.memory
+            .borrow()
+            .get(&addr)
+            .copied()
+            .ok_or(Errno::ReadElf)
+    }
+
+    fn patch_slot(&self, addr: usize, value: usize, _pathname: &str) -> Result<(), Errno> {
+        *self.patch_count.borrow_mut() += 1;
+        if !*self.patch_batch_open.borrow() {
+            *self.unbatched_patches.borrow_mut() += 1;
+        }
+        if self.stale_alias_writes.borrow().contains(&addr) {
+            return Err(Errno::PatchVerifyFailed);
+        }
+        if !self.ignored_writes.borrow().contains(&addr) {
+            self.memory.borrow_mut().insert(addr, value);
+        }
+        let unload = *self.unload_on_patch.borrow();
+        if let Some(base_addr) = unload.filter(|base| addr == base + 0x100) {
+            self.unload_on_patch.borrow_mut().take();
+            self.unload(base_addr);
+        }
+        Ok(())
+    }
+
+    fn begin_patch_batch(&self) {
+        assert!(!self.patch_batch_open.replace(true), "nested patch batch");
+    }
+
+    fn finish_patch_batch(&self) {
+        assert!(self.patch_batch_open.replace(false), "patch batch not open");
+    }
+}
+
+impl ElfReader for FakeEnv {
+    // 以 base_addr 标识已解析的模块
+    type Image = usize;
+
+    fn open(&self, base_addr: usize, _pathname: &str) -> Result<usize, Errno> {
+        self.modules
+            .borrow()
+            .iter()
+            .any(|module| module.info.base_addr == base_addr)
+            .then_some(base_addr)
+            .ok_or(Errno::ReadElf)
+    }
+
+    fn find_exports(&self, image: &usize, symbol: &str) -> Result<Vec<usize>, Errno> {
+        let modules = self.modules.borrow();
+        let module = modules
+            .iter()
+            .find(|module| module.info.base_addr == *image);
+        Ok(module.map_or_else(Vec::new, |module| {
+            let global = module.exports.get(symbol);
+            global
+                .into_iter()
+                .chain(module.weak_exports.get(symbol))
+                .copied()
+                .collect()
+        }))
+    }
+
+    fn find_import_names(&self, image: &usize, pattern: &str) -> Result<Vec<String>, Errno> {
+        let modules = self.modules.borrow();
+        let module = modules
+            .iter()
+            .find(|module| module.info.base_addr == *image);
+        Ok(module
+            .into_iter()
+            .flat_map(|module| module.plt_slots.keys())
+            .filter(|name| sym_pattern_match(pattern, name))
+            .map(|name| name.to_string())
+            .collect())
+    }
+
+    fn find_slots(
+        &self,
+        image: &usize,
+        symbol: &str,
+        callee_addrs: Option<&BTreeSet<usize>>,
+    ) -> Result<GotSlots, Errno> {
+        if self.corrupt_modules.borrow().contains(image) {
+            return Err(Errno::Format);
+        }
+        if self.unsupported_symbols.borrow().contains(symbol) {
+            return Err(Errno::UnsupportedReloc);
+        }
+        let modules = self.modules.borrow();
+        let Some(module) = modules
+            .iter()
+            .find(|module| module.info.base_addr == *image)
+        else {
+            return Ok(GotSlots::default());
+        };
+        let plt = module.plt_slots.get(symbol).cloned().unwrap_or_default();
+        if let Some(expected) = callee_addrs {
+            let memory = self.memory.borrow();
+            if !plt.iter().any(|slot| expected.contains(&memory[slot])) {
+                return Ok(GotSlots::default());
+            }
+        }
+        Ok(GotSlots {
+            plt,
+            data: Vec::new(),
+        })
+    }
+}
+
+fn module_info(pathname: &str, base_addr: usize, instance_id: usize) -> ModuleInfo {
+    ModuleInfo {
+        pathname: pathname.to_string(),
+        base_addr,
+        instance_id,
+        namespace_id: 0,
+        soname: None,
+    }
+}
+
+fn add_task(
+    state: &mut CoreState,
+    task_type: TaskType,
+    caller: Option<&str>,
+    callee: Option<&str>,
+    new_func: usize,
+) -> HookStub {
+    let stub = state.task_order.len() as HookStub + 1;
+    state.tasks.insert(
+        stub,
+        Task {
+            stub,
+            task_type,
+            caller_path_name: caller.map(str::to_string),
+            caller_allow_filter: None,
+            caller_allow_list: Vec::new(),
+            callee_path_name: callee.map(str::to_string),
+            sym_name: SYMBOL.to_string(),
+            new_func,
+            hooked: None,
+            event_callback: None,
+            callee_selection: CalleeSelection::All,
+            bind_policy: SingleBindPolicy::FirstMatch,
+            paused: false,
+            priority: DEFAULT_PROXY_PRIORITY,
+            threads: Vec::new(),
+        },
+    );
+    state.task_order.push(stub);
+    stub
+}
+
+fn hooked_bases(state: &CoreState) -> BTreeSet<usize> {
+    state.slots.keys().map(|key| key.caller_base_addr).collect()
+}
+
+fn slot_key(state: &CoreState, base_addr: usize) -> SlotKey {
+    state
+        .slots
+        .keys()
+        .find(|key| key.caller_base_addr == base_addr)
+        .cloned()
+        .expect("slot not tracked")
+}
+
+#[test]
+fn only_new_refresh_skips_known_modules() {
+    let env = FakeEnv::default();
+    env.add_caller("/system/lib64/liba.so", 0x10000, 1, LIBC_PUTS);
+    env.add_caller("/system/lib64/libb.so", 0x20000, 2, LIBC_PUTS);
+    let mut state = CoreState::default();
+    add_task(&mut state, TaskType::All, None, None, 0xa000);
+
+    let (status, _) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(status, Errno::Ok);
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x10000, 0x20000]));
+    let patches = env.patch_count();
+
+    env.add_caller("/system/lib64/libc_user.so", 0x30000, 3, LIBC_PUTS);
+    let (status, _) = refresh_internal(&env, &mut state, true, None);
+    assert_eq!(status, Errno::Ok);
+    assert_eq!(env.patch_count(), patches + 1);
+    assert_eq!(hooked_bases(&state).len(), 3);
+    assert_eq!(state.known_modules.len(), 3);
+    restore_all_with(&env, &mut state);
+}
+
+#[test]
+fn refresh_unhook_and_restore_patch_inside_batches() {
+    let env = FakeEnv::default();
+    env.add_caller("/system/lib64/liba.so", 0x10000, 1, LIBC_PUTS);
+    env.add_caller("/system/lib64/libb.so", 0x20000, 2, LIBC_PUTS);
+    let mut state = CoreState::default();
+    let stub = add_task(&mut state, TaskType::All, None, None, 0xa000);
+    add_task(&mut state, TaskType::All, None, None, 0xb000);
+
+    let (status, _) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(status, Errno::Ok);
+    let patches = env.patch_count();
+    assert!(patches >= 2);
+    assert_eq!(unhook_task_with(&env, &mut state, stub), Errno::Ok);
+    assert_eq!(env.patch_count(), patches + 2);
+    assert_eq!(restore_all_with(&env, &mut state), Errno::Ok);
+    assert_eq!(env.slot_value(0x10000), LIBC_PUTS);
+    assert!(!*env.patch_batch_open.borrow());
+    assert_eq!(*env.unbatched_patches.borrow(), 0);
+}
+
+// 模块级 CFI 补丁按刷新结束时仍有活动 slot 的模块重新计算
+#[test]
+fn settled_hooked_modules_follow_active_slots() {
+    let env = FakeEnv::default();
+    env.add_caller("/system/lib64/liba.so", 0x10000, 1, LIBC_PUTS);
+    env.add_caller("/system/lib64/libb.so", 0x20000, 2, LIBC_PUTS);
+    let mut state = CoreState::default();
+    let stub = add_task(&mut state, TaskType::Single, Some("liba.so"), None, 0xa000);
+
+    let (status, _) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(status, Errno::Ok);
+    assert_eq!(*env.settled_hooked.borrow(), BTreeSet::from([(0x10000, 1)]));
+
+    // 与 unhook 入口一致，撤销 slot 后同时移除任务
+    assert_eq!(unhook_task_with(&env, &mut state, stub), Errno::Ok);
+    state.tasks.remove(&stub);
+    state.task_order.retain(|value| *value != stub);
+    let (status, _) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(status, Errno::Ok);
+    assert!(env.settled_hooked.borrow().is_empty());
+}
+
+#[test]
+fn malformed_module_is_skipped_without_failing_refresh() {
+    let env = FakeEnv::default();
+    env.add_caller("/system/lib64/liba.so", 0x10000, 1, LIBC_PUTS);
+    env.add_caller("/system/lib64/libbroken.so", 0x20000, 2, LIBC_PUTS);
+    env.corrupt_modules.borrow_mut().insert(0x20000);
+    let mut state = CoreState::default();
+    add_task(&mut state, TaskType::All, None, None, 0xa000);
+    add_task(&mut state, TaskType::All, None, None, 0xb000);
+
+    let (status, _) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(status, Errno::Ok);
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x10000]));
+    assert_eq!(env.slot_value(0x20000), LIBC_PUTS);
+    restore_all_with(&env, &mut state);
+}
+
+#[test]
+fn unsupported_relocation_is_recorded_without_failing_refresh() {
+    let env = FakeEnv::default();
+    env.add_caller("/system/lib64/liba.so", 0x10000, 1, LIBC_PUTS);
+    env.unsupported_symbols.borrow_mut().insert(SYMBOL);
+    let mut state = CoreState {
+        recordable: true,
+        ..Default::default()
+    };
+    add_task(&mut state, TaskType::All, None, None, 0xa000);
+
+    let (status, _) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(status, Errno::Ok);
+    assert!(state.slots.is_empty());
+    let record = state.records.back().expect("unsupported record");
+    assert_eq!(record.status_code, Errno::UnsupportedReloc.as_i32());
+    assert_eq!(record.lib_name, "/system/lib64/liba.so");
+}
+
+#[test]
+fn ignored_caller_is_left_untouched() {
+    let env = FakeEnv::default();
+    env.add_caller("/system/lib64/liba.so", 0x10000, 1, LIBC_PUTS);
+    env.add_caller("/system/lib64/libb.so", 0x20000, 2, LIBC_PUTS);
+    let mut state = CoreState::default();
+    state.ignore_callers.push("libb.so".to_string());
+    add_task(&mut state, TaskType::All, None, None, 0xa000);
+
+    let _ = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x10000]));
+    assert_eq!(env.slot_value(0x20000), LIBC_PUTS);
+    restore_all_with(&env, &mut state);
+}
+
+#[test]
+fn soname_rules_match_parsed_soname_instead_of_path() {
+    let env = FakeEnv::default();
+    env.add_caller("/vendor/lib64/libssl_v3.so", 0x10000, 1, LIBC_PUTS);
+    env.add_caller("/data/a/libssl.so", 0x20000, 2, LIBC_PUTS);
+    env.add_callee("/apex/bionic/lib64/libc.so", 0x90000, LIBC_PUTS);
+    env.set_soname(0x10000, "libssl.so");
+    env.set_soname(0x90000, "libc.so");
+    let mut state = CoreState::default();
+    add_task(
+        &mut state,
+        TaskType::Single,
+        Some("soname:libssl.so"),
+        Some("soname:libc.so"),
+        0xa000,
+    );
+
+    let (status, _) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(status, Errno::Ok);
+    // 路径以 libssl.so 结尾但未声明 SONAME 的模块不匹配
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x10000]));
+    assert_eq!(env.slot_value(0x20000), LIBC_PUTS);
+    restore_all_with(&env, &mut state);
+}
+
+#[test]
+fn single_task_binds_once_and_rebinds_after_unload() {
+    let env = FakeEnv::default();
+    env.add_caller("/data/a/libfoo.so", 0x10000, 1, LIBC_PUTS);
+    env.add_caller("/data/b/libfoo.so", 0x20000, 2, LIBC_PUTS);
+    let mut state = CoreState::default();
+    let stub = add_task(
+        &mut state,
+        TaskType::Single,
+        Some("libfoo.so"),
+        None,
+        0xa000,
+    );
+
+    let _ = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x10000]));
+    assert!(state.single_task_targets.contains_key(&stub));
+
+    // 再次全量刷新不会扩散到同名的第二个实例
+    let _ = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x10000]));
+
+    env.unload(0x10000);
+    let _ = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x20000]));
+    assert!(state.single_task_targets[&stub].contains("/data/b/libfoo.so"));
+    restore_all_with(&env, &mut state);
+}
+
+fn task_bases(state: &CoreState, stub: HookStub) -> BTreeSet<usize> {
+    state
+        .task_slots
+        .get(&stub)
+        .map(|keys| keys.iter().map(|key| key.caller_base_addr).collect())
+        .unwrap_or_default()
+}
+
+#[test]
+fn single_bind_policies_select_instances() {
+    let env = FakeEnv::default();
+    env.add_caller("/data/a/libfoo.so", 0x10000, 1, LIBC_PUTS);
+    env.add_caller("/data/b/libfoo.so", 0x20000, 2, LIBC_PUTS);
+    env.add_caller("/data/c/libfoo.so", 0x30000, 3, LIBC_PUTS);
+    env.observation_orders.borrow_mut().insert(0x10000, 5);
+    let mut state = CoreState::default();
+    let first = add_task(
+        &mut state,
+        TaskType::Single,
+        Some("libfoo.so"),
+        None,
+        0xa000,
+    );
+    let all = add_task(
+        &mut state,
+        TaskType::Single,
+        Some("libfoo.so"),
+        None,
+        0xb000,
+    );
+    let newest = add_task(
+        &mut state,
+        TaskType::Single,
+        Some("libfoo.so"),
+        None,
+        0xc000,
+    );
+    state.tasks.get_mut(&all).unwrap().bind_policy = SingleBindPolicy::AllMatches;
+    state.tasks.get_mut(&newest).unwrap().bind_policy = SingleBindPolicy::NewestInstance;
+
+    let _ = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(task_bases(&state, first), BTreeSet::from([0x10000]));
+    assert_eq!(
+        task_bases(&state, all),
+        BTreeSet::from([0x10000, 0x20000, 0x30000])
+    );
+    assert!(!state.single_task_targets.contains_key(&all));
+    // 被观察到的实例晚于未观察到的实例
+    assert_eq!(task_bases(&state, newest), BTreeSet::from([0x10000]));
+
+    // 绑定的实例卸载后，剩余实例都未被观察到，按加载顺序挑最后一个
+    env.unload(0x10000);
+    let _ = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(task_bases(&state, first), BTreeSet::from([0x20000]));
+    assert_eq!(task_bases(&state, all), BTreeSet::from([0x20000, 0x30000]));
+    assert_eq!(task_bases(&state, newest), BTreeSet::from([0x30000]));
+    restore_all_with(&env, &mut state);
+}
+
+#[test]
+fn overlapping_tasks_share_one_slot_hub() {
+    let env = FakeEnv::default();
+    env.add_caller("/system/lib64/liba.so", 0x10000, 1, LIBC_PUTS);
+    let mut state = CoreState::default();
+    let first = add_task(&mut state, TaskType::All, None, None, 0xa000);
+    let second = add_task(&mut state, TaskType::Single, Some("liba.so"), None, 0xb000);
+
+    let _ = refresh_internal(&env, &mut state, false, None);
+    let key = slot_key(&state, 0x10000);
+    let slot = state.slots[&key].clone();
+    assert_eq!(slot.task_chain, vec![first, second]);
+    assert_eq!(slot.orig_func, LIBC_PUTS);
+    let trampo = hub::hub_trampo(slot.hub_ptr as *mut hub::Hub);
+    assert_eq!(env.slot_value(0x10000), trampo);
+
+    assert_eq!(unhook_task_with(&env, &mut state, first), Errno::Ok);
+    assert_eq!(env.slot_value(0x10000), trampo);
+    assert_eq!(state.slots[&key].task_chain, vec![second]);
+
+    assert_eq!(unhook_task_with(&env, &mut state, second), Errno::Ok);
+    assert_eq!(env.slot_value(0x10000), LIBC_PUTS);
+    assert!(state.slots.is_empty());
+}
+
+#[test]
+fn reused_base_with_new_instance_drops_stale_slot() {
+    let env = FakeEnv::default();
+    env.add_caller("/system/lib64/liba.so", 0x10000, 1, LIBC_PUTS);
+    let mut state = CoreState::default();
+    let stub = add_task(&mut state, TaskType::All, None, None, 0xa000);
+    let _ = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(slot_key(&state, 0x10000).caller_instance_id, 1);
+
+    // 同一路径重新加载到相同基址，slot 内存恢复为 linker 写入的原值
+    env.unload(0x10000);
+    env.add_caller("/system/lib64/liba.so", 0x10000, 7, OTHER_PUTS);
+    let _ = refresh_internal(&env, &mut state, false, None);
+
+    assert_eq!(state.slots.len(), 1);
+    let key = slot_key(&state, 0x10000);
+    assert_eq!(key.caller_instance_id, 7);
+    assert_eq!(state.slots[&key].orig_func, OTHER_PUTS);
+    assert_eq!(state.task_slots[&stub], BTreeSet::from([key]));
+    restore_all_with(&env, &mut state);
+    assert_eq!(env.slot_value(0x10000), OTHER_PUTS);
+}
+
+#[test]
+fn callee_filter_selects_only_bound_callers() {
+    let env = FakeEnv::default();
+    env.add_callee(
+        "/apex/com.android.runtime/lib64/bionic/libc.so",
+        0x90000,
+        LIBC_PUTS,
+    );
+    env.add_caller("/system/lib64/liba.so", 0x10000, 1, LIBC_PUTS);
+    env.add_caller("/system/lib64/libb.so", 0x20000, 2, OTHER_PUTS);
+    let mut state = CoreState::default();
+    add_task(&mut state, TaskType::All, None, Some("libc.so"), 0xa000);
+
+    let (status, _) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(status, Errno::Ok);
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x10000]));
+    assert_eq!(env.slot_value(0x20000), OTHER_PUTS);
+
+    // callee 模块不存在时视为无符号，不写入任何 slot
+    let mut state = CoreState::default();
+    add_task(
+        &mut state,
+        TaskType::All,
+        None,
+        Some("libmissing.so"),
+        0xa000,
+    );
+    let _ = refresh_internal(&env, &mut state, false, None);
+    assert!(state.slots.is_empty());
+}
+
+#[test]
+fn patch_verify_failure_records_expected_and_observed() {
+    let env = FakeEnv::default();
+    env.add_caller("/system/lib64/libalias.so", 0x10000, 1, LIBC_PUTS);
+    env.add_caller("/system/lib64/liba.so", 0x20000, 2, LIBC_PUTS);
+    env.stale_alias_writes.borrow_mut().insert(0x10100);
+    let mut state = CoreState {
+        recordable: true,
+        ..Default::default()
+    };
+    add_task(&mut state, TaskType::All, None, None, 0xa000);
+
+    let (status, _) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(status, Errno::PatchVerifyFailed);
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x20000]));
+    assert_eq!(state.write_guard.rejected.len(), 1);
+    let record = state
+        .records
+        .iter()
+        .find(|record| record.status_code == Errno::PatchVerifyFailed.as_i32())
+        .expect("verify failure record");
+    assert_eq!(record.lib_name, "/system/lib64/libalias.so");
+    assert_eq!(record.observed_addr, Some(LIBC_PUTS));
+    assert_ne!(record.new_addr, 0);
+    assert_ne!(record.new_addr, LIBC_PUTS);
+
+    // 拉黑后不再重复写入过期别名
+    let patches = env.patch_count();
+    let (status, _) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(status, Errno::Ok);
+    assert_eq!(env.patch_count(), patches);
+    restore_all_with(&env, &mut state);
+}
+
+#[test]
+fn rejected_write_blacklists_module_page() {
+    let env = FakeEnv::default();
+    env.add_caller("/vendor/lib64/libprotected.so", 0x10000, 1, LIBC_PUTS);
+    env.add_caller("/system/lib64/liba.so", 0x20000, 2, LIBC_PUTS);
+    env.ignore_writes(0x10000);
+    let mut state = CoreState::default();
+    add_task(&mut state, TaskType::All, None, None, 0xa000);
+
+    let (status, _) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(status, Errno::WriteRejected);
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x20000]));
+    assert_eq!(state.write_guard.rejected.len(), 1);
+    let patches = env.patch_count();
+
+    // 黑名单生效期间不再尝试写入，其他模块不受影响
+    let (status, _) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(status, Errno::Ok);
+    assert_eq!(env.patch_count(), patches);
+    assert_eq!(env.slot_value(0x10000), LIBC_PUTS);
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x20000]));
+
+    // 新实例不继承旧实例的黑名单
+    env.unload(0x10000);
+    env.ignored_writes.borrow_mut().clear();
+    env.add_caller("/vendor/lib64/libprotected.so", 0x10000, 3, LIBC_PUTS);
+    let (status, _) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(status, Errno::Ok);
+    assert!(state.write_guard.rejected.is_empty());
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x10000, 0x20000]));
+    restore_all_with(&env, &mut state);
+}
+
+unsafe extern "C" fn ignore_hooked(
+    _task_stub: HookStub,
+    _status_code: i32,
+    _caller_path_name: *const c_char,
+    _sym_name: *const c_char,
+    _new_func: *mut c_void,
+    _prev_func: *mut c_void,
+    _arg: *mut c_void,
+) {
+}
+
+#[test]
+fn unloading_module_is_never_bound_to_single_task() {
+    let env = FakeEnv::default();
+    env.add_caller("/data/a/libfoo.so", 0x10000, 1, LIBC_PUTS);
+    env.add_caller("/data/b/libfoo.so", 0x20000, 2, LIBC_PUTS);
+    let mut state = CoreState::default();
+    let stub = add_task(
+        &mut state,
+        TaskType::Single,
+        Some("libfoo.so"),
+        None,
+        0xa000,
+    );
+    state.tasks.get_mut(&stub).unwrap().hooked = Some(HookedEntry {
+        callback: ignore_hooked,
+        arg: 0,
+    });
+    *env.unload_on_patch.borrow_mut() = Some(0x10000);
+
+    // 第一个实例在写入后被卸载：归类为 ModuleUnloading，同轮绑定到仍存活的实例
+    let (status, events) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(status, Errno::Ok);
+    let unloading: Vec<_> = events
+        .iter()
+        .filter(|event| event.status == Errno::ModuleUnloading)
+        .map(|event| event.caller_path_name.as_str())
+        .collect();
+    assert_eq!(unloading, vec!["/data/a/libfoo.so"]);
+    assert!(state.single_task_targets[&stub].contains("/data/b/libfoo.so"));
+
+    // 下一轮看到真实模块列表，卸载实例的 slot 被清理
+    let (status, events) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(status, Errno::Ok);
+    assert!(
+        events
+            .iter()
+            .all(|event| event.status != Errno::ModuleUnloading)
+    );
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x20000]));
+    assert!(state.single_task_targets[&stub].contains("/data/b/libfoo.so"));
+    restore_all_with(&env, &mut state);
+}
+
+unsafe extern "C" fn ignore_event(
+    _task_stub: HookStub,
+    _event: HookEventKind,
+    _status_code: i32,
+    _caller_path_name: *const c_char,
+    _sym_name: *const c_char,
+    _new_func: *mut c_void,
+    _prev_func: *mut c_void,
+    _arg: *mut c_void,
+) {
+}
+
+fn event_kinds(events: &[CallbackEvent]) -> Vec<(HookEventKind, &str)> {
+    events
+        .iter()
+        .map(|event| (event.kind, event.caller_path_name.as_str()))
+        .collect()
+}
+
+#[test]
+fn module_reload_reports_unloaded_then_rehooked() {
+    let env = FakeEnv::default();
+    env.add_caller("/data/a/libfoo.so", 0x10000, 1, LIBC_PUTS);
+    let mut state = CoreState {
+        recordable: true,
+        ..Default::default()
+    };
+    let stub = add_task(
+        &mut state,
+        TaskType::Single,
+        Some("libfoo.so"),
+        None,
+        0xa000,
+    );
+    state.tasks.get_mut(&stub).unwrap().event_callback = Some(HookEventEntry {
+        callback: ignore_event,
+        arg: 0,
+    });
+
+    let (_, events) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(
+        event_kinds(&events),
+        vec![(HookEventKind::Hooked, "/data/a/libfoo.so")]
+    );
+
+    env.unload(0x10000);
+    let (_, events) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(
+        event_kinds(&events),
+        vec![(HookEventKind::ModuleUnloaded, "/data/a/libfoo.so")]
+    );
+
+    env.add_caller("/data/a/libfoo.so", 0x30000, 3, LIBC_PUTS);
+    let (_, events) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(
+        event_kinds(&events),
+        vec![(HookEventKind::Rehooked, "/data/a/libfoo.so")]
+    );
+    assert!(state.rehook_targets.is_empty());
+    let record = state.records.back().expect("rehook record");
+    assert_eq!(record.op, RecordOp::Rehook);
+    assert_eq!(
+        record.module.as_ref().map(|module| module.base_addr),
+        Some(0x30000)
+    );
+
+    let events = unhook_events(&state, stub);
+    assert_eq!(
+        event_kinds(&events),
+        vec![(HookEventKind::Unhooked, "/data/a/libfoo.so")]
+    );
+    assert_eq!(unhook_task_with(&env, &mut state, stub), Errno::Ok);
+    assert_eq!(env.slot_value(0x30000), LIBC_PUTS);
+}
+
+#[test]
+fn module_refresh_touches_only_target_module() {
+    let env = FakeEnv::default();
+    env.add_caller("/system/lib64/liba.so", 0x10000, 1, LIBC_PUTS);
+    env.add_caller("/system/lib64/libb.so", 0x20000, 2, LIBC_PUTS);
+    let mut state = CoreState::default();
+    add_task(&mut state, TaskType::All, None, None, 0xa000);
+
+    let target = module_info("/system/lib64/libb.so", 0x20000, 2);
+    let (status, _) = refresh_module_with(&env, &mut state, &target);
+    assert_eq!(status, Errno::Ok);
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x20000]));
+    assert_eq!(env.slot_value(0x10000), LIBC_PUTS);
+    assert_eq!(env.patch_count(), 1);
+    assert_eq!(state.known_modules.len(), 1);
+
+    // 全量刷新补上其余模块，目标模块不重复写入
+    let _ = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x10000, 0x20000]));
+    assert_eq!(env.patch_count(), 2);
+    restore_all_with(&env, &mut state);
+}
+
+#[test]
+fn unloaded_refresh_prunes_closed_module_and_rebinds_single() {
+    let env = FakeEnv::default();
+    env.add_caller("/data/a/libfoo.so", 0x10000, 1, LIBC_PUTS);
+    env.add_caller("/data/b/libfoo.so", 0x20000, 2, LIBC_PUTS);
+    env.add_caller("/system/lib64/libc_user.so", 0x30000, 3, LIBC_PUTS);
+    let mut state = CoreState::default();
+    let stub = add_task(
+        &mut state,
+        TaskType::Single,
+        Some("libfoo.so"),
+        None,
+        0xa000,
+    );
+    add_task(
+        &mut state,
+        TaskType::Single,
+        Some("libc_user.so"),
+        None,
+        0xb000,
+    );
+    let _ = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x10000, 0x30000]));
+    let patches_before = env.patch_count();
+
+    // 引用计数未归零的模块仍在加载中，不做任何清理
+    let still_loaded = module_info("/system/lib64/libc_user.so", 0x30000, 3);
+    let (status, events) = refresh_unloaded_with(&env, &mut state, &[still_loaded]);
+    assert_eq!(status, Errno::Ok);
+    assert!(events.is_empty());
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x10000, 0x30000]));
+
+    let closed = module_info("/data/a/libfoo.so", 0x10000, 1);
+    env.unload(0x10000);
+    let (status, _) = refresh_unloaded_with(&env, &mut state, &[closed]);
+    assert_eq!(status, Errno::Ok);
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x20000, 0x30000]));
+    assert!(state.single_task_targets[&stub].contains("/data/b/libfoo.so"));
+    assert_eq!(state.known_modules.len(), 2);
+    // 只有重新绑定的 single 任务写入一次
+    assert_eq!(env.patch_count(), patches_before + 1);
+    restore_all_with(&env, &mut state);
+}
+
+#[test]
+fn orig_funcs_follow_bound_slots() {
+    let env = FakeEnv::default();
+    let mut state = CoreState::default();
+    let stub = add_task(
+        &mut state,
+        TaskType::Single,
+        Some("libfoo.so"),
+        None,
+        0xa000,
+    );
+    assert!(task_orig_funcs(&state, stub).is_empty());
+
+    env.add_caller("/data/a/libfoo.so", 0x10000, 1, LIBC_PUTS);
+    let _ = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(
+        task_orig_funcs(&state, stub),
+        vec![("/data/a/libfoo.so".to_string(), LIBC_PUTS)]
+    );
+
+    env.unload(0x10000);
+    let _ = refresh_internal(&env, &mut state, false, None);
+    assert!(task_orig_funcs(&state, stub).is_empty());
+
+    env.add_caller("/data/b/libfoo.so", 0x20000, 2, OTHER_PUTS);
+    let _ = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(
+        task_orig_funcs(&state, stub),
+        vec![("/data/b/libfoo.so".to_string(), OTHER_PUTS)]
+    );
+    restore_all_with(&env, &mut state);
+}
+
+#[test]
+fn chain_preview_reports_our_chain_and_foreign_slot() {
+    let env = FakeEnv::default();
+    env.add_callee(
+        "/apex/com.android.runtime/lib64/bionic/libc.so",
+        0x90000,
+        LIBC_PUTS,
+    );
+    env.add_caller("/system/lib64/liba.so", 0x10000, 1, LIBC_PUTS);
+    env.add_caller("/system/lib64/libb.so", 0x20000, 2, LIBC_PUTS);
+    let mut state = CoreState::default();
+    let first = add_task(&mut state, TaskType::Single, Some("liba.so"), None, 0xa000);
+    let second = add_task(&mut state, TaskType::Single, Some("liba.so"), None, 0xb000);
+    let _ = refresh_internal(&env, &mut state, false, None);
+
+    // libb 的 slot 被第三方改写，不经过本库
+    env.memory.borrow_mut().insert(0x20000 + 0x100, 0xf000);
+    let slots_before = state.slots.len();
+    let patches = env.patch_count();
+
+    let modules = env.enumerate_modules();
+    let ours = preview_chain(&env, &state, &modules, "liba.so", None, SYMBOL).unwrap();
+    assert_eq!(ours.len(), 1);
+    assert_eq!(ours[0].module.base_addr, 0x10000);
+    assert_eq!(ours[0].orig, LIBC_PUTS);
+    let owners: Vec<_> = ours[0].entries.iter().map(|entry| entry.owner).collect();
+    // 后加入的 proxy 位于链首
+    assert_eq!(
+        owners,
+        vec![ChainOwner::OurTask(second), ChainOwner::OurTask(first)]
+    );
+
+    let foreign =
+        preview_chain(&env, &state, &modules, "libb.so", Some("libc.so"), SYMBOL).unwrap();
+    assert_eq!(foreign.len(), 1);
+    assert_eq!(foreign[0].slot_addr, 0x20100);
+    assert_eq!(foreign[0].entries.len(), 1);
+    assert_eq!(foreign[0].entries[0].owner, ChainOwner::Foreign);
+    assert_eq!(foreign[0].entries[0].func, 0xf000);
+    assert_eq!(foreign[0].orig, LIBC_PUTS);
+
+    // 预览不修改状态也不写入 slot
+    assert_eq!(state.slots.len(), slots_before);
+    assert_eq!(env.patch_count(), patches);
+    restore_all_with(&env, &mut state);
+}
+
+// APEX bionic 与 vendor 副本同时加载，libc.so 规则匹配到两个实例
+fn duplicate_libc_env() -> FakeEnv {
+    let env = FakeEnv::default();
+    env.add_callee(
+        "/apex/com.android.runtime/lib64/bionic/libc.so",
+        0x90000,
+        LIBC_PUTS,
+    );
+    env.add_callee("/vendor/lib64/libc.so", 0xa0000, OTHER_PUTS);
+    env.add_caller("/system/lib64/liba.so", 0x10000, 1, LIBC_PUTS);
+    env.add_caller("/vendor/lib64/libb.so", 0x20000, 2, OTHER_PUTS);
+    for (base_addr, namespace_id) in [
+        (0x90000, 0x1),
+        (0x10000, 0x1),
+        (0xa0000, 0x2),
+        (0x20000, 0x2),
+    ] {
+        env.set_namespace(base_addr, namespace_id);
+    }
+    env
+}
+
+#[test]
+fn callee_filter_accepts_weak_definition() {
+    let env = FakeEnv::default();
+    env.add_callee("/system/lib64/libc.so", 0x90000, LIBC_PUTS);
+    env.add_weak_export(0x90000, OTHER_PUTS);
+    env.add_caller("/system/lib64/liba.so", 0x10000, 1, LIBC_PUTS);
+    env.add_caller("/system/lib64/libb.so", 0x20000, 2, OTHER_PUTS);
+    let mut state = CoreState::default();
+    let stub = add_task(&mut state, TaskType::All, None, Some("libc.so"), 0xa000);
+
+    let (status, _) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(status, Errno::Ok);
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x10000, 0x20000]));
+    // 同一实例的全局与弱定义不算多实例匹配
+    assert!(!state.callee_candidates.contains_key(&stub));
+    restore_all_with(&env, &mut state);
+}
+
+#[test]
+fn duplicate_callee_follows_task_selection() {
+    let env = duplicate_libc_env();
+    let mut state = CoreState::default();
+    let stub = add_task(&mut state, TaskType::All, None, Some("libc.so"), 0xa000);
+    let (status, _) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(status, Errno::Ok);
+    assert_eq!(state.callee_candidates[&stub], 2);
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x10000, 0x20000]));
+    // 实例数不变时不重复记录
+    let records = state.records.len();
+    let _ = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(state.records.len(), records);
+    restore_all_with(&env, &mut state);
+
+    // Error 策略拒绝猜测，不写入任何 slot
+    let mut state = CoreState::default();
+    let stub = add_task(&mut state, TaskType::All, None, Some("libc.so"), 0xa000);
+    state.tasks.get_mut(&stub).unwrap().callee_selection = CalleeSelection::Error;
+    let (status, events) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(status, Errno::AmbiguousCallee);
+    assert!(
+        events
+            .iter()
+            .all(|event| event.status == Errno::AmbiguousCallee)
+    );
+    assert!(state.slots.is_empty());
+
+    // CallerNamespace 只接受与 caller 同一 namespace 的导出
+    env.memory.borrow_mut().insert(0x20000 + 0x100, LIBC_PUTS);
+    let mut state = CoreState::default();
+    let stub = add_task(&mut state, TaskType::All, None, Some("libc.so"), 0xa000);
+    state.tasks.get_mut(&stub).unwrap().callee_selection = CalleeSelection::CallerNamespace;
+    let (status, _) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(status, Errno::Ok);
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x10000]));
+    assert_eq!(env.slot_value(0x20000), LIBC_PUTS);
+    restore_all_with(&env, &mut state);
+
+    // 卸载副本后不再视为多实例
+    env.unload(0xa0000);
+    let mut state = CoreState::default();
+    let stub = add_task(&mut state, TaskType::All, None, Some("libc.so"), 0xa000);
+    state.callee_candidates.insert(stub, 2);
+    let _ = refresh_internal(&env, &mut state, false, None);
+    assert!(!state.callee_candidates.contains_key(&stub));
+    restore_all_with(&env, &mut state);
+}
+
+#[test]
+fn batched_tasks_share_one_pass_with_own_status() {
+    let env = FakeEnv::default();
+    env.add_caller("/vendor/lib64/libprotected.so", 0x10000, 1, LIBC_PUTS);
+    env.add_caller("/system/lib64/liba.so", 0x20000, 2, LIBC_PUTS);
+    env.add_caller("/system/lib64/libb.so", 0x30000, 3, LIBC_PUTS);
+    env.ignore_writes(0x10000);
+    let mut state = CoreState::default();
+    let rejected = add_task(
+        &mut state,
+        TaskType::Single,
+        Some("libprotected.so"),
+        None,
+        0xa000,
+    );
+    let applied = add_task(&mut state, TaskType::Single, Some("liba.so"), None, 0xb000);
+    // 未列入本批的任务不参与这一轮
+    add_task(&mut state, TaskType::Single, Some("libb.so"), None, 0xc000);
+
+    let pass = refresh_pass(&env, &mut state, false, Some(&[rejected, applied]));
+    assert_eq!(pass.status, Errno::WriteRejected);
+    assert_eq!(
+        pass.task_status,
+        BTreeMap::from([(rejected, Errno::WriteRejected)])
+    );
+    assert_eq!(hooked_bases(&state), BTreeSet::from([0x20000]));
+    assert_eq!(env.slot_value(0x30000), LIBC_PUTS);
+    restore_all_with(&env, &mut state);
+}
+
+#[test]
+fn paused_task_keeps_slot_and_stays_paused_on_reload() {
+    let env = FakeEnv::default();
+    env.add_caller("/system/lib64/liba.so", 0x10000, 1, LIBC_PUTS);
+    let mut state = CoreState::default();
+    let first = add_task(&mut state, TaskType::All, None, None, 0xa000);
+    let second = add_task(&mut state, TaskType::All, None, None, 0xb000);
+    let _ = refresh_internal(&env, &mut state, false, None);
+    let hub_ptr = state.slots[&slot_key(&state, 0x10000)].hub_ptr as *mut hub::Hub;
+    let trampo = hub::hub_trampo(hub_ptr);
+
+    assert_eq!(set_task_paused(&mut state, second, true), Errno::Ok);
+    assert_eq!(set_task_paused(&mut state, second, true), Errno::Ok);
+    assert_eq!(hub::enabled_proxies(hub_ptr), vec![0xa000]);
+    assert_eq!(set_task_paused(&mut state, first, true), Errno::Ok);
+    assert_eq!(hub::first_enabled(hub_ptr), LIBC_PUTS);
+    assert_eq!(env.slot_value(0x10000), trampo);
+    assert_eq!(state.task_slots[&first].len(), 1);
+
+    // 重新加载的模块沿用暂停状态
+    env.unload(0x10000);
+    let _ = refresh_internal(&env, &mut state, false, None);
+    env.add_caller("/system/lib64/liba.so", 0x40000, 4, LIBC_PUTS);
+    let _ = refresh_internal(&env, &mut state, false, None);
+    let hub_ptr = state.slots[&slot_key(&state, 0x40000)].hub_ptr as *mut hub::Hub;
+    assert_eq!(hub::first_enabled(hub_ptr), LIBC_PUTS);
+
+    assert_eq!(set_task_paused(&mut state, first, false), Errno::Ok);
+    assert_eq!(hub::enabled_proxies(hub_ptr), vec![0xa000]);
+
+    // 暂停中的任务也能正常卸载，其余任务不受影响
+    assert_eq!(unhook_task_with(&env, &mut state, second), Errno::Ok);
+    assert_eq!(hub::enabled_proxies(hub_ptr), vec![0xa000]);
+    assert_eq!(env.slot_value(0x40000), hub::hub_trampo(hub_ptr));
+    assert_eq!(set_task_paused(&mut state, 0xdead, true), Errno::InvalidArg);
+    restore_all_with(&env, &mut state);
+}
+
+#[test]
+fn slot_guard_chains_foreign_trampoline_as_tail() {
+    const FOREIGN_TRAMPO: usize = 0x7f00;
+    let env = FakeEnv::default();
+    env.add_caller("/system/lib64/liba.so", 0x10000, 1, LIBC_PUTS);
+    let mut state = CoreState {
+        recordable: true,
+        ..Default::default()
+    };
+    let stub = add_task(&mut state, TaskType::All, None, None, 0xa000);
+    state.tasks.get_mut(&stub).unwrap().event_callback = Some(HookEventEntry {
+        callback: ignore_event,
+        arg: 0,
+    });
+    let _ = refresh_internal(&env, &mut state, false, None);
+    let key = slot_key(&state, 0x10000);
+    let hub_ptr = state.slots[&key].hub_ptr as *mut hub::Hub;
+    let trampo = hub::hub_trampo(hub_ptr);
+
+    let mut events = Vec::new();
+    assert_eq!(verify_slots(&env, &mut state, &mut events), Errno::Ok);
+    assert!(events.is_empty());
+
+    env.overwrite_slot(0x10000, FOREIGN_TRAMPO);
+    assert_eq!(verify_slots(&env, &mut state, &mut events), Errno::Ok);
+    assert_eq!(env.slot_value(0x10000), trampo);
+    assert_eq!(state.slots[&key].orig_func, FOREIGN_TRAMPO);
+    assert_eq!(hub::hub_tail(hub_ptr), FOREIGN_TRAMPO);
+    assert_eq!(hub::first_enabled(hub_ptr), 0xa000);
+    assert_eq!(
+        event_kinds(&events),
+        vec![(HookEventKind::Rehooked, "/system/lib64/liba.so")]
+    );
+    assert_eq!(events[0].status, Errno::SlotTampered);
+    let record = state.records.back().expect("rehook record");
+    assert_eq!(record.status_code, Errno::SlotTampered.as_i32());
+
+    // 外来 hook 可能仍回调 trampoline：卸载后 hub 保留为直通层，slot 不交还
+    assert_eq!(unhook_task_with(&env, &mut state, stub), Errno::Ok);
+    assert_eq!(env.slot_value(0x10000), trampo);
+    assert!(state.slots[&key].task_chain.is_empty());
+    assert_eq!(hub::first_enabled(hub_ptr), FOREIGN_TRAMPO);
+    state.tasks.remove(&stub);
+
+    // 再次挂载沿用同一 hub，链尾仍是外来 hook
+    let stub = add_task(&mut state, TaskType::All, None, None, 0xb000);
+    let _ = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(state.slots[&key].hub_ptr, hub_ptr as usize);
+    assert_eq!(hub::enabled_proxies(hub_ptr), vec![0xb000]);
+    assert_eq!(unhook_task_with(&env, &mut state, stub), Errno::Ok);
+
+    // 运行时整体恢复时 slot 交还给外来入口，hub 随之撤下
+    assert_eq!(restore_all_with(&env, &mut state), Errno::Ok);
+    assert_eq!(env.slot_value(0x10000), FOREIGN_TRAMPO);
+    assert!(state.slots.is_empty());
+}
+
+#[test]
+fn slot_guard_resets_tail_when_foreign_restores_trampoline() {
+    const FOREIGN_TRAMPO: usize = 0x7f00;
+    let env = FakeEnv::default();
+    env.add_caller("/system/lib64/liba.so", 0x10000, 1, LIBC_PUTS);
+    env.add_caller("/system/lib64/libb.so", 0x20000, 2, LIBC_PUTS);
+    let mut state = CoreState::default();
+    let stub = add_task(&mut state, TaskType::Single, Some("liba.so"), None, 0xa000);
+    let other = add_task(&mut state, TaskType::Single, Some("libb.so"), None, 0xb000);
+    let _ = refresh_internal(&env, &mut state, false, None);
+    let mut hubs = Vec::new();
+    for base in [0x10000, 0x20000] {
+        let key = slot_key(&state, base);
+        let hub_ptr = state.slots[&key].hub_ptr as *mut hub::Hub;
+        env.overwrite_slot(base, FOREIGN_TRAMPO);
+        hubs.push((key, hub_ptr));
+    }
+    let mut events = Vec::new();
+    assert_eq!(verify_slots(&env, &mut state, &mut events), Errno::Ok);
+
+    // libb.so 只剩直通 hub；外来 hook 撤销时写回保存的 trampoline
+    assert_eq!(unhook_task_with(&env, &mut state, other), Errno::Ok);
+    state.tasks.remove(&other);
+    for (key, hub_ptr) in &hubs {
+        env.overwrite_slot(key.caller_base_addr, hub::hub_trampo(*hub_ptr));
+    }
+
+    // 外来入口仍映射时保持接入
+    assert_eq!(verify_slots(&env, &mut state, &mut events), Errno::Ok);
+    assert_eq!(hub::hub_tail(hubs[0].1), FOREIGN_TRAMPO);
+
+    // 所在库卸载后链尾复原，无任务的 slot 交还原函数
+    env.unmapped_code.borrow_mut().insert(FOREIGN_TRAMPO);
+    assert_eq!(verify_slots(&env, &mut state, &mut events), Errno::Ok);
+    assert_eq!(hub::hub_tail(hubs[0].1), LIBC_PUTS);
+    assert_eq!(state.slots[&hubs[0].0].orig_func, LIBC_PUTS);
+    assert_eq!(env.slot_value(0x10000), hub::hub_trampo(hubs[0].1));
+    assert!(!state.slots.contains_key(&hubs[1].0));
+    assert_eq!(env.slot_value(0x20000), LIBC_PUTS);
+
+    assert_eq!(unhook_task_with(&env, &mut state, stub), Errno::Ok);
+    assert_eq!(env.slot_value(0x10000), LIBC_PUTS);
+}
+
+#[test]
+fn slot_guard_chains_module_value_and_resets_on_export() {
+    let env = FakeEnv::default();
+    env.add_caller("/system/lib64/liba.so", 0x10000, 1, LIBC_PUTS);
+    env.module_code.borrow_mut().insert(OTHER_PUTS);
+    let mut state = CoreState::default();
+    let stub = add_task(&mut state, TaskType::All, None, None, 0xa000);
+    let _ = refresh_internal(&env, &mut state, false, None);
+    let key = slot_key(&state, 0x10000);
+    let hub_ptr = state.slots[&key].hub_ptr as *mut hub::Hub;
+    let trampo = hub::hub_trampo(hub_ptr);
+
+    // 可符号化的值同样接为链尾，改回原函数时链尾复原
+    for (value, tail) in [
+        (LIBC_PUTS, LIBC_PUTS),
+        (OTHER_PUTS, OTHER_PUTS),
+        (LIBC_PUTS, LIBC_PUTS),
+    ] {
+        env.overwrite_slot(0x10000, value);
+        let mut events = Vec::new();
+        assert_eq!(verify_slots(&env, &mut state, &mut events), Errno::Ok);
+        assert_eq!(env.slot_value(0x10000), trampo);
+        assert_eq!(state.slots[&key].orig_func, tail);
+        assert_eq!(hub::hub_tail(hub_ptr), tail);
+    }
+
+    // 写回失败时不改动链尾并上报写入错误
+    env.ignore_writes(0x10000);
+    env.overwrite_slot(0x10000, 0x7f00);
+    let mut events = Vec::new();
+    assert_eq!(
+        verify_slots(&env, &mut state, &mut events),
+        Errno::WriteRejected
+    );
+    assert_eq!(hub::hub_tail(hub_ptr), LIBC_PUTS);
+    assert_eq!(state.slots[&key].orig_func, LIBC_PUTS);
+    assert_eq!(unhook_task_with(&env, &mut state, stub), Errno::Ok);
+}
+
+#[test]
+fn pattern_task_expands_to_matching_imports_under_limit() {
+    let env = FakeEnv::default();
+    env.add_caller("/system/lib64/liba.so", 0x10000, 1, LIBC_PUTS);
+    env.add_import(0x10000, "pthread_create", 0x10200, 0xc100);
+    env.add_import(0x10000, "pthread_join", 0x10300, 0xc200);
+    let mut state = CoreState {
+        recordable: true,
+        ..Default::default()
+    };
+    let stub = add_task(&mut state, TaskType::Single, Some("liba.so"), None, 0xa000);
+    let task = state.tasks.get_mut(&stub).unwrap();
+    task.sym_name = "pthread_*".to_string();
+    task.event_callback = Some(HookEventEntry {
+        callback: ignore_event,
+        arg: 0,
+    });
+
+    // 超过上限时整个模块不挂载
+    set_match_limit(1);
+    let (status, _) = refresh_internal(&env, &mut state, false, None);
+    set_match_limit(DEFAULT_MATCH_LIMIT);
+    assert_eq!(status, Errno::PatternLimit);
+    assert!(state.slots.is_empty());
+    let record = state.records.back().expect("limit record");
+    assert_eq!(record.sym_name, "pthread_*");
+
+    let (status, events) = refresh_internal(&env, &mut state, false, None);
+    assert_eq!(status, Errno::Ok);
+    let hooked: BTreeSet<usize> = state.slots.keys().map(|key| key.slot_addr).collect();
+    assert_eq!(hooked, BTreeSet::from([0x10200, 0x10300]));
+    assert_eq!(env.slot_value(0x10000), LIBC_PUTS);
+    let event_names: Vec<&str> = events.iter().map(|e| e.sym_name.as_str()).collect();
+    assert_eq!(event_names, vec!["pthread_create", "pthread_join"]);
+    let record_names: Vec<&str> = state
+        .records
+        .iter()
+        .rev()
+        .take(2)
+        .map(|record| record.sym_name.as_str())
+        .collect();
+    assert_eq!(record_names, vec!["pthread_join", "pthread_create"]);
+
+    assert_eq!(unhook_task_with(&env, &mut state, stub), Errno::Ok);
+    assert_eq!(env.memory.borrow()[&0x10200], 0xc100);
+}
@@ -0,0 +1,165 @@
+// 文本规则加载：逐行解析为 hook_single / hook_all / add_ignore 调用
+// 路径规则的限定符语义由 rules 模块负责，这里只做分词与合法性检查
+use crate::api::{HookStub, RuleLineResult, RuleLoadReport};
+use crate::errno::Errno;
+use std::ffi::c_void;
+
+use super::rules;
+
+#[derive(Debug, Eq, PartialEq)]
+enum RuleLine<'a> {
+    Ignore(&'a str),
+    Hook {
+        caller: &'a str,
+        callee: Option<&'a str>,
+        sym_name: &'a str,
+        proxy_name: &'a str,
+    },
+}
+
+pub(super) fn load_rules(text: &str, new_func_resolver: fn(&str) -> *mut c_void) -> RuleLoadReport {
+    let mut report = RuleLoadReport::default();
+    for (index, raw) in text.lines().enumerate() {
+        let Some(parsed) = parse_line(raw) else {
+            continue;
+        };
+        let result = parsed.and_then(|line| apply_line(line, new_func_resolver));
+        report.lines.push(RuleLineResult {
+            line: index + 1,
+            result,
+        });
+    }
+    report
+}
+
+fn apply_line(
+    line: RuleLine<'_>,
+    new_func_resolver: fn(&str) -> *mut c_void,
+) -> Result<Option<HookStub>, Errno> {
+    match line {
+        RuleLine::Ignore(rule) => match super::add_ignore(rule) {
+            Errno::Ok => Ok(None),
+            err => Err(err),
+        },
+        RuleLine::Hook {
+            caller,
+            callee,
+            sym_name,
+            proxy_name,
+        } => {
+            let new_func = new_func_resolver(proxy_name);
+            if new_func.is_null() {
+                return Err(Errno::NotFound);
+            }
+            let null = std::ptr::null_mut();
+            let stub = if caller == "*" {
+                super::hook_all(callee, sym_name, new_func, None, null)?
+            } else {
+                super::hook_single(caller, callee, sym_name, new_func, None, null)?
+            };
+            Ok(Some(stub))
+        }
+    }
+}
+
+// 空行与纯注释行返回 None；其余行解析失败时返回 InvalidArg
+fn parse_line(raw: &str) -> Option<Result<RuleLine<'_>, Errno>> {
+    let content = raw.split('#').next().unwrap_or_default();
+    let tokens: Vec<&str> = content.split_whitespace().collect();
+    if tokens.is_empty() {
+        return None;
+    }
+    let line = match tokens.as_slice() {
+        ["ignore", rule] => Some(RuleLine::Ignore(rule)).filter(|_| rules::is_valid_rule(rule)),
+        ["ignore", ..] => None,
+        [caller, sym_name, proxy_name] => hook_line(caller, "*", sym_name, proxy_name),
+        [caller, callee, sym_name, proxy_name] => hook_line(caller, callee, sym_name, proxy_name),
+        _ => None,
+    };
+    Some(line.ok_or(Errno::InvalidArg))
+}
+
+fn hook_line<'a>(
+    caller: &'a str,
+    callee: &'a str,
+    sym_name: &'a str,
+    proxy_name: &'a str,
+) -> Option<RuleLine<'a>> {
+    let valid = |rule: &str| rule == "*" || rules::is_valid_rule(rule);
+    if !valid(caller) || !valid(callee) {
+        return None;
+    }
+    Some(RuleLine::Hook {
+        caller,
+        callee: (callee != "*").then_some(callee),
+        sym_name,
+        proxy_name,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RuleLine, parse_line};
+    use crate::errno::Errno;
+
+    #[test]
+    fn blank_and_comment_lines_are_skipped() {
+        assert_eq!(parse_line(""), None);
+        assert_eq!(parse_line("   "), None);
+        assert_eq!(parse_line("# libfoo.so open my_open"), None);
+    }
+
+    #[test]
+    fn three_token_line_has_no_callee() {
+        assert_eq!(
+            parse_line("libfoo.so open my_open # trailing"),
+            Some(Ok(RuleLine::Hook {
+                caller: "libfoo.so",
+                callee: None,
+                sym_name: "open",
+                proxy_name: "my_open",
+            }))
+        );
+    }
+
+    #[test]
+    fn four_token_line_keeps_qualified_rules() {
+        assert_eq!(
+            parse_line("* libc.so@0x7000%2 read my_read"),
+            Some(Ok(RuleLine::Hook {
+                caller: "*",
+                callee: Some("libc.so@0x7000%2"),
+                sym_name: "read",
+                proxy_name: "my_read",
+            }))
+        );
+        assert_eq!(
+            parse_line("libfoo.so * read my_read"),
+            Some(Ok(RuleLine::Hook {
+                caller: "libfoo.so",
+                callee: None,
+                sym_name: "read",
+                proxy_name: "my_read",
+            }))
+        );
+    }
+
+    #[test]
+    fn ignore_line_is_parsed() {
+        assert_eq!(
+            parse_line("ignore libbar.so^0x10"),
+            Some(Ok(RuleLine::Ignore("libbar.so^0x10")))
+        );
+    }
+
+    #[test]
+    fn malformed_lines_are_invalid() {
+        assert_eq!(parse_line("libfoo.so open"), Some(Err(Errno::InvalidArg)));
+        assert_eq!(parse_line("a b c d e"), Some(Err(Errno::InvalidArg)));
+        assert_eq!(parse_line("ignore a b"), Some(Err(Errno::InvalidArg)));
+        assert_eq!(
+            parse_line("@0x7000 open my_open"),
+            Some(Err(Errno::InvalidArg))
+        );
+    }
+}
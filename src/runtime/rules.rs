@@ -1,26 +1,38 @@
 // 模块路径匹配规则解析与判定
 // 支持路径后缀 @base_addr、%instance_id、^namespace_id 精确限定
+// soname: 前缀的规则按 DT_SONAME 完全匹配，不依赖模块所在路径
+// 符号名通配模式只支持 '*'（匹配任意长度，含空串），其余字符按字面比较
+use std::collections::BTreeSet;
+
+use super::state::ModuleInfo;
+
+const SONAME_PREFIX: &str = "soname:";
+
+const SYM_WILDCARD: char = '*';
 
 // 解析后的路径规则，各限定符均为可选
 struct ParsedPathRule<'a> {
     path_rule: &'a str,
+    // path_rule 为 DT_SONAME 而不是路径
+    is_soname: bool,
     base_rule: Option<usize>,
     instance_rule: Option<usize>,
     namespace_rule: Option<usize>,
 }
 
-// 仅匹配路径部分，忽略 base/instance/namespace 限定符
+// 仅匹配路径部分，忽略 base/instance/namespace 限定符；soname 规则不匹配任何路径
 pub(super) fn path_match(linker_path: &str, external_path: &str) -> bool {
     let Some(rule) = parse_path_rule(external_path) else {
         return false;
     };
-    path_match_only(linker_path, rule.path_rule)
+    !rule.is_soname && path_match_only(linker_path, rule.path_rule)
 }
 
-// 完整模块匹配：路径 + base_addr + instance_id + namespace_id
-// 规则中未指定的限定符视为通配
+// 完整模块匹配：路径（或 soname）+ base_addr + instance_id + namespace_id
+// 规则中未指定的限定符视为通配；linker_soname 为 None 时 soname 规则不匹配
 pub(super) fn module_match(
     linker_path: &str,
+    linker_soname: Option<&str>,
     linker_base_addr: usize,
     linker_instance_id: usize,
     linker_namespace_id: usize,
@@ -29,7 +41,12 @@ pub(super) fn module_match(
     let Some(rule) = parse_path_rule(external_path) else {
         return false;
     };
-    if !path_match_only(linker_path, rule.path_rule) {
+    let name_matched = if rule.is_soname {
+        linker_soname == Some(rule.path_rule)
+    } else {
+        path_match_only(linker_path, rule.path_rule)
+    };
+    if !name_matched {
         return false;
     }
     if rule.base_rule.is_some_and(|base| base != linker_base_addr) {
@@ -50,10 +67,91 @@ pub(super) fn module_match(
     true
 }
 
+// 规则间匹配：task 自身的 caller 规则被 external_path 覆盖时返回 true
+// 路径按 path_match 语义比较，soname 只与 soname 规则按名称比较
+// external_path 指定的限定符要求 task 规则给出相同值
+pub(super) fn rule_match(task_rule: &str, external_path: &str) -> bool {
+    let (Some(task), Some(rule)) = (parse_path_rule(task_rule), parse_path_rule(external_path))
+    else {
+        return false;
+    };
+    let name_matched = match (task.is_soname, rule.is_soname) {
+        (true, true) => task.path_rule == rule.path_rule,
+        (false, false) => path_match_only(task.path_rule, rule.path_rule),
+        _ => false,
+    };
+    name_matched
+        && (rule.base_rule.is_none() || rule.base_rule == task.base_rule)
+        && (rule.instance_rule.is_none() || rule.instance_rule == task.instance_rule)
+        && (rule.namespace_rule.is_none() || rule.namespace_rule == task.namespace_rule)
+}
+
+// 规则能否按 @base / %instance / ^namespace 语义解析
+pub(super) fn is_valid_rule(rule: &str) -> bool {
+    parse_path_rule(rule).is_some()
+}
+
+// 规则（含 '!' 例外的 ignore 规则）是否按 soname 匹配，模块扫描据此决定是否解析 DT_SONAME
+pub(super) fn is_soname_rule(rule: &str) -> bool {
+    rule.strip_prefix('!')
+        .unwrap_or(rule)
+        .starts_with(SONAME_PREFIX)
+}
+
+pub(super) fn is_sym_pattern(sym_name: &str) -> bool {
+    sym_name.contains(SYM_WILDCARD)
+}
+
+// 贪心匹配，遇到不匹配时回退到最近一个 '*' 多吞一个字符，复杂度 O(pattern * name)
+pub(super) fn sym_pattern_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.as_bytes(), name.as_bytes());
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        if pattern.get(p) == Some(&(SYM_WILDCARD as u8)) {
+            backtrack = Some((p + 1, n));
+            p += 1;
+        } else if pattern.get(p) == Some(&name[n]) {
+            p += 1;
+            n += 1;
+        } else if let Some((star_next, star_n)) = backtrack {
+            p = star_next;
+            n = star_n + 1;
+            backtrack = Some((star_next, n));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&byte| byte == SYM_WILDCARD as u8)
+}
+
+// 模块匹配列表中任一规则
+pub(super) fn module_match_any(
+    linker_path: &str,
+    linker_soname: Option<&str>,
+    linker_base_addr: usize,
+    linker_instance_id: usize,
+    linker_namespace_id: usize,
+    rules: &[String],
+) -> bool {
+    rules.iter().any(|rule| {
+        module_match(
+            linker_path,
+            linker_soname,
+            linker_base_addr,
+            linker_instance_id,
+            linker_namespace_id,
+            rule,
+        )
+    })
+}
+
 // 判断模块是否应跳过 hook
 // 空路径、vDSO（以 '[' 开头）和自身 so 始终跳过
+// 以 '!' 开头的规则为例外：命中时不跳过，优先于其他 ignore 规则
 pub(super) fn should_ignore(
     pathname: &str,
+    soname: Option<&str>,
     base_addr: usize,
     instance_id: usize,
     namespace_id: usize,
@@ -67,9 +165,62 @@ pub(super) fn should_ignore(
         return true;
     }
 
+    matches_ignore_rules(
+        pathname,
+        soname,
+        base_addr,
+        instance_id,
+        namespace_id,
+        ignores,
+    )
+}
+
+// 只按 ignore 规则判定，不含空路径、vDSO 与自身 so 的固定跳过
+fn matches_ignore_rules(
+    pathname: &str,
+    soname: Option<&str>,
+    base_addr: usize,
+    instance_id: usize,
+    namespace_id: usize,
+    ignores: &[String],
+) -> bool {
+    let matches =
+        |rule: &str| module_match(pathname, soname, base_addr, instance_id, namespace_id, rule);
+    if ignores
+        .iter()
+        .filter_map(|rule| rule.strip_prefix('!'))
+        .any(matches)
+    {
+        return false;
+    }
     ignores
         .iter()
-        .any(|rule| module_match(pathname, base_addr, instance_id, namespace_id, rule))
+        .filter(|rule| !rule.starts_with('!'))
+        .any(|rule| matches(rule))
+}
+
+// 命中 ignore 规则的模块 (base_addr, instance_id)，供 CFI 扫描跳过
+pub(super) fn ignored_module_keys(
+    modules: &[ModuleInfo],
+    ignores: &[String],
+) -> BTreeSet<(usize, usize)> {
+    if ignores.is_empty() {
+        return BTreeSet::new();
+    }
+    modules
+        .iter()
+        .filter(|module| {
+            matches_ignore_rules(
+                &module.pathname,
+                module.soname.as_deref(),
+                module.base_addr,
+                module.instance_id,
+                module.namespace_id,
+                ignores,
+            )
+        })
+        .map(|module| (module.base_addr, module.instance_id))
+        .collect()
 }
 
 // 纯路径匹配：绝对路径要求完全相等，相对路径使用后缀匹配
@@ -85,8 +236,12 @@ fn path_match_only(linker_path: &str, external_path: &str) -> bool {
     }
 }
 
-// 从右向左依次拆分 ^namespace、%instance、@base 后缀
+// 去掉 soname: 前缀后从右向左依次拆分 ^namespace、%instance、@base 后缀
 fn parse_path_rule(external_path: &str) -> Option<ParsedPathRule<'_>> {
+    let (is_soname, external_path) = match external_path.strip_prefix(SONAME_PREFIX) {
+        Some(soname) => (true, soname),
+        None => (false, external_path),
+    };
     if external_path.is_empty() {
         return None;
     }
@@ -96,6 +251,7 @@ fn parse_path_rule(external_path: &str) -> Option<ParsedPathRule<'_>> {
     let (path_rule, base_rule) = split_base_rule(path_and_base)?;
     Some(ParsedPathRule {
         path_rule,
+        is_soname,
         base_rule,
         instance_rule,
         namespace_rule,
@@ -154,7 +310,22 @@ fn parse_hex_usize(rule: &str) -> Option<usize> {
 
 #[cfg(test)]
 mod tests {
-    use super::{module_match, path_match, should_ignore};
+    use super::{
+        ignored_module_keys, is_soname_rule, module_match, module_match_any, path_match,
+        rule_match, should_ignore, sym_pattern_match,
+    };
+    use crate::runtime::state::ModuleInfo;
+
+    #[test]
+    fn rule_match_compares_task_rules() {
+        assert!(rule_match("libfoo.so", "libfoo.so"));
+        assert!(rule_match("/data/app/libfoo.so", "libfoo.so"));
+        assert!(!rule_match("libfoo.so", "/data/app/libfoo.so"));
+        assert!(rule_match("libfoo.so@0x1a2b", "libfoo.so"));
+        assert!(rule_match("libfoo.so@0x1a2b", "libfoo.so@0x1a2b"));
+        assert!(!rule_match("libfoo.so", "libfoo.so@0x1a2b"));
+        assert!(!rule_match("libbar.so", "libfoo.so"));
+    }
 
     #[test]
     fn path_match_ignores_instance_suffix() {
@@ -168,6 +339,7 @@ mod tests {
     fn module_match_supports_base_and_instance_suffix() {
         assert!(module_match(
             "/data/app/libfoo.so",
+            None,
             0x1A2B,
             0x55AA,
             0x10,
@@ -175,6 +347,7 @@ mod tests {
         ));
         assert!(!module_match(
             "/data/app/libfoo.so",
+            None,
             0x1A2B,
             0x55AA,
             0x10,
@@ -186,6 +359,7 @@ mod tests {
     fn module_match_keeps_base_only_compatibility() {
         assert!(module_match(
             "/data/app/libfoo.so",
+            None,
             0x1A2B,
             0x100,
             0x10,
@@ -197,6 +371,7 @@ mod tests {
     fn module_match_supports_namespace_suffix() {
         assert!(module_match(
             "/data/app/libfoo.so",
+            None,
             0x1A2B,
             0x55AA,
             0x66CC,
@@ -204,6 +379,7 @@ mod tests {
         ));
         assert!(!module_match(
             "/data/app/libfoo.so",
+            None,
             0x1A2B,
             0x55AA,
             0x66CD,
@@ -214,14 +390,115 @@ mod tests {
     #[test]
     fn should_ignore_uses_instance_rule() {
         let ignores = vec!["libfoo.so%0x1234".to_string()];
-        assert!(should_ignore("/data/app/libfoo.so", 0x1, 0x1234, 0x10, &ignores));
-        assert!(!should_ignore("/data/app/libfoo.so", 0x1, 0x5678, 0x20, &ignores));
+        assert!(should_ignore("/data/app/libfoo.so", None, 0x1, 0x1234, 0x10, &ignores));
+        assert!(!should_ignore("/data/app/libfoo.so", None, 0x1, 0x5678, 0x20, &ignores));
     }
 
     #[test]
     fn should_ignore_uses_namespace_rule() {
         let ignores = vec!["libfoo.so%0x1234^0x8888".to_string()];
-        assert!(should_ignore("/data/app/libfoo.so", 0x1, 0x1234, 0x8888, &ignores));
-        assert!(!should_ignore("/data/app/libfoo.so", 0x1, 0x1234, 0x9999, &ignores));
+        assert!(should_ignore("/data/app/libfoo.so", None, 0x1, 0x1234, 0x8888, &ignores));
+        assert!(!should_ignore("/data/app/libfoo.so", None, 0x1, 0x1234, 0x9999, &ignores));
+    }
+
+    #[test]
+    fn should_ignore_negated_rule_takes_precedence() {
+        let ignores = vec!["libfoo.so".to_string(), "!libfoo.so@0x2000".to_string()];
+        assert!(should_ignore("/data/a/libfoo.so", None, 0x1000, 0x1, 0x10, &ignores));
+        assert!(!should_ignore("/data/b/libfoo.so", None, 0x2000, 0x2, 0x10, &ignores));
+        // 规则顺序不影响例外的优先级
+        let reversed: Vec<String> = ignores.into_iter().rev().collect();
+        assert!(!should_ignore("/data/b/libfoo.so", None, 0x2000, 0x2, 0x10, &reversed));
+    }
+
+    #[test]
+    fn should_ignore_negated_rule_alone_ignores_nothing() {
+        let ignores = vec!["!libfoo.so".to_string()];
+        assert!(!should_ignore("/data/a/libfoo.so", None, 0x1000, 0x1, 0x10, &ignores));
+        assert!(!should_ignore("/data/a/libbar.so", None, 0x1000, 0x1, 0x10, &ignores));
+        assert!(should_ignore("", None, 0x1000, 0x1, 0x10, &ignores));
+    }
+
+    // CFI 扫描只跟随 ignore 规则，自身 so 不属于规则命中
+    #[test]
+    fn ignored_module_keys_follow_rules_only() {
+        let module = |pathname: &str, base_addr: usize| ModuleInfo {
+            pathname: pathname.to_string(),
+            base_addr,
+            instance_id: base_addr + 1,
+            namespace_id: 0,
+            soname: None,
+        };
+        let modules = [
+            module("/apex/lib64/libart.so", 0x1000),
+            module("/data/app/libsrx_hook.so", 0x2000),
+            module("/data/app/libfoo.so", 0x3000),
+        ];
+        assert!(ignored_module_keys(&modules, &[]).is_empty());
+        let ignores = vec!["libart.so".to_string(), "!libfoo.so".to_string()];
+        assert_eq!(
+            ignored_module_keys(&modules, &ignores)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            [(0x1000, 0x1001)]
+        );
+    }
+
+    #[test]
+    fn module_match_any_requires_one_entry() {
+        let rules = vec!["libfoo.so%0x1".to_string(), "libbar.so".to_string()];
+        assert!(module_match_any("/data/a/libfoo.so", None, 0x1000, 0x1, 0x10, &rules));
+        assert!(!module_match_any("/data/b/libfoo.so", None, 0x2000, 0x2, 0x10, &rules));
+        assert!(module_match_any("/data/b/libbar.so", None, 0x3000, 0x3, 0x10, &rules));
+        assert!(!module_match_any("/data/a/libfoo.so", None, 0x1000, 0x1, 0x10, &[]));
+    }
+
+    #[test]
+    fn soname_rule_matches_parsed_soname() {
+        let rule = "soname:libssl.so";
+        let ssl = Some("libssl.so");
+        assert!(module_match("/apex/libssl.so.3", ssl, 1, 2, 3, rule));
+        assert!(!module_match("/data/libssl.so", None, 1, 2, 3, rule));
+        let other = Some("libssl.so.1");
+        assert!(!module_match("/data/libssl.so", other, 1, 2, 3, rule));
+        assert!(!path_match("/data/libssl.so", rule));
+    }
+
+    #[test]
+    fn soname_rule_keeps_instance_suffix() {
+        let rule = "soname:libssl.so%0x2";
+        let ssl = Some("libssl.so");
+        assert!(module_match("/system/libssl.so", ssl, 1, 2, 3, rule));
+        assert!(!module_match("/system/libssl.so", ssl, 1, 4, 3, rule));
+    }
+
+    #[test]
+    fn soname_rule_only_matches_soname_rules() {
+        assert!(rule_match("soname:libssl.so%0x2", "soname:libssl.so"));
+        assert!(!rule_match("soname:libssl.so", "libssl.so"));
+        assert!(!rule_match("libssl.so", "soname:libssl.so"));
+        assert!(is_soname_rule("soname:libssl.so"));
+        assert!(is_soname_rule("!soname:libssl.so"));
+        assert!(!is_soname_rule("libsoname:x.so"));
+    }
+
+    #[test]
+    fn should_ignore_supports_soname_rule() {
+        let ignores = vec!["soname:libssl.so".to_string()];
+        let ssl = Some("libssl.so");
+        assert!(should_ignore("/apex/libssl.so.3", ssl, 1, 2, 3, &ignores));
+        assert!(!should_ignore("/apex/libssl.so.3", None, 1, 2, 3, &ignores));
+    }
+
+    #[test]
+    fn sym_pattern_supports_wildcards() {
+        assert!(sym_pattern_match("pthread_*", "pthread_create"));
+        assert!(sym_pattern_match("*_lock", "pthread_mutex_lock"));
+        assert!(sym_pattern_match("p*_*lock", "pthread_mutex_lock"));
+        assert!(sym_pattern_match("*", ""));
+        assert!(sym_pattern_match("open*", "open"));
+        assert!(!sym_pattern_match("pthread_*", "pthread"));
+        assert!(!sym_pattern_match("*_lock", "pthread_mutex_unlock_x"));
+        assert!(!sym_pattern_match("open", "open64"));
     }
 }
@@ -1,28 +1,43 @@
 // 运行时核心状态定义，包含所有 hook 任务、slot、模块信息及全局同步原语
 use crate::api::{
-    CallerAllowFilter, HookMode, HookStub, HookedCallback, PostDlopenCallback, PreDlopenCallback,
+    CalleeSelection, CallerAllowFilter, CallerAllowFilterEx, DlopenFilterCallback,
+    HookEventCallback, HookMode, HookStub, HookedCallback, PostDlcloseCallback, PostDlopenCallback,
+    PreDlcloseCallback, PreDlopenCallback, RecordCallback, RecordOp, SingleBindPolicy,
 };
 use crate::errno::Errno;
 use once_cell::sync::Lazy;
 use std::collections::{BTreeMap, BTreeSet};
 use std::collections::VecDeque;
-use std::sync::atomic::{AtomicI32, Ordering};
-use std::sync::{Condvar, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::{
+    Condvar, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError,
+};
 use std::thread::JoinHandle;
 
 // 无锁的安装时 PID，用于检测 fork 子进程
 // fork 后子进程的 PID 与此值不同，可快速判断是否在 fork 子进程中
 static INSTALL_PID: AtomicI32 = AtomicI32::new(0);
+// atfork 子进程回调置位，之后无需再比较 PID
+static FORKED_CHILD: AtomicBool = AtomicBool::new(false);
 
 // 记录安装时的 PID（初始化时调用）
 pub(crate) fn set_install_pid(pid: i32) {
     INSTALL_PID.store(pid, Ordering::Release);
+    FORKED_CHILD.store(false, Ordering::Release);
+}
+
+// 仅由 atfork 子进程回调调用，只做原子写入
+pub(super) fn mark_forked_child() {
+    FORKED_CHILD.store(true, Ordering::Release);
 }
 
 // 无锁检测是否在 fork 子进程中
 // 返回 true 表示当前进程是 fork 出来的子进程
 #[inline]
 pub(crate) fn is_forked_child() -> bool {
+    if FORKED_CHILD.load(Ordering::Acquire) {
+        return true;
+    }
     let install_pid = INSTALL_PID.load(Ordering::Acquire);
     if install_pid <= 0 {
         return false;
@@ -31,20 +46,43 @@ pub(crate) fn is_forked_child() -> bool {
     current_pid != install_pid
 }
 
+// clear / shutdown 复位期间置位，不经 state 锁的只读查询据此拒绝，避免读到恢复了一半的 slot
+static RESETTING: AtomicBool = AtomicBool::new(false);
+
+pub(super) fn set_resetting(flag: bool) {
+    RESETTING.store(flag, Ordering::Release);
+}
+
+pub(super) fn is_resetting() -> bool {
+    RESETTING.load(Ordering::Acquire)
+}
+
 // Mutex/RwLock poison 恢复扩展，避免持锁线程 panic 后引发连锁 panic
 pub(crate) trait MutexPoisonRecover<T> {
     fn lock_or_poison(&self) -> MutexGuard<'_, T>;
+    // 锁被占用时返回 None，不阻塞
+    fn try_lock_or_poison(&self) -> Option<MutexGuard<'_, T>>;
 }
 
 pub(crate) trait RwLockPoisonRecover<T> {
     fn read_or_poison(&self) -> RwLockReadGuard<'_, T>;
     fn write_or_poison(&self) -> RwLockWriteGuard<'_, T>;
+    // 写锁被占用时返回 None，不阻塞
+    fn try_read_or_poison(&self) -> Option<RwLockReadGuard<'_, T>>;
 }
 
 impl<T> MutexPoisonRecover<T> for Mutex<T> {
     fn lock_or_poison(&self) -> MutexGuard<'_, T> {
         self.lock().unwrap_or_else(|e| e.into_inner())
     }
+
+    fn try_lock_or_poison(&self) -> Option<MutexGuard<'_, T>> {
+        match self.try_lock() {
+            Ok(guard) => Some(guard),
+            Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
 }
 
 impl<T> RwLockPoisonRecover<T> for RwLock<T> {
@@ -55,6 +93,14 @@ impl<T> RwLockPoisonRecover<T> for RwLock<T> {
     fn write_or_poison(&self) -> RwLockWriteGuard<'_, T> {
         self.write().unwrap_or_else(|e| e.into_inner())
     }
+
+    fn try_read_or_poison(&self) -> Option<RwLockReadGuard<'_, T>> {
+        match self.try_read() {
+            Ok(guard) => Some(guard),
+            Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
 }
 
 // hook 任务的作用域类型
@@ -72,13 +118,36 @@ pub(super) struct HookedEntry {
     pub(super) arg: usize,
 }
 
+// 任务生命周期回调入口
+#[derive(Clone, Copy)]
+pub(super) struct HookEventEntry {
+    pub(super) callback: HookEventCallback,
+    pub(super) arg: usize,
+}
+
 // caller 过滤器，用于 Partial 模式按调用方筛选
 #[derive(Clone, Copy)]
 pub(super) struct AllowFilterEntry {
-    pub(super) filter: CallerAllowFilter,
+    pub(super) filter: CallerFilter,
     pub(super) arg: usize,
 }
 
+// hook_partial 只传路径，hook_partial_ex 额外传模块实例信息
+#[derive(Clone, Copy)]
+pub(super) enum CallerFilter {
+    Path(CallerAllowFilter),
+    Instance(CallerAllowFilterEx),
+}
+
+impl CallerFilter {
+    fn addr(self) -> usize {
+        match self {
+            Self::Path(filter) => filter as usize,
+            Self::Instance(filter) => filter as usize,
+        }
+    }
+}
+
 // dlopen/dlclose 事件的用户回调
 #[derive(Clone, Copy)]
 pub(super) struct DlopenCallbackEntry {
@@ -87,6 +156,25 @@ pub(super) struct DlopenCallbackEntry {
     pub(super) arg: usize,
 }
 
+#[derive(Clone, Copy)]
+pub(super) struct DlopenFilterEntry {
+    pub(super) filter: DlopenFilterCallback,
+    pub(super) arg: usize,
+}
+
+#[derive(Clone, Copy)]
+pub(super) struct DlcloseCallbackEntry {
+    pub(super) pre: Option<PreDlcloseCallback>,
+    pub(super) post: Option<PostDlcloseCallback>,
+    pub(super) arg: usize,
+}
+
+#[derive(Clone, Copy)]
+pub(super) struct RecordListenerEntry {
+    pub(super) callback: RecordCallback,
+    pub(super) arg: usize,
+}
+
 // 单个 hook 任务的完整描述
 #[derive(Clone)]
 pub(super) struct Task {
@@ -94,10 +182,64 @@ pub(super) struct Task {
     pub(super) task_type: TaskType,
     pub(super) caller_path_name: Option<String>,
     pub(super) caller_allow_filter: Option<AllowFilterEntry>,
+    // All 任务的 caller 白名单，非空时只 hook 匹配其中任一规则的模块
+    pub(super) caller_allow_list: Vec<String>,
     pub(super) callee_path_name: Option<String>,
     pub(super) sym_name: String,
     pub(super) new_func: usize,
     pub(super) hooked: Option<HookedEntry>,
+    pub(super) event_callback: Option<HookEventEntry>,
+    pub(super) callee_selection: CalleeSelection,
+    // 仅 Single 任务使用：caller 规则匹配到多个实例时的绑定方式
+    pub(super) bind_policy: SingleBindPolicy,
+    // 暂停的任务保留 slot 与 hub 节点，仅让调用跳过其 proxy；refresh 新挂的 slot 同样保持暂停
+    pub(super) paused: bool,
+    // hub 链中的执行顺序，数值大的先执行
+    pub(super) priority: i32,
+    // 只拦截这些线程的调用，升序去重；为空时对所有线程生效
+    pub(super) threads: Vec<i32>,
+}
+
+// 未指定优先级的任务，同优先级按注册顺序后进先出
+pub(super) const DEFAULT_PROXY_PRIORITY: i32 = 0;
+// monitor 内部 dlopen / dlclose proxy 的优先级，始终排在用户 proxy 之前，用户任务不可使用
+pub(super) const INTERNAL_PROXY_PRIORITY: i32 = i32::MAX;
+
+// 任务的注册去重键：作用域、规则、符号与 proxy 完全相同的任务视为重复注册
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(super) struct TaskKey {
+    task_type: TaskType,
+    caller_path_name: Option<String>,
+    caller_allow_filter: Option<(usize, usize)>,
+    caller_allow_list: Vec<String>,
+    callee_path_name: Option<String>,
+    sym_name: String,
+    new_func: usize,
+}
+
+impl Task {
+    // 任务携带的全部模块规则：caller、caller 白名单与 callee
+    pub(super) fn module_rules(&self) -> impl Iterator<Item = &str> {
+        self.caller_path_name
+            .iter()
+            .chain(&self.caller_allow_list)
+            .chain(&self.callee_path_name)
+            .map(String::as_str)
+    }
+
+    pub(super) fn key(&self) -> TaskKey {
+        TaskKey {
+            task_type: self.task_type,
+            caller_path_name: self.caller_path_name.clone(),
+            caller_allow_filter: self
+                .caller_allow_filter
+                .map(|entry| (entry.filter.addr(), entry.arg)),
+            caller_allow_list: self.caller_allow_list.clone(),
+            callee_path_name: self.callee_path_name.clone(),
+            sym_name: self.sym_name.clone(),
+            new_func: self.new_func,
+        }
+    }
 }
 
 // PLT slot 的唯一标识，由 caller 模块信息和 slot 地址组成
@@ -118,25 +260,48 @@ pub(super) struct SlotEntry {
     pub(super) hub_ptr: usize,
 }
 
+// 拒绝写入的 slot 页，以 (模块实例键, 页起始地址) 标识，模块实例卸载时清除
+#[derive(Default)]
+pub(super) struct SlotWriteGuard {
+    // 受保护写入失败的累计次数
+    pub(super) faults: BTreeMap<(String, usize), u32>,
+    pub(super) rejected: BTreeSet<(String, usize)>,
+}
+
 // linker 中已加载模块的标识信息
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug)]
 pub(super) struct ModuleInfo {
     pub(super) pathname: String,
     pub(super) base_addr: usize,
     pub(super) instance_id: usize,
     pub(super) namespace_id: usize,
+    // DT_SONAME，仅在存在 soname: 规则时由模块扫描补全
+    pub(super) soname: Option<String>,
 }
 
-// hook/unhook 操作类型，用于审计记录
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub(super) enum RecordOp {
-    Hook,
-    Unhook,
+// soname 是按需补全的派生信息，同一模块实例补全前后视为相同
+impl PartialEq for ModuleInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.pathname == other.pathname
+            && self.base_addr == other.base_addr
+            && self.instance_id == other.instance_id
+            && self.namespace_id == other.namespace_id
+    }
 }
 
-// 单条操作审计记录
+impl Eq for ModuleInfo {}
+
+// 记录关联的 caller 模块实例，路径保存在 lib_name 中
+#[derive(Clone, Copy, Debug)]
+pub(super) struct RecordModule {
+    pub(super) base_addr: usize,
+    pub(super) instance_id: usize,
+    pub(super) namespace_id: usize,
+}
+
+// 单条操作审计记录，文本与结构化导出共用
 #[derive(Clone, Debug)]
-pub(super) struct RecordEntry {
+pub(super) struct StoredRecord {
     pub(super) op: RecordOp,
     pub(super) ts_ms: u64,
     pub(super) status_code: i32,
@@ -144,7 +309,10 @@ pub(super) struct RecordEntry {
     pub(super) lib_name: String,
     pub(super) sym_name: String,
     pub(super) new_addr: usize,
+    // 写入校验失败时回读到的 slot 值，new_addr 为期望值
+    pub(super) observed_addr: Option<usize>,
     pub(super) stub: HookStub,
+    pub(super) module: Option<RecordModule>,
 }
 
 // 初始化状态，记录当前 hook 模式和初始化结果
@@ -167,8 +335,6 @@ impl Default for InitInfo {
 pub(super) struct CoreState {
     pub(super) process_id: usize,
     pub(super) init: InitInfo,
-    pub(super) debug: bool,
-    pub(super) next_stub: HookStub,
     // stub -> Task 映射，存储所有已注册的 hook 任务
     pub(super) tasks: BTreeMap<HookStub, Task>,
     pub(super) task_order: Vec<HookStub>,
@@ -176,34 +342,126 @@ pub(super) struct CoreState {
     pub(super) task_slots: BTreeMap<HookStub, BTreeSet<SlotKey>>,
     pub(super) slots: BTreeMap<SlotKey, SlotEntry>,
     pub(super) single_task_targets: BTreeMap<HookStub, String>,
+    // stub -> callee 规则匹配到的导出实例数，仅记录多于一个的任务
+    pub(super) callee_candidates: BTreeMap<HookStub, usize>,
+    // stub -> 命中统计块地址，仅包含开启统计的任务
+    pub(super) hit_stats: BTreeMap<HookStub, usize>,
+    // stub -> 线程集合地址，仅包含限定了线程的任务，首次挂载时创建
+    pub(super) tid_filters: BTreeMap<HookStub, usize>,
+    // (stub, caller 路径)：任务挂载过、随模块卸载失去的 caller，再次挂上时按 Rehooked 通知
+    pub(super) rehook_targets: BTreeSet<(HookStub, String)>,
     pub(super) ignore_callers: Vec<String>,
     pub(super) known_modules: BTreeSet<String>,
+    pub(super) write_guard: SlotWriteGuard,
     pub(super) recordable: bool,
-    pub(super) records: Vec<RecordEntry>,
+    pub(super) records: VecDeque<StoredRecord>,
+    // 缓冲区满或缩容时淘汰的记录数，clear 时归零
+    pub(super) records_dropped: u64,
+    pub(super) record_listener: Option<RecordListenerEntry>,
+    // 已写入、尚未投递给监听器的记录，释放 state 锁后统一投递
+    pub(super) pending_record_notifications: Vec<StoredRecord>,
     pub(super) dlopen_callbacks: Vec<DlopenCallbackEntry>,
+    pub(super) dlopen_filters: Vec<DlopenFilterEntry>,
+    pub(super) dlclose_callbacks: Vec<DlcloseCallbackEntry>,
     // 待处理的 dlopen handle 队列，用于异步刷新
     pub(super) pending_module_handles: VecDeque<usize>,
     pub(super) pending_module_handle_set: BTreeSet<usize>,
+    // dlclose 前解析出的模块身份，monitor 只针对这些模块做清理
+    pub(super) pending_unloaded_modules: Vec<ModuleInfo>,
     pub(super) refresh_requested: bool,
     pub(super) monitor_running: bool,
     pub(super) monitor_thread: Option<JoinHandle<()>>,
 }
 
+// 任务登记域：注册只在此登记并分配 stub，由持有 refresh_mutex 的一方合并进 CoreState 后应用
+// 刷新进行中注册不必等待 state 锁
+pub(super) struct TaskRegistry {
+    // init 成功后开放注册，clear 后关闭
+    pub(super) open: bool,
+    pub(super) next_stub: HookStub,
+    pub(super) pending: Vec<Task>,
+    // 已登记任务（含待合并与已生效）的去重键，unhook / clear 时移除
+    pub(super) keys: BTreeMap<HookStub, TaskKey>,
+    // 注册阶段被拒绝的 hook 记录，下次合并任务或导出记录时写入 records
+    pub(super) rejected: Vec<StoredRecord>,
+}
+
+impl Default for TaskRegistry {
+    fn default() -> Self {
+        Self {
+            open: false,
+            next_stub: 1,
+            pending: Vec::new(),
+            keys: BTreeMap::new(),
+            rejected: Vec::new(),
+        }
+    }
+}
+
 // 全局同步容器：state 保护核心状态，refresh_mutex 串行化 refresh
-// dlclose_lock 在 dlclose 期间阻止 slot 写入
+// dlclose_lock 在 dlclose 期间阻止 slot 写入，registry 保护待合并的任务登记
+// 锁顺序：dlclose_lock -> refresh_mutex -> state -> registry；registry 为叶子锁，持有期间不获取其他锁
 pub(super) struct GlobalState {
     pub(super) state: Mutex<CoreState>,
     pub(super) refresh_mutex: Mutex<()>,
     pub(super) dlclose_lock: RwLock<()>,
     pub(super) condvar: Condvar,
+    registry: Mutex<TaskRegistry>,
 }
 
 pub(super) static GLOBAL: Lazy<GlobalState> = Lazy::new(|| GlobalState {
-    state: Mutex::new(CoreState {
-        next_stub: 1,
-        ..CoreState::default()
-    }),
+    state: Mutex::new(CoreState::default()),
     refresh_mutex: Mutex::new(()),
     dlclose_lock: RwLock::new(()),
     condvar: Condvar::new(),
+    registry: Mutex::new(TaskRegistry::default()),
 });
+
+#[cfg(debug_assertions)]
+thread_local! {
+    static REGISTRY_HELD: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+// registry 锁守卫，debug 构建下跟踪持有状态以校验锁顺序
+pub(super) struct RegistryGuard {
+    guard: MutexGuard<'static, TaskRegistry>,
+}
+
+impl std::ops::Deref for RegistryGuard {
+    type Target = TaskRegistry;
+
+    fn deref(&self) -> &TaskRegistry {
+        &self.guard
+    }
+}
+
+impl std::ops::DerefMut for RegistryGuard {
+    fn deref_mut(&mut self) -> &mut TaskRegistry {
+        &mut self.guard
+    }
+}
+
+impl Drop for RegistryGuard {
+    fn drop(&mut self) {
+        #[cfg(debug_assertions)]
+        REGISTRY_HELD.with(|held| held.set(false));
+    }
+}
+
+pub(super) fn lock_registry() -> RegistryGuard {
+    debug_assert_registry_released();
+    let guard = GLOBAL.registry.lock_or_poison();
+    #[cfg(debug_assertions)]
+    REGISTRY_HELD.with(|held| held.set(true));
+    RegistryGuard { guard }
+}
+
+// 获取 registry 之外的锁前调用，持有 registry 时获取外层锁会违反锁顺序
+#[inline]
+pub(super) fn debug_assert_registry_released() {
+    #[cfg(debug_assertions)]
+    debug_assert!(
+        !REGISTRY_HELD.with(|held| held.get()),
+        "lock order violated: registry lock held"
+    );
+}
@@ -1,11 +1,16 @@
+use crate::api::{
+    HUB_STACK_DEFAULT_HARD_CAP, HUB_STACK_FIXED_DEPTH, HubStackStats, LogLevel, ModuleIdentity,
+    ThreadStateStats,
+};
 use once_cell::sync::OnceCell;
 use std::ffi::c_void;
 use std::mem::MaybeUninit;
 use std::ptr;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicI32, AtomicU64, AtomicUsize, Ordering};
 
-pub(crate) const HUB_STACK_CAP: usize = 32;
+const HUB_STACK_CAP: usize = HUB_STACK_FIXED_DEPTH;
 pub(crate) const PROXY_STACK_CAP: usize = 32;
+const CALLER_MODULE_CACHE_CAP: usize = 8;
 
 // Hub 栈帧只存原始数据，避免跨模块私有类型耦合
 #[derive(Clone, Copy)]
@@ -114,24 +119,202 @@ impl<T: Copy, const N: usize> FixedStack<T, N> {
     }
 }
 
-// 线程运行时状态，集中管理两类调用栈
+// Hub 栈：固定数组为快速路径，深度到达溢出阈值后转入线程私有的堆区，总深度不超过硬上限
+// 堆区在首次溢出时按剩余容量一次性预留，之后压栈不再分配；运行在备用信号栈上时不分配，按溢出处理
+// 逻辑顺序为固定数组在前、堆区在后，堆区非空时固定数组不再接收新帧
+pub(crate) struct HubStack {
+    fixed: FixedStack<HubFrame, HUB_STACK_CAP>,
+    spill: Vec<HubFrame>,
+}
+
+impl HubStack {
+    fn new() -> Self {
+        Self {
+            fixed: FixedStack::new(),
+            spill: Vec::new(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.fixed.len() + self.spill.len()
+    }
+
+    pub(crate) fn clear(&mut self) -> usize {
+        let spilled = self.spill.len();
+        self.spill.clear();
+        self.fixed.clear() + spilled
+    }
+
+    pub(crate) fn push(&mut self, frame: HubFrame) -> bool {
+        let threshold = HUB_SPILL_THRESHOLD.load(Ordering::Relaxed);
+        let hard_cap = HUB_STACK_HARD_CAP.load(Ordering::Relaxed);
+        let depth = self.len();
+        if self.spill.is_empty() && depth < threshold && self.fixed.push(frame) {
+            return true;
+        }
+        if depth >= hard_cap || !self.reserve_spill(hard_cap - depth) {
+            HUB_STACK_OVERFLOW.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        self.spill.push(frame);
+        HUB_STACK_SPILLED.fetch_add(1, Ordering::Relaxed);
+        HUB_STACK_MAX_DEPTH.fetch_max(depth + 1, Ordering::Relaxed);
+        true
+    }
+
+    // 保证堆区还能容纳 additional 帧；已有余量时不分配
+    fn reserve_spill(&mut self, additional: usize) -> bool {
+        if self.spill.len() < self.spill.capacity() {
+            return true;
+        }
+        if on_signal_stack() || self.spill.try_reserve_exact(additional).is_err() {
+            return false;
+        }
+        HUB_STACK_SPILL_ALLOCS.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<HubFrame> {
+        self.spill.pop().or_else(|| self.fixed.pop())
+    }
+
+    pub(crate) fn last(&self) -> Option<HubFrame> {
+        self.spill.last().copied().or_else(|| self.fixed.last())
+    }
+
+    pub(crate) fn get(&self, index: usize) -> Option<HubFrame> {
+        match index.checked_sub(self.fixed.len()) {
+            Some(spill_index) => self.spill.get(spill_index).copied(),
+            None => self.fixed.get(index),
+        }
+    }
+
+    // 移除固定数组中的帧后，把堆区最底部的帧补回固定数组，保持两段连续
+    pub(crate) fn remove(&mut self, index: usize) -> Option<HubFrame> {
+        if let Some(spill_index) = index.checked_sub(self.fixed.len()) {
+            return (spill_index < self.spill.len()).then(|| self.spill.remove(spill_index));
+        }
+        let removed = self.fixed.remove(index)?;
+        if !self.spill.is_empty() {
+            let _ = self.fixed.push(self.spill.remove(0));
+        }
+        Some(removed)
+    }
+
+    pub(crate) fn rposition_by<F>(&self, mut pred: F) -> Option<usize>
+    where
+        F: FnMut(HubFrame) -> bool,
+    {
+        if let Some(index) = self.spill.iter().rposition(|frame| pred(*frame)) {
+            return Some(self.fixed.len() + index);
+        }
+        self.fixed.rposition_by(pred)
+    }
+}
+
+// 当前是否运行在备用信号栈上，信号处理函数中不做堆分配
+fn on_signal_stack() -> bool {
+    let mut current: libc::stack_t = unsafe { std::mem::zeroed() };
+    if unsafe { libc::sigaltstack(ptr::null(), &mut current) } != 0 {
+        return false;
+    }
+    current.ss_flags & libc::SS_ONSTACK != 0
+}
+
+// 调用方模块缓存，按返回地址所在页索引，末尾为最近使用；代数变化时整体作废
+pub(crate) struct CallerModuleCache {
+    generation: u64,
+    entries: Vec<(usize, ModuleIdentity)>,
+}
+
+impl CallerModuleCache {
+    fn new() -> Self {
+        Self {
+            generation: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    // 返回命中项与当前代数，写回时需带上该代数
+    pub(crate) fn lookup(&mut self, page: usize) -> (Option<ModuleIdentity>, u64) {
+        let generation = CALLER_MODULE_GENERATION.load(Ordering::Acquire);
+        if generation != self.generation {
+            self.entries.clear();
+            self.generation = generation;
+        }
+        let Some(index) = self.entries.iter().position(|(key, _)| *key == page) else {
+            return (None, generation);
+        };
+        let entry = self.entries.remove(index);
+        let identity = entry.1.clone();
+        self.entries.push(entry);
+        (Some(identity), generation)
+    }
+
+    pub(crate) fn insert(&mut self, page: usize, generation: u64, identity: ModuleIdentity) {
+        if generation != self.generation
+            || generation != CALLER_MODULE_GENERATION.load(Ordering::Acquire)
+        {
+            return;
+        }
+        if self.entries.len() >= CALLER_MODULE_CACHE_CAP {
+            self.entries.remove(0);
+        }
+        self.entries.push((page, identity));
+    }
+}
+
+// 线程运行时状态，集中管理两类调用栈、观测抑制深度与调用方模块缓存
 pub(crate) struct ThreadRuntimeState {
-    hub_stack: FixedStack<HubFrame, HUB_STACK_CAP>,
+    hub_stack: HubStack,
     proxy_stack: FixedStack<ProxyFrame, PROXY_STACK_CAP>,
+    suppress_depth: u32,
+    caller_modules: CallerModuleCache,
+    // 本线程是否绕过所有 hub，新线程默认启用
+    hook_disabled: bool,
+    // 首次需要时通过 gettid 取得并缓存，0 表示尚未读取
+    tid: i32,
+    // 创建状态的进程，析构时据此判断是否计入存活数
+    owner_pid: i32,
 }
 
 impl ThreadRuntimeState {
-    fn new() -> Self {
+    fn new(owner_pid: i32) -> Self {
         Self {
-            hub_stack: FixedStack::new(),
+            hub_stack: HubStack::new(),
             proxy_stack: FixedStack::new(),
+            suppress_depth: 0,
+            caller_modules: CallerModuleCache::new(),
+            hook_disabled: false,
+            tid: 0,
+            owner_pid,
         }
     }
 
-    pub(crate) fn hub_stack_mut(&mut self) -> &mut FixedStack<HubFrame, HUB_STACK_CAP> {
+    pub(crate) fn caller_modules_mut(&mut self) -> &mut CallerModuleCache {
+        &mut self.caller_modules
+    }
+
+    pub(crate) fn suppress_depth_mut(&mut self) -> &mut u32 {
+        &mut self.suppress_depth
+    }
+
+    pub(crate) fn hook_disabled_mut(&mut self) -> &mut bool {
+        &mut self.hook_disabled
+    }
+
+    pub(crate) fn hub_stack_mut(&mut self) -> &mut HubStack {
         &mut self.hub_stack
     }
 
+    // hub 栈与当前线程 tid，供按线程过滤的 proxy 选择使用
+    pub(crate) fn hub_stack_with_tid(&mut self) -> (&mut HubStack, i32) {
+        if self.tid == 0 {
+            self.tid = unsafe { libc::gettid() };
+        }
+        (&mut self.hub_stack, self.tid)
+    }
+
     pub(crate) fn proxy_stack_mut(&mut self) -> &mut FixedStack<ProxyFrame, PROXY_STACK_CAP> {
         &mut self.proxy_stack
     }
@@ -149,7 +332,17 @@ static THREAD_STATE_BIND_FAIL: AtomicU64 = AtomicU64::new(0);
 static THREAD_STATE_ACCESS_FAIL: AtomicU64 = AtomicU64::new(0);
 static THREAD_STATE_RESERVED_HIT: AtomicU64 = AtomicU64::new(0);
 static HUB_STACK_OVERFLOW: AtomicU64 = AtomicU64::new(0);
+// Hub 栈溢出策略与堆区使用计数
+static HUB_SPILL_THRESHOLD: AtomicUsize = AtomicUsize::new(HUB_STACK_CAP);
+static HUB_STACK_HARD_CAP: AtomicUsize = AtomicUsize::new(HUB_STACK_DEFAULT_HARD_CAP);
+static HUB_STACK_SPILLED: AtomicU64 = AtomicU64::new(0);
+static HUB_STACK_SPILL_ALLOCS: AtomicU64 = AtomicU64::new(0);
+static HUB_STACK_MAX_DEPTH: AtomicUsize = AtomicUsize::new(0);
 static PROXY_STACK_OVERFLOW: AtomicU64 = AtomicU64::new(0);
+// 存活的线程状态数及其所属进程：fork 子进程继承的计数属于父进程，按 pid 区分后再增减
+static LIVE_THREAD_STATES: AtomicU64 = AtomicU64::new(0);
+static LIVE_THREAD_STATES_PID: AtomicI32 = AtomicI32::new(0);
+static CALLER_MODULE_GENERATION: AtomicU64 = AtomicU64::new(0);
 
 type ThreadStatePtr = *mut ThreadRuntimeState;
 
@@ -173,9 +366,9 @@ unsafe extern "C" fn destroy_thread_state(raw_ptr: *mut c_void) {
         }
     }
 
-    unsafe {
-        drop(Box::from_raw(raw_ptr as ThreadStatePtr));
-    }
+    let state = unsafe { Box::from_raw(raw_ptr as ThreadStatePtr) };
+    note_thread_state_destroyed(state.owner_pid);
+    drop(state);
 
     if let Some(keys) = THREAD_STATE_KEYS.get() {
         unsafe {
@@ -188,6 +381,46 @@ fn should_log_every_step(count: u64) -> bool {
     count == 1 || count.is_multiple_of(256)
 }
 
+fn should_report(count: u64) -> bool {
+    crate::log::is_enabled(LogLevel::Warn) && should_log_every_step(count)
+}
+
+// 计数始终累加供诊断接口读取；WARN 未被屏蔽时返回需要输出的累计次数
+fn next_report_count(counter: &AtomicU64) -> Option<u64> {
+    let count = counter.fetch_add(1, Ordering::Relaxed) + 1;
+    should_report(count).then_some(count)
+}
+
+fn note_thread_state_created(pid: i32) {
+    let owner = LIVE_THREAD_STATES_PID.load(Ordering::Acquire);
+    if owner != pid
+        && LIVE_THREAD_STATES_PID
+            .compare_exchange(owner, pid, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    {
+        LIVE_THREAD_STATES.store(0, Ordering::Release);
+    }
+    LIVE_THREAD_STATES.fetch_add(1, Ordering::AcqRel);
+}
+
+// fork 前创建、在子进程中析构的状态不计入子进程
+fn note_thread_state_destroyed(owner_pid: i32) {
+    let pid = unsafe { libc::getpid() };
+    if owner_pid != pid || LIVE_THREAD_STATES_PID.load(Ordering::Acquire) != pid {
+        return;
+    }
+    let _ = LIVE_THREAD_STATES.fetch_update(Ordering::AcqRel, Ordering::Acquire, |live| {
+        live.checked_sub(1)
+    });
+}
+
+fn live_thread_states() -> u64 {
+    if LIVE_THREAD_STATES_PID.load(Ordering::Acquire) != unsafe { libc::getpid() } {
+        return 0;
+    }
+    LIVE_THREAD_STATES.load(Ordering::Acquire)
+}
+
 fn is_fork_child_text() -> &'static str {
     if crate::runtime::state::is_forked_child() {
         "是"
@@ -197,8 +430,7 @@ fn is_fork_child_text() -> &'static str {
 }
 
 fn report_thread_state_key_init_fail(phase: &str, ret: i32) {
-    let count = THREAD_STATE_KEY_INIT_FAIL.fetch_add(1, Ordering::Relaxed) + 1;
-    if should_log_every_step(count) {
+    if let Some(count) = next_report_count(&THREAD_STATE_KEY_INIT_FAIL) {
         crate::log::warn(format_args!(
             "线程状态 key 初始化失败: phase={} ret={} 次数={} fork_child={}",
             phase,
@@ -257,8 +489,7 @@ fn is_thread_state_reserved(keys: ThreadStateKeys) -> bool {
 }
 
 fn report_thread_state_reserved() {
-    let count = THREAD_STATE_RESERVED_HIT.fetch_add(1, Ordering::Relaxed) + 1;
-    if should_log_every_step(count) {
+    if let Some(count) = next_report_count(&THREAD_STATE_RESERVED_HIT) {
         crate::log::warn(format_args!(
             "线程状态析构保护命中: 次数={} fork_child={}",
             count,
@@ -278,14 +509,16 @@ fn get_or_init_thread_state_ptr(keys: ThreadStateKeys) -> Option<ThreadStatePtr>
         return Some(existing);
     }
 
-    let raw_ptr = Box::into_raw(Box::new(ThreadRuntimeState::new()));
+    let pid = unsafe { libc::getpid() };
+    let raw_ptr = Box::into_raw(Box::new(ThreadRuntimeState::new(pid)));
     let ret = unsafe { libc::pthread_setspecific(keys.state_key, raw_ptr as *const c_void) };
-    if ret != 0 {
+    if ret == 0 {
+        note_thread_state_created(pid);
+    } else {
         unsafe {
             drop(Box::from_raw(raw_ptr));
         }
-        let count = THREAD_STATE_BIND_FAIL.fetch_add(1, Ordering::Relaxed) + 1;
-        if should_log_every_step(count) {
+        if let Some(count) = next_report_count(&THREAD_STATE_BIND_FAIL) {
             crate::log::warn(format_args!(
                 "线程状态绑定失败: ret={} 次数={} fork_child={}",
                 ret,
@@ -309,6 +542,25 @@ pub(crate) fn init_current_thread_state() -> bool {
     with_thread_state(|_| ()).is_some()
 }
 
+// fork 子进程重新初始化时调用：丢弃调用线程从父进程继承的状态，按当前 PID 重新绑定
+// 子进程中不存在的其他线程无法析构各自的状态，这部分内存只能遗留
+pub(crate) fn rebind_current_thread_state() -> bool {
+    let Some(keys) = ensure_thread_state_keys() else {
+        return false;
+    };
+    let inherited = unsafe { libc::pthread_getspecific(keys.state_key) } as ThreadStatePtr;
+    unsafe {
+        let _ = libc::pthread_setspecific(keys.state_key, ptr::null());
+        let _ = libc::pthread_setspecific(keys.reserved_key, ptr::null());
+    }
+    if !inherited.is_null() {
+        let state = unsafe { Box::from_raw(inherited) };
+        note_thread_state_destroyed(state.owner_pid);
+        drop(state);
+    }
+    init_current_thread_state()
+}
+
 // 访问当前线程状态，失败时返回 None
 pub(crate) fn with_thread_state<R, F>(f: F) -> Option<R>
 where
@@ -324,10 +576,37 @@ where
     Some(f(state))
 }
 
+// 只访问已绑定的线程状态，不创建实例也不分配内存，未绑定或 fork 子进程返回 None
+#[inline]
+pub(crate) fn with_bound_thread_state<R, F>(f: F) -> Option<R>
+where
+    F: FnOnce(&mut ThreadRuntimeState) -> R,
+{
+    if should_skip_thread_state() {
+        return None;
+    }
+    let keys = THREAD_STATE_KEYS.get()?;
+    let raw_ptr = unsafe { libc::pthread_getspecific(keys.state_key) } as ThreadStatePtr;
+    if raw_ptr.is_null() {
+        return None;
+    }
+    Some(f(unsafe { &mut *raw_ptr }))
+}
+
+// hub 入口的快速检查，未绑定线程状态时视为启用
+#[inline]
+pub(crate) fn is_thread_hook_disabled() -> bool {
+    with_bound_thread_state(|state| state.hook_disabled).unwrap_or(false)
+}
+
+// 模块卸载后作废所有线程的调用方模块缓存
+pub(crate) fn invalidate_caller_module_cache() {
+    CALLER_MODULE_GENERATION.fetch_add(1, Ordering::AcqRel);
+}
+
 // 记录线程状态不可用，避免每次热路径刷屏
 pub(crate) fn report_thread_state_unavailable(site: &str) {
-    let count = THREAD_STATE_ACCESS_FAIL.fetch_add(1, Ordering::Relaxed) + 1;
-    if should_log_every_step(count) {
+    if let Some(count) = next_report_count(&THREAD_STATE_ACCESS_FAIL) {
         crate::log::warn(format_args!(
             "线程状态不可用: site={} 次数={} fork_child={}",
             site,
@@ -337,13 +616,30 @@ pub(crate) fn report_thread_state_unavailable(site: &str) {
     }
 }
 
-// 记录 Hub 固定栈溢出
+// 设置 Hub 栈的溢出阈值与硬上限，None 恢复默认值；已在堆区中的帧不受影响
+pub(crate) fn set_hub_stack_limits(limits: Option<(usize, usize)>) {
+    let (threshold, hard_cap) = limits.unwrap_or((HUB_STACK_CAP, HUB_STACK_DEFAULT_HARD_CAP));
+    HUB_SPILL_THRESHOLD.store(threshold, Ordering::Relaxed);
+    HUB_STACK_HARD_CAP.store(hard_cap, Ordering::Relaxed);
+}
+
+pub(crate) fn hub_stack_stats() -> HubStackStats {
+    HubStackStats {
+        spilled_frames: HUB_STACK_SPILLED.load(Ordering::Relaxed),
+        spill_allocs: HUB_STACK_SPILL_ALLOCS.load(Ordering::Relaxed),
+        overflows: HUB_STACK_OVERFLOW.load(Ordering::Relaxed),
+        max_depth: HUB_STACK_MAX_DEPTH.load(Ordering::Relaxed),
+    }
+}
+
+// 记录 Hub 栈溢出：深度到达硬上限或堆区无法分配，本次调用绕过 proxy；计数已在 HubStack::push 中累加
 pub(crate) fn report_hub_stack_overflow() {
-    let count = HUB_STACK_OVERFLOW.fetch_add(1, Ordering::Relaxed) + 1;
-    if should_log_every_step(count) {
+    let count = HUB_STACK_OVERFLOW.load(Ordering::Relaxed);
+    if should_report(count) {
         crate::log::warn(format_args!(
-            "Hub 固定栈溢出: cap={} 次数={} fork_child={}",
-            HUB_STACK_CAP,
+            "Hub 栈溢出: threshold={} hard_cap={} 次数={} fork_child={}",
+            HUB_SPILL_THRESHOLD.load(Ordering::Relaxed),
+            HUB_STACK_HARD_CAP.load(Ordering::Relaxed),
             count,
             is_fork_child_text()
         ));
@@ -352,8 +648,7 @@ pub(crate) fn report_hub_stack_overflow() {
 
 // 记录 Proxy 固定栈溢出
 pub(crate) fn report_proxy_stack_overflow() {
-    let count = PROXY_STACK_OVERFLOW.fetch_add(1, Ordering::Relaxed) + 1;
-    if should_log_every_step(count) {
+    if let Some(count) = next_report_count(&PROXY_STACK_OVERFLOW) {
         crate::log::warn(format_args!(
             "Proxy 固定栈溢出: cap={} 次数={} fork_child={}",
             PROXY_STACK_CAP,
@@ -362,3 +657,27 @@ pub(crate) fn report_proxy_stack_overflow() {
         ));
     }
 }
+
+pub(crate) fn thread_state_stats() -> ThreadStateStats {
+    ThreadStateStats {
+        key_init_failures: THREAD_STATE_KEY_INIT_FAIL.load(Ordering::Relaxed),
+        bind_failures: THREAD_STATE_BIND_FAIL.load(Ordering::Relaxed),
+        reserved_hits: THREAD_STATE_RESERVED_HIT.load(Ordering::Relaxed),
+        hub_stack_overflows: HUB_STACK_OVERFLOW.load(Ordering::Relaxed),
+        proxy_stack_overflows: PROXY_STACK_OVERFLOW.load(Ordering::Relaxed),
+        live_states: live_thread_states(),
+    }
+}
+
+// 清零诊断计数；存活线程状态数反映当前状态，不受影响
+pub(crate) fn reset_thread_state_stats() {
+    for counter in [
+        &THREAD_STATE_KEY_INIT_FAIL,
+        &THREAD_STATE_BIND_FAIL,
+        &THREAD_STATE_RESERVED_HIT,
+        &HUB_STACK_OVERFLOW,
+        &PROXY_STACK_OVERFLOW,
+    ] {
+        counter.store(0, Ordering::Relaxed);
+    }
+}